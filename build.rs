@@ -0,0 +1,50 @@
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Trimmed stdout of a command, if it succeeded
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(out.stdout).ok()?;
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    // rerun whenever the sources change, so the build date is not left over
+    // from an earlier build
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=crates");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=BIFROST_GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // allow packagers to pass the hash, when building without a git checkout
+    let git_hash = env::var("BIFROST_GIT_HASH")
+        .ok()
+        .or_else(|| output("git", &["rev-parse", "--short=12", "HEAD"]));
+    if let Some(hash) = git_hash {
+        println!("cargo:rustc-env=BIFROST_GIT_HASH={hash}");
+    }
+
+    // honor reproducible builds
+    let build_time = env::var("SOURCE_DATE_EPOCH").ok().or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|time| time.as_secs().to_string())
+    });
+    if let Some(time) = build_time {
+        println!("cargo:rustc-env=BIFROST_BUILD_TIME={time}");
+    }
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    if let Some(version) = output(&rustc, &["--version"]) {
+        println!("cargo:rustc-env=BIFROST_RUST_VERSION={version}");
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+/// Version and build information of the running instance
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct About {
+    pub version: String,
+    /// Git commit the binary was built from, if known
+    pub git_hash: Option<String>,
+    /// Build time, in rfc 3339 format
+    pub build_date: Option<String>,
+    /// Version of the rust compiler used for the build
+    pub rust_version: Option<String>,
+    pub uptime_secs: u64,
+    /// Cargo features enabled at build time
+    pub features: Vec<String>,
+}
+
+impl Client {
+    pub async fn about(&self) -> BifrostResult<About> {
+        self.get("about").await
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::ResourceLink;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ActionResponse {
+    /// Resources the action was sent to
+    pub targets: Vec<ResourceLink>,
+
+    /// Resources the action could not be sent to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<ActionFailure>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ActionFailure {
+    pub target: ResourceLink,
+    pub error: String,
+}
+
+impl Client {
+    /// Turn off all lights (in all rooms)
+    pub async fn action_all_off(&self) -> BifrostResult<ActionResponse> {
+        self.post("actions/all-off", ()).await
+    }
+
+    /// Turn off all lights in room `id`
+    pub async fn action_room_off(&self, id: Uuid) -> BifrostResult<ActionResponse> {
+        self.post(&format!("actions/room/{id}/off"), ()).await
+    }
+
+    /// Recall scene `id`
+    pub async fn action_scene_recall(&self, id: Uuid) -> BifrostResult<ActionResponse> {
+        self.post(&format!("actions/scene/{id}/recall"), ()).await
+    }
+}
@@ -1,8 +1,10 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use hue::api::{
-    GroupedLightUpdate, LightUpdate, ResourceLink, RoomUpdate, Scene, SceneUpdate,
+    DeviceUpdate, GroupedLightUpdate, LightUpdate, ResourceLink, RoomUpdate, Scene, SceneUpdate,
     ZigbeeDeviceDiscoveryUpdate,
 };
 use hue::stream::HueStreamLightsV2;
@@ -11,20 +13,13 @@ use crate::Client;
 use crate::config::{HassServer, Z2mServer};
 use crate::error::BifrostResult;
 
+/// Requests for the backend owning a resource (or, if not resource specific,
+/// for all backends).
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum BackendRequest {
     LightUpdate(ResourceLink, LightUpdate),
     SensorEnabledUpdate(ResourceLink, bool),
-    HassSync,
-    /// Upsert a single entity from Home Assistant into the Hue resource DB (fetches HA state).
-    HassUpsertEntity(String),
-    /// Remove a single entity from the Hue resource DB (no HA call).
-    HassRemoveEntity(String),
-    /// Rebuild room metadata/assignments from the current UI config without HA requests.
-    HassUpdateRooms,
-    HassConnect,
-    HassDisconnect,
 
     SceneCreate(ResourceLink, u32, Scene),
     SceneUpdate(ResourceLink, SceneUpdate),
@@ -33,6 +28,8 @@ pub enum BackendRequest {
 
     RoomUpdate(ResourceLink, RoomUpdate),
 
+    DeviceUpdate(ResourceLink, DeviceUpdate),
+
     Delete(ResourceLink),
 
     EntertainmentStart(Uuid),
@@ -40,6 +37,118 @@ pub enum BackendRequest {
     EntertainmentStop(),
 
     ZigbeeDeviceDiscovery(ResourceLink, ZigbeeDeviceDiscoveryUpdate),
+
+    /// Press a (virtual) button
+    ButtonPress(ResourceLink),
+}
+
+impl BackendRequest {
+    /// The resource this request is for, which determines the backend it is
+    /// routed to. Requests without a target are sent to all backends.
+    #[must_use]
+    pub const fn target(&self) -> Option<ResourceLink> {
+        match self {
+            Self::LightUpdate(link, _)
+            | Self::SensorEnabledUpdate(link, _)
+            | Self::SceneUpdate(link, _)
+            | Self::GroupedLightUpdate(link, _)
+            | Self::RoomUpdate(link, _)
+            | Self::DeviceUpdate(link, _)
+            | Self::Delete(link)
+            | Self::ButtonPress(link) => Some(*link),
+
+            // the scene does not exist yet, so route to the owner of the room
+            Self::SceneCreate(_, _, scene) => Some(scene.group),
+
+            Self::EntertainmentStart(_)
+            | Self::EntertainmentFrame(_)
+            | Self::EntertainmentStop()
+            | Self::ZigbeeDeviceDiscovery(_, _) => None,
+        }
+    }
+}
+
+/// Features implemented by a backend.
+///
+/// Requests needing a feature the target backend does not implement are
+/// refused, instead of being silently ignored by the backend.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    pub scenes: bool,
+    pub entertainment: bool,
+    pub gradients: bool,
+    pub sensors: bool,
+    /// Buttons can be pressed remotely
+    pub buttons: bool,
+}
+
+impl BackendCapabilities {
+    /// The feature needed by `req`, if this backend does not implement it
+    #[must_use]
+    pub const fn missing(&self, req: &BackendRequest) -> Option<&'static str> {
+        match req {
+            BackendRequest::SceneCreate(..) | BackendRequest::SceneUpdate(..) if !self.scenes => {
+                Some("scenes")
+            }
+            BackendRequest::LightUpdate(_, upd) if upd.gradient.is_some() && !self.gradients => {
+                Some("gradients")
+            }
+            BackendRequest::SensorEnabledUpdate(..) if !self.sensors => Some("sensors"),
+            BackendRequest::ButtonPress(_) if !self.buttons => Some("buttons"),
+            // stopping is always fine, so sessions can end cleanly
+            BackendRequest::EntertainmentStart(_) | BackendRequest::EntertainmentFrame(_)
+                if !self.entertainment =>
+            {
+                Some("entertainment")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Requests for Home Assistant backends only
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum HassRequest {
+    Sync,
+    /// Upsert a single entity from Home Assistant into the Hue resource DB (fetches HA state).
+    UpsertEntity(String),
+    /// Remove a single entity from the Hue resource DB (no HA call).
+    RemoveEntity(String),
+    /// Rebuild room metadata/assignments from the current UI config without HA requests.
+    UpdateRooms,
+    Connect,
+    Disconnect,
+    /// Notify Home Assistant that an app paired with the bridge
+    /// (device type, and whether the link button was active).
+    NotifyPairing(String, bool),
+    /// Forward activity from hue clients as a `bifrost_event` event
+    ForwardActivity(BifrostActivity),
+    /// Compare Home Assistant states with the Hue resources. The reports
+    /// are published for the request with this id.
+    Verify(Uuid),
+    /// Fix the mismatches between Home Assistant and the Hue resources
+    /// (reported like `Verify`)
+    Reconcile(Uuid),
+}
+
+/// Activity originating from hue clients (or the bridge itself)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BifrostActivity {
+    SceneRecall {
+        scene: ResourceLink,
+        name: String,
+        room: Option<String>,
+    },
+    GroupedLightToggle {
+        grouped_light: ResourceLink,
+        room: Option<String>,
+        on: bool,
+    },
+    LinkButton {
+        source: String,
+    },
 }
 
 impl Client {
@@ -50,4 +159,16 @@ impl Client {
     pub async fn post_backend_hass(&self, name: &str, backend: HassServer) -> BifrostResult<()> {
         self.post(&format!("backend/hass/{name}"), backend).await
     }
+
+    /// Features of each running backend
+    pub async fn backend_capabilities(
+        &self,
+    ) -> BifrostResult<BTreeMap<String, BackendCapabilities>> {
+        self.get("backend").await
+    }
+
+    /// Number of requests each backend skipped, because it fell behind
+    pub async fn backend_lag(&self) -> BifrostResult<BTreeMap<String, u64>> {
+        self.get("backend/lag").await
+    }
 }
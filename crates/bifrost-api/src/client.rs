@@ -1,19 +1,27 @@
+use std::sync::{Arc, OnceLock};
+
 use reqwest::{Method, Url};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+use crate::csrf::{CSRF_HEADER, CsrfToken};
 use crate::error::BifrostResult;
 
 #[derive(Clone)]
 pub struct Client {
     client: reqwest::Client,
     url: Url,
+    csrf: Arc<OnceLock<Option<String>>>,
 }
 
 impl Client {
     #[must_use]
-    pub const fn new(client: reqwest::Client, url: Url) -> Self {
-        Self { client, url }
+    pub fn new(client: reqwest::Client, url: Url) -> Self {
+        Self {
+            client,
+            url,
+            csrf: Arc::new(OnceLock::new()),
+        }
     }
 
     #[must_use]
@@ -29,8 +37,18 @@ impl Client {
     ) -> BifrostResult<O> {
         let url = self.url.join(scope)?;
 
+        let token = if method == Method::GET {
+            None
+        } else {
+            self.csrf_token().await?
+        };
+
         let mut req = self.client.request(method, url);
 
+        if let Some(token) = token {
+            req = req.header(CSRF_HEADER, token);
+        }
+
         if let Some(data) = data {
             req = req.json(&data);
         }
@@ -40,6 +58,25 @@ impl Client {
         Ok(response)
     }
 
+    /// Csrf token of the server, fetched on first use
+    async fn csrf_token(&self) -> BifrostResult<Option<String>> {
+        if let Some(token) = self.csrf.get() {
+            return Ok(token.clone());
+        }
+
+        let url = self.url.join("csrf")?;
+        let res: CsrfToken = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(self.csrf.get_or_init(|| res.token).clone())
+    }
+
     pub async fn get<T: DeserializeOwned>(&self, scope: &str) -> BifrostResult<T> {
         self.request(scope, Method::GET, None::<()>).await
     }
@@ -59,4 +96,8 @@ impl Client {
     ) -> BifrostResult<O> {
         self.request(scope, Method::PUT, Some(data)).await
     }
+
+    pub async fn delete<T: DeserializeOwned>(&self, scope: &str) -> BifrostResult<T> {
+        self.request(scope, Method::DELETE, None::<()>).await
+    }
 }
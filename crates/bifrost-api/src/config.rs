@@ -1,9 +1,14 @@
-use std::net::Ipv4Addr;
-use std::{collections::BTreeMap, num::NonZeroU32};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::time::Duration;
 
 use camino::Utf8PathBuf;
 use hue::api::RoomArchetype;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use svc::policy::{Policy, Retry};
+use svc::serviceid::ServiceName;
 use url::Url;
 
 use crate::{Client, error::BifrostResult};
@@ -32,6 +37,18 @@ pub struct BifrostConfig {
     pub cert_file: Utf8PathBuf,
     pub hass_ui_file: Utf8PathBuf,
     pub hass_runtime_file: Utf8PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<Url>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_channel_size: Option<NonZeroUsize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_channel_size: Option<NonZeroUsize>,
+    /// Prefix light names with their room name on the v1 api (used by voice
+    /// assistants)
+    #[serde(default)]
+    pub voice_room_prefix: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default, Eq, PartialEq)]
@@ -59,6 +76,426 @@ pub struct HassServer {
     pub url: Url,
     pub token_env: Option<String>,
     pub poll_interval_secs: Option<NonZeroU32>,
+    pub pairing_notification: Option<bool>,
+    pub retry_queue_size: Option<usize>,
+    pub retry_ttl_secs: Option<NonZeroU32>,
+    pub export_room_groups: Option<bool>,
+    pub forward_events: Option<bool>,
+    pub sync_timeout_secs: Option<NonZeroU32>,
+    pub sync_chunk_size: Option<NonZeroUsize>,
+    pub heartbeat_secs: Option<NonZeroU32>,
+    pub heartbeat_entity: Option<String>,
+    pub nightly_reconcile: Option<bool>,
+    pub reconcile_hour: Option<u32>,
+    pub entertainment_fallback: Option<bool>,
+    pub entertainment_fps: Option<NonZeroU32>,
+}
+
+impl HassServer {
+    pub const DEFAULT_TOKEN_ENV: &str = "HASS_TOKEN";
+
+    /// Environment variable holding the access token
+    #[must_use]
+    pub fn token_env(&self) -> &str {
+        self.token_env.as_deref().unwrap_or(Self::DEFAULT_TOKEN_ENV)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UpdaterConfig {
+    #[serde(default = "UpdaterConfig::default_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_url: Option<Url>,
+}
+
+impl UpdaterConfig {
+    const fn default_enabled() -> bool {
+        true
+    }
+}
+
+impl Default for UpdaterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            pinned_version: None,
+            mirror_url: None,
+        }
+    }
+}
+
+/// Checks for new Bifrost releases. Not to be confused with
+/// [`UpdaterConfig`], which finds the emulated hue firmware version.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SelfUpdateConfig {
+    /// GitHub repository ("owner/name") publishing the releases
+    #[serde(default = "SelfUpdateConfig::default_repository")]
+    pub repository: String,
+    /// Hours between release checks
+    #[serde(default = "SelfUpdateConfig::default_interval_hours")]
+    pub interval_hours: NonZeroU32,
+    /// Download the release binary for this platform into `staging_dir`
+    /// (not inside containers, which are updated by pulling a new image)
+    #[serde(default)]
+    pub download: bool,
+    #[serde(default = "SelfUpdateConfig::default_staging_dir")]
+    pub staging_dir: Utf8PathBuf,
+}
+
+impl SelfUpdateConfig {
+    fn default_repository() -> String {
+        "joeblack2k/bifrost-hass-ha-bridge".to_string()
+    }
+
+    const fn default_interval_hours() -> NonZeroU32 {
+        NonZeroU32::new(24).unwrap()
+    }
+
+    fn default_staging_dir() -> Utf8PathBuf {
+        Utf8PathBuf::from("update")
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EventStreamConfig {
+    /// Interval between keep-alive comments on the event stream
+    #[serde(default = "EventStreamConfig::default_keep_alive_secs")]
+    pub keep_alive_secs: NonZeroU32,
+    /// Compress the event stream, for clients that accept gzip
+    #[serde(default)]
+    pub gzip: bool,
+    /// Send headers that disable response buffering in reverse proxies
+    #[serde(default)]
+    pub behind_proxy: bool,
+}
+
+impl EventStreamConfig {
+    const fn default_keep_alive_secs() -> NonZeroU32 {
+        NonZeroU32::new(15).unwrap()
+    }
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive_secs: Self::default_keep_alive_secs(),
+            gzip: false,
+            behind_proxy: false,
+        }
+    }
+}
+
+/// Workarounds for a family of hue clients (apps)
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ClientCompatConfig {
+    /// Start of the devicetype the app sends when pairing (case-insensitive)
+    pub devicetype: String,
+    /// Clients at these addresses always belong to this family
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<IpAddr>,
+    /// Add `appdata` to v1 scenes
+    pub scene_appdata: Option<bool>,
+    /// Use hue style (mac address based) uniqueid for v1 lights
+    pub hue_uniqueid: Option<bool>,
+    /// Include group 0 (all lights) when listing v1 groups
+    pub list_group_0: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, Eq, PartialEq)]
+pub struct LinkButtonConfig {
+    /// Home Assistant entity that presses the link button when turned on
+    /// (or, for `button`/`input_button` entities, when pressed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hass_entity: Option<String>,
+    /// Home Assistant event type that presses the link button when fired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hass_event: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpio: Option<LinkButtonGpio>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct LinkButtonGpio {
+    pub pin: u32,
+    #[serde(default)]
+    pub active_low: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct IdentifyConfig {
+    /// Gpio pin driving an indicator led, blinked when the bridge is identified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpio: Option<IdentifyGpio>,
+    /// Briefly pulse all lights when no led is configured (or it fails)
+    #[serde(default = "IdentifyConfig::default_pulse_lights")]
+    pub pulse_lights: bool,
+}
+
+impl IdentifyConfig {
+    const fn default_pulse_lights() -> bool {
+        true
+    }
+}
+
+impl Default for IdentifyConfig {
+    fn default() -> Self {
+        Self {
+            gpio: None,
+            pulse_lights: Self::default_pulse_lights(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct IdentifyGpio {
+    pub pin: u32,
+    #[serde(default)]
+    pub active_low: bool,
+}
+
+/// Presence simulation ("away mode"), randomly toggling the lights in some
+/// rooms during the evening, to make the home look occupied
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PresenceConfig {
+    /// Names of the rooms to toggle
+    #[serde(default)]
+    pub rooms: Vec<String>,
+    /// Hour (local time, 0-23) when the simulation starts
+    #[serde(default = "PresenceConfig::default_start_hour")]
+    pub start_hour: u32,
+    /// Hour (local time, 0-23) when the simulation ends, and the lights it
+    /// turned on are turned off again
+    #[serde(default = "PresenceConfig::default_end_hour")]
+    pub end_hour: u32,
+    /// Shortest time (in minutes) between two toggles
+    #[serde(default = "PresenceConfig::default_min_interval")]
+    pub min_interval: u32,
+    /// Longest time (in minutes) between two toggles
+    #[serde(default = "PresenceConfig::default_max_interval")]
+    pub max_interval: u32,
+    /// Home Assistant entity (e.g. an `input_boolean`) that enables the
+    /// simulation while on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hass_entity: Option<String>,
+}
+
+impl PresenceConfig {
+    const fn default_start_hour() -> u32 {
+        18
+    }
+
+    const fn default_end_hour() -> u32 {
+        23
+    }
+
+    const fn default_min_interval() -> u32 {
+        10
+    }
+
+    const fn default_max_interval() -> u32 {
+        45
+    }
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            rooms: vec![],
+            start_hour: Self::default_start_hour(),
+            end_hour: Self::default_end_hour(),
+            min_interval: Self::default_min_interval(),
+            max_interval: Self::default_max_interval(),
+            hass_entity: None,
+        }
+    }
+}
+
+/// Mqtt broker to publish bifrost controls to, using Home Assistant mqtt
+/// discovery (requires the "mqtt" feature)
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Environment variable holding the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+    #[serde(default = "MqttConfig::default_client_id")]
+    pub client_id: String,
+    /// Topic prefix Home Assistant watches for discovery messages
+    #[serde(default = "MqttConfig::default_discovery_prefix")]
+    pub discovery_prefix: String,
+    /// Topic prefix for the state and command topics
+    #[serde(default = "MqttConfig::default_base_topic")]
+    pub base_topic: String,
+}
+
+impl MqttConfig {
+    const fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_client_id() -> String {
+        "bifrost".to_string()
+    }
+
+    fn default_discovery_prefix() -> String {
+        "homeassistant".to_string()
+    }
+
+    fn default_base_topic() -> String {
+        "bifrost".to_string()
+    }
+}
+
+/// Separate http server for the management api and web ui (`/bifrost`).
+///
+/// When configured, `/bifrost` is only served here, and not on the hue bridge
+/// http and https ports.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ManagementConfig {
+    #[serde(default = "ManagementConfig::default_address")]
+    pub address: Ipv4Addr,
+    pub port: u16,
+}
+
+impl ManagementConfig {
+    const fn default_address() -> Ipv4Addr {
+        Ipv4Addr::LOCALHOST
+    }
+}
+
+/// Protection of the management api (`/bifrost`) against requests from other
+/// web sites
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SecurityConfig {
+    /// Web origins (e.g. "https://admin.lan") allowed to use the management
+    /// api from a browser, or "*" for any. The web ui itself is always
+    /// allowed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cors_origins: Vec<String>,
+    /// Require a csrf token for requests that change anything
+    #[serde(default = "SecurityConfig::default_csrf")]
+    pub csrf: bool,
+}
+
+impl SecurityConfig {
+    const fn default_csrf() -> bool {
+        true
+    }
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            cors_origins: vec![],
+            csrf: Self::default_csrf(),
+        }
+    }
+}
+
+/// Part of the day, used to pick scene variants
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOfDay {
+    Morning,
+    Day,
+    Evening,
+    Night,
+}
+
+/// Start hours (local time, 0-23) of the parts of the day
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TimeOfDayConfig {
+    #[serde(default = "TimeOfDayConfig::default_morning")]
+    pub morning: u32,
+    #[serde(default = "TimeOfDayConfig::default_day")]
+    pub day: u32,
+    #[serde(default = "TimeOfDayConfig::default_evening")]
+    pub evening: u32,
+    #[serde(default = "TimeOfDayConfig::default_night")]
+    pub night: u32,
+}
+
+impl TimeOfDayConfig {
+    const fn default_morning() -> u32 {
+        6
+    }
+
+    const fn default_day() -> u32 {
+        10
+    }
+
+    const fn default_evening() -> u32 {
+        18
+    }
+
+    const fn default_night() -> u32 {
+        22
+    }
+
+    /// The part of the day `hour` falls in.
+    ///
+    /// This is the part that started most recently, wrapping around
+    /// midnight.
+    #[must_use]
+    pub fn at(&self, hour: u32) -> TimeOfDay {
+        let starts = [
+            (self.morning, TimeOfDay::Morning),
+            (self.day, TimeOfDay::Day),
+            (self.evening, TimeOfDay::Evening),
+            (self.night, TimeOfDay::Night),
+        ];
+
+        let since = |start: u32| (hour + 24 - start % 24) % 24;
+
+        starts
+            .into_iter()
+            .min_by_key(|(start, _)| since(*start))
+            .map_or(TimeOfDay::Day, |(_, tod)| tod)
+    }
+}
+
+impl Default for TimeOfDayConfig {
+    fn default() -> Self {
+        Self {
+            morning: Self::default_morning(),
+            day: Self::default_day(),
+            evening: Self::default_evening(),
+            night: Self::default_night(),
+        }
+    }
+}
+
+/// The bridge home ("group 0" on the v1 api), which holds the rooms
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct BridgeHomeConfig {
+    /// Name of the bridge home (defaults to "Group 0" on the v1 api)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Add rooms to the bridge home, when backends create them
+    #[serde(default = "BridgeHomeConfig::default_attach_rooms")]
+    pub attach_rooms: bool,
+}
+
+impl BridgeHomeConfig {
+    const fn default_attach_rooms() -> bool {
+        true
+    }
+}
+
+impl Default for BridgeHomeConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            attach_rooms: Self::default_attach_rooms(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default, Eq, PartialEq)]
@@ -67,6 +504,92 @@ pub struct RoomConfig {
     pub icon: Option<RoomArchetype>,
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// Leave the service failed when it stops with an error
+    Never,
+    /// Restart the service when it stops with an error
+    #[default]
+    OnFailure,
+    /// Restart the service whenever it stops, even without an error
+    Always,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ServicePolicyConfig {
+    #[serde(default)]
+    pub restart: RestartMode,
+    /// Give up after this many consecutive restarts (unlimited if null)
+    #[serde(default = "ServicePolicyConfig::default_max_restarts")]
+    pub max_restarts: Option<u32>,
+    /// Delay before the first restart
+    #[serde(default = "ServicePolicyConfig::default_delay_ms")]
+    pub delay_ms: u64,
+    /// Upper limit for the delay, when backing off
+    #[serde(default = "ServicePolicyConfig::default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Factor to multiply the delay by, for each consecutive restart
+    #[serde(default = "ServicePolicyConfig::default_backoff")]
+    pub backoff: u32,
+    /// A run lasting at least this long ends a series of consecutive
+    /// restarts (resetting both `max_restarts` and the backoff)
+    #[serde(default = "ServicePolicyConfig::default_reset_after_ms")]
+    pub reset_after_ms: u64,
+}
+
+impl ServicePolicyConfig {
+    #[allow(clippy::unnecessary_wraps)]
+    const fn default_max_restarts() -> Option<u32> {
+        Some(10)
+    }
+
+    const fn default_delay_ms() -> u64 {
+        1000
+    }
+
+    const fn default_max_delay_ms() -> u64 {
+        5 * 60 * 1000
+    }
+
+    const fn default_backoff() -> u32 {
+        2
+    }
+
+    const fn default_reset_after_ms() -> u64 {
+        10 * 60 * 1000
+    }
+
+    #[must_use]
+    pub const fn policy(&self) -> Policy {
+        let retry = match (self.restart, self.max_restarts) {
+            (RestartMode::Never, _) => Retry::No,
+            (_, Some(limit)) => Retry::Limit(limit),
+            (_, None) => Retry::Forever,
+        };
+
+        Policy::new()
+            .with_retry(retry)
+            .with_delay(Duration::from_millis(self.delay_ms))
+            .with_backoff(self.backoff, Duration::from_millis(self.max_delay_ms))
+            .with_restart_on_success(matches!(self.restart, RestartMode::Always))
+            .with_reset_after(Duration::from_millis(self.reset_after_ms))
+    }
+}
+
+impl Default for ServicePolicyConfig {
+    fn default() -> Self {
+        Self {
+            restart: RestartMode::default(),
+            max_restarts: Self::default_max_restarts(),
+            delay_ms: Self::default_delay_ms(),
+            max_delay_ms: Self::default_max_delay_ms(),
+            backoff: Self::default_backoff(),
+            reset_after_ms: Self::default_reset_after_ms(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AppConfig {
     pub bridge: BridgeConfig,
@@ -76,7 +599,33 @@ pub struct AppConfig {
     pub hass: HassConfig,
     pub bifrost: BifrostConfig,
     #[serde(default)]
+    pub updater: UpdaterConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_update: Option<SelfUpdateConfig>,
+    #[serde(default)]
+    pub linkbutton: LinkButtonConfig,
+    #[serde(default)]
+    pub identify: IdentifyConfig,
+    #[serde(default)]
+    pub presence: PresenceConfig,
+    #[serde(default)]
+    pub time_of_day: TimeOfDayConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mqtt: Option<MqttConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub management: Option<ManagementConfig>,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub eventstream: EventStreamConfig,
+    #[serde(default)]
     pub rooms: BTreeMap<String, RoomConfig>,
+    #[serde(default)]
+    pub home: BridgeHomeConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, ServicePolicyConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub compat: BTreeMap<String, ClientCompatConfig>,
 }
 
 impl AppConfig {
@@ -84,6 +633,21 @@ impl AppConfig {
     pub fn has_backends(&self) -> bool {
         !self.z2m.servers.is_empty() || !self.hass.servers.is_empty()
     }
+
+    /// Run policy for the service `name` (e.g. "http" or "z2m@kitchen")
+    ///
+    /// Falls back to the policy configured for the template ("z2m"), and then
+    /// to the default policy.
+    #[must_use]
+    pub fn service_policy(&self, name: &str) -> Policy {
+        let svc_name = ServiceName::from(name);
+        self.services
+            .get(name)
+            .or_else(|| self.services.get(svc_name.name()))
+            .cloned()
+            .unwrap_or_default()
+            .policy()
+    }
 }
 
 impl Z2mServer {
@@ -142,4 +706,9 @@ impl Client {
     pub async fn config(&self) -> BifrostResult<AppConfig> {
         self.get("config").await
     }
+
+    /// The state database of the running instance (as stored in the state file)
+    pub async fn state(&self) -> BifrostResult<Value> {
+        self.get("state").await
+    }
 }
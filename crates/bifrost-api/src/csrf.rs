@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Header carrying the csrf token, on requests that change anything
+pub const CSRF_HEADER: &str = "x-bifrost-csrf";
+
+/// Csrf token of the running instance
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CsrfToken {
+    /// The token, or `None` if csrf protection is disabled
+    pub token: Option<String>,
+}
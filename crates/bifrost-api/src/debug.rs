@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::{ResourceLink, ResourceRecord};
+use svc::serviceid::ServiceName;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeStats {
+    pub workers: Vec<WorkerStats>,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+    pub process: ProcessStats,
+    pub services: BTreeMap<Uuid, ServiceUsage>,
+    /// Only available when built with the "alloc-stats" feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alloc: Option<AllocStats>,
+}
+
+/// Tokio worker thread statistics
+///
+/// A worker that is busy (nearly) all the time, while its park count stays
+/// the same, is likely blocked by a task.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct WorkerStats {
+    pub busy_ms: u64,
+    pub park_count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ProcessStats {
+    pub user_ms: u64,
+    pub system_ms: u64,
+    /// Peak resident memory (kilobytes on linux, bytes on macos)
+    pub max_rss: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServiceUsage {
+    pub name: ServiceName,
+    pub busy_ms: u64,
+    pub uptime_ms: u64,
+    /// Estimated usage (of a single core), based on time spent polling the
+    /// service task. Work spawned into separate tasks is not included.
+    pub cpu_percent: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AllocStats {
+    /// Bytes currently allocated
+    pub allocated: u64,
+    /// Highest number of bytes allocated at once
+    pub peak: u64,
+    pub allocations: u64,
+    pub deallocations: u64,
+}
+
+/// A (Hue Sync) entertainment streaming session
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EntertainmentSession {
+    /// Address of the streaming client
+    pub client: String,
+    /// PSK identity presented in the DTLS handshake
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Digest of the PSK used for the handshake (never the key itself)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub psk_digest: Option<String>,
+    /// Entertainment configuration being streamed to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area: Option<Uuid>,
+    pub started_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_frame_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<String>,
+    pub frames: u64,
+    /// Frames missing from the sequence numbers of the received frames
+    pub dropped_frames: u64,
+    /// Frames received in the last full second
+    pub fps: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Entertainment streaming counters, since startup
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EntertainmentCounters {
+    pub sessions: u64,
+    pub handshake_failures: u64,
+    pub timeouts: u64,
+    pub desyncs: u64,
+    pub errors: u64,
+    pub frames: u64,
+    pub dropped_frames: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EntertainmentReport {
+    pub active: Vec<EntertainmentSession>,
+    /// Most recently finished sessions, newest first
+    pub recent: Vec<EntertainmentSession>,
+    pub counters: EntertainmentCounters,
+}
+
+/// Requests for a hue api feature that bifrost does not implement (yet)
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CompatGap {
+    pub method: String,
+    /// Request path, with ids replaced by "{id}"
+    pub path: String,
+    pub reason: String,
+    pub count: u64,
+    pub first_seen: String,
+    pub last_seen: String,
+    /// Digest of the last request body (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_digest: Option<String>,
+    /// Devicetypes (as sent when pairing) of the apps making the request
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub devicetypes: BTreeSet<String>,
+}
+
+/// Latency and status summary for a single http route
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RouteStats {
+    pub method: String,
+    /// Route pattern, e.g. "/clip/v2/resource/{rtype}"
+    pub route: String,
+    pub count: u64,
+    pub sum_ms: f64,
+    pub max_ms: f64,
+    /// Latency percentiles, estimated from histogram buckets
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    /// Number of responses, by status code
+    pub status: BTreeMap<u16, u64>,
+}
+
+/// Time from sending a request to a backend, until the resulting state
+/// change is reported back
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BackendLatencyStats {
+    pub backend: String,
+    /// Number of confirmed requests
+    pub count: u64,
+    /// Number of requests never confirmed
+    pub timeouts: u64,
+    pub max_ms: f64,
+    /// Latency percentiles, estimated from histogram buckets
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// A light (or room) whose requests consistently take long to confirm
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SlowEntity {
+    pub id: Uuid,
+    pub name: String,
+    pub backend: String,
+    /// Latency of the most recent requests (timeouts included)
+    pub recent_ms: Vec<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LatencyReport {
+    pub backends: Vec<BackendLatencyStats>,
+    pub slow: Vec<SlowEntity>,
+}
+
+/// A resource, together with everything needed to see how it fits in
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResourceInspection {
+    /// The resource itself (including its id_v1 mapping, if any)
+    pub resource: ResourceRecord,
+    /// The resource owning this one (e.g. the device of a light)
+    pub owner: Option<ResourceLink>,
+    /// Id of the backend handling requests for the resource, if any
+    pub backend: Option<String>,
+    /// Resources owned by, or referenced from, this resource
+    pub services: Vec<ResourceLink>,
+    /// Owner and services that do not exist
+    pub missing: Vec<ResourceLink>,
+}
+
+/// A resource and (recursively) the resources it owns or references
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ResourceTree {
+    pub resource: ResourceLink,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_v1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// The resource is referenced, but does not exist
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub missing: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ResourceTree>,
+}
+
+impl Client {
+    pub async fn debug_resource(&self, id: Uuid) -> BifrostResult<ResourceInspection> {
+        self.get(&format!("debug/resource/{id}")).await
+    }
+
+    pub async fn debug_resource_tree(&self, id: Uuid) -> BifrostResult<ResourceTree> {
+        self.get(&format!("debug/resource/{id}/tree")).await
+    }
+
+    pub async fn debug_entertainment(&self) -> BifrostResult<EntertainmentReport> {
+        self.get("debug/entertainment").await
+    }
+
+    pub async fn debug_gaps(&self) -> BifrostResult<Vec<CompatGap>> {
+        self.get("debug/gaps").await
+    }
+
+    /// Forget all recorded gaps. Returns the gaps that were removed.
+    pub async fn debug_clear_gaps(&self) -> BifrostResult<Vec<CompatGap>> {
+        self.delete("debug/gaps").await
+    }
+
+    pub async fn debug_routes(&self) -> BifrostResult<Vec<RouteStats>> {
+        self.get("debug/routes").await
+    }
+
+    pub async fn debug_latency(&self) -> BifrostResult<LatencyReport> {
+        self.get("debug/latency").await
+    }
+
+    pub async fn debug_runtime(&self) -> BifrostResult<RuntimeStats> {
+        self.get("debug/runtime").await
+    }
+}
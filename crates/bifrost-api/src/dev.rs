@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+/// An endpoint of the emulated hue apis, with an example request
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ApiExample {
+    /// "v1", "v2" or "bridge"
+    pub api: String,
+    pub method: String,
+    /// Path of the example request (using ids from the live instance)
+    pub path: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+    /// Only show the request, instead of sending it (for requests with lasting
+    /// effects, like pairing a new app)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+}
+
+/// The endpoints shown by the api explorer (`/bifrost/dev/api`)
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ApiCatalog {
+    /// Where the hue apis are served, if not on the same server as the
+    /// management api
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Username (application key) accepted by the hue apis
+    pub username: String,
+    pub endpoints: Vec<ApiExample>,
+}
+
+impl Client {
+    pub async fn dev_api_catalog(&self) -> BifrostResult<ApiCatalog> {
+        self.get("dev/api/endpoints").await
+    }
+}
@@ -1,7 +1,20 @@
+pub mod about;
+pub mod action;
 pub mod backend;
 pub mod config;
+pub mod csrf;
+pub mod debug;
+pub mod dev;
 pub mod error;
+pub mod light;
+pub mod presence;
+pub mod resource;
+pub mod room;
+pub mod rule;
+pub mod scene;
 pub mod service;
+pub mod updater;
+pub mod voice;
 pub mod websocket;
 
 mod client;
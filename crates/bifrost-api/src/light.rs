@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::LightUpdate;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+/// Default state for a light, applied when it becomes available again
+/// (typically after a power cut), if it comes back on.
+///
+/// This is applied by bifrost, independently of the power-on behavior
+/// configured in the light itself.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct LightStartup {
+    /// Brightness (in percent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<f64>,
+    /// Color temperature (in mirek)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirek: Option<u16>,
+}
+
+impl LightStartup {
+    /// True if there is nothing to apply
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.brightness.is_none() && self.mirek.is_none()
+    }
+
+    /// The update to send to a light, to apply this startup state
+    #[must_use]
+    pub fn light_update(&self) -> LightUpdate {
+        LightUpdate::new()
+            .with_brightness(self.brightness.map(|bri| bri.clamp(0.0, 100.0)))
+            .with_color_temperature(self.mirek)
+    }
+}
+
+/// Order in which the segments of a gradient light are addressed, when
+/// streaming entertainment.
+///
+/// Some gradient lights (mostly behind zigbee2mqtt) number their segments
+/// differently from what Hue apps expect, which shows up as colors in the
+/// wrong place. Entry `n` is the physical segment that entertainment segment
+/// `n` is rendered on.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LightSegmentMap {
+    pub segments: Vec<u16>,
+}
+
+impl LightSegmentMap {
+    /// Physical segment for entertainment segment `index`. Segments that are
+    /// not mapped are rendered as-is.
+    #[must_use]
+    pub fn segment(&self, index: u16) -> u16 {
+        self.segments
+            .get(usize::from(index))
+            .copied()
+            .unwrap_or(index)
+    }
+}
+
+/// Entertainment segments of a light
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LightSegments {
+    pub name: String,
+    /// Number of segments the light renders
+    pub count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<LightSegmentMap>,
+}
+
+impl Client {
+    /// Startup states of all lights that have one
+    pub async fn light_startups(&self) -> BifrostResult<BTreeMap<Uuid, LightStartup>> {
+        self.get("lights/startup").await
+    }
+
+    /// Startup state of light `id`
+    pub async fn light_startup(&self, id: Uuid) -> BifrostResult<Option<LightStartup>> {
+        self.get(&format!("lights/{id}/startup")).await
+    }
+
+    /// Set the startup state of light `id`
+    pub async fn set_light_startup(&self, id: Uuid, startup: LightStartup) -> BifrostResult<()> {
+        self.put(&format!("lights/{id}/startup"), startup).await
+    }
+
+    /// Remove the startup state of light `id`
+    pub async fn delete_light_startup(&self, id: Uuid) -> BifrostResult<()> {
+        self.delete(&format!("lights/{id}/startup")).await
+    }
+
+    /// Segments of all lights that render more than one
+    pub async fn light_segments(&self) -> BifrostResult<BTreeMap<Uuid, LightSegments>> {
+        self.get("lights/segments").await
+    }
+
+    /// Segments of light `id`
+    pub async fn light_segment(&self, id: Uuid) -> BifrostResult<LightSegments> {
+        self.get(&format!("lights/{id}/segments")).await
+    }
+
+    /// Set the segment order of light `id`
+    pub async fn set_light_segment_map(&self, id: Uuid, map: LightSegmentMap) -> BifrostResult<()> {
+        self.put(&format!("lights/{id}/segments"), map).await
+    }
+
+    /// Restore the default segment order of light `id`
+    pub async fn delete_light_segment_map(&self, id: Uuid) -> BifrostResult<()> {
+        self.delete(&format!("lights/{id}/segments")).await
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use hue::api::ResourceLink;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+/// State of the presence simulation ("away mode")
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PresenceStatus {
+    /// Is the simulation enabled?
+    pub enabled: bool,
+    /// Is the simulation currently toggling lights (enabled, and within the
+    /// configured hours)?
+    pub active: bool,
+    /// Grouped lights currently turned on by the simulation
+    pub lit: Vec<ResourceLink>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PresenceUpdate {
+    pub enabled: bool,
+}
+
+impl Client {
+    pub async fn presence(&self) -> BifrostResult<PresenceStatus> {
+        self.get("presence").await
+    }
+
+    /// Enable or disable the presence simulation
+    pub async fn set_presence(&self, enabled: bool) -> BifrostResult<PresenceStatus> {
+        self.put("presence", PresenceUpdate { enabled }).await
+    }
+}
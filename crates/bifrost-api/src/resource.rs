@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::ResourceLink;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ResourceOwner {
+    /// The requested resource
+    pub resource: ResourceLink,
+    /// Id of the backend owning the resource (e.g. "z2m@kitchen"), if any
+    pub owner: Option<String>,
+    /// The resource claimed by the owning backend (e.g. the device of a light)
+    pub claimed: Option<ResourceLink>,
+}
+
+impl Client {
+    pub async fn resource_owner(&self, id: Uuid) -> BifrostResult<ResourceOwner> {
+        self.get(&format!("resources/{id}/owner")).await
+    }
+}
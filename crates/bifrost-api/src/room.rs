@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::{LightPowerup, LightPowerupUpdate, ResourceLink};
+
+use crate::Client;
+use crate::action::ActionResponse;
+use crate::error::BifrostResult;
+
+/// Aggregated state of a single room
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RoomSummary {
+    pub room: ResourceLink,
+    pub name: String,
+    /// Number of lights in the room
+    pub lights: usize,
+    /// Number of lights currently on
+    pub lights_on: usize,
+    /// Average brightness (in percent) of the lights that are on
+    pub average_brightness: Option<f64>,
+    /// Number of (enabled) motion sensors in the room
+    pub motion_sensors: usize,
+    /// Is any motion sensor in the room currently reporting motion?
+    pub motion: bool,
+    /// Average temperature (in °C) reported by sensors in the room
+    pub temperature: Option<f64>,
+}
+
+/// Power-on behavior of a single light in a room
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LightPowerupState {
+    pub light: ResourceLink,
+    pub name: String,
+    pub powerup: Option<LightPowerup>,
+}
+
+/// Rooms of different backends, that share the same name
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomDuplicate {
+    pub name: String,
+    pub rooms: Vec<RoomOwner>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomOwner {
+    pub room: ResourceLink,
+    /// Backend that created the room (e.g. "z2m@default"), if known
+    pub backend: Option<String>,
+}
+
+impl Client {
+    pub async fn room_summary(&self) -> BifrostResult<Vec<RoomSummary>> {
+        self.get("rooms/summary").await
+    }
+
+    /// Rooms sharing their name with a room of another backend
+    pub async fn room_duplicates(&self) -> BifrostResult<Vec<RoomDuplicate>> {
+        self.get("rooms/duplicates").await
+    }
+
+    /// Power-on behavior of each light in room `id`
+    pub async fn room_powerup(&self, id: Uuid) -> BifrostResult<Vec<LightPowerupState>> {
+        self.get(&format!("rooms/{id}/powerup")).await
+    }
+
+    /// Set the power-on behavior of all lights in room `id`
+    pub async fn set_room_powerup(
+        &self,
+        id: Uuid,
+        powerup: LightPowerupUpdate,
+    ) -> BifrostResult<ActionResponse> {
+        self.put(&format!("rooms/{id}/powerup"), powerup).await
+    }
+}
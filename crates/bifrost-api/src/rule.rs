@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::ResourceLink;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+/// What a rule does when it triggers
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Recall a scene
+    Scene { scene: ResourceLink },
+    /// Turn on the room, optionally at a fixed brightness (in percent)
+    Light {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        brightness: Option<f64>,
+    },
+    /// Turn off the room
+    Off,
+}
+
+/// Motion-activated lighting, executed by bifrost itself
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MotionRule {
+    pub name: String,
+    #[serde(default = "MotionRule::default_enabled")]
+    pub enabled: bool,
+    /// Motion sensor service triggering the rule
+    pub sensor: ResourceLink,
+    /// Room whose lights are controlled
+    pub room: ResourceLink,
+    /// Action during the day
+    pub day: RuleAction,
+    /// Action at night (if different from the day action)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub night: Option<RuleAction>,
+    /// Hour (local time, 0-23) when night starts
+    #[serde(default = "MotionRule::default_night_start")]
+    pub night_start_hour: u32,
+    /// Hour (local time, 0-23) when night ends
+    #[serde(default = "MotionRule::default_night_end")]
+    pub night_end_hour: u32,
+    /// Seconds without motion before the lights are turned off again
+    #[serde(default = "MotionRule::default_timeout")]
+    pub timeout: u32,
+    /// Only trigger while the light level reported next to the motion sensor
+    /// is below this (in hue units: 10000 * log10(lux) + 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_light_level: Option<u32>,
+}
+
+impl MotionRule {
+    const fn default_enabled() -> bool {
+        true
+    }
+
+    const fn default_night_start() -> u32 {
+        23
+    }
+
+    const fn default_night_end() -> u32 {
+        7
+    }
+
+    const fn default_timeout() -> u32 {
+        300
+    }
+}
+
+/// Door/window-activated lighting, executed by bifrost itself
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ContactRule {
+    pub name: String,
+    #[serde(default = "ContactRule::default_enabled")]
+    pub enabled: bool,
+    /// Contact sensor service triggering the rule
+    pub sensor: ResourceLink,
+    /// Room whose lights are controlled
+    pub room: ResourceLink,
+    /// Action when the contact opens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<RuleAction>,
+    /// Action when the contact closes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close: Option<RuleAction>,
+    /// Seconds after triggering before the lights are turned off again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_off: Option<u32>,
+}
+
+impl ContactRule {
+    const fn default_enabled() -> bool {
+        true
+    }
+}
+
+impl Client {
+    pub async fn motion_rules(&self) -> BifrostResult<BTreeMap<Uuid, MotionRule>> {
+        self.get("rules").await
+    }
+
+    pub async fn motion_rule(&self, id: Uuid) -> BifrostResult<MotionRule> {
+        self.get(&format!("rules/{id}")).await
+    }
+
+    /// Add a new motion rule, returning its id
+    pub async fn add_motion_rule(&self, rule: MotionRule) -> BifrostResult<Uuid> {
+        self.post("rules", rule).await
+    }
+
+    pub async fn set_motion_rule(&self, id: Uuid, rule: MotionRule) -> BifrostResult<()> {
+        self.put(&format!("rules/{id}"), rule).await
+    }
+
+    pub async fn delete_motion_rule(&self, id: Uuid) -> BifrostResult<()> {
+        self.delete(&format!("rules/{id}")).await
+    }
+
+    pub async fn contact_rules(&self) -> BifrostResult<BTreeMap<Uuid, ContactRule>> {
+        self.get("rules/contact").await
+    }
+
+    pub async fn contact_rule(&self, id: Uuid) -> BifrostResult<ContactRule> {
+        self.get(&format!("rules/contact/{id}")).await
+    }
+
+    /// Add a new contact rule, returning its id
+    pub async fn add_contact_rule(&self, rule: ContactRule) -> BifrostResult<Uuid> {
+        self.post("rules/contact", rule).await
+    }
+
+    pub async fn set_contact_rule(&self, id: Uuid, rule: ContactRule) -> BifrostResult<()> {
+        self.put(&format!("rules/contact/{id}"), rule).await
+    }
+
+    pub async fn delete_contact_rule(&self, id: Uuid) -> BifrostResult<()> {
+        self.delete(&format!("rules/contact/{id}")).await
+    }
+}
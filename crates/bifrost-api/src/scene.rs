@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use hue::api::ResourceLink;
+
+use crate::Client;
+use crate::config::TimeOfDay;
+use crate::error::BifrostResult;
+
+/// Scenes to recall instead of a scene, depending on the time of day.
+///
+/// Parts of the day without a variant recall the scene itself.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SceneVariants {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub morning: Option<ResourceLink>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<ResourceLink>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evening: Option<ResourceLink>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub night: Option<ResourceLink>,
+}
+
+impl SceneVariants {
+    /// True if there are no variants at all
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.morning.is_none()
+            && self.day.is_none()
+            && self.evening.is_none()
+            && self.night.is_none()
+    }
+
+    /// The variant for `time` (if any)
+    #[must_use]
+    pub const fn get(&self, time: TimeOfDay) -> Option<&ResourceLink> {
+        match time {
+            TimeOfDay::Morning => self.morning.as_ref(),
+            TimeOfDay::Day => self.day.as_ref(),
+            TimeOfDay::Evening => self.evening.as_ref(),
+            TimeOfDay::Night => self.night.as_ref(),
+        }
+    }
+
+    /// All scenes used as a variant
+    pub fn scenes(&self) -> impl Iterator<Item = &ResourceLink> {
+        [&self.morning, &self.day, &self.evening, &self.night]
+            .into_iter()
+            .flatten()
+    }
+}
+
+impl Client {
+    /// Variants of all scenes that have them
+    pub async fn all_scene_variants(&self) -> BifrostResult<BTreeMap<Uuid, SceneVariants>> {
+        self.get("scenes/variants").await
+    }
+
+    /// Variants of scene `id`
+    pub async fn scene_variants(&self, id: Uuid) -> BifrostResult<Option<SceneVariants>> {
+        self.get(&format!("scenes/{id}/variants")).await
+    }
+
+    /// Set the variants of scene `id`
+    pub async fn set_scene_variants(&self, id: Uuid, variants: SceneVariants) -> BifrostResult<()> {
+        self.put(&format!("scenes/{id}/variants"), variants).await
+    }
+
+    /// Remove the variants of scene `id`
+    pub async fn delete_scene_variants(&self, id: Uuid) -> BifrostResult<()> {
+        self.delete(&format!("scenes/{id}/variants")).await
+    }
+}
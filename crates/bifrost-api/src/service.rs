@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -14,6 +14,15 @@ pub struct Service {
     pub id: Uuid,
     pub name: ServiceName,
     pub state: ServiceState,
+    /// Number of times the service has been restarted by its run policy
+    #[serde(default)]
+    pub restarts: u32,
+    /// Services that must be running before this service starts
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub depends_on: BTreeSet<ServiceName>,
+    /// Dependencies that are holding back the start of this service
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub waiting_for: BTreeSet<ServiceName>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default, Eq, PartialEq)]
@@ -35,4 +44,16 @@ impl Client {
         self.put(&format!("service/{id}"), ServiceState::Running)
             .await
     }
+
+    /// Regenerate a backend service ("z2m@name" or "hass@name") from its
+    /// template, with the (partial) server settings in `overrides` applied.
+    ///
+    /// Returns the id of the new service.
+    pub async fn service_reconfigure(
+        &self,
+        id: Uuid,
+        overrides: &serde_json::Value,
+    ) -> BifrostResult<Uuid> {
+        self.put(&format!("service/{id}/config"), overrides).await
+    }
 }
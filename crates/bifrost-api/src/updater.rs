@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UpdaterStatus {
+    pub enabled: bool,
+    pub pinned_version: Option<u64>,
+    pub mirror_url: Option<Url>,
+    pub last_fetch: Option<String>,
+    pub version: u64,
+    pub version_name: String,
+    pub software_version: String,
+}
+
+/// Newest Bifrost release, compared to the running version
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ReleaseStatus {
+    /// False unless release checks are configured
+    pub enabled: bool,
+    pub current_version: String,
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    pub release_url: Option<Url>,
+    pub published_at: Option<String>,
+    pub last_check: Option<String>,
+    pub last_error: Option<String>,
+    /// Path of the downloaded release binary, once staged
+    pub staged_path: Option<String>,
+}
+
+impl Client {
+    pub async fn updater_status(&self) -> BifrostResult<UpdaterStatus> {
+        self.get("updater").await
+    }
+
+    pub async fn release_status(&self) -> BifrostResult<ReleaseStatus> {
+        self.get("updater/release").await
+    }
+
+    pub async fn release_check(&self) -> BifrostResult<ReleaseStatus> {
+        self.post("updater/release/check", ()).await
+    }
+}
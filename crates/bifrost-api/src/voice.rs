@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Client;
+use crate::error::BifrostResult;
+
+/// A light, as exposed to voice assistants (through the hue v1 api)
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct VoiceDevice {
+    pub id: Uuid,
+    pub id_v1: Option<u32>,
+    /// Name of the room the light is in (if any)
+    pub room: Option<String>,
+    /// Name of the light itself
+    pub name: String,
+    /// Room-qualified name (e.g. "Kitchen Ceiling")
+    pub voice_name: String,
+    /// Is the name currently exposed on the v1 api the room-qualified name?
+    pub prefixed: bool,
+    /// Is the exposed name shared with another light? Voice assistants can't
+    /// tell such lights apart.
+    pub ambiguous: bool,
+}
+
+impl Client {
+    /// All lights, with the names voice assistants will import
+    pub async fn voice_devices(&self) -> BifrostResult<Vec<VoiceDevice>> {
+        self.get("voice/devices").await
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::ResourceLink;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceSoftwareUpdateState {
+    #[default]
+    NoUpdate,
+    UpdatePending,
+    ReadyToInstall,
+    Installing,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeviceSoftwareUpdate {
+    pub owner: ResourceLink,
+    pub state: DeviceSoftwareUpdateState,
+    pub problems: Vec<Value>,
+}
+
+impl DeviceSoftwareUpdate {
+    #[must_use]
+    pub const fn new(owner: ResourceLink) -> Self {
+        Self {
+            owner,
+            state: DeviceSoftwareUpdateState::NoUpdate,
+            problems: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceSoftwareUpdateAction {
+    /// Check for (and download) a newer firmware version
+    Check,
+    /// Install a downloaded firmware version
+    Install,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeviceSoftwareUpdateUpdate {
+    pub action: DeviceSoftwareUpdateAction,
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::api::{ColorTemperatureUpdate, ColorUpdate, DimmingUpdate, On, ResourceLink, Stub};
+use crate::api::{
+    ColorTemperatureUpdate, ColorUpdate, DimmingUpdate, LightDynamicsUpdate, LightUpdate, On,
+    ResourceLink, Stub,
+};
 use crate::legacy_api::ApiLightStateUpdate;
 use crate::xy::XY;
 
@@ -133,6 +136,23 @@ impl GroupedLightUpdate {
     }
 }
 
+/* the same update, for a single light of the group */
+impl From<&GroupedLightUpdate> for LightUpdate {
+    fn from(upd: &GroupedLightUpdate) -> Self {
+        Self {
+            on: upd.on,
+            dimming: upd.dimming,
+            color: upd.color,
+            color_temperature: upd.color_temperature,
+            dynamics: upd
+                .dynamics
+                .as_ref()
+                .map(|dyn_upd| LightDynamicsUpdate::new().with_duration(dyn_upd.duration)),
+            ..Self::default()
+        }
+    }
+}
+
 /* conversion from v1 api */
 impl From<&ApiLightStateUpdate> for GroupedLightUpdate {
     fn from(upd: &ApiLightStateUpdate) -> Self {
@@ -124,19 +124,7 @@ impl Light {
             }),
             metadata,
             owner,
-            powerup: Some(LightPowerup {
-                preset: LightPowerupPreset::Safety,
-                configured: true,
-                on: LightPowerupOn::On {
-                    on: On { on: true },
-                },
-                dimming: LightPowerupDimming::Dimming {
-                    dimming: DimmingUpdate { brightness: 100.0 },
-                },
-                color: LightPowerupColor::ColorTemperature {
-                    color_temperature: ColorTemperatureUpdate::new(366),
-                },
-            }),
+            powerup: Some(LightPowerup::preset(LightPowerupPreset::Safety)),
             signaling: Some(LightSignaling {
                 signal_values: vec![
                     LightSignal::NoSignal,
@@ -224,6 +212,10 @@ impl AddAssign<&LightUpdate> for Light {
                 grad.points.clone_from(&grupd.points);
             }
         }
+
+        if let Some(powerup) = &upd.powerup {
+            self.powerup = Some(LightPowerup::from_update(powerup));
+        }
     }
 }
 
@@ -274,6 +266,10 @@ impl Sub<&Light> for &Light {
             upd = upd.with_color_xy(rhs.as_color_opt());
         }
 
+        if self.powerup != rhs.powerup {
+            upd.powerup = rhs.powerup.as_ref().map(Into::into);
+        }
+
         upd
     }
 }
@@ -331,7 +327,7 @@ pub struct LightGradientUpdate {
     pub points: Vec<LightGradientPoint>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum LightPowerupPreset {
     Safety,
@@ -353,6 +349,94 @@ pub struct LightPowerup {
     pub color: LightPowerupColor,
 }
 
+impl LightPowerup {
+    /// The behavior of one of the standard presets.
+    ///
+    /// The custom preset has no fixed behavior, so it comes back unset.
+    #[must_use]
+    pub const fn preset(preset: LightPowerupPreset) -> Self {
+        let (on, dimming, color) = match preset {
+            LightPowerupPreset::Safety => (
+                LightPowerupOn::On {
+                    on: On { on: true },
+                },
+                LightPowerupDimming::Dimming {
+                    dimming: DimmingUpdate { brightness: 100.0 },
+                },
+                LightPowerupColor::ColorTemperature {
+                    color_temperature: ColorTemperatureUpdate { mirek: Some(366) },
+                },
+            ),
+            LightPowerupPreset::Powerfail => (
+                LightPowerupOn::Previous,
+                LightPowerupDimming::Previous,
+                LightPowerupColor::Previous,
+            ),
+            LightPowerupPreset::LastOnState => (
+                LightPowerupOn::On {
+                    on: On { on: true },
+                },
+                LightPowerupDimming::Previous,
+                LightPowerupColor::Previous,
+            ),
+            LightPowerupPreset::Custom => (
+                LightPowerupOn::None,
+                LightPowerupDimming::None,
+                LightPowerupColor::None,
+            ),
+        };
+
+        Self {
+            preset,
+            configured: true,
+            on,
+            dimming,
+            color,
+        }
+    }
+
+    /// The behavior requested by a powerup update from a hue app.
+    ///
+    /// Standard presets always get their standard behavior, and only custom
+    /// presets use the on/dimming/color settings from the update.
+    #[must_use]
+    pub fn from_update(upd: &LightPowerupUpdate) -> Self {
+        if upd.preset != LightPowerupPreset::Custom {
+            return Self::preset(upd.preset);
+        }
+
+        Self {
+            preset: LightPowerupPreset::Custom,
+            configured: true,
+            on: upd.on.clone(),
+            dimming: upd.dimming.clone(),
+            color: upd.color.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LightPowerupUpdate {
+    pub preset: LightPowerupPreset,
+    #[serde(default, skip_serializing_if = "LightPowerupOn::is_none")]
+    pub on: LightPowerupOn,
+    #[serde(default, skip_serializing_if = "LightPowerupDimming::is_none")]
+    pub dimming: LightPowerupDimming,
+    #[serde(default, skip_serializing_if = "LightPowerupColor::is_none")]
+    pub color: LightPowerupColor,
+}
+
+impl From<&LightPowerup> for LightPowerupUpdate {
+    fn from(value: &LightPowerup) -> Self {
+        Self {
+            preset: value.preset,
+            on: value.on.clone(),
+            dimming: value.dimming.clone(),
+            color: value.color.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(tag = "mode", rename_all = "snake_case")]
 pub enum LightPowerupOn {
@@ -657,7 +741,7 @@ pub struct LightUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner: Option<ResourceLink>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub powerup: Option<Value>,
+    pub powerup: Option<LightPowerupUpdate>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dynamics: Option<LightDynamicsUpdate>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -907,3 +991,69 @@ impl From<Dimming> for f64 {
         value.brightness
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::api::{
+        LightPowerup, LightPowerupColor, LightPowerupDimming, LightPowerupOn, LightPowerupPreset,
+        LightPowerupUpdate, LightUpdate, On,
+    };
+
+    #[test]
+    fn powerup_update_deserialize() {
+        let upd: LightUpdate = serde_json::from_value(json!({
+            "powerup": {
+                "preset": "custom",
+                "on": {"mode": "on", "on": {"on": false}},
+            }
+        }))
+        .unwrap();
+
+        let powerup = upd.powerup.unwrap();
+        assert_eq!(powerup.preset, LightPowerupPreset::Custom);
+        assert_eq!(
+            powerup.on,
+            LightPowerupOn::On {
+                on: On { on: false }
+            }
+        );
+        assert!(powerup.dimming.is_none());
+    }
+
+    #[test]
+    fn powerup_preset_ignores_custom_fields() {
+        let upd = LightPowerupUpdate {
+            preset: LightPowerupPreset::Powerfail,
+            on: LightPowerupOn::On {
+                on: On { on: false },
+            },
+            dimming: LightPowerupDimming::None,
+            color: LightPowerupColor::None,
+        };
+
+        let powerup = LightPowerup::from_update(&upd);
+
+        assert_eq!(powerup, LightPowerup::preset(LightPowerupPreset::Powerfail));
+        assert_eq!(powerup.on, LightPowerupOn::Previous);
+        assert!(powerup.configured);
+    }
+
+    #[test]
+    fn powerup_custom_uses_update() {
+        let upd = LightPowerupUpdate {
+            preset: LightPowerupPreset::Custom,
+            on: LightPowerupOn::Previous,
+            dimming: LightPowerupDimming::Previous,
+            color: LightPowerupColor::None,
+        };
+
+        let powerup = LightPowerup::from_update(&upd);
+
+        assert_eq!(powerup.preset, LightPowerupPreset::Custom);
+        assert_eq!(powerup.on, LightPowerupOn::Previous);
+        assert_eq!(powerup.dimming, LightPowerupDimming::Previous);
+        assert_eq!(LightPowerupUpdate::from(&powerup), upd);
+    }
+}
@@ -1,5 +1,6 @@
 mod behavior;
 mod device;
+mod device_software_update;
 mod entertainment;
 mod entertainment_config;
 mod grouped_light;
@@ -11,13 +12,21 @@ mod stream;
 mod stubs;
 mod update;
 mod zigbee_device_discovery;
+mod zone;
 
 pub use behavior::{
     BehaviorInstance, BehaviorInstanceConfiguration, BehaviorInstanceMetadata,
     BehaviorInstanceUpdate, BehaviorScript, BehaviorScriptMetadata, WakeupConfiguration,
     WakeupStyle,
 };
-pub use device::{Device, DeviceArchetype, DeviceProductData, DeviceUpdate, Identify};
+pub use device::{
+    Device, DeviceArchetype, DeviceIdentify, DeviceIdentifyUpdate, DeviceProductData, DeviceUpdate,
+    Identify,
+};
+pub use device_software_update::{
+    DeviceSoftwareUpdate, DeviceSoftwareUpdateAction, DeviceSoftwareUpdateState,
+    DeviceSoftwareUpdateUpdate,
+};
 pub use entertainment::{Entertainment, EntertainmentSegment, EntertainmentSegments};
 pub use entertainment_config::{
     EntertainmentConfiguration, EntertainmentConfigurationAction,
@@ -38,23 +47,24 @@ pub use light::{
     LightEffectValues, LightEffects, LightEffectsV2, LightEffectsV2Update, LightFunction,
     LightGradient, LightGradientMode, LightGradientPoint, LightGradientUpdate, LightMetadata,
     LightMode, LightPowerup, LightPowerupColor, LightPowerupDimming, LightPowerupOn,
-    LightPowerupPreset, LightProductData, LightSignal, LightSignaling, LightTimedEffect,
-    LightTimedEffects, LightTimedEffectsUpdate, LightUpdate, MirekSchema, On,
+    LightPowerupPreset, LightPowerupUpdate, LightProductData, LightSignal, LightSignaling,
+    LightTimedEffect, LightTimedEffects, LightTimedEffectsUpdate, LightUpdate, MirekSchema, On,
 };
 pub use resource::{RType, ResourceLink, ResourceRecord};
 pub use room::{Room, RoomArchetype, RoomMetadata, RoomMetadataUpdate, RoomUpdate};
 pub use scene::{
-    Scene, SceneAction, SceneActionElement, SceneActive, SceneMetadata, SceneRecall, SceneStatus,
-    SceneStatusEnum, SceneUpdate,
+    Scene, SceneAction, SceneActionElement, SceneActive, SceneMetadata, ScenePalette,
+    ScenePaletteColor, ScenePaletteColorTemperature, SceneRecall, SceneStatus, SceneStatusEnum,
+    SceneUpdate,
 };
 use serde::ser::SerializeMap;
 pub use stream::HueStreamKey;
 pub use stubs::{
-    Bridge, BridgeHome, Button, ButtonData, ButtonMetadata, ButtonReport, DevicePower,
-    DeviceSoftwareUpdate, DollarRef, GeofenceClient, Geolocation, GroupedLightLevel, GroupedMotion,
-    Homekit, InternetConnectivity, InternetConnectivityStatus, LightLevel, Matter, Metadata,
-    MetadataUpdate, Motion, PrivateGroup, PublicImage, RelativeRotary, SmartScene, Taurus,
-    Temperature, TimeZone, ZigbeeConnectivity, ZigbeeConnectivityStatus, Zone,
+    AuthV1, Bridge, BridgeHome, Button, ButtonData, ButtonMetadata, ButtonReport, DevicePower,
+    DollarRef, GeofenceClient, Geolocation, GroupedLightLevel, GroupedMotion, Homekit,
+    InternetConnectivity, InternetConnectivityStatus, LightLevel, Matter, Metadata, MetadataUpdate,
+    Motion, PrivateGroup, PublicImage, RelativeRotary, SmartScene, Taurus, TaurusCapability,
+    Temperature, TimeZone, ZigbeeConnectivity, ZigbeeConnectivityStatus,
 };
 pub use update::Update;
 pub use zigbee_device_discovery::{
@@ -62,6 +72,7 @@ pub use zigbee_device_discovery::{
     ZigbeeDeviceDiscoveryStatus, ZigbeeDeviceDiscoveryUpdate, ZigbeeDeviceDiscoveryUpdateAction,
     ZigbeeDeviceDiscoveryUpdateActionType,
 };
+pub use zone::{Zone, ZoneUpdate};
 
 use std::fmt::Debug;
 
@@ -87,7 +98,7 @@ impl Serialize for Stub {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Resource {
-    AuthV1(ResourceLink),
+    AuthV1(AuthV1),
     BehaviorInstance(BehaviorInstance),
     BehaviorScript(BehaviorScript),
     Bridge(Bridge),
@@ -181,7 +192,7 @@ impl Resource {
     #[must_use]
     pub const fn owner(&self) -> Option<ResourceLink> {
         match self {
-            Self::AuthV1(_) => None,
+            Self::AuthV1(obj) => Some(obj.owner),
             Self::BehaviorInstance(_) => None,
             Self::BehaviorScript(_) => None,
             Self::Bridge(obj) => Some(obj.owner),
@@ -318,8 +329,7 @@ macro_rules! resource_conversion_impl {
     };
 }
 
-// AuthV1 is not a real resource (only used in links)
-// resource_conversion_impl!(AuthV1);
+resource_conversion_impl!(AuthV1);
 resource_conversion_impl!(BehaviorInstance);
 resource_conversion_impl!(BehaviorScript);
 resource_conversion_impl!(Bridge);
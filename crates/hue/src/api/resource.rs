@@ -10,7 +10,7 @@ use crate::api::Resource;
 #[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "snake_case")]
 pub enum RType {
-    /// Only used in [`ResourceLink`] references
+    /// Authentication endpoint for the v1 api (one instance per bridge)
     AuthV1,
     BehaviorInstance,
     BehaviorScript,
@@ -5,7 +5,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::api::{
-    ColorTemperatureUpdate, ColorUpdate, DimmingUpdate, LightGradientUpdate, On, ResourceLink,
+    ColorTemperatureUpdate, ColorUpdate, DimmingUpdate, LightDynamicsUpdate, LightGradientUpdate,
+    On, ResourceLink,
 };
 use crate::date_format;
 
@@ -43,23 +44,8 @@ pub struct Scene {
     pub auto_dynamic: bool,
     pub group: ResourceLink,
     pub metadata: SceneMetadata,
-    /* palette: { */
-    /*     color: [], */
-    /*     color_temperature: [ */
-    /*         { */
-    /*             color_temperature: { */
-    /*                 mirek: u32 */
-    /*             }, */
-    /*             dimming: { */
-    /*                 brightness: f64, */
-    /*             } */
-    /*         } */
-    /*     ], */
-    /*     dimming: [], */
-    /*     effects: [] */
-    /* }, */
-    #[serde(default, skip_serializing_if = "Value::is_null")]
-    pub palette: Value,
+    #[serde(default)]
+    pub palette: ScenePalette,
     #[serde(default)]
     pub speed: f64,
     pub status: Option<SceneStatus>,
@@ -83,6 +69,44 @@ pub struct SceneAction {
     pub effects: Value,
 }
 
+/// Colors (and brightness levels) a scene cycles through, when recalled with
+/// the `dynamic_palette` action
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ScenePalette {
+    #[serde(default)]
+    pub color: Vec<ScenePaletteColor>,
+    #[serde(default)]
+    pub dimming: Vec<DimmingUpdate>,
+    #[serde(default)]
+    pub color_temperature: Vec<ScenePaletteColorTemperature>,
+    #[serde(default)]
+    pub effects: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects_v2: Vec<Value>,
+}
+
+impl ScenePalette {
+    /// True if the palette has no colors or brightness levels to cycle through
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.color.is_empty() && self.color_temperature.is_empty() && self.dimming.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ScenePaletteColor {
+    pub color: ColorUpdate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimming: Option<DimmingUpdate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ScenePaletteColorTemperature {
+    pub color_temperature: ColorTemperatureUpdate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimming: Option<DimmingUpdate>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SceneActionElement {
     pub action: SceneAction,
@@ -116,13 +140,60 @@ pub struct SceneUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<SceneMetadataUpdate>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub palette: Option<Value>,
+    pub palette: Option<ScenePalette>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub speed: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auto_dynamic: Option<bool>,
 }
 
+impl Scene {
+    /// The actions to apply when recalling this scene with `recall`.
+    ///
+    /// The `overrides` (actions sent along with the recall) replace the
+    /// stored actions of their lights, for this recall only.
+    ///
+    /// With `recall.dimming`, the brightness of every light the scene turns on
+    /// is scaled by that percentage (lights without a stored brightness get
+    /// it as is), so the scene can be recalled at e.g. 50%.
+    #[must_use]
+    pub fn recall_actions(
+        &self,
+        recall: &SceneRecall,
+        overrides: &[SceneActionElement],
+    ) -> Vec<SceneActionElement> {
+        let mut actions = self.actions.clone();
+        for elem in overrides {
+            match actions.iter_mut().find(|own| own.target == elem.target) {
+                Some(own) => own.action = elem.action.clone(),
+                None => actions.push(elem.clone()),
+            }
+        }
+
+        let Some(dim) = recall.dimming else {
+            return actions;
+        };
+
+        let factor = (dim.brightness / 100.0).clamp(0.0, 1.0);
+
+        actions
+            .into_iter()
+            .map(|mut elem| {
+                if elem.action.on.is_none_or(|on| on.on) {
+                    let brightness = elem
+                        .action
+                        .dimming
+                        .map_or(dim.brightness, |stored| stored.brightness * factor);
+                    elem.action.dimming = Some(DimmingUpdate {
+                        brightness: brightness.clamp(0.0, 100.0),
+                    });
+                }
+                elem
+            })
+            .collect()
+    }
+}
+
 impl SceneUpdate {
     #[must_use]
     pub fn new() -> Self {
@@ -216,3 +287,152 @@ pub struct SceneRecall {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dimming: Option<DimmingUpdate>,
 }
+
+impl SceneRecall {
+    /// The transition of the recalled lights, when recalled over `duration`
+    /// (in milliseconds)
+    #[must_use]
+    pub fn dynamics(&self) -> Option<LightDynamicsUpdate> {
+        self.duration
+            .map(|duration| LightDynamicsUpdate::new().with_duration(Some(duration)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::api::{DimmingUpdate, Scene, SceneActionElement, ScenePalette, SceneRecall};
+
+    fn scene() -> Scene {
+        serde_json::from_value(json!({
+            "actions": [
+                {
+                    "target": {"rid": "00000000-0000-0000-0000-000000000001", "rtype": "light"},
+                    "action": {"on": {"on": true}, "dimming": {"brightness": 80.0}},
+                },
+                {
+                    "target": {"rid": "00000000-0000-0000-0000-000000000002", "rtype": "light"},
+                    "action": {"on": {"on": true}},
+                },
+                {
+                    "target": {"rid": "00000000-0000-0000-0000-000000000003", "rtype": "light"},
+                    "action": {"on": {"on": false}},
+                },
+            ],
+            "group": {"rid": "00000000-0000-0000-0000-000000000004", "rtype": "room"},
+            "metadata": {"name": "Scene"},
+            "status": null,
+        }))
+        .unwrap()
+    }
+
+    const fn dimming(brightness: f64) -> Option<DimmingUpdate> {
+        Some(DimmingUpdate { brightness })
+    }
+
+    #[test]
+    fn palette_roundtrip() {
+        let data = json!({
+            "color": [
+                {"color": {"xy": {"x": 0.6, "y": 0.3}}, "dimming": {"brightness": 70.0}},
+            ],
+            "dimming": [],
+            "color_temperature": [
+                {"color_temperature": {"mirek": 300}, "dimming": {"brightness": 40.0}},
+            ],
+            "effects": [],
+        });
+
+        let palette: ScenePalette = serde_json::from_value(data.clone()).unwrap();
+
+        assert_eq!(palette.color.len(), 1);
+        assert_eq!(palette.color_temperature[0].dimming, dimming(40.0));
+        assert!(!palette.is_empty());
+        assert_eq!(serde_json::to_value(&palette).unwrap(), data);
+    }
+
+    #[test]
+    fn palette_missing_is_empty() {
+        assert!(scene().palette.is_empty());
+    }
+
+    #[test]
+    fn recall_without_dimming() {
+        let actions = scene().recall_actions(&SceneRecall::default(), &[]);
+
+        assert_eq!(actions[0].action.dimming, dimming(80.0));
+        assert_eq!(actions[1].action.dimming, None);
+        assert_eq!(actions[2].action.dimming, None);
+    }
+
+    #[test]
+    fn recall_scales_dimming() {
+        let recall = SceneRecall {
+            dimming: dimming(50.0),
+            ..SceneRecall::default()
+        };
+        let actions = scene().recall_actions(&recall, &[]);
+
+        // stored brightness is scaled
+        assert_eq!(actions[0].action.dimming, dimming(40.0));
+        // no stored brightness: use recall brightness
+        assert_eq!(actions[1].action.dimming, dimming(50.0));
+        // lights turned off are left alone
+        assert_eq!(actions[2].action.dimming, None);
+    }
+
+    #[test]
+    fn recall_dimming_is_clamped() {
+        let recall = SceneRecall {
+            dimming: dimming(150.0),
+            ..SceneRecall::default()
+        };
+        let actions = scene().recall_actions(&recall, &[]);
+
+        assert_eq!(actions[0].action.dimming, dimming(80.0));
+        assert_eq!(actions[1].action.dimming, dimming(100.0));
+    }
+
+    #[test]
+    fn recall_overrides() {
+        let overrides: Vec<SceneActionElement> = serde_json::from_value(json!([
+            {
+                "target": {"rid": "00000000-0000-0000-0000-000000000002", "rtype": "light"},
+                "action": {"on": {"on": true}, "dimming": {"brightness": 60.0}},
+            },
+            {
+                "target": {"rid": "00000000-0000-0000-0000-000000000005", "rtype": "light"},
+                "action": {"on": {"on": false}},
+            },
+        ]))
+        .unwrap();
+        let recall = SceneRecall {
+            dimming: dimming(50.0),
+            ..SceneRecall::default()
+        };
+        let actions = scene().recall_actions(&recall, &overrides);
+
+        assert_eq!(actions.len(), 4);
+        assert_eq!(actions[0].action.dimming, dimming(40.0));
+        // the override is scaled like a stored action
+        assert_eq!(actions[1].action.dimming, dimming(30.0));
+        assert_eq!(actions[2].action.dimming, None);
+        assert_eq!(actions[3].target, overrides[1].target);
+        assert_eq!(actions[3].action.dimming, None);
+    }
+
+    #[test]
+    fn recall_duration() {
+        let recall = SceneRecall {
+            duration: Some(1500),
+            ..SceneRecall::default()
+        };
+
+        assert_eq!(
+            recall.dynamics().and_then(|dynamics| dynamics.duration),
+            Some(1500)
+        );
+        assert!(SceneRecall::default().dynamics().is_none());
+    }
+}
@@ -2,6 +2,7 @@ use hex::FromHexError;
 
 use crate::error::{HueError, HueResult};
 
+#[derive(Clone)]
 pub struct HueStreamKey {
     key: [u8; Self::BYTE_SIZE],
 }
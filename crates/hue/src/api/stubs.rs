@@ -63,13 +63,6 @@ pub struct DevicePower {
     pub power_state: Value,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct DeviceSoftwareUpdate {
-    pub owner: ResourceLink,
-    pub state: Value,
-    pub problems: Vec<Value>,
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GeofenceClient {
     pub name: String,
@@ -169,12 +162,52 @@ pub struct SmartScene {
     pub week_timeslots: Value,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TaurusCapability {
+    Sensor,
+    Collector,
+    Sync,
+
+    #[serde(untagged)]
+    Other(String),
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Taurus {
-    pub capabilities: Vec<String>,
+    pub capabilities: Vec<TaurusCapability>,
+    pub owner: ResourceLink,
+}
+
+impl Taurus {
+    #[must_use]
+    pub fn new(owner: ResourceLink) -> Self {
+        Self {
+            capabilities: vec![
+                TaurusCapability::Sensor,
+                TaurusCapability::Collector,
+                TaurusCapability::Sync,
+            ],
+            owner,
+        }
+    }
+}
+
+/// The v1 api authentication endpoint (application keys). Real bridges only
+/// refer to it in links (e.g. from entertainment configurations), it is not
+/// listed as a resource.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthV1 {
     pub owner: ResourceLink,
 }
 
+impl AuthV1 {
+    #[must_use]
+    pub const fn new(owner: ResourceLink) -> Self {
+        Self { owner }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum InternetConnectivityStatus {
@@ -206,14 +239,6 @@ pub struct ZigbeeConnectivity {
     pub status: ZigbeeConnectivityStatus,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Zone {
-    pub metadata: Metadata,
-    pub children: BTreeSet<ResourceLink>,
-    #[serde(default)]
-    pub services: BTreeSet<ResourceLink>,
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Temperature {
     pub enabled: bool,
@@ -260,3 +285,64 @@ pub struct MetadataUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function: Option<LightFunction>,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, from_str, json, to_value};
+    use uuid::uuid;
+
+    use crate::api::{RType, Resource, ResourceRecord, TaurusCapability};
+
+    /// A `taurus_7455` record, in the form a real bridge lists it
+    const TAURUS: &str = r#"{
+        "id": "0ae81bff-5f28-4b5b-b2a3-f93c28e8b0b6",
+        "owner": {
+            "rid": "5c8f3a6c-4e0d-4d4a-9d7e-0d0e1c4b2f11",
+            "rtype": "device"
+        },
+        "capabilities": ["sensor", "collector", "sync"],
+        "type": "taurus_7455"
+    }"#;
+
+    #[test]
+    fn taurus_bridge_record() {
+        let rec: ResourceRecord = from_str(TAURUS).unwrap();
+        let Resource::Taurus(obj) = &rec.obj else {
+            panic!("Wrong resource type");
+        };
+
+        assert_eq!(
+            obj.capabilities,
+            [
+                TaurusCapability::Sensor,
+                TaurusCapability::Collector,
+                TaurusCapability::Sync,
+            ]
+        );
+        assert_eq!(
+            obj.owner,
+            RType::Device.link_to(uuid!("5c8f3a6c-4e0d-4d4a-9d7e-0d0e1c4b2f11"))
+        );
+        assert_eq!(to_value(&rec).unwrap(), from_str::<Value>(TAURUS).unwrap());
+    }
+
+    #[test]
+    fn taurus_unknown_capability() {
+        let mut data: Value = from_str(TAURUS).unwrap();
+        data["capabilities"] = json!(["sensor", "relay"]);
+
+        let rec: ResourceRecord = serde_json::from_value(data.clone()).unwrap();
+        let Resource::Taurus(obj) = &rec.obj else {
+            panic!("Wrong resource type");
+        };
+
+        assert_eq!(
+            obj.capabilities,
+            [
+                TaurusCapability::Sensor,
+                TaurusCapability::Other("relay".to_string()),
+            ]
+        );
+        assert_eq!(to_value(&rec).unwrap(), data);
+    }
+}
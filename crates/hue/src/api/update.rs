@@ -3,14 +3,13 @@ use serde_json::Value;
 
 use crate::api::{
     BehaviorInstanceUpdate, DeviceUpdate, EntertainmentConfigurationUpdate, GroupedLightUpdate,
-    LightUpdate, RType, RoomUpdate, SceneUpdate,
+    LightUpdate, RType, RoomUpdate, SceneUpdate, ZoneUpdate,
 };
 
 type BridgeUpdate = Value;
 type BridgeHomeUpdate = Value;
 type ZigbeeDeviceDiscoveryUpdate = Value;
 type SmartSceneUpdate = Value;
-type ZoneUpdate = Value;
 type GeolocationUpdate = Value;
 
 #[allow(clippy::large_enum_variant)]
@@ -0,0 +1,42 @@
+use std::collections::BTreeSet;
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{RType, ResourceLink, RoomMetadata, RoomMetadataUpdate};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Zone {
+    pub children: BTreeSet<ResourceLink>,
+    pub metadata: RoomMetadata,
+    #[serde(default)]
+    pub services: BTreeSet<ResourceLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ZoneUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<BTreeSet<ResourceLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RoomMetadataUpdate>,
+}
+
+impl Zone {
+    #[must_use]
+    pub fn grouped_light_service(&self) -> Option<&ResourceLink> {
+        self.services
+            .iter()
+            .find(|rl| rl.rtype == RType::GroupedLight)
+    }
+}
+
+impl AddAssign<&ZoneUpdate> for Zone {
+    fn add_assign(&mut self, rhs: &ZoneUpdate) {
+        if let Some(md) = &rhs.metadata {
+            self.metadata += md;
+        }
+        if let Some(children) = &rhs.children {
+            self.children.clone_from(children);
+        }
+    }
+}
@@ -114,7 +114,7 @@ mod tests {
     use serde_json::json;
     use uuid::Uuid;
 
-    use crate::api::{RType, Resource, ResourceLink, ResourceRecord};
+    use crate::api::{AuthV1, RType, Resource, ResourceRecord};
     use crate::event::{Add, Delete, Event, EventBlock, Update};
 
     // just some uuid for testing
@@ -125,10 +125,7 @@ mod tests {
         let obj = ResourceRecord::new(
             ID,
             None,
-            Resource::AuthV1(ResourceLink {
-                rid: ID,
-                rtype: RType::AuthV1,
-            }),
+            Resource::AuthV1(AuthV1::new(RType::Device.link_to(ID))),
         );
 
         let add = EventBlock::add(vec![obj.clone()]);
@@ -595,6 +595,36 @@ pub struct ApiLight {
 }
 
 impl ApiLight {
+    #[must_use]
+    pub fn with_name(self, name: String) -> Self {
+        Self { name, ..self }
+    }
+
+    /// Use a hue style uniqueid (mac address of the light, and endpoint),
+    /// which some clients expect
+    #[must_use]
+    pub fn with_hue_uniqueid(self, uuid: &Uuid) -> Self {
+        let b = uuid.as_bytes();
+        Self {
+            uniqueid: format!(
+                "00:17:88:01:{:02x}:{:02x}:{:02x}:{:02x}-0b",
+                b[0], b[1], b[2], b[3]
+            ),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_reachable(self, reachable: bool) -> Self {
+        Self {
+            state: ApiLightState {
+                reachable,
+                ..self.state
+            },
+            ..self
+        }
+    }
+
     fn v1_archetype(archetype: &api::DeviceArchetype) -> String {
         use api::DeviceArchetype;
         match archetype {
@@ -49,6 +49,12 @@ impl HueStreamHeader {
 
         Ok(hdr)
     }
+
+    /// Sequence number of the frame (wraps around after 255)
+    #[must_use]
+    pub const fn seqnr(&self) -> u8 {
+        self.seqnr
+    }
 }
 
 #[derive(Clone, Debug)]
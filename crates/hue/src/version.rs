@@ -41,11 +41,25 @@ impl SwVersion {
         Self { version, name }
     }
 
+    /// Construct a version from a version number alone, deriving the name
+    /// from the legacy api version (e.g. `1970084010` becomes "1.70.0")
+    #[must_use]
+    pub fn from_u64(version: u64) -> Self {
+        let mut res = Self::new(version, String::new());
+        res.name = res.get_legacy_apiversion();
+        res
+    }
+
     #[must_use]
     pub const fn as_u64(&self) -> u64 {
         self.version
     }
 
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     #[must_use]
     pub fn get_legacy_apiversion(&self) -> String {
         let version = format!("{:05}", self.version);
@@ -128,6 +142,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_u64() {
+        assert_eq!(
+            SwVersion::from_u64(HUE_BRIDGE_V2_DEFAULT_SWVERSION),
+            SwVersion::default()
+        );
+        assert_eq!(SwVersion::from_u64(12345).name(), "1.34.0");
+    }
+
     #[test]
     fn get_legacy_swversion() {
         let version = SwVersion::new(1234, String::new());
@@ -51,6 +51,12 @@ pub enum SvcError {
     #[error("Service has failed")]
     ServiceFailed,
 
+    #[error("Service {0} is not generated from a template")]
+    NotTemplated(ServiceName),
+
+    #[error("Service {0} cannot depend on {1}: dependency cycle")]
+    DependencyCycle(ServiceName, ServiceName),
+
     #[error("Templated service generation failed")]
     ServiceGeneration(Box<dyn Error + Send>),
 }
@@ -12,3 +12,5 @@ pub mod rpc;
 pub mod runservice;
 #[cfg(feature = "manager")]
 pub mod template;
+#[cfg(feature = "manager")]
+pub mod usage;
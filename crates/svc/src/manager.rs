@@ -4,7 +4,9 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt::Debug;
 use std::future::Future;
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use futures::future::BoxFuture;
 use tokio::select;
@@ -13,20 +15,34 @@ use tokio::task::{AbortHandle, JoinHandle, JoinSet};
 use uuid::Uuid;
 
 use crate::error::{RunSvcError, SvcError, SvcResult};
+use crate::policy::Policy;
 use crate::rpc::RpcRequest;
 use crate::runservice::StandardService;
 use crate::serviceid::{IntoServiceId, ServiceId, ServiceName};
 use crate::template::ServiceTemplate;
-use crate::traits::{Service, ServiceRunner, ServiceState};
+use crate::traits::{FunctionService, Service, ServiceRunner, ServiceState};
+use crate::usage::{ServiceUsage, Timed};
 
 #[derive(Debug)]
 pub struct ServiceInstance {
     tx: watch::Sender<ServiceState>,
     name: ServiceName,
     state: ServiceState,
+    restarts: u32,
+    busy: Arc<AtomicU64>,
+    registered: Instant,
     abort_handle: AbortHandle,
 }
 
+impl ServiceInstance {
+    fn usage(&self) -> ServiceUsage {
+        ServiceUsage {
+            busy: Duration::from_nanos(self.busy.load(Ordering::Relaxed)),
+            uptime: self.registered.elapsed(),
+        }
+    }
+}
+
 pub type ServiceFunc = Box<
     dyn FnOnce(
             Uuid,
@@ -40,12 +56,28 @@ pub type ServiceFunc = Box<
 pub struct ServiceEvent {
     id: Uuid,
     state: ServiceState,
+    restarts: u32,
 }
 
 impl ServiceEvent {
     #[must_use]
     pub const fn new(id: Uuid, state: ServiceState) -> Self {
-        Self { id, state }
+        Self {
+            id,
+            state,
+            restarts: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_restarts(self, restarts: u32) -> Self {
+        Self { restarts, ..self }
+    }
+
+    /// Number of times the service has been restarted by its run policy
+    #[must_use]
+    pub const fn restarts(&self) -> u32 {
+        self.restarts
     }
 
     #[must_use]
@@ -64,6 +96,11 @@ pub enum SvmRequest {
     Stop(RpcRequest<ServiceId, SvcResult<Uuid>>),
     Start(RpcRequest<ServiceId, SvcResult<Uuid>>),
     Status(RpcRequest<ServiceId, SvcResult<ServiceState>>),
+    Restarts(RpcRequest<ServiceId, SvcResult<u32>>),
+    Usage(RpcRequest<ServiceId, SvcResult<ServiceUsage>>),
+    Remove(RpcRequest<ServiceId, SvcResult<()>>),
+    Depend(RpcRequest<(ServiceName, ServiceName, Option<Duration>), SvcResult<()>>),
+    Dependencies(RpcRequest<ServiceId, SvcResult<Dependencies>>),
     List(RpcRequest<(), Vec<(Uuid, ServiceName)>>),
     Resolve(RpcRequest<ServiceId, SvcResult<Uuid>>),
     LookupName(RpcRequest<ServiceId, SvcResult<ServiceName>>),
@@ -118,6 +155,41 @@ impl SvmClient {
             .await
     }
 
+    /// Like [`Self::register_service`], with a specific policy for handling
+    /// errors while running
+    pub async fn register_service_with_policy<S>(
+        &mut self,
+        name: impl AsRef<str>,
+        svc: S,
+        policy: Policy,
+    ) -> SvcResult<Uuid>
+    where
+        S: Service + 'static,
+    {
+        let svc = StandardService::new(&name, svc).with_run_policy(policy);
+        self.register(&name, svc).await
+    }
+
+    /// Like [`Self::register_function`], with a specific policy for handling
+    /// errors while running.
+    ///
+    /// Since a restarted service needs a new future, `func` is called to
+    /// create one every time the service is (re)started.
+    pub async fn register_function_with_policy<F, Fut, E>(
+        &mut self,
+        name: impl AsRef<str>,
+        func: F,
+        policy: Policy,
+    ) -> SvcResult<Uuid>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Error + Send + 'static,
+    {
+        let svc = StandardService::new(&name, FunctionService::new(func)).with_run_policy(policy);
+        self.register(&name, svc).await
+    }
+
     pub async fn register<S>(&mut self, name: impl AsRef<str>, svc: S) -> SvcResult<Uuid>
     where
         S: ServiceRunner + Send + 'static,
@@ -209,6 +281,82 @@ impl SvmClient {
         self.rpc(SvmRequest::Status, id.service_id()).await?
     }
 
+    /// Estimated cpu usage of the service task
+    pub async fn usage(&mut self, id: impl IntoServiceId) -> SvcResult<ServiceUsage> {
+        self.rpc(SvmRequest::Usage, id.service_id()).await?
+    }
+
+    /// Remove a service from the service manager, aborting it if still running
+    pub async fn remove(&mut self, id: impl IntoServiceId) -> SvcResult<()> {
+        self.rpc(SvmRequest::Remove, id.service_id()).await?
+    }
+
+    /// Stop a templated service instance ("name@instance"), and generate it
+    /// from its template again, to pick up changed settings.
+    pub async fn regenerate(&mut self, id: impl IntoServiceId) -> SvcResult<Uuid> {
+        let uuid = self.resolve(&id).await?;
+        let name = self.lookup_name(uuid).await?;
+        if name.instance().is_none() {
+            return Err(SvcError::NotTemplated(name));
+        }
+
+        match self.status(uuid).await? {
+            ServiceState::Starting | ServiceState::Running | ServiceState::Stopping => {
+                self.stop(uuid).await?;
+                self.wait_for_stop(uuid).await?;
+            }
+            ServiceState::Registered
+            | ServiceState::Configured
+            | ServiceState::Stopped
+            | ServiceState::Failed => {}
+        }
+
+        self.remove(uuid).await?;
+        self.start(name).await
+    }
+
+    /// Declare that service `name` depends on service `dependency`
+    ///
+    /// Starting `name` also starts `dependency`, and `name` is held back
+    /// until `dependency` is running. Both services can be declared before
+    /// they are registered (or instantiated from a template).
+    pub async fn depend(
+        &mut self,
+        name: impl Into<ServiceName>,
+        dependency: impl Into<ServiceName>,
+    ) -> SvcResult<()> {
+        self.rpc(SvmRequest::Depend, (name.into(), dependency.into(), None))
+            .await?
+    }
+
+    /// Like [`Self::depend`], but `name` is held back for at most `max_wait`.
+    /// After that, it starts even if `dependency` is not running (e.g.
+    /// because it keeps failing to start).
+    pub async fn depend_for(
+        &mut self,
+        name: impl Into<ServiceName>,
+        dependency: impl Into<ServiceName>,
+        max_wait: Duration,
+    ) -> SvcResult<()> {
+        self.rpc(
+            SvmRequest::Depend,
+            (name.into(), dependency.into(), Some(max_wait)),
+        )
+        .await?
+    }
+
+    pub async fn dependencies(
+        &mut self,
+        id: impl IntoServiceId + Send + 'static,
+    ) -> SvcResult<Dependencies> {
+        self.rpc(SvmRequest::Dependencies, id.service_id()).await?
+    }
+
+    /// Number of times the service has been restarted by its run policy
+    pub async fn restarts(&mut self, id: impl IntoServiceId + Send + 'static) -> SvcResult<u32> {
+        self.rpc(SvmRequest::Restarts, id.service_id()).await?
+    }
+
     pub async fn list(&mut self) -> SvcResult<Vec<(Uuid, ServiceName)>> {
         self.rpc(SvmRequest::List, ()).await
     }
@@ -224,6 +372,11 @@ impl Debug for SvmRequest {
             Self::Stop(arg0) => f.debug_tuple("Stop").field(arg0).finish(),
             Self::Start(arg0) => f.debug_tuple("Start").field(arg0).finish(),
             Self::Status(arg0) => f.debug_tuple("Status").field(arg0).finish(),
+            Self::Restarts(arg0) => f.debug_tuple("Restarts").field(arg0).finish(),
+            Self::Usage(arg0) => f.debug_tuple("Usage").field(arg0).finish(),
+            Self::Remove(arg0) => f.debug_tuple("Remove").field(arg0).finish(),
+            Self::Depend(arg0) => f.debug_tuple("Depend").field(arg0).finish(),
+            Self::Dependencies(arg0) => f.debug_tuple("Dependencies").field(arg0).finish(),
             Self::List(arg0) => f.debug_tuple("List").field(arg0).finish(),
             Self::Register(_arg0) => f.debug_tuple("Register").field(&"<service>").finish(),
             Self::RegisterTemplate(_arg0) => f
@@ -238,6 +391,13 @@ impl Debug for SvmRequest {
     }
 }
 
+/// Dependencies of a service, and which of them are holding back its start
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    pub depends_on: BTreeSet<ServiceName>,
+    pub waiting_for: BTreeSet<ServiceName>,
+}
+
 pub struct ServiceManager {
     control_rx: mpsc::UnboundedReceiver<SvmRequest>,
     control_tx: mpsc::UnboundedSender<SvmRequest>,
@@ -248,6 +408,11 @@ pub struct ServiceManager {
     names: BTreeMap<ServiceName, Uuid>,
     tasks: JoinSet<Result<(), RunSvcError>>,
     templates: BTreeMap<String, Box<dyn ServiceTemplate>>,
+    depends: BTreeMap<ServiceName, BTreeSet<ServiceName>>,
+    /// Longest time a service is held back by a dependency, if limited
+    max_wait: BTreeMap<(ServiceName, ServiceName), Duration>,
+    /// Services waiting for their dependencies, and since when
+    pending: BTreeMap<Uuid, Instant>,
     shutdown: bool,
 }
 
@@ -272,6 +437,9 @@ impl ServiceManager {
             names: BTreeMap::new(),
             tasks: JoinSet::new(),
             templates: BTreeMap::new(),
+            depends: BTreeMap::new(),
+            max_wait: BTreeMap::new(),
+            pending: BTreeMap::new(),
             shutdown: false,
         }
     }
@@ -310,12 +478,17 @@ impl ServiceManager {
         let (tx, rx) = watch::channel(ServiceState::Registered);
         let id = Uuid::new_v4();
 
-        let abort_handle = self.tasks.spawn((svc)(id, rx, self.service_tx.clone()));
+        let busy = Arc::new(AtomicU64::new(0));
+        let fut = (svc)(id, rx, self.service_tx.clone());
+        let abort_handle = self.tasks.spawn(Timed::new(fut, busy.clone()));
 
         let rec = ServiceInstance {
             tx,
             name: name.clone(),
             state: ServiceState::Registered,
+            restarts: 0,
+            busy,
+            registered: Instant::now(),
             abort_handle,
         };
 
@@ -351,6 +524,7 @@ impl ServiceManager {
         let id = self.resolve(handle)?;
         self.svcs.remove(&id);
         self.names.retain(|_, v| *v != id);
+        self.pending.remove(&id);
 
         Ok(())
     }
@@ -371,14 +545,154 @@ impl ServiceManager {
     fn start(&mut self, id: impl IntoServiceId) -> SvcResult<Uuid> {
         let id = id.service_id();
 
-        // if the service is known, attempt to start it
-        if let Ok(svc) = self.get(&id) {
-            log::debug!("Starting service: {id} {}", &svc.name);
+        // if the service is unknown, it might be a named instance
+        let uuid = match self.resolve(&id) {
+            Ok(uuid) => uuid,
+            Err(_) => self.instantiate(id)?,
+        };
+
+        // start dependencies first (cycles are rejected in depend())
+        let name = self.svcs[&uuid].name.clone();
+        for dep in self.depends.get(&name).cloned().unwrap_or_default() {
+            self.start(dep)?;
+        }
+
+        let waiting = self.waiting_for(uuid);
+        if waiting.is_empty() {
+            self.pending.remove(&uuid);
+            log::debug!("Starting service: {uuid} {name}");
+            self.svcs[&uuid].tx.send(ServiceState::Running)?;
+        } else {
+            log::debug!("Service {name} waiting for dependencies: {waiting:?}");
+            self.pending.entry(uuid).or_insert_with(Instant::now);
+        }
+
+        Ok(uuid)
+    }
+
+    /// Dependencies of service `id` that are not running yet
+    fn not_running(&self, id: Uuid) -> BTreeSet<ServiceName> {
+        let Some(deps) = self.depends.get(&self.svcs[&id].name) else {
+            return BTreeSet::new();
+        };
+
+        deps.iter()
+            .filter(|dep| {
+                !self
+                    .get(dep)
+                    .is_ok_and(|svc| svc.state == ServiceState::Running)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// When service `id` stops waiting for `dep`, if the wait is limited
+    fn wait_deadline(&self, id: Uuid, dep: &ServiceName) -> Option<Instant> {
+        let since = self.pending.get(&id)?;
+        let key = (self.svcs[&id].name.clone(), dep.clone());
+        self.max_wait.get(&key).map(|max_wait| *since + *max_wait)
+    }
+
+    /// Dependencies holding back the start of service `id`
+    fn waiting_for(&self, id: Uuid) -> BTreeSet<ServiceName> {
+        let now = Instant::now();
+        self.not_running(id)
+            .into_iter()
+            .filter(|dep| self.wait_deadline(id, dep).is_none_or(|end| end > now))
+            .collect()
+    }
+
+    /// Next time a pending service stops waiting for a dependency
+    fn next_deadline(&self) -> Option<Instant> {
+        let now = Instant::now();
+        self.pending
+            .keys()
+            .flat_map(|id| {
+                self.not_running(*id)
+                    .into_iter()
+                    .filter_map(|dep| self.wait_deadline(*id, &dep))
+            })
+            .filter(|end| *end > now)
+            .min()
+    }
+
+    /// Start the pending services whose dependencies are all running (or
+    /// have been waited for long enough)
+    fn start_pending(&mut self) -> SvcResult<()> {
+        let ready: Vec<Uuid> = self
+            .pending
+            .keys()
+            .copied()
+            .filter(|id| self.waiting_for(*id).is_empty())
+            .collect();
+
+        for id in ready {
+            let missing = self.not_running(id);
+            self.pending.remove(&id);
+            let svc = &self.svcs[&id];
+            if missing.is_empty() {
+                log::debug!("Dependencies ready, starting service: {id} {}", svc.name);
+            } else {
+                log::warn!(
+                    "Service {} waited too long for {missing:?}, starting it anyway",
+                    svc.name
+                );
+            }
             svc.tx.send(ServiceState::Running)?;
-            return self.resolve(&id);
         }
 
-        // ..else, check if it's a named instance
+        Ok(())
+    }
+
+    /// Returns true if `name` depends on `dep`, directly or indirectly
+    fn depends_on(&self, name: &ServiceName, dep: &ServiceName) -> bool {
+        self.depends
+            .get(name)
+            .is_some_and(|deps| deps.iter().any(|d| d == dep || self.depends_on(d, dep)))
+    }
+
+    fn depend(
+        &mut self,
+        name: ServiceName,
+        dep: ServiceName,
+        max_wait: Option<Duration>,
+    ) -> SvcResult<()> {
+        if name == dep || self.depends_on(&dep, &name) {
+            return Err(SvcError::DependencyCycle(name, dep));
+        }
+
+        let key = (name.clone(), dep.clone());
+        match max_wait {
+            Some(max_wait) => self.max_wait.insert(key, max_wait),
+            None => self.max_wait.remove(&key),
+        };
+        self.depends.entry(name).or_default().insert(dep);
+
+        Ok(())
+    }
+
+    fn dependencies(&self, id: impl IntoServiceId) -> SvcResult<Dependencies> {
+        let uuid = self.resolve(id)?;
+        let depends_on = self
+            .depends
+            .get(&self.svcs[&uuid].name)
+            .cloned()
+            .unwrap_or_default();
+
+        let waiting_for = if self.pending.contains_key(&uuid) {
+            self.waiting_for(uuid)
+        } else {
+            BTreeSet::new()
+        };
+
+        Ok(Dependencies {
+            depends_on,
+            waiting_for,
+        })
+    }
+
+    /// Create a service from a template, for a named instance ("name@instance")
+    fn instantiate(&mut self, id: ServiceId) -> SvcResult<Uuid> {
         let ServiceId::Name(svc_name) = &id else {
             return Err(SvcError::ServiceNotFound(id));
         };
@@ -392,15 +706,19 @@ impl ServiceManager {
         };
 
         let inner = tmpl.generate(inst.to_string())?;
-        let svc = StandardService::new(svc_name.name(), inner);
+        let mut svc = StandardService::new(svc_name.name(), inner);
+        if let Some(policy) = tmpl.run_policy(inst) {
+            svc = svc.with_run_policy(policy);
+        }
 
         let uuid = self.register(svc_name.clone(), svc.boxed())?;
 
         Ok(uuid)
     }
 
-    fn stop(&self, id: impl IntoServiceId) -> SvcResult<Uuid> {
+    fn stop(&mut self, id: impl IntoServiceId) -> SvcResult<Uuid> {
         let id = self.resolve(id)?;
+        self.pending.remove(&id);
 
         if self.svcs[&id].state == ServiceState::Stopped {
             return Ok(id);
@@ -427,20 +745,42 @@ impl ServiceManager {
     }
 
     async fn next_event(&mut self) -> SvcResult<()> {
+        // wake up when a pending service has waited long enough
+        let deadline = self.next_deadline();
+        let wait = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into());
+
         tokio::select! {
             event = self.control_rx.recv() => self.handle_svm_request(event.ok_or(SvcError::Shutdown)?).await,
-            event = self.service_rx.recv() => {
-                self.handle_service_event(event.ok_or(SvcError::Shutdown)?);
-                Ok(())
-            },
+            event = self.service_rx.recv() => self.handle_service_event(event.ok_or(SvcError::Shutdown)?),
+            () = wait, if deadline.is_some() => self.start_pending(),
         }
     }
 
-    fn handle_service_event(&mut self, event: ServiceEvent) {
+    fn handle_service_event(&mut self, event: ServiceEvent) -> SvcResult<()> {
         self.notify_subscribers(event);
         let name = &self.svcs[&event.id].name;
         log::trace!("[{name}] [{}] Service is now {:?}", event.id, event.state);
-        self.svcs.get_mut(&event.id).unwrap().state = event.state;
+        let svc = self.svcs.get_mut(&event.id).unwrap();
+        svc.state = event.state;
+        svc.restarts = event.restarts;
+
+        match event.state {
+            ServiceState::Running => self.start_pending()?,
+            ServiceState::Failed => {
+                let name = &self.svcs[&event.id].name;
+                for id in self.pending.keys() {
+                    if self.waiting_for(*id).contains(name) {
+                        log::warn!(
+                            "Service {} is waiting for failed service {name}",
+                            self.svcs[id].name
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
     }
 
     async fn handle_svm_request(&mut self, upd: SvmRequest) -> SvcResult<()> {
@@ -451,6 +791,18 @@ impl ServiceManager {
 
             SvmRequest::Status(rpc) => rpc.respond(|id| Ok(self.get(&id)?.state)),
 
+            SvmRequest::Restarts(rpc) => rpc.respond(|id| Ok(self.get(&id)?.restarts)),
+
+            SvmRequest::Usage(rpc) => rpc.respond(|id| Ok(self.get(&id)?.usage())),
+
+            SvmRequest::Remove(rpc) => rpc.respond(|id| self.abort(&id)),
+
+            SvmRequest::Depend(rpc) => {
+                rpc.respond(|(name, dep, max_wait)| self.depend(name, dep, max_wait));
+            }
+
+            SvmRequest::Dependencies(rpc) => rpc.respond(|id| self.dependencies(&id)),
+
             SvmRequest::List(rpc) => rpc.respond(|()| {
                 let mut res = vec![];
 
@@ -475,7 +827,8 @@ impl ServiceManager {
 
             SvmRequest::Subscribe(rpc) => {
                 for (id, svc) in &self.svcs {
-                    rpc.data().send(ServiceEvent::new(*id, svc.state))?;
+                    rpc.data()
+                        .send(ServiceEvent::new(*id, svc.state).with_restarts(svc.restarts))?;
                 }
 
                 rpc.respond(|tx| {
@@ -513,7 +866,7 @@ impl ServiceManager {
         Ok(())
     }
 
-    fn stop_multiple(&self, handles: &[impl IntoServiceId]) -> SvcResult<()> {
+    fn stop_multiple(&mut self, handles: &[impl IntoServiceId]) -> SvcResult<()> {
         let ids = self.resolve_multiple(handles)?;
         for id in ids {
             self.stop(id)?;
@@ -572,3 +925,179 @@ impl ServiceManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::manager::ServiceManager;
+    use crate::policy::{Policy, Retry};
+    use crate::traits::{Service, ServiceState};
+
+    /// Service that never manages to start, like a backend that cannot
+    /// connect
+    struct Unreachable;
+
+    #[async_trait]
+    impl Service for Unreachable {
+        type Error = io::Error;
+
+        async fn start(&mut self) -> Result<(), io::Error> {
+            Err(io::Error::other("unreachable"))
+        }
+
+        async fn run(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn restart_failed_function() {
+        let (mut client, future) = ServiceManager::spawn();
+
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let func = move || {
+            let counter = counter.clone();
+            async move {
+                // fail twice, then complete successfully
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(io::Error::other("failed"))
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        let policy = Policy::new().with_retry(Retry::Limit(2));
+
+        client
+            .register_function_with_policy("flaky", func, policy)
+            .await
+            .unwrap();
+        client.start("flaky").await.unwrap();
+        client.wait_for_stop("flaky").await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(client.restarts("flaky").await.unwrap(), 2);
+
+        client.shutdown().await.unwrap();
+        future.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn reset_retries_after_healthy_run() {
+        let (mut client, future) = ServiceManager::spawn();
+
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let func = move || {
+            let counter = counter.clone();
+            async move {
+                // fail, run for a while and fail, fail again, then complete
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 | 2 => Err(io::Error::other("failed")),
+                    1 => {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Err(io::Error::other("failed"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+        };
+        // three failures in total, but never more than two in a row
+        let policy = Policy::new()
+            .with_retry(Retry::Limit(2))
+            .with_reset_after(Duration::from_millis(50));
+
+        client
+            .register_function_with_policy("flaky", func, policy)
+            .await
+            .unwrap();
+        client.start("flaky").await.unwrap();
+        client.wait_for_stop("flaky").await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        assert_eq!(client.restarts("flaky").await.unwrap(), 3);
+
+        client.shutdown().await.unwrap();
+        future.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn stop_during_backoff() {
+        let (mut client, future) = ServiceManager::spawn();
+
+        let func = || async { Err::<(), _>(io::Error::other("failed")) };
+        let policy = Policy::new()
+            .with_retry(Retry::Forever)
+            .with_delay(Duration::from_secs(60));
+
+        client
+            .register_function_with_policy("failing", func, policy)
+            .await
+            .unwrap();
+        client.start("failing").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // stopping does not wait for the delay before the next restart
+        tokio::time::timeout(Duration::from_secs(5), async {
+            client.stop("failing").await.unwrap();
+            client.wait_for_stop("failing").await.unwrap();
+        })
+        .await
+        .unwrap();
+
+        client.shutdown().await.unwrap();
+        future.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn start_in_dependency_order() {
+        let (mut client, future) = ServiceManager::spawn();
+
+        let forever = || std::future::pending::<Result<(), io::Error>>();
+        for name in ["db", "http", "mdns"] {
+            client
+                .register_function_with_policy(name, forever, Policy::new())
+                .await
+                .unwrap();
+        }
+        client
+            .register_service("backend", Unreachable)
+            .await
+            .unwrap();
+
+        client.depend("mdns", "http").await.unwrap();
+        client.depend("http", "db").await.unwrap();
+        client
+            .depend_for("http", "backend", Duration::from_millis(200))
+            .await
+            .unwrap();
+
+        client.start("mdns").await.unwrap();
+
+        // nothing is started before its dependencies
+        let mdns = client.dependencies("mdns").await.unwrap();
+        assert_eq!(mdns.waiting_for.len(), 1);
+        assert!(mdns.waiting_for.iter().all(|dep| dep.name() == "http"));
+        assert_ne!(client.status("http").await.unwrap(), ServiceState::Running);
+
+        // http stops waiting for the backend that never starts, and then
+        // mdns starts after it
+        client.wait_for_start("mdns").await.unwrap();
+        assert_eq!(client.status("http").await.unwrap(), ServiceState::Running);
+        assert_eq!(client.status("db").await.unwrap(), ServiceState::Running);
+        assert_ne!(
+            client.status("backend").await.unwrap(),
+            ServiceState::Running
+        );
+
+        client.shutdown().await.unwrap();
+        future.await.unwrap().unwrap();
+    }
+}
@@ -15,6 +15,15 @@ pub enum Retry {
 pub struct Policy {
     pub retry: Retry,
     pub delay: Option<Duration>,
+    /// Factor to multiply the delay by, for each consecutive attempt
+    pub backoff: u32,
+    /// Upper limit for the delay, when backing off
+    pub max_delay: Option<Duration>,
+    /// Restart the service when it completes successfully, too
+    pub restart_on_success: bool,
+    /// A run lasting at least this long resets the count of consecutive
+    /// attempts (and with it, the backoff)
+    pub reset_after: Option<Duration>,
 }
 
 impl Default for Policy {
@@ -29,6 +38,10 @@ impl Policy {
         Self {
             retry: Retry::No,
             delay: None,
+            backoff: 1,
+            max_delay: None,
+            restart_on_success: false,
+            reset_after: None,
         }
     }
 
@@ -53,6 +66,50 @@ impl Policy {
         }
     }
 
+    /// Multiply the delay by `factor` for each consecutive attempt, up to
+    /// `max_delay`
+    #[must_use]
+    pub const fn with_backoff(self, factor: u32, max_delay: Duration) -> Self {
+        Self {
+            backoff: factor,
+            max_delay: Some(max_delay),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub const fn with_restart_on_success(self, restart_on_success: bool) -> Self {
+        Self {
+            restart_on_success,
+            ..self
+        }
+    }
+
+    /// Count attempts from 0 again, after a run of at least `reset_after`
+    #[must_use]
+    pub const fn with_reset_after(self, reset_after: Duration) -> Self {
+        Self {
+            reset_after: Some(reset_after),
+            ..self
+        }
+    }
+
+    /// Does a run of length `elapsed` reset the count of consecutive attempts?
+    #[must_use]
+    pub fn should_reset(&self, elapsed: Duration) -> bool {
+        self.reset_after
+            .is_some_and(|reset_after| elapsed >= reset_after)
+    }
+
+    /// Delay before attempt number `attempt` (counting from 0)
+    #[must_use]
+    pub fn delay_for(&self, attempt: u32) -> Option<Duration> {
+        let delay = self.delay?;
+        let factor = self.backoff.saturating_pow(attempt);
+        let delay = delay.saturating_mul(factor);
+        Some(self.max_delay.map_or(delay, |max| delay.min(max)))
+    }
+
     #[cfg(feature = "manager")]
     pub async fn sleep(&self) {
         if let Some(dur) = self.delay {
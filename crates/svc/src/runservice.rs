@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, watch};
 use tokio::time::sleep;
 use uuid::Uuid;
@@ -13,6 +13,9 @@ use crate::traits::{Service, ServiceRunner, ServiceState, StopResult};
 struct State {
     id: Uuid,
     retry: u32,
+    restarts: u32,
+    /// When the current state was entered, or the service last restarted
+    since: Instant,
     state: ServiceState,
     tx: mpsc::UnboundedSender<ServiceEvent>,
 }
@@ -26,6 +29,8 @@ impl State {
         Self {
             id,
             retry: 0,
+            restarts: 0,
+            since: Instant::now(),
             state,
             tx,
         }
@@ -34,13 +39,35 @@ impl State {
     pub fn set(&mut self, next: ServiceState) -> Result<(), RunSvcError> {
         self.state = next;
         self.retry = 0;
-        Ok(self.tx.send(ServiceEvent::new(self.id, self.state))?)
+        self.since = Instant::now();
+        self.notify()
+    }
+
+    /// Record that the service is being restarted (without changing state)
+    pub fn restarted(&mut self) -> Result<(), RunSvcError> {
+        self.restarts += 1;
+        self.since = Instant::now();
+        self.notify()
+    }
+
+    fn notify(&self) -> Result<(), RunSvcError> {
+        let event = ServiceEvent::new(self.id, self.state).with_restarts(self.restarts);
+        Ok(self.tx.send(event)?)
     }
 
     pub const fn get(&self) -> ServiceState {
         self.state
     }
 
+    /// Time since the current state was entered, or the service last restarted
+    pub fn elapsed(&self) -> Duration {
+        self.since.elapsed()
+    }
+
+    pub const fn reset_retry(&mut self) {
+        self.retry = 0;
+    }
+
     pub const fn retry(&mut self) -> u32 {
         let res = self.retry;
         self.retry += 1;
@@ -48,6 +75,28 @@ impl State {
     }
 }
 
+/// Wait for the delay before restart `attempt`, unless the service is asked
+/// to stop in the meantime.
+///
+/// Returns false if the service should stop instead of restarting.
+async fn wait_restart(
+    policy: &Policy,
+    attempt: u32,
+    rx: &mut watch::Receiver<ServiceState>,
+) -> Result<bool, RunSvcError> {
+    let Some(delay) = policy.delay_for(attempt) else {
+        return Ok(true);
+    };
+
+    tokio::select! {
+        () = sleep(delay) => Ok(true),
+        res = rx.wait_for(|state| *state == ServiceState::Stopped) => {
+            res?;
+            Ok(false)
+        }
+    }
+}
+
 pub struct StandardService<S: Service> {
     name: String,
     svc: S,
@@ -173,14 +222,32 @@ impl<S: Service> ServiceRunner for StandardService<S> {
                 ServiceState::Running => {
                     tokio::select! {
                         res = svc.run() => match res {
+                            Ok(()) if self.run_policy.restart_on_success => {
+                                log::debug!(target:target, "Service completed successfully, restarting..");
+                                if wait_restart(&self.run_policy, 0, &mut rx).await? {
+                                    state.restarted()?;
+                                } else {
+                                    state.set(ServiceState::Stopping)?;
+                                }
+                            }
                             Ok(()) => {
                                 log::debug!(target:target, "Service completed successfully");
                                 state.set(ServiceState::Stopping)?;
                             }
                             Err(err) => {
-                                self.run_policy.sleep().await;
-                                if self.run_policy.should_retry(state.retry()) {
-                                    log::warn!(target:target, "Service failed to start, retrying..");
+                                // a service that ran fine for a while did not
+                                // fail consecutively
+                                if self.run_policy.should_reset(state.elapsed()) {
+                                    state.reset_retry();
+                                }
+                                let attempt = state.retry();
+                                if self.run_policy.should_retry(attempt) {
+                                    log::warn!(target:target, "Service failed, restarting: {err}");
+                                    if wait_restart(&self.run_policy, attempt, &mut rx).await? {
+                                        state.restarted()?;
+                                    } else {
+                                        state.set(ServiceState::Stopping)?;
+                                    }
                                 } else {
                                     log::error!(target:target, "Failed to run service: {err}");
                                     match svc.stop().await {
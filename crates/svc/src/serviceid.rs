@@ -90,6 +90,12 @@ impl<I: IntoServiceId> IntoServiceId for &I {
     }
 }
 
+impl IntoServiceId for ServiceName {
+    fn service_id(self) -> ServiceId {
+        ServiceId::Name(self)
+    }
+}
+
 impl IntoServiceId for Uuid {
     fn service_id(self) -> ServiceId {
         ServiceId::Id(self)
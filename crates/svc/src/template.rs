@@ -3,11 +3,17 @@ use async_trait::async_trait;
 #[cfg(feature = "manager")]
 use crate::error::RunSvcError;
 use crate::error::SvcError;
+use crate::policy::Policy;
 use crate::traits::{BoxDynService, Service, StopResult};
 
 #[cfg(feature = "manager")]
 pub trait ServiceTemplate: Send {
     fn generate(&self, instance: String) -> Result<BoxDynService, SvcError>;
+
+    /// Policy for handling errors while running `instance`, if not the default
+    fn run_policy(&self, _instance: &str) -> Option<Policy> {
+        None
+    }
 }
 
 pub struct ErrorAdapter<S: Service> {
@@ -13,6 +13,8 @@ use crate::template::ErrorAdapter;
 #[cfg(feature = "manager")]
 use std::future::Future;
 #[cfg(feature = "manager")]
+use std::pin::Pin;
+#[cfg(feature = "manager")]
 use tokio::sync::{mpsc, watch};
 #[cfg(feature = "manager")]
 use uuid::Uuid;
@@ -160,3 +162,44 @@ where
         self.await
     }
 }
+
+/// Service running the future returned by a function.
+///
+/// A plain future can only be awaited until it completes once, so it cannot
+/// be restarted. This service calls the function again for every new run.
+#[cfg(feature = "manager")]
+pub struct FunctionService<F, Fut> {
+    func: F,
+    running: Option<Pin<Box<Fut>>>,
+}
+
+#[cfg(feature = "manager")]
+impl<F, Fut> FunctionService<F, Fut> {
+    #[must_use]
+    pub const fn new(func: F) -> Self {
+        Self {
+            func,
+            running: None,
+        }
+    }
+}
+
+#[cfg(feature = "manager")]
+#[async_trait]
+impl<E, F, Fut> Service for FunctionService<F, Fut>
+where
+    E: Error + Send + 'static,
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = Result<(), E>> + Send,
+{
+    type Error = E;
+
+    async fn run(&mut self) -> Result<(), E> {
+        // an interrupted run (e.g. by a state change request) is resumed
+        let func = &mut self.func;
+        let fut = self.running.get_or_insert_with(|| Box::pin(func()));
+        let res = fut.await;
+        self.running = None;
+        res
+    }
+}
@@ -0,0 +1,51 @@
+//! Cpu usage estimates for services, based on time spent polling them.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Future wrapper, that adds the time spent polling `inner` to a counter
+pub struct Timed<F> {
+    inner: F,
+    busy: Arc<AtomicU64>,
+}
+
+impl<F> Timed<F> {
+    #[must_use]
+    pub const fn new(inner: F, busy: Arc<AtomicU64>) -> Self {
+        Self { inner, busy }
+    }
+}
+
+impl<F: Future + Unpin> Future for Timed<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let start = Instant::now();
+        let res = Pin::new(&mut self.inner).poll(cx);
+        let nanos = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.busy.fetch_add(nanos, Ordering::Relaxed);
+        res
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ServiceUsage {
+    /// Total time spent running the service task
+    pub busy: Duration,
+    /// Time since the service was registered
+    pub uptime: Duration,
+}
+
+impl ServiceUsage {
+    /// Estimated cpu usage (of a single core) since the service was registered
+    #[must_use]
+    pub const fn cpu_percent(&self) -> f64 {
+        if self.uptime.is_zero() {
+            return 0.0;
+        }
+        self.busy.as_secs_f64() / self.uptime.as_secs_f64() * 100.0
+    }
+}
@@ -80,6 +80,9 @@ pub enum Message {
     #[serde(rename = "bridge/response/device/remove")]
     BridgeDeviceRemove(Response<DeviceRemoveResponse>),
 
+    #[serde(rename = "bridge/response/device/rename")]
+    BridgeDeviceRename(Response<DeviceRename>),
+
     #[serde(rename = "bridge/response/device/options")]
     BridgeDeviceOptions(Value),
 
@@ -121,6 +124,14 @@ pub struct DeviceRemove {
     pub id: String,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceRename {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub homeassistant_rename: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DeviceRemoveResponse {
     pub id: String,
@@ -1,7 +1,7 @@
 use serde::Serialize;
 use serde_json::Value;
 
-use crate::api::{DeviceRemove, GroupMemberChange, PermitJoin};
+use crate::api::{DeviceRemove, DeviceRename, GroupMemberChange, PermitJoin};
 use crate::update::DeviceUpdate;
 
 #[derive(Clone, Debug, Serialize)]
@@ -45,6 +45,9 @@ pub enum Z2mRequest<'a> {
     #[serde(untagged)]
     DeviceRemove(DeviceRemove),
 
+    #[serde(untagged)]
+    DeviceRename(DeviceRename),
+
     #[serde(untagged)]
     Update(&'a DeviceUpdate),
 
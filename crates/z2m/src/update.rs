@@ -37,6 +37,18 @@ pub struct DeviceUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub power_on_behavior: Option<PowerOnBehavior>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub hue_power_on_behavior: Option<HuePowerOnBehavior>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub hue_power_on_brightness: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub hue_power_on_color_temperature: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub hue_power_on_color: Option<HexColor>,
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     #[serde(default)]
     pub update: HashMap<String, Value>,
@@ -186,6 +198,19 @@ pub enum PowerOnBehavior {
     Previous,
 }
 
+/// Power-on behavior of Philips Hue lights (set using the manufacturer
+/// specific cluster, which also supports brightness and color)
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HuePowerOnBehavior {
+    On,
+    Off,
+    Recover,
+
+    #[serde(other)]
+    Unknown,
+}
+
 #[derive(Copy, Debug, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct ColorOptions {
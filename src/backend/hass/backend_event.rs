@@ -1,20 +1,37 @@
+use std::collections::BTreeSet;
 use std::sync::Arc;
+use std::time::Instant;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_json::{Map, Value, json};
 
-use bifrost_api::backend::BackendRequest;
+use bifrost_api::backend::{BackendRequest, BifrostActivity, HassRequest};
 use hue::api::{
-    GroupedLight, GroupedLightUpdate, LightUpdate, Motion, Resource, ResourceLink, Room, Scene,
-    SceneActive, SceneStatus, SceneStatusEnum, SceneUpdate,
+    Button, ButtonReport, DeviceUpdate, GroupedLight, GroupedLightUpdate, Light, LightLevel,
+    LightPowerup, LightPowerupOn, LightPowerupUpdate, LightUpdate, Motion, RType, Resource,
+    ResourceLink, Room, RoomUpdate, Scene, SceneActionElement, SceneActive, SceneRecall,
+    SceneStatus, SceneStatusEnum, SceneUpdate, Temperature,
 };
 
-use crate::backend::hass::{HassBackend, HassEntityBinding, HassEntityKind, HassServiceKind};
+use crate::backend::hass::import;
+use crate::backend::hass::precedence::PendingLightUpdate;
+use crate::backend::hass::retry::RetryQueue;
+use crate::backend::hass::{HassBackend, HassEntityBinding, HassRoomBinding};
 use crate::error::ApiResult;
-use crate::model::hass::HassSwitchMode;
+use crate::model::hass::{
+    HassConflictPolicy, HassEntityKind, HassServiceKind, HassSwitchMode, HassUiConfig,
+};
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
+
+/// Convert hue brightness (percent) to Home Assistant brightness (0..255)
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn ha_brightness(percent: f64) -> u16 {
+    // a NaN is clamped to NaN, and cast to 0
+    (percent * 255.0 / 100.0).round().clamp(0.0, 255.0) as u16
+}
 
 impl HassBackend {
-    fn lookup_binding_by_light(&self, link: &ResourceLink) -> Option<HassEntityBinding> {
+    pub(super) fn lookup_binding_by_light(&self, link: &ResourceLink) -> Option<HassEntityBinding> {
         let entity_id = self.light_map.get(&link.rid)?;
         self.entity_map.get(entity_id).cloned()
     }
@@ -24,24 +41,138 @@ impl HassBackend {
         self.entity_map.get(entity_id).cloned()
     }
 
-    fn lookup_binding_by_device(&self, link: &ResourceLink) -> Option<HassEntityBinding> {
+    pub(super) fn lookup_binding_by_device(
+        &self,
+        link: &ResourceLink,
+    ) -> Option<HassEntityBinding> {
         let entity_id = self.device_map.get(&link.rid)?;
         self.entity_map.get(entity_id).cloned()
     }
 
-    async fn backend_light_update(
-        &self,
+    /// Send light update to Home Assistant. Unless the entity uses the
+    /// "backend wins" conflict policy, it is also applied to the Hue light
+    /// right away, and tracked until Home Assistant confirms it.
+    pub(super) async fn backend_light_update(
+        &mut self,
         binding: &HassEntityBinding,
         upd: &LightUpdate,
     ) -> ApiResult<()> {
+        if let Some(powerup) = &upd.powerup {
+            self.backend_light_powerup(binding, powerup).await?;
+        }
+
+        let written_at = self.send_light_update(binding, upd).await?;
+
+        if !matches!(
+            binding.service_kind,
+            HassServiceKind::Light | HassServiceKind::Switch
+        ) {
+            return Ok(());
+        }
+
+        let policy = self
+            .ui_state
+            .lock()
+            .await
+            .config
+            .conflict_policy(&binding.entity_id);
+        if policy == HassConflictPolicy::BackendWins {
+            return Ok(());
+        }
+
+        let mut res = self.state.lock().await;
+        res.update::<Light>(&binding.service_link.rid, |light| *light += upd)?;
+        res.refresh_light_groups(&binding.service_link.rid)?;
+        drop(res);
+        self.pending_lights.insert(
+            binding.service_link.rid,
+            PendingLightUpdate::new(upd, written_at),
+        );
+        // the hue light no longer reflects the last imported state
+        self.fingerprints.remove(&binding.entity_id);
+
+        Ok(())
+    }
+
+    /// Store the power-on behavior of a light, and apply it in Home
+    /// Assistant, if the light has a "power on behavior" select entity (as
+    /// created by the zigbee integrations).
+    async fn backend_light_powerup(
+        &self,
+        binding: &HassEntityBinding,
+        upd: &LightPowerupUpdate,
+    ) -> ApiResult<()> {
+        let powerup = LightPowerup::from_update(upd);
+
+        self.state
+            .lock()
+            .await
+            .update::<Light>(&binding.service_link.rid, |light| {
+                light.powerup = Some(powerup.clone());
+            })?;
+
+        let wanted: &[&str] = match powerup.on {
+            LightPowerupOn::None => return Ok(()),
+            LightPowerupOn::Previous => &["previous", "previousvalue", "recover"],
+            LightPowerupOn::On { on } if on.on => &["on"],
+            LightPowerupOn::On { .. } => &["off"],
+        };
+
+        let object_id = binding
+            .entity_id
+            .split_once('.')
+            .map_or(binding.entity_id.as_str(), |(_, id)| id);
+        let select = format!("select.{object_id}_power_on_behavior");
+
+        let Ok(state) = self.client.get_state(&select).await else {
+            log::debug!(
+                "[{}] No power-on behavior entity for {}, only storing it",
+                self.name,
+                binding.entity_id
+            );
+            return Ok(());
+        };
+
+        let option = state
+            .attributes
+            .get("options")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .find(|opt| wanted.contains(&opt.to_ascii_lowercase().as_str()));
+
+        let Some(option) = option else {
+            log::warn!(
+                "[{}] {select} does not support power-on behavior {:?}",
+                self.name,
+                powerup.on
+            );
+            return Ok(());
+        };
+
+        let mut data = Map::new();
+        data.insert("option".to_string(), json!(option));
+        self.client
+            .call_service("select", "select_option", &select, data)
+            .await
+    }
+
+    pub(super) async fn send_light_update(
+        &self,
+        binding: &HassEntityBinding,
+        upd: &LightUpdate,
+    ) -> ApiResult<Option<DateTime<Utc>>> {
+        let mut written_at = None;
         match binding.kind {
             HassEntityKind::Light => {
                 if let Some(on) = upd.on {
                     if !on.on {
-                        self.client
-                            .call_service("light", "turn_off", &binding.entity_id, Map::new())
+                        written_at = self
+                            .client
+                            .call_service_at("light", "turn_off", &binding.entity_id, Map::new())
                             .await?;
-                        return Ok(());
+                        return Ok(written_at);
                     }
                 }
 
@@ -49,12 +180,10 @@ impl HassBackend {
 
                 if binding.capabilities.supports_brightness {
                     if let Some(dim) = upd.dimming {
-                        let bri_value = (dim.brightness * 255.0 / 100.0).round().clamp(0.0, 255.0);
-                        let bri = format!("{bri_value:.0}")
-                            .parse::<u16>()
-                            .ok()
-                            .map_or(0, |x| x.min(255));
-                        data.insert("brightness".to_string(), json!(bri));
+                        data.insert(
+                            "brightness".to_string(),
+                            json!(ha_brightness(dim.brightness)),
+                        );
                     }
                 }
 
@@ -78,25 +207,245 @@ impl HassBackend {
                 }
 
                 if upd.on.is_some_and(|on| on.on) || !data.is_empty() {
-                    self.client
-                        .call_service("light", "turn_on", &binding.entity_id, data)
+                    written_at = self
+                        .client
+                        .call_service_at("light", "turn_on", &binding.entity_id, data)
                         .await?;
                 }
             }
             HassEntityKind::Switch => {
                 if let Some(on) = upd.on {
                     let service = if on.on { "turn_on" } else { "turn_off" };
-                    self.client
-                        .call_service("switch", service, &binding.entity_id, Map::new())
+                    written_at = self
+                        .client
+                        .call_service_at("switch", service, &binding.entity_id, Map::new())
+                        .await?;
+                }
+            }
+            HassEntityKind::Fan => {
+                if upd.on.is_some_and(|on| !on.on) {
+                    written_at = self
+                        .client
+                        .call_service_at("fan", "turn_off", &binding.entity_id, Map::new())
+                        .await?;
+                    return Ok(written_at);
+                }
+
+                let mut data = Map::new();
+                let dimmable = binding.switch_mode == Some(HassSwitchMode::Light)
+                    && binding.capabilities.supports_brightness;
+                if let Some(dim) = upd.dimming.filter(|_| dimmable) {
+                    data.insert(
+                        "percentage".to_string(),
+                        json!(dim.brightness.round().clamp(0.0, 100.0)),
+                    );
+                }
+
+                if upd.on.is_some_and(|on| on.on) || !data.is_empty() {
+                    written_at = self
+                        .client
+                        .call_service_at("fan", "turn_on", &binding.entity_id, data)
                         .await?;
                 }
             }
-            HassEntityKind::BinarySensor => {}
+            HassEntityKind::MediaPlayer => {
+                if let Some(on) = upd.on {
+                    let service = if on.on { "turn_on" } else { "turn_off" };
+                    written_at = self
+                        .client
+                        .call_service_at("media_player", service, &binding.entity_id, Map::new())
+                        .await?;
+                }
+            }
+            HassEntityKind::Cover => {
+                let position = upd
+                    .dimming
+                    .filter(|_| binding.capabilities.supports_brightness)
+                    .map(|dim| dim.brightness.round().clamp(0.0, 100.0));
+
+                match (upd.on.map(|on| on.on), position) {
+                    (Some(false), _) => {
+                        written_at = self
+                            .client
+                            .call_service_at("cover", "close_cover", &binding.entity_id, Map::new())
+                            .await?;
+                    }
+                    (_, Some(position)) => {
+                        let mut data = Map::new();
+                        data.insert("position".to_string(), json!(position));
+                        written_at = self
+                            .client
+                            .call_service_at(
+                                "cover",
+                                "set_cover_position",
+                                &binding.entity_id,
+                                data,
+                            )
+                            .await?;
+                    }
+                    (Some(true), None) => {
+                        written_at = self
+                            .client
+                            .call_service_at("cover", "open_cover", &binding.entity_id, Map::new())
+                            .await?;
+                    }
+                    (None, None) => {}
+                }
+            }
+            HassEntityKind::BinarySensor
+            | HassEntityKind::Button
+            | HassEntityKind::Temperature
+            | HassEntityKind::LightLevel
+            | HassEntityKind::Event
+            | HassEntityKind::Lock
+            | HassEntityKind::Sensor
+            | HassEntityKind::Script => {}
+        }
+
+        Ok(written_at)
+    }
+
+    /// Run the script (or press the button) behind a hue button, and report
+    /// the press on the button resource.
+    async fn backend_button_press(&self, binding: &HassEntityBinding) -> ApiResult<()> {
+        let (domain, service) = if binding.kind == HassEntityKind::Script {
+            ("script", "turn_on")
+        } else {
+            ("button", "press")
+        };
+        self.client
+            .call_service(domain, service, &binding.entity_id, Map::new())
+            .await?;
+
+        self.state
+            .lock()
+            .await
+            .update::<Button>(&binding.service_link.rid, |button| {
+                button.button.button_report = Some(ButtonReport {
+                    updated: Utc::now(),
+                    event: "short_release".to_string(),
+                });
+            })?;
+
+        Ok(())
+    }
+
+    /// Apply room changes from a hue app to the room config, so the next
+    /// room refresh does not revert them.
+    async fn backend_room_update(
+        &mut self,
+        link: &ResourceLink,
+        upd: &RoomUpdate,
+    ) -> ApiResult<()> {
+        let Some(binding) = self
+            .room_map
+            .values()
+            .find(|binding| binding.room_link == *link)
+            .cloned()
+        else {
+            return Ok(());
+        };
+
+        if let Some(name) = upd.metadata.as_ref().and_then(|md| md.name.as_deref()) {
+            self.backend_room_rename(&binding, name).await;
+        }
+
+        if let Some(children) = &upd.children {
+            self.backend_room_children(&binding, children).await?;
         }
 
         Ok(())
     }
 
+    async fn backend_room_rename(&mut self, binding: &HassRoomBinding, name: &str) {
+        if binding.room_name == name {
+            return;
+        }
+
+        if let Some(known) = self.room_map.get_mut(&binding.room_id) {
+            known.room_name = name.to_string();
+        }
+
+        let mut lock = self.ui_state.lock().await;
+        lock.rename_room(&binding.room_id, name);
+        let _ = lock.persist_and_log(
+            None,
+            &format!(
+                "Renamed room {} to {} from hue app",
+                binding.room_id,
+                name.trim()
+            ),
+        );
+    }
+
+    /// Move lights between rooms, by updating the room preference of their
+    /// entities. Lights removed from a room go back to the default room.
+    async fn backend_room_children(
+        &mut self,
+        binding: &HassRoomBinding,
+        children: &BTreeSet<ResourceLink>,
+    ) -> ApiResult<()> {
+        let current = self
+            .state
+            .lock()
+            .await
+            .get::<Room>(&binding.room_link)?
+            .children
+            .clone();
+
+        let mut lock = self.ui_state.lock().await;
+
+        for added in children.difference(&current) {
+            if let Some(entity_id) = self.device_map.get(&added.rid) {
+                lock.set_entity_room(entity_id, Some(binding.room_id.clone()));
+            }
+        }
+
+        for removed in current.difference(children) {
+            if let Some(entity_id) = self.device_map.get(&removed.rid) {
+                lock.set_entity_room(entity_id, Some(HassUiConfig::DEFAULT_ROOM_ID.to_string()));
+            }
+        }
+
+        let _ = lock.persist_and_log(
+            None,
+            &format!("Updated lights in room {} from hue app", binding.room_id),
+        );
+        drop(lock);
+
+        self.refresh_rooms_from_ui_config().await
+    }
+
+    /// Persist device metadata edited in a hue app as entity preferences, so
+    /// the next sync does not overwrite it.
+    async fn backend_device_update(&self, binding: &HassEntityBinding, upd: &DeviceUpdate) {
+        let Some(md) = &upd.metadata else {
+            return;
+        };
+
+        let mut lock = self.ui_state.lock().await;
+
+        if let Some(name) = &md.name {
+            lock.set_entity_alias(&binding.entity_id, Some(name.clone()));
+        }
+
+        if let Some(archetype) = &md.archetype {
+            if let Some(archetype) = import::hass_light_archetype(archetype) {
+                lock.set_entity_light_archetype(&binding.entity_id, Some(archetype));
+            } else {
+                log::debug!(
+                    "[{}] Archetype {archetype:?} has no Home Assistant equivalent, not saved",
+                    self.name
+                );
+            }
+        }
+
+        let _ = lock.persist_and_log(
+            None,
+            &format!("Device {} updated from hue app", binding.entity_id),
+        );
+    }
+
     async fn backend_sensor_enabled_update(
         &self,
         binding: &HassEntityBinding,
@@ -105,11 +454,14 @@ impl HassBackend {
         {
             let mut lock = self.ui_state.lock().await;
             lock.set_entity_sensor_enabled(&binding.entity_id, enabled);
-            let _ = lock.persist_and_log(&format!(
-                "Sensor {} {}",
-                binding.entity_id,
-                if enabled { "enabled" } else { "disabled" }
-            ));
+            let _ = lock.persist_and_log(
+                None,
+                &format!(
+                    "Sensor {} {}",
+                    binding.entity_id,
+                    if enabled { "enabled" } else { "disabled" }
+                ),
+            );
         }
 
         let mut lock = self.state.lock().await;
@@ -132,7 +484,21 @@ impl HassBackend {
                     }
                 }
             }
-            HassServiceKind::Light | HassServiceKind::Switch => {}
+            HassServiceKind::Temperature => {
+                if lock.get::<Temperature>(&binding.service_link).is_ok() {
+                    lock.update::<Temperature>(&binding.service_link.rid, |temp| {
+                        temp.enabled = enabled;
+                    })?;
+                }
+            }
+            HassServiceKind::LightLevel => {
+                if lock.get::<LightLevel>(&binding.service_link).is_ok() {
+                    lock.update::<LightLevel>(&binding.service_link.rid, |level| {
+                        level.enabled = enabled;
+                    })?;
+                }
+            }
+            HassServiceKind::Light | HassServiceKind::Switch | HassServiceKind::Button => {}
         }
         drop(lock);
 
@@ -141,10 +507,17 @@ impl HassBackend {
             .set_entity_registry_disabled(&binding.entity_id, !enabled)
             .await
         {
-            self.ui_log(format!(
-                "HA entity registry update failed for {}: {}",
-                binding.entity_id, err
-            ))
+            self.ui_log_entry(
+                TimelineEntry::new(
+                    TimelineCategory::Command,
+                    format!(
+                        "HA entity registry update failed for {}: {}",
+                        binding.entity_id, err
+                    ),
+                )
+                .with_level(TimelineLevel::Warning)
+                .with_entity(&binding.entity_id),
+            )
             .await;
         }
 
@@ -152,7 +525,7 @@ impl HassBackend {
     }
 
     async fn backend_grouped_light_update(
-        &self,
+        &mut self,
         link: &ResourceLink,
         upd: &GroupedLightUpdate,
     ) -> ApiResult<()> {
@@ -178,13 +551,10 @@ impl HassBackend {
 
         for child in children {
             if let Some(binding) = self.lookup_binding_by_device(&child) {
-                let grouped_as_light = match binding.kind {
-                    HassEntityKind::Light => true,
-                    HassEntityKind::Switch => {
-                        binding.switch_mode.unwrap_or(HassSwitchMode::Plug) == HassSwitchMode::Light
-                    }
-                    HassEntityKind::BinarySensor => false,
-                };
+                // media players are turned off with the room, but not on
+                let grouped_as_light = binding.kind.groups_as_light(binding.switch_mode)
+                    || (binding.kind == HassEntityKind::MediaPlayer
+                        && upd.on.is_some_and(|on| !on.on));
                 if grouped_as_light {
                     self.backend_light_update(&binding, &light_upd).await?;
                 }
@@ -206,69 +576,223 @@ impl HassBackend {
             crate::model::state::AuxData::new().with_index(sid),
         );
         lock.add(link_scene, Resource::Scene(scene.clone()))?;
-
-        let snapshot_entities = lock
-            .get::<Room>(&scene.group)
-            .map(|room| {
-                room.children
-                    .iter()
-                    .filter_map(|device| self.lookup_binding_by_device(device))
-                    .filter(|binding| match binding.kind {
-                        HassEntityKind::Light => true,
-                        HassEntityKind::Switch => {
-                            binding.switch_mode.unwrap_or(HassSwitchMode::Plug)
-                                == HassSwitchMode::Light
-                        }
-                        HassEntityKind::BinarySensor => false,
-                    })
-                    .map(|binding| binding.entity_id)
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default();
         drop(lock);
 
-        let short = link_scene.rid.simple().to_string();
-        let scene_id = format!("bifrost_{}", &short[..short.len().min(12)]);
+        self.export_scene(link_scene, scene).await;
+
+        Ok(())
+    }
+
+    /// Id of the Home Assistant scene exported for a hue scene
+    fn ha_scene_id(link: &ResourceLink) -> String {
+        let short = link.rid.simple().to_string();
+        format!("bifrost_{}", &short[..short.len().min(12)])
+    }
+
+    /// Home Assistant entity states (as used by `scene.create`) for the
+    /// actions of a hue scene
+    fn scene_entity_states(&self, scene: &Scene) -> Map<String, Value> {
+        let mut entities = Map::new();
+
+        for elem in &scene.actions {
+            let Some(binding) = self.lookup_binding_by_light(&elem.target) else {
+                continue;
+            };
+
+            let action = &elem.action;
+            let on = action.on.is_none_or(|on| on.on);
+
+            let mut state = Map::new();
+            if binding.kind == HassEntityKind::Cover {
+                state.insert(
+                    "state".to_string(),
+                    json!(if on { "open" } else { "closed" }),
+                );
+                if let Some(dim) = action
+                    .dimming
+                    .filter(|_| on && binding.capabilities.supports_brightness)
+                {
+                    state.insert(
+                        "current_position".to_string(),
+                        json!(dim.brightness.round().clamp(0.0, 100.0)),
+                    );
+                }
+                entities.insert(binding.entity_id, Value::Object(state));
+                continue;
+            }
+            state.insert("state".to_string(), json!(if on { "on" } else { "off" }));
+
+            if on && binding.kind == HassEntityKind::Light {
+                let caps = binding.capabilities;
+                if let Some(dim) = action.dimming.filter(|_| caps.supports_brightness) {
+                    state.insert(
+                        "brightness".to_string(),
+                        json!(ha_brightness(dim.brightness)),
+                    );
+                }
+                if let Some(color) = action.color.filter(|_| caps.supports_color) {
+                    state.insert("xy_color".to_string(), json!([color.xy.x, color.xy.y]));
+                } else if let Some(mirek) = action
+                    .color_temperature
+                    .and_then(|ct| ct.mirek)
+                    .filter(|_| caps.supports_color_temp)
+                {
+                    state.insert("color_temp".to_string(), json!(mirek));
+                }
+            }
+
+            let dimmable_fan = binding.kind == HassEntityKind::Fan
+                && binding.switch_mode == Some(HassSwitchMode::Light)
+                && binding.capabilities.supports_brightness;
+            if let Some(dim) = action.dimming.filter(|_| on && dimmable_fan) {
+                state.insert(
+                    "percentage".to_string(),
+                    json!(dim.brightness.round().clamp(0.0, 100.0)),
+                );
+            }
+
+            entities.insert(binding.entity_id, Value::Object(state));
+        }
+
+        entities
+    }
+
+    /// Export hue scene to Home Assistant (using `scene.create`), so it can be
+    /// recalled by Home Assistant automations too.
+    ///
+    /// Uses the light states of the scene actions if possible, otherwise a
+    /// snapshot of the current state of the lights in the room.
+    async fn export_scene(&mut self, link_scene: &ResourceLink, scene: &Scene) {
+        let scene_id = Self::ha_scene_id(link_scene);
         let ha_entity_id = format!("scene.{scene_id}");
 
-        if snapshot_entities.is_empty() {
-            self.ui_log(format!(
-                "Skipped scene writeback for {} (empty room snapshot)",
-                scene.metadata.name
-            ))
-            .await;
-        } else if let Err(err) = self
-            .client
-            .create_scene_snapshot(&scene_id, &scene.metadata.name, snapshot_entities)
-            .await
-        {
-            self.ui_log(format!(
-                "Scene writeback failed for {}: {}",
-                scene.metadata.name, err
-            ))
+        let entities = self.scene_entity_states(scene);
+
+        let result = if entities.is_empty() {
+            let snapshot_entities = self.scene_snapshot_entities(scene).await;
+            if snapshot_entities.is_empty() {
+                self.ui_log(
+                    TimelineCategory::Command,
+                    format!(
+                        "Skipped scene writeback for {} (empty room snapshot)",
+                        scene.metadata.name
+                    ),
+                )
+                .await;
+                return;
+            }
+            self.client
+                .create_scene_snapshot(&scene_id, &scene.metadata.name, snapshot_entities)
+                .await
+        } else {
+            self.client
+                .create_scene(&scene_id, &scene.metadata.name, entities)
+                .await
+        };
+
+        if let Err(err) = result {
+            self.ui_log_entry(
+                TimelineEntry::new(
+                    TimelineCategory::Command,
+                    format!(
+                        "Scene writeback failed for {}: {}",
+                        scene.metadata.name, err
+                    ),
+                )
+                .with_level(TimelineLevel::Warning),
+            )
             .await;
         } else {
             self.scene_map.insert(link_scene.rid, ha_entity_id);
         }
+    }
+
+    async fn backend_delete(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        match link.rtype {
+            RType::Scene => self.backend_scene_delete(link).await,
+            rtype => {
+                log::warn!(
+                    "[{}] Deleting objects of type {rtype:?} is not supported",
+                    self.name
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Delete a hue scene, and the Home Assistant scene exported for it.
+    ///
+    /// Removing the scene also drops its aux data, which frees its scene
+    /// index for reuse.
+    async fn backend_scene_delete(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        // the scene map does not survive restarts, so fall back to the id the
+        // scene would have been exported as
+        let known = self.scene_map.remove(&link.rid);
+        let ha_entity_id = known
+            .clone()
+            .unwrap_or_else(|| format!("scene.{}", Self::ha_scene_id(link)));
+
+        if let Err(err) = self.client.delete_scene(&ha_entity_id).await {
+            if known.is_some() {
+                log::warn!(
+                    "[{}] Failed to delete Home Assistant scene {ha_entity_id}: {err}",
+                    self.name
+                );
+            } else {
+                log::debug!(
+                    "[{}] No Home Assistant scene {ha_entity_id} to delete: {err}",
+                    self.name
+                );
+            }
+        }
+
+        self.state.lock().await.delete(link)?;
 
         Ok(())
     }
 
-    async fn backend_scene_recall(&mut self, link: &ResourceLink) -> ApiResult<()> {
-        if let Some(ha_scene) = self.scene_map.get(&link.rid) {
-            self.client.turn_on_scene(ha_scene).await?;
-            return Ok(());
+    async fn scene_snapshot_entities(&self, scene: &Scene) -> Vec<String> {
+        self.state
+            .lock()
+            .await
+            .get::<Room>(&scene.group)
+            .map(|room| {
+                room.children
+                    .iter()
+                    .filter_map(|device| self.lookup_binding_by_device(device))
+                    .filter(|binding| binding.kind.groups_as_light(binding.switch_mode))
+                    .map(|binding| binding.entity_id)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn backend_scene_recall(
+        &mut self,
+        link: &ResourceLink,
+        recall: &SceneRecall,
+        overrides: &[SceneActionElement],
+    ) -> ApiResult<()> {
+        // exported scenes cannot be recalled at a different brightness, or
+        // with different actions
+        if recall.dimming.is_none() && overrides.is_empty() {
+            if let Some(ha_scene) = self.scene_map.get(&link.rid) {
+                self.client.turn_on_scene(ha_scene, recall.duration).await?;
+                return Ok(());
+            }
         }
 
         let scene_actions = {
             let lock = self.state.lock().await;
-            lock.get::<Scene>(link)?.actions.clone()
+            lock.get::<Scene>(link)?.recall_actions(recall, overrides)
         };
 
         for action in scene_actions {
             if let Some(binding) = self.lookup_binding_by_light(&action.target) {
-                if matches!(binding.kind, HassEntityKind::Switch)
-                    && binding.switch_mode.unwrap_or(HassSwitchMode::Plug) != HassSwitchMode::Light
+                if matches!(
+                    binding.kind,
+                    HassEntityKind::Switch | HassEntityKind::Fan | HassEntityKind::MediaPlayer
+                ) && binding.switch_mode.unwrap_or(HassSwitchMode::Plug) != HassSwitchMode::Light
                 {
                     continue;
                 }
@@ -277,7 +801,7 @@ impl HassBackend {
                     dimming: action.action.dimming,
                     color: action.action.color,
                     color_temperature: action.action.color_temperature,
-                    dynamics: None,
+                    dynamics: recall.dynamics(),
                     ..LightUpdate::default()
                 };
                 self.backend_light_update(&binding, &upd).await?;
@@ -292,38 +816,188 @@ impl HassBackend {
         link: &ResourceLink,
         upd: &SceneUpdate,
     ) -> ApiResult<()> {
-        {
+        let recall = upd.recall.as_ref().filter(|recall| {
+            matches!(
+                recall.action,
+                Some(SceneStatusEnum::Active) | Some(SceneStatusEnum::Static)
+            )
+        });
+        // the actions sent along with a recall only apply to this recall
+        let (stored, overrides) = match (recall, &upd.actions) {
+            (Some(_), Some(actions)) => (
+                SceneUpdate {
+                    actions: None,
+                    ..upd.clone()
+                },
+                actions.as_slice(),
+            ),
+            _ => (upd.clone(), [].as_slice()),
+        };
+
+        let scene = {
             let mut lock = self.state.lock().await;
             lock.update::<Scene>(&link.rid, |scene| {
-                *scene += upd;
-                if let Some(recall) = &upd.recall {
-                    if matches!(
-                        recall.action,
-                        Some(SceneStatusEnum::Active) | Some(SceneStatusEnum::Static)
-                    ) {
-                        scene.status = Some(SceneStatus {
-                            active: SceneActive::Static,
-                            last_recall: Some(Utc::now()),
-                        });
-                    }
+                *scene += &stored;
+                if recall.is_some() {
+                    scene.status = Some(SceneStatus {
+                        active: SceneActive::Static,
+                        last_recall: Some(Utc::now()),
+                    });
                 }
             })?;
+            lock.get::<Scene>(link)?.clone()
+        };
+
+        // keep the exported Home Assistant scene up to date
+        if stored.actions.is_some() || stored.metadata.is_some() {
+            self.export_scene(link, &scene).await;
         }
 
-        if let Some(recall) = &upd.recall {
-            if matches!(
-                recall.action,
-                Some(SceneStatusEnum::Active) | Some(SceneStatusEnum::Static)
-            ) {
-                self.backend_scene_recall(link).await?;
+        if let Some(recall) = recall {
+            self.backend_scene_recall(link, recall, overrides).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn backend_forward_activity(&self, activity: &BifrostActivity) -> ApiResult<()> {
+        let message = match activity {
+            BifrostActivity::SceneRecall { name, room, .. } => match room {
+                Some(room) => Some(format!("Recalled scene {name} in {room}")),
+                None => Some(format!("Recalled scene {name}")),
+            },
+            BifrostActivity::GroupedLightToggle { room, on, .. } => Some(format!(
+                "Turned {} {}",
+                room.as_deref().unwrap_or("group"),
+                if *on { "on" } else { "off" }
+            )),
+            // link button presses are already recorded as pairing events
+            BifrostActivity::LinkButton { .. } => None,
+        };
+        if let Some(message) = message {
+            self.ui_log(TimelineCategory::Command, message).await;
+        }
+
+        if !self.server.forward_events.unwrap_or(true) {
+            return Ok(());
+        }
+
+        let Value::Object(data) = serde_json::to_value(activity)? else {
+            return Ok(());
+        };
+
+        self.client.fire_event("bifrost_event", data).await
+    }
+
+    async fn backend_notify_pairing(&self, devicetype: &str, linkbutton: bool) -> ApiResult<()> {
+        let mut data = Map::new();
+        data.insert("devicetype".to_string(), json!(devicetype));
+        data.insert("linkbutton".to_string(), json!(linkbutton));
+        self.client.fire_event("bifrost_pairing", data).await?;
+
+        if !self.server.pairing_notification.unwrap_or(true) {
+            return Ok(());
+        }
+
+        let mut message = format!("The app \"{devicetype}\" paired with the Bifrost bridge.");
+        if !linkbutton {
+            message.push_str(" The link button was not pressed.");
+        }
+
+        let mut data = Map::new();
+        data.insert("title".to_string(), json!("Bifrost: new app paired"));
+        data.insert("message".to_string(), json!(message));
+        data.insert("notification_id".to_string(), json!("bifrost_pairing"));
+        self.client
+            .call_service("persistent_notification", "create", "", data)
+            .await
+    }
+
+    /// Handle backend request, queueing it for a later retry if Home Assistant
+    /// is unreachable (except entertainment stream requests).
+    pub(super) async fn handle_backend_request(
+        &mut self,
+        req: Arc<BackendRequest>,
+    ) -> ApiResult<()> {
+        // entertainment streams are never queued: a late frame is useless,
+        // since it is replaced by the next one anyway
+        match &*req {
+            BackendRequest::EntertainmentStart(_) | BackendRequest::EntertainmentStop() => {
+                return self.handle_backend_event(req).await;
+            }
+            BackendRequest::EntertainmentFrame(_) => {
+                // skip rendering while Home Assistant is known to be unreachable
+                if self.retry.is_empty() {
+                    return self.handle_backend_event(req).await;
+                }
                 return Ok(());
             }
+            _ => {}
         }
 
-        Ok(())
+        // keep requests in order, if some are already waiting. The queue is
+        // drained from its own tick of the event loop.
+        if !self.retry.is_empty() {
+            self.queue_retry(req).await;
+            return Ok(());
+        }
+
+        match self.handle_backend_event(req.clone()).await {
+            Err(err) if RetryQueue::is_retryable(&err) => {
+                log::warn!(
+                    "[{}] Home Assistant unreachable, queueing request: {err}",
+                    self.name
+                );
+                self.queue_retry(req).await;
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
+    async fn queue_retry(&mut self, req: Arc<BackendRequest>) {
+        if !self.retry.push(req) {
+            log::warn!("[{}] Retry queue disabled, dropping request", self.name);
+        }
+        self.publish_retry_status().await;
+    }
+
+    /// Retry queued requests in order, until Home Assistant fails again or
+    /// the budget of this pass (see [`RetryQueue::PASS_REQUESTS`]) is used up
+    pub(super) async fn drain_retry_queue(&mut self) {
+        let started = Instant::now();
+        for _ in 0..RetryQueue::PASS_REQUESTS {
+            if started.elapsed() >= RetryQueue::PASS_TIME {
+                break;
+            }
+            let Some((queued_at, req)) = self.retry.pop() else {
+                break;
+            };
+            match self.handle_backend_event(req.clone()).await {
+                Ok(()) => self.retry.record_retried(),
+                Err(err) if RetryQueue::is_retryable(&err) => {
+                    self.retry.requeue((queued_at, req));
+                    break;
+                }
+                Err(err) => {
+                    log::error!("[{}] Retried request failed: {err}", self.name);
+                    self.retry.record_failed();
+                }
+            }
+        }
+
+        self.publish_retry_status().await;
+    }
+
+    async fn publish_retry_status(&self) {
+        self.ui_state
+            .lock()
+            .await
+            .retry_queue
+            .insert(self.name.clone(), self.retry.status());
     }
 
-    pub(super) async fn handle_backend_event(&mut self, req: Arc<BackendRequest>) -> ApiResult<()> {
+    async fn handle_backend_event(&mut self, req: Arc<BackendRequest>) -> ApiResult<()> {
         match &*req {
             BackendRequest::LightUpdate(link, upd) => {
                 if let Some(binding) = self.lookup_binding_by_light(link) {
@@ -336,53 +1010,108 @@ impl HassBackend {
                         .await?;
                 }
             }
-            BackendRequest::HassSync => {
+            BackendRequest::ButtonPress(link) => {
+                if let Some(binding) = self.lookup_binding_by_sensor(link).filter(|binding| {
+                    matches!(
+                        binding.kind,
+                        HassEntityKind::Button | HassEntityKind::Script
+                    )
+                }) {
+                    self.backend_button_press(&binding).await?;
+                }
+            }
+            BackendRequest::GroupedLightUpdate(link, upd) => {
+                self.backend_grouped_light_update(link, upd).await?;
+            }
+            BackendRequest::SceneCreate(link, sid, scene) => {
+                self.backend_scene_create(link, *sid, scene).await?;
+            }
+            BackendRequest::SceneUpdate(link, upd) => {
+                self.backend_scene_update(link, upd).await?;
+            }
+
+            BackendRequest::DeviceUpdate(link, upd) => {
+                if let Some(binding) = self.lookup_binding_by_device(link) {
+                    self.backend_device_update(&binding, upd).await;
+                }
+            }
+
+            BackendRequest::RoomUpdate(link, upd) => {
+                self.backend_room_update(link, upd).await?;
+            }
+
+            BackendRequest::Delete(link) => {
+                self.backend_delete(link).await?;
+            }
+
+            BackendRequest::EntertainmentStart(area) => {
+                self.entertainment_start(area).await?;
+            }
+            BackendRequest::EntertainmentFrame(frame) => {
+                self.entertainment_frame(frame).await;
+            }
+            BackendRequest::EntertainmentStop() => {
+                self.renderer = None;
+            }
+
+            BackendRequest::ZigbeeDeviceDiscovery(_, _) => {}
+        }
+
+        Ok(())
+    }
+
+    pub(super) async fn handle_hass_request(&mut self, req: Arc<HassRequest>) -> ApiResult<()> {
+        match &*req {
+            HassRequest::Sync => {
                 let _ = self.run_sync("manual").await;
             }
-            BackendRequest::HassUpsertEntity(entity_id) => {
+            HassRequest::UpsertEntity(entity_id) => {
                 let _ = self.sync_entity_by_id(entity_id).await;
             }
-            BackendRequest::HassRemoveEntity(entity_id) => {
+            HassRequest::RemoveEntity(entity_id) => {
                 let _ = self.remove_entity_by_id(entity_id).await;
             }
-            BackendRequest::HassUpdateRooms => {
+            HassRequest::UpdateRooms => {
                 let _ = self.refresh_rooms_from_ui_config().await;
             }
-            BackendRequest::HassConnect => {
+            HassRequest::Connect => {
                 {
                     let mut rt = self.runtime_state.lock().await;
                     rt.config.enabled = true;
-                    let _ = rt.save();
+                    rt.save();
                 }
                 self.ws = None;
                 let _ = self.run_sync("connect").await;
             }
-            BackendRequest::HassDisconnect => {
+            HassRequest::Disconnect => {
                 {
                     let mut rt = self.runtime_state.lock().await;
                     rt.config.enabled = false;
-                    let _ = rt.save();
+                    rt.save();
                 }
                 self.ws = None;
-                self.ui_log("Home Assistant backend disconnected by user")
-                    .await;
+                self.ui_log(
+                    TimelineCategory::Connection,
+                    "Home Assistant backend disconnected by user",
+                )
+                .await;
             }
-            BackendRequest::GroupedLightUpdate(link, upd) => {
-                self.backend_grouped_light_update(link, upd).await?;
+            HassRequest::NotifyPairing(devicetype, linkbutton) => {
+                if let Err(err) = self.backend_notify_pairing(devicetype, *linkbutton).await {
+                    log::warn!("[{}] Failed to send pairing notification: {err}", self.name);
+                }
             }
-            BackendRequest::SceneCreate(link, sid, scene) => {
-                self.backend_scene_create(link, *sid, scene).await?;
+            HassRequest::ForwardActivity(activity) => {
+                if let Err(err) = self.backend_forward_activity(activity).await {
+                    log::warn!("[{}] Failed to forward activity: {err}", self.name);
+                }
             }
-            BackendRequest::SceneUpdate(link, upd) => {
-                self.backend_scene_update(link, upd).await?;
+            HassRequest::Verify(id) => {
+                self.run_verify(false, false, Some(*id)).await;
+            }
+            HassRequest::Reconcile(id) => {
+                self.run_verify(true, true, Some(*id)).await;
             }
-
-            BackendRequest::RoomUpdate(_, _)
-            | BackendRequest::Delete(_)
-            | BackendRequest::EntertainmentStart(_)
-            | BackendRequest::EntertainmentFrame(_)
-            | BackendRequest::EntertainmentStop()
-            | BackendRequest::ZigbeeDeviceDiscovery(_, _) => {}
         }
 
         Ok(())
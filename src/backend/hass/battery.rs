@@ -0,0 +1,234 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde_json::json;
+
+use hue::api::{Device, DevicePower, RType, Resource, ResourceLink};
+
+use crate::backend::hass::HassBackend;
+use crate::backend::hass::client::HassState;
+use crate::backend::hass::import::device_class;
+use crate::error::ApiResult;
+use crate::resource::Resources;
+
+/// Battery levels of Home Assistant devices, from their battery sensors
+#[derive(Clone, Debug, Default)]
+pub(super) struct Batteries {
+    /// Home Assistant device id of each battery sensor entity
+    entities: HashMap<String, String>,
+    /// Last seen battery level (in percent), by Home Assistant device id
+    levels: HashMap<String, u8>,
+    /// Hue devices of the imported entities, by Home Assistant device id
+    devices: HashMap<String, BTreeSet<ResourceLink>>,
+}
+
+impl Batteries {
+    /// Remember `state`, if it is a battery sensor. Without `device_id`, only
+    /// sensors already seen with one are recognized. Returns the Home
+    /// Assistant device id, if it was.
+    pub(super) fn observe(&mut self, state: &HassState, device_id: Option<&str>) -> Option<String> {
+        if !is_battery_sensor(state) {
+            return None;
+        }
+
+        let device_id = match device_id {
+            Some(device_id) => {
+                self.entities
+                    .insert(state.entity_id.clone(), device_id.to_string());
+                device_id.to_string()
+            }
+            None => self.entities.get(&state.entity_id)?.clone(),
+        };
+
+        if let Some(level) = battery_level(state) {
+            self.levels.insert(device_id.clone(), level);
+        } else {
+            self.levels.remove(&device_id);
+        }
+
+        Some(device_id)
+    }
+
+    /// Forget everything, before a full import
+    pub(super) fn clear(&mut self) {
+        self.entities.clear();
+        self.levels.clear();
+        self.devices.clear();
+    }
+
+    /// Note that `device_link` is the hue device of an entity belonging to
+    /// the Home Assistant device `device_id`
+    pub(super) fn add_device(&mut self, device_id: &str, device_link: ResourceLink) {
+        self.devices
+            .entry(device_id.to_string())
+            .or_default()
+            .insert(device_link);
+    }
+
+    /// Forget `device_link`, once its entity is removed
+    pub(super) fn remove_device(&mut self, device_link: &ResourceLink) {
+        for links in self.devices.values_mut() {
+            links.remove(device_link);
+        }
+    }
+}
+
+fn is_battery_sensor(state: &HassState) -> bool {
+    state.entity_id.starts_with("sensor.") && device_class(state) == Some("battery")
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn battery_level(state: &HassState) -> Option<u8> {
+    let level = state.state.parse::<f64>().ok()?;
+    // a NaN would be clamped to NaN, and cast to 0
+    if level.is_nan() {
+        return None;
+    }
+    Some(level.round().clamp(0.0, 100.0) as u8)
+}
+
+/// Hue battery state for a battery `level` (in percent)
+const fn battery_state(level: u8) -> &'static str {
+    match level {
+        0..5 => "critical",
+        5..20 => "low",
+        _ => "normal",
+    }
+}
+
+impl HassBackend {
+    /// Link of the device power service of `device`
+    fn device_power_link(device: &ResourceLink) -> ResourceLink {
+        RType::DevicePower.deterministic(format!("{}:device_power", device.rid))
+    }
+
+    /// Update the device power services of the hue devices belonging to the
+    /// Home Assistant device `device_id`
+    pub(super) fn sync_battery(&self, res: &mut Resources, device_id: &str) -> ApiResult<()> {
+        let Some(links) = self.batteries.devices.get(device_id) else {
+            return Ok(());
+        };
+        let level = self.batteries.levels.get(device_id).copied();
+
+        for device_link in links {
+            if res.get::<Device>(device_link).is_err() {
+                continue;
+            }
+
+            let link = Self::device_power_link(device_link);
+            let Some(level) = level else {
+                // the battery sensor is gone, or has no level
+                if res.get_resource(&link).is_ok() {
+                    res.delete(&link)?;
+                }
+                continue;
+            };
+
+            let power_state = json!({
+                "battery_state": battery_state(level),
+                "battery_level": level,
+            });
+
+            if res.get::<DevicePower>(&link).is_err() {
+                let power = DevicePower {
+                    owner: *device_link,
+                    power_state,
+                };
+                res.add(&link, Resource::DevicePower(power))?;
+                res.update::<Device>(&device_link.rid, |dev| {
+                    dev.services.insert(link);
+                })?;
+            } else {
+                res.update::<DevicePower>(&link.rid, |power| {
+                    power.power_state = power_state;
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the device power services of all imported entities
+    pub(super) fn sync_batteries(&self, res: &mut Resources) -> ApiResult<()> {
+        for device_id in self.batteries.devices.keys() {
+            self.sync_battery(res, device_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use serde_json::json;
+
+    use hue::api::DevicePower;
+    use hue::version::SwVersion;
+
+    use crate::backend::hass::HassBackend;
+    use crate::backend::hass::battery::{battery_level, battery_state};
+    use crate::backend::hass::client::HassState;
+    use crate::backend::hass::import::tests::{backend, sensor};
+    use crate::model::hass::{HassEntityKind, HassServiceKind};
+    use crate::model::state::State;
+    use crate::resource::Resources;
+
+    fn battery(state: &str) -> HassState {
+        serde_json::from_value(json!({
+            "entity_id": "sensor.hallway_battery",
+            "state": state,
+            "attributes": {"device_class": "battery"},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn battery_levels() {
+        assert_eq!(battery_level(&battery("87")), Some(87));
+        assert_eq!(battery_level(&battery("49.6")), Some(50));
+        assert_eq!(battery_level(&battery("-3")), Some(0));
+        assert_eq!(battery_level(&battery("120")), Some(100));
+        assert_eq!(battery_level(&battery("NaN")), None);
+        assert_eq!(battery_level(&battery("unavailable")), None);
+    }
+
+    #[test]
+    fn battery_states() {
+        assert_eq!(battery_state(0), "critical");
+        assert_eq!(battery_state(4), "critical");
+        assert_eq!(battery_state(5), "low");
+        assert_eq!(battery_state(19), "low");
+        assert_eq!(battery_state(20), "normal");
+        assert_eq!(battery_state(100), "normal");
+    }
+
+    #[test]
+    fn battery_of_single_entity() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-hass-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let mut svc = backend(&dir);
+        let motion = sensor(
+            "binary_sensor.hallway_motion",
+            HassEntityKind::BinarySensor,
+            HassServiceKind::Motion,
+        );
+        svc.sync_single_entity(&motion, &mut res).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            svc.batteries.observe(&battery("3"), Some("hallway")),
+            Some("hallway".to_string())
+        );
+        let (device_link, _) = svc.links_for_imported(&motion);
+        svc.batteries.add_device("hallway", device_link);
+        svc.sync_battery(&mut res, "hallway").unwrap();
+
+        let link = HassBackend::device_power_link(&device_link);
+        let power = res.get::<DevicePower>(&link).unwrap();
+        assert_eq!(power.power_state["battery_level"], 3);
+        assert_eq!(power.power_state["battery_state"], "critical");
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Counts failures of individual events in the backend event loop.
+///
+/// A single failing event is logged and skipped, but if too many events fail
+/// within a short window, something is more fundamentally wrong, and the
+/// breaker trips, so the service can be restarted.
+pub(super) struct CircuitBreaker {
+    failures: VecDeque<Instant>,
+    threshold: usize,
+    window: Duration,
+    total: u64,
+}
+
+impl CircuitBreaker {
+    pub const DEFAULT_THRESHOLD: usize = 5;
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            failures: VecDeque::with_capacity(threshold),
+            threshold,
+            window,
+            total: 0,
+        }
+    }
+
+    /// Record a failure. Returns true if the breaker has tripped.
+    pub fn record_failure(&mut self) -> bool {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn record_failure_at(&mut self, now: Instant) -> bool {
+        self.total += 1;
+
+        while self
+            .failures
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > self.window)
+        {
+            self.failures.pop_front();
+        }
+
+        self.failures.push_back(now);
+        self.failures.len() >= self.threshold
+    }
+
+    /// Number of failures since the backend was created
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn reset(&mut self) {
+        self.failures.clear();
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_THRESHOLD, Self::DEFAULT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::backend::hass::breaker::CircuitBreaker;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn closed_below_threshold() {
+        let mut breaker = CircuitBreaker::new(3, WINDOW);
+        let now = Instant::now();
+
+        assert!(!breaker.record_failure_at(now));
+        assert!(!breaker.record_failure_at(now));
+        assert_eq!(breaker.total(), 2);
+    }
+
+    #[test]
+    fn opens_at_threshold() {
+        let mut breaker = CircuitBreaker::new(3, WINDOW);
+        let now = Instant::now();
+
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+        assert!(breaker.record_failure_at(now));
+    }
+
+    #[test]
+    fn closes_after_reset() {
+        let mut breaker = CircuitBreaker::new(3, WINDOW);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            breaker.record_failure_at(now);
+        }
+
+        // after a restart, the breaker starts over (half-open): a single
+        // failure is tolerated again, and it only trips at the threshold
+        breaker.reset();
+        assert!(!breaker.record_failure_at(now));
+        assert!(!breaker.record_failure_at(now));
+        assert!(breaker.record_failure_at(now));
+
+        // the total is kept across resets
+        assert_eq!(breaker.total(), 6);
+    }
+
+    #[test]
+    fn failures_expire_after_window() {
+        let mut breaker = CircuitBreaker::new(3, WINDOW);
+        let now = Instant::now();
+
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+
+        // old failures no longer count, so the breaker stays closed
+        let later = now + WINDOW + Duration::from_secs(1);
+        assert!(!breaker.record_failure_at(later));
+        assert!(!breaker.record_failure_at(later));
+        assert!(breaker.record_failure_at(later));
+    }
+}
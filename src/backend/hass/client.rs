@@ -1,19 +1,21 @@
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
 use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use serde_json::{Map, Value, json};
 use tokio::net::TcpStream;
-use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{WebSocketStream, connect_async};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use url::Url;
 
 use bifrost_api::config::HassServer;
 
 use crate::error::{ApiError, ApiResult};
+use crate::server::proxy;
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct HassState {
@@ -21,6 +23,51 @@ pub struct HassState {
     pub state: String,
     #[serde(default)]
     pub attributes: Map<String, Value>,
+    #[serde(default)]
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HassConfigEntry {
+    pub entry_id: String,
+    pub domain: String,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HassEntityRegistryEntry {
+    pub entity_id: String,
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// The Home Assistant device of the entity, if any
+    #[serde(default)]
+    pub device_id: Option<String>,
+    /// Label ids (Home Assistant 2024.4+)
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HassLabelRegistryEntry {
+    pub label_id: String,
+    pub name: String,
+}
+
+impl HassEntityRegistryEntry {
+    /// The unique id, qualified with the integration (platform) name, since
+    /// it is only unique within that.
+    #[must_use]
+    pub fn qualified_unique_id(&self) -> Option<String> {
+        let unique_id = self.unique_id.as_ref()?;
+        Some(format!("{}.{unique_id}", self.platform))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct HassFlowResult {
+    flow_id: String,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -32,12 +79,22 @@ pub struct HassCoreConfig {
     pub latitude: Option<f64>,
     #[serde(default)]
     pub longitude: Option<f64>,
+    #[serde(default)]
+    pub unit_system: HassUnitSystem,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HassUnitSystem {
+    #[serde(default)]
+    pub temperature: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct HassClient {
     backend_name: String,
     base_url: Url,
     http: reqwest::Client,
+    proxy: Option<Url>,
     token: Option<String>,
 }
 
@@ -48,11 +105,18 @@ pub struct HassStateChangedEvent {
     pub old_state: Option<HassState>,
 }
 
+#[derive(Clone, Debug)]
+pub enum HassWsEvent {
+    StateChanged(HassStateChangedEvent),
+    Other { event_type: String },
+}
+
 #[derive(Debug, Deserialize)]
 struct HassWsEventEnvelope {
     #[serde(default)]
     pub event_type: String,
-    pub data: HassWsEventData,
+    #[serde(default)]
+    pub data: Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,15 +144,48 @@ enum HassWsIncoming {
     },
     #[serde(rename = "event")]
     Event { event: HassWsEventEnvelope },
+    #[serde(rename = "pong")]
+    Pong { id: u64 },
     #[serde(other)]
     Other,
 }
 
 pub struct HassWs {
     socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+    /// Id and send time of the ping still waiting for a pong
+    ping: Option<(u64, Instant)>,
+    latency: Option<Duration>,
 }
 
 impl HassWs {
+    /// Send a ping, unless one is still waiting for its pong
+    pub async fn send_ping(&mut self) -> ApiResult<()> {
+        if self.ping.is_some() {
+            return Ok(());
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let ping = json!({"id": id, "type": "ping"});
+        self.socket
+            .send(Message::Text(ping.to_string().into()))
+            .await?;
+        self.ping = Some((id, Instant::now()));
+        Ok(())
+    }
+
+    /// Time the current ping has been waiting for a pong
+    #[must_use]
+    pub fn ping_outstanding(&self) -> Option<Duration> {
+        self.ping.map(|(_, sent)| sent.elapsed())
+    }
+
+    /// Round trip time of the last answered ping
+    #[must_use]
+    pub const fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
     async fn recv_json(&mut self) -> ApiResult<Option<HassWsIncoming>> {
         let Some(msg) = self.socket.next().await else {
             return Ok(None);
@@ -100,16 +197,27 @@ impl HassWs {
         Ok(Some(serde_json::from_str::<HassWsIncoming>(&text)?))
     }
 
-    pub async fn next_state_changed(&mut self) -> ApiResult<Option<HassStateChangedEvent>> {
+    pub async fn next_event(&mut self) -> ApiResult<Option<HassWsEvent>> {
         while let Some(msg) = self.recv_json().await? {
+            if let HassWsIncoming::Pong { id } = msg {
+                if let Some((_, sent)) = self.ping.filter(|(ping_id, _)| *ping_id == id) {
+                    self.latency = Some(sent.elapsed());
+                    self.ping = None;
+                }
+                continue;
+            }
             if let HassWsIncoming::Event { event } = msg {
                 if event.event_type == "state_changed" {
-                    return Ok(Some(HassStateChangedEvent {
-                        entity_id: event.data.entity_id,
-                        new_state: event.data.new_state,
-                        old_state: event.data.old_state,
-                    }));
+                    let data: HassWsEventData = serde_json::from_value(event.data)?;
+                    return Ok(Some(HassWsEvent::StateChanged(HassStateChangedEvent {
+                        entity_id: data.entity_id,
+                        new_state: data.new_state,
+                        old_state: data.old_state,
+                    })));
                 }
+                return Ok(Some(HassWsEvent::Other {
+                    event_type: event.event_type,
+                }));
             }
         }
         Ok(None)
@@ -119,14 +227,15 @@ impl HassWs {
 #[derive(Debug, Serialize)]
 struct HassTemplateRequest<'a> {
     template: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variables: Option<Value>,
 }
 
 impl HassClient {
-    const DEFAULT_TOKEN_ENV: &'static str = "HASS_TOKEN";
     const DEFAULT_TIMEOUT_SECS: u64 = 10;
 
-    pub fn new(backend_name: &str, server: &HassServer) -> ApiResult<Self> {
-        let http = reqwest::Client::builder()
+    pub fn new(backend_name: &str, server: &HassServer, proxy: Option<Url>) -> ApiResult<Self> {
+        let http = proxy::client_builder(proxy.as_ref())?
             .timeout(Duration::from_secs(Self::DEFAULT_TIMEOUT_SECS))
             .build()?;
 
@@ -134,15 +243,13 @@ impl HassClient {
             backend_name: backend_name.to_string(),
             base_url: server.url.clone(),
             http,
+            proxy,
             token: None,
         })
     }
 
     pub fn load_token_from_env(&mut self, server: &HassServer) -> ApiResult<()> {
-        let token_env = server
-            .token_env
-            .as_deref()
-            .unwrap_or(Self::DEFAULT_TOKEN_ENV);
+        let token_env = server.token_env();
         let token = std::env::var(token_env).map_err(|_| {
             ApiError::service_error(format!(
                 "[{}] Missing Home Assistant token env var {}",
@@ -262,6 +369,7 @@ impl HassClient {
             .bearer_auth(self.token()?)
             .json(&HassTemplateRequest {
                 template: &template,
+                variables: None,
             })
             .send()
             .await?;
@@ -276,10 +384,13 @@ impl HassClient {
         Ok(Some(area.to_string()))
     }
 
-    pub async fn get_entity_areas(&self) -> ApiResult<HashMap<String, String>> {
+    /// Areas of the entities that may be imported, by entity id. Of the
+    /// numeric sensors, only temperatures, light levels and `sensors` (those
+    /// with a threshold) are included.
+    pub async fn get_entity_areas(&self, sensors: &[&str]) -> ApiResult<HashMap<String, String>> {
         // Returns one line per entity in format: entity_id|area_name
         let template = r#"
-{% for s in states if s.entity_id.startswith('light.') or s.entity_id.startswith('switch.') or s.entity_id.startswith('binary_sensor.') %}
+{% for s in states if s.domain in ['light', 'switch', 'binary_sensor', 'cover', 'fan', 'media_player', 'lock'] or (s.domain == 'sensor' and (s.attributes.device_class in ['temperature', 'illuminance'] or s.entity_id in sensors)) %}
 {{ s.entity_id }}|{{ area_name(s.entity_id) or '' }}
 {% endfor %}
 "#;
@@ -288,7 +399,10 @@ impl HassClient {
             .http
             .post(url)
             .bearer_auth(self.token()?)
-            .json(&HassTemplateRequest { template })
+            .json(&HassTemplateRequest {
+                template,
+                variables: Some(json!({ "sensors": sensors })),
+            })
             .send()
             .await?;
         let response = self
@@ -317,8 +431,23 @@ impl HassClient {
         domain: &str,
         service: &str,
         entity_id: &str,
-        mut data: Map<String, Value>,
+        data: Map<String, Value>,
     ) -> ApiResult<()> {
+        self.call_service_at(domain, service, entity_id, data)
+            .await
+            .map(|_| ())
+    }
+
+    /// Call a service, and return when Home Assistant performed it, by its
+    /// own clock: the last update of `entity_id`, if the call changed its
+    /// state, or else the time of the response (rounded up to the second).
+    pub async fn call_service_at(
+        &self,
+        domain: &str,
+        service: &str,
+        entity_id: &str,
+        mut data: Map<String, Value>,
+    ) -> ApiResult<Option<DateTime<Utc>>> {
         let url = self.endpoint_url(&format!("/api/services/{domain}/{service}"))?;
         if !entity_id.trim().is_empty() {
             data.insert(
@@ -335,9 +464,146 @@ impl HassClient {
             .json(&payload)
             .send()
             .await?;
-        let _response = self
+        let response = self
             .check_status(response, &format!("POST /api/services/{domain}/{service}"))
             .await?;
+
+        // the date header has a resolution of seconds, so the call happened
+        // before the end of that second
+        let date = response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.with_timezone(&Utc) + chrono::TimeDelta::seconds(1));
+
+        // the states changed while the service was performed
+        let changed: Vec<HassState> = response.json().await.unwrap_or_default();
+        let updated = changed
+            .into_iter()
+            .find(|state| state.entity_id == entity_id)
+            .and_then(|state| state.last_updated);
+
+        Ok(updated.or(date))
+    }
+
+    /// Create or replace the state of `entity_id` (which does not need to
+    /// belong to any integration)
+    pub async fn set_state(
+        &self,
+        entity_id: &str,
+        state: &str,
+        attributes: Map<String, Value>,
+    ) -> ApiResult<()> {
+        let url = self.endpoint_url(&format!("/api/states/{entity_id}"))?;
+        let payload = json!({
+            "state": state,
+            "attributes": attributes,
+        });
+
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(self.token()?)
+            .json(&payload)
+            .send()
+            .await?;
+        let _response = self
+            .check_status(response, &format!("POST /api/states/{entity_id}"))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fire_event(&self, event_type: &str, data: Map<String, Value>) -> ApiResult<()> {
+        let url = self.endpoint_url(&format!("/api/events/{event_type}"))?;
+
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(self.token()?)
+            .json(&Value::Object(data))
+            .send()
+            .await?;
+        let _response = self
+            .check_status(response, &format!("POST /api/events/{event_type}"))
+            .await?;
+        Ok(())
+    }
+
+    async fn post_json<T: DeserializeOwned>(&self, endpoint: &str, data: &Value) -> ApiResult<T> {
+        let url = self.endpoint_url(endpoint)?;
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(self.token()?)
+            .json(data)
+            .send()
+            .await?;
+        let response = self
+            .check_status(response, &format!("POST {endpoint}"))
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn get_config_entries(&self, domain: &str) -> ApiResult<Vec<HassConfigEntry>> {
+        let mut url = self.endpoint_url("/api/config/config_entries/entry")?;
+        url.query_pairs_mut().append_pair("domain", domain);
+        let response = self.http.get(url).bearer_auth(self.token()?).send().await?;
+        let response = self
+            .check_status(response, "GET /api/config/config_entries/entry")
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Create a light group helper (through the "group" config flow)
+    pub async fn create_light_group(&self, name: &str, entities: &[String]) -> ApiResult<()> {
+        let flow: HassFlowResult = self
+            .post_json(
+                "/api/config/config_entries/flow",
+                &json!({"handler": "group", "show_advanced_options": false}),
+            )
+            .await?;
+
+        let step = format!("/api/config/config_entries/flow/{}", flow.flow_id);
+        let _: Value = self
+            .post_json(&step, &json!({"next_step_id": "light"}))
+            .await?;
+        let _: Value = self
+            .post_json(
+                &step,
+                &json!({
+                    "name": name,
+                    "entities": entities,
+                    "hide_members": false,
+                    "all": false,
+                }),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replace the members of a light group helper (through its options flow)
+    pub async fn update_light_group(&self, entry_id: &str, entities: &[String]) -> ApiResult<()> {
+        let flow: HassFlowResult = self
+            .post_json(
+                "/api/config/config_entries/options/flow",
+                &json!({"handler": entry_id}),
+            )
+            .await?;
+
+        let step = format!("/api/config/config_entries/options/flow/{}", flow.flow_id);
+        let _: Value = self
+            .post_json(
+                &step,
+                &json!({
+                    "entities": entities,
+                    "hide_members": false,
+                    "all": false,
+                }),
+            )
+            .await?;
+
         Ok(())
     }
 
@@ -357,11 +623,43 @@ impl HassClient {
         self.call_service("scene", "create", "", data).await
     }
 
-    pub async fn turn_on_scene(&self, entity_id: &str) -> ApiResult<()> {
-        self.call_service("scene", "turn_on", entity_id, Map::new())
+    /// Create (or replace) a scene with the given entity states
+    pub async fn create_scene(
+        &self,
+        scene_id: &str,
+        name: &str,
+        entities: Map<String, Value>,
+    ) -> ApiResult<()> {
+        let mut data = Map::new();
+        data.insert("scene_id".to_string(), Value::String(scene_id.to_string()));
+        data.insert("name".to_string(), Value::String(name.to_string()));
+        data.insert("entities".to_string(), Value::Object(entities));
+        self.call_service("scene", "create", "", data).await
+    }
+
+    /// Delete a scene created with `scene.create`
+    pub async fn delete_scene(&self, entity_id: &str) -> ApiResult<()> {
+        self.call_service("scene", "delete", entity_id, Map::new())
             .await
     }
 
+    /// Turn on scene `entity_id`, with a transition of `duration_ms`
+    pub async fn turn_on_scene(&self, entity_id: &str, duration_ms: Option<u32>) -> ApiResult<()> {
+        let mut data = Map::new();
+        if let Some(duration_ms) = duration_ms {
+            data.insert(
+                "transition".to_string(),
+                Value::from(f64::from(duration_ms) / 1000.0),
+            );
+        }
+        self.call_service("scene", "turn_on", entity_id, data).await
+    }
+
+    async fn ws_connect(&self) -> ApiResult<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let ws_url = self.ws_endpoint_url()?;
+        proxy::connect_websocket(&ws_url, self.proxy.as_ref(), None).await
+    }
+
     fn ws_endpoint_url(&self) -> ApiResult<Url> {
         let mut url = self.endpoint_url("/api/websocket")?;
         let scheme = match url.scheme() {
@@ -377,9 +675,9 @@ impl HassClient {
         Ok(url)
     }
 
-    pub async fn subscribe_state_changed(&self) -> ApiResult<HassWs> {
-        let ws_url = self.ws_endpoint_url()?;
-        let (mut socket, _response) = connect_async(ws_url.as_str()).await?;
+    /// Subscribe to `state_changed` events, and any additional event types
+    pub async fn subscribe_events(&self, extra_event_types: &[&str]) -> ApiResult<HassWs> {
+        let mut socket = self.ws_connect().await?;
 
         // Consume initial auth challenge.
         let _ = socket.next().await;
@@ -414,50 +712,57 @@ impl HassClient {
             }
         }
 
-        // Subscribe to state_changed events.
-        let sub = serde_json::json!({
-            "id": 1,
-            "type": "subscribe_events",
-            "event_type": "state_changed",
-        });
-        socket.send(Message::Text(sub.to_string().into())).await?;
-
-        // Wait for subscribe result.
-        loop {
-            let Some(msg) = socket.next().await else {
-                return Err(ApiError::service_error(format!(
-                    "[{}] Home Assistant websocket closed during subscribe",
-                    self.backend_name
-                )));
-            };
-            let msg = msg.map_err(ApiError::from)?;
-            if let Message::Text(text) = msg {
-                let value: HassWsIncoming = serde_json::from_str(&text)?;
-                if let HassWsIncoming::Result { id, success, error } = value {
-                    if id == 1 && success {
-                        break;
-                    }
-                    if id == 1 && !success {
-                        return Err(ApiError::service_error(format!(
-                            "[{}] Home Assistant subscribe_events failed: {}",
-                            self.backend_name,
-                            error.unwrap_or(Value::Null)
-                        )));
+        let event_types = std::iter::once("state_changed").chain(extra_event_types.iter().copied());
+
+        let mut next_id = 1;
+        for (sub_id, event_type) in (1..).zip(event_types) {
+            next_id = sub_id + 1;
+            let sub = serde_json::json!({
+                "id": sub_id,
+                "type": "subscribe_events",
+                "event_type": event_type,
+            });
+            socket.send(Message::Text(sub.to_string().into())).await?;
+
+            // Wait for subscribe result.
+            loop {
+                let Some(msg) = socket.next().await else {
+                    return Err(ApiError::service_error(format!(
+                        "[{}] Home Assistant websocket closed during subscribe",
+                        self.backend_name
+                    )));
+                };
+                let msg = msg.map_err(ApiError::from)?;
+                if let Message::Text(text) = msg {
+                    let value: HassWsIncoming = serde_json::from_str(&text)?;
+                    if let HassWsIncoming::Result { id, success, error } = value {
+                        if id == sub_id && success {
+                            break;
+                        }
+                        if id == sub_id && !success {
+                            return Err(ApiError::service_error(format!(
+                                "[{}] Home Assistant subscribe_events ({event_type}) failed: {}",
+                                self.backend_name,
+                                error.unwrap_or(Value::Null)
+                            )));
+                        }
                     }
                 }
             }
         }
 
-        Ok(HassWs { socket })
+        Ok(HassWs {
+            socket,
+            next_id,
+            ping: None,
+            latency: None,
+        })
     }
 
-    pub async fn set_entity_registry_disabled(
-        &self,
-        entity_id: &str,
-        disabled: bool,
-    ) -> ApiResult<()> {
-        let ws_url = self.ws_endpoint_url()?;
-        let (mut socket, _response) = connect_async(ws_url.as_str()).await?;
+    /// Send a single command over a fresh websocket connection, and return
+    /// its result
+    async fn ws_command(&self, mut req: Value, what: &str) -> ApiResult<Value> {
+        let mut socket = self.ws_connect().await?;
 
         let first = socket
             .next()
@@ -497,24 +802,19 @@ impl HassClient {
             }
         }
 
-        let req = serde_json::json!({
-            "id": 1,
-            "type": "config/entity_registry/update",
-            "entity_id": entity_id,
-            "disabled_by": if disabled { Value::String("user".to_string()) } else { Value::Null },
-        });
+        req["id"] = json!(1);
         socket.send(Message::Text(req.to_string().into())).await?;
 
         while let Some(msg) = socket.next().await {
             let msg = msg?;
             if let Message::Text(text) = msg {
-                let value: Value = serde_json::from_str(&text)?;
+                let mut value: Value = serde_json::from_str(&text)?;
                 if value.get("id").and_then(Value::as_u64) == Some(1) {
                     if value.get("success").and_then(Value::as_bool) == Some(true) {
-                        return Ok(());
+                        return Ok(value["result"].take());
                     }
                     return Err(ApiError::service_error(format!(
-                        "[{}] HA entity registry update failed: {}",
+                        "[{}] HA {what} failed: {}",
                         self.backend_name, value
                     )));
                 }
@@ -522,8 +822,50 @@ impl HassClient {
         }
 
         Err(ApiError::service_error(format!(
-            "[{}] No websocket response for entity registry update",
+            "[{}] No websocket response for {what}",
             self.backend_name
         )))
     }
+
+    pub async fn set_entity_registry_disabled(
+        &self,
+        entity_id: &str,
+        disabled: bool,
+    ) -> ApiResult<()> {
+        let req = serde_json::json!({
+            "type": "config/entity_registry/update",
+            "entity_id": entity_id,
+            "disabled_by": if disabled { Value::String("user".to_string()) } else { Value::Null },
+        });
+        self.ws_command(req, "entity registry update").await?;
+        Ok(())
+    }
+
+    /// All entries in the entity registry, by entity id
+    pub async fn get_entity_registry(&self) -> ApiResult<HashMap<String, HassEntityRegistryEntry>> {
+        let req = serde_json::json!({
+            "type": "config/entity_registry/list",
+        });
+        let result = self.ws_command(req, "entity registry list").await?;
+        let entries: Vec<HassEntityRegistryEntry> = serde_json::from_value(result)?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.entity_id.clone(), entry))
+            .collect())
+    }
+
+    /// Names of all labels, by label id
+    pub async fn get_label_registry(&self) -> ApiResult<HashMap<String, String>> {
+        let req = serde_json::json!({
+            "type": "config/label_registry/list",
+        });
+        let result = self.ws_command(req, "label registry list").await?;
+        let entries: Vec<HassLabelRegistryEntry> = serde_json::from_value(result)?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.label_id, entry.name))
+            .collect())
+    }
 }
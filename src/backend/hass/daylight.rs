@@ -0,0 +1,242 @@
+use chrono::Utc;
+use maplit::btreeset;
+use serde_json::{Value, json};
+
+use hue::api::{
+    Device, DeviceArchetype, DeviceProductData, LightLevel, Metadata, RType, Resource, ResourceLink,
+};
+
+use crate::backend::hass::HassBackend;
+use crate::backend::hass::client::HassState;
+use crate::backend::hass::import::{light_level, light_level_report};
+use crate::error::ApiResult;
+use crate::model::hass::HassUiConfig;
+use crate::resource::Resources;
+
+/// The entity tracking the position of the sun
+const SUN_ENTITY: &str = "sun.sun";
+
+/// Inputs of the virtual daylight sensor, from the last seen states
+#[derive(Clone, Debug, Default)]
+pub(super) struct Daylight {
+    /// Elevation of the sun, in degrees
+    elevation: Option<f64>,
+    /// Condition of the weather entity (e.g. "cloudy")
+    condition: Option<String>,
+}
+
+impl Daylight {
+    /// Remember `state`, if it is an input of the daylight sensor. Returns
+    /// true if it was.
+    pub(super) fn observe(&mut self, state: &HassState, config: &HassUiConfig) -> bool {
+        if !config.daylight_sensor {
+            return false;
+        }
+
+        if state.entity_id == SUN_ENTITY {
+            self.elevation = sun_elevation(state);
+            true
+        } else if config.daylight_weather_entity.as_deref() == Some(state.entity_id.as_str()) {
+            self.condition = Some(state.state.clone())
+                .filter(|cond| !matches!(cond.as_str(), "unavailable" | "unknown"));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Estimated outdoor illuminance, in lux (if the sun position is known)
+    fn lux(&self) -> Option<f64> {
+        self.elevation
+            .map(|elevation| estimate_lux(elevation, self.condition.as_deref()))
+    }
+}
+
+fn sun_elevation(state: &HassState) -> Option<f64> {
+    if let Some(elevation) = state.attributes.get("elevation").and_then(Value::as_f64) {
+        return Some(elevation);
+    }
+
+    // without an elevation, assume a sun well above (or below) the horizon
+    match state.state.as_str() {
+        "above_horizon" => Some(30.0),
+        "below_horizon" => Some(-10.0),
+        _ => None,
+    }
+}
+
+/// Estimate the outdoor illuminance (in lux) for a sun at `elevation`
+/// degrees, and the weather `condition`.
+fn estimate_lux(elevation: f64, condition: Option<&str>) -> f64 {
+    // below civil twilight, it is dark
+    if elevation <= -6.0 {
+        return 0.0;
+    }
+
+    // a clear sky gives about 400 lux at sunrise, and 100000 lux at zenith
+    let clear = if elevation <= 0.0 {
+        400.0 * (elevation + 6.0) / 6.0
+    } else {
+        99600.0f64.mul_add(elevation.to_radians().sin(), 400.0)
+    };
+
+    let factor = match condition.unwrap_or_default() {
+        "partlycloudy" => 0.6,
+        "cloudy" | "windy-variant" => 0.3,
+        "fog" | "rainy" | "snowy" | "snowy-rainy" | "hail" => 0.15,
+        "pouring" | "lightning" | "lightning-rainy" => 0.1,
+        // sunny, or no weather entity
+        _ => 1.0,
+    };
+
+    clear * factor
+}
+
+impl HassBackend {
+    /// Model id of the daylight sensor device
+    pub const DAYLIGHT_MODEL_ID: &str = "hass-daylight";
+
+    /// Links of the daylight sensor device, and its light level service. All
+    /// servers share a single daylight sensor.
+    pub(super) fn daylight_links() -> (ResourceLink, ResourceLink) {
+        (
+            RType::Device.deterministic("hass:daylight:device"),
+            RType::LightLevel.deterministic("hass:daylight:light_level"),
+        )
+    }
+
+    /// Create, update or (when disabled) remove the daylight sensor
+    pub(super) fn sync_daylight(
+        &self,
+        res: &mut Resources,
+        config: &HassUiConfig,
+    ) -> ApiResult<()> {
+        let (device_link, service_link) = Self::daylight_links();
+
+        // the first backend to create the sensor keeps it up to date
+        let backend_id = self.backend_id();
+        if res
+            .backend_owner(&device_link.rid)
+            .is_some_and(|(_, owner)| owner != backend_id)
+        {
+            return Ok(());
+        }
+
+        if !config.daylight_sensor {
+            if res.get_resource(&device_link).is_ok() {
+                res.delete(&device_link)?;
+            }
+            return Ok(());
+        }
+
+        let lux = self.daylight.lux();
+        let level = light_level_report(lux.map_or(0.0, light_level));
+        let light = json!({
+            "light_level": level,
+            "light_level_valid": lux.is_some(),
+            "light_level_report": {
+                "changed": Utc::now().to_rfc3339(),
+                "light_level": level,
+            },
+        });
+
+        res.claim(&backend_id, &device_link)?;
+
+        if res.get::<Device>(&device_link).is_err() {
+            let name = "Daylight";
+            let device = Device {
+                product_data: DeviceProductData {
+                    model_id: Self::DAYLIGHT_MODEL_ID.to_string(),
+                    manufacturer_name: "Home Assistant".to_string(),
+                    product_name: name.to_string(),
+                    product_archetype: DeviceArchetype::UnknownArchetype,
+                    certified: false,
+                    software_version: "1.0.0".to_string(),
+                    hardware_platform_type: None,
+                },
+                metadata: Metadata::new(DeviceArchetype::UnknownArchetype, name),
+                services: btreeset![service_link],
+                usertest: None,
+                identify: None,
+            };
+            res.add(&device_link, Resource::Device(device))?;
+        }
+
+        if res.get::<LightLevel>(&service_link).is_err() {
+            let service = LightLevel {
+                enabled: true,
+                light,
+                owner: device_link,
+            };
+            res.add(&service_link, Resource::LightLevel(service))?;
+        } else {
+            res.update::<LightLevel>(&service_link.rid, |level| {
+                level.light = light;
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, json};
+
+    use crate::backend::hass::client::HassState;
+    use crate::backend::hass::daylight::{estimate_lux, sun_elevation};
+
+    fn sun(state: &str, elevation: Option<f64>) -> HassState {
+        let mut attributes = Map::new();
+        if let Some(elevation) = elevation {
+            attributes.insert("elevation".to_string(), json!(elevation));
+        }
+        HassState {
+            entity_id: "sun.sun".to_string(),
+            state: state.to_string(),
+            attributes,
+            last_updated: None,
+        }
+    }
+
+    fn assert_lux(lux: f64, expected: f64) {
+        assert!((lux - expected).abs() < 1e-6, "{lux} != {expected}");
+    }
+
+    #[test]
+    fn elevation_from_attribute() {
+        assert_eq!(sun_elevation(&sun("above_horizon", Some(12.5))), Some(12.5));
+        assert_eq!(sun_elevation(&sun("below_horizon", Some(-3.0))), Some(-3.0));
+    }
+
+    #[test]
+    fn elevation_from_state() {
+        assert_eq!(sun_elevation(&sun("above_horizon", None)), Some(30.0));
+        assert_eq!(sun_elevation(&sun("below_horizon", None)), Some(-10.0));
+        assert_eq!(sun_elevation(&sun("unavailable", None)), None);
+    }
+
+    #[test]
+    fn lux_in_twilight() {
+        assert_lux(estimate_lux(-10.0, None), 0.0);
+        assert_lux(estimate_lux(-6.0, None), 0.0);
+        assert_lux(estimate_lux(-3.0, None), 200.0);
+        assert_lux(estimate_lux(0.0, None), 400.0);
+    }
+
+    #[test]
+    fn lux_in_daylight() {
+        assert_lux(estimate_lux(30.0, None), 50200.0);
+        assert_lux(estimate_lux(90.0, None), 100_000.0);
+        assert_lux(estimate_lux(90.0, Some("sunny")), 100_000.0);
+    }
+
+    #[test]
+    fn lux_dimmed_by_weather() {
+        assert_lux(estimate_lux(90.0, Some("partlycloudy")), 60000.0);
+        assert_lux(estimate_lux(90.0, Some("cloudy")), 30000.0);
+        assert_lux(estimate_lux(90.0, Some("rainy")), 15000.0);
+        assert_lux(estimate_lux(90.0, Some("pouring")), 10000.0);
+        assert_lux(estimate_lux(-10.0, Some("cloudy")), 0.0);
+    }
+}
@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+
+use crate::backend::hass::HassBackend;
+use crate::backend::hass::client::{HassState, HassWs};
+use crate::error::ApiResult;
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
+
+/// Tracks whether the websocket event stream keeps up with Home Assistant.
+///
+/// Home Assistant sends a `state_changed` event for every state update, so
+/// any state updated after the last received event (allowing for some
+/// delivery delay) means events were missed.
+#[derive(Default)]
+pub(super) struct StreamHealth {
+    /// Home Assistant timestamp of the newest state change received
+    last_event_at: Option<DateTime<Utc>>,
+    stalls: u64,
+}
+
+impl StreamHealth {
+    /// Time allowed for a pong to arrive
+    pub const PING_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Seconds allowed for a state change to arrive as an event
+    pub const EVENT_GRACE_SECS: i64 = 15;
+
+    pub fn record_event(&mut self, stamp: Option<DateTime<Utc>>) {
+        if let Some(stamp) = stamp {
+            self.last_event_at = self.last_event_at.max(Some(stamp));
+        }
+    }
+
+    /// Entities updated after the last received event, which should have
+    /// arrived by now
+    pub fn missed<'a>(&self, states: &'a [HassState], now: DateTime<Utc>) -> Vec<&'a HassState> {
+        let Some(since) = self.last_event_at else {
+            return vec![];
+        };
+        states
+            .iter()
+            .filter(|state| {
+                state.last_updated.is_some_and(|at| {
+                    at > since && (now - at).num_seconds() > Self::EVENT_GRACE_SECS
+                })
+            })
+            .collect()
+    }
+
+    pub const fn record_stall(&mut self) {
+        self.stalls += 1;
+    }
+
+    pub const fn last_event_at(&self) -> Option<DateTime<Utc>> {
+        self.last_event_at
+    }
+
+    pub const fn stalls(&self) -> u64 {
+        self.stalls
+    }
+}
+
+impl HassBackend {
+    /// Ping the websocket, and start fetching the states from Home Assistant
+    /// in the background. The states are sent to `states_tx`, to be compared
+    /// with the received events by [`Self::resync_missed`].
+    pub(super) async fn check_stream_health(
+        &mut self,
+        states_tx: &mpsc::Sender<ApiResult<Vec<HassState>>>,
+    ) {
+        let Some(ws) = &mut self.ws else {
+            return;
+        };
+
+        let no_pong = ws
+            .ping_outstanding()
+            .filter(|waited| *waited > StreamHealth::PING_TIMEOUT);
+        let pinged = match no_pong {
+            None => ws.send_ping().await,
+            Some(_) => Ok(()),
+        };
+        if let Err(err) = pinged {
+            log::debug!("[{}] WS ping failed: {err}", self.name);
+            self.ws = None;
+        }
+        let latency = self.ws.as_ref().and_then(HassWs::latency);
+
+        if let Some(waited) = no_pong {
+            self.stream_stalled(&format!("no pong for {}s", waited.as_secs()), 0)
+                .await;
+        }
+
+        // fetching all states may take a while on large installations, so it
+        // is done outside of the event loop (one fetch at a time)
+        if let Ok(permit) = states_tx.clone().try_reserve_owned() {
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                permit.send(client.get_states().await);
+            });
+        }
+
+        self.update_stream_status(latency).await;
+    }
+
+    /// Compare the received events with the `states` in Home Assistant. If
+    /// the stream appears stalled, reconnect and resync the entities that
+    /// changed in the meantime.
+    pub(super) async fn resync_missed(&mut self, states: ApiResult<Vec<HassState>>) {
+        let states = match states {
+            Ok(states) => states,
+            Err(err) => {
+                log::debug!("[{}] Stream health check skipped: {err}", self.name);
+                return;
+            }
+        };
+
+        // the first check after startup only sets the baseline
+        if self.health.last_event_at().is_none() {
+            let newest = states.iter().filter_map(|state| state.last_updated).max();
+            self.health.record_event(newest);
+        }
+
+        let missed: Vec<HassState> = self
+            .health
+            .missed(&states, Utc::now())
+            .into_iter()
+            .cloned()
+            .collect();
+        if missed.is_empty() {
+            return;
+        }
+
+        self.stream_stalled(
+            &format!("{} state changes not received", missed.len()),
+            missed.len(),
+        )
+        .await;
+
+        for state in missed {
+            self.health.record_event(state.last_updated);
+            let entity_id = state.entity_id.clone();
+            if let Err(err) = self.handle_state_update(state).await {
+                log::warn!("[{}] Failed to resync {entity_id}: {err}", self.name);
+            }
+        }
+
+        let latency = self.ws.as_ref().and_then(HassWs::latency);
+        self.update_stream_status(latency).await;
+    }
+
+    /// Reconnect the stalled event stream, resyncing `resyncs` entities
+    async fn stream_stalled(&mut self, reason: &str, resyncs: usize) {
+        self.health.record_stall();
+        self.ws = None;
+        self.ui_log_entry(
+            TimelineEntry::new(
+                TimelineCategory::Connection,
+                format!(
+                    "Realtime state sync stalled ({reason}), reconnecting and resyncing {resyncs} entities"
+                ),
+            )
+            .with_level(TimelineLevel::Warning),
+        )
+        .await;
+    }
+
+    async fn update_stream_status(&self, latency: Option<Duration>) {
+        let mut ui = self.ui_state.lock().await;
+        ui.sync.ws_latency_ms =
+            latency.map(|latency| u64::try_from(latency.as_millis()).unwrap_or(u64::MAX));
+        ui.sync.last_event_at = self.health.last_event_at().map(|at| at.to_rfc3339());
+        ui.sync.ws_stalls = self.health.stalls();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta, Utc};
+    use serde_json::Map;
+
+    use crate::backend::hass::client::HassState;
+    use crate::backend::hass::health::StreamHealth;
+
+    fn state(entity_id: &str, last_updated: Option<DateTime<Utc>>) -> HassState {
+        HassState {
+            entity_id: entity_id.to_string(),
+            state: "on".to_string(),
+            attributes: Map::new(),
+            last_updated,
+        }
+    }
+
+    fn ids<'a>(states: &[&'a HassState]) -> Vec<&'a str> {
+        states
+            .iter()
+            .map(|state| state.entity_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn missed_without_baseline() {
+        let now = Utc::now();
+        let health = StreamHealth::default();
+        let states = [state("light.a", Some(now - TimeDelta::minutes(5)))];
+
+        assert!(health.missed(&states, now).is_empty());
+    }
+
+    #[test]
+    fn missed_after_last_event() {
+        let now = Utc::now();
+        let mut health = StreamHealth::default();
+        health.record_event(Some(now - TimeDelta::minutes(10)));
+
+        let states = [
+            // received before the last event
+            state("light.old", Some(now - TimeDelta::minutes(20))),
+            // updated after the last event, and not received
+            state("light.missed", Some(now - TimeDelta::minutes(5))),
+            // updated recently, the event may still be on its way
+            state("light.recent", Some(now - TimeDelta::seconds(5))),
+            state("light.unknown", None),
+        ];
+
+        assert_eq!(ids(&health.missed(&states, now)), ["light.missed"]);
+    }
+
+    #[test]
+    fn missed_grace_period() {
+        let now = Utc::now();
+        let mut health = StreamHealth::default();
+        health.record_event(Some(now - TimeDelta::minutes(1)));
+
+        let grace = TimeDelta::seconds(StreamHealth::EVENT_GRACE_SECS);
+        let states = [
+            state("light.edge", Some(now - grace)),
+            state("light.late", Some(now - grace - TimeDelta::seconds(1))),
+        ];
+
+        assert_eq!(ids(&health.missed(&states, now)), ["light.late"]);
+    }
+
+    #[test]
+    fn record_event_keeps_newest() {
+        let now = Utc::now();
+        let mut health = StreamHealth::default();
+        health.record_event(Some(now));
+        health.record_event(Some(now - TimeDelta::minutes(1)));
+        health.record_event(None);
+
+        assert_eq!(health.last_event_at(), Some(now));
+    }
+}
@@ -1,32 +1,41 @@
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use maplit::btreeset;
 use serde_json::{Value, json};
 
 use hue::api::{
-    ColorTemperature, Device, DeviceArchetype, DeviceProductData, Dimming, DimmingUpdate,
-    GroupedLight, Light, LightColor, LightMetadata, Metadata, MirekSchema, Motion, On, RType,
-    Resource, ResourceLink, Room, RoomArchetype, RoomMetadata, ZigbeeConnectivity,
-    ZigbeeConnectivityStatus,
+    Button, ButtonData, ButtonMetadata, ButtonReport, ColorTemperature, Device, DeviceArchetype,
+    DeviceProductData, Dimming, DimmingUpdate, Entertainment, EntertainmentSegment,
+    EntertainmentSegments, GroupedLight, Light, LightColor, LightLevel, LightMetadata, Metadata,
+    MirekSchema, Motion, On, RType, Resource, ResourceLink, Room, RoomArchetype, RoomMetadata,
+    Temperature, ZigbeeConnectivity, ZigbeeConnectivityStatus, Zone,
 };
 use hue::xy::XY;
 use uuid::Uuid;
 
 use crate::backend::hass::client::HassState;
-use crate::backend::hass::{
-    HassBackend, HassEntityBinding, HassEntityKind, HassLightCapabilities, HassServiceKind,
-};
-use crate::error::ApiResult;
+use crate::backend::hass::{HassBackend, HassEntityBinding, HassRoomBinding, HassRoomLight};
+use crate::backend::hass::{remote, room_groups};
+use crate::error::{ApiError, ApiResult};
 use crate::model::hass::{
-    HassEntitySummary, HassLightArchetype, HassSensorKind, HassSwitchMode, HassUiConfig,
+    HassConflictPolicy, HassEntityKind, HassEntitySummary, HassLightArchetype, HassLightBrightness,
+    HassLightCapabilities, HassRoomConfig, HassSensorKind, HassServiceKind, HassSwitchMode,
+    HassUiConfig,
 };
+use crate::model::state::AuxData;
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
 use crate::resource::Resources;
 
 #[derive(Clone, Debug)]
 struct ImportedEntity {
     entity_id: String,
+    /// Unique id from the entity registry (stays the same when renamed)
+    unique_id: Option<String>,
+    /// Label ids from the entity registry
+    labels: Vec<String>,
     name: String,
     kind: HassEntityKind,
     service_kind: HassServiceKind,
@@ -42,15 +51,125 @@ struct ImportedEntity {
     sensor_enabled: bool,
     switch_mode: Option<HassSwitchMode>,
     light_archetype: Option<HassLightArchetype>,
+    conflict_policy: HassConflictPolicy,
+    last_updated: Option<DateTime<Utc>>,
+    /// The Home Assistant device of the entity
+    device_id: Option<String>,
+    /// Value of a temperature (in degrees celsius) or light level sensor
+    measurement: Option<f64>,
+    /// Last event of an event entity
+    button_report: Option<ButtonReport>,
+    /// Hue button events an event entity can report
+    event_values: Vec<String>,
+    /// Number of an event entity, among the buttons of its device
+    control_id: Option<u32>,
+    /// Device class of the entity, in lower case
+    device_class: Option<String>,
 }
 
 impl ImportedEntity {
-    fn domain(&self) -> &'static str {
+    const fn domain(&self) -> &'static str {
+        self.kind.domain()
+    }
+
+    /// Covers of garages, gates and doors are only imported when made visible
+    /// explicitly, so turning on a room or scene does not open them
+    fn is_access_cover(&self) -> bool {
+        self.kind == HassEntityKind::Cover
+            && matches!(
+                self.device_class.as_deref(),
+                Some("garage" | "gate" | "door")
+            )
+    }
+
+    /// Is this entity included by the filters of `config`?
+    fn is_included(&self, config: &HassUiConfig) -> bool {
+        config.should_include(
+            &self.entity_id,
+            &self.name,
+            self.area_name.as_deref(),
+            &self.labels,
+            self.available,
+        ) && (!self.is_access_cover() || config.is_manually_visible(&self.entity_id))
+    }
+
+    /// Apply the per-entity settings of `config`: the alias, the switch mode
+    /// of switches and fans, and the archetype and conflict policy of lights
+    fn apply_preferences(&mut self, config: &HassUiConfig) {
+        if let Some(alias) = config.entity_alias(&self.entity_id) {
+            self.name = alias;
+        }
+        match self.kind {
+            HassEntityKind::Switch => {
+                self.switch_mode = Some(config.switch_mode(&self.entity_id));
+            }
+            HassEntityKind::Fan => {
+                self.switch_mode = Some(config.fan_mode(&self.entity_id));
+            }
+            HassEntityKind::Light
+            | HassEntityKind::Cover
+            | HassEntityKind::MediaPlayer
+            | HassEntityKind::BinarySensor
+            | HassEntityKind::Button
+            | HassEntityKind::Temperature
+            | HassEntityKind::LightLevel
+            | HassEntityKind::Event
+            | HassEntityKind::Lock
+            | HassEntityKind::Sensor
+            | HassEntityKind::Script => {}
+        }
+        if self.kind.groups_as_light(self.switch_mode) {
+            self.light_archetype = Some(config.light_archetype(&self.entity_id));
+            self.conflict_policy = config.conflict_policy(&self.entity_id);
+        }
+    }
+
+    /// Numeric sensors (other than temperatures) are only imported when they
+    /// have a threshold, scripts, buttons and events only when enabled
+    fn is_supported(&self, config: &HassUiConfig) -> bool {
         match self.kind {
-            HassEntityKind::Light => "light",
-            HassEntityKind::Switch => "switch",
-            HassEntityKind::BinarySensor => "binary_sensor",
+            HassEntityKind::Button | HassEntityKind::Script => config.import_buttons,
+            HassEntityKind::Event => config.import_remotes,
+            HassEntityKind::Sensor => config.sensor_threshold(&self.entity_id).is_some(),
+            _ => true,
+        }
+    }
+
+    /// Turn a numeric sensor into a binary one, which is on while its value
+    /// is past the configured threshold
+    fn apply_threshold(&mut self, config: &HassUiConfig) {
+        if self.domain() != "sensor" {
+            return;
         }
+        let Some(threshold) = config.sensor_threshold(&self.entity_id) else {
+            return;
+        };
+        let value = self.state.trim().parse::<f64>().ok();
+        // a temperature or light level sensor with a threshold is a binary
+        // sensor instead
+        self.kind = HassEntityKind::Sensor;
+        self.detected_sensor_kind = Some(threshold.sensor_kind);
+        self.service_kind = match threshold.sensor_kind {
+            HassSensorKind::Contact => HassServiceKind::Contact,
+            HassSensorKind::Motion | HassSensorKind::Ignore => HassServiceKind::Motion,
+        };
+        self.available = value.is_some();
+        self.on = value.is_some_and(|value| threshold.is_active(value));
+    }
+
+    /// Parse the value of a temperature sensor (in degrees celsius) or of a
+    /// light level sensor (on the hue scale)
+    fn apply_measurement(&mut self, config: &HassUiConfig) {
+        let value = self.state.trim().parse::<f64>().ok();
+        self.measurement = match self.kind {
+            HassEntityKind::Temperature => {
+                value.map(|value| celsius(value, config.hass_temperature_unit.as_deref()))
+            }
+            HassEntityKind::LightLevel => value.map(light_level),
+            _ => return,
+        };
+        self.available = value.is_some();
+        self.sensor_enabled = config.sensor_enabled(&self.entity_id);
     }
 
     fn mapped_type(&self) -> String {
@@ -65,8 +184,43 @@ impl ImportedEntity {
             }
             HassServiceKind::Motion => "motion".to_string(),
             HassServiceKind::Contact => "contact".to_string(),
+            HassServiceKind::Button => "button".to_string(),
+            HassServiceKind::Temperature => "temperature".to_string(),
+            HassServiceKind::LightLevel => "light_level".to_string(),
         }
     }
+
+    /// Hash of everything that ends up in the Hue resources for this entity,
+    /// used to skip unchanged entities during a full sync.
+    ///
+    /// `last_updated` is deliberately left out, since it changes even when
+    /// nothing else does.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.entity_id.hash(&mut hasher);
+        self.name.hash(&mut hasher);
+        self.kind.hash(&mut hasher);
+        self.service_kind.hash(&mut hasher);
+        self.state.hash(&mut hasher);
+        self.available.hash(&mut hasher);
+        self.on.hash(&mut hasher);
+        self.brightness.map(f64::to_bits).hash(&mut hasher);
+        self.xy_color
+            .map(|xy| (xy.x.to_bits(), xy.y.to_bits()))
+            .hash(&mut hasher);
+        self.color_temp.hash(&mut hasher);
+        self.measurement.map(f64::to_bits).hash(&mut hasher);
+        self.area_name.hash(&mut hasher);
+        self.capabilities.hash(&mut hasher);
+        self.detected_sensor_kind.hash(&mut hasher);
+        self.sensor_enabled.hash(&mut hasher);
+        self.switch_mode.hash(&mut hasher);
+        self.light_archetype.hash(&mut hasher);
+        self.conflict_policy.hash(&mut hasher);
+        self.event_values.hash(&mut hasher);
+        self.control_id.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 fn value_to_f64(value: &Value) -> Option<f64> {
@@ -143,6 +297,71 @@ fn parse_light_capabilities(state: &HassState) -> HassLightCapabilities {
     }
 }
 
+/// Covers that can be moved to a position, can be dimmed
+fn parse_cover_capabilities(state: &HassState) -> HassLightCapabilities {
+    const SUPPORT_SET_POSITION: u64 = 4;
+
+    let supported_features = state
+        .attributes
+        .get("supported_features")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+
+    HassLightCapabilities {
+        supports_brightness: state.attributes.contains_key("current_position")
+            || supported_features & SUPPORT_SET_POSITION != 0,
+        supports_color: false,
+        supports_color_temp: false,
+    }
+}
+
+/// Fans that can change speed, can be dimmed
+fn parse_fan_capabilities(state: &HassState) -> HassLightCapabilities {
+    const SUPPORT_SET_SPEED: u64 = 1;
+
+    let supported_features = state
+        .attributes
+        .get("supported_features")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+
+    HassLightCapabilities {
+        supports_brightness: state.attributes.contains_key("percentage")
+            || supported_features & SUPPORT_SET_SPEED != 0,
+        supports_color: false,
+        supports_color_temp: false,
+    }
+}
+
+/// Is an entity of `kind` in `state` shown as on? Covers are on while (at
+/// least partially) open.
+pub(super) fn entity_on(kind: HassEntityKind, state: &str) -> bool {
+    match kind {
+        HassEntityKind::Cover => matches!(state, "open" | "opening"),
+        // media players are on while idle or playing too
+        HassEntityKind::MediaPlayer => {
+            !matches!(state, "off" | "standby" | "unavailable" | "unknown")
+        }
+        HassEntityKind::BinarySensor => state == "on",
+        // locks are contact sensors, that are open while not locked
+        HassEntityKind::Lock => matches!(
+            state,
+            "unlocked" | "unlocking" | "open" | "opening" | "jammed"
+        ),
+        // numeric sensors are on while past their threshold
+        HassEntityKind::Temperature
+        | HassEntityKind::LightLevel
+        | HassEntityKind::Event
+        | HassEntityKind::Sensor => false,
+        // scripts are on while running
+        HassEntityKind::Light
+        | HassEntityKind::Switch
+        | HassEntityKind::Fan
+        | HassEntityKind::Button
+        | HassEntityKind::Script => state == "on",
+    }
+}
+
 fn detected_sensor_kind(state: &HassState) -> HassSensorKind {
     match state
         .attributes
@@ -158,7 +377,39 @@ fn detected_sensor_kind(state: &HassState) -> HassSensorKind {
     }
 }
 
+pub(super) fn device_class(state: &HassState) -> Option<&str> {
+    state.attributes.get("device_class").and_then(Value::as_str)
+}
+
+/// Convert a temperature in `unit` to degrees celsius
+fn celsius(value: f64, unit: Option<&str>) -> f64 {
+    if unit.is_some_and(|unit| unit.trim_start_matches('°').eq_ignore_ascii_case("F")) {
+        (value - 32.0) * 5.0 / 9.0
+    } else {
+        value
+    }
+}
+
+/// Convert an illuminance in lux to the hue light level scale
+/// (10000 * log10(lux) + 1)
+pub(super) fn light_level(lux: f64) -> f64 {
+    if lux < 1.0 {
+        return 0.0;
+    }
+    10000.0f64.mul_add(lux.log10(), 1.0).round()
+}
+
+/// A light level (see [`light_level`]) as the integer of hue reports
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(super) fn light_level_report(level: f64) -> u32 {
+    level.round().clamp(0.0, f64::from(u32::MAX)) as u32
+}
+
 fn parse_imported_entity(state: &HassState, area_name: Option<String>) -> Option<ImportedEntity> {
+    if room_groups::is_room_group(state) {
+        return None;
+    }
+
     let (domain, _) = state.entity_id.split_once('.')?;
     let (kind, service_kind, capabilities, detected_kind) = match domain {
         "light" => (
@@ -173,6 +424,66 @@ fn parse_imported_entity(state: &HassState, area_name: Option<String>) -> Option
             HassLightCapabilities::default(),
             None,
         ),
+        "fan" => (
+            HassEntityKind::Fan,
+            HassServiceKind::Switch,
+            parse_fan_capabilities(state),
+            None,
+        ),
+        "media_player" => (
+            HassEntityKind::MediaPlayer,
+            HassServiceKind::Switch,
+            HassLightCapabilities::default(),
+            None,
+        ),
+        "cover" => (
+            HassEntityKind::Cover,
+            HassServiceKind::Light,
+            parse_cover_capabilities(state),
+            None,
+        ),
+        "sensor" if device_class(state) == Some("temperature") => (
+            HassEntityKind::Temperature,
+            HassServiceKind::Temperature,
+            HassLightCapabilities::default(),
+            None,
+        ),
+        "sensor" if device_class(state) == Some("illuminance") => (
+            HassEntityKind::LightLevel,
+            HassServiceKind::LightLevel,
+            HassLightCapabilities::default(),
+            None,
+        ),
+        "sensor" => (
+            HassEntityKind::Sensor,
+            HassServiceKind::Motion,
+            HassLightCapabilities::default(),
+            Some(HassSensorKind::Ignore),
+        ),
+        "script" => (
+            HassEntityKind::Script,
+            HassServiceKind::Button,
+            HassLightCapabilities::default(),
+            None,
+        ),
+        "button" => (
+            HassEntityKind::Button,
+            HassServiceKind::Button,
+            HassLightCapabilities::default(),
+            None,
+        ),
+        "event" => (
+            HassEntityKind::Event,
+            HassServiceKind::Button,
+            HassLightCapabilities::default(),
+            None,
+        ),
+        "lock" => (
+            HassEntityKind::Lock,
+            HassServiceKind::Contact,
+            HassLightCapabilities::default(),
+            Some(HassSensorKind::Contact),
+        ),
         "binary_sensor" => {
             let detected = detected_sensor_kind(state);
             let sk = match detected {
@@ -190,8 +501,15 @@ fn parse_imported_entity(state: &HassState, area_name: Option<String>) -> Option
         _ => return None,
     };
 
-    let available = !matches!(state.state.as_str(), "unavailable" | "unknown");
-    let on = available && state.state == "on";
+    // event entities are "unknown" until their first event
+    let available =
+        state.state != "unavailable" && (kind == HassEntityKind::Event || state.state != "unknown");
+    let on = available && entity_on(kind, &state.state);
+    let (button_report, event_values) = if kind == HassEntityKind::Event {
+        (remote::button_report(state), remote::event_values(state))
+    } else {
+        (None, vec![])
+    };
 
     let name = state
         .attributes
@@ -200,14 +518,24 @@ fn parse_imported_entity(state: &HassState, area_name: Option<String>) -> Option
         .unwrap_or(&state.entity_id)
         .to_string();
 
-    let brightness = if matches!(kind, HassEntityKind::Light) && capabilities.supports_brightness {
-        state
+    let brightness = match kind {
+        HassEntityKind::Light if capabilities.supports_brightness => state
             .attributes
             .get("brightness")
             .and_then(value_to_f64)
-            .map(|x| x.clamp(0.0, 255.0))
-    } else {
-        None
+            .map(|x| x.clamp(0.0, 255.0)),
+        // cover positions and fan speeds are percentages
+        HassEntityKind::Cover if capabilities.supports_brightness => state
+            .attributes
+            .get("current_position")
+            .and_then(value_to_f64)
+            .map(|x| (x * 255.0 / 100.0).clamp(0.0, 255.0)),
+        HassEntityKind::Fan if capabilities.supports_brightness => state
+            .attributes
+            .get("percentage")
+            .and_then(value_to_f64)
+            .map(|x| (x * 255.0 / 100.0).clamp(0.0, 255.0)),
+        _ => None,
     };
     let xy_color = if matches!(kind, HassEntityKind::Light) && capabilities.supports_color {
         state.attributes.get("xy_color").and_then(parse_xy_color)
@@ -226,6 +554,8 @@ fn parse_imported_entity(state: &HassState, area_name: Option<String>) -> Option
 
     Some(ImportedEntity {
         entity_id: state.entity_id.clone(),
+        unique_id: None,
+        labels: vec![],
         name,
         kind,
         service_kind,
@@ -239,12 +569,29 @@ fn parse_imported_entity(state: &HassState, area_name: Option<String>) -> Option
         capabilities,
         detected_sensor_kind: detected_kind,
         sensor_enabled: true,
-        switch_mode: if matches!(kind, HassEntityKind::Switch) {
-            Some(HassSwitchMode::Plug)
-        } else {
-            None
+        switch_mode: match kind {
+            HassEntityKind::Switch | HassEntityKind::MediaPlayer => Some(HassSwitchMode::Plug),
+            HassEntityKind::Fan => Some(HassSwitchMode::Light),
+            HassEntityKind::Light
+            | HassEntityKind::Cover
+            | HassEntityKind::BinarySensor
+            | HassEntityKind::Button
+            | HassEntityKind::Temperature
+            | HassEntityKind::LightLevel
+            | HassEntityKind::Event
+            | HassEntityKind::Lock
+            | HassEntityKind::Sensor
+            | HassEntityKind::Script => None,
         },
         light_archetype: None,
+        conflict_policy: HassConflictPolicy::default(),
+        last_updated: state.last_updated,
+        device_id: None,
+        measurement: None,
+        button_report,
+        event_values,
+        control_id: None,
+        device_class: device_class(state).map(str::to_ascii_lowercase),
     })
 }
 
@@ -276,25 +623,50 @@ fn device_archetype(archetype: HassLightArchetype) -> DeviceArchetype {
     }
 }
 
+/// The inverse of [`device_archetype`], for archetypes chosen in a hue app
+pub(super) const fn hass_light_archetype(
+    archetype: &DeviceArchetype,
+) -> Option<HassLightArchetype> {
+    let res = match archetype {
+        DeviceArchetype::ClassicBulb => HassLightArchetype::ClassicBulb,
+        DeviceArchetype::SultanBulb => HassLightArchetype::SultanBulb,
+        DeviceArchetype::CandleBulb => HassLightArchetype::CandleBulb,
+        DeviceArchetype::SpotBulb => HassLightArchetype::SpotBulb,
+        DeviceArchetype::VintageBulb => HassLightArchetype::VintageBulb,
+        DeviceArchetype::FloodBulb => HassLightArchetype::FloodBulb,
+        DeviceArchetype::CeilingRound => HassLightArchetype::CeilingRound,
+        DeviceArchetype::CeilingSquare => HassLightArchetype::CeilingSquare,
+        DeviceArchetype::PendantRound => HassLightArchetype::PendantRound,
+        DeviceArchetype::PendantLong => HassLightArchetype::PendantLong,
+        DeviceArchetype::FloorShade => HassLightArchetype::FloorShade,
+        DeviceArchetype::FloorLantern => HassLightArchetype::FloorLantern,
+        DeviceArchetype::TableShade => HassLightArchetype::TableShade,
+        DeviceArchetype::WallSpot => HassLightArchetype::WallSpot,
+        DeviceArchetype::WallLantern => HassLightArchetype::WallLantern,
+        DeviceArchetype::RecessedCeiling => HassLightArchetype::RecessedCeiling,
+        DeviceArchetype::HueLightstrip => HassLightArchetype::HueLightstrip,
+        DeviceArchetype::HuePlay => HassLightArchetype::HuePlay,
+        DeviceArchetype::HueGo => HassLightArchetype::HueGo,
+        DeviceArchetype::HueBloom => HassLightArchetype::HueBloom,
+        DeviceArchetype::HueIris => HassLightArchetype::HueIris,
+        DeviceArchetype::HueSigne => HassLightArchetype::HueSigne,
+        DeviceArchetype::HueTube => HassLightArchetype::HueTube,
+        _ => return None,
+    };
+    Some(res)
+}
+
 fn light_archetype(imported: &ImportedEntity) -> DeviceArchetype {
-    match imported.kind {
-        HassEntityKind::Light => device_archetype(
+    if imported.kind.groups_as_light(imported.switch_mode) {
+        device_archetype(
             imported
                 .light_archetype
                 .unwrap_or(HassLightArchetype::ClassicBulb),
-        ),
-        HassEntityKind::Switch => {
-            if imported.switch_mode == Some(HassSwitchMode::Light) {
-                device_archetype(
-                    imported
-                        .light_archetype
-                        .unwrap_or(HassLightArchetype::ClassicBulb),
-                )
-            } else {
-                DeviceArchetype::Plug
-            }
-        }
-        HassEntityKind::BinarySensor => DeviceArchetype::UnknownArchetype,
+        )
+    } else if imported.kind.is_sensor() {
+        DeviceArchetype::UnknownArchetype
+    } else {
+        DeviceArchetype::Plug
     }
 }
 
@@ -328,13 +700,42 @@ fn ieee_like_from_uuid(id: &Uuid) -> String {
     )
 }
 
+const fn connectivity_status(available: bool) -> ZigbeeConnectivityStatus {
+    if available {
+        ZigbeeConnectivityStatus::Connected
+    } else {
+        ZigbeeConnectivityStatus::ConnectivityIssue
+    }
+}
+
+/// Entertainment service for lights rendered in software, so the Hue app
+/// lets them join entertainment areas
+fn fallback_entertainment(device: ResourceLink, light: ResourceLink) -> Entertainment {
+    Entertainment {
+        equalizer: false,
+        owner: device,
+        proxy: false,
+        renderer: true,
+        max_streams: None,
+        renderer_reference: Some(light),
+        segments: Some(EntertainmentSegments {
+            configurable: false,
+            max_segments: 1,
+            segments: vec![EntertainmentSegment {
+                start: 0,
+                length: 1,
+            }],
+        }),
+    }
+}
+
 fn apply_light_state(light: &mut Light, imported: &ImportedEntity) {
     light.metadata.name.clone_from(&imported.name);
     light.metadata.archetype = light_archetype(imported);
     light.on = On { on: imported.on };
 
     match imported.kind {
-        HassEntityKind::Light => {
+        HassEntityKind::Light | HassEntityKind::Cover => {
             // Hue clients infer supported controls from field presence, not just capability flags.
             // Home Assistant often omits brightness/color/ct values when the light is off.
             // Keep the last known values (or set a sane default) so the Hue app still shows controls.
@@ -389,7 +790,33 @@ fn apply_light_state(light: &mut Light, imported: &ImportedEntity) {
                 light.color_temperature_delta = None;
             }
         }
-        HassEntityKind::Switch | HassEntityKind::BinarySensor => {
+        HassEntityKind::Fan => {
+            // fans exposed as lights are dimmed by changing their speed
+            let dimmable = imported.switch_mode == Some(HassSwitchMode::Light)
+                && imported.capabilities.supports_brightness;
+            let brightness = imported
+                .brightness
+                .map(|b| (b / 255.0 * 100.0).clamp(0.0, 100.0))
+                .or_else(|| light.dimming.as_ref().map(|dim| dim.brightness))
+                .unwrap_or(100.0);
+            light.dimming = dimmable.then_some(Dimming {
+                brightness,
+                min_dim_level: None,
+            });
+            light.color = None;
+            light.color_temperature = None;
+            light.color_temperature_delta = None;
+        }
+        HassEntityKind::Switch
+        | HassEntityKind::MediaPlayer
+        | HassEntityKind::BinarySensor
+        | HassEntityKind::Button
+        | HassEntityKind::Temperature
+        | HassEntityKind::LightLevel
+        | HassEntityKind::Event
+        | HassEntityKind::Lock
+        | HassEntityKind::Sensor
+        | HassEntityKind::Script => {
             light.dimming = None;
             light.color = None;
             light.color_temperature = None;
@@ -398,6 +825,81 @@ fn apply_light_state(light: &mut Light, imported: &ImportedEntity) {
     }
 }
 
+/// The hue temperature report of a temperature sensor
+fn temperature_value(imported: &ImportedEntity) -> Value {
+    let temperature = imported.measurement.unwrap_or_default();
+    json!({
+        "temperature": temperature,
+        "temperature_valid": imported.available,
+        "temperature_report": {
+            "changed": imported.last_updated.unwrap_or_else(Utc::now).to_rfc3339(),
+            "temperature": temperature,
+        },
+    })
+}
+
+/// The hue light level report of an illuminance sensor
+fn light_level_value(imported: &ImportedEntity) -> Value {
+    let level = light_level_report(imported.measurement.unwrap_or_default());
+    json!({
+        "light_level": level,
+        "light_level_valid": imported.available,
+        "light_level_report": {
+            "changed": imported.last_updated.unwrap_or_else(Utc::now).to_rfc3339(),
+            "light_level": level,
+        },
+    })
+}
+
+fn make_button(device_link: ResourceLink, imported: &ImportedEntity) -> Button {
+    let event_values = if imported.kind == HassEntityKind::Event {
+        json!(imported.event_values)
+    } else {
+        json!(["initial_press", "short_release"])
+    };
+    Button {
+        owner: device_link,
+        metadata: ButtonMetadata {
+            control_id: imported.control_id.unwrap_or(1),
+        },
+        button: ButtonData {
+            last_event: imported
+                .button_report
+                .as_ref()
+                .map(|report| json!(report.event)),
+            button_report: imported.button_report.clone(),
+            repeat_interval: None,
+            event_values: Some(event_values),
+        },
+    }
+}
+
+/// Number the event entities of each Home Assistant device (in entity id
+/// order), as the buttons of a remote
+fn assign_control_ids(imported: &mut HashMap<String, ImportedEntity>) {
+    let mut buttons = BTreeMap::<String, BTreeSet<String>>::new();
+    for entity in imported.values() {
+        if let Some(device_id) = entity
+            .device_id
+            .as_ref()
+            .filter(|_| entity.kind == HassEntityKind::Event)
+        {
+            buttons
+                .entry(device_id.clone())
+                .or_default()
+                .insert(entity.entity_id.clone());
+        }
+    }
+
+    for entity_ids in buttons.values() {
+        for (control_id, entity_id) in (1..).zip(entity_ids) {
+            if let Some(entity) = imported.get_mut(entity_id) {
+                entity.control_id = Some(control_id);
+            }
+        }
+    }
+}
+
 fn make_contact_resource(imported: &ImportedEntity, device_link: ResourceLink) -> Value {
     json!({
         "owner": device_link,
@@ -410,19 +912,43 @@ fn make_contact_resource(imported: &ImportedEntity, device_link: ResourceLink) -
     })
 }
 
+/// Names of the labels `ids`, using the label registry `names`. Labels
+/// missing from the registry keep their id.
+fn label_names(ids: &[String], names: &HashMap<String, String>) -> Vec<String> {
+    ids.iter()
+        .map(|id| names.get(id).unwrap_or(id).clone())
+        .collect()
+}
+
 impl HassBackend {
-    fn links_for_entity(
+    /// Key for the deterministic resource ids of an entity. Derived from the
+    /// unique id if known, so the ids survive renaming the entity.
+    fn entity_key(&self, entity_id: &str, unique_id: Option<&str>) -> String {
+        unique_id.map_or_else(
+            || format!("hass:{}:{}", self.name, entity_id),
+            |unique_id| format!("hass:{}:uid:{}", self.name, unique_id),
+        )
+    }
+
+    /// Deterministic links for the entity with resource key `key`
+    fn links_for_key(
         &self,
-        entity_id: &str,
+        key: &str,
         service_kind: HassServiceKind,
     ) -> (ResourceLink, ResourceLink) {
-        let key = format!("hass:{}:{}", self.name, entity_id);
         let service = match service_kind {
             HassServiceKind::Light | HassServiceKind::Switch => {
                 RType::Light.deterministic(format!("{key}:light"))
             }
             HassServiceKind::Motion => RType::Motion.deterministic(format!("{key}:motion")),
             HassServiceKind::Contact => RType::Contact.deterministic(format!("{key}:contact")),
+            HassServiceKind::Button => RType::Button.deterministic(format!("{key}:button")),
+            HassServiceKind::Temperature => {
+                RType::Temperature.deterministic(format!("{key}:temperature"))
+            }
+            HassServiceKind::LightLevel => {
+                RType::LightLevel.deterministic(format!("{key}:light_level"))
+            }
         };
         (
             RType::Device.deterministic(format!("{key}:device")),
@@ -430,6 +956,260 @@ impl HassBackend {
         )
     }
 
+    fn links_for_entity(
+        &self,
+        entity_id: &str,
+        service_kind: HassServiceKind,
+    ) -> (ResourceLink, ResourceLink) {
+        self.links_for_key(&self.entity_key(entity_id, None), service_kind)
+    }
+
+    /// Links for an imported entity.
+    ///
+    /// Entities that are already bound keep their links (states from older
+    /// versions used ids derived from the entity id). Only the service link
+    /// changes, if the service kind changed.
+    pub(super) fn links_for_imported(
+        &self,
+        imported: &ImportedEntity,
+    ) -> (ResourceLink, ResourceLink) {
+        let key = self.entity_key(&imported.entity_id, imported.unique_id.as_deref());
+        let (device_link, service_link) = self.links_for_key(&key, imported.service_kind);
+
+        let (device_link, service_link) = match self.entity_map.get(&imported.entity_id) {
+            Some(binding) if binding.service_kind == imported.service_kind => {
+                (binding.device_link, binding.service_link)
+            }
+            Some(binding) => (binding.device_link, service_link),
+            None => (device_link, service_link),
+        };
+
+        (
+            self.host_device(imported).unwrap_or(device_link),
+            service_link,
+        )
+    }
+
+    /// The device `imported` shares with other entities of the same Home
+    /// Assistant device, if any: light level sensors are attached to the
+    /// device of a motion sensor, and the event entities of a remote are the
+    /// buttons of a single device.
+    fn host_device(&self, imported: &ImportedEntity) -> Option<ResourceLink> {
+        let device_id = imported.device_id.as_ref()?;
+        match imported.service_kind {
+            HassServiceKind::LightLevel => self.motion_devices.get(device_id).copied(),
+            HassServiceKind::Button if imported.kind == HassEntityKind::Event => {
+                Some(self.remote_device_link(device_id))
+            }
+            _ => None,
+        }
+    }
+
+    /// Register the devices of all motion sensors in `imported`, before any
+    /// entity is synced, so light level sensors find their host regardless
+    /// of the order of the entities.
+    fn resolve_hosts(&mut self, imported: &HashMap<String, ImportedEntity>) {
+        for imported in imported.values() {
+            if imported.service_kind != HassServiceKind::Motion {
+                continue;
+            }
+            if let Some(device_id) = &imported.device_id {
+                let (device_link, _) = self.links_for_imported(imported);
+                self.motion_devices.insert(device_id.clone(), device_link);
+            }
+        }
+    }
+
+    /// Is `device_link` the device of an entity other than `entity_id`? (a
+    /// light level sensor attached to a motion sensor, or a remote button)
+    fn is_hosted(&self, entity_id: &str, device_link: &ResourceLink) -> bool {
+        self.device_map
+            .get(&device_link.rid)
+            .is_some_and(|owner| owner != entity_id)
+    }
+
+    /// Previous entity id of `imported`, if it was renamed in Home Assistant
+    /// (found by its unique id).
+    fn renamed_from(&self, imported: &ImportedEntity) -> Option<String> {
+        let unique_id = imported.unique_id.as_ref()?;
+        if self.entity_map.contains_key(&imported.entity_id) {
+            return None;
+        }
+        self.entity_map
+            .values()
+            .find(|binding| binding.unique_id.as_ref() == Some(unique_id))
+            .map(|binding| binding.entity_id.clone())
+    }
+
+    /// Renamed entities in `parsed`, as (old, new) entity id pairs
+    fn detect_renames(&self, parsed: &[ImportedEntity]) -> Vec<(String, String)> {
+        let current = parsed
+            .iter()
+            .map(|imported| imported.entity_id.as_str())
+            .collect::<HashSet<_>>();
+
+        parsed
+            .iter()
+            .filter_map(|imported| {
+                let old_id = self.renamed_from(imported)?;
+                (!current.contains(old_id.as_str())).then(|| (old_id, imported.entity_id.clone()))
+            })
+            .collect()
+    }
+
+    /// If `imported` was renamed in Home Assistant, move the binding of its
+    /// old entity id to the new one. This keeps the Hue resources (and so the
+    /// rooms and scenes referring to them) in place.
+    fn follow_rename(&mut self, imported: &ImportedEntity) {
+        let Some(old_id) = self.renamed_from(imported) else {
+            return;
+        };
+        let Some(mut binding) = self.entity_map.remove(&old_id) else {
+            return;
+        };
+
+        log::info!(
+            "[{}] Entity {old_id} was renamed to {}",
+            self.name,
+            imported.entity_id
+        );
+        self.fingerprints.remove(&old_id);
+        binding.entity_id.clone_from(&imported.entity_id);
+        self.entity_map.insert(imported.entity_id.clone(), binding);
+    }
+
+    /// Rooms of other backends
+    fn is_foreign_room(&self, res: &Resources, id: Uuid) -> bool {
+        let backend_id = self.backend_id();
+        res.get_id::<Room>(id).is_ok()
+            && res
+                .backend_owner(&id)
+                .is_some_and(|(_, owner)| owner != backend_id)
+    }
+
+    /// Room of another backend to place the devices of `room` in, as selected
+    /// with `merge_into`.
+    ///
+    /// Returns the links of the room and its grouped light.
+    fn merge_target(
+        &self,
+        res: &Resources,
+        room: &HassRoomConfig,
+    ) -> Option<(ResourceLink, ResourceLink)> {
+        let target = room
+            .merge_into
+            .filter(|id| self.is_foreign_room(res, *id))?;
+
+        let grouped_light = res
+            .get_id::<Room>(target)
+            .ok()?
+            .services
+            .iter()
+            .find(|svc| svc.rtype == RType::GroupedLight)
+            .copied()?;
+
+        Some((RType::Room.link_to(target), grouped_light))
+    }
+
+    /// Room of another backend with the same name as `room` (ignoring case)
+    fn duplicate_of(&self, res: &Resources, room: &HassRoomConfig) -> Option<Uuid> {
+        let name = room.name.trim().to_lowercase();
+        res.get_resource_ids_by_type(RType::Room)
+            .into_iter()
+            .filter(|id| self.is_foreign_room(res, *id))
+            .filter(|id| {
+                res.get_id::<Room>(*id)
+                    .is_ok_and(|hue_room| hue_room.metadata.name.trim().to_lowercase() == name)
+            })
+            .min()
+    }
+
+    /// Queue a timeline warning for rooms that were merged into a room of
+    /// another backend since the last room update, and (once) for rooms that
+    /// duplicate a room of another backend, without being merged into it
+    fn note_room_merges(
+        &mut self,
+        res: &Resources,
+        config: &HassUiConfig,
+        wanted: &HashMap<String, HassRoomBinding>,
+    ) {
+        for room in &config.rooms {
+            let Some(binding) = wanted.get(&room.id) else {
+                continue;
+            };
+
+            let message = if binding.merged {
+                let known = self
+                    .room_map
+                    .get(&room.id)
+                    .is_some_and(|old| old.merged && old.room_link == binding.room_link);
+                if known {
+                    continue;
+                }
+
+                let owner = res
+                    .backend_owner(&binding.room_link.rid)
+                    .map_or_else(|| "another backend".to_string(), |(_, id)| id.to_string());
+                format!("Room {} merged into a room of {owner}", binding.room_name)
+            } else {
+                if self.duplicate_of(res, room).is_none() {
+                    self.room_duplicates.remove(&room.id);
+                    continue;
+                }
+                if !self.room_duplicates.insert(room.id.clone()) {
+                    continue;
+                }
+
+                format!(
+                    "Room {} also exists on another backend. Select it with merge_into to merge them",
+                    binding.room_name
+                )
+            };
+
+            log::warn!("[{}] {message}", self.name);
+            self.room_notices.push(
+                TimelineEntry::new(TimelineCategory::Config, message)
+                    .with_level(TimelineLevel::Warning),
+            );
+        }
+    }
+
+    /// Show room merges found by [`Self::ensure_rooms`] in the activity timeline
+    pub(super) async fn flush_room_notices(&mut self) {
+        for entry in std::mem::take(&mut self.room_notices) {
+            self.ui_log_entry(entry).await;
+        }
+    }
+
+    /// Remove our devices from `room`, keeping devices of other backends
+    fn release_room_devices(&self, res: &mut Resources, room: &ResourceLink) -> ApiResult<()> {
+        res.update::<Room>(&room.rid, |hue_room| {
+            hue_room
+                .children
+                .retain(|dev| self.lookup_binding_by_device(dev).is_none());
+        })
+    }
+
+    /// Set the devices of a room. The devices of other backends are kept, if
+    /// the room was merged into a room of another backend.
+    fn set_room_children(
+        &self,
+        res: &mut Resources,
+        room: &HassRoomBinding,
+        children: BTreeSet<ResourceLink>,
+    ) -> ApiResult<()> {
+        res.update::<Room>(&room.room_link.rid, |hue_room| {
+            if room.merged {
+                hue_room
+                    .children
+                    .retain(|dev| self.lookup_binding_by_device(dev).is_none());
+                hue_room.children.extend(children);
+            } else {
+                hue_room.children = children;
+            }
+        })
+    }
+
     pub(super) fn ensure_rooms(
         &mut self,
         res: &mut Resources,
@@ -439,16 +1219,55 @@ impl HassBackend {
             .rooms
             .iter()
             .map(|room| {
-                let binding = self.room_binding(room);
+                let mut binding = self.room_binding(room);
+                if let Some((room_link, grouped_light_link)) = self.merge_target(res, room) {
+                    binding.room_link = room_link;
+                    binding.grouped_light_link = grouped_light_link;
+                    binding.merged = true;
+                }
                 (room.id.clone(), binding)
             })
             .collect::<HashMap<_, _>>();
 
+        self.note_room_merges(res, config, &wanted);
+
+        // take our devices back from rooms we are no longer merged into
+        let unmerged = self
+            .room_map
+            .values()
+            .filter(|old| old.merged)
+            .filter(|old| {
+                wanted
+                    .get(&old.room_id)
+                    .is_none_or(|new| new.room_link != old.room_link)
+            })
+            .map(|old| old.room_link)
+            .collect::<Vec<_>>();
+        for link in unmerged {
+            if res.get::<Room>(&link).is_ok() {
+                self.release_room_devices(res, &link)?;
+            }
+        }
+
         for room in &config.rooms {
             let binding = wanted
                 .get(&room.id)
                 .expect("wanted map must contain configured room");
 
+            if binding.merged {
+                // remove the room we created before the merge
+                let (own_room, _) = self.room_links_for_id(&room.id);
+                if res.get::<Room>(&own_room).is_ok() {
+                    res.delete(&own_room)?;
+                }
+                continue;
+            }
+
+            if let Err(err) = res.claim(&self.backend_id(), &binding.room_link) {
+                log::warn!("[{}] Skipping room {}: {err}", self.name, binding.room_name);
+                continue;
+            }
+
             if res.get::<Room>(&binding.room_link).is_err() {
                 let room = Room {
                     children: BTreeSet::new(),
@@ -474,19 +1293,18 @@ impl HassBackend {
             }
         }
 
-        for id in res.get_resource_ids_by_type(RType::BridgeHome) {
-            res.update(&id, |bh: &mut hue::api::BridgeHome| {
-                bh.children.extend(wanted.values().map(|x| x.room_link));
-            })?;
+        for binding in wanted.values().filter(|x| !x.merged) {
+            res.attach_room(&binding.room_link)?;
         }
 
         let stale_rooms = self
             .room_map
             .iter()
-            .filter(|(room_id, _)| !wanted.contains_key(*room_id))
+            .filter(|(room_id, binding)| !binding.merged && !wanted.contains_key(*room_id))
             .map(|(_, binding)| binding.clone())
             .collect::<Vec<_>>();
         for stale in stale_rooms {
+            res.detach_room(&stale.room_link)?;
             if let Err(err) = res.delete(&stale.room_link) {
                 log::warn!(
                     "[{}] Failed to delete stale room {}: {}",
@@ -544,12 +1362,25 @@ impl HassBackend {
                 .get(&room.room_id)
                 .cloned()
                 .unwrap_or_default();
-            res.update::<Room>(&room.room_link.rid, |hue_room| {
-                hue_room.metadata.name.clone_from(&room.room_name);
-                hue_room.children = children;
-            })?;
+            if !room.merged {
+                res.update::<Room>(&room.room_link.rid, |hue_room| {
+                    hue_room.metadata.name.clone_from(&room.room_name);
+                })?;
+            }
+            self.set_room_children(&mut res, room, children)?;
+        }
+
+        // lights may have moved, and the brightness strategy changed
+        for (entity_id, light) in &mut self.room_lights {
+            if let Some(room_id) = entity_room.get(entity_id) {
+                light.room_id.clone_from(room_id);
+            }
+        }
+        for room_id in self.room_map.keys() {
+            self.refresh_room_state(&ui_config, room_id, &mut res)?;
         }
         drop(res);
+        self.flush_room_notices().await;
 
         {
             let mut ui = self.ui_state.lock().await;
@@ -559,27 +1390,54 @@ impl HassBackend {
                     summary.room_name = ui_config.room_name(room_id);
                 }
             }
+            ui.reindex();
         }
 
-        self.ui_log("Updated room metadata and assignments from UI config")
-            .await;
+        self.ui_log(
+            TimelineCategory::Config,
+            "Updated room metadata and assignments from UI config",
+        )
+        .await;
         Ok(())
     }
 
-    fn sync_single_entity(
+    pub(super) fn sync_single_entity(
         &mut self,
         imported: &ImportedEntity,
         res: &mut Resources,
     ) -> ApiResult<()> {
-        let (device_link, service_link) =
-            self.links_for_entity(&imported.entity_id, imported.service_kind);
-        let link_zbc = RType::ZigbeeConnectivity
-            .deterministic(format!("hass:{}:{}:zbc", self.name, imported.entity_id));
+        self.follow_rename(imported);
+        let (device_link, service_link) = self.links_for_imported(imported);
+
+        // claim first, so a conflict leaves no half-imported entity behind
+        res.claim(&self.backend_id(), &device_link)?;
+
+        let link_ent = RType::Entertainment.deterministic(format!(
+            "{}:ent",
+            self.entity_key(&imported.entity_id, imported.unique_id.as_deref())
+        ));
+        let renders = self.entertainment_fallback()
+            && imported.kind == HassEntityKind::Light
+            && imported.service_kind == HassServiceKind::Light;
+        let link_zbc = res
+            .get::<Device>(&device_link)
+            .ok()
+            .and_then(|dev| {
+                dev.services
+                    .iter()
+                    .find(|link| link.rtype == RType::ZigbeeConnectivity)
+                    .copied()
+            })
+            .unwrap_or_else(|| {
+                let key = self.entity_key(&imported.entity_id, imported.unique_id.as_deref());
+                RType::ZigbeeConnectivity.deterministic(format!("{key}:zbc"))
+            });
         let binding = self
             .entity_map
             .entry(imported.entity_id.clone())
             .or_insert_with(|| HassEntityBinding {
                 entity_id: imported.entity_id.clone(),
+                unique_id: imported.unique_id.clone(),
                 name: imported.name.clone(),
                 kind: imported.kind,
                 service_kind: imported.service_kind,
@@ -590,6 +1448,10 @@ impl HassBackend {
             });
 
         let previous_service_link = binding.service_link;
+        let previous_device_link = binding.device_link;
+        if imported.unique_id.is_some() {
+            binding.unique_id.clone_from(&imported.unique_id);
+        }
         binding.name.clone_from(&imported.name);
         binding.kind = imported.kind;
         binding.service_kind = imported.service_kind;
@@ -597,6 +1459,7 @@ impl HassBackend {
         binding.device_link = device_link;
         binding.capabilities = imported.capabilities;
         binding.switch_mode = imported.switch_mode;
+        let binding = binding.clone();
 
         if previous_service_link != binding.service_link {
             self.light_map.remove(&previous_service_link.rid);
@@ -606,24 +1469,53 @@ impl HassBackend {
             }
         }
 
-        self.device_map
-            .insert(binding.device_link.rid, imported.entity_id.clone());
+        if previous_device_link != binding.device_link {
+            // moved to (or away from) a host device: the old device goes,
+            // unless other entities are still attached to it
+            self.migrate_device(res, &imported.entity_id, &previous_device_link)?;
+        }
+
+        let hosted = self.is_hosted(&imported.entity_id, &binding.device_link)
+            && res.get::<Device>(&binding.device_link).is_ok();
+        if !hosted {
+            self.device_map
+                .insert(binding.device_link.rid, imported.entity_id.clone());
+        }
+        if let Some(device_id) = imported
+            .device_id
+            .clone()
+            .filter(|_| imported.service_kind == HassServiceKind::Motion)
+        {
+            self.motion_devices.insert(device_id, binding.device_link);
+        }
         match imported.service_kind {
             HassServiceKind::Light | HassServiceKind::Switch => {
                 self.light_map
                     .insert(binding.service_link.rid, imported.entity_id.clone());
                 self.sensor_map.remove(&binding.service_link.rid);
             }
-            HassServiceKind::Motion | HassServiceKind::Contact => {
+            HassServiceKind::Motion
+            | HassServiceKind::Contact
+            | HassServiceKind::Button
+            | HassServiceKind::Temperature
+            | HassServiceKind::LightLevel => {
                 self.sensor_map
                     .insert(binding.service_link.rid, imported.entity_id.clone());
                 self.light_map.remove(&binding.service_link.rid);
             }
         }
 
-        if res.get::<Device>(&binding.device_link).is_err() {
+        if hosted {
+            // the device belongs to another entity, only add the service
+            res.update::<Device>(&binding.device_link.rid, |dev| {
+                dev.services.insert(binding.service_link);
+            })?;
+        } else if res.get::<Device>(&binding.device_link).is_err() {
             let mut dev = make_device(binding.service_link, imported);
             dev.services.insert(link_zbc);
+            if renders {
+                dev.services.insert(link_ent);
+            }
             res.add(&binding.device_link, Resource::Device(dev))?;
         } else {
             res.update::<Device>(&binding.device_link.rid, |dev| {
@@ -631,23 +1523,57 @@ impl HassBackend {
                 dev.metadata.archetype = light_archetype(imported);
                 dev.product_data.product_name.clone_from(&imported.name);
                 dev.product_data.product_archetype = light_archetype(imported);
-                dev.services = btreeset![binding.service_link, link_zbc];
+                // keep the services of the other entities of this device
+                dev.services.retain(|link| {
+                    matches!(
+                        link.rtype,
+                        RType::LightLevel | RType::DevicePower | RType::Button
+                    )
+                });
+                dev.services.extend([binding.service_link, link_zbc]);
+                if renders {
+                    dev.services.insert(link_ent);
+                }
             })?;
         }
 
-        if res.get::<ZigbeeConnectivity>(&link_zbc).is_err() {
-            // Hue app expects zigbee_connectivity for "real" devices. For HA entities we emulate it.
-            let zbc = ZigbeeConnectivity {
-                owner: binding.device_link,
-                mac_address: ieee_like_from_uuid(&binding.device_link.rid),
-                status: ZigbeeConnectivityStatus::Connected,
-                channel: Some(json!({
-                    "status": "set",
-                    "value": "channel_25",
-                })),
-                extended_pan_id: None,
-            };
-            res.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
+        if !hosted {
+            let aux = res
+                .aux_get(&binding.device_link)
+                .cloned()
+                .unwrap_or_default();
+            res.aux_set(
+                &binding.device_link,
+                aux.with_hass(binding.to_aux(&self.name)),
+            );
+
+            if res.get::<ZigbeeConnectivity>(&link_zbc).is_err() {
+                // Hue app expects zigbee_connectivity for "real" devices. For HA entities we emulate it.
+                let zbc = ZigbeeConnectivity {
+                    owner: binding.device_link,
+                    mac_address: ieee_like_from_uuid(&binding.device_link.rid),
+                    status: connectivity_status(imported.available),
+                    channel: Some(json!({
+                        "status": "set",
+                        "value": "channel_25",
+                    })),
+                    extended_pan_id: None,
+                };
+                res.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
+            } else {
+                res.update::<ZigbeeConnectivity>(&link_zbc.rid, |zbc| {
+                    zbc.status = connectivity_status(imported.available);
+                })?;
+            }
+        }
+
+        if renders {
+            if res.get::<Entertainment>(&link_ent).is_err() {
+                let ent = fallback_entertainment(binding.device_link, binding.service_link);
+                res.add(&link_ent, Resource::Entertainment(ent))?;
+            }
+        } else if res.get_resource(&link_ent).is_ok() {
+            res.delete(&link_ent)?;
         }
 
         match imported.service_kind {
@@ -659,10 +1585,11 @@ impl HassBackend {
                     );
                     apply_light_state(&mut light, imported);
                     res.add(&binding.service_link, Resource::Light(light))?;
-                } else {
+                } else if self.accept_light_state(&binding.service_link, imported) {
                     res.update::<Light>(&binding.service_link.rid, |light| {
                         apply_light_state(light, imported);
                     })?;
+                    res.refresh_light_groups(&binding.service_link.rid)?;
                 }
             }
             HassServiceKind::Motion => {
@@ -691,16 +1618,294 @@ impl HassBackend {
                     })?;
                 }
             }
-            HassServiceKind::Contact => {
-                let value = make_contact_resource(imported, binding.device_link);
-                if res.get_resource(&binding.service_link).is_ok() {
-                    let _ = res.delete(&binding.service_link);
+            HassServiceKind::Contact => {
+                let value = make_contact_resource(imported, binding.device_link);
+                if res.get_resource(&binding.service_link).is_ok() {
+                    let _ = res.delete(&binding.service_link);
+                }
+                res.add(&binding.service_link, Resource::Contact(value))?;
+            }
+            HassServiceKind::Button => {
+                if res.get::<Button>(&binding.service_link).is_err() {
+                    res.add(
+                        &binding.service_link,
+                        Resource::Button(make_button(binding.device_link, imported)),
+                    )?;
+                } else if imported.kind == HassEntityKind::Event {
+                    // a new report is sent to hue clients as a button event
+                    res.update::<Button>(&binding.service_link.rid, |button| {
+                        if let Some(control_id) = imported.control_id {
+                            button.metadata.control_id = control_id;
+                        }
+                        button.button.event_values = Some(json!(imported.event_values));
+                        if let Some(report) = &imported.button_report {
+                            button.button.last_event = Some(json!(report.event));
+                            button.button.button_report = Some(report.clone());
+                        }
+                    })?;
+                }
+            }
+            HassServiceKind::Temperature => {
+                let temperature = temperature_value(imported);
+                if res.get::<Temperature>(&binding.service_link).is_err() {
+                    res.add(
+                        &binding.service_link,
+                        Resource::Temperature(Temperature {
+                            enabled: imported.sensor_enabled,
+                            owner: binding.device_link,
+                            temperature,
+                        }),
+                    )?;
+                } else {
+                    res.update::<Temperature>(&binding.service_link.rid, |temp| {
+                        temp.enabled = imported.sensor_enabled;
+                        temp.temperature = temperature;
+                    })?;
+                }
+            }
+            HassServiceKind::LightLevel => {
+                let light = light_level_value(imported);
+                if res.get::<LightLevel>(&binding.service_link).is_err() {
+                    res.add(
+                        &binding.service_link,
+                        Resource::LightLevel(LightLevel {
+                            enabled: imported.sensor_enabled,
+                            light,
+                            owner: binding.device_link,
+                        }),
+                    )?;
+                } else {
+                    res.update::<LightLevel>(&binding.service_link.rid, |level| {
+                        level.enabled = imported.sensor_enabled;
+                        level.light = light;
+                        level.owner = binding.device_link;
+                    })?;
+                }
+            }
+        }
+
+        if hosted {
+            // the device aux belongs to the host entity, so the binding of a
+            // hosted entity is saved with its service
+            res.aux_set(
+                &binding.service_link,
+                AuxData::new().with_hass(binding.to_aux(&self.name)),
+            );
+        }
+
+        self.fingerprints
+            .insert(imported.entity_id.clone(), imported.fingerprint());
+
+        Ok(())
+    }
+
+    /// Detach `entity_id` from its previous device `device_link`, deleting
+    /// the device if no other entity uses it.
+    fn migrate_device(
+        &mut self,
+        res: &mut Resources,
+        entity_id: &str,
+        device_link: &ResourceLink,
+    ) -> ApiResult<()> {
+        if self.device_map.get(&device_link.rid).map(String::as_str) == Some(entity_id) {
+            self.device_map.remove(&device_link.rid);
+        }
+
+        let shared = self
+            .entity_map
+            .values()
+            .any(|other| other.entity_id != entity_id && other.device_link == *device_link);
+        if shared || res.get::<Device>(device_link).is_err() {
+            return Ok(());
+        }
+
+        log::info!(
+            "[{}] Moved {entity_id} off device {device_link:?}",
+            self.name
+        );
+        self.batteries.remove_device(device_link);
+        res.delete(device_link)
+    }
+
+    /// Has this entity already been imported with the exact same inputs (and
+    /// are its resources still in place)?
+    fn is_unchanged(&self, imported: &ImportedEntity, res: &Resources) -> bool {
+        let Some(binding) = self.entity_map.get(&imported.entity_id) else {
+            return false;
+        };
+
+        self.fingerprints.get(&imported.entity_id) == Some(&imported.fingerprint())
+            && !self.pending_lights.contains_key(&binding.service_link.rid)
+            && res.get_resource(&binding.device_link).is_ok()
+            && res.get_resource(&binding.service_link).is_ok()
+    }
+
+    /// Restore the entity bindings saved by earlier runs, so Hue commands can
+    /// be routed before the first sync with Home Assistant completes.
+    ///
+    /// Devices without a saved binding (from older versions) are matched
+    /// against the deterministic links of the last known `entities`.
+    /// Returns the number of restored bindings.
+    pub(super) fn restore_bindings(
+        &mut self,
+        res: &mut Resources,
+        entities: &[HassEntitySummary],
+    ) -> usize {
+        let mut bindings = vec![];
+
+        for rid in res.get_resource_ids_by_type(RType::Device) {
+            let device_link = RType::Device.link_to(rid);
+            let Ok(dev) = res.get::<Device>(&device_link) else {
+                continue;
+            };
+            // the device itself, followed by the entities hosted on it
+            for link in std::iter::once(&device_link).chain(&dev.services) {
+                let Ok(AuxData {
+                    hass: Some(aux), ..
+                }) = res.aux_get(link)
+                else {
+                    continue;
+                };
+                if aux.backend == self.name {
+                    bindings.push(HassEntityBinding::from_aux(device_link, aux.clone()));
+                }
+            }
+        }
+
+        for summary in entities.iter().filter(|summary| summary.included) {
+            let (kind, service_kind) = match summary.domain.as_str() {
+                "light" => (HassEntityKind::Light, HassServiceKind::Light),
+                "switch" => (HassEntityKind::Switch, HassServiceKind::Switch),
+                "cover" => (HassEntityKind::Cover, HassServiceKind::Light),
+                "fan" => (HassEntityKind::Fan, HassServiceKind::Switch),
+                "media_player" => (HassEntityKind::MediaPlayer, HassServiceKind::Switch),
+                "script" => (HassEntityKind::Script, HassServiceKind::Button),
+                "button" => (HassEntityKind::Button, HassServiceKind::Button),
+                "event" => (HassEntityKind::Event, HassServiceKind::Button),
+                _ => continue,
+            };
+            let (device_link, service_link) =
+                self.links_for_entity(&summary.entity_id, service_kind);
+            if bindings
+                .iter()
+                .any(|binding| binding.device_link == device_link)
+            {
+                continue;
+            }
+            bindings.push(HassEntityBinding {
+                entity_id: summary.entity_id.clone(),
+                unique_id: None,
+                name: summary.name.clone(),
+                kind,
+                service_kind,
+                service_link,
+                device_link,
+                capabilities: HassLightCapabilities {
+                    supports_brightness: summary.supports_brightness,
+                    supports_color: summary.supports_color,
+                    supports_color_temp: summary.supports_color_temp,
+                },
+                switch_mode: summary.switch_mode,
+            });
+        }
+
+        let mut restored = 0;
+        for binding in bindings {
+            if res.get_resource(&binding.device_link).is_err()
+                || res.get_resource(&binding.service_link).is_err()
+                || res.claim(&self.backend_id(), &binding.device_link).is_err()
+            {
+                continue;
+            }
+
+            // the host entity comes first, hosted entities keep it as owner
+            self.device_map
+                .entry(binding.device_link.rid)
+                .or_insert_with(|| binding.entity_id.clone());
+            match binding.service_kind {
+                HassServiceKind::Light | HassServiceKind::Switch => {
+                    self.light_map
+                        .insert(binding.service_link.rid, binding.entity_id.clone());
+                }
+                HassServiceKind::Motion
+                | HassServiceKind::Contact
+                | HassServiceKind::Button
+                | HassServiceKind::Temperature
+                | HassServiceKind::LightLevel => {
+                    self.sensor_map
+                        .insert(binding.service_link.rid, binding.entity_id.clone());
                 }
-                res.add(&binding.service_link, Resource::Contact(value))?;
             }
+            self.entity_map.insert(binding.entity_id.clone(), binding);
+            restored += 1;
         }
 
-        Ok(())
+        restored
+    }
+
+    /// Forget the bindings of an entity that is no longer imported
+    fn forget_entity(&mut self, entity_id: &str) -> Option<HassEntityBinding> {
+        self.fingerprints.remove(entity_id);
+        self.room_lights.remove(entity_id);
+        let binding = self.entity_map.remove(entity_id)?;
+        self.light_map.remove(&binding.service_link.rid);
+        self.sensor_map.remove(&binding.service_link.rid);
+        if !self.is_hosted(entity_id, &binding.device_link) {
+            self.device_map.remove(&binding.device_link.rid);
+        }
+        self.batteries.remove_device(&binding.device_link);
+        if binding.service_kind == HassServiceKind::Motion {
+            self.motion_devices
+                .retain(|_, device| *device != binding.device_link);
+        }
+        Some(binding)
+    }
+
+    /// Delete the hue resources of a forgotten entity. A device that other
+    /// entities are still attached to is kept, only the service is deleted.
+    fn delete_entity_resources(
+        &self,
+        res: &mut Resources,
+        binding: &HassEntityBinding,
+    ) -> ApiResult<()> {
+        if self
+            .entity_map
+            .values()
+            .any(|other| other.device_link == binding.device_link)
+        {
+            res.delete(&binding.service_link)
+        } else {
+            res.delete(&binding.device_link)
+        }
+    }
+
+    /// Apply the conflict policy, if the light has a command pending that
+    /// Home Assistant has not yet confirmed.
+    fn accept_light_state(&mut self, link: &ResourceLink, imported: &ImportedEntity) -> bool {
+        let Some(pending) = self.pending_lights.get(&link.rid) else {
+            return true;
+        };
+
+        let (apply, resolved) = pending.resolve(
+            imported.conflict_policy,
+            imported.on,
+            imported.brightness,
+            imported.last_updated,
+        );
+
+        if resolved {
+            self.pending_lights.remove(&link.rid);
+        }
+
+        if !apply {
+            log::debug!(
+                "[{}] Holding state of {} until Home Assistant confirms the last command",
+                self.name,
+                imported.entity_id
+            );
+        }
+
+        apply
     }
 
     fn prune_homeassistant_devices(
@@ -733,10 +1938,7 @@ impl HassBackend {
             if res.delete(&link).is_ok() {
                 removed += 1;
                 if let Some(entity_id) = self.device_map.remove(&rid) {
-                    if let Some(binding) = self.entity_map.remove(&entity_id) {
-                        self.light_map.remove(&binding.service_link.rid);
-                        self.sensor_map.remove(&binding.service_link.rid);
-                    }
+                    self.forget_entity(&entity_id);
                 }
             }
         }
@@ -745,49 +1947,128 @@ impl HassBackend {
     }
 
     fn sync_grouped_light_states(
-        &self,
+        &mut self,
+        config: &HassUiConfig,
         imported_map: &HashMap<String, ImportedEntity>,
         entity_room: &HashMap<String, String>,
         res: &mut Resources,
     ) -> ApiResult<()> {
-        for room in self.room_map.values() {
-            let mut any_on = false;
-            let mut values = Vec::new();
+        self.room_lights.clear();
+        for (entity_id, imported) in imported_map {
+            if let Some(room_id) = entity_room.get(entity_id) {
+                self.note_room_light(imported, room_id.clone());
+            }
+        }
+
+        for room_id in self.room_map.keys() {
+            self.refresh_room_state(config, room_id, res)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remember the state of light `imported` in room `room_id`
+    fn note_room_light(&mut self, imported: &ImportedEntity, room_id: String) {
+        if !imported.kind.groups_as_light(imported.switch_mode) {
+            self.room_lights.remove(&imported.entity_id);
+            return;
+        }
+
+        let brightness = imported.brightness.map(|br| HassLightBrightness {
+            on: imported.on,
+            brightness: (br / 255.0 * 100.0).clamp(0.0, 100.0),
+            last_updated: imported.last_updated,
+        });
+        self.room_lights.insert(
+            imported.entity_id.clone(),
+            HassRoomLight {
+                room_id,
+                on: imported.on,
+                brightness,
+            },
+        );
+    }
 
+    /// Update the grouped light of room `room_id` from the state of its
+    /// lights, using the brightness strategy of the room
+    fn refresh_room_state(
+        &self,
+        config: &HassUiConfig,
+        room_id: &str,
+        res: &mut Resources,
+    ) -> ApiResult<()> {
+        // merged rooms hold lights of several backends, so their state is
+        // derived from all of them (see [`Resources::refresh_merged_room`])
+        let Some(room) = self.room_map.get(room_id).filter(|room| !room.merged) else {
+            return Ok(());
+        };
+
+        let strategy = config
+            .rooms
+            .iter()
+            .find(|cfg| cfg.id == room_id)
+            .and_then(|cfg| cfg.brightness_strategy)
+            .unwrap_or_default();
+
+        let lights: Vec<&HassRoomLight> = self
+            .room_lights
+            .iter()
+            .filter(|(entity_id, light)| {
+                light.room_id == room_id && self.entity_map.contains_key(*entity_id)
+            })
+            .map(|(_, light)| light)
+            .collect();
+        let any_on = lights.iter().any(|light| light.on);
+        let values: Vec<HassLightBrightness> =
+            lights.iter().filter_map(|light| light.brightness).collect();
+        let dimming = strategy.aggregate(&values).map(DimmingUpdate::new);
+
+        res.update::<GroupedLight>(&room.grouped_light_link.rid, |grouped| {
+            grouped.on = Some(On { on: any_on });
+            grouped.dimming = dimming;
+        })
+    }
+
+    /// Set the lights of this backend in the zones named by
+    /// [`HassUiConfig::label_zones`] to those with a matching label. Other
+    /// children of these zones are left alone.
+    fn sync_label_zones(
+        &self,
+        config: &HassUiConfig,
+        imported_map: &HashMap<String, ImportedEntity>,
+        res: &mut Resources,
+    ) -> ApiResult<()> {
+        for id in res.get_resource_ids_by_type(RType::Zone) {
+            let link = ResourceLink::new(id, RType::Zone);
+            let zone = res.get::<Zone>(&link)?;
+            if !config.is_label_zone(&zone.metadata.name) {
+                continue;
+            }
+
+            let mut children: BTreeSet<ResourceLink> = zone
+                .children
+                .iter()
+                .filter(|child| {
+                    !self.light_map.contains_key(&child.rid)
+                        && !self.device_map.contains_key(&child.rid)
+                })
+                .copied()
+                .collect();
             for binding in self.entity_map.values() {
-                if entity_room.get(&binding.entity_id) != Some(&room.room_id) {
-                    continue;
-                }
-                let grouped_as_light = match binding.kind {
-                    HassEntityKind::Light => true,
-                    HassEntityKind::Switch => {
-                        binding.switch_mode.unwrap_or(HassSwitchMode::Plug) == HassSwitchMode::Light
-                    }
-                    HassEntityKind::BinarySensor => false,
-                };
-                if !grouped_as_light {
-                    continue;
-                }
-                if let Some(imported) = imported_map.get(&binding.entity_id) {
-                    any_on |= imported.on;
-                    if let Some(br) = imported.brightness {
-                        values.push((br / 255.0 * 100.0).clamp(0.0, 100.0));
-                    }
+                let labelled = imported_map
+                    .get(&binding.entity_id)
+                    .is_some_and(|imported| {
+                        config.in_label_zone(&zone.metadata.name, &imported.labels)
+                    });
+                if labelled && binding.service_link.rtype == RType::Light {
+                    children.insert(binding.service_link);
                 }
             }
 
-            let dimming = if values.is_empty() {
-                None
-            } else {
-                let sum: f64 = values.iter().sum();
-                let count = u32::try_from(values.len()).map_or(1.0, f64::from);
-                Some(DimmingUpdate::new(sum / count))
-            };
-
-            res.update::<GroupedLight>(&room.grouped_light_link.rid, |grouped| {
-                grouped.on = Some(On { on: any_on });
-                grouped.dimming = dimming;
-            })?;
+            if children != zone.children {
+                res.update::<Zone>(&id, |zone| zone.children = children)?;
+                res.refresh_zone(&link)?;
+            }
         }
 
         Ok(())
@@ -803,6 +2084,10 @@ impl HassBackend {
             return room_id.clone();
         }
 
+        if let Some(room_id) = config.room_for_labels(&imported.labels) {
+            return room_id;
+        }
+
         if config.sync_hass_areas_to_rooms {
             if let Some(area_name) = imported.area_name.as_deref() {
                 if let Some(room_id) = config.room_for_area(area_name) {
@@ -817,9 +2102,39 @@ impl HassBackend {
     pub(super) async fn sync_entities(&mut self) -> ApiResult<()> {
         self.apply_runtime_connection().await?;
 
-        let states = self.client.get_states().await?;
-        let core_config = self.client.get_core_config().await.ok();
-        let area_map = match self.client.get_entity_areas().await {
+        let thresholds: Vec<String> = {
+            let ui_state = self.ui_state.lock().await;
+            let config = ui_state.config_normalized();
+            config.sensor_thresholds.keys().cloned().collect()
+        };
+        let sensors: Vec<&str> = thresholds.iter().map(String::as_str).collect();
+
+        let (states, core_config, areas, registry, labels) = tokio::join!(
+            self.client.get_states(),
+            self.client.get_core_config(),
+            self.client.get_entity_areas(&sensors),
+            self.client.get_entity_registry(),
+            self.client.get_label_registry(),
+        );
+        let states = states?;
+        let core_config = core_config.ok();
+        let registry = registry.unwrap_or_else(|err| {
+            log::warn!(
+                "[{}] Failed to query Home Assistant entity registry. Using entity ids for resource ids, ignoring labels: {}",
+                self.name,
+                err
+            );
+            HashMap::new()
+        });
+        let labels = labels.unwrap_or_else(|err| {
+            log::warn!(
+                "[{}] Failed to query Home Assistant label registry. Using label ids for names: {}",
+                self.name,
+                err
+            );
+            HashMap::new()
+        });
+        let area_map = match areas {
             Ok(map) => map,
             Err(err) => {
                 log::warn!(
@@ -827,8 +2142,14 @@ impl HassBackend {
                     self.name,
                     err
                 );
-                self.ui_log(format!("Area sync fallback (no areas): {err}"))
-                    .await;
+                self.ui_log_entry(
+                    TimelineEntry::new(
+                        TimelineCategory::Sync,
+                        format!("Area sync fallback (no areas): {err}"),
+                    )
+                    .with_level(TimelineLevel::Warning),
+                )
+                .await;
                 HashMap::new()
             }
         };
@@ -836,14 +2157,41 @@ impl HassBackend {
         let mut parsed = states
             .iter()
             .filter_map(|state| {
-                parse_imported_entity(state, area_map.get(&state.entity_id).cloned())
+                let mut imported =
+                    parse_imported_entity(state, area_map.get(&state.entity_id).cloned())?;
+                if let Some(entry) = registry.get(&state.entity_id) {
+                    imported.unique_id = entry.qualified_unique_id();
+                    imported.device_id.clone_from(&entry.device_id);
+                    imported.labels = label_names(&entry.labels, &labels);
+                }
+                Some(imported)
             })
             .collect::<Vec<_>>();
         parsed.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
 
+        let renames = self.detect_renames(&parsed);
+
         let mut ui_state = self.ui_state.lock().await;
-        let mut ui_config = ui_state.config_normalized();
+        let mut ui_config = HassUiConfig::clone(&ui_state.config_normalized());
+        self.batteries.clear();
+        for state in &states {
+            self.daylight.observe(state, &ui_config);
+            let device_id = registry
+                .get(&state.entity_id)
+                .and_then(|entry| entry.device_id.as_deref());
+            self.batteries.observe(state, device_id);
+        }
+        self.registry = registry;
+        self.label_names = labels;
+        parsed.retain(|imported| {
+            ui_config.domain_enabled(&self.name, imported.domain())
+                && imported.is_supported(&ui_config)
+        });
         let mut changed = false;
+        for (old_id, new_id) in &renames {
+            ui_config.rename_entity(old_id, new_id);
+            changed = true;
+        }
         if let Some(core) = core_config {
             let timezone = core
                 .timezone
@@ -858,6 +2206,10 @@ impl HassBackend {
                 ui_config.set_hass_location(timezone, lat, long);
                 changed = true;
             }
+            if ui_config.hass_temperature_unit != core.unit_system.temperature {
+                ui_config.hass_temperature_unit = core.unit_system.temperature;
+                changed = true;
+            }
         }
         if ui_config.sync_hass_areas_to_rooms {
             for imported in &parsed {
@@ -871,35 +2223,37 @@ impl HassBackend {
         }
         if changed {
             ui_state.set_config(ui_config.clone());
-            ui_state.persist_and_log("Synced Home Assistant metadata into Bifrost state")?;
+            ui_state.persist_and_log(None, "Synced Home Assistant metadata into Bifrost state")?;
         } else {
             ui_state.set_config(ui_config.clone());
         }
         drop(ui_state);
 
+        for (old_id, new_id) in &renames {
+            self.ui_log_entry(
+                TimelineEntry::new(
+                    TimelineCategory::Sync,
+                    format!("Entity {old_id} was renamed to {new_id} (kept its Hue device)"),
+                )
+                .with_entity(new_id),
+            )
+            .await;
+        }
+
         let mut imported_included = HashMap::new();
         let mut summaries = Vec::with_capacity(parsed.len());
         let mut entity_room = HashMap::new();
 
         for imported in &parsed {
             let mut imported = imported.clone();
-            if let Some(alias) = ui_config.entity_alias(&imported.entity_id) {
-                imported.name = alias;
-            }
-            if matches!(imported.kind, HassEntityKind::Switch) {
-                imported.switch_mode = Some(ui_config.switch_mode(&imported.entity_id));
-            }
-            if matches!(imported.kind, HassEntityKind::Light)
-                || (matches!(imported.kind, HassEntityKind::Switch)
-                    && imported.switch_mode == Some(HassSwitchMode::Light))
-            {
-                imported.light_archetype = Some(ui_config.light_archetype(&imported.entity_id));
-            }
+            imported.apply_threshold(&ui_config);
+            imported.apply_measurement(&ui_config);
+            imported.apply_preferences(&ui_config);
 
             let detected_sensor_kind = imported
                 .detected_sensor_kind
                 .unwrap_or(HassSensorKind::Ignore);
-            if matches!(imported.kind, HassEntityKind::BinarySensor) {
+            if imported.kind.is_binary_sensor() {
                 imported.service_kind =
                     match ui_config.sensor_kind(&imported.entity_id, detected_sensor_kind) {
                         HassSensorKind::Motion => HassServiceKind::Motion,
@@ -915,12 +2269,15 @@ impl HassBackend {
             let selected_sensor_kind = match imported.service_kind {
                 HassServiceKind::Motion => Some(HassSensorKind::Motion),
                 HassServiceKind::Contact => Some(HassSensorKind::Contact),
-                HassServiceKind::Light | HassServiceKind::Switch => None,
+                HassServiceKind::Light
+                | HassServiceKind::Switch
+                | HassServiceKind::Button
+                | HassServiceKind::Temperature
+                | HassServiceKind::LightLevel => None,
             };
 
-            let mut included =
-                ui_config.should_include(&imported.entity_id, &imported.name, imported.available);
-            if matches!(imported.kind, HassEntityKind::BinarySensor)
+            let mut included = imported.is_included(&ui_config);
+            if imported.kind.is_binary_sensor()
                 && matches!(
                     ui_config.sensor_kind(&imported.entity_id, detected_sensor_kind),
                     HassSensorKind::Ignore
@@ -953,38 +2310,73 @@ impl HassBackend {
                 sensor_kind: selected_sensor_kind,
                 light_archetype: imported.light_archetype,
                 enabled: imported.sensor_enabled,
+                labels: imported.labels.clone(),
             });
         }
 
         {
             let mut ui_state = self.ui_state.lock().await;
-            ui_state.entities = summaries;
+            ui_state.set_entities(summaries);
         }
 
+        assign_control_ids(&mut imported_included);
+
         let state = self.state.clone();
         let mut res = state.lock().await;
         self.ensure_rooms(&mut res, &ui_config)?;
 
-        for imported in imported_included.values() {
-            self.sync_single_entity(imported, &mut res)?;
+        // Only touch resources of entities whose inputs changed since the
+        // last import, to avoid needless events (and lock hold time).
+        let mut changed = imported_included
+            .values()
+            .filter(|imported| !self.is_unchanged(imported, &res))
+            .collect::<Vec<_>>();
+        let unchanged = imported_included.len() - changed.len();
+        drop(res);
+
+        // Hosts go first, so the devices of hosted entities exist when those
+        // are synced.
+        self.resolve_hosts(&imported_included);
+        changed.sort_by_key(|imported| self.host_device(imported).is_some());
+
+        // Write in bounded chunks, releasing the lock in between, so hue
+        // clients are not starved during large imports.
+        for chunk in changed.chunks(self.sync_chunk_size()) {
+            let mut res = state.lock().await;
+            for imported in chunk {
+                match self.sync_single_entity(imported, &mut res) {
+                    Ok(()) => {}
+                    Err(err @ ApiError::OwnershipConflict(..)) => {
+                        log::warn!("[{}] Skipping {}: {err}", self.name, imported.entity_id);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            drop(res);
+            tokio::task::yield_now().await;
         }
 
+        let mut res = state.lock().await;
+
         // If the user previously exposed many entities, they may still exist in the persisted
-        // Hue resource DB after a restart (since `entity_map` is in-memory only). Always prune
+        // Hue resource DB without a saved binding (e.g. from older versions). Always prune
         // any Home Assistant-generated devices that are no longer included.
-        let keep_device_rids = imported_included
+        let mut keep_device_rids = imported_included
             .values()
             .map(|imported| {
-                let (device_link, _service_link) =
-                    self.links_for_entity(&imported.entity_id, imported.service_kind);
+                let (device_link, _service_link) = self.links_for_imported(imported);
                 device_link.rid
             })
             .collect::<HashSet<_>>();
+        if ui_config.daylight_sensor {
+            keep_device_rids.insert(Self::daylight_links().0.rid);
+        }
         let pruned = self.prune_homeassistant_devices(&mut res, &keep_device_rids)?;
         if pruned > 0 {
-            self.ui_log(format!(
-                "Pruned {pruned} stale Home Assistant devices from Hue bridge"
-            ))
+            self.ui_log(
+                TimelineCategory::Sync,
+                format!("Pruned {pruned} stale Home Assistant devices from Hue bridge"),
+            )
             .await;
         }
 
@@ -995,11 +2387,8 @@ impl HassBackend {
             .cloned()
             .collect::<Vec<_>>();
         for entity_id in stale {
-            if let Some(binding) = self.entity_map.remove(&entity_id) {
-                self.light_map.remove(&binding.service_link.rid);
-                self.sensor_map.remove(&binding.service_link.rid);
-                self.device_map.remove(&binding.device_link.rid);
-                if let Err(err) = res.delete(&binding.device_link) {
+            if let Some(binding) = self.forget_entity(&entity_id) {
+                if let Err(err) = self.delete_entity_resources(&mut res, &binding) {
                     log::warn!(
                         "[{}] Failed to delete stale entity {}: {}",
                         self.name,
@@ -1010,6 +2399,16 @@ impl HassBackend {
             }
         }
 
+        self.sync_daylight(&mut res, &ui_config)?;
+
+        for imported in imported_included.values() {
+            if let Some(device_id) = imported.device_id.as_deref() {
+                let (device_link, _service_link) = self.links_for_imported(imported);
+                self.batteries.add_device(device_id, device_link);
+            }
+        }
+        self.sync_batteries(&mut res)?;
+
         let mut children_by_room = self
             .room_map
             .keys()
@@ -1032,20 +2431,24 @@ impl HassBackend {
                 .get(&room.room_id)
                 .cloned()
                 .unwrap_or_default();
-            res.update::<Room>(&room.room_link.rid, |hue_room| {
-                hue_room.children = children;
-            })?;
+            self.set_room_children(&mut res, room, children)?;
         }
 
-        self.sync_grouped_light_states(&imported_included, &entity_room, &mut res)?;
-
-        self.ui_log(format!(
-            "Synced {} entities ({} exposed, {} hidden) across {} rooms",
-            parsed.len(),
-            imported_included.len(),
-            parsed.len().saturating_sub(imported_included.len()),
-            self.room_map.len()
-        ))
+        self.sync_grouped_light_states(&ui_config, &imported_included, &entity_room, &mut res)?;
+        self.sync_label_zones(&ui_config, &imported_included, &mut res)?;
+
+        self.flush_room_notices().await;
+        self.ui_log(
+            TimelineCategory::Sync,
+            format!(
+                "Synced {} entities ({} exposed, {} unchanged, {} hidden) across {} rooms",
+                parsed.len(),
+                imported_included.len(),
+                unchanged,
+                parsed.len().saturating_sub(imported_included.len()),
+                self.room_map.len()
+            ),
+        )
         .await;
 
         Ok(())
@@ -1062,12 +2465,45 @@ impl HassBackend {
                 self.name, entity_id
             )));
         };
+        // the registry is queried again only for entities added since the
+        // last full sync
+        if !self.registry.contains_key(entity_id) {
+            let (registry, labels) = tokio::join!(
+                self.client.get_entity_registry(),
+                self.client.get_label_registry(),
+            );
+            match registry {
+                Ok(registry) => self.registry = registry,
+                Err(err) => log::debug!(
+                    "[{}] Failed to query Home Assistant entity registry for {}: {}",
+                    self.name,
+                    entity_id,
+                    err
+                ),
+            }
+            if let Ok(labels) = labels {
+                self.label_names = labels;
+            }
+        }
+        if let Some(entry) = self.registry.get(entity_id) {
+            imported.unique_id = entry.qualified_unique_id();
+            imported.device_id.clone_from(&entry.device_id);
+            imported.labels = label_names(&entry.labels, &self.label_names);
+        }
+        if imported.kind == HassEntityKind::Event {
+            if let Some(device_id) = &imported.device_id {
+                imported.control_id = Some(self.remote_control_id(device_id, &imported.entity_id));
+            }
+        }
 
         let ui_state = self.ui_state.lock().await;
         let ui_config = ui_state.config_normalized();
-        let mut include =
-            ui_config.should_include(&imported.entity_id, &imported.name, imported.available);
-        if matches!(imported.kind, HassEntityKind::BinarySensor) {
+        imported.apply_threshold(&ui_config);
+        imported.apply_measurement(&ui_config);
+        let mut include = ui_config.domain_enabled(&self.name, imported.domain())
+            && imported.is_supported(&ui_config)
+            && imported.is_included(&ui_config);
+        if imported.kind.is_binary_sensor() {
             let detected_sensor_kind = imported
                 .detected_sensor_kind
                 .unwrap_or(HassSensorKind::Ignore);
@@ -1082,28 +2518,23 @@ impl HassBackend {
 
         if !include {
             // If user toggled to hidden quickly, do not import.
-            self.ui_log(format!(
-                "Skipped import of {} (not included by UI config)",
-                imported.entity_id
-            ))
+            self.ui_log_entry(
+                TimelineEntry::new(
+                    TimelineCategory::Sync,
+                    format!(
+                        "Skipped import of {} (not included by UI config)",
+                        imported.entity_id
+                    ),
+                )
+                .with_entity(&imported.entity_id),
+            )
             .await;
             return Ok(());
         }
 
         // Apply alias + sensor settings (UI config is source of truth).
-        if let Some(alias) = ui_config.entity_alias(&imported.entity_id) {
-            imported.name = alias;
-        }
-        if matches!(imported.kind, HassEntityKind::Switch) {
-            imported.switch_mode = Some(ui_config.switch_mode(&imported.entity_id));
-        }
-        if matches!(imported.kind, HassEntityKind::Light)
-            || (matches!(imported.kind, HassEntityKind::Switch)
-                && imported.switch_mode == Some(HassSwitchMode::Light))
-        {
-            imported.light_archetype = Some(ui_config.light_archetype(&imported.entity_id));
-        }
-        if matches!(imported.kind, HassEntityKind::BinarySensor) {
+        imported.apply_preferences(&ui_config);
+        if imported.kind.is_binary_sensor() {
             let detected = imported
                 .detected_sensor_kind
                 .unwrap_or(HassSensorKind::Ignore);
@@ -1124,7 +2555,7 @@ impl HassBackend {
         self.sync_single_entity(&imported, &mut res)?;
 
         // Move to selected room (remove from others first).
-        let (device_link, _svc) = self.links_for_entity(&imported.entity_id, imported.service_kind);
+        let (device_link, _svc) = self.links_for_imported(&imported);
         for room in self.room_map.values() {
             res.try_update::<Room>(&room.room_link.rid, |hue_room| {
                 hue_room.children.remove(&device_link);
@@ -1138,20 +2569,67 @@ impl HassBackend {
             })?;
         }
 
-        self.ui_log(format!("Upserted {} into Hue bridge", imported.entity_id))
-            .await;
+        if let Some(device_id) = imported.device_id.as_deref() {
+            self.batteries.add_device(device_id, device_link);
+            self.sync_battery(&mut res, device_id)?;
+        }
+
+        self.flush_room_notices().await;
+        self.ui_log_entry(
+            TimelineEntry::new(
+                TimelineCategory::Sync,
+                format!("Upserted {} into Hue bridge", imported.entity_id),
+            )
+            .with_entity(&imported.entity_id),
+        )
+        .await;
         Ok(())
     }
 
+    async fn set_reachable(&self, device: &ResourceLink, available: bool) -> ApiResult<()> {
+        let mut res = self.state.lock().await;
+        let Some(link_zbc) = res
+            .get::<Device>(device)
+            .ok()
+            .and_then(|dev| dev.service(RType::ZigbeeConnectivity))
+            .copied()
+        else {
+            return Ok(());
+        };
+        res.update::<ZigbeeConnectivity>(&link_zbc.rid, |zbc| {
+            zbc.status = connectivity_status(available);
+        })
+    }
+
     pub(super) async fn handle_state_update(&mut self, state: HassState) -> ApiResult<()> {
         // Realtime HA -> Hue sync: update only included entities without polling.
         let ui_state = self.ui_state.lock().await;
         let ui_config = ui_state.config_normalized();
+        // state updates carry no area or labels, so use the ones from the last sync
+        let (area_name, labels) = ui_state
+            .entities
+            .iter()
+            .find(|ent| ent.entity_id == state.entity_id)
+            .map(|ent| (ent.area_name.clone(), ent.labels.clone()))
+            .unwrap_or_default();
         drop(ui_state);
 
-        let Some(mut imported) = parse_imported_entity(&state, None) else {
+        if self.daylight.observe(&state, &ui_config) {
+            let mut res = self.state.lock().await;
+            return self.sync_daylight(&mut res, &ui_config);
+        }
+
+        if let Some(device_id) = self.batteries.observe(&state, None) {
+            let mut res = self.state.lock().await;
+            return self.sync_battery(&mut res, &device_id);
+        }
+
+        let Some(mut imported) = parse_imported_entity(&state, area_name) else {
             return Ok(());
         };
+        imported.labels = labels;
+        imported.apply_threshold(&ui_config);
+        imported.apply_measurement(&ui_config);
 
         // HA websocket state_changed events can omit capability metadata like supported_color_modes.
         // Never downgrade a light to "on/off only" just because the incremental payload is sparse.
@@ -1164,9 +2642,10 @@ impl HassBackend {
         }
 
         // Decide inclusion based on UI config (explicit visible overrides patterns/defaults).
-        let mut include =
-            ui_config.should_include(&imported.entity_id, &imported.name, imported.available);
-        if matches!(imported.kind, HassEntityKind::BinarySensor) {
+        let mut include = ui_config.domain_enabled(&self.name, imported.domain())
+            && imported.is_supported(&ui_config)
+            && imported.is_included(&ui_config);
+        if imported.kind.is_binary_sensor() {
             let detected = imported
                 .detected_sensor_kind
                 .unwrap_or(HassSensorKind::Ignore);
@@ -1178,22 +2657,17 @@ impl HassBackend {
             }
         }
         if !include {
+            // entities that went unavailable are kept until the next sync,
+            // but reported as unreachable
+            if let Some(binding) = self.entity_map.get(&imported.entity_id) {
+                self.set_reachable(&binding.device_link, imported.available)
+                    .await?;
+            }
             return Ok(());
         }
 
-        if let Some(alias) = ui_config.entity_alias(&imported.entity_id) {
-            imported.name = alias;
-        }
-        if matches!(imported.kind, HassEntityKind::Switch) {
-            imported.switch_mode = Some(ui_config.switch_mode(&imported.entity_id));
-        }
-        if matches!(imported.kind, HassEntityKind::Light)
-            || (matches!(imported.kind, HassEntityKind::Switch)
-                && imported.switch_mode == Some(HassSwitchMode::Light))
-        {
-            imported.light_archetype = Some(ui_config.light_archetype(&imported.entity_id));
-        }
-        if matches!(imported.kind, HassEntityKind::BinarySensor) {
+        imported.apply_preferences(&ui_config);
+        if imported.kind.is_binary_sensor() {
             let detected = imported
                 .detected_sensor_kind
                 .unwrap_or(HassSensorKind::Ignore);
@@ -1207,29 +2681,449 @@ impl HassBackend {
 
         let state = self.state.clone();
         let mut res = state.lock().await;
+        // confirms requests for this entity (see [`crate::server::latency`])
+        if let Some(binding) = self.entity_map.get(&imported.entity_id) {
+            res.backend_report(&binding.service_link.rid);
+        }
         self.ensure_rooms(&mut res, &ui_config)?;
         self.sync_single_entity(&imported, &mut res)?;
 
+        let room_id = Self::assigned_room_id(&ui_config, &imported);
+        if imported.kind.groups_as_light(imported.switch_mode) {
+            self.note_room_light(&imported, room_id.clone());
+            self.refresh_room_state(&ui_config, &room_id, &mut res)?;
+        }
+        drop(res);
+
+        self.flush_room_notices().await;
         Ok(())
     }
 
     pub(super) async fn remove_entity_by_id(&mut self, entity_id: &str) -> ApiResult<()> {
-        let device_link =
-            RType::Device.deterministic(format!("hass:{}:{}:device", self.name, entity_id));
+        let binding = self.forget_entity(entity_id);
 
         {
             let mut res = self.state.lock().await;
-            let _ = res.delete(&device_link);
+            let _ = match binding {
+                Some(binding) => self.delete_entity_resources(&mut res, &binding),
+                None => res.delete(&self.links_for_entity(entity_id, HassServiceKind::Light).0),
+            };
         }
 
-        if let Some(binding) = self.entity_map.remove(entity_id) {
-            self.light_map.remove(&binding.service_link.rid);
-            self.sensor_map.remove(&binding.service_link.rid);
-            self.device_map.remove(&binding.device_link.rid);
+        self.ui_log_entry(
+            TimelineEntry::new(
+                TimelineCategory::Sync,
+                format!("Removed {entity_id} from Hue bridge"),
+            )
+            .with_entity(entity_id),
+        )
+        .await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use camino::Utf8PathBuf;
+    use tokio::sync::Mutex;
+
+    use hue::api::{Device, Light, LightLevel, LightMetadata, RType};
+    use hue::version::SwVersion;
+
+    use crate::backend::hass::HassBackend;
+    use crate::backend::hass::import::{
+        ImportedEntity, apply_light_state, celsius, entity_on, label_names, light_archetype,
+        light_level, light_level_report, parse_imported_entity,
+    };
+    use crate::model::hass::{
+        HassConflictPolicy, HassEntityKind, HassLightCapabilities, HassRuntimeState,
+        HassSensorKind, HassSensorThreshold, HassServiceKind, HassSwitchMode, HassUiConfig,
+        HassUiState,
+    };
+    use crate::model::state::State;
+    use crate::resource::Resources;
+
+    pub(in crate::backend::hass) fn backend(dir: &Utf8PathBuf) -> HassBackend {
+        let server = serde_json::from_value(serde_json::json!({
+            "url": "http://localhost:8123/",
+        }))
+        .unwrap();
+        HassBackend::new(
+            "home".to_string(),
+            server,
+            None,
+            Arc::new(Mutex::new(Resources::new(
+                SwVersion::default(),
+                State::new(),
+            ))),
+            Arc::new(Mutex::new(HassUiState::load(dir.join("ui.yaml")).unwrap())),
+            Arc::new(Mutex::new(
+                HassRuntimeState::load(dir.join("runtime.yaml"), None).unwrap(),
+            )),
+        )
+        .unwrap()
+    }
+
+    pub(in crate::backend::hass) fn sensor(
+        entity_id: &str,
+        kind: HassEntityKind,
+        service_kind: HassServiceKind,
+    ) -> ImportedEntity {
+        ImportedEntity {
+            entity_id: entity_id.to_string(),
+            unique_id: None,
+            labels: vec![],
+            name: entity_id.to_string(),
+            kind,
+            service_kind,
+            state: "off".to_string(),
+            available: true,
+            on: false,
+            brightness: None,
+            xy_color: None,
+            color_temp: None,
+            area_name: None,
+            capabilities: HassLightCapabilities::default(),
+            detected_sensor_kind: None,
+            sensor_enabled: true,
+            switch_mode: None,
+            light_archetype: None,
+            conflict_policy: HassConflictPolicy::default(),
+            last_updated: None,
+            device_id: Some("hallway".to_string()),
+            measurement: Some(10.0),
+            button_report: None,
+            event_values: vec![],
+            control_id: None,
+            device_class: None,
         }
+    }
 
-        self.ui_log(format!("Removed {} from Hue bridge", entity_id))
-            .await;
-        Ok(())
+    #[test]
+    fn access_covers_excluded_by_default() {
+        let mut config = HassUiConfig {
+            default_add_new_devices_to_hue: true,
+            ..HassUiConfig::default()
+        };
+        let mut garage = sensor(
+            "cover.garage",
+            HassEntityKind::Cover,
+            HassServiceKind::Light,
+        );
+        garage.device_class = Some("garage".to_string());
+        let mut blind = sensor("cover.blind", HassEntityKind::Cover, HassServiceKind::Light);
+        blind.device_class = Some("blind".to_string());
+
+        assert!(!garage.is_included(&config));
+        assert!(blind.is_included(&config));
+
+        config.set_entity_hidden("cover.garage", false);
+        assert!(garage.is_included(&config));
+    }
+
+    #[test]
+    fn covers_by_position() {
+        let parse = |state: &str, attributes: serde_json::Value| {
+            let state = serde_json::from_value(serde_json::json!({
+                "entity_id": "cover.blind",
+                "state": state,
+                "attributes": attributes,
+            }))
+            .unwrap();
+            parse_imported_entity(&state, None).unwrap()
+        };
+
+        let blind = parse(
+            "open",
+            serde_json::json!({"current_position": 40, "supported_features": 15}),
+        );
+        assert_eq!(blind.kind, HassEntityKind::Cover);
+        assert_eq!(blind.service_kind, HassServiceKind::Light);
+        assert!(blind.on);
+        assert!(blind.capabilities.supports_brightness);
+        assert!(!blind.capabilities.supports_color);
+        assert_eq!(blind.brightness, Some(102.0));
+
+        // closing covers are still (partially) open
+        assert!(parse("opening", serde_json::json!({})).on);
+        assert!(!parse("closing", serde_json::json!({})).on);
+        assert!(!parse("closed", serde_json::json!({"current_position": 0})).on);
+
+        // covers that cannot be positioned only open and close
+        let garage = parse("open", serde_json::json!({"supported_features": 3}));
+        assert!(garage.on);
+        assert!(!garage.capabilities.supports_brightness);
+        assert_eq!(garage.brightness, None);
+    }
+
+    #[test]
+    fn fans_by_speed() {
+        let parse = |state: &str, attributes: serde_json::Value| {
+            let state = serde_json::from_value(serde_json::json!({
+                "entity_id": "fan.ceiling",
+                "state": state,
+                "attributes": attributes,
+            }))
+            .unwrap();
+            parse_imported_entity(&state, None).unwrap()
+        };
+
+        let mut fan = parse(
+            "on",
+            serde_json::json!({"percentage": 60, "supported_features": 1}),
+        );
+        assert_eq!(fan.kind, HassEntityKind::Fan);
+        assert_eq!(fan.service_kind, HassServiceKind::Switch);
+        assert!(fan.on);
+        assert!(fan.capabilities.supports_brightness);
+        assert_eq!(fan.brightness, Some(153.0));
+
+        // fans are lights by default, dimmed by changing their speed
+        let mut config = HassUiConfig::default();
+        fan.apply_preferences(&config);
+        assert_eq!(fan.switch_mode, Some(HassSwitchMode::Light));
+
+        let device = RType::Device.link_to(uuid::Uuid::new_v4());
+        let mut light = Light::new(device, LightMetadata::new(light_archetype(&fan), &fan.name));
+        apply_light_state(&mut light, &fan);
+        assert_eq!(light.dimming.map(|dim| dim.brightness), Some(60.0));
+        assert!(light.color.is_none());
+
+        // fans shown as plugs are only switched on and off
+        config.set_entity_switch_mode("fan.ceiling", Some(HassSwitchMode::Plug));
+        fan.apply_preferences(&config);
+        apply_light_state(&mut light, &fan);
+        assert!(light.dimming.is_none());
+
+        // fans without speed control cannot be dimmed
+        let fan = parse("off", serde_json::json!({"supported_features": 0}));
+        assert!(!fan.on);
+        assert!(!fan.capabilities.supports_brightness);
+        assert_eq!(fan.brightness, None);
+    }
+
+    #[test]
+    fn kinds_by_domain() {
+        let parse = |entity_id: &str, device_class: Option<&str>| {
+            let state = serde_json::from_value(serde_json::json!({
+                "entity_id": entity_id,
+                "state": "21",
+                "attributes": { "device_class": device_class },
+            }))
+            .unwrap();
+            parse_imported_entity(&state, None).unwrap()
+        };
+
+        for (entity_id, device_class, kind) in [
+            ("lock.front", None, HassEntityKind::Lock),
+            ("script.movie", None, HassEntityKind::Script),
+            ("button.doorbell", None, HassEntityKind::Button),
+            ("sensor.co2", None, HassEntityKind::Sensor),
+            (
+                "sensor.hallway",
+                Some("temperature"),
+                HassEntityKind::Temperature,
+            ),
+        ] {
+            let imported = parse(entity_id, device_class);
+            assert_eq!(imported.kind, kind, "{entity_id}");
+            assert_eq!(Some(imported.domain()), entity_id.split('.').next());
+        }
+
+        // numeric sensors are only imported with a threshold
+        let mut config = HassUiConfig::default();
+        let mut co2 = parse("sensor.co2", None);
+        assert!(!co2.is_supported(&config));
+        config.sensor_thresholds.insert(
+            "sensor.hallway".to_string(),
+            HassSensorThreshold {
+                sensor_kind: HassSensorKind::Motion,
+                below: None,
+                above: Some(20.0),
+            },
+        );
+        let mut hallway = parse("sensor.hallway", Some("temperature"));
+        hallway.apply_threshold(&config);
+        co2.apply_threshold(&config);
+        assert_eq!(hallway.kind, HassEntityKind::Sensor);
+        assert!(hallway.on);
+        assert!(hallway.is_supported(&config));
+        assert_eq!(co2.kind, HassEntityKind::Sensor);
+        assert!(!co2.is_supported(&config));
+    }
+
+    #[test]
+    fn lock_states() {
+        for state in ["unlocked", "unlocking", "open", "opening", "jammed"] {
+            assert!(entity_on(HassEntityKind::Lock, state), "{state}");
+        }
+        for state in ["locked", "locking", "unavailable", "unknown"] {
+            assert!(!entity_on(HassEntityKind::Lock, state), "{state}");
+        }
+
+        // only locks know these states
+        assert!(entity_on(HassEntityKind::BinarySensor, "on"));
+        for state in ["off", "open", "jammed", "unlocked"] {
+            assert!(!entity_on(HassEntityKind::BinarySensor, state), "{state}");
+        }
+    }
+
+    #[test]
+    fn light_level_scale() {
+        assert_eq!(light_level_report(light_level(0.0)), 0);
+        assert_eq!(light_level_report(light_level(0.5)), 0);
+        assert_eq!(light_level_report(light_level(1.0)), 1);
+        assert_eq!(light_level_report(light_level(10.0)), 10001);
+        assert_eq!(light_level_report(light_level(1000.0)), 30001);
+        assert_eq!(light_level_report(-5.0), 0);
+        assert_eq!(light_level_report(1.5), 2);
+    }
+
+    #[test]
+    fn celsius_units() {
+        assert!((celsius(21.5, None) - 21.5).abs() < f64::EPSILON);
+        assert!((celsius(21.5, Some("°C")) - 21.5).abs() < f64::EPSILON);
+        assert!((celsius(212.0, Some("°F")) - 100.0).abs() < f64::EPSILON);
+        assert!(celsius(32.0, Some("F")).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn labels_by_name() {
+        let names = HashMap::from([("hue_lamp".to_string(), "Hue lamp".to_string())]);
+        let ids = vec!["hue_lamp".to_string(), "unknown".to_string()];
+
+        assert_eq!(label_names(&ids, &names), ["Hue lamp", "unknown"]);
+    }
+
+    #[test]
+    fn restore_hosted_bindings() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-hass-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let mut svc = backend(&dir);
+        svc.sync_single_entity(
+            &sensor(
+                "binary_sensor.hallway_motion",
+                HassEntityKind::BinarySensor,
+                HassServiceKind::Motion,
+            ),
+            &mut res,
+        )
+        .unwrap();
+        svc.sync_single_entity(
+            &sensor(
+                "sensor.hallway_illuminance",
+                HassEntityKind::LightLevel,
+                HassServiceKind::LightLevel,
+            ),
+            &mut res,
+        )
+        .unwrap();
+
+        // a restarted backend restores both the host and the hosted entity
+        let mut restarted = backend(&dir);
+        let restored = restarted.restore_bindings(&mut res, &[]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(restored, 2);
+        let motion = &restarted.entity_map["binary_sensor.hallway_motion"];
+        let level = &restarted.entity_map["sensor.hallway_illuminance"];
+        assert_eq!(level.device_link, motion.device_link);
+        assert_eq!(
+            restarted.device_map[&motion.device_link.rid],
+            "binary_sensor.hallway_motion"
+        );
+        assert!(restarted.sensor_map.contains_key(&level.service_link.rid));
+    }
+
+    #[test]
+    fn renames_by_unique_id() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-hass-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let mut svc = backend(&dir);
+        let mut old = sensor("light.old", HassEntityKind::Light, HassServiceKind::Light);
+        old.unique_id = Some("hue:1".to_string());
+        svc.sync_single_entity(&old, &mut res).unwrap();
+        let links = svc.links_for_imported(&old);
+
+        let mut new = old.clone();
+        new.entity_id = "light.new".to_string();
+        let other = sensor("light.other", HassEntityKind::Light, HassServiceKind::Light);
+
+        assert_eq!(
+            svc.detect_renames(&[new.clone(), other.clone()]),
+            [("light.old".to_string(), "light.new".to_string())]
+        );
+        // the old entity still exists, so this is a different entity
+        assert!(svc.detect_renames(&[old, new.clone(), other]).is_empty());
+
+        // the renamed entity keeps the resources of the old one
+        svc.sync_single_entity(&new, &mut res).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!svc.entity_map.contains_key("light.old"));
+        assert_eq!(svc.entity_map["light.new"].entity_id, "light.new");
+        assert_eq!(svc.links_for_imported(&new), links);
+        assert!(svc.detect_renames(&[new]).is_empty());
+    }
+
+    #[test]
+    fn light_level_moves_to_motion_device() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-hass-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let mut svc = backend(&dir);
+        let motion = sensor(
+            "binary_sensor.hallway_motion",
+            HassEntityKind::BinarySensor,
+            HassServiceKind::Motion,
+        );
+        let illuminance = sensor(
+            "sensor.hallway_illuminance",
+            HassEntityKind::LightLevel,
+            HassServiceKind::LightLevel,
+        );
+
+        // the light level sensor was synced before its host was known
+        svc.sync_single_entity(&illuminance, &mut res).unwrap();
+        let old_device = svc.entity_map["sensor.hallway_illuminance"].device_link;
+
+        let imported = [motion.clone(), illuminance.clone()]
+            .into_iter()
+            .map(|imported| (imported.entity_id.clone(), imported))
+            .collect();
+        svc.resolve_hosts(&imported);
+        svc.sync_single_entity(&motion, &mut res).unwrap();
+        svc.sync_single_entity(&illuminance, &mut res).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let host = svc.entity_map["binary_sensor.hallway_motion"].device_link;
+        let level = &svc.entity_map["sensor.hallway_illuminance"];
+        assert_eq!(level.device_link, host);
+        assert!(res.get::<Device>(&old_device).is_err());
+        assert!(!svc.device_map.contains_key(&old_device.rid));
+        assert_eq!(
+            res.get::<LightLevel>(&level.service_link).unwrap().owner,
+            host
+        );
+        assert!(
+            res.get::<Device>(&host)
+                .unwrap()
+                .services
+                .contains(&level.service_link)
+        );
     }
 }
@@ -1,30 +1,61 @@
 mod backend_event;
+mod battery;
+mod breaker;
 mod client;
+mod daylight;
+mod health;
 mod import;
-
-use std::collections::HashMap;
+mod precedence;
+mod remote;
+mod render;
+mod retry;
+mod room_groups;
+mod verify;
+
+use std::collections::{BTreeSet, HashMap};
+use std::num::{NonZeroU32, NonZeroUsize};
 use std::sync::Arc;
 use std::time::Instant;
 
 use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use serde_json::{Map, json};
 use svc::error::SvcError;
+use svc::policy::Policy;
 use svc::template::ServiceTemplate;
 use svc::traits::{BoxDynService, Service};
 use thiserror::Error;
-use tokio::sync::{Mutex, broadcast::Receiver};
+use tokio::sync::{Mutex, broadcast::Receiver, mpsc};
 use tokio::time::{Duration, MissedTickBehavior, interval};
+use url::Url;
 use uuid::Uuid;
 
-use bifrost_api::backend::BackendRequest;
-use bifrost_api::config::HassServer;
+use bifrost_api::backend::{BackendCapabilities, BackendRequest, BifrostActivity, HassRequest};
 use hue::api::{RType, ResourceLink};
 
+use crate::backend::router::received;
+use crate::config::{HassServer, LinkButtonConfig};
 use crate::error::{ApiError, ApiResult};
-use crate::model::hass::{HassRoomConfig, HassRuntimeState, HassSwitchMode, HassUiState};
+use crate::model::hass::{
+    HassEntityAux, HassEntityKind, HassLightBrightness, HassLightCapabilities, HassRoomConfig,
+    HassRuntimeState, HassServiceKind, HassSwitchMode, HassUiState,
+};
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
 use crate::resource::Resources;
 use crate::server::appstate::AppState;
-
-use self::client::{HassClient, HassWs};
+use crate::server::linkbutton::LinkButton;
+use crate::server::presence::Presence;
+
+use self::battery::Batteries;
+use self::breaker::CircuitBreaker;
+use self::client::{
+    HassClient, HassEntityRegistryEntry, HassStateChangedEvent, HassWs, HassWsEvent,
+};
+use self::daylight::Daylight;
+use self::health::StreamHealth;
+use self::precedence::PendingLightUpdate;
+use self::render::SoftwareRenderer;
+use self::retry::RetryQueue;
 
 #[derive(Error, Debug)]
 pub enum TemplateError {
@@ -32,31 +63,10 @@ pub enum TemplateError {
     NotFound(String),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub(super) enum HassEntityKind {
-    Light,
-    Switch,
-    BinarySensor,
-}
-
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub(super) enum HassServiceKind {
-    Light,
-    Switch,
-    Motion,
-    Contact,
-}
-
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub(super) struct HassLightCapabilities {
-    pub supports_brightness: bool,
-    pub supports_color: bool,
-    pub supports_color_temp: bool,
-}
-
 #[derive(Clone, Debug)]
 pub(super) struct HassEntityBinding {
     pub entity_id: String,
+    pub unique_id: Option<String>,
     pub name: String,
     pub kind: HassEntityKind,
     pub service_kind: HassServiceKind,
@@ -66,12 +76,55 @@ pub(super) struct HassEntityBinding {
     pub switch_mode: Option<HassSwitchMode>,
 }
 
+impl HassEntityBinding {
+    /// Persistent form of this binding, for backend `backend`
+    fn to_aux(&self, backend: &str) -> HassEntityAux {
+        HassEntityAux {
+            backend: backend.to_string(),
+            entity_id: self.entity_id.clone(),
+            unique_id: self.unique_id.clone(),
+            name: self.name.clone(),
+            kind: self.kind,
+            service_kind: self.service_kind,
+            service_link: self.service_link,
+            capabilities: self.capabilities,
+            switch_mode: self.switch_mode,
+        }
+    }
+
+    fn from_aux(device_link: ResourceLink, aux: HassEntityAux) -> Self {
+        Self {
+            entity_id: aux.entity_id,
+            unique_id: aux.unique_id,
+            name: aux.name,
+            kind: aux.kind,
+            service_kind: aux.service_kind,
+            service_link: aux.service_link,
+            device_link,
+            capabilities: aux.capabilities,
+            switch_mode: aux.switch_mode,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(super) struct HassRoomBinding {
     pub room_id: String,
     pub room_name: String,
     pub room_link: ResourceLink,
     pub grouped_light_link: ResourceLink,
+    /// The links point to a room of another backend, that the devices of this
+    /// room were merged into
+    pub merged: bool,
+}
+
+/// A light of a room, as last reported by Home Assistant, to derive the
+/// state of the room from
+#[derive(Clone, Debug)]
+pub(super) struct HassRoomLight {
+    pub room_id: String,
+    pub on: bool,
+    pub brightness: Option<HassLightBrightness>,
 }
 
 pub struct HassServiceTemplate {
@@ -95,14 +148,41 @@ impl ServiceTemplate for HassServiceTemplate {
         let svc = HassBackend::new(
             name,
             server.clone(),
+            config.bifrost.proxy.clone(),
             self.state.res.clone(),
             self.state.hass_ui(),
             self.state.hass_runtime(),
         )
-        .map_err(SvcError::generation)?;
+        .map_err(SvcError::generation)?
+        .with_linkbutton(self.state.linkbutton(), config.linkbutton.clone())
+        .with_presence(self.state.presence(), config.presence.hass_entity.clone());
 
         Ok(svc.boxed())
     }
+
+    fn run_policy(&self, instance: &str) -> Option<Policy> {
+        let policy = self
+            .state
+            .config()
+            .service_policy(&format!("hass@{instance}"));
+        Some(policy)
+    }
+}
+
+/// Default heartbeat entity of backend `name`, so each Home Assistant
+/// backend reports its own status
+fn heartbeat_entity(name: &str) -> String {
+    let object_id: String = name
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("binary_sensor.bifrost_{object_id}")
 }
 
 pub struct HassBackend {
@@ -117,20 +197,72 @@ pub struct HassBackend {
     sensor_map: HashMap<Uuid, String>,
     device_map: HashMap<Uuid, String>,
     room_map: HashMap<String, HassRoomBinding>,
+    /// Lights grouped in rooms, by entity id
+    room_lights: HashMap<String, HassRoomLight>,
     scene_map: HashMap<Uuid, String>,
     ws: Option<HassWs>,
+    linkbutton: Option<LinkButton>,
+    linkbutton_config: LinkButtonConfig,
+    presence: Option<(Presence, String)>,
+    retry: RetryQueue,
+    pending_lights: HashMap<Uuid, PendingLightUpdate>,
+    room_groups: HashMap<String, Vec<String>>,
+    /// Fingerprint of the inputs each entity was last imported with
+    fingerprints: HashMap<String, u64>,
+    /// Hue devices of motion sensors, by Home Assistant device id. Light
+    /// level sensors of the same device are attached to them.
+    motion_devices: HashMap<String, ResourceLink>,
+    /// Entity registry of the last full sync, by entity id, used for syncing
+    /// single entities
+    registry: HashMap<String, HassEntityRegistryEntry>,
+    /// Label names of the last full sync, by label id
+    label_names: HashMap<String, String>,
+    daylight: Daylight,
+    batteries: Batteries,
+    breaker: CircuitBreaker,
+    /// Day of the last nightly reconcile
+    last_reconcile: Option<NaiveDate>,
+    health: StreamHealth,
+    /// Entertainment stream rendering, while streaming to our lights
+    renderer: Option<SoftwareRenderer>,
+    /// Room merges found while updating rooms, waiting to be shown in the
+    /// activity timeline
+    room_notices: Vec<TimelineEntry>,
+    /// Rooms already warned about, for sharing their name with a room of
+    /// another backend
+    room_duplicates: BTreeSet<String>,
 }
 
 impl HassBackend {
+    const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+    const DEFAULT_SYNC_CHUNK_SIZE: usize = 50;
+    const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(5);
+    const DEFAULT_RECONCILE_HOUR: u32 = 3;
+
+    /// Name of the backend started when no Home Assistant servers are
+    /// configured (connected from the web ui instead)
+    pub const RUNTIME_NAME: &str = "runtime";
+
     pub fn new(
         name: String,
         server: HassServer,
+        proxy: Option<Url>,
         state: Arc<Mutex<Resources>>,
         ui_state: Arc<Mutex<HassUiState>>,
         runtime_state: Arc<Mutex<HassRuntimeState>>,
     ) -> ApiResult<Self> {
+        let retry = RetryQueue::new(
+            server
+                .retry_queue_size
+                .unwrap_or(RetryQueue::DEFAULT_CAPACITY),
+            server
+                .retry_ttl_secs
+                .map_or(RetryQueue::DEFAULT_TTL, |secs| {
+                    Duration::from_secs(u64::from(secs.get()))
+                }),
+        );
         Ok(Self {
-            client: HassClient::new(&name, &server)?,
+            client: HassClient::new(&name, &server, proxy)?,
             name,
             server,
             state,
@@ -141,11 +273,67 @@ impl HassBackend {
             sensor_map: HashMap::new(),
             device_map: HashMap::new(),
             room_map: HashMap::new(),
+            room_lights: HashMap::new(),
             scene_map: HashMap::new(),
             ws: None,
+            linkbutton: None,
+            linkbutton_config: LinkButtonConfig::default(),
+            presence: None,
+            retry,
+            pending_lights: HashMap::new(),
+            room_groups: HashMap::new(),
+            fingerprints: HashMap::new(),
+            motion_devices: HashMap::new(),
+            registry: HashMap::new(),
+            label_names: HashMap::new(),
+            daylight: Daylight::default(),
+            batteries: Batteries::default(),
+            breaker: CircuitBreaker::default(),
+            last_reconcile: None,
+            health: StreamHealth::default(),
+            renderer: None,
+            room_notices: Vec::new(),
+            room_duplicates: BTreeSet::new(),
         })
     }
 
+    /// Allow the configured Home Assistant entity/event to press the link button
+    #[must_use]
+    pub fn with_linkbutton(self, linkbutton: LinkButton, config: LinkButtonConfig) -> Self {
+        Self {
+            linkbutton: Some(linkbutton),
+            linkbutton_config: config,
+            ..self
+        }
+    }
+
+    /// Let the configured Home Assistant entity enable the presence simulation
+    #[must_use]
+    pub fn with_presence(self, presence: Presence, entity: Option<String>) -> Self {
+        Self {
+            presence: entity.map(|entity| (presence, entity)),
+            ..self
+        }
+    }
+
+    /// Id used to route backend requests to this backend
+    pub(super) fn backend_id(&self) -> String {
+        format!("hass@{}", self.name)
+    }
+
+    /// Home Assistant lights have no gradients, and can only join
+    /// entertainment areas when rendered in software
+    #[must_use]
+    pub fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            scenes: true,
+            entertainment: self.entertainment_fallback(),
+            gradients: false,
+            sensors: true,
+            buttons: true,
+        }
+    }
+
     pub(super) fn room_links_for_id(&self, room_id: &str) -> (ResourceLink, ResourceLink) {
         (
             RType::Room.deterministic(format!("hass:{}:room:{}", self.name, room_id)),
@@ -160,19 +348,23 @@ impl HassBackend {
             room_name: room.name.clone(),
             room_link,
             grouped_light_link,
+            merged: false,
         }
     }
 
-    pub(super) async fn ui_log(&self, message: impl AsRef<str>) {
+    pub(super) async fn ui_log(&self, category: TimelineCategory, message: impl Into<String>) {
+        self.ui_log_entry(TimelineEntry::new(category, message))
+            .await;
+    }
+
+    /// Add entry to the activity timeline, with this backend as the source
+    pub(super) async fn ui_log_entry(&self, entry: TimelineEntry) {
         let mut ui = self.ui_state.lock().await;
-        ui.push_log(format!("[{}] {}", self.name, message.as_ref()));
+        ui.push_entry(entry.with_source(&self.name));
     }
 
     fn token_env_name(&self) -> String {
-        self.server
-            .token_env
-            .clone()
-            .unwrap_or_else(|| "HASS_TOKEN".to_string())
+        self.server.token_env().to_string()
     }
 
     async fn apply_runtime_connection(&mut self) -> ApiResult<()> {
@@ -191,7 +383,7 @@ impl HassBackend {
         let url = if runtime_url.trim().is_empty() {
             self.server.url.clone()
         } else {
-            url::Url::parse(runtime_url.trim())?
+            Url::parse(runtime_url.trim())?
         };
 
         if let Some(token) = runtime_token {
@@ -209,30 +401,68 @@ impl HassBackend {
         })
     }
 
+    fn sync_chunk_size(&self) -> usize {
+        self.server
+            .sync_chunk_size
+            .map_or(Self::DEFAULT_SYNC_CHUNK_SIZE, NonZeroUsize::get)
+    }
+
     async fn run_sync(&mut self, reason: &str) -> ApiResult<()> {
-        {
-            let mut ui = self.ui_state.lock().await;
-            ui.mark_sync_started();
-            ui.push_log(format!("Sync requested: {reason}"));
-        }
+        self.ui_state.lock().await.mark_sync_started();
+        self.ui_log(TimelineCategory::Sync, format!("Sync requested: {reason}"))
+            .await;
+
+        let timeout = self
+            .server
+            .sync_timeout_secs
+            .map_or(Self::DEFAULT_SYNC_TIMEOUT, |secs| {
+                Duration::from_secs(u64::from(secs.get()))
+            });
 
         let start = Instant::now();
-        let result = self.sync_entities().await;
+        let result = tokio::time::timeout(timeout, self.sync_entities())
+            .await
+            .unwrap_or_else(|_| {
+                Err(ApiError::service_error(format!(
+                    "[{}] Sync timed out after {}s",
+                    self.name,
+                    timeout.as_secs()
+                )))
+            });
         let elapsed_u128 = start.elapsed().as_millis();
         let elapsed = u64::try_from(elapsed_u128).unwrap_or(u64::MAX);
 
-        let mut ui = self.ui_state.lock().await;
-        match &result {
+        let entry = match &result {
             Ok(()) => {
-                ui.mark_sync_finished(Ok(elapsed));
-                ui.push_log(format!("Sync completed in {elapsed}ms"));
+                self.ui_state.lock().await.mark_sync_finished(Ok(elapsed));
+                TimelineEntry::new(
+                    TimelineCategory::Sync,
+                    format!("Sync completed in {elapsed}ms"),
+                )
             }
             Err(err) => {
-                ui.mark_sync_finished(Err(err.to_string()));
-                ui.push_log(format!("Sync failed: {err}"));
+                self.ui_state
+                    .lock()
+                    .await
+                    .mark_sync_finished(Err(err.to_string()));
+                TimelineEntry::new(TimelineCategory::Sync, format!("Sync failed: {err}"))
+                    .with_level(TimelineLevel::Error)
+            }
+        };
+        self.ui_log_entry(entry).await;
+
+        if result.is_ok() {
+            if let Err(err) = self.export_room_groups().await {
+                self.ui_log_entry(
+                    TimelineEntry::new(
+                        TimelineCategory::Sync,
+                        format!("Failed to export rooms as light groups: {err}"),
+                    )
+                    .with_level(TimelineLevel::Warning),
+                )
+                .await;
             }
         }
-        drop(ui);
 
         result
     }
@@ -255,11 +485,21 @@ impl HassBackend {
             return;
         }
 
-        match self.client.subscribe_state_changed().await {
+        let extra_events: Vec<&str> = self
+            .linkbutton_config
+            .hass_event
+            .as_deref()
+            .into_iter()
+            .collect();
+
+        match self.client.subscribe_events(&extra_events).await {
             Ok(ws) => {
                 self.ws = Some(ws);
-                self.ui_log("Realtime state sync connected (Home Assistant websocket)")
-                    .await;
+                self.ui_log(
+                    TimelineCategory::Connection,
+                    "Realtime state sync connected (Home Assistant websocket)",
+                )
+                .await;
             }
             Err(err) => {
                 log::debug!("[{}] WS connect failed: {}", self.name, err);
@@ -267,7 +507,210 @@ impl HassBackend {
         }
     }
 
-    async fn event_loop(&mut self, chan: &mut Receiver<Arc<BackendRequest>>) -> ApiResult<()> {
+    /// True if this state change should press the link button.
+    ///
+    /// Toggle-like entities (e.g. `input_boolean`) press the button when turned
+    /// on, while `button`/`input_button` entities press it whenever their state
+    /// (the timestamp of the last press) changes.
+    fn is_linkbutton_press(&self, ev: &HassStateChangedEvent) -> bool {
+        if self.linkbutton_config.hass_entity.as_ref() != Some(&ev.entity_id) {
+            return false;
+        }
+
+        let old = ev.old_state.as_ref().map(|st| st.state.as_str());
+        let Some(new) = ev.new_state.as_ref().map(|st| st.state.as_str()) else {
+            return false;
+        };
+
+        if matches!(new, "unavailable" | "unknown") || old == Some(new) {
+            return false;
+        }
+
+        let domain = ev.entity_id.split('.').next().unwrap_or_default();
+        matches!(domain, "button" | "input_button") || new == "on"
+    }
+
+    /// Follow the presence simulation entity, if this state change is for it
+    async fn update_presence(&self, ev: &HassStateChangedEvent) {
+        let Some((presence, entity)) = &self.presence else {
+            return;
+        };
+
+        if &ev.entity_id != entity {
+            return;
+        }
+
+        match ev.new_state.as_ref().map(|st| st.state.as_str()) {
+            Some("on") => presence.set_enabled(true).await,
+            Some("off") => presence.set_enabled(false).await,
+            _ => {}
+        }
+    }
+
+    /// Read the current state of the presence simulation entity
+    async fn sync_presence(&self) {
+        let Some((presence, entity)) = &self.presence else {
+            return;
+        };
+
+        match self.client.get_state(entity).await {
+            Ok(state) => presence.set_enabled(state.state == "on").await,
+            Err(err) => log::warn!(
+                "[{}] Cannot read presence simulation entity {entity}: {err}",
+                self.name
+            ),
+        }
+    }
+
+    /// Publish the status of bifrost to the heartbeat entity in Home
+    /// Assistant (if enabled).
+    ///
+    /// The entity is not updated when bifrost goes down unexpectedly, so
+    /// automations should check how long ago it was last updated.
+    async fn publish_heartbeat(&self, online: bool) {
+        if self.server.heartbeat_secs.is_none() {
+            return;
+        }
+
+        let entity = self
+            .server
+            .heartbeat_entity
+            .clone()
+            .unwrap_or_else(|| heartbeat_entity(&self.name));
+
+        let lights = self
+            .state
+            .lock()
+            .await
+            .get_resource_ids_by_type(RType::Light)
+            .len();
+
+        let mut attrs = Map::new();
+        attrs.insert(
+            "friendly_name".to_string(),
+            json!(format!("Bifrost ({})", self.name)),
+        );
+        attrs.insert("device_class".to_string(), json!("connectivity"));
+        attrs.insert("version".to_string(), json!(env!("CARGO_PKG_VERSION")));
+        attrs.insert("backend".to_string(), json!(self.name));
+        attrs.insert("entities".to_string(), json!(self.entity_map.len()));
+        attrs.insert("lights".to_string(), json!(lights));
+        attrs.insert("last_heartbeat".to_string(), json!(Utc::now().to_rfc3339()));
+
+        // runs inside the event loop, so a slow Home Assistant must not hold
+        // up event handling
+        let state = if online { "on" } else { "off" };
+        let publish = self.client.set_state(&entity, state, attrs);
+        match tokio::time::timeout(Self::HEARTBEAT_TIMEOUT, publish).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::debug!(
+                "[{}] Failed to publish heartbeat to {entity}: {err}",
+                self.name
+            ),
+            Err(_) => log::debug!("[{}] Timed out publishing heartbeat to {entity}", self.name),
+        }
+    }
+
+    /// True if a light came back on after being unavailable (typically
+    /// after a power cut).
+    fn is_power_restore(ev: &HassStateChangedEvent) -> bool {
+        let old = ev.old_state.as_ref().map(|st| st.state.as_str());
+        let new = ev.new_state.as_ref().map(|st| st.state.as_str());
+
+        old == Some("unavailable") && new == Some("on")
+    }
+
+    /// Apply the configured startup state (if any) to a light
+    async fn apply_light_startup(&mut self, entity_id: &str) -> ApiResult<()> {
+        let Some(binding) = self.entity_map.get(entity_id).cloned() else {
+            return Ok(());
+        };
+
+        if !matches!(binding.kind, HassEntityKind::Light) {
+            return Ok(());
+        }
+
+        let startup = self
+            .state
+            .lock()
+            .await
+            .light_startup(&binding.service_link)
+            .cloned();
+        let Some(startup) = startup else {
+            return Ok(());
+        };
+
+        log::info!(
+            "[{}] {entity_id} is back after power loss, applying startup state {startup:?}",
+            self.name
+        );
+
+        self.backend_light_update(&binding, &startup.light_update())
+            .await
+    }
+
+    async fn press_linkbutton(&self, source: &str) {
+        let Some(linkbutton) = &self.linkbutton else {
+            return;
+        };
+
+        log::info!("[{}] Link button pressed via {source}", self.name);
+        linkbutton.press(LinkButton::PRESS_DURATION).await;
+        self.state
+            .lock()
+            .await
+            .forward_activity(BifrostActivity::LinkButton {
+                source: format!("hass:{source}"),
+            });
+        self.ui_log(
+            TimelineCategory::Pairing,
+            format!(
+                "Virtual bridge button pressed via {source} ({}s active)",
+                LinkButton::PRESS_DURATION.as_secs()
+            ),
+        )
+        .await;
+    }
+
+    /// Log (and skip) a failed event, unless too many events failed recently,
+    /// in which case the error is returned, to get the service restarted.
+    async fn isolate_failure(&mut self, what: &str, result: ApiResult<()>) -> ApiResult<()> {
+        let Err(err) = result else {
+            return Ok(());
+        };
+
+        let tripped = self.breaker.record_failure();
+        log::error!(
+            "[{}] Failed to handle {what} ({} failures so far): {err}",
+            self.name,
+            self.breaker.total()
+        );
+        self.ui_log_entry(
+            TimelineEntry::new(
+                TimelineCategory::Service,
+                format!("Failed to handle {what}: {err}"),
+            )
+            .with_level(TimelineLevel::Error),
+        )
+        .await;
+
+        if tripped {
+            self.breaker.reset();
+            log::error!(
+                "[{}] Too many failures, restarting Home Assistant backend",
+                self.name
+            );
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn event_loop(
+        &mut self,
+        chan: &mut Receiver<Arc<BackendRequest>>,
+        hass_chan: &mut Receiver<Arc<HassRequest>>,
+    ) -> ApiResult<()> {
         if let Err(err) = self.run_sync("startup").await {
             log::error!(
                 "[{}] Initial Home Assistant sync failed: {}",
@@ -276,29 +719,85 @@ impl HassBackend {
             );
         }
 
+        self.sync_presence().await;
+
         let mut ws_tick = interval(Duration::from_secs(10));
         ws_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
+        let mut retry_tick = interval(Duration::from_secs(2));
+        retry_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let heartbeat = self.server.heartbeat_secs.is_some();
+        let mut heartbeat_tick = interval(Duration::from_secs(u64::from(
+            self.server.heartbeat_secs.map_or(60, NonZeroU32::get),
+        )));
+        heartbeat_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut reconcile_tick = interval(Duration::from_secs(60));
+        reconcile_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut health_tick = interval(Duration::from_secs(60));
+        health_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let (health_tx, mut health_rx) = mpsc::channel(1);
+
+        let id = self.backend_id();
+
         loop {
             if let Some(ws) = &mut self.ws {
                 tokio::select! {
                     _ = ws_tick.tick() => {
                         self.ensure_ws_connected().await;
                     }
+                    _ = retry_tick.tick(), if !self.retry.is_empty() => {
+                        self.drain_retry_queue().await;
+                    }
+                    _ = heartbeat_tick.tick(), if heartbeat => {
+                        self.publish_heartbeat(true).await;
+                    }
+                    _ = reconcile_tick.tick() => {
+                        self.nightly_reconcile().await;
+                    }
+                    _ = health_tick.tick() => {
+                        self.check_stream_health(&health_tx).await;
+                    }
+                    Some(states) = health_rx.recv() => {
+                        self.resync_missed(states).await;
+                    }
                     req = chan.recv() => {
-                        let req = req?;
-                        self.handle_backend_event(req).await?;
+                        if let Some(req) = received(&self.state, &id, req).await? {
+                            let result = self.handle_backend_request(req).await;
+                            self.isolate_failure("backend request", result).await?;
+                        }
                     }
-                    ev = ws.next_state_changed() => {
+                    req = hass_chan.recv() => {
+                        if let Some(req) = received(&self.state, &id, req).await? {
+                            let result = self.handle_hass_request(req).await;
+                            self.isolate_failure("Home Assistant request", result).await?;
+                        }
+                    }
+                    ev = ws.next_event() => {
                         match ev {
-                            Ok(Some(ev)) => {
-                                // Keep fields "used" to avoid -D warnings while still being explicit
-                                // about which parts drive Hue state updates.
-                                let _entity_id = ev.entity_id;
-                                let _old_state = ev.old_state;
+                            Ok(Some(HassWsEvent::StateChanged(ev))) => {
+                                if self.is_linkbutton_press(&ev) {
+                                    self.press_linkbutton(&ev.entity_id).await;
+                                }
+                                self.update_presence(&ev).await;
+                                self.health.record_event(
+                                    ev.new_state.as_ref().and_then(|state| state.last_updated),
+                                );
+                                let restored = Self::is_power_restore(&ev);
                                 if let Some(new_state) = ev.new_state {
                                     let _ = self.handle_state_update(new_state).await;
                                 }
+                                if restored {
+                                    let result = self.apply_light_startup(&ev.entity_id).await;
+                                    self.isolate_failure("light startup", result).await?;
+                                }
+                            }
+                            Ok(Some(HassWsEvent::Other { event_type })) => {
+                                if self.linkbutton_config.hass_event.as_ref() == Some(&event_type) {
+                                    self.press_linkbutton(&event_type).await;
+                                }
                             }
                             Ok(None) => {
                                 // websocket closed, reconnect later
@@ -316,9 +815,26 @@ impl HassBackend {
                     _ = ws_tick.tick() => {
                         self.ensure_ws_connected().await;
                     }
+                    _ = retry_tick.tick(), if !self.retry.is_empty() => {
+                        self.drain_retry_queue().await;
+                    }
+                    _ = heartbeat_tick.tick(), if heartbeat => {
+                        self.publish_heartbeat(true).await;
+                    }
+                    Some(states) = health_rx.recv() => {
+                        self.resync_missed(states).await;
+                    }
                     req = chan.recv() => {
-                        let req = req?;
-                        self.handle_backend_event(req).await?;
+                        if let Some(req) = received(&self.state, &id, req).await? {
+                            let result = self.handle_backend_request(req).await;
+                            self.isolate_failure("backend request", result).await?;
+                        }
+                    }
+                    req = hass_chan.recv() => {
+                        if let Some(req) = received(&self.state, &id, req).await? {
+                            let result = self.handle_hass_request(req).await;
+                            self.isolate_failure("Home Assistant request", result).await?;
+                        }
                     }
                 }
             }
@@ -331,10 +847,24 @@ impl Service for HassBackend {
     type Error = ApiError;
 
     async fn start(&mut self) -> ApiResult<()> {
+        let entities = self.ui_state.lock().await.entities.clone();
+        let state = self.state.clone();
+        let restored = self.restore_bindings(&mut *state.lock().await, &entities);
+        if restored > 0 {
+            log::info!(
+                "[{}] Restored {restored} entity bindings from saved state",
+                self.name
+            );
+        }
+
         match self.apply_runtime_connection().await {
             Ok(()) => {
                 log::info!("[{}] Home Assistant backend ready", self.name);
-                self.ui_log("Home Assistant backend started").await;
+                self.ui_log(
+                    TimelineCategory::Connection,
+                    "Home Assistant backend started",
+                )
+                .await;
             }
             Err(err) => {
                 log::warn!(
@@ -342,10 +872,13 @@ impl Service for HassBackend {
                     self.name,
                     err
                 );
-                self.ui_log(format!(
-                    "Backend started without active HA connection: {}",
-                    err
-                ))
+                self.ui_log_entry(
+                    TimelineEntry::new(
+                        TimelineCategory::Connection,
+                        format!("Backend started without active HA connection: {err}"),
+                    )
+                    .with_level(TimelineLevel::Warning),
+                )
                 .await;
             }
         }
@@ -353,11 +886,28 @@ impl Service for HassBackend {
     }
 
     async fn run(&mut self) -> ApiResult<()> {
-        let mut chan = self.state.lock().await.backend_event_stream();
-        self.event_loop(&mut chan).await
+        let id = self.backend_id();
+        let mut lock = self.state.lock().await;
+        let mut chan = lock.backend_event_stream(&id, self.capabilities());
+        let mut hass_chan = lock.hass_event_stream(&id);
+        drop(lock);
+        self.event_loop(&mut chan, &mut hass_chan).await
     }
 
     async fn stop(&mut self) -> ApiResult<()> {
+        self.publish_heartbeat(false).await;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::hass::heartbeat_entity;
+
+    #[test]
+    fn heartbeat_entity_per_backend() {
+        assert_eq!(heartbeat_entity("runtime"), "binary_sensor.bifrost_runtime");
+        assert_eq!(heartbeat_entity("Home-2"), "binary_sensor.bifrost_home_2");
+        assert_ne!(heartbeat_entity("a"), heartbeat_entity("b"));
+    }
+}
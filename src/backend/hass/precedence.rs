@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use hue::api::LightUpdate;
+
+use crate::model::hass::HassConflictPolicy;
+
+/// A light command sent to Home Assistant, and already applied to the Hue
+/// light, which Home Assistant has not yet confirmed.
+#[derive(Clone, Debug)]
+pub(super) struct PendingLightUpdate {
+    on: Option<bool>,
+    brightness: Option<f64>,
+    /// When the command was sent (local clock)
+    sent_at: DateTime<Utc>,
+    /// When Home Assistant performed the command (by its own clock, so it
+    /// compares with the times of its state updates)
+    written_at: Option<DateTime<Utc>>,
+}
+
+impl PendingLightUpdate {
+    /// After this long, the state reported by Home Assistant is used,
+    /// regardless of policy (the command was most likely lost).
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Brightness difference (in percent) still considered a match, since
+    /// Home Assistant rounds brightness to 0..255
+    const BRIGHTNESS_TOLERANCE: f64 = 1.0;
+
+    pub fn new(upd: &LightUpdate, written_at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            on: upd.on.map(|on| on.on),
+            brightness: upd.dimming.map(|dim| dim.brightness),
+            sent_at: Utc::now(),
+            written_at,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        (Utc::now() - self.sent_at)
+            .to_std()
+            .is_ok_and(|age| age > Self::TIMEOUT)
+    }
+
+    /// Does the reported state (on, brightness in 0..255) match the command?
+    fn is_confirmed_by(&self, on: bool, brightness: Option<f64>) -> bool {
+        if self.on.is_some_and(|want| want != on) {
+            return false;
+        }
+
+        // brightness is not reported for lights that are off
+        if !on {
+            return true;
+        }
+
+        match (self.brightness, brightness) {
+            (Some(want), Some(bri)) => {
+                (want - bri / 255.0 * 100.0).abs() <= Self::BRIGHTNESS_TOLERANCE
+            }
+            _ => true,
+        }
+    }
+
+    /// Should a state update from Home Assistant be applied to the Hue light?
+    ///
+    /// Returns `(apply, resolved)`, where `resolved` means the pending command
+    /// no longer needs to be tracked.
+    ///
+    /// `changed_at` is the time of the state update, by the clock of Home
+    /// Assistant. For [`HassConflictPolicy::LastWriteWins`], a state update
+    /// is only newer than the command if both times are known.
+    pub fn resolve(
+        &self,
+        policy: HassConflictPolicy,
+        on: bool,
+        brightness: Option<f64>,
+        changed_at: Option<DateTime<Utc>>,
+    ) -> (bool, bool) {
+        if self.is_confirmed_by(on, brightness) || self.is_expired() {
+            return (true, true);
+        }
+
+        match policy {
+            HassConflictPolicy::BackendWins => (true, true),
+            HassConflictPolicy::LastWriteWins => {
+                let newer = changed_at
+                    .zip(self.written_at)
+                    .is_some_and(|(changed, written)| changed > written);
+                (newer, newer)
+            }
+            HassConflictPolicy::Hold => (false, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use crate::backend::hass::precedence::PendingLightUpdate;
+    use crate::model::hass::HassConflictPolicy;
+
+    fn pending(sent_at: DateTime<Utc>) -> PendingLightUpdate {
+        PendingLightUpdate {
+            on: Some(true),
+            brightness: Some(50.0),
+            sent_at,
+            written_at: Some(sent_at),
+        }
+    }
+
+    #[test]
+    fn resolve_policies() {
+        use HassConflictPolicy::{BackendWins, Hold, LastWriteWins};
+
+        let now = Utc::now();
+        let before = Some(now - TimeDelta::seconds(1));
+        let after = Some(now + TimeDelta::seconds(1));
+
+        // (policy, on, brightness, changed_at, expected (apply, resolved))
+        let cases = [
+            // confirming states are applied, regardless of policy
+            (BackendWins, true, Some(127.5), None, (true, true)),
+            (LastWriteWins, true, Some(127.5), before, (true, true)),
+            (Hold, true, Some(128.0), None, (true, true)),
+            // conflicting states
+            (BackendWins, false, None, before, (true, true)),
+            (BackendWins, true, Some(255.0), None, (true, true)),
+            (LastWriteWins, false, None, before, (false, false)),
+            (LastWriteWins, false, None, None, (false, false)),
+            (LastWriteWins, false, None, after, (true, true)),
+            (LastWriteWins, true, Some(255.0), after, (true, true)),
+            (Hold, false, None, after, (false, false)),
+            (Hold, true, Some(255.0), None, (false, false)),
+        ];
+
+        for (policy, on, bri, changed_at, expected) in cases {
+            assert_eq!(
+                pending(now).resolve(policy, on, bri, changed_at),
+                expected,
+                "{policy:?}, on={on}, bri={bri:?}, changed_at={changed_at:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_by_hass_clock() {
+        // the clock of Home Assistant is an hour behind
+        let written_at = Utc::now() - TimeDelta::hours(1);
+        let mut pending = PendingLightUpdate {
+            written_at: Some(written_at),
+            ..pending(Utc::now())
+        };
+
+        let after = Some(written_at + TimeDelta::seconds(1));
+        let before = Some(written_at - TimeDelta::seconds(1));
+        let policy = HassConflictPolicy::LastWriteWins;
+        assert_eq!(pending.resolve(policy, false, None, after), (true, true));
+        assert_eq!(pending.resolve(policy, false, None, before), (false, false));
+
+        // without a time for the command, wait for confirmation (or timeout)
+        pending.written_at = None;
+        assert_eq!(pending.resolve(policy, false, None, after), (false, false));
+    }
+
+    #[test]
+    fn resolve_expired() {
+        let sent_at = Utc::now() - TimeDelta::seconds(60);
+
+        for policy in [
+            HassConflictPolicy::BackendWins,
+            HassConflictPolicy::LastWriteWins,
+            HassConflictPolicy::Hold,
+        ] {
+            assert_eq!(
+                pending(sent_at).resolve(policy, false, None, None),
+                (true, true),
+                "{policy:?}"
+            );
+        }
+    }
+}
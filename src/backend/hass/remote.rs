@@ -0,0 +1,185 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use hue::api::{ButtonReport, RType, ResourceLink};
+
+use crate::backend::hass::HassBackend;
+use crate::backend::hass::client::HassState;
+use crate::model::hass::HassEntityKind;
+
+/// Hue button event for the Home Assistant event type `event_type`. Unknown
+/// event types (e.g. "ring" for a doorbell) are reported as short presses.
+fn button_event(event_type: &str) -> &'static str {
+    match event_type {
+        "initial_press" | "press" | "pressed" => "initial_press",
+        "repeat" | "hold" | "held" => "repeat",
+        "long_press" => "long_press",
+        "long_release" | "release" | "released" => "long_release",
+        "double_short_release" | "double_press" | "double" | "multi_press_2" => {
+            "double_short_release"
+        }
+        _ => "short_release",
+    }
+}
+
+/// Hue button events an event entity can report
+pub(super) fn event_values(state: &HassState) -> Vec<String> {
+    let values = state
+        .attributes
+        .get("event_types")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(button_event)
+        .collect::<BTreeSet<_>>();
+
+    if values.is_empty() {
+        vec!["short_release".to_string()]
+    } else {
+        values.into_iter().map(ToString::to_string).collect()
+    }
+}
+
+/// The last event of an event entity, as a hue button report. The state of
+/// an event entity is the time of its last event.
+pub(super) fn button_report(state: &HassState) -> Option<ButtonReport> {
+    let event_type = state.attributes.get("event_type").and_then(Value::as_str)?;
+    let updated = DateTime::parse_from_rfc3339(&state.state)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+        .or(state.last_updated)?;
+
+    Some(ButtonReport {
+        updated,
+        event: button_event(event_type).to_string(),
+    })
+}
+
+impl HassBackend {
+    /// Link of the hue device shared by the event entities (buttons) of the
+    /// Home Assistant device `device_id`
+    pub(super) fn remote_device_link(&self, device_id: &str) -> ResourceLink {
+        RType::Device.deterministic(format!("hass:{}:remote:{device_id}", self.name))
+    }
+
+    /// Control id of the event entity `entity_id` of the Home Assistant
+    /// device `device_id`, when synced on its own. Numbers it among the
+    /// imported event entities of that device, in entity id order, like a
+    /// full sync does.
+    pub(super) fn remote_control_id(&self, device_id: &str, entity_id: &str) -> u32 {
+        let device_link = self.remote_device_link(device_id);
+        let before = self
+            .entity_map
+            .values()
+            .filter(|binding| {
+                binding.kind == HassEntityKind::Event
+                    && binding.device_link == device_link
+                    && binding.entity_id.as_str() < entity_id
+            })
+            .count();
+
+        u32::try_from(before).map_or(u32::MAX, |before| before + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use hue::version::SwVersion;
+
+    use crate::backend::hass::client::HassState;
+    use crate::backend::hass::import::tests::{backend, sensor};
+    use crate::backend::hass::remote::{button_event, button_report, event_values};
+    use crate::model::hass::{HassEntityKind, HassServiceKind};
+    use crate::model::state::State;
+    use crate::resource::Resources;
+
+    fn state(state: &str, attributes: serde_json::Value) -> HassState {
+        serde_json::from_value(json!({
+            "entity_id": "event.remote_button_1",
+            "state": state,
+            "attributes": attributes,
+            "last_updated": "2026-01-01T12:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn button_events() {
+        assert_eq!(button_event("press"), "initial_press");
+        assert_eq!(button_event("hold"), "repeat");
+        assert_eq!(button_event("long_press"), "long_press");
+        assert_eq!(button_event("released"), "long_release");
+        assert_eq!(button_event("multi_press_2"), "double_short_release");
+        assert_eq!(button_event("short_release"), "short_release");
+        assert_eq!(button_event("ring"), "short_release");
+    }
+
+    #[test]
+    fn event_values_of_entity() {
+        let remote = state(
+            "unknown",
+            json!({"event_types": ["press", "pressed", "hold", "ring"]}),
+        );
+        assert_eq!(
+            event_values(&remote),
+            ["initial_press", "repeat", "short_release"]
+        );
+
+        assert_eq!(
+            event_values(&state("unknown", json!({}))),
+            ["short_release"]
+        );
+    }
+
+    #[test]
+    fn button_report_of_entity() {
+        let report = button_report(&state(
+            "2026-01-02T08:30:00.000+00:00",
+            json!({"event_type": "double_press"}),
+        ))
+        .unwrap();
+        assert_eq!(report.event, "double_short_release");
+        assert_eq!(
+            report.updated,
+            Utc.with_ymd_and_hms(2026, 1, 2, 8, 30, 0).unwrap()
+        );
+
+        // no valid event time: fall back to the last update
+        let report = button_report(&state("unknown", json!({"event_type": "press"}))).unwrap();
+        assert_eq!(
+            report.updated,
+            Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()
+        );
+
+        // no event yet
+        assert!(button_report(&state("unknown", json!({}))).is_none());
+    }
+
+    #[test]
+    fn control_ids_of_single_entities() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-hass-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let mut svc = backend(&dir);
+        for entity_id in ["event.remote_button_1", "event.remote_button_3"] {
+            let button = sensor(entity_id, HassEntityKind::Event, HassServiceKind::Button);
+            svc.sync_single_entity(&button, &mut res).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(svc.remote_control_id("hallway", "event.remote_button_1"), 1);
+        assert_eq!(svc.remote_control_id("hallway", "event.remote_button_2"), 2);
+        assert_eq!(svc.remote_control_id("hallway", "event.remote_button_4"), 3);
+        assert_eq!(svc.remote_control_id("kitchen", "event.remote_button_4"), 1);
+    }
+}
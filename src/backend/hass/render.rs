@@ -0,0 +1,262 @@
+//! Software rendering of entertainment streams, for Home Assistant lights
+//! (which have no way of streaming).
+//!
+//! Channel colors are turned into regular light updates, at a (much) lower
+//! frame rate than the stream itself.
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroU32;
+
+use uuid::Uuid;
+
+use hue::api::{
+    Entertainment, EntertainmentConfiguration, LightDynamicsUpdate, LightUpdate, On, ResourceLink,
+};
+use hue::stream::HueStreamLightsV2;
+use hue::xy::XY;
+
+use crate::backend::hass::HassBackend;
+use crate::error::ApiResult;
+use crate::model::throttle::Throttle;
+
+/// Colors closer than this to the last rendered color are not sent again
+const COLOR_EPSILON: f64 = 0.002;
+
+/// Brightness (percent) changes smaller than this are not sent again
+const BRIGHTNESS_EPSILON: f64 = 1.0;
+
+pub struct SoftwareRenderer {
+    throttle: Throttle,
+    /// Entertainment channel, and the light it is rendered on
+    channels: Vec<(u8, ResourceLink)>,
+    /// Last rendered color and brightness, by light
+    last: HashMap<Uuid, (XY, f64)>,
+}
+
+impl SoftwareRenderer {
+    pub const DEFAULT_FPS: u32 = 2;
+
+    #[must_use]
+    pub fn new(fps: u32, channels: Vec<(u8, ResourceLink)>) -> Self {
+        Self {
+            throttle: Throttle::from_fps(fps),
+            channels,
+            last: HashMap::new(),
+        }
+    }
+
+    fn changed(&mut self, light: &ResourceLink, xy: XY, bri: f64) -> bool {
+        let same = self
+            .last
+            .get(&light.rid)
+            .is_some_and(|(last_xy, last_bri)| {
+                (last_xy.x - xy.x).abs() < COLOR_EPSILON
+                    && (last_xy.y - xy.y).abs() < COLOR_EPSILON
+                    && (last_bri - bri).abs() < BRIGHTNESS_EPSILON
+            });
+        if !same {
+            self.last.insert(light.rid, (xy, bri));
+        }
+        !same
+    }
+
+    /// Light updates for `frame`, unless it is skipped to keep the frame rate
+    /// down. Lights without changes are not updated.
+    pub fn render(&mut self, frame: &HueStreamLightsV2) -> Vec<(ResourceLink, LightUpdate)> {
+        if !self.throttle.tick() {
+            return vec![];
+        }
+
+        let colors: BTreeMap<u8, (XY, f64)> = match frame {
+            HueStreamLightsV2::Rgb(lights) => lights
+                .iter()
+                .map(|light| (light.channel, light.rgb.to_xy()))
+                .collect(),
+            HueStreamLightsV2::Xy(lights) => lights
+                .iter()
+                .map(|light| (light.channel, light.xy.to_xy()))
+                .collect(),
+        };
+
+        // lights with several channels (segments) show the last one
+        let mut lights = BTreeMap::new();
+        for (channel, light) in &self.channels {
+            if let Some(&(xy, bri)) = colors.get(channel) {
+                lights.insert(*light, (xy, bri / 255.0 * 100.0));
+            }
+        }
+
+        let duration = u32::try_from(self.throttle.interval().num_milliseconds()).ok();
+
+        lights
+            .into_iter()
+            .filter(|(light, (xy, bri))| self.changed(light, *xy, *bri))
+            .map(|(light, (xy, bri))| {
+                let upd = if bri < BRIGHTNESS_EPSILON {
+                    LightUpdate::new().with_on(On::new(false))
+                } else {
+                    LightUpdate::new()
+                        .with_on(On::new(true))
+                        .with_brightness(Some(bri))
+                        .with_color_xy(xy)
+                        .with_dynamics(Some(LightDynamicsUpdate::new().with_duration(duration)))
+                };
+                (light, upd)
+            })
+            .collect()
+    }
+}
+
+impl HassBackend {
+    pub(super) fn entertainment_fallback(&self) -> bool {
+        self.server.entertainment_fallback == Some(true)
+    }
+
+    /// Start rendering the stream to entertainment area `area`, if it has
+    /// any of our lights
+    pub(super) async fn entertainment_start(&mut self, area: &Uuid) -> ApiResult<()> {
+        self.renderer = None;
+        if !self.entertainment_fallback() {
+            return Ok(());
+        }
+
+        let lock = self.state.lock().await;
+        let entconf: &EntertainmentConfiguration = lock.get_id(*area)?;
+
+        let mut channels = vec![];
+        for chan in &entconf.channels {
+            let Ok(channel) = u8::try_from(chan.channel_id) else {
+                continue;
+            };
+            for member in &chan.members {
+                let light = lock
+                    .get::<Entertainment>(&member.service)
+                    .ok()
+                    .and_then(|ent| ent.renderer_reference)
+                    .filter(|light| self.light_map.contains_key(&light.rid));
+                if let Some(light) = light {
+                    channels.push((channel, light));
+                }
+            }
+        }
+        drop(lock);
+
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let fps = self
+            .server
+            .entertainment_fps
+            .map_or(SoftwareRenderer::DEFAULT_FPS, NonZeroU32::get);
+        log::info!(
+            "[{}] Rendering entertainment stream on {} channels, at {fps} fps",
+            self.name,
+            channels.len()
+        );
+        self.renderer = Some(SoftwareRenderer::new(fps, channels));
+
+        Ok(())
+    }
+
+    /// Render a frame. Failed updates are not retried, since the next frame
+    /// replaces them anyway.
+    pub(super) async fn entertainment_frame(&mut self, frame: &HueStreamLightsV2) {
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+
+        for (light, upd) in renderer.render(frame) {
+            let Some(binding) = self.lookup_binding_by_light(&light) else {
+                continue;
+            };
+            if let Err(err) = self.send_light_update(&binding, &upd).await {
+                log::debug!(
+                    "[{}] Failed to render frame on {}: {err}",
+                    self.name,
+                    binding.entity_id
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use uuid::Uuid;
+
+    use hue::api::{On, RType, ResourceLink};
+    use hue::stream::{HueStreamLightsV2, Xy16, Xy16V2};
+
+    use crate::backend::hass::render::SoftwareRenderer;
+    use crate::model::throttle::Throttle;
+
+    fn frame(x: u16, y: u16, b: u16) -> HueStreamLightsV2 {
+        HueStreamLightsV2::Xy(vec![Xy16V2 {
+            channel: 0,
+            xy: Xy16 { x, y, b },
+        }])
+    }
+
+    /// A renderer for a single light, that renders every frame
+    fn renderer() -> (SoftwareRenderer, ResourceLink) {
+        let light = RType::Light.link_to(Uuid::new_v4());
+        let mut renderer = SoftwareRenderer::new(SoftwareRenderer::DEFAULT_FPS, vec![(0, light)]);
+        renderer.throttle = Throttle::new(Duration::zero());
+        (renderer, light)
+    }
+
+    #[test]
+    fn throttle_from_fps() {
+        assert_eq!(Throttle::from_fps(1).interval(), Duration::seconds(1));
+        assert_eq!(
+            Throttle::from_fps(2).interval(),
+            Duration::milliseconds(500)
+        );
+        assert_eq!(
+            Throttle::from_fps(3).interval(),
+            Duration::microseconds(333_333)
+        );
+
+        // a fresh throttle waits a full interval before the first tick
+        assert!(!Throttle::from_fps(1).tick());
+        assert!(Throttle::new(Duration::zero()).tick());
+    }
+
+    #[test]
+    fn skips_throttled_frames() {
+        let light = RType::Light.link_to(Uuid::new_v4());
+        let mut renderer = SoftwareRenderer::new(1, vec![(0, light)]);
+
+        assert!(renderer.render(&frame(0x5000, 0x5000, 0xFFFF)).is_empty());
+    }
+
+    #[test]
+    fn skips_unchanged_colors() {
+        let (mut renderer, light) = renderer();
+
+        let updates = renderer.render(&frame(0x5000, 0x5000, 0xFFFF));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0, light);
+        assert_eq!(updates[0].1.on, Some(On::new(true)));
+
+        // within the color and brightness epsilons
+        assert!(renderer.render(&frame(0x5010, 0x5010, 0xFE00)).is_empty());
+
+        // color changed
+        assert_eq!(renderer.render(&frame(0x6000, 0x5010, 0xFE00)).len(), 1);
+
+        // brightness changed
+        assert_eq!(renderer.render(&frame(0x6000, 0x5010, 0x8000)).len(), 1);
+    }
+
+    #[test]
+    fn dark_channels_turn_off() {
+        let (mut renderer, _) = renderer();
+
+        let updates = renderer.render(&frame(0x5000, 0x5000, 0x0100));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].1.on, Some(On::new(false)));
+        assert!(updates[0].1.dimming.is_none());
+    }
+}
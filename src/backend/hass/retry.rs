@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bifrost_api::backend::BackendRequest;
+
+use crate::error::ApiError;
+use crate::model::hass::HassRetryQueueStatus;
+
+/// Bounded queue of backend requests that failed because Home Assistant was
+/// unreachable, to be retried once it is back.
+///
+/// Requests older than the ttl are dropped instead of retried, since replaying
+/// stale light commands is more surprising than losing them.
+pub(super) struct RetryQueue {
+    queue: VecDeque<(Instant, Arc<BackendRequest>)>,
+    capacity: usize,
+    ttl: Duration,
+    status: HassRetryQueueStatus,
+}
+
+impl RetryQueue {
+    pub const DEFAULT_CAPACITY: usize = 64;
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+    /// Most requests retried in a single pass, so a long queue does not hold
+    /// up the event loop of the backend
+    pub const PASS_REQUESTS: usize = 8;
+    /// A pass stops retrying after this time (checked between requests)
+    pub const PASS_TIME: Duration = Duration::from_secs(1);
+
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            queue: VecDeque::with_capacity(capacity),
+            capacity,
+            ttl,
+            status: HassRetryQueueStatus::default(),
+        }
+    }
+
+    /// Is this error caused by Home Assistant being (temporarily) unreachable?
+    pub fn is_retryable(err: &ApiError) -> bool {
+        match err {
+            ApiError::ReqwestError(err) => {
+                err.is_connect()
+                    || err.is_timeout()
+                    || err.status().is_some_and(|status| status.is_server_error())
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Queue request for retrying, dropping the oldest request if full.
+    ///
+    /// Returns false if queueing is disabled (capacity of 0).
+    pub fn push(&mut self, req: Arc<BackendRequest>) -> bool {
+        if self.capacity == 0 {
+            self.status.dropped_overflow += 1;
+            return false;
+        }
+
+        if self.queue.len() >= self.capacity {
+            self.queue.pop_front();
+            self.status.dropped_overflow += 1;
+        }
+
+        self.queue.push_back((Instant::now(), req));
+        self.status.queued = self.queue.len();
+        true
+    }
+
+    /// Put back a request that failed again, keeping its original age
+    pub fn requeue(&mut self, entry: (Instant, Arc<BackendRequest>)) {
+        self.queue.push_front(entry);
+        self.status.queued = self.queue.len();
+    }
+
+    /// Take the oldest request that has not yet expired
+    pub fn pop(&mut self) -> Option<(Instant, Arc<BackendRequest>)> {
+        while let Some((queued_at, req)) = self.queue.pop_front() {
+            if queued_at.elapsed() <= self.ttl {
+                self.status.queued = self.queue.len();
+                return Some((queued_at, req));
+            }
+            self.status.dropped_expired += 1;
+        }
+
+        self.status.queued = 0;
+        None
+    }
+
+    pub const fn record_retried(&mut self) {
+        self.status.retried += 1;
+    }
+
+    pub const fn record_failed(&mut self) {
+        self.status.failed += 1;
+    }
+
+    pub const fn status(&self) -> HassRetryQueueStatus {
+        self.status
+    }
+}
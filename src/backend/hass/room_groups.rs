@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use hue::api::{RType, Room, Zone};
+
+use crate::backend::hass::client::HassState;
+use crate::backend::hass::{HassBackend, HassEntityBinding};
+use crate::error::ApiResult;
+use crate::model::hass::HassEntityKind;
+use crate::model::timeline::TimelineCategory;
+
+/// Title prefix of the light group helpers created for Bifrost rooms and zones
+const ROOM_GROUP_PREFIX: &str = "Bifrost ";
+
+/// Is this one of the light groups created by [`HassBackend::export_room_groups`]?
+///
+/// These must not be imported as lights themselves.
+pub(super) fn is_room_group(state: &HassState) -> bool {
+    state.entity_id.starts_with("light.")
+        && state
+            .attributes
+            .get("entity_id")
+            .is_some_and(Value::is_array)
+        && state
+            .attributes
+            .get("friendly_name")
+            .and_then(Value::as_str)
+            .is_some_and(|name| name.starts_with(ROOM_GROUP_PREFIX))
+}
+
+impl HassBackend {
+    /// Can this entity be a member of a light group?
+    fn is_group_member(binding: &HassEntityBinding) -> bool {
+        // light groups cannot hold covers or fans
+        matches!(binding.kind, HassEntityKind::Light | HassEntityKind::Switch)
+            && binding.kind.groups_as_light(binding.switch_mode)
+    }
+
+    fn group_members(bindings: impl Iterator<Item = HassEntityBinding>) -> Vec<String> {
+        let mut members = bindings
+            .filter(Self::is_group_member)
+            .map(|binding| binding.entity_id)
+            .collect::<Vec<_>>();
+        members.sort();
+        members.dedup();
+        members
+    }
+
+    /// Light entities of each room and zone, keyed by light group title
+    async fn room_group_members(&self) -> BTreeMap<String, Vec<String>> {
+        let lock = self.state.lock().await;
+
+        let mut groups = BTreeMap::new();
+        for binding in self.room_map.values() {
+            let Ok(room) = lock.get::<Room>(&binding.room_link) else {
+                continue;
+            };
+
+            let members = Self::group_members(
+                room.children
+                    .iter()
+                    .filter_map(|device| self.lookup_binding_by_device(device)),
+            );
+
+            groups.insert(format!("{ROOM_GROUP_PREFIX}{}", binding.room_name), members);
+        }
+
+        // zones can hold lights from several rooms (and backends), so only
+        // the lights imported by this backend are exported
+        for id in lock.get_resource_ids_by_type(RType::Zone) {
+            let link = RType::Zone.link_to(id);
+            let (Ok(zone), Ok(lights)) = (lock.get::<Zone>(&link), lock.get_zone_lights(&link))
+            else {
+                continue;
+            };
+
+            let members = Self::group_members(
+                lights
+                    .iter()
+                    .filter_map(|light| self.lookup_binding_by_light(light)),
+            );
+
+            // rooms take precedence, if a zone has the same name
+            groups
+                .entry(format!("{ROOM_GROUP_PREFIX}{}", zone.metadata.name))
+                .or_insert(members);
+        }
+
+        groups
+    }
+
+    /// Create (or update) a light group helper in Home Assistant for each
+    /// room and zone, so Home Assistant dashboards can follow the Bifrost
+    /// rooms and zones.
+    pub(super) async fn export_room_groups(&mut self) -> ApiResult<()> {
+        if !self.server.export_room_groups.unwrap_or(false) {
+            return Ok(());
+        }
+
+        let wanted = self.room_group_members().await;
+
+        let existing = self
+            .client
+            .get_config_entries("group")
+            .await?
+            .into_iter()
+            .map(|entry| (entry.title, entry.entry_id))
+            .collect::<HashMap<_, _>>();
+
+        for (title, members) in wanted {
+            if members.is_empty() || self.room_groups.get(&title) == Some(&members) {
+                continue;
+            }
+
+            if let Some(entry_id) = existing.get(&title) {
+                self.client.update_light_group(entry_id, &members).await?;
+                self.ui_log(
+                    TimelineCategory::Sync,
+                    format!("Updated Home Assistant light group {title}"),
+                )
+                .await;
+            } else {
+                self.client.create_light_group(&title, &members).await?;
+                self.ui_log(
+                    TimelineCategory::Sync,
+                    format!("Created Home Assistant light group {title}"),
+                )
+                .await;
+            }
+
+            self.room_groups.insert(title, members);
+        }
+
+        Ok(())
+    }
+}
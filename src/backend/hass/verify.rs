@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Local, Timelike, Utc};
+use hue::api::{Device, Light, RType, ResourceLink};
+use uuid::Uuid;
+
+use crate::backend::hass::HassBackend;
+use crate::backend::hass::client::HassState;
+use crate::backend::hass::import::entity_on;
+use crate::error::ApiResult;
+use crate::model::hass::{HassMismatch, HassMismatchKind, HassVerifyReport};
+use crate::model::state::AuxData;
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
+use crate::resource::Resources;
+
+const fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+fn mismatch(
+    kind: HassMismatchKind,
+    entity_id: Option<&str>,
+    device: Option<ResourceLink>,
+    detail: impl Into<String>,
+) -> HassMismatch {
+    HassMismatch {
+        kind,
+        entity_id: entity_id.map(ToString::to_string),
+        device,
+        detail: detail.into(),
+    }
+}
+
+impl HassBackend {
+    /// Compare the current Home Assistant states with the Hue resources
+    pub(super) async fn verify(&self) -> ApiResult<HassVerifyReport> {
+        let states = self.client.get_states().await?;
+        let states: HashMap<&str, &HassState> = states
+            .iter()
+            .map(|state| (state.entity_id.as_str(), state))
+            .collect();
+
+        let not_imported: Vec<String> = {
+            let ui = self.ui_state.lock().await;
+            ui.entities
+                .iter()
+                .filter(|ent| ent.included && !self.entity_map.contains_key(&ent.entity_id))
+                .filter(|ent| states.contains_key(ent.entity_id.as_str()))
+                .map(|ent| ent.entity_id.clone())
+                .collect()
+        };
+
+        let mismatches = self.find_mismatches(&states, &not_imported, &*self.state.lock().await);
+
+        Ok(HassVerifyReport {
+            backend: self.name.clone(),
+            checked_at: Utc::now().to_rfc3339(),
+            checked: self.entity_map.len(),
+            mismatches,
+            reconciled: 0,
+            error: None,
+        })
+    }
+
+    /// The mismatches between the Home Assistant `states` and the Hue
+    /// resources in `res`. `not_imported` are the included entities without
+    /// a binding.
+    fn find_mismatches(
+        &self,
+        states: &HashMap<&str, &HassState>,
+        not_imported: &[String],
+        res: &Resources,
+    ) -> Vec<HassMismatch> {
+        let mut mismatches = vec![];
+
+        let mut bindings: Vec<_> = self.entity_map.values().collect();
+        bindings.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+
+        for binding in &bindings {
+            let entity_id = Some(binding.entity_id.as_str());
+            let device = Some(binding.device_link);
+
+            let Some(state) = states.get(binding.entity_id.as_str()) else {
+                mismatches.push(mismatch(
+                    HassMismatchKind::Orphan,
+                    entity_id,
+                    device,
+                    "Entity no longer exists in Home Assistant",
+                ));
+                continue;
+            };
+
+            if res.get::<Device>(&binding.device_link).is_err()
+                || res.get_resource(&binding.service_link).is_err()
+            {
+                mismatches.push(mismatch(
+                    HassMismatchKind::Missing,
+                    entity_id,
+                    device,
+                    "Hue resources are missing",
+                ));
+                continue;
+            }
+
+            if binding.kind.is_sensor() || matches!(state.state.as_str(), "unavailable" | "unknown")
+            {
+                continue;
+            }
+
+            if let Ok(light) = res.get::<Light>(&binding.service_link) {
+                let hass_on = entity_on(binding.kind, &state.state);
+                if light.on.on != hass_on {
+                    mismatches.push(mismatch(
+                        HassMismatchKind::State,
+                        entity_id,
+                        device,
+                        format!(
+                            "Hue reports {}, Home Assistant reports {}",
+                            on_off(light.on.on),
+                            on_off(hass_on)
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for entity_id in not_imported {
+            mismatches.push(mismatch(
+                HassMismatchKind::Missing,
+                Some(entity_id.as_str()),
+                None,
+                "Included, but not imported into Hue",
+            ));
+        }
+
+        let bound: HashSet<_> = bindings.iter().map(|binding| binding.device_link).collect();
+        for id in res.get_resource_ids_by_type(RType::Device) {
+            let link = RType::Device.link_to(id);
+            let Ok(dev) = res.get::<Device>(&link) else {
+                continue;
+            };
+            if !dev.product_data.model_id.starts_with("hass-") || bound.contains(&link) {
+                continue;
+            }
+            // the daylight sensor has no entity of its own
+            if dev.product_data.model_id == Self::DAYLIGHT_MODEL_ID {
+                continue;
+            }
+            // only devices saved by this backend are ours to report, other
+            // backends (and older states) may import the same entities
+            let ours = matches!(
+                res.aux_get(&link),
+                Ok(AuxData { hass: Some(aux), .. }) if aux.backend == self.name
+            );
+            if !ours {
+                continue;
+            }
+            mismatches.push(mismatch(
+                HassMismatchKind::Orphan,
+                None,
+                Some(link),
+                format!(
+                    "Device {:?} has no matching Home Assistant entity",
+                    dev.metadata.name
+                ),
+            ));
+        }
+
+        mismatches
+    }
+
+    /// Fix the mismatches in `report`. Orphans are only removed with
+    /// `remove_orphans`. Returns the number of fixed mismatches.
+    async fn reconcile(&mut self, report: &HassVerifyReport, remove_orphans: bool) -> usize {
+        let mut fixed = 0;
+
+        for found in &report.mismatches {
+            if found.kind == HassMismatchKind::Orphan && !remove_orphans {
+                continue;
+            }
+            let result = match (found.kind, &found.entity_id, found.device) {
+                (HassMismatchKind::Orphan, Some(entity_id), _) => {
+                    self.remove_entity_by_id(entity_id).await
+                }
+                (HassMismatchKind::Orphan, None, Some(device)) => {
+                    self.state.lock().await.delete(&device)
+                }
+                (HassMismatchKind::State | HassMismatchKind::Missing, Some(entity_id), _) => {
+                    self.sync_entity_by_id(entity_id).await
+                }
+                _ => continue,
+            };
+
+            match result {
+                Ok(()) => fixed += 1,
+                Err(err) => log::warn!("[{}] Failed to reconcile {found:?}: {err}", self.name),
+            }
+
+            // let hue clients in between, reconciling many entities may take a while
+            tokio::task::yield_now().await;
+        }
+
+        fixed
+    }
+
+    /// Check (and optionally reconcile) the Hue resources, and publish the
+    /// report to the web ui (and to the verify request `request`, if any)
+    pub(super) async fn run_verify(
+        &mut self,
+        reconcile: bool,
+        remove_orphans: bool,
+        request: Option<Uuid>,
+    ) {
+        let mut report = self.verify().await;
+
+        let found = match &report {
+            Ok(found) if reconcile && !found.mismatches.is_empty() => Some(found.clone()),
+            _ => None,
+        };
+
+        let mut reconciled = 0;
+        if let Some(found) = found {
+            reconciled = self.reconcile(&found, remove_orphans).await;
+            self.ui_log(
+                TimelineCategory::Sync,
+                format!(
+                    "Reconciled {reconciled} of {} mismatches with Home Assistant",
+                    found.mismatches.len()
+                ),
+            )
+            .await;
+            report = self.verify().await;
+        }
+
+        let report = match report {
+            Ok(report) => HassVerifyReport {
+                reconciled,
+                ..report
+            },
+            Err(err) => {
+                self.ui_log_entry(
+                    TimelineEntry::new(
+                        TimelineCategory::Sync,
+                        format!("Consistency check failed: {err}"),
+                    )
+                    .with_level(TimelineLevel::Warning),
+                )
+                .await;
+                HassVerifyReport {
+                    backend: self.name.clone(),
+                    checked_at: Utc::now().to_rfc3339(),
+                    checked: 0,
+                    mismatches: vec![],
+                    reconciled,
+                    error: Some(err.to_string()),
+                }
+            }
+        };
+
+        let mut ui = self.ui_state.lock().await;
+        if reconcile {
+            ui.mark_reconcile_finished(&report);
+        }
+        ui.finish_verify(request, report);
+    }
+
+    /// Run a full sync and reconcile, once a night (if enabled)
+    pub(super) async fn nightly_reconcile(&mut self) {
+        if self.server.nightly_reconcile != Some(true) {
+            return;
+        }
+
+        let now = Local::now();
+        let hour = self
+            .server
+            .reconcile_hour
+            .unwrap_or(Self::DEFAULT_RECONCILE_HOUR);
+        if now.hour() != hour || self.last_reconcile == Some(now.date_naive()) {
+            return;
+        }
+        self.last_reconcile = Some(now.date_naive());
+
+        log::info!("[{}] Running nightly reconcile", self.name);
+        if let Err(err) = self.run_sync("nightly").await {
+            log::warn!("[{}] Nightly sync failed: {err}", self.name);
+        }
+        // orphaned devices are only reported: deleting them unattended would
+        // also delete them from the rooms, scenes and automations of the user
+        self.run_verify(true, false, None).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use camino::Utf8PathBuf;
+    use serde_json::Map;
+
+    use hue::version::SwVersion;
+
+    use crate::backend::hass::client::HassState;
+    use crate::backend::hass::import::tests::{backend, sensor};
+    use crate::model::hass::{HassEntityKind, HassMismatchKind, HassServiceKind};
+    use crate::model::state::State;
+    use crate::resource::Resources;
+
+    fn state(entity_id: &str, state: &str) -> HassState {
+        HassState {
+            entity_id: entity_id.to_string(),
+            state: state.to_string(),
+            attributes: Map::new(),
+            last_updated: None,
+        }
+    }
+
+    #[test]
+    fn find_mismatches() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-verify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let mut svc = backend(&dir);
+        for (entity_id, kind, service_kind) in [
+            ("light.desk", HassEntityKind::Light, HassServiceKind::Light),
+            ("light.shelf", HassEntityKind::Light, HassServiceKind::Light),
+            (
+                "binary_sensor.door",
+                HassEntityKind::BinarySensor,
+                HassServiceKind::Contact,
+            ),
+            (
+                "binary_sensor.hallway_motion",
+                HassEntityKind::BinarySensor,
+                HassServiceKind::Motion,
+            ),
+        ] {
+            svc.sync_single_entity(&sensor(entity_id, kind, service_kind), &mut res)
+                .unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the motion sensor binding is lost, its device is left behind
+        let motion = svc
+            .entity_map
+            .remove("binary_sensor.hallway_motion")
+            .unwrap();
+
+        let states = [
+            state("light.desk", "on"),
+            state("light.shelf", "off"),
+            state("light.new", "on"),
+        ];
+        let states = states
+            .iter()
+            .map(|state| (state.entity_id.as_str(), state))
+            .collect::<HashMap<_, _>>();
+        let mismatches = svc.find_mismatches(&states, &["light.new".to_string()], &res);
+
+        let found: Vec<_> = mismatches
+            .iter()
+            .map(|found| (found.kind, found.entity_id.as_deref(), found.device))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    HassMismatchKind::Orphan,
+                    Some("binary_sensor.door"),
+                    Some(svc.entity_map["binary_sensor.door"].device_link)
+                ),
+                (
+                    HassMismatchKind::State,
+                    Some("light.desk"),
+                    Some(svc.entity_map["light.desk"].device_link)
+                ),
+                (HassMismatchKind::Missing, Some("light.new"), None),
+                (HassMismatchKind::Orphan, None, Some(motion.device_link)),
+            ]
+        );
+    }
+}
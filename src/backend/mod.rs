@@ -1,2 +1,4 @@
 pub mod hass;
+pub mod ownership;
+pub mod router;
 pub mod z2m;
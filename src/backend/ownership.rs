@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use hue::api::ResourceLink;
+
+use crate::error::{ApiError, ApiResult};
+
+/// Maps resources to the backend (service id, e.g. "z2m@kitchen") that
+/// created them.
+///
+/// Only top-level resources (devices, rooms) are claimed directly. Services
+/// belonging to them (lights, grouped lights, scenes, ..) are resolved through
+/// their owner links, by [`crate::resource::Resources::backend_owner`].
+#[derive(Clone, Debug, Default)]
+pub struct OwnershipRegistry {
+    owners: HashMap<Uuid, String>,
+}
+
+impl OwnershipRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register backend `backend` as the owner of `link`.
+    ///
+    /// Claiming a resource again from the same backend is a no-op, but a
+    /// resource already owned by a different backend is a conflict.
+    pub fn claim(&mut self, link: &ResourceLink, backend: &str) -> ApiResult<()> {
+        match self.owners.get(&link.rid) {
+            Some(owner) if owner == backend => Ok(()),
+            Some(owner) => Err(ApiError::OwnershipConflict(
+                *link,
+                owner.clone(),
+                backend.to_string(),
+            )),
+            None => {
+                log::trace!("Backend {backend} claimed {link:?}");
+                self.owners.insert(link.rid, backend.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn release(&mut self, rid: &Uuid) {
+        self.owners.remove(rid);
+    }
+
+    #[must_use]
+    pub fn get(&self, rid: &Uuid) -> Option<&str> {
+        self.owners.get(rid).map(String::as_str)
+    }
+}
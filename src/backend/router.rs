@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{Receiver, Sender};
+use uuid::Uuid;
+
+use bifrost_api::backend::{BackendCapabilities, BackendRequest, HassRequest};
+
+use crate::error::{ApiError, ApiResult};
+use crate::resource::Resources;
+
+/// Signals used to measure backend latency (see [`crate::server::latency`])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LatencySignal {
+    /// A request that changes the state of resource `id` was sent to `backend`
+    Sent { id: Uuid, backend: String },
+    /// A backend reported the actual state of resource `id`
+    Reported(Uuid),
+}
+
+/// Delivers backend requests to backends, by id.
+///
+/// Each backend subscribes with its id (e.g. "z2m@kitchen" or
+/// "hass@homeassistant"). Requests for a resource are only sent to the owning
+/// backend (see [`crate::backend::ownership::OwnershipRegistry`]), while
+/// requests without a target resource are sent to all backends.
+///
+/// Requests are only delivered to backends that implement the features they
+/// need (see [`BackendCapabilities`]).
+#[derive(Clone, Debug)]
+pub struct BackendRouter {
+    backends: BTreeMap<String, Sender<Arc<BackendRequest>>>,
+    capabilities: BTreeMap<String, BackendCapabilities>,
+    hass: BTreeMap<String, Sender<Arc<HassRequest>>>,
+    monitor: Sender<Arc<BackendRequest>>,
+    latency: Sender<LatencySignal>,
+    buffer_size: usize,
+    /// Number of requests skipped by each backend, because it fell behind
+    lagged: BTreeMap<String, u64>,
+}
+
+impl BackendRouter {
+    pub const DEFAULT_BUFFER_SIZE: usize = 32;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_buffer_size(Self::DEFAULT_BUFFER_SIZE)
+    }
+
+    #[must_use]
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        Self {
+            backends: BTreeMap::new(),
+            capabilities: BTreeMap::new(),
+            hass: BTreeMap::new(),
+            monitor: Sender::new(buffer_size),
+            latency: Sender::new(buffer_size),
+            buffer_size,
+            lagged: BTreeMap::new(),
+        }
+    }
+
+    /// Subscribe to requests for backend `id`, which implements `capabilities`
+    pub fn subscribe(
+        &mut self,
+        id: &str,
+        capabilities: BackendCapabilities,
+    ) -> Receiver<Arc<BackendRequest>> {
+        self.capabilities.insert(id.to_string(), capabilities);
+        self.backends
+            .entry(id.to_string())
+            .or_insert_with(|| Sender::new(self.buffer_size))
+            .subscribe()
+    }
+
+    /// Subscribe to Home Assistant specific requests for backend `id`
+    pub fn subscribe_hass(&mut self, id: &str) -> Receiver<Arc<HassRequest>> {
+        self.hass
+            .entry(id.to_string())
+            .or_insert_with(|| Sender::new(self.buffer_size))
+            .subscribe()
+    }
+
+    /// Record that backend `id` skipped `count` requests
+    pub fn record_lag(&mut self, id: &str, count: u64) {
+        *self.lagged.entry(id.to_string()).or_default() += count;
+    }
+
+    /// Features of each backend
+    #[must_use]
+    pub const fn capabilities(&self) -> &BTreeMap<String, BackendCapabilities> {
+        &self.capabilities
+    }
+
+    /// The feature needed by `req`, if backend `id` does not implement it
+    fn missing(&self, id: &str, req: &BackendRequest) -> Option<&'static str> {
+        self.capabilities.get(id).and_then(|caps| caps.missing(req))
+    }
+
+    /// Number of requests skipped by each backend, because it fell behind
+    #[must_use]
+    pub const fn lagged(&self) -> &BTreeMap<String, u64> {
+        &self.lagged
+    }
+
+    /// Subscribe to a copy of all backend requests (for monitoring only)
+    #[must_use]
+    pub fn monitor(&self) -> Receiver<Arc<BackendRequest>> {
+        self.monitor.subscribe()
+    }
+
+    /// Subscribe to the signals used to measure backend latency
+    #[must_use]
+    pub fn latency(&self) -> Receiver<LatencySignal> {
+        self.latency.subscribe()
+    }
+
+    /// Send a latency signal (ignored while nobody is measuring)
+    pub fn signal(&self, signal: LatencySignal) {
+        let _ = self.latency.send(signal);
+    }
+
+    /// Send request to backend `id`
+    pub fn send(&self, id: &str, req: BackendRequest) -> ApiResult<()> {
+        let req = Arc::new(req);
+        let _ = self.monitor.send(req.clone());
+
+        let Some(chan) = self.backends.get(id) else {
+            return Err(ApiError::BackendNotRunning(id.to_string()));
+        };
+
+        if let Some(feature) = self.missing(id, &req) {
+            return Err(ApiError::BackendUnsupported(id.to_string(), feature));
+        }
+
+        chan.send(req)
+            .map_err(|_| ApiError::BackendNotRunning(id.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Send request to all running backends that support it
+    pub fn broadcast(&self, req: BackendRequest) -> ApiResult<()> {
+        let req = Arc::new(req);
+        let _ = self.monitor.send(req.clone());
+
+        let mut unsupported = None;
+        let mut delivered = 0;
+        for (id, chan) in &self.backends {
+            if let Some(feature) = self.missing(id, &req) {
+                unsupported = Some(ApiError::BackendUnsupported(id.clone(), feature));
+                continue;
+            }
+            if chan.send(req.clone()).is_ok() {
+                delivered += 1;
+            }
+        }
+
+        if delivered == 0 {
+            return Err(unsupported.unwrap_or(ApiError::NoBackends));
+        }
+
+        Ok(())
+    }
+
+    /// Send request to all running Home Assistant backends
+    pub fn broadcast_hass(&self, req: HassRequest) -> ApiResult<()> {
+        let req = Arc::new(req);
+
+        let delivered = self
+            .hass
+            .values()
+            .filter(|chan| chan.send(req.clone()).is_ok())
+            .count();
+
+        if delivered == 0 {
+            return Err(ApiError::NoBackends);
+        }
+
+        Ok(())
+    }
+}
+
+/// Unpack a request received by backend `id`.
+///
+/// If the backend fell behind, the skipped requests are logged and counted,
+/// and `None` is returned. The receiver itself has already moved on to the
+/// oldest request still buffered, so the backend can simply continue.
+pub async fn received<T>(
+    res: &Mutex<Resources>,
+    id: &str,
+    req: Result<T, RecvError>,
+) -> ApiResult<Option<T>> {
+    match req {
+        Ok(req) => Ok(Some(req)),
+        Err(RecvError::Lagged(count)) => {
+            log::warn!("[{id}] Backend fell behind, skipped {count} requests");
+            res.lock().await.backend_lagged(id, count);
+            Ok(None)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+impl Default for BackendRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bifrost_api::backend::{BackendCapabilities, BackendRequest};
+    use hue::api::{LightGradientUpdate, LightUpdate, RType, ResourceLink};
+    use uuid::Uuid;
+
+    use crate::backend::router::BackendRouter;
+    use crate::error::ApiError;
+
+    fn link(rtype: RType) -> ResourceLink {
+        rtype.link_to(Uuid::new_v4())
+    }
+
+    fn gradient_update() -> BackendRequest {
+        let upd = LightUpdate::new().with_gradient(Some(LightGradientUpdate {
+            mode: None,
+            points: vec![],
+        }));
+        BackendRequest::LightUpdate(link(RType::Light), upd)
+    }
+
+    #[test]
+    fn missing_features() {
+        let none = BackendCapabilities::default();
+        let all = BackendCapabilities {
+            scenes: true,
+            entertainment: true,
+            gradients: true,
+            sensors: true,
+            buttons: true,
+        };
+
+        let requests = [
+            (gradient_update(), "gradients"),
+            (
+                BackendRequest::SensorEnabledUpdate(link(RType::Motion), true),
+                "sensors",
+            ),
+            (BackendRequest::ButtonPress(link(RType::Button)), "buttons"),
+            (
+                BackendRequest::EntertainmentStart(Uuid::new_v4()),
+                "entertainment",
+            ),
+        ];
+        for (req, feature) in &requests {
+            assert_eq!(none.missing(req), Some(*feature));
+            assert_eq!(all.missing(req), None);
+        }
+
+        // plain updates, and stopping a stream, need no features
+        let plain = BackendRequest::LightUpdate(link(RType::Light), LightUpdate::new());
+        assert_eq!(none.missing(&plain), None);
+        assert_eq!(none.missing(&BackendRequest::EntertainmentStop()), None);
+    }
+
+    #[test]
+    fn broadcast_skips_unsupported() {
+        let mut router = BackendRouter::new();
+        let mut plain = router.subscribe("z2m@plain", BackendCapabilities::default());
+        let mut gradients = router.subscribe(
+            "z2m@gradients",
+            BackendCapabilities {
+                gradients: true,
+                ..BackendCapabilities::default()
+            },
+        );
+
+        router.broadcast(gradient_update()).unwrap();
+
+        assert!(gradients.try_recv().is_ok());
+        assert!(plain.try_recv().is_err());
+    }
+
+    #[test]
+    fn broadcast_unsupported_everywhere() {
+        let mut router = BackendRouter::new();
+        let _rx = router.subscribe("z2m@plain", BackendCapabilities::default());
+
+        assert!(matches!(
+            router.broadcast(gradient_update()),
+            Err(ApiError::BackendUnsupported(id, "gradients")) if id == "z2m@plain"
+        ));
+    }
+
+    #[test]
+    fn broadcast_without_backends() {
+        let router = BackendRouter::new();
+
+        assert!(matches!(
+            router.broadcast(BackendRequest::EntertainmentStop()),
+            Err(ApiError::NoBackends)
+        ));
+    }
+}
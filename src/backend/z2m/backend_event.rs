@@ -10,21 +10,71 @@ use uuid::Uuid;
 use bifrost_api::backend::BackendRequest;
 use hue::api::{
     Entertainment, EntertainmentConfiguration, GroupedLight, GroupedLightUpdate, Light,
-    LightEffectsV2Update, LightGradientMode, LightUpdate, RType, Resource, ResourceLink, Room,
-    RoomUpdate, Scene, SceneActive, SceneStatus, SceneStatusEnum, SceneUpdate,
-    ZigbeeDeviceDiscoveryUpdate,
+    LightEffectsV2Update, LightGradientMode, LightPowerup, LightPowerupColor, LightPowerupDimming,
+    LightPowerupOn, LightUpdate, RType, Resource, ResourceLink, Room, RoomUpdate, Scene,
+    SceneActive, SceneStatus, SceneStatusEnum, SceneUpdate, ZigbeeDeviceDiscoveryUpdate,
 };
 use hue::error::HueError;
 use hue::stream::HueStreamLightsV2;
-use z2m::update::{DeviceEffect, DeviceUpdate};
+use z2m::hexcolor::HexColor;
+use z2m::update::{DeviceEffect, DeviceUpdate, HuePowerOnBehavior, PowerOnBehavior};
 
 use crate::backend::z2m::Z2mBackend;
 use crate::backend::z2m::entertainment::EntStream;
+use crate::backend::z2m::palette;
 use crate::backend::z2m::websocket::Z2mWebSocket;
 use crate::error::ApiResult;
 use crate::model::state::AuxData;
+use crate::resource::Resources;
 
 impl Z2mBackend {
+    /// Translate hue powerup settings to z2m power-on options.
+    ///
+    /// Philips Hue lights support on/off, brightness and color through the
+    /// `hue_power_on_*` options. Other lights only get the generic (on/off)
+    /// power-on behavior, and startup color temperature.
+    fn make_powerup_update(powerup: &LightPowerup, hue: bool) -> DeviceUpdate {
+        let mut upd = DeviceUpdate::new();
+
+        if hue {
+            upd.hue_power_on_behavior = match powerup.on {
+                LightPowerupOn::None => None,
+                LightPowerupOn::Previous => Some(HuePowerOnBehavior::Recover),
+                LightPowerupOn::On { on } if on.on => Some(HuePowerOnBehavior::On),
+                LightPowerupOn::On { .. } => Some(HuePowerOnBehavior::Off),
+            };
+
+            if let LightPowerupDimming::Dimming { dimming } = powerup.dimming {
+                upd.hue_power_on_brightness =
+                    Some((dimming.brightness / 100.0).unit_to_u8_clamped_light());
+            }
+
+            match powerup.color {
+                LightPowerupColor::ColorTemperature { color_temperature } => {
+                    upd.hue_power_on_color_temperature = color_temperature.mirek;
+                }
+                LightPowerupColor::Color { color } => {
+                    let [r, g, b] = color.xy.to_rgb(255.0);
+                    upd.hue_power_on_color = Some(HexColor::new(r, g, b));
+                }
+                LightPowerupColor::None | LightPowerupColor::Previous => {}
+            }
+        } else {
+            upd.power_on_behavior = match powerup.on {
+                LightPowerupOn::None => None,
+                LightPowerupOn::Previous => Some(PowerOnBehavior::Previous),
+                LightPowerupOn::On { on } if on.on => Some(PowerOnBehavior::On),
+                LightPowerupOn::On { .. } => Some(PowerOnBehavior::Off),
+            };
+
+            if let LightPowerupColor::ColorTemperature { color_temperature } = powerup.color {
+                upd.color_temp_startup = color_temperature.mirek.map(f64::from);
+            }
+        }
+
+        upd
+    }
+
     #[allow(clippy::match_same_arms)]
     fn make_hue_specific_update(upd: &LightUpdate) -> ApiResult<HueZigbeeUpdate> {
         let mut hz = HueZigbeeUpdate::new();
@@ -100,8 +150,26 @@ impl Z2mBackend {
             })?;
         }
         let hue_effects = lock.get::<Light>(link)?.effects.is_some();
+
+        // z2m does not report power-on settings back, so keep track of them
+        // here
+        let powerup = upd.powerup.as_ref().map(LightPowerup::from_update);
+        if let Some(powerup) = &powerup {
+            lock.update::<Light>(&link.rid, |light| {
+                light.powerup = Some(powerup.clone());
+            })?;
+        }
         drop(lock);
 
+        if let Some(powerup) = &powerup {
+            log::info!(
+                "[{}] Set power-on behavior of {topic}: {powerup:?}",
+                self.name
+            );
+            let payload = Self::make_powerup_update(powerup, hue_effects);
+            z2mws.send_update(topic, &payload).await?;
+        }
+
         /* step 1: send generic light update */
         let transition = upd
             .dynamics
@@ -193,6 +261,124 @@ impl Z2mBackend {
         Ok(())
     }
 
+    /// Mark scene `link` as `active`, and the other scenes in its room as
+    /// inactive
+    fn scene_activate(
+        lock: &mut Resources,
+        link: &ResourceLink,
+        active: SceneActive,
+    ) -> ApiResult<()> {
+        let room = lock.get::<Scene>(link)?.group;
+
+        for rid in lock.get_scenes_for_room(&room.rid) {
+            lock.update::<Scene>(&rid, |scn| {
+                scn.status = Some(SceneStatus {
+                    active: if rid == link.rid {
+                        active
+                    } else {
+                        SceneActive::Inactive
+                    },
+                    last_recall: None,
+                });
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Start cycling the lights in the room of scene `link` through the
+    /// scene palette (replacing any palette already playing there)
+    async fn backend_scene_play_palette(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        let mut lock = self.state.lock().await;
+        let scene = lock.get::<Scene>(link)?.clone();
+
+        if scene.palette.is_empty() {
+            log::warn!("[{}] Scene {link:?} has no palette to play", self.name);
+            return Ok(());
+        }
+
+        let topics: Vec<String> = lock
+            .get::<Room>(&scene.group)?
+            .children
+            .iter()
+            .filter_map(|device| self.rmap.get(device))
+            .cloned()
+            .collect();
+
+        Self::scene_activate(&mut lock, link, SceneActive::DynamicPalette)?;
+        drop(lock);
+
+        self.stop_palette(&scene.group);
+
+        let interval = palette::step_interval(scene.speed);
+        let steps = palette::palette_steps(&scene.palette, interval);
+
+        log::info!(
+            "[{}] Playing palette of scene {link:?} ({} steps, {interval:?} each)",
+            self.name,
+            steps.len()
+        );
+
+        let job = tokio::spawn(palette::play_palette(
+            self.message_tx.clone(),
+            topics,
+            steps,
+            interval,
+        ));
+        self.palettes
+            .insert(scene.group, (*link, job.abort_handle()));
+
+        Ok(())
+    }
+
+    /// Stop palette playback in `room`, returning the scene that was playing
+    fn stop_palette(&mut self, room: &ResourceLink) -> Option<ResourceLink> {
+        let (scene, job) = self.palettes.remove(room)?;
+        job.abort();
+        log::debug!("[{}] Stopped palette of scene {scene:?}", self.name);
+        Some(scene)
+    }
+
+    /// Stop palette playback in `room`, and mark the scene that was playing
+    /// as no longer active
+    async fn end_palette(&mut self, room: &ResourceLink) -> ApiResult<()> {
+        if let Some(scene) = self.stop_palette(room) {
+            self.state.lock().await.update::<Scene>(&scene.rid, |scn| {
+                scn.status = Some(SceneStatus {
+                    active: SceneActive::Inactive,
+                    last_recall: None,
+                });
+            })?;
+        }
+        Ok(())
+    }
+
+    /// End palette playback in the room of `light`, since it was changed by
+    /// hand
+    async fn end_palette_for_light(&mut self, light: &ResourceLink) -> ApiResult<()> {
+        if self.palettes.is_empty() {
+            return Ok(());
+        }
+
+        let lock = self.state.lock().await;
+        let device = lock.get::<Light>(light)?.owner;
+        let rooms: Vec<ResourceLink> = self
+            .palettes
+            .keys()
+            .filter(|room| {
+                lock.get::<Room>(room)
+                    .is_ok_and(|room| room.children.contains(&device))
+            })
+            .copied()
+            .collect();
+        drop(lock);
+
+        for room in rooms {
+            self.end_palette(&room).await?;
+        }
+        Ok(())
+    }
+
     async fn backend_scene_update(
         &mut self,
         z2mws: &mut Z2mWebSocket,
@@ -201,33 +387,49 @@ impl Z2mBackend {
     ) -> ApiResult<()> {
         let mut lock = self.state.lock().await;
 
-        let scene = lock.get::<Scene>(link)?;
-
         let index = lock
             .aux_get(link)?
             .index
             .ok_or(HueError::NotFound(link.rid))?;
 
         if let Some(recall) = &upd.recall {
-            if recall.action == Some(SceneStatusEnum::Active) {
-                let scenes = lock.get_scenes_for_room(&scene.group.rid);
-                for rid in scenes {
-                    lock.update::<Scene>(&rid, |scn| {
-                        scn.status = Some(SceneStatus {
-                            active: if rid == link.rid {
-                                SceneActive::Static
-                            } else {
-                                SceneActive::Inactive
-                            },
-                            last_recall: None,
-                        });
-                    })?;
-                }
+            if recall.action == Some(SceneStatusEnum::DynamicPalette) {
+                drop(lock);
+                self.backend_scene_play_palette(link).await?;
+            } else if recall.action == Some(SceneStatusEnum::Active) {
+                Self::scene_activate(&mut lock, link, SceneActive::Static)?;
 
                 let room = lock.get::<Scene>(link)?.group;
                 drop(lock);
 
-                if let Some(topic) = self.rmap.get(&room).cloned() {
+                self.stop_palette(&room);
+
+                if recall.dimming.is_some() || recall.duration.is_some() || upd.actions.is_some() {
+                    // z2m scenes are stored on the devices, and cannot be
+                    // recalled at a different brightness (or over a given
+                    // time, or with other actions). Send the (scaled) scene
+                    // actions to each light instead.
+                    log::info!("[{}] Recall scene with overrides: {link:?}", self.name);
+
+                    let overrides = upd.actions.as_deref().unwrap_or_default();
+                    let actions = self
+                        .state
+                        .lock()
+                        .await
+                        .get::<Scene>(link)?
+                        .recall_actions(recall, overrides);
+                    for elem in actions {
+                        let upd = LightUpdate {
+                            on: elem.action.on,
+                            dimming: elem.action.dimming,
+                            color: elem.action.color,
+                            color_temperature: elem.action.color_temperature,
+                            dynamics: recall.dynamics(),
+                            ..LightUpdate::default()
+                        };
+                        self.backend_light_update(z2mws, &elem.target, &upd).await?;
+                    }
+                } else if let Some(topic) = self.rmap.get(&room).cloned() {
                     log::info!("[{}] Recall scene: {link:?}", self.name);
 
                     let mut lock = self.state.lock().await;
@@ -264,13 +466,18 @@ impl Z2mBackend {
     }
 
     async fn backend_grouped_light_update(
-        &self,
+        &mut self,
         z2mws: &mut Z2mWebSocket,
         link: &ResourceLink,
         upd: &GroupedLightUpdate,
     ) -> ApiResult<()> {
         let room = self.state.lock().await.get::<GroupedLight>(link)?.owner;
 
+        // turning off the room ends any dynamic palette playing there
+        if upd.on.is_some_and(|on| !on.on) {
+            self.end_palette(&room).await?;
+        }
+
         if let Some(topic) = self.rmap.get(&room) {
             z2mws.send_update(topic, &upd.into()).await?;
         }
@@ -317,9 +524,41 @@ impl Z2mBackend {
         Ok(())
     }
 
-    async fn backend_delete(&self, z2mws: &mut Z2mWebSocket, link: &ResourceLink) -> ApiResult<()> {
+    async fn backend_device_update(
+        &self,
+        z2mws: &mut Z2mWebSocket,
+        link: &ResourceLink,
+        upd: &hue::api::DeviceUpdate,
+    ) -> ApiResult<()> {
+        let Some(name) = upd.metadata.as_ref().and_then(|md| md.name.as_ref()) else {
+            return Ok(());
+        };
+
+        // rename the device in z2m, since the next import uses its friendly name
+        if let Some(topic) = self.rmap.get(link) {
+            if topic != name {
+                log::info!("[{}] Requesting z2m rename of {topic} to {name}", self.name);
+                z2mws.send_device_rename(topic, name).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn backend_delete(
+        &mut self,
+        z2mws: &mut Z2mWebSocket,
+        link: &ResourceLink,
+    ) -> ApiResult<()> {
         match link.rtype {
             RType::Scene => {
+                self.palettes.retain(|_, (scene, job)| {
+                    if scene == link {
+                        job.abort();
+                    }
+                    scene != link
+                });
+
                 let lock = self.state.lock().await;
                 let room = lock.get::<Scene>(link)?.group;
                 let index = lock
@@ -384,16 +623,20 @@ impl Z2mBackend {
                 let light_id = ent
                     .renderer_reference
                     .ok_or(HueError::NotFound(member.service.rid))?;
-                let topic = self
-                    .rmap
-                    .get(&light_id)
-                    .ok_or(HueError::NotFound(light_id.rid))?;
+                // lights of other backends are rendered by those
+                let Some(topic) = self.rmap.get(&light_id) else {
+                    continue;
+                };
                 let dev = self
                     .network
                     .get(topic)
                     .ok_or(HueError::NotFound(member.service.rid))?;
 
-                let segment_addr = dev.network_address + member.index;
+                // some gradient lights number their segments differently
+                let index = lock
+                    .light_segment_map(&light_id)
+                    .map_or(member.index, |map| map.segment(member.index));
+                let segment_addr = dev.network_address + index;
 
                 addrs
                     .entry(dev.friendly_name.clone())
@@ -486,15 +729,19 @@ impl Z2mBackend {
 
         match &*req {
             BackendRequest::LightUpdate(link, upd) => {
+                // changing a light by hand ends the palette playing in its
+                // room, so the two do not fight over it
+                if upd.on.is_some()
+                    || upd.dimming.is_some()
+                    || upd.color.is_some()
+                    || upd.color_temperature.is_some()
+                    || upd.gradient.is_some()
+                {
+                    self.end_palette_for_light(link).await?;
+                }
                 self.backend_light_update(z2mws, link, upd).await
             }
-            BackendRequest::SensorEnabledUpdate(_, _) => Ok(()),
-            BackendRequest::HassSync => Ok(()),
-            BackendRequest::HassUpsertEntity(_) => Ok(()),
-            BackendRequest::HassRemoveEntity(_) => Ok(()),
-            BackendRequest::HassUpdateRooms => Ok(()),
-            BackendRequest::HassConnect => Ok(()),
-            BackendRequest::HassDisconnect => Ok(()),
+            BackendRequest::SensorEnabledUpdate(_, _) | BackendRequest::ButtonPress(_) => Ok(()),
 
             BackendRequest::SceneCreate(link, sid, scene) => {
                 self.backend_scene_create(z2mws, link, *sid, scene).await
@@ -512,6 +759,10 @@ impl Z2mBackend {
                 self.backend_room_update(z2mws, link, upd).await
             }
 
+            BackendRequest::DeviceUpdate(link, upd) => {
+                self.backend_device_update(z2mws, link, upd).await
+            }
+
             BackendRequest::Delete(link) => self.backend_delete(z2mws, link).await,
 
             BackendRequest::EntertainmentStart(ent_id) => {
@@ -3,9 +3,12 @@ use serde_json::Value;
 use tokio_tungstenite::tungstenite;
 use uuid::Uuid;
 
-use hue::api::{DimmingUpdate, GroupedLight, Light, LightUpdate, RType, Resource, Room};
+use hue::api::{
+    DimmingUpdate, GroupedLight, Light, LightUpdate, RType, Resource, ResourceLink, Room,
+};
 use z2m::api::{
-    BridgeDevices, DeviceRemoveResponse, GroupMemberChange, Message, RawMessage, Response,
+    BridgeDevices, DeviceRemoveResponse, DeviceRename, GroupMemberChange, Message, RawMessage,
+    Response,
 };
 use z2m::update::DeviceUpdate;
 
@@ -18,6 +21,8 @@ impl Z2mBackend {
 
         let mut lock = self.state.lock().await;
         lock.update::<Light>(uuid, |light| *light += &upd)?;
+        lock.backend_report(uuid);
+        lock.refresh_light_groups(uuid)?;
 
         self.learner.learn(uuid, &lock, devupd)?;
         self.learner.collect(&mut lock)?;
@@ -28,6 +33,13 @@ impl Z2mBackend {
 
     async fn handle_update_grouped_light(&self, uuid: &Uuid, upd: &DeviceUpdate) -> ApiResult<()> {
         let mut res = self.state.lock().await;
+        res.backend_report(uuid);
+
+        // the state of merged rooms is derived from all of their lights
+        if res.is_merged_room(&RType::GroupedLight.link_to(*uuid)) {
+            return Ok(());
+        }
+
         res.update::<GroupedLight>(uuid, |glight| {
             if let Some(state) = &upd.state {
                 glight.on = Some((*state).into());
@@ -69,9 +81,59 @@ impl Z2mBackend {
         Ok(())
     }
 
+    /// Track devices going offline, and coming back online.
+    ///
+    /// See <https://www.zigbee2mqtt.io/guide/usage/mqtt_topics_and_messages.html#zigbee2mqtt-friendly-name-availability>
+    fn handle_availability(&mut self, name: &str, payload: &Value) {
+        // older z2m versions send a plain string instead of an object
+        let online = payload.get("state").unwrap_or(payload).as_str() == Some("online");
+
+        if !online {
+            self.offline.insert(name.to_string());
+            self.restored.remove(name);
+        } else if self.offline.remove(name) {
+            log::debug!("[{}] Device {name} is available again", self.name);
+            self.restored.insert(name.to_string());
+        }
+    }
+
+    /// Apply the configured startup state to a light that came back online,
+    /// once it reports its state.
+    async fn apply_light_startup(&mut self, name: &str, link: &ResourceLink, payload: &Value) {
+        let Some(state) = payload.get("state").and_then(Value::as_str) else {
+            return;
+        };
+
+        self.restored.remove(name);
+
+        // lights that come back off are left alone
+        if state != "ON" {
+            return;
+        }
+
+        let Some(startup) = self.state.lock().await.light_startup(link).cloned() else {
+            return;
+        };
+
+        log::info!(
+            "[{}] Light {name} is back after power loss, applying startup state {startup:?}",
+            self.name
+        );
+
+        let upd = DeviceUpdate::new()
+            .with_brightness(startup.brightness.map(|bri| bri / 100.0 * 254.0))
+            .with_color_temp(startup.mirek);
+
+        let _ = self.message_tx.send((name.to_string(), upd));
+    }
+
     async fn handle_device_message(&mut self, msg: RawMessage) -> ApiResult<()> {
-        if msg.topic.ends_with("/availability") || msg.topic.ends_with("/action") {
-            // availability: https://www.zigbee2mqtt.io/guide/usage/mqtt_topics_and_messages.html#zigbee2mqtt-friendly-name-availability
+        if let Some(name) = msg.topic.strip_suffix("/availability") {
+            self.handle_availability(name, &msg.payload);
+            return Ok(());
+        }
+
+        if msg.topic.ends_with("/action") {
             // action: https://www.home-assistant.io/integrations/device_trigger.mqtt/
             return Ok(());
         }
@@ -95,6 +157,11 @@ impl Z2mBackend {
             );
         }
 
+        if val.rtype == RType::Light && self.restored.contains(&msg.topic) {
+            self.apply_light_startup(&msg.topic, val, &msg.payload)
+                .await;
+        }
+
         /* return Ok here, since we do not want to break the event loop */
         Ok(())
     }
@@ -158,6 +225,29 @@ impl Z2mBackend {
         Ok(())
     }
 
+    fn bridge_device_rename(&mut self, data: &DeviceRename) {
+        log::info!(
+            "[{}] Device {} renamed to {}",
+            self.name,
+            data.from,
+            data.to
+        );
+
+        if let Some(link) = self.map.remove(&data.from) {
+            self.map.insert(data.to.clone(), link);
+        }
+
+        for name in self.rmap.values_mut() {
+            if *name == data.from {
+                name.clone_from(&data.to);
+            }
+        }
+
+        if let Some(dev) = self.network.remove(&data.from) {
+            self.network.insert(data.to.clone(), dev);
+        }
+    }
+
     #[allow(clippy::collapsible_else_if)]
     async fn bridge_group_member_change(
         &self,
@@ -239,6 +329,15 @@ impl Z2mBackend {
                 self.bridge_group_member_change(change, added).await?;
             }
 
+            Message::BridgeDeviceRename(obj) => {
+                let Response::Ok { data, .. } = obj else {
+                    log::warn!("[{}] Error reported from z2m: {obj:?}", self.name);
+                    return Ok(());
+                };
+
+                self.bridge_device_rename(data);
+            }
+
             Message::BridgeDeviceRemove(obj) => {
                 let Response::Ok { data, .. } = obj else {
                     log::warn!("[{}] Error reported from z2m: {obj:?}", self.name);
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 use chrono::Utc;
 use maplit::btreeset;
@@ -6,11 +6,11 @@ use serde_json::json;
 use uuid::Uuid;
 
 use hue::api::{
-    BridgeHome, Button, ButtonData, ButtonMetadata, ButtonReport, DeviceArchetype,
-    DeviceProductData, Entertainment, EntertainmentSegment, EntertainmentSegments, GroupedLight,
-    Light, LightEffects, LightEffectsV2, LightMetadata, Metadata, RType, Resource, ResourceLink,
-    Room, RoomArchetype, RoomMetadata, Scene, SceneActive, SceneMetadata, SceneRecall, SceneStatus,
-    Stub, Taurus, ZigbeeConnectivity, ZigbeeConnectivityStatus,
+    Button, ButtonData, ButtonMetadata, ButtonReport, DeviceArchetype, DeviceProductData,
+    Entertainment, EntertainmentSegment, EntertainmentSegments, GroupedLight, Light, LightEffects,
+    LightEffectsV2, LightMetadata, Metadata, RType, Resource, ResourceLink, Room, RoomArchetype,
+    RoomMetadata, Scene, SceneActive, SceneMetadata, ScenePalette, SceneRecall, SceneStatus, Stub,
+    Taurus, ZigbeeConnectivity, ZigbeeConnectivityStatus,
 };
 use hue::scene_icons;
 use z2m::api::ExposeLight;
@@ -38,7 +38,19 @@ impl Z2mBackend {
         let link_zigcon = RType::ZigbeeConnectivity.deterministic(&apidev.ieee_address);
 
         let product_data = DeviceProductData::guess_from_device(apidev);
-        let metadata = LightMetadata::new(product_data.product_archetype.clone(), name);
+
+        // keep the archetype if it was changed in a hue app, since z2m has
+        // nowhere to store it
+        let archetype = self
+            .state
+            .lock()
+            .await
+            .get::<hue::api::Device>(&link_device)
+            .map_or_else(
+                |_| product_data.product_archetype.clone(),
+                |dev| dev.metadata.archetype.clone(),
+            );
+        let metadata = LightMetadata::new(archetype, name);
 
         let effects =
             apidev.manufacturer.as_deref() == Some(DeviceProductData::SIGNIFY_MANUFACTURER_NAME);
@@ -118,14 +130,7 @@ impl Z2mBackend {
         // FIXME: The Taurus objects are seen on Hue Entertainment devices on a
         // real hue bridge, but nobody knows what it does. Some clients seem to
         // want them present, though.
-        let taurus = Taurus {
-            capabilities: vec![
-                "sensor".to_string(),
-                "collector".to_string(),
-                "sync".to_string(),
-            ],
-            owner: link_device,
-        };
+        let taurus = Taurus::new(link_device);
 
         let zigcon = ZigbeeConnectivity {
             channel: None,
@@ -137,7 +142,7 @@ impl Z2mBackend {
 
         let mut res = self.state.lock().await;
         res.aux_set(&link_light, AuxData::new().with_topic(name));
-        res.add(&link_device, Resource::Device(dev))?;
+        res.add_owned(&self.backend_id(), &link_device, Resource::Device(dev))?;
         res.add(&link_light, Resource::Light(light))?;
         res.add(&link_enttm, Resource::Entertainment(enttm))?;
         res.add(&link_taurus, Resource::Taurus(taurus))?;
@@ -191,7 +196,7 @@ impl Z2mBackend {
             extended_pan_id: None,
         };
 
-        res.add(&link_device, Resource::Device(dev))?;
+        res.add_owned(&self.backend_id(), &link_device, Resource::Device(dev))?;
         res.add(&link_button, Resource::Button(button))?;
         res.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
         drop(res);
@@ -221,7 +226,7 @@ impl Z2mBackend {
         let link_room = RType::Room.deterministic(&grp.friendly_name);
         let link_glight = RType::GroupedLight.deterministic((link_room.rid, grp.id));
 
-        let children = grp
+        let mut children: BTreeSet<ResourceLink> = grp
             .members
             .iter()
             .map(|f| RType::Device.deterministic(&f.ieee_address))
@@ -243,12 +248,7 @@ impl Z2mBackend {
                     image: guess_scene_icon(&scn.name),
                     name: scn.name.to_string(),
                 },
-                palette: json!({
-                    "color": [],
-                    "dimming": [],
-                    "color_temperature": [],
-                    "effects": [],
-                }),
+                palette: ScenePalette::default(),
                 speed: 0.5,
                 recall: SceneRecall {
                     action: None,
@@ -301,7 +301,12 @@ impl Z2mBackend {
             );
         }
 
-        let mut metadata = RoomMetadata::new(RoomArchetype::Home, room_name);
+        // z2m groups cannot be renamed without changing the room id, so
+        // names (and icons) changed in a hue app are kept from the known room
+        let mut metadata = res.get::<Room>(&link_room).map_or_else(
+            |_| RoomMetadata::new(RoomArchetype::Home, room_name),
+            |room| room.metadata.clone(),
+        );
         if let Some(room_conf) = self.config.rooms.get(&topic) {
             if let Some(name) = &room_conf.name {
                 metadata.name = name.to_string();
@@ -311,6 +316,15 @@ impl Z2mBackend {
             }
         }
 
+        // keep the devices other backends merged into this room
+        let backend_id = self.backend_id();
+        if let Ok(room) = res.get::<Room>(&link_room) {
+            children.extend(room.children.iter().filter(|dev| {
+                res.backend_owner(&dev.rid)
+                    .is_some_and(|(_, owner)| owner != backend_id)
+            }));
+        }
+
         let room = Room {
             children,
             metadata,
@@ -321,13 +335,9 @@ impl Z2mBackend {
         self.rmap.insert(link_glight, topic.clone());
         self.rmap.insert(link_room, topic.clone());
 
-        for id in &res.get_resource_ids_by_type(RType::BridgeHome) {
-            res.update(id, |bh: &mut BridgeHome| {
-                bh.children.insert(link_room);
-            })?;
-        }
+        res.attach_room(&link_room)?;
 
-        res.add(&link_room, Resource::Room(room))?;
+        res.add_owned(&self.backend_id(), &link_room, Resource::Room(room))?;
 
         let glight = GroupedLight::new(link_room);
 
@@ -3,6 +3,7 @@ mod bridge_event;
 mod bridge_import;
 pub mod entertainment;
 pub mod learn;
+pub mod palette;
 pub mod websocket;
 pub mod zclcommand;
 
@@ -14,6 +15,7 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use native_tls::TlsConnector;
 use svc::error::SvcError;
+use svc::policy::Policy;
 use svc::template::ServiceTemplate;
 use svc::traits::{BoxDynService, Service};
 use thiserror::Error;
@@ -21,14 +23,14 @@ use tokio::net::TcpStream;
 use tokio::select;
 use tokio::sync::broadcast::Receiver;
 use tokio::sync::{Mutex, mpsc};
-use tokio_tungstenite::{
-    Connector, MaybeTlsStream, WebSocketStream, connect_async_tls_with_config,
-};
+use tokio::task::AbortHandle;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
 
-use bifrost_api::backend::BackendRequest;
+use bifrost_api::backend::{BackendCapabilities, BackendRequest};
 use hue::api::ResourceLink;
 use z2m::update::DeviceUpdate;
 
+use crate::backend::router::received;
 use crate::backend::z2m::entertainment::EntStream;
 use crate::backend::z2m::learn::SceneLearn;
 use crate::backend::z2m::websocket::Z2mWebSocket;
@@ -37,6 +39,7 @@ use crate::error::{ApiError, ApiResult};
 use crate::model::throttle::Throttle;
 use crate::resource::Resources;
 use crate::server::appstate::AppState;
+use crate::server::proxy;
 
 #[derive(Error, Debug)]
 pub enum TemplateError {
@@ -66,6 +69,14 @@ impl ServiceTemplate for Z2mServiceTemplate {
 
         Ok(svc.boxed())
     }
+
+    fn run_policy(&self, instance: &str) -> Option<Policy> {
+        let policy = self
+            .state
+            .config()
+            .service_policy(&format!("z2m@{instance}"));
+        Some(policy)
+    }
 }
 
 pub struct Z2mBackend {
@@ -84,12 +95,29 @@ pub struct Z2mBackend {
     throttle: Throttle,
     socket: Option<WebSocketStream<MaybeTlsStream<TcpStream>>>,
 
+    // devices reported offline, and lights waiting for their startup state
+    // after coming back online
+    offline: HashSet<String>,
+    restored: HashSet<String>,
+
+    // dynamic palette playback, by room: (scene, task)
+    palettes: HashMap<ResourceLink, (ResourceLink, AbortHandle)>,
+
     // for sending delayed messages over the websocket
     message_rx: mpsc::UnboundedReceiver<(String, DeviceUpdate)>,
     message_tx: mpsc::UnboundedSender<(String, DeviceUpdate)>,
 }
 
 impl Z2mBackend {
+    /// Features implemented by all z2m backends
+    pub const CAPABILITIES: BackendCapabilities = BackendCapabilities {
+        scenes: true,
+        entertainment: true,
+        gradients: true,
+        sensors: true,
+        buttons: false,
+    };
+
     const DEFAULT_FPS: u32 = 20;
     const LIGHT_BREATHE_DURATION: Duration = Duration::from_secs(2);
 
@@ -123,22 +151,32 @@ impl Z2mBackend {
             fps,
             message_rx,
             message_tx,
+            palettes: HashMap::new(),
+            offline: HashSet::new(),
+            restored: HashSet::new(),
             socket: None,
             counter: 0,
         })
     }
 
+    /// Id used to route backend requests to this backend
+    pub(super) fn backend_id(&self) -> String {
+        format!("z2m@{}", self.name)
+    }
+
     pub async fn event_loop(
         &mut self,
         chan: &mut Receiver<Arc<BackendRequest>>,
         mut socket: Z2mWebSocket,
     ) -> ApiResult<()> {
+        let id = self.backend_id();
         loop {
             select! {
                 // all backend event handling implemented in backend::z2m::backend_event
                 pkt = chan.recv() => {
-                    let api_req = pkt?;
-                    self.handle_backend_event(&mut socket, api_req).await?;
+                    if let Some(api_req) = received(&self.state, &id, pkt).await? {
+                        self.handle_backend_event(&mut socket, api_req).await?;
+                    }
                     // FIXME: this used to be our "throttle" feature, but it breaks entertainment mode
                     /* tokio::time::sleep(std::time::Duration::from_millis(100)).await; */
                 },
@@ -195,14 +233,14 @@ impl Service for Z2mBackend {
         };
 
         log::info!("[{}] Connecting to {}", self.name, &sanitized_url);
-        match connect_async_tls_with_config(url.as_str(), None, false, connector.clone()).await {
-            Ok((socket, _)) => {
+        match proxy::connect_websocket(&url, self.config.bifrost.proxy.as_ref(), connector).await {
+            Ok(socket) => {
                 self.socket = Some(socket);
                 Ok(())
             }
             Err(err) => {
                 log::error!("[{}] Connect failed: {err:?}", self.name);
-                Err(err.into())
+                Err(err)
             }
         }
     }
@@ -210,7 +248,12 @@ impl Service for Z2mBackend {
     async fn run(&mut self) -> ApiResult<()> {
         if let Some(socket) = self.socket.take() {
             let z2m_socket = Z2mWebSocket::new(self.name.clone(), socket);
-            let mut chan = self.state.lock().await.backend_event_stream();
+            let id = self.backend_id();
+            let mut chan = self
+                .state
+                .lock()
+                .await
+                .backend_event_stream(&id, Self::CAPABILITIES);
             let res = self.event_loop(&mut chan, z2m_socket).await;
             if let Err(err) = res {
                 log::error!("[{}] Event loop broke: {err}", self.name);
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::sleep;
+
+use hue::api::{DimmingUpdate, ScenePalette};
+use z2m::update::DeviceUpdate;
+
+/// Time between palette steps at the lowest scene speed
+const SLOWEST_STEP: Duration = Duration::from_secs(60);
+
+/// Time between palette steps at the highest scene speed
+const FASTEST_STEP: Duration = Duration::from_secs(2);
+
+/// Time between palette steps, for a scene `speed` (0.0 - 1.0)
+#[must_use]
+pub fn step_interval(speed: f64) -> Duration {
+    let speed = if speed.is_finite() {
+        speed.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    SLOWEST_STEP.mul_f64(1.0 - speed) + FASTEST_STEP.mul_f64(speed)
+}
+
+/// The light states to cycle through when playing `palette`.
+///
+/// Each step fades over the whole `interval`, so the colors flow into each
+/// other, instead of jumping.
+#[must_use]
+pub fn palette_steps(palette: &ScenePalette, interval: Duration) -> Vec<DeviceUpdate> {
+    let transition = Some(interval.as_secs_f64());
+    let brightness = |dim: Option<DimmingUpdate>| dim.map(|dim| dim.brightness / 100.0 * 254.0);
+
+    let colors = palette.color.iter().map(|col| {
+        DeviceUpdate::default()
+            .with_state(Some(true))
+            .with_color_xy(Some(col.color.xy))
+            .with_brightness(brightness(col.dimming))
+    });
+
+    let temperatures = palette.color_temperature.iter().map(|ct| {
+        DeviceUpdate::default()
+            .with_state(Some(true))
+            .with_color_temp(ct.color_temperature.mirek)
+            .with_brightness(brightness(ct.dimming))
+    });
+
+    let mut steps: Vec<DeviceUpdate> = colors.chain(temperatures).collect();
+
+    // palettes with only brightness levels just vary brightness
+    if steps.is_empty() {
+        steps = palette
+            .dimming
+            .iter()
+            .map(|dim| {
+                DeviceUpdate::default()
+                    .with_state(Some(true))
+                    .with_brightness(brightness(Some(*dim)))
+            })
+            .collect();
+    }
+
+    steps
+        .into_iter()
+        .map(|step| step.with_transition(transition))
+        .collect()
+}
+
+/// Cycle the lights on `topics` through `steps`, until aborted (or the
+/// backend goes away).
+///
+/// Each light is offset by one step from the previous, so the room shows
+/// several palette colors at once.
+pub async fn play_palette(
+    tx: UnboundedSender<(String, DeviceUpdate)>,
+    topics: Vec<String>,
+    steps: Vec<DeviceUpdate>,
+    interval: Duration,
+) {
+    if steps.is_empty() {
+        return;
+    }
+
+    for round in 0.. {
+        for (index, topic) in topics.iter().enumerate() {
+            let step = &steps[(round + index) % steps.len()];
+            if tx.send((topic.clone(), step.clone())).is_err() {
+                return;
+            }
+        }
+
+        sleep(interval).await;
+    }
+}
@@ -6,7 +6,7 @@ use hue::zigbee::{HueZigbeeUpdate, ZigbeeMessage};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::{self, Message};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
-use z2m::api::{DeviceRemove, GroupMemberChange, PermitJoin};
+use z2m::api::{DeviceRemove, DeviceRename, GroupMemberChange, PermitJoin};
 use z2m::request::Z2mPayload;
 use z2m::update::DeviceUpdate;
 use z2m::{api::RawMessage, request::Z2mRequest};
@@ -55,6 +55,10 @@ impl Z2mWebSocket {
                 topic: "bridge/request/device/remove".into(),
                 payload: serde_json::to_value(dev)?,
             },
+            Z2mRequest::DeviceRename(rename) => RawMessage {
+                topic: "bridge/request/device/rename".into(),
+                payload: serde_json::to_value(rename)?,
+            },
             _ => RawMessage {
                 topic: format!("{topic}/set"),
                 payload: serde_json::to_value(payload)?,
@@ -166,6 +170,16 @@ impl Z2mWebSocket {
 
         self.send("", &z2mreq).await
     }
+
+    pub async fn send_device_rename(&mut self, from: &str, to: &str) -> ApiResult<()> {
+        let z2mreq = Z2mRequest::DeviceRename(DeviceRename {
+            from: from.to_string(),
+            to: to.to_string(),
+            homeassistant_rename: false,
+        });
+
+        self.send("", &z2mreq).await
+    }
 }
 
 impl Stream for Z2mWebSocket
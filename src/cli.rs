@@ -0,0 +1,243 @@
+use camino::Utf8Path;
+use chrono::Local;
+use url::{Url, form_urlencoded};
+
+use bifrost_api::Client;
+use bifrost_api::config::AppConfig;
+
+use crate::config;
+use crate::error::ApiResult;
+use crate::model::hass::{HassEntitiesResponse, HassLinkButtonResponse, HassTimelineResponse};
+
+/// Address of the management api of the bifrost instance using `config`
+pub fn management_url(config: &AppConfig) -> ApiResult<Url> {
+    let (address, port) = config.management.as_ref().map_or(
+        (config.bridge.ipaddress, config.bridge.http_port),
+        |mconf| (mconf.address, mconf.port),
+    );
+    Ok(Url::parse(&format!("http://{address}:{port}/bifrost/"))?)
+}
+
+/// Client for the management api, at `url` if given, or otherwise at the
+/// address found in the configuration file.
+pub fn client(config_file: &Utf8Path, url: Option<Url>) -> ApiResult<Client> {
+    let mut url = match url {
+        Some(url) => url,
+        None => management_url(&config::parse(config_file)?)?,
+    };
+
+    // make sure relative api paths are resolved below the given path
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(Client::from_url(url))
+}
+
+/// Problems in `config` that do not prevent bifrost from starting, but are
+/// likely mistakes.
+#[must_use]
+pub fn config_problems(config: &AppConfig) -> Vec<String> {
+    let mut problems = vec![];
+
+    if !config.has_backends() {
+        problems.push(
+            "No backends configured. Lights can only be controlled once Home Assistant \
+             is connected from the web ui."
+                .to_string(),
+        );
+    }
+
+    for (name, server) in &config.hass.servers {
+        let token_env = server.token_env();
+        if std::env::var(token_env).is_ok_and(|token| !token.trim().is_empty()) {
+            continue;
+        }
+        problems.push(format!(
+            "Home Assistant server {name:?}: token env var {token_env} is not set"
+        ));
+    }
+
+    if cfg!(not(feature = "mqtt")) && config.mqtt.is_some() {
+        problems.push(
+            "Mqtt is configured, but bifrost was built without the \"mqtt\" feature".to_string(),
+        );
+    }
+
+    problems
+}
+
+/// Parse the configuration file, and report anything suspicious
+pub fn check_config(config_file: &Utf8Path) -> ApiResult<()> {
+    let config = config::parse(config_file)?;
+    let bconf = &config.bridge;
+
+    println!("Configuration {config_file} is valid");
+    println!();
+    println!("  bridge:   {} [{}]", bconf.name, bconf.mac);
+    println!(
+        "  address:  {} (http {}, https {})",
+        bconf.ipaddress, bconf.http_port, bconf.https_port
+    );
+    for name in config.z2m.servers.keys() {
+        println!("  backend:  z2m@{name}");
+    }
+    for name in config.hass.servers.keys() {
+        println!("  backend:  hass@{name}");
+    }
+
+    for file in [&config.bifrost.state_file, &config.bifrost.cert_file] {
+        if !file.exists() {
+            println!("  note:     {file} does not exist yet, and will be created");
+        }
+    }
+
+    let problems = config_problems(&config);
+    if !problems.is_empty() {
+        println!();
+    }
+    for problem in problems {
+        println!("warning: {problem}");
+    }
+
+    Ok(())
+}
+
+/// Print the state database of the running instance, in the state file format
+pub async fn export_state(client: &Client) -> ApiResult<()> {
+    let state = client.state().await?;
+    print!("{}", serde_yml::to_string(&state)?);
+    Ok(())
+}
+
+/// Press the (virtual) link button, to allow apps to pair
+pub async fn pair(client: &Client) -> ApiResult<()> {
+    let res: HassLinkButtonResponse = client.post("hass/linkbutton", ()).await?;
+    println!(
+        "Link button pressed: apps can pair within the next {} seconds",
+        res.active_for_seconds
+    );
+    Ok(())
+}
+
+/// List the pairing activity of the running instance (since it was started)
+pub async fn pair_list(client: &Client) -> ApiResult<()> {
+    let res: HassTimelineResponse = client.get("hass/timeline?category=pairing").await?;
+
+    if res.entries.is_empty() {
+        println!("No pairing activity");
+    }
+
+    for entry in res.entries {
+        let at = entry.at.with_timezone(&Local);
+        println!("{}  {}", at.format("%Y-%m-%d %H:%M:%S"), entry.message);
+    }
+
+    Ok(())
+}
+
+/// List the Home Assistant entities known to the running instance
+pub async fn entity_list(
+    client: &Client,
+    domain: Option<&str>,
+    search: Option<&str>,
+    included: bool,
+) -> ApiResult<()> {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    if let Some(domain) = domain {
+        query.append_pair("domain", domain);
+    }
+    if let Some(search) = search {
+        query.append_pair("search", search);
+    }
+    if included {
+        query.append_pair("included", "true");
+    }
+
+    let res: HassEntitiesResponse = client
+        .get(&format!("hass/entities?{}", query.finish()))
+        .await?;
+
+    let width = res
+        .entities
+        .iter()
+        .map(|ent| ent.entity_id.len())
+        .max()
+        .unwrap_or_default();
+
+    for ent in &res.entities {
+        println!(
+            "{} {:width$}  {:12}  {}",
+            if ent.included { '*' } else { ' ' },
+            ent.entity_id,
+            ent.state,
+            ent.room_name,
+        );
+    }
+
+    println!("{} entities (* = exposed to hue clients)", res.total);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use bifrost_api::config::{AppConfig, ManagementConfig};
+
+    use crate::cli::{config_problems, management_url};
+
+    fn config() -> AppConfig {
+        serde_json::from_value(serde_json::json!({
+            "bridge": {
+                "name": "Bifrost",
+                "mac": "00:11:22:33:44:55",
+                "ipaddress": "10.0.0.12",
+                "http_port": 8080,
+                "https_port": 443,
+                "entm_port": 2100,
+                "netmask": "255.255.255.0",
+                "gateway": "10.0.0.1",
+                "timezone": "Europe/Copenhagen",
+            },
+            "bifrost": {
+                "state_file": "state.yaml",
+                "cert_file": "cert.pem",
+                "hass_ui_file": "hass-ui.yaml",
+                "hass_runtime_file": "hass-runtime.yaml",
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn url_from_config() {
+        assert_eq!(
+            management_url(&config()).unwrap().as_str(),
+            "http://10.0.0.12:8080/bifrost/"
+        );
+    }
+
+    #[test]
+    fn url_from_management_config() {
+        let mut config = config();
+        config.management = Some(ManagementConfig {
+            address: Ipv4Addr::LOCALHOST,
+            port: 8081,
+        });
+
+        assert_eq!(
+            management_url(&config).unwrap().as_str(),
+            "http://127.0.0.1:8081/bifrost/"
+        );
+    }
+
+    #[test]
+    fn warns_without_backends() {
+        let problems = config_problems(&config());
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("No backends configured"));
+    }
+}
@@ -1,9 +1,164 @@
-use camino::Utf8Path;
-use config::{Config, ConfigError};
+use camino::{Utf8Path, Utf8PathBuf};
+use config::{Config, ConfigError, FileFormat, FileSourceString};
+use serde_yml::{Mapping, Value};
 
 pub use bifrost_api::config::*;
 
+/// Maximum nesting of `!include` directives (to catch include loops)
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Extensions of config files, in lookup order. A config file name without
+/// extension is looked up with each of these appended (like
+/// [`config::File::with_name`]).
+const CONFIG_EXTENSIONS: &[&str] = &["yaml", "yml", "toml", "json", "ini"];
+
+/// Environment variable lookup, used for `${VAR}` interpolation
+type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Expand `${VAR}` and `${VAR:-default}` references in `text`.
+///
+/// A literal `${` can be written as `$${`. References to variables that are
+/// not set (without default), and unterminated references, are kept as they
+/// are, so existing values containing `${` keep working.
+fn interpolate(text: &str, env: EnvLookup) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find("${") {
+        // escaped: "$${" becomes "${"
+        if rest[..pos].ends_with('$') {
+            res.push_str(&rest[..pos]);
+            res.push('{');
+            rest = &rest[pos + 2..];
+            continue;
+        }
+
+        res.push_str(&rest[..pos]);
+        let Some(end) = rest[pos..].find('}') else {
+            break;
+        };
+
+        let reference = &rest[pos + 2..pos + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        if let Some(value) = env(name).or_else(|| default.map(ToString::to_string)) {
+            res.push_str(&value);
+        } else {
+            log::warn!(
+                "Environment variable {name} is not set, keeping ${{{reference}}} in config"
+            );
+            res.push_str(&rest[pos..=pos + end]);
+        }
+
+        rest = &rest[pos + end + 1..];
+    }
+
+    res.push_str(rest);
+    res
+}
+
+/// Expand variables in all strings in `value`, and replace `!include`
+/// directives with the content of the included file (relative to `base`).
+fn resolve(
+    value: Value,
+    base: &Utf8Path,
+    depth: usize,
+    env: EnvLookup,
+) -> Result<Value, ConfigError> {
+    match value {
+        Value::String(text) => Ok(Value::String(interpolate(&text, env))),
+        Value::Sequence(seq) => seq
+            .into_iter()
+            .map(|value| resolve(value, base, depth, env))
+            .collect::<Result<_, _>>()
+            .map(Value::Sequence),
+        Value::Mapping(map) => {
+            let mut res = Mapping::new();
+            for (key, value) in map {
+                res.insert(key, resolve(value, base, depth, env)?);
+            }
+            Ok(Value::Mapping(res))
+        }
+        Value::Tagged(tagged) if tagged.tag == "!include" => {
+            let Value::String(filename) = tagged.value else {
+                return Err(ConfigError::Message(
+                    "!include must be followed by a file name".to_string(),
+                ));
+            };
+            let filename = interpolate(&filename, env);
+            load(&base.join(filename), depth + 1, env)
+        }
+        Value::Tagged(mut tagged) => {
+            tagged.value = resolve(tagged.value, base, depth, env)?;
+            Ok(Value::Tagged(tagged))
+        }
+        value => Ok(value),
+    }
+}
+
+/// Load yaml file `filename`, with variables and includes resolved
+fn load(filename: &Utf8Path, depth: usize, env: EnvLookup) -> Result<Value, ConfigError> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(ConfigError::Message(format!(
+            "Includes nested too deeply at {filename} (include loop?)"
+        )));
+    }
+
+    let text = std::fs::read_to_string(filename)
+        .map_err(|err| ConfigError::Message(format!("Cannot read {filename}: {err}")))?;
+
+    let value = serde_yml::from_str(&text)
+        .map_err(|err| ConfigError::Message(format!("Cannot parse {filename}: {err}")))?;
+
+    let base = filename.parent().unwrap_or_else(|| Utf8Path::new(""));
+    resolve(value, base, depth, env)
+}
+
+/// Find the config file `filename`, which may be given without extension
+/// (so "config" finds "config.yaml", "config.yml", "config.toml", ..)
+fn resolve_filename(filename: &Utf8Path) -> Result<Utf8PathBuf, ConfigError> {
+    if filename.is_file() {
+        return Ok(filename.to_path_buf());
+    }
+
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| Utf8PathBuf::from(format!("{filename}.{ext}")))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| ConfigError::Message(format!("Config file {filename} not found")))
+}
+
+/// Config source for `filename`. Yaml files get variables and includes
+/// resolved, other formats are read as they are.
+fn source(filename: &Utf8Path) -> Result<config::File<FileSourceString, FileFormat>, ConfigError> {
+    let format = match filename.extension() {
+        Some("yaml" | "yml") => {
+            let yaml = load(filename, 0, &|name| std::env::var(name).ok())?;
+            let text =
+                serde_yml::to_string(&yaml).map_err(|err| ConfigError::Foreign(Box::new(err)))?;
+            return Ok(config::File::from_str(&text, FileFormat::Yaml));
+        }
+        Some("toml") => FileFormat::Toml,
+        Some("json") => FileFormat::Json,
+        Some("ini") => FileFormat::Ini,
+        _ => {
+            return Err(ConfigError::Message(format!(
+                "Config file {filename} has an unsupported format (use .yaml, .toml, .json or .ini)"
+            )));
+        }
+    };
+
+    let text = std::fs::read_to_string(filename)
+        .map_err(|err| ConfigError::Message(format!("Cannot read {filename}: {err}")))?;
+    Ok(config::File::from_str(&text, format))
+}
+
 pub fn parse(filename: &Utf8Path) -> Result<AppConfig, ConfigError> {
+    let filename = resolve_filename(filename)?;
+
     let settings = Config::builder()
         .set_default("bifrost.state_file", "state.yaml")?
         .set_default("bifrost.cert_file", "cert.pem")?
@@ -12,8 +167,122 @@ pub fn parse(filename: &Utf8Path) -> Result<AppConfig, ConfigError> {
         .set_default("bridge.http_port", 80)?
         .set_default("bridge.https_port", 443)?
         .set_default("bridge.entm_port", 2100)?
-        .add_source(config::File::with_name(filename.as_str()))
+        .add_source(source(&filename)?)
         .build()?;
 
-    settings.try_deserialize()
+    let config: AppConfig = settings.try_deserialize()?;
+    validate(&config)?;
+    Ok(config)
+}
+
+/// Check the settings that cannot be checked by their types
+fn validate(config: &AppConfig) -> Result<(), ConfigError> {
+    for (name, server) in &config.hass.servers {
+        if server.reconcile_hour.is_some_and(|hour| hour > 23) {
+            return Err(ConfigError::Message(format!(
+                "hass.servers.{name}.reconcile_hour must be between 0 and 23"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use serde_yml::Value;
+
+    use crate::config::{interpolate, load, resolve_filename};
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HASS_HOST" => Some("hass.lan".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolate_plain() {
+        assert_eq!(interpolate("no variables", &env), "no variables");
+        assert_eq!(interpolate("$ and {}", &env), "$ and {}");
+    }
+
+    #[test]
+    fn interpolate_variables() {
+        assert_eq!(
+            interpolate("http://${HASS_HOST}:8123/", &env),
+            "http://hass.lan:8123/"
+        );
+        assert_eq!(interpolate("[${EMPTY}]", &env), "[]");
+    }
+
+    #[test]
+    fn interpolate_default() {
+        assert_eq!(interpolate("${MISSING:-8123}", &env), "8123");
+        assert_eq!(interpolate("${HASS_HOST:-x}", &env), "hass.lan");
+    }
+
+    #[test]
+    fn interpolate_escaped() {
+        assert_eq!(interpolate("$${HASS_HOST}", &env), "${HASS_HOST}");
+    }
+
+    #[test]
+    fn interpolate_keeps_unknown() {
+        // e.g. passwords that happen to contain "${"
+        assert_eq!(interpolate("pa${MISSING}ss", &env), "pa${MISSING}ss");
+        assert_eq!(interpolate("${HASS_HOST", &env), "${HASS_HOST");
+        assert_eq!(interpolate("${X} ${HASS_HOST}", &env), "${X} hass.lan");
+    }
+
+    #[test]
+    fn include_files() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(dir.join("main.yaml"), "hass: !include hass.yaml\n").unwrap();
+        std::fs::write(dir.join("hass.yaml"), "url: http://${HASS_HOST}/\n").unwrap();
+        std::fs::write(dir.join("loop.yaml"), "again: !include loop.yaml\n").unwrap();
+
+        let value = load(&dir.join("main.yaml"), 0, &env);
+        let looped = load(&dir.join("loop.yaml"), 0, &env);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            value.unwrap()["hass"]["url"],
+            Value::String("http://hass.lan/".to_string())
+        );
+        assert!(looped.is_err());
+    }
+
+    #[test]
+    fn resolve_filenames() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(dir.join("config.yaml"), "bridge: {}\n").unwrap();
+        std::fs::write(dir.join("other.yml"), "bridge: {}\n").unwrap();
+        std::fs::write(dir.join("config.toml"), "[bridge]\n").unwrap();
+        std::fs::write(dir.join("settings.toml"), "[bridge]\n").unwrap();
+
+        let exact = resolve_filename(&dir.join("config.yaml"));
+        let bare = resolve_filename(&dir.join("config"));
+        let bare_yml = resolve_filename(&dir.join("other"));
+        let toml = resolve_filename(&dir.join("config.toml"));
+        let bare_toml = resolve_filename(&dir.join("settings"));
+        let missing = resolve_filename(&dir.join("missing"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(exact.unwrap(), dir.join("config.yaml"));
+        assert_eq!(bare.unwrap(), dir.join("config.yaml"));
+        assert_eq!(bare_yml.unwrap(), dir.join("other.yml"));
+        assert_eq!(toml.unwrap(), dir.join("config.toml"));
+        assert_eq!(bare_toml.unwrap(), dir.join("settings.toml"));
+        assert!(missing.is_err());
+    }
 }
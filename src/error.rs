@@ -2,13 +2,14 @@ use std::num::{ParseIntError, TryFromIntError};
 use std::sync::Arc;
 
 use camino::Utf8PathBuf;
-use hue::api::RType;
+use hue::api::{RType, ResourceLink};
 use thiserror::Error;
 use tokio::task::JoinError;
 
 use bifrost_api::backend::BackendRequest;
 use hue::event::EventBlock;
 use svc::error::SvcError;
+use svc::serviceid::ServiceName;
 
 #[derive(Error, Debug)]
 pub enum ApiError {
@@ -97,6 +98,9 @@ pub enum ApiError {
     #[error(transparent)]
     HueError(#[from] hue::error::HueError),
 
+    #[error(transparent)]
+    BifrostError(#[from] bifrost_api::error::BifrostError),
+
     #[error(transparent)]
     OpenSslError(#[from] openssl::error::Error),
 
@@ -142,6 +146,54 @@ pub enum ApiError {
     #[error("Deleting object of type {0:?} is not allowed by hue protocol")]
     DeleteNotAllowed(RType),
 
+    /* bifrost errors: backend routing */
+    #[error("No backend owns this {} ({})", rtype_name(.0.rtype), .0.rid)]
+    NoBackendOwner(ResourceLink),
+
+    #[error("Resource {0:?} is owned by backend {1}, and cannot be claimed by {2}")]
+    OwnershipConflict(ResourceLink, String, String),
+
+    #[error("Backend {0} is not running")]
+    BackendNotRunning(String),
+
+    #[error("No backends are running")]
+    NoBackends,
+
+    #[error("Backend {0} does not support {1}")]
+    BackendUnsupported(String, &'static str),
+
+    #[error("Service {0} cannot be reconfigured at runtime")]
+    ServiceNotConfigurable(ServiceName),
+
+    #[error("Light {0} has no segment {1} (it has {2} segments)")]
+    SegmentNotFound(uuid::Uuid, u16, u32),
+
+    #[error("Zones can only hold lights and devices, not {0:?}")]
+    ZoneChildInvalid(ResourceLink),
+
+    #[error("Button update contains no press event")]
+    ButtonNoPress,
+
+    #[error("Scene {0} does not belong to room {1}")]
+    RuleSceneWrongRoom(uuid::Uuid, uuid::Uuid),
+
+    /* bifrost errors: home assistant */
+    #[error("Home Assistant entity {0} is not imported as a light")]
+    HassEntityNotFound(String),
+
+    #[error("Bridge reset not confirmed: missing, expired or invalid confirmation token")]
+    ResetNotConfirmed,
+
+    /* bifrost errors: self update */
+    #[error("Release asset {0} has no published checksum ({0}.sha256)")]
+    ReleaseChecksumMissing(String),
+
+    #[error("Release asset {0} does not match its published checksum")]
+    ReleaseChecksumMismatch(String),
+
+    #[error("Release tag {0:?} cannot be used as a staging directory name")]
+    ReleaseTagInvalid(String),
+
     /* bifrost errors */
     #[error("Missing auxiliary data resource {0:?}")]
     AuxNotFound(uuid::Uuid),
@@ -174,6 +226,14 @@ pub enum ApiError {
     ZigbeeMessageError,
 }
 
+/// Human readable name of a resource type (e.g. "grouped light")
+fn rtype_name(rtype: RType) -> String {
+    serde_json::to_value(rtype)
+        .ok()
+        .and_then(|val| val.as_str().map(|name| name.replace('_', " ")))
+        .unwrap_or_else(|| format!("{rtype:?}"))
+}
+
 impl From<SvcError> for ApiError {
     fn from(value: SvcError) -> Self {
         Self::SvcError(value.to_string())
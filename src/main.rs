@@ -1,8 +1,14 @@
 use std::io::Write;
+use std::time::Duration;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Parser, Subcommand};
 
 use bifrost::backend;
+use bifrost::cli;
 use bifrost::config;
 use bifrost::error::ApiResult;
+use bifrost::routes::RouteSet;
 use bifrost::server::appstate::AppState;
 use bifrost::server::http::HttpServer;
 use bifrost::server::mdns::MdnsService;
@@ -16,6 +22,66 @@ use url::Url;
 
 use bifrost_api::config::HassServer;
 
+/// Longest time the servers wait for the backends to start
+const BACKEND_STARTUP_WAIT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Configuration file
+    #[arg(short, long, default_value = "config.yaml")]
+    config: Utf8PathBuf,
+
+    /// Management api of a running bifrost, for subcommands (default: the
+    /// http address from the configuration file)
+    #[arg(short, long)]
+    url: Option<Url>,
+
+    /// Run bifrost if no command is given
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check the configuration file for errors and likely mistakes
+    CheckConfig,
+
+    /// Print the state database of a running bifrost
+    ExportState,
+
+    /// Press the link button of a running bifrost, so apps can pair
+    Pair {
+        /// List pairing activity instead
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Home Assistant entities of a running bifrost
+    Entity {
+        #[command(subcommand)]
+        command: EntityCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum EntityCommand {
+    /// List entities
+    List {
+        /// Only entities in this domain (e.g. "light")
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Only entities matching these words
+        #[arg(long)]
+        search: Option<String>,
+
+        /// Only entities exposed to hue clients
+        #[arg(long)]
+        included: bool,
+    },
+}
+
 /*
  * Formatter function to output in syslog format. This makes sense when running
  * as a service (where output might go to a log file, or the system journal)
@@ -68,55 +134,226 @@ fn init_logging() -> ApiResult<()> {
 
 #[allow(clippy::similar_names)]
 async fn build_tasks(appstate: &AppState) -> ApiResult<()> {
-    let bconf = &appstate.config().bridge;
+    let config = appstate.config();
+    let bconf = &config.bridge;
 
     let mut mgr = appstate.manager();
 
-    mgr.register_service("mdns", MdnsService::new(bconf.mac, bconf.ipaddress))
-        .await?;
+    // record service lifecycle in the activity timeline, starting it first
+    // to catch the startup of all other services
+    let state = appstate.clone();
+    let svc = move || server::timeline::service_timeline(state.manager(), state.hass_ui());
+    mgr.register_function_with_policy(
+        "service-timeline",
+        svc,
+        config.service_policy("service-timeline"),
+    )
+    .await?;
+    mgr.start("service-timeline").await?;
+
+    mgr.register_service_with_policy(
+        "mdns",
+        MdnsService::new(bconf.mac, bconf.ipaddress),
+        config.service_policy("mdns"),
+    )
+    .await?;
 
     log::info!("Serving mac [{}]", bconf.mac);
 
+    // with a separate management server, the bridge ports only serve hue
+    // bridge emulation
+    let routes = if config.management.is_some() {
+        RouteSet::Hue
+    } else {
+        RouteSet::All
+    };
+
     // register plain http service
     let http_service = HttpServer::http(
         bconf.ipaddress,
         bconf.http_port,
-        server::build_service(Protocol::Http, appstate.clone()),
+        server::build_service(Protocol::Http, appstate.clone(), routes),
     );
-    mgr.register_service("http", http_service).await?;
+    mgr.register_service_with_policy("http", http_service, config.service_policy("http"))
+        .await?;
 
     let https_service = HttpServer::https_openssl(
         bconf.ipaddress,
         bconf.https_port,
-        server::build_service(Protocol::Https, appstate.clone()),
+        server::build_service(Protocol::Https, appstate.clone(), routes),
         &appstate.config().bifrost.cert_file,
     )?;
 
     // .. if either tls backend is enabled, register https service
-    mgr.register_service("https", https_service).await?;
+    mgr.register_service_with_policy("https", https_service, config.service_policy("https"))
+        .await?;
+
+    // register management api server, if configured
+    if let Some(mconf) = &config.management {
+        log::info!(
+            "Serving management api on http://{}:{}/bifrost/",
+            mconf.address,
+            mconf.port
+        );
+        let management_service = HttpServer::http(
+            mconf.address,
+            mconf.port,
+            server::build_service(Protocol::Http, appstate.clone(), RouteSet::Management),
+        );
+        mgr.register_service_with_policy(
+            "http-management",
+            management_service,
+            config.service_policy("http-management"),
+        )
+        .await?;
+    }
 
     // register config writer
-    let svc = server::config_writer(
-        appstate.res.clone(),
-        appstate.config().bifrost.state_file.clone(),
-    );
-    mgr.register_function("config-writer", svc).await?;
+    let state = appstate.clone();
+    let svc =
+        move || server::config_writer(state.res.clone(), state.config().bifrost.state_file.clone());
+    mgr.register_function_with_policy("config-writer", svc, config.service_policy("config-writer"))
+        .await?;
 
-    // register version updater
-    let svc = server::version_updater(appstate.res.clone(), appstate.updater());
-    mgr.register_function("version-updater", svc).await?;
+    // register writers for the Home Assistant state files
+    let state = appstate.clone();
+    let svc = move || server::state_file_writer(state.hass_ui());
+    mgr.register_function_with_policy(
+        "hass-ui-writer",
+        svc,
+        config.service_policy("hass-ui-writer"),
+    )
+    .await?;
+
+    let state = appstate.clone();
+    let svc = move || server::state_file_writer(state.hass_runtime());
+    mgr.register_function_with_policy(
+        "hass-runtime-writer",
+        svc,
+        config.service_policy("hass-runtime-writer"),
+    )
+    .await?;
+
+    // register version updater, unless the emulated version is fixed
+    if appstate.updater().lock().await.is_dynamic() {
+        let state = appstate.clone();
+        let bridge_id = hue::bridge_id(bconf.mac);
+        let svc = move || {
+            server::updater::version_updater(state.res.clone(), state.updater(), bridge_id.clone())
+        };
+        mgr.register_function_with_policy(
+            "version-updater",
+            svc,
+            config.service_policy("version-updater"),
+        )
+        .await?;
+    } else {
+        log::info!("Firmware version updater disabled by config");
+    }
+
+    // register bifrost release checker, if configured
+    if appstate.release_checker().lock().await.is_enabled() {
+        let state = appstate.clone();
+        let svc = move || server::release::release_checker(state.release_checker());
+        mgr.register_function_with_policy(
+            "release-checker",
+            svc,
+            config.service_policy("release-checker"),
+        )
+        .await?;
+    }
+
+    // register gpio link button, if configured
+    if let Some(gpio) = appstate.config().linkbutton.gpio.clone() {
+        let state = appstate.clone();
+        let svc = move || {
+            server::linkbutton::linkbutton_gpio(
+                state.linkbutton(),
+                state.res.clone(),
+                state.hass_ui(),
+                gpio.clone(),
+            )
+        };
+        mgr.register_function_with_policy(
+            "linkbutton-gpio",
+            svc,
+            config.service_policy("linkbutton-gpio"),
+        )
+        .await?;
+    }
+
+    // register mqtt discovery publisher, if configured
+    #[cfg(feature = "mqtt")]
+    if let Some(mqtt) = config.mqtt.clone() {
+        let state = appstate.clone();
+        let svc = move || {
+            server::mqtt::mqtt_publisher(
+                mqtt.clone(),
+                state.res.clone(),
+                state.linkbutton(),
+                state.hass_ui(),
+            )
+        };
+        mgr.register_function_with_policy("mqtt", svc, config.service_policy("mqtt"))
+            .await?;
+    }
+
+    #[cfg(not(feature = "mqtt"))]
+    if config.mqtt.is_some() {
+        log::warn!("Mqtt is configured, but bifrost was built without the \"mqtt\" feature");
+    }
+
+    // register backend latency measurement
+    let state = appstate.clone();
+    let svc = move || {
+        server::latency::backend_latency(state.latency(), state.res.clone(), state.hass_ui())
+    };
+    mgr.register_function_with_policy(
+        "backend-latency",
+        svc,
+        config.service_policy("backend-latency"),
+    )
+    .await?;
+
+    // register cleanup of links to deleted resources
+    let state = appstate.clone();
+    let svc = move || server::cleanup::link_cleanup(state.res.clone());
+    mgr.register_function_with_policy("link-cleanup", svc, config.service_policy("link-cleanup"))
+        .await?;
+
+    // register rule engine (motion and contact rules)
+    let state = appstate.clone();
+    let svc = move || server::rules::rule_engine(state.res.clone());
+    mgr.register_function_with_policy("rules", svc, config.service_policy("rules"))
+        .await?;
+
+    // register presence simulation (even without rooms, so it can be
+    // enabled, and rooms configured, at runtime)
+    let state = appstate.clone();
+    let svc = move || {
+        server::presence::presence_simulation(
+            state.presence(),
+            state.res.clone(),
+            state.config().presence.clone(),
+        )
+    };
+    mgr.register_function_with_policy("presence", svc, config.service_policy("presence"))
+        .await?;
 
     // register ssdp listener
-    let svc = server::ssdp::SsdpService::new(bconf.mac, bconf.ipaddress, appstate.updater());
-    mgr.register_service("ssdp", svc).await?;
+    let svc = server::ssdp::SsdpService::new(bconf.mac, bconf.ipaddress, appstate.res.clone());
+    mgr.register_service_with_policy("ssdp", svc, config.service_policy("ssdp"))
+        .await?;
 
     // register entertainment streaming listener
     let svc = server::entertainment::EntertainmentService::new(
         bconf.ipaddress,
         bconf.entm_port,
         appstate.res.clone(),
+        appstate.entertainment(),
     )?;
-    mgr.register_service("entertainment", svc).await?;
+    mgr.register_service_with_policy("entertainment", svc, config.service_policy("entertainment"))
+        .await?;
 
     // register all z2m backends as services
     let template = backend::z2m::Z2mServiceTemplate::new(appstate.clone());
@@ -126,14 +363,18 @@ async fn build_tasks(appstate: &AppState) -> ApiResult<()> {
     let template = backend::hass::HassServiceTemplate::new(appstate.clone());
     mgr.register_template("hass", template).await?;
 
+    let mut backends = vec![];
+
     // start named z2m instances, since templated services appear when started
     for name in appstate.config().z2m.servers.keys() {
         mgr.start(ServiceId::instance("z2m", name)).await?;
+        backends.push(format!("z2m@{name}"));
     }
 
     // start named hass instances, since templated services appear when started
     for name in appstate.config().hass.servers.keys() {
         mgr.start(ServiceId::instance("hass", name)).await?;
+        backends.push(format!("hass@{name}"));
     }
 
     if appstate.config().hass.servers.is_empty() {
@@ -142,21 +383,62 @@ async fn build_tasks(appstate: &AppState) -> ApiResult<()> {
             .expect("fallback Home Assistant URL should always be valid");
         let server = HassServer {
             url: fallback_url,
-            token_env: Some("HASS_TOKEN".to_string()),
+            token_env: Some(HassServer::DEFAULT_TOKEN_ENV.to_string()),
             poll_interval_secs: None,
+            pairing_notification: None,
+            retry_queue_size: None,
+            retry_ttl_secs: None,
+            export_room_groups: None,
+            forward_events: None,
+            sync_timeout_secs: None,
+            sync_chunk_size: None,
+            heartbeat_secs: None,
+            heartbeat_entity: None,
+            nightly_reconcile: None,
+            reconcile_hour: None,
+            entertainment_fallback: None,
+            entertainment_fps: None,
         };
         let svc = backend::hass::HassBackend::new(
-            "runtime".to_string(),
+            backend::hass::HassBackend::RUNTIME_NAME.to_string(),
             server,
+            appstate.config().bifrost.proxy.clone(),
             appstate.res.clone(),
             appstate.hass_ui(),
             appstate.hass_runtime(),
-        )?;
-        mgr.register_service("hass-runtime", svc).await?;
+        )?
+        .with_linkbutton(appstate.linkbutton(), appstate.config().linkbutton.clone())
+        .with_presence(
+            appstate.presence(),
+            appstate.config().presence.hass_entity.clone(),
+        );
+        mgr.register_service_with_policy(
+            "hass-runtime",
+            svc,
+            config.service_policy("hass@runtime"),
+        )
+        .await?;
         mgr.start("hass-runtime").await?;
+        backends.push("hass-runtime".to_string());
+    }
+
+    // serve clients once the backends have started, so they rarely see a
+    // bridge without devices. A backend that cannot connect only delays the
+    // servers for a while, instead of blocking them. (resources are loaded
+    // from the state file before any service is registered, so config-writer
+    // needs no dependency)
+    for svc in ["http", "https", "entertainment"] {
+        for backend in &backends {
+            mgr.depend_for(svc, backend.as_str(), BACKEND_STARTUP_WAIT)
+                .await?;
+        }
     }
 
-    // finally, iterate over all services and start them
+    // only advertise the bridge once it can be reached
+    mgr.depend("mdns", "http").await?;
+    mgr.depend("ssdp", "http").await?;
+
+    // finally, iterate over all services and start them, in dependency order
     for (id, _name) in mgr.list().await? {
         mgr.start(id).await?;
     }
@@ -189,13 +471,13 @@ fn install_signal_handlers(appstate: &AppState) -> ApiResult<()> {
     Ok(())
 }
 
-async fn run() -> ApiResult<()> {
+async fn run(config_file: &Utf8Path) -> ApiResult<()> {
     init_logging()?;
 
     #[cfg(feature = "server-banner")]
     server::banner::print()?;
 
-    let config = config::parse("config.yaml".into())?;
+    let config = config::parse(config_file)?;
     log::debug!("Configuration loaded successfully");
 
     if !config.has_backends() {
@@ -217,12 +499,50 @@ async fn run() -> ApiResult<()> {
 
     future.await??;
 
+    // the state file writers batch up saves, so save changes made shortly
+    // before shutdown
+    server::save_state_file(&appstate.hass_ui()).await;
+    server::save_state_file(&appstate.hass_runtime()).await;
+
     Ok(())
 }
 
+/// Run a companion command against the configuration, or a running bifrost
+async fn run_command(args: &Args, command: Command) -> ApiResult<()> {
+    let client = || cli::client(&args.config, args.url.clone());
+
+    match command {
+        Command::CheckConfig => cli::check_config(&args.config),
+        Command::ExportState => cli::export_state(&client()?).await,
+        Command::Pair { list: false } => cli::pair(&client()?).await,
+        Command::Pair { list: true } => cli::pair_list(&client()?).await,
+        Command::Entity {
+            command:
+                EntityCommand::List {
+                    domain,
+                    search,
+                    included,
+                },
+        } => {
+            let client = client()?;
+            cli::entity_list(&client, domain.as_deref(), search.as_deref(), included).await
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    if let Err(err) = run().await {
+    let mut args = Args::parse();
+
+    if let Some(command) = args.command.take() {
+        if let Err(err) = run_command(&args, command).await {
+            eprintln!("bifrost: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(err) = run(&args.config).await {
         log::error!("Bifrost error: {err}");
         log::error!("Fatal error encountered, cannot continue.");
     }
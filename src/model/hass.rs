@@ -1,15 +1,161 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::fs::File;
+use std::sync::Arc;
 
-use camino::Utf8PathBuf;
-use chrono::{DateTime, Local, Utc};
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, mpsc};
 use url::Url;
+use uuid::Uuid;
+
+use hue::api::ResourceLink;
+use hue::xy::XY;
 
 use crate::error::{ApiError, ApiResult};
+use crate::model::search::EntitySearchIndex;
+use crate::model::timeline::{
+    Timeline, TimelineCategory, TimelineEntry, TimelineLevel, TimelineQuery,
+};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HassEntityKind {
+    Light,
+    Switch,
+    BinarySensor,
+    /// Blinds, curtains, etc. Imported as lights, that are on while open, with
+    /// the position as brightness.
+    Cover,
+    /// Imported as lights (with the speed as brightness), or as plugs
+    Fan,
+    /// Imported as plugs, which are turned off with the rest of the room
+    MediaPlayer,
+    /// Scripts and buttons, imported as Hue buttons. Pressing them runs the
+    /// script (or presses the button).
+    Button,
+    /// Numeric sensors with the temperature device class, imported as Hue
+    /// temperature sensors
+    Temperature,
+    /// Numeric sensors with the illuminance device class, imported as Hue
+    /// light level sensors
+    LightLevel,
+    /// Event entities (e.g. the buttons of remotes), imported as Hue buttons
+    /// that report each event
+    Event,
+    /// Imported as contact sensors, that are open while not locked
+    Lock,
+    /// Other numeric sensors, imported as binary sensors that are on while
+    /// past their threshold
+    Sensor,
+    /// Imported as Hue buttons, like `Button`. Pressing them runs the script.
+    Script,
+}
+
+impl HassEntityKind {
+    /// Is this kind imported as a Hue sensor or button (rather than a light
+    /// or plug)?
+    #[must_use]
+    pub const fn is_sensor(self) -> bool {
+        match self {
+            Self::BinarySensor
+            | Self::Button
+            | Self::Temperature
+            | Self::LightLevel
+            | Self::Event
+            | Self::Lock
+            | Self::Sensor
+            | Self::Script => true,
+            Self::Light | Self::Switch | Self::Cover | Self::Fan | Self::MediaPlayer => false,
+        }
+    }
+
+    /// Is this kind imported as a motion or contact sensor, as configured by
+    /// its sensor kind?
+    #[must_use]
+    pub const fn is_binary_sensor(self) -> bool {
+        matches!(self, Self::BinarySensor | Self::Lock | Self::Sensor)
+    }
+
+    /// The Home Assistant domain of entities of this kind
+    #[must_use]
+    pub const fn domain(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Switch => "switch",
+            Self::BinarySensor => "binary_sensor",
+            Self::Cover => "cover",
+            Self::Fan => "fan",
+            Self::MediaPlayer => "media_player",
+            Self::Button => "button",
+            Self::Temperature | Self::LightLevel | Self::Sensor => "sensor",
+            Self::Event => "event",
+            Self::Lock => "lock",
+            Self::Script => "script",
+        }
+    }
+
+    /// Is an entity of this kind controlled as a light, by its room and by
+    /// scenes? Switches and fans are, when shown as lights.
+    #[must_use]
+    pub fn groups_as_light(self, switch_mode: Option<HassSwitchMode>) -> bool {
+        match self {
+            Self::Light | Self::Cover => true,
+            Self::Switch | Self::Fan => switch_mode == Some(HassSwitchMode::Light),
+            Self::MediaPlayer
+            | Self::BinarySensor
+            | Self::Button
+            | Self::Temperature
+            | Self::LightLevel
+            | Self::Event
+            | Self::Lock
+            | Self::Sensor
+            | Self::Script => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HassServiceKind {
+    Light,
+    Switch,
+    Motion,
+    Contact,
+    Button,
+    Temperature,
+    LightLevel,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct HassLightCapabilities {
+    pub supports_brightness: bool,
+    pub supports_color: bool,
+    pub supports_color_temp: bool,
+}
+
+/// Binding of a Home Assistant entity to its Hue resources.
+///
+/// Saved as aux data of the Hue device, so the hass backend can route
+/// commands right after a restart, before Home Assistant is reachable.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassEntityAux {
+    /// Name of the hass backend that owns the entity
+    pub backend: String,
+    pub entity_id: String,
+    /// Unique id from the Home Assistant entity registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unique_id: Option<String>,
+    pub name: String,
+    pub kind: HassEntityKind,
+    pub service_kind: HassServiceKind,
+    pub service_link: ResourceLink,
+    pub capabilities: HassLightCapabilities,
+    pub switch_mode: Option<HassSwitchMode>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum HassSensorKind {
     Motion,
@@ -17,14 +163,29 @@ pub enum HassSensorKind {
     Ignore,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum HassSwitchMode {
     Plug,
     Light,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+/// How to handle Home Assistant state updates for a light, while a command
+/// from a Hue client has not yet been confirmed by Home Assistant
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HassConflictPolicy {
+    /// Always use the state reported by Home Assistant
+    #[default]
+    BackendWins,
+    /// Use the state reported by Home Assistant, if it changed after the command
+    /// (both by the clock of Home Assistant)
+    LastWriteWins,
+    /// Keep the commanded state until Home Assistant confirms it
+    Hold,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum HassLightArchetype {
     #[default]
@@ -126,6 +287,61 @@ impl Default for HassFakeCloudState {
     }
 }
 
+/// How the brightness of a room (grouped light) is derived from its lights
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HassBrightnessStrategy {
+    /// Average of all lights
+    #[default]
+    Average,
+    /// Average of the lights that are on
+    AverageOn,
+    /// Brightest light that is on
+    Max,
+    /// Light that changed most recently
+    LastChanged,
+}
+
+/// Brightness (0-100) of a single light in a room
+#[derive(Clone, Copy, Debug)]
+pub struct HassLightBrightness {
+    pub on: bool,
+    pub brightness: f64,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+impl HassBrightnessStrategy {
+    /// Brightness of the room, or `None` if no light reports a brightness.
+    ///
+    /// Except for `Average`, only the lights that are on are considered,
+    /// unless all lights are off.
+    #[must_use]
+    pub fn aggregate(self, lights: &[HassLightBrightness]) -> Option<f64> {
+        let on: Vec<_> = lights.iter().filter(|light| light.on).copied().collect();
+        let pool: &[HassLightBrightness] = if self == Self::Average || on.is_empty() {
+            lights
+        } else {
+            &on
+        };
+        if pool.is_empty() {
+            return None;
+        }
+
+        match self {
+            Self::Average | Self::AverageOn => {
+                let sum: f64 = pool.iter().map(|light| light.brightness).sum();
+                let count = u32::try_from(pool.len()).map_or(1.0, f64::from);
+                Some(sum / count)
+            }
+            Self::Max => pool.iter().map(|light| light.brightness).reduce(f64::max),
+            Self::LastChanged => pool
+                .iter()
+                .max_by_key(|light| light.last_updated)
+                .map(|light| light.brightness),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct HassRoomConfig {
     pub id: String,
@@ -134,6 +350,12 @@ pub struct HassRoomConfig {
     pub source_area: Option<String>,
     #[serde(default)]
     pub auto_created: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness_strategy: Option<HassBrightnessStrategy>,
+    /// Hue room (owned by another backend) to place the devices of this room
+    /// in, instead of creating a room of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_into: Option<Uuid>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
@@ -152,9 +374,31 @@ pub struct HassEntityPreference {
     pub switch_mode: Option<HassSwitchMode>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub light_archetype: Option<HassLightArchetype>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_policy: Option<HassConflictPolicy>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+/// Exposes a numeric Home Assistant sensor (e.g. illuminance) as a binary
+/// Hue sensor, which is active while the value is below `below` or above
+/// `above`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct HassSensorThreshold {
+    pub sensor_kind: HassSensorKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub below: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub above: Option<f64>,
+}
+
+impl HassSensorThreshold {
+    #[must_use]
+    pub fn is_active(&self, value: f64) -> bool {
+        self.below.is_some_and(|below| value < below)
+            || self.above.is_some_and(|above| value > above)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct HassUiConfig {
     #[serde(default)]
     pub hidden_entity_ids: Vec<String>,
@@ -170,6 +414,9 @@ pub struct HassUiConfig {
     pub entity_preferences: HashMap<String, HassEntityPreference>,
     #[serde(default)]
     pub ignored_area_names: Vec<String>,
+    /// Entities in these Home Assistant areas are added to Hue
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub included_area_names: Vec<String>,
     #[serde(default = "HassUiConfig::default_add_new")]
     pub default_add_new_devices_to_hue: bool,
     #[serde(default = "HassUiConfig::default_sync_areas")]
@@ -184,6 +431,42 @@ pub struct HassUiConfig {
     pub hass_lat: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub hass_long: Option<String>,
+    /// Temperature unit of Home Assistant (e.g. "°F")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hass_temperature_unit: Option<String>,
+    /// Entity domains (e.g. "`binary_sensor`") not imported, by backend name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub disabled_domains: BTreeMap<String, BTreeSet<String>>,
+    /// Entities with any of these Home Assistant labels (by name, ignoring
+    /// case) are added to Hue
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_labels: Vec<String>,
+    /// Entities with any of these Home Assistant labels are not added to Hue
+    /// (unless added explicitly)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_labels: Vec<String>,
+    /// Room id for entities with a label (unless assigned to a room explicitly)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub label_rooms: BTreeMap<String, String>,
+    /// Hue zone (by name) for entities with a label. The Home Assistant
+    /// devices in these zones follow the labels.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub label_zones: BTreeMap<String, String>,
+    /// Numeric sensors exposed as binary sensors, by entity id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sensor_thresholds: BTreeMap<String, HassSensorThreshold>,
+    /// Import `script.*` and `button.*` entities as Hue buttons
+    #[serde(default)]
+    pub import_buttons: bool,
+    /// Import `event.*` entities (remotes, doorbells) as Hue buttons
+    #[serde(default)]
+    pub import_remotes: bool,
+    /// Expose a virtual daylight (light level) sensor, computed from `sun.sun`
+    #[serde(default)]
+    pub daylight_sensor: bool,
+    /// Weather entity dimming the daylight sensor on cloudy days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daylight_weather_entity: Option<String>,
 }
 
 impl Default for HassUiConfig {
@@ -196,6 +479,7 @@ impl Default for HassUiConfig {
             rooms: Vec::new(),
             entity_preferences: HashMap::new(),
             ignored_area_names: Vec::new(),
+            included_area_names: Vec::new(),
             default_add_new_devices_to_hue: Self::default_add_new(),
             sync_hass_areas_to_rooms: Self::default_sync_areas(),
             fake_cloud_mode: Self::default_fake_cloud_mode(),
@@ -203,12 +487,39 @@ impl Default for HassUiConfig {
             hass_timezone: None,
             hass_lat: None,
             hass_long: None,
+            hass_temperature_unit: None,
+            disabled_domains: BTreeMap::new(),
+            include_labels: Vec::new(),
+            exclude_labels: Vec::new(),
+            label_rooms: BTreeMap::new(),
+            label_zones: BTreeMap::new(),
+            sensor_thresholds: BTreeMap::new(),
+            import_buttons: false,
+            import_remotes: false,
+            daylight_sensor: false,
+            daylight_weather_entity: None,
         };
         cfg.ensure_default_room();
         cfg
     }
 }
 
+/// Is any of `labels` one of `rules`? Labels are matched by name, ignoring
+/// case.
+fn has_label(rules: &[String], labels: &[String]) -> bool {
+    labels
+        .iter()
+        .any(|label| rules.iter().any(|rule| rule.eq_ignore_ascii_case(label)))
+}
+
+/// The value of the rule for `label` (by name, ignoring case)
+fn label_rule<'a>(rules: &'a BTreeMap<String, String>, label: &str) -> Option<&'a String> {
+    rules
+        .iter()
+        .find(|(rule, _)| rule.eq_ignore_ascii_case(label))
+        .map(|(_, value)| value)
+}
+
 impl HassUiConfig {
     pub const DEFAULT_ROOM_ID: &'static str = "home-assistant";
     const DEFAULT_ROOM_NAME: &'static str = "Home Assistant";
@@ -255,6 +566,8 @@ impl HassUiConfig {
                     name: Self::DEFAULT_ROOM_NAME.to_string(),
                     source_area: None,
                     auto_created: false,
+                    brightness_strategy: None,
+                    merge_into: None,
                 },
             );
         }
@@ -285,6 +598,12 @@ impl HassUiConfig {
             .map(|x| x.trim().to_string())
             .filter(|x| !x.is_empty())
             .collect();
+        self.included_area_names = self
+            .included_area_names
+            .iter()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect();
         self.hass_timezone = self
             .hass_timezone
             .as_ref()
@@ -300,6 +619,32 @@ impl HassUiConfig {
             .as_ref()
             .map(|x| x.trim().to_string())
             .filter(|x| !x.is_empty());
+        self.disabled_domains
+            .retain(|_, domains| !domains.is_empty());
+        self.include_labels = self
+            .include_labels
+            .iter()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect();
+        self.exclude_labels = self
+            .exclude_labels
+            .iter()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect();
+        self.label_rooms = self
+            .label_rooms
+            .iter()
+            .map(|(label, room_id)| (label.trim().to_string(), room_id.trim().to_string()))
+            .filter(|(label, room_id)| !label.is_empty() && !room_id.is_empty())
+            .collect();
+        self.label_zones = self
+            .label_zones
+            .iter()
+            .map(|(label, zone)| (label.trim().to_string(), zone.trim().to_string()))
+            .filter(|(label, zone)| !label.is_empty() && !zone.is_empty())
+            .collect();
 
         let mut seen = BTreeSet::new();
         let mut normalized = Vec::new();
@@ -321,6 +666,8 @@ impl HassUiConfig {
                     .map(|x| x.trim().to_string())
                     .filter(|x| !x.is_empty()),
                 auto_created: room.auto_created,
+                brightness_strategy: room.brightness_strategy,
+                merge_into: room.merge_into,
             });
         }
         self.rooms = normalized;
@@ -365,9 +712,18 @@ impl HassUiConfig {
                 || pref.sensor_enabled.is_some()
                 || pref.switch_mode.is_some()
                 || pref.light_archetype.is_some()
+                || pref.conflict_policy.is_some()
         });
     }
 
+    /// Was the entity made visible explicitly?
+    pub fn is_manually_visible(&self, entity_id: &str) -> bool {
+        self.entity_preferences
+            .get(entity_id)
+            .and_then(|x| x.visible)
+            == Some(true)
+    }
+
     pub fn is_manually_hidden(&self, entity_id: &str) -> bool {
         if self
             .entity_preferences
@@ -462,6 +818,40 @@ impl HassUiConfig {
         self.normalize();
     }
 
+    pub fn set_entity_conflict_policy(
+        &mut self,
+        entity_id: &str,
+        conflict_policy: Option<HassConflictPolicy>,
+    ) {
+        let pref = self
+            .entity_preferences
+            .entry(entity_id.to_string())
+            .or_default();
+        pref.conflict_policy = conflict_policy;
+        self.normalize();
+    }
+
+    /// Move all settings of entity `old` to `new` (after the entity was
+    /// renamed in Home Assistant)
+    pub fn rename_entity(&mut self, old: &str, new: &str) {
+        if let Some(pref) = self.entity_preferences.remove(old) {
+            self.entity_preferences.insert(new.to_string(), pref);
+        }
+        if let Some(threshold) = self.sensor_thresholds.remove(old) {
+            self.sensor_thresholds.insert(new.to_string(), threshold);
+        }
+        for entity_id in self
+            .hidden_entity_ids
+            .iter_mut()
+            .chain(&mut self.exclude_entity_ids)
+        {
+            if entity_id.eq_ignore_ascii_case(old) {
+                *entity_id = new.to_string();
+            }
+        }
+        self.normalize();
+    }
+
     #[must_use]
     pub fn entity_alias(&self, entity_id: &str) -> Option<String> {
         self.entity_preferences
@@ -478,6 +868,11 @@ impl HassUiConfig {
             .unwrap_or(detected)
     }
 
+    #[must_use]
+    pub fn sensor_threshold(&self, entity_id: &str) -> Option<&HassSensorThreshold> {
+        self.sensor_thresholds.get(entity_id)
+    }
+
     #[must_use]
     pub fn sensor_enabled(&self, entity_id: &str) -> bool {
         self.entity_preferences
@@ -494,6 +889,15 @@ impl HassUiConfig {
             .unwrap_or(HassSwitchMode::Plug)
     }
 
+    /// Fans are exposed as (dimmable) lights, unless configured as plugs
+    #[must_use]
+    pub fn fan_mode(&self, entity_id: &str) -> HassSwitchMode {
+        self.entity_preferences
+            .get(entity_id)
+            .and_then(|x| x.switch_mode)
+            .unwrap_or(HassSwitchMode::Light)
+    }
+
     #[must_use]
     pub fn light_archetype(&self, entity_id: &str) -> HassLightArchetype {
         self.entity_preferences
@@ -502,6 +906,14 @@ impl HassUiConfig {
             .unwrap_or(HassLightArchetype::ClassicBulb)
     }
 
+    #[must_use]
+    pub fn conflict_policy(&self, entity_id: &str) -> HassConflictPolicy {
+        self.entity_preferences
+            .get(entity_id)
+            .and_then(|x| x.conflict_policy)
+            .unwrap_or_default()
+    }
+
     pub fn room_for_area(&self, area_name: &str) -> Option<String> {
         self.rooms
             .iter()
@@ -547,6 +959,8 @@ impl HassUiConfig {
             name: area_name.to_string(),
             source_area: Some(area_name.to_string()),
             auto_created: true,
+            brightness_strategy: None,
+            merge_into: None,
         });
         self.normalize();
         room_id
@@ -603,8 +1017,50 @@ impl HassUiConfig {
         }
     }
 
+    /// Are entities of `domain` imported by backend `backend`?
+    #[must_use]
+    pub fn domain_enabled(&self, backend: &str, domain: &str) -> bool {
+        self.disabled_domains
+            .get(backend)
+            .is_none_or(|domains| !domains.contains(domain))
+    }
+
+    /// Room assigned to the first of `labels` that has an existing room
+    #[must_use]
+    pub fn room_for_labels(&self, labels: &[String]) -> Option<String> {
+        labels
+            .iter()
+            .filter_map(|label| label_rule(&self.label_rooms, label))
+            .find(|room_id| self.rooms.iter().any(|room| &room.id == *room_id))
+            .cloned()
+    }
+
+    /// Does any of `labels` assign an entity to the zone named `zone`?
+    #[must_use]
+    pub fn in_label_zone(&self, zone: &str, labels: &[String]) -> bool {
+        labels.iter().any(|label| {
+            label_rule(&self.label_zones, label).is_some_and(|name| name.eq_ignore_ascii_case(zone))
+        })
+    }
+
+    /// Are the Home Assistant devices of the zone named `zone` assigned by
+    /// labels?
+    #[must_use]
+    pub fn is_label_zone(&self, zone: &str) -> bool {
+        self.label_zones
+            .values()
+            .any(|name| name.eq_ignore_ascii_case(zone))
+    }
+
     #[must_use]
-    pub fn should_include(&self, entity_id: &str, display_name: &str, available: bool) -> bool {
+    pub fn should_include(
+        &self,
+        entity_id: &str,
+        display_name: &str,
+        area_name: Option<&str>,
+        labels: &[String],
+        available: bool,
+    ) -> bool {
         if !self.include_unavailable && !available {
             return false;
         }
@@ -625,6 +1081,10 @@ impl HassUiConfig {
             return false;
         }
 
+        if has_label(&self.exclude_labels, labels) {
+            return false;
+        }
+
         if self.exclude_name_patterns.iter().any(|x| {
             if x.is_empty() {
                 return false;
@@ -635,6 +1095,18 @@ impl HassUiConfig {
             return false;
         }
 
+        if has_label(&self.include_labels, labels) {
+            return true;
+        }
+
+        if area_name.is_some_and(|area| {
+            self.included_area_names
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(area))
+        }) {
+            return true;
+        }
+
         self.default_add_new_devices_to_hue
     }
 }
@@ -671,6 +1143,42 @@ pub struct HassEntitySummary {
     pub light_archetype: Option<HassLightArchetype>,
     #[serde(default)]
     pub enabled: bool,
+    /// Home Assistant labels (by name)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+impl HassEntitySummary {
+    /// Placeholder for the state of entities loaded from the state file,
+    /// until the first sync with Home Assistant
+    pub const UNKNOWN_STATE: &str = "unknown";
+
+    /// Copy without the state that changes all the time (and is meaningless
+    /// after a restart)
+    #[must_use]
+    pub fn without_volatile_state(&self) -> Self {
+        Self {
+            state: Self::UNKNOWN_STATE.to_string(),
+            available: false,
+            ..self.clone()
+        }
+    }
+
+    /// Is this entity included by the filters of `config`? Binary sensors
+    /// (and locks) of kind "ignore" are left out. Temperature and light level
+    /// sensors have no sensor kind, so they are always kept.
+    #[must_use]
+    pub fn is_included(&self, config: &HassUiConfig) -> bool {
+        config.should_include(
+            &self.entity_id,
+            &self.name,
+            self.area_name.as_deref(),
+            &self.labels,
+            self.available,
+        ) && !self.sensor_kind.is_some_and(|detected| {
+            config.sensor_kind(&self.entity_id, detected) == HassSensorKind::Ignore
+        })
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
@@ -683,6 +1191,71 @@ pub struct HassSyncStatus {
     pub sync_in_progress: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_sync_duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reconcile_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reconcile_result: Option<String>,
+    /// Round trip time of the last websocket ping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_latency_ms: Option<u64>,
+    /// Home Assistant timestamp of the last received state change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_event_at: Option<String>,
+    /// Number of times the websocket stream was found stalled
+    #[serde(default)]
+    pub ws_stalls: u64,
+}
+
+/// Ephemeral view of a single web UI session (e.g. a browser tab), so
+/// concurrent sessions do not see each other's action results.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassSessionView {
+    pub session_id: String,
+    /// Results of actions performed by this session (newest first)
+    pub results: Vec<String>,
+    /// This session requested a sync that has not finished yet
+    pub sync_pending: bool,
+    #[serde(skip)]
+    last_seen: Option<DateTime<Utc>>,
+}
+
+impl HassSessionView {
+    const MAX_RESULTS: usize = 50;
+
+    fn new(session_id: String) -> Self {
+        Self {
+            session_id,
+            results: Vec::new(),
+            sync_pending: false,
+            last_seen: Some(Utc::now()),
+        }
+    }
+
+    fn push_result(&mut self, message: String) {
+        self.results.insert(0, message);
+        self.results.truncate(Self::MAX_RESULTS);
+    }
+}
+
+/// Counters for the queue of light commands waiting for Home Assistant to
+/// become reachable again
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+pub struct HassRetryQueueStatus {
+    /// Requests currently waiting to be retried
+    #[serde(default)]
+    pub queued: usize,
+    /// Requests successfully delivered after being queued
+    #[serde(default)]
+    pub retried: u64,
+    /// Requests that failed permanently when retried
+    #[serde(default)]
+    pub failed: u64,
+    /// Requests dropped because they waited longer than the ttl
+    #[serde(default)]
+    pub dropped_expired: u64,
+    /// Requests dropped because the queue was full (or disabled)
+    #[serde(default)]
+    pub dropped_overflow: u64,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -700,6 +1273,8 @@ pub struct HassPatinaState {
     pub interaction_count: u64,
     #[serde(default)]
     pub interactions_by_key: HashMap<String, u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage: BTreeMap<HassPatinaFeature, HassPatinaUsage>,
 }
 
 impl Default for HassPatinaState {
@@ -708,10 +1283,46 @@ impl Default for HassPatinaState {
             install_date: Utc::now().to_rfc3339(),
             interaction_count: 0,
             interactions_by_key: HashMap::new(),
+            usage: BTreeMap::new(),
         }
     }
 }
 
+/// Features counted in the patina usage breakdown
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum HassPatinaFeature {
+    SyncRun,
+    SceneRecall,
+    RoomCreate,
+}
+
+impl HassPatinaFeature {
+    pub const ALL: [Self; 3] = [Self::SyncRun, Self::SceneRecall, Self::RoomCreate];
+}
+
+/// Use of a single feature.
+///
+/// Only counts and dates are kept (no times, names or entity ids), and only
+/// in the local state file. They are never forwarded to Home Assistant.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassPatinaUsage {
+    #[serde(default)]
+    pub count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_used: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<NaiveDate>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassPatinaUsageReport {
+    pub install_date: String,
+    pub days_since_install: u64,
+    /// Usage of every feature (including unused ones)
+    pub features: BTreeMap<HassPatinaFeature, HassPatinaUsage>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct HassPatinaPublic {
     pub install_date: String,
@@ -761,10 +1372,27 @@ pub struct HassTokenRequest {
     pub token: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+/// State kept in a file of its own, which is written in the background (by
+/// [`crate::server::state_file_writer`]) whenever it changes
+pub trait StateFile {
+    fn filename(&self) -> &Utf8Path;
+
+    /// Notified whenever the state should be saved
+    fn changes(&self) -> Arc<Notify>;
+
+    /// Serialize the state, as it should be saved to the file
+    fn serialize_file(&self) -> ApiResult<String>;
+
+    /// Report that saving the state failed (it is logged already)
+    fn save_failed(&mut self, _err: &ApiError) {}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HassRuntimeState {
     pub file: Utf8PathBuf,
     pub config: HassRuntimeConfig,
+    #[serde(skip)]
+    changes: Arc<Notify>,
 }
 
 impl HassRuntimeState {
@@ -794,17 +1422,20 @@ impl HassRuntimeState {
             config.sync_mode = "manual".to_string();
         }
 
-        let state = Self { file, config };
+        let state = Self {
+            file,
+            config,
+            changes: Arc::default(),
+        };
         if !state.file.is_file() {
-            state.save()?;
+            fs::write(&state.file, state.serialize_file()?)?;
         }
         Ok(state)
     }
 
-    pub fn save(&self) -> ApiResult<()> {
-        let file = File::create(&self.file)?;
-        serde_yml::to_writer(file, &self.config)?;
-        Ok(())
+    /// Schedule the state to be saved
+    pub fn save(&self) {
+        self.changes.notify_one();
     }
 
     pub fn public_config(&self) -> HassRuntimeConfigPublic {
@@ -877,29 +1508,65 @@ impl HassRuntimeState {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+impl StateFile for HassRuntimeState {
+    fn filename(&self) -> &Utf8Path {
+        &self.file
+    }
+
+    fn changes(&self) -> Arc<Notify> {
+        self.changes.clone()
+    }
+
+    fn serialize_file(&self) -> ApiResult<String> {
+        Ok(serde_yml::to_string(&self.config)?)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HassUiState {
     pub file: Utf8PathBuf,
     pub config: HassUiConfig,
     #[serde(default)]
     pub patina: HassPatinaState,
     pub entities: Vec<HassEntitySummary>,
-    pub logs: Vec<String>,
+    #[serde(default)]
+    pub timeline: Timeline,
     #[serde(default)]
     pub sync: HassSyncStatus,
+    /// Retry queue counters, by backend name
+    #[serde(skip)]
+    pub retry_queue: BTreeMap<String, HassRetryQueueStatus>,
+    /// Latest consistency check, by backend name
+    #[serde(skip)]
+    pub verify: BTreeMap<String, HassVerifyReport>,
+    /// Consistency check requests waiting for reports, by request id
+    #[serde(skip)]
+    verify_requests: HashMap<Uuid, mpsc::UnboundedSender<HassVerifyReport>>,
+    #[serde(skip)]
+    sessions: HashMap<String, HassSessionView>,
+    #[serde(skip)]
+    search_index: EntitySearchIndex,
+    /// Snapshot of `config`, normalized whenever it changes
+    #[serde(skip)]
+    normalized: Arc<HassUiConfig>,
+    #[serde(skip)]
+    changes: Arc<Notify>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 struct HassUiStateFile {
     #[serde(default)]
     config: HassUiConfig,
     #[serde(default)]
     patina: HassPatinaState,
+    /// Last known entities, so the web UI is populated right after startup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    entities: Vec<HassEntitySummary>,
 }
 
 impl HassUiState {
     pub fn load(file: Utf8PathBuf) -> ApiResult<Self> {
-        let (mut config, patina) = if file.is_file() {
+        let (mut config, patina, entities) = if file.is_file() {
             match fs::read_to_string(&file) {
                 Ok(raw) => {
                     let has_v2_shape = serde_yml::from_str::<serde_yml::Value>(&raw)
@@ -912,73 +1579,93 @@ impl HassUiState {
 
                     if has_v2_shape {
                         match serde_yml::from_str::<HassUiStateFile>(&raw) {
-                            Ok(state) => (state.config, state.patina),
+                            Ok(state) => (state.config, state.patina, state.entities),
                             Err(err) => {
                                 log::warn!(
                                     "Failed to parse V2 UI state {}, using defaults: {}",
                                     file,
                                     err
                                 );
-                                (HassUiConfig::default(), HassPatinaState::default())
+                                (
+                                    HassUiConfig::default(),
+                                    HassPatinaState::default(),
+                                    Vec::new(),
+                                )
                             }
                         }
                     } else {
                         match serde_yml::from_str::<HassUiConfig>(&raw) {
-                            Ok(config) => (config, HassPatinaState::default()),
+                            Ok(config) => (config, HassPatinaState::default(), Vec::new()),
                             Err(err) => {
                                 log::warn!(
                                     "Failed to parse V1 UI state {}, using defaults: {}",
                                     file,
                                     err
                                 );
-                                (HassUiConfig::default(), HassPatinaState::default())
+                                (
+                                    HassUiConfig::default(),
+                                    HassPatinaState::default(),
+                                    Vec::new(),
+                                )
                             }
                         }
                     }
                 }
                 Err(err) => {
                     log::warn!("Failed to read {}, using defaults: {}", file, err);
-                    (HassUiConfig::default(), HassPatinaState::default())
+                    (
+                        HassUiConfig::default(),
+                        HassPatinaState::default(),
+                        Vec::new(),
+                    )
                 }
             }
         } else {
-            (HassUiConfig::default(), HassPatinaState::default())
+            (
+                HassUiConfig::default(),
+                HassPatinaState::default(),
+                Vec::new(),
+            )
         };
         config.normalize();
 
-        let state = Self {
+        let mut state = Self {
             file,
             config,
             patina,
-            entities: Vec::new(),
-            logs: Vec::new(),
+            entities,
+            timeline: Timeline::new(),
             sync: HassSyncStatus::default(),
+            retry_queue: BTreeMap::new(),
+            verify: BTreeMap::new(),
+            verify_requests: HashMap::new(),
+            sessions: HashMap::new(),
+            search_index: EntitySearchIndex::default(),
+            normalized: Arc::default(),
+            changes: Arc::default(),
         };
 
+        state.config_changed();
+        state.reindex();
+
         if !state.file.is_file() {
-            state.save_config()?;
+            fs::write(&state.file, state.serialize_file()?)?;
         }
 
         Ok(state)
     }
 
-    pub fn save_config(&self) -> ApiResult<()> {
-        let mut cfg = self.config.clone();
-        cfg.normalize();
-        let mut patina = self.patina.clone();
-        if patina.install_date.trim().is_empty() {
-            patina.install_date = Utc::now().to_rfc3339();
-        }
-        patina
-            .interactions_by_key
-            .retain(|k, _| !k.trim().is_empty());
-        let file = File::create(&self.file)?;
-        let state = HassUiStateFile {
-            config: cfg,
-            patina,
-        };
-        serde_yml::to_writer(file, &state)?;
-        Ok(())
+    /// Entity summaries as persisted in the state file, without volatile state
+    fn persisted_entities(&self) -> Vec<HassEntitySummary> {
+        self.entities
+            .iter()
+            .map(HassEntitySummary::without_volatile_state)
+            .collect()
+    }
+
+    /// Schedule the state to be saved
+    pub fn save_config(&self) {
+        self.changes.notify_one();
     }
 
     fn patina_days_since_install(&self) -> u64 {
@@ -1039,12 +1726,97 @@ impl HassUiState {
         }
     }
 
-    pub fn push_log(&mut self, message: impl AsRef<str>) {
-        let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
-        self.logs.push(format!("[{ts}] {}", message.as_ref()));
-        if self.logs.len() > 200 {
-            let drain = self.logs.len() - 200;
-            self.logs.drain(0..drain);
+    /// Count a use of `feature` (today)
+    pub fn record_patina_usage(&mut self, feature: HassPatinaFeature) {
+        let today = Local::now().date_naive();
+        let usage = self.patina.usage.entry(feature).or_default();
+        usage.count = usage.count.saturating_add(1);
+        usage.first_used.get_or_insert(today);
+        usage.last_used = Some(today);
+    }
+
+    #[must_use]
+    pub fn patina_usage(&self) -> HassPatinaUsageReport {
+        let features = HassPatinaFeature::ALL
+            .into_iter()
+            .map(|feature| {
+                let usage = self.patina.usage.get(&feature).cloned();
+                (feature, usage.unwrap_or_default())
+            })
+            .collect();
+
+        HassPatinaUsageReport {
+            install_date: self.patina.install_date.clone(),
+            days_since_install: self.patina_days_since_install(),
+            features,
+        }
+    }
+
+    /// Forget all feature usage (the patina level itself is kept)
+    pub fn reset_patina_usage(&mut self) {
+        self.patina.usage.clear();
+    }
+
+    /// Add entry to the activity timeline
+    pub fn push_entry(&mut self, entry: TimelineEntry) {
+        self.timeline.push(entry);
+    }
+
+    /// Add (informational) entry to the activity timeline
+    pub fn push_event(&mut self, category: TimelineCategory, message: impl Into<String>) {
+        self.push_entry(TimelineEntry::new(category, message));
+    }
+
+    /// Add entry to the activity timeline, and record it as an action result
+    /// for `session` (if any)
+    pub fn push_session_event(
+        &mut self,
+        session: Option<&str>,
+        category: TimelineCategory,
+        message: impl AsRef<str>,
+    ) {
+        self.push_event(category, message.as_ref());
+        if let Some(view) = session.and_then(|id| self.sessions.get_mut(id)) {
+            let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
+            view.push_result(format!("[{ts}] {}", message.as_ref()));
+        }
+    }
+
+    /// Start a new web UI session, expiring idle ones
+    pub fn create_session(&mut self) -> HassSessionView {
+        const MAX_SESSIONS: usize = 32;
+        const IDLE_TIMEOUT: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+
+        let now = Utc::now();
+        self.sessions
+            .retain(|_, view| view.last_seen.is_some_and(|seen| now - seen < IDLE_TIMEOUT));
+
+        if self.sessions.len() >= MAX_SESSIONS {
+            let oldest = self
+                .sessions
+                .values()
+                .min_by_key(|view| view.last_seen)
+                .map(|view| view.session_id.clone());
+            if let Some(oldest) = oldest {
+                self.sessions.remove(&oldest);
+            }
+        }
+
+        let view = HassSessionView::new(Uuid::new_v4().to_string());
+        self.sessions.insert(view.session_id.clone(), view.clone());
+        view
+    }
+
+    /// Look up session (marking it as seen)
+    pub fn session(&mut self, session: Option<&str>) -> Option<HassSessionView> {
+        let view = self.sessions.get_mut(session?)?;
+        view.last_seen = Some(Utc::now());
+        Some(view.clone())
+    }
+
+    pub fn mark_sync_requested(&mut self, session: Option<&str>) {
+        if let Some(view) = session.and_then(|id| self.sessions.get_mut(id)) {
+            view.sync_pending = true;
         }
     }
 
@@ -1054,6 +1826,41 @@ impl HassUiState {
         self.sync.last_sync_at = Some(Utc::now().to_rfc3339());
     }
 
+    /// Receive the reports of the consistency check request `id`, until
+    /// [`Self::unsubscribe_verify`]
+    pub fn subscribe_verify(&mut self, id: Uuid) -> mpsc::UnboundedReceiver<HassVerifyReport> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.verify_requests.insert(id, tx);
+        rx
+    }
+
+    pub fn unsubscribe_verify(&mut self, id: &Uuid) {
+        self.verify_requests.remove(id);
+    }
+
+    /// Publish the consistency check `report`, made for the request
+    /// `request` (if any)
+    pub fn finish_verify(&mut self, request: Option<Uuid>, report: HassVerifyReport) {
+        if let Some(tx) = request.and_then(|id| self.verify_requests.get(&id)) {
+            let _ = tx.send(report.clone());
+        }
+        self.verify.insert(report.backend.clone(), report);
+    }
+
+    pub fn mark_reconcile_finished(&mut self, report: &HassVerifyReport) {
+        self.sync.last_reconcile_at = Some(report.checked_at.clone());
+        self.sync.last_reconcile_result = Some(report.error.as_ref().map_or_else(
+            || {
+                format!(
+                    "ok: {} reconciled, {} remaining",
+                    report.reconciled,
+                    report.mismatches.len()
+                )
+            },
+            |err| format!("error: {err}"),
+        ));
+    }
+
     pub fn mark_sync_finished(&mut self, result: Result<u64, String>) {
         self.sync.sync_in_progress = false;
         self.sync.last_sync_at = Some(Utc::now().to_rfc3339());
@@ -1061,11 +1868,20 @@ impl HassUiState {
             Ok(duration_ms) => {
                 self.sync.last_sync_duration_ms = Some(duration_ms);
                 self.sync.last_sync_result = Some("ok".to_string());
+                self.record_patina_usage(HassPatinaFeature::SyncRun);
+                self.save_config();
             }
             Err(err) => {
                 self.sync.last_sync_result = Some(format!("error: {err}"));
             }
         }
+
+        let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
+        let result = self.sync.last_sync_result.clone().unwrap_or_default();
+        for view in self.sessions.values_mut().filter(|view| view.sync_pending) {
+            view.sync_pending = false;
+            view.push_result(format!("[{ts}] Sync finished: {result}"));
+        }
     }
 
     pub fn add_room(&mut self, room_name: &str) -> Option<HassRoomConfig> {
@@ -1094,9 +1910,11 @@ impl HassUiState {
             name: name.to_string(),
             source_area: None,
             auto_created: false,
+            brightness_strategy: None,
+            merge_into: None,
         };
         self.config.rooms.push(room.clone());
-        self.config.normalize();
+        self.config_changed();
         Some(room)
     }
 
@@ -1119,7 +1937,7 @@ impl HassUiState {
                 pref.room_id = None;
             }
         }
-        self.config.normalize();
+        self.config_changed();
     }
 
     pub fn rename_room(&mut self, room_id: &str, name: &str) {
@@ -1130,32 +1948,56 @@ impl HassUiState {
         if let Some(room) = self.config.rooms.iter_mut().find(|room| room.id == room_id) {
             room.name = trimmed.to_string();
         }
-        self.config.normalize();
+        self.config_changed();
+    }
+
+    pub fn set_room_brightness_strategy(
+        &mut self,
+        room_id: &str,
+        strategy: HassBrightnessStrategy,
+    ) {
+        if let Some(room) = self.config.rooms.iter_mut().find(|room| room.id == room_id) {
+            room.brightness_strategy =
+                Some(strategy).filter(|strategy| *strategy != HassBrightnessStrategy::default());
+        }
+        self.config_changed();
+    }
+
+    pub fn set_room_merge(&mut self, room_id: &str, target: Option<Uuid>) {
+        if let Some(room) = self.config.rooms.iter_mut().find(|room| room.id == room_id) {
+            room.merge_into = target;
+        }
+        self.config_changed();
     }
 
     pub fn set_entity_visibility(&mut self, entity_id: &str, hidden: bool) {
         self.config.set_entity_hidden(entity_id, hidden);
+        self.config_changed();
     }
 
     pub fn set_entity_room(&mut self, entity_id: &str, room_id: Option<String>) {
         self.config.set_entity_room(entity_id, room_id);
-        self.config.normalize();
+        self.config_changed();
     }
 
     pub fn set_entity_alias(&mut self, entity_id: &str, alias: Option<String>) {
         self.config.set_entity_alias(entity_id, alias);
+        self.config_changed();
     }
 
     pub fn set_entity_sensor_kind(&mut self, entity_id: &str, sensor_kind: Option<HassSensorKind>) {
         self.config.set_entity_sensor_kind(entity_id, sensor_kind);
+        self.config_changed();
     }
 
     pub fn set_entity_sensor_enabled(&mut self, entity_id: &str, enabled: bool) {
         self.config.set_entity_sensor_enabled(entity_id, enabled);
+        self.config_changed();
     }
 
     pub fn set_entity_switch_mode(&mut self, entity_id: &str, switch_mode: Option<HassSwitchMode>) {
         self.config.set_entity_switch_mode(entity_id, switch_mode);
+        self.config_changed();
     }
 
     pub fn set_entity_light_archetype(
@@ -1165,25 +2007,129 @@ impl HassUiState {
     ) {
         self.config
             .set_entity_light_archetype(entity_id, light_archetype);
+        self.config_changed();
+    }
+
+    pub fn set_entity_conflict_policy(
+        &mut self,
+        entity_id: &str,
+        conflict_policy: Option<HassConflictPolicy>,
+    ) {
+        self.config
+            .set_entity_conflict_policy(entity_id, conflict_policy);
+        self.config_changed();
     }
 
     pub fn visible_logs(&self) -> Vec<String> {
-        self.logs.iter().rev().cloned().collect()
+        self.timeline.render()
     }
 
     pub fn set_config(&mut self, config: HassUiConfig) {
         self.config = config;
+        self.config_changed();
+        self.reindex();
+    }
+
+    /// Normalize `config`, and update the snapshot handed out by
+    /// [`Self::config_normalized`]. Must be called after every change.
+    fn config_changed(&mut self) {
         self.config.normalize();
+        self.normalized = Arc::new(self.config.clone());
+    }
+
+    /// Cheap snapshot of the normalized config
+    #[must_use]
+    pub fn config_normalized(&self) -> Arc<HassUiConfig> {
+        self.normalized.clone()
+    }
+
+    /// Replace the entity list (after a sync), and update the search index.
+    ///
+    /// The state file is only rewritten if the persisted part of the entities
+    /// changed, to avoid a write on every sync.
+    pub fn set_entities(&mut self, entities: Vec<HassEntitySummary>) {
+        let old = self.persisted_entities();
+        self.entities = entities;
+        self.reindex();
+
+        if self.persisted_entities() != old {
+            self.save_config();
+        }
+    }
+
+    /// Rebuild the search index, after entities have been changed in place
+    pub fn reindex(&mut self) {
+        let aliases: Vec<Option<String>> = self
+            .entities
+            .iter()
+            .map(|ent| self.config.entity_alias(&ent.entity_id))
+            .collect();
+
+        self.search_index =
+            EntitySearchIndex::build(self.entities.iter().zip(&aliases).map(|(ent, alias)| {
+                let fields = [
+                    Some(ent.name.as_str()),
+                    ent.area_name.as_deref(),
+                    alias.as_deref(),
+                    Some(ent.room_name.as_str()),
+                ];
+                (ent.entity_id.as_str(), fields.into_iter().flatten())
+            }));
+    }
+
+    #[must_use]
+    pub fn query_entities(&self, query: &HassEntitiesQuery) -> HassEntitiesResponse {
+        query.apply(&self.entities, &self.search_index)
+    }
+
+    #[must_use]
+    pub fn entity_counts(&self) -> HassEntityCounts {
+        let mut counts = HassEntityCounts::default();
+        for ent in &self.entities {
+            *counts.by_domain.entry(ent.domain.clone()).or_default() += 1;
+            if !ent.included {
+                counts.hidden += 1;
+            }
+        }
+        counts
     }
+}
 
-    pub fn config_normalized(&self) -> HassUiConfig {
+impl StateFile for HassUiState {
+    fn filename(&self) -> &Utf8Path {
+        &self.file
+    }
+
+    fn changes(&self) -> Arc<Notify> {
+        self.changes.clone()
+    }
+
+    fn serialize_file(&self) -> ApiResult<String> {
         let mut cfg = self.config.clone();
         cfg.normalize();
-        cfg
+        let mut patina = self.patina.clone();
+        if patina.install_date.trim().is_empty() {
+            patina.install_date = Utc::now().to_rfc3339();
+        }
+        patina
+            .interactions_by_key
+            .retain(|k, _| !k.trim().is_empty());
+        let state = HassUiStateFile {
+            config: cfg,
+            patina,
+            entities: self.persisted_entities(),
+        };
+        Ok(serde_yml::to_string(&state)?)
     }
 
-    pub fn bridge_log_snapshot(&self) -> Vec<HassEntitySummary> {
-        self.entities.clone()
+    fn save_failed(&mut self, err: &ApiError) {
+        self.push_entry(
+            TimelineEntry::new(
+                TimelineCategory::Config,
+                format!("Failed to save {}: {err}", self.file),
+            )
+            .with_level(TimelineLevel::Error),
+        );
     }
 }
 
@@ -1209,6 +2155,8 @@ pub struct HassBridgeInfo {
     pub default_add_new_devices_to_hue: bool,
     pub sync_hass_areas_to_rooms: bool,
     pub sync_status: HassSyncStatus,
+    /// Retry queue counters, by backend name
+    pub retry_queue: BTreeMap<String, HassRetryQueueStatus>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -1233,6 +2181,42 @@ pub struct HassRoomRenameRequest {
     pub name: String,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassRoomBrightnessRequest {
+    pub room_id: String,
+    pub brightness_strategy: HassBrightnessStrategy,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassRoomMergeRequest {
+    pub room_id: String,
+    /// Hue room to merge into, or `None` to give the room its own Hue room again
+    #[serde(default)]
+    pub target: Option<Uuid>,
+}
+
+/// Direct control of a single (light) entity, e.g. to test it from the web UI
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct HassEntityStateRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on: Option<bool>,
+    /// Brightness in percent (0..100)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<f64>,
+    /// Color temperature in mirek
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_temperature: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<XY>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassEntityStateResponse {
+    pub entity_id: String,
+    /// The light the command was sent to
+    pub light: ResourceLink,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct HassEntityPatchRequest {
     pub entity_id: String,
@@ -1250,6 +2234,8 @@ pub struct HassEntityPatchRequest {
     pub switch_mode: Option<HassSwitchMode>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub light_archetype: Option<HassLightArchetype>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_policy: Option<HassConflictPolicy>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -1257,6 +2243,11 @@ pub struct HassLogsResponse {
     pub logs: Vec<String>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassTimelineResponse {
+    pub entries: Vec<TimelineEntry>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct HassRoomsResponse {
     pub rooms: Vec<HassRoomConfig>,
@@ -1265,15 +2256,157 @@ pub struct HassRoomsResponse {
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct HassEntitiesResponse {
     pub entities: Vec<HassEntitySummary>,
+    /// Number of entities matching the filters (before limit/offset)
+    #[serde(default)]
+    pub total: usize,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HassEntitySortKey {
+    #[default]
+    EntityId,
+    Name,
+    Domain,
+    Room,
+    State,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HassSortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Query parameters for `GET /bifrost/hass/entities`
+///
+/// Filters are combined, so all given filters must match.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+pub struct HassEntitiesQuery {
+    /// Domain, or comma separated list of domains
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Room id (see [`HassRoomConfig`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub included: Option<bool>,
+    /// Words to search for in entity id, name, area, alias and room.
+    ///
+    /// Words match by prefix, and allow a single typo. Unless another sort
+    /// key is given, results are sorted by relevance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<HassEntitySortKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<HassSortOrder>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl HassEntitiesQuery {
+    #[must_use]
+    pub fn matches(&self, ent: &HassEntitySummary) -> bool {
+        if self
+            .domain
+            .as_ref()
+            .is_some_and(|domains| !domains.split(',').any(|domain| domain.trim() == ent.domain))
+        {
+            return false;
+        }
+
+        if self.room.as_ref().is_some_and(|room| *room != ent.room_id) {
+            return false;
+        }
+
+        !self
+            .included
+            .is_some_and(|included| included != ent.included)
+    }
+
+    /// Sort entities by the requested key, or by search relevance if no key
+    /// was given
+    fn sort(&self, entities: &mut [&HassEntitySummary], scores: Option<&HashMap<&str, u32>>) {
+        entities.sort_by(|a, b| {
+            let ord = match (self.sort, scores) {
+                (None, Some(scores)) => scores
+                    .get(b.entity_id.as_str())
+                    .cmp(&scores.get(a.entity_id.as_str())),
+                (key, _) => match key.unwrap_or_default() {
+                    HassEntitySortKey::EntityId => a.entity_id.cmp(&b.entity_id),
+                    HassEntitySortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                    HassEntitySortKey::Domain => a.domain.cmp(&b.domain),
+                    HassEntitySortKey::Room => a.room_name.cmp(&b.room_name),
+                    HassEntitySortKey::State => a.state.cmp(&b.state),
+                },
+            }
+            .then_with(|| a.entity_id.cmp(&b.entity_id));
+
+            match self.order.unwrap_or_default() {
+                HassSortOrder::Asc => ord,
+                HassSortOrder::Desc => ord.reverse(),
+            }
+        });
+    }
+
+    /// Filter, sort and paginate entities
+    #[must_use]
+    pub fn apply(
+        &self,
+        entities: &[HassEntitySummary],
+        index: &EntitySearchIndex,
+    ) -> HassEntitiesResponse {
+        let scores = self.search.as_deref().and_then(|query| index.search(query));
+
+        let mut matching: Vec<&HassEntitySummary> = entities
+            .iter()
+            .filter(|ent| self.matches(ent))
+            .filter(|ent| {
+                scores
+                    .as_ref()
+                    .is_none_or(|scores| scores.contains_key(ent.entity_id.as_str()))
+            })
+            .collect();
+        let total = matching.len();
+
+        self.sort(&mut matching, scores.as_ref());
+
+        let entities = matching
+            .into_iter()
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+
+        HassEntitiesResponse { entities, total }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct HassUiPayload {
     pub config: HassUiConfig,
-    pub entities: Vec<HassEntitySummary>,
-    pub logs: Vec<String>,
+    /// Names of the configured Home Assistant backends
+    #[serde(default)]
+    pub backends: Vec<String>,
+    /// The entities themselves are paged (see [`HassEntitiesQuery`])
+    pub entity_counts: HassEntityCounts,
+    pub timeline: Vec<TimelineEntry>,
     pub sync: HassSyncStatus,
     pub patina: HassPatinaPublic,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<HassSessionView>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+pub struct HassEntityCounts {
+    pub by_domain: BTreeMap<String, usize>,
+    /// Entities not added to Hue
+    pub hidden: usize,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -1288,9 +2421,95 @@ pub struct HassApplyResponse {
     pub removed_devices: usize,
 }
 
+/// What a bridge reset clears
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HassResetScope {
+    /// Everything (factory reset)
+    #[default]
+    All,
+    /// Only devices imported from Home Assistant (they return on the next sync)
+    HassDevices,
+    /// Only scenes
+    Scenes,
+    /// Only the paired apps, and their recorded pairing activity. Apps using
+    /// entertainment streams must pair again.
+    Pairings,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+pub struct HassResetTokenRequest {
+    #[serde(default)]
+    pub scope: HassResetScope,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassResetTokenResponse {
+    pub token: String,
+    pub scope: HassResetScope,
+    pub expires_in_seconds: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassResetBridgeRequest {
+    #[serde(default)]
+    pub scope: HassResetScope,
+    /// Confirmation token (see `POST /bifrost/hass/reset-bridge/token`)
+    pub token: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct HassResetBridgeResponse {
     pub reset: bool,
+    #[serde(default)]
+    pub scope: HassResetScope,
+    /// Number of resources (or pairing entries) removed
+    #[serde(default)]
+    pub removed: usize,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HassMismatchKind {
+    /// Hue and Home Assistant disagree on whether the entity is on
+    State,
+    /// An included entity has no (complete) Hue resources
+    Missing,
+    /// Hue resources without a matching Home Assistant entity
+    Orphan,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassMismatch {
+    pub kind: HassMismatchKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<ResourceLink>,
+    pub detail: String,
+}
+
+/// Result of comparing the Home Assistant states with the Hue resources of
+/// a single backend
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassVerifyReport {
+    pub backend: String,
+    pub checked_at: String,
+    /// Number of imported entities checked
+    pub checked: usize,
+    pub mismatches: Vec<HassMismatch>,
+    /// Number of mismatches fixed before this check (when reconciling)
+    #[serde(default)]
+    pub reconciled: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct HassVerifyResponse {
+    /// All backends reported back in time
+    pub complete: bool,
+    pub reports: Vec<HassVerifyReport>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -1307,20 +2526,363 @@ pub struct HassPatinaEventRequest {
 }
 
 impl HassUiState {
-    pub fn payload(&self) -> HassUiPayload {
+    pub fn payload(&mut self, session: Option<&str>) -> HassUiPayload {
         HassUiPayload {
-            config: self.config_normalized(),
-            entities: self.bridge_log_snapshot(),
-            logs: self.visible_logs(),
+            config: HassUiConfig::clone(&self.normalized),
+            backends: vec![],
+            entity_counts: self.entity_counts(),
+            timeline: self.timeline.query(&TimelineQuery::default()),
             sync: self.sync.clone(),
             patina: self.patina_public(),
+            session: self.session(session),
         }
     }
 
-    pub fn persist_and_log(&mut self, reason: &str) -> ApiResult<()> {
-        self.config.normalize();
-        self.save_config()?;
-        self.push_log(reason);
+    pub fn persist_and_log(&mut self, session: Option<&str>, reason: &str) -> ApiResult<()> {
+        self.config_changed();
+        self.save_config();
+        self.push_session_event(session, TimelineCategory::Config, reason);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use chrono::{DateTime, Local, Utc};
+
+    use crate::model::hass::{
+        HassBrightnessStrategy, HassEntitiesQuery, HassEntitySummary, HassLightBrightness,
+        HassPatinaFeature, HassSensorKind, HassSensorThreshold, HassSortOrder, HassUiConfig,
+        HassUiState, HassVerifyReport,
+    };
+    use crate::model::search::EntitySearchIndex;
+
+    fn summary(entity_id: &str, sensor_kind: Option<HassSensorKind>) -> HassEntitySummary {
+        HassEntitySummary {
+            entity_id: entity_id.to_string(),
+            domain: "sensor".to_string(),
+            name: entity_id.to_string(),
+            state: "21.5".to_string(),
+            available: true,
+            included: true,
+            hidden: false,
+            area_name: None,
+            room_id: HassUiConfig::DEFAULT_ROOM_ID.to_string(),
+            room_name: String::new(),
+            mapped_type: String::new(),
+            supports_brightness: false,
+            supports_color: false,
+            supports_color_temp: false,
+            switch_mode: None,
+            sensor_kind,
+            light_archetype: None,
+            enabled: true,
+            labels: vec![],
+        }
+    }
+
+    #[test]
+    fn measurements_are_included() {
+        let cfg = HassUiConfig {
+            default_add_new_devices_to_hue: true,
+            ..HassUiConfig::default()
+        };
+
+        // temperature and light level sensors have no sensor kind
+        assert!(summary("sensor.temperature", None).is_included(&cfg));
+        assert!(summary("sensor.illuminance", None).is_included(&cfg));
+    }
+
+    #[test]
+    fn rename_entity_settings() {
+        let mut cfg = HassUiConfig::default();
+        cfg.set_entity_alias("sensor.old", Some("Hallway".to_string()));
+        cfg.set_entity_hidden("sensor.old", true);
+        cfg.sensor_thresholds.insert(
+            "sensor.old".to_string(),
+            HassSensorThreshold {
+                sensor_kind: HassSensorKind::Motion,
+                below: Some(10.0),
+                above: None,
+            },
+        );
+
+        cfg.rename_entity("sensor.old", "sensor.new");
+
+        assert_eq!(cfg.entity_alias("sensor.new").as_deref(), Some("Hallway"));
+        assert_eq!(cfg.entity_alias("sensor.old"), None);
+        assert!(cfg.sensor_thresholds.contains_key("sensor.new"));
+        assert!(!cfg.sensor_thresholds.contains_key("sensor.old"));
+        assert_eq!(cfg.hidden_entity_ids, ["sensor.new"]);
+    }
+
+    #[test]
+    fn ignored_sensors_are_excluded() {
+        let mut cfg = HassUiConfig {
+            default_add_new_devices_to_hue: true,
+            ..HassUiConfig::default()
+        };
+
+        assert!(summary("sensor.power", Some(HassSensorKind::Motion)).is_included(&cfg));
+        assert!(!summary("sensor.noise", Some(HassSensorKind::Ignore)).is_included(&cfg));
+
+        cfg.set_entity_sensor_kind("sensor.power", Some(HassSensorKind::Ignore));
+        assert!(!summary("sensor.power", Some(HassSensorKind::Motion)).is_included(&cfg));
+    }
+
+    fn entities() -> Vec<HassEntitySummary> {
+        let mut kitchen = summary("light.kitchen", None);
+        kitchen.domain = "light".to_string();
+        kitchen.room_id = "kitchen".to_string();
+        let mut hall = summary("light.hall", None);
+        hall.domain = "light".to_string();
+        hall.included = false;
+        let mut fan = summary("fan.ceiling", None);
+        fan.domain = "fan".to_string();
+        vec![kitchen, hall, fan, summary("sensor.temperature", None)]
+    }
+
+    fn entity_ids(query: &HassEntitiesQuery) -> Vec<String> {
+        let entities = entities();
+        let index = EntitySearchIndex::build(
+            entities
+                .iter()
+                .map(|ent| (ent.entity_id.as_str(), [ent.name.as_str()])),
+        );
+        query
+            .apply(&entities, &index)
+            .entities
+            .into_iter()
+            .map(|ent| ent.entity_id)
+            .collect()
+    }
+
+    #[test]
+    fn entities_query_filters() {
+        let query = |domain: &str| HassEntitiesQuery {
+            domain: Some(domain.to_string()),
+            ..HassEntitiesQuery::default()
+        };
+        assert_eq!(entity_ids(&query("light")), ["light.hall", "light.kitchen"]);
+        assert_eq!(
+            entity_ids(&query("light, fan")),
+            ["fan.ceiling", "light.hall", "light.kitchen"]
+        );
+
+        let query = HassEntitiesQuery {
+            room: Some("kitchen".to_string()),
+            ..HassEntitiesQuery::default()
+        };
+        assert_eq!(entity_ids(&query), ["light.kitchen"]);
+
+        let query = HassEntitiesQuery {
+            included: Some(false),
+            ..HassEntitiesQuery::default()
+        };
+        assert_eq!(entity_ids(&query), ["light.hall"]);
+
+        let query = HassEntitiesQuery {
+            search: Some("ceilng".to_string()),
+            ..HassEntitiesQuery::default()
+        };
+        assert_eq!(entity_ids(&query), ["fan.ceiling"]);
+    }
+
+    #[test]
+    fn entities_query_pages() {
+        let query = HassEntitiesQuery {
+            order: Some(HassSortOrder::Desc),
+            offset: Some(1),
+            limit: Some(2),
+            ..HassEntitiesQuery::default()
+        };
+        assert_eq!(entity_ids(&query), ["light.kitchen", "light.hall"]);
+
+        let entities = entities();
+        let res = query.apply(&entities, &EntitySearchIndex::default());
+        assert_eq!(res.total, 4);
+    }
+
+    fn labels(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn labels_match_by_name() {
+        let cfg = HassUiConfig {
+            default_add_new_devices_to_hue: false,
+            include_labels: labels(&["Hue"]),
+            exclude_labels: labels(&["Hidden"]),
+            ..HassUiConfig::default()
+        };
+
+        let include =
+            |names: &[&str]| cfg.should_include("light.x", "X", None, &labels(names), true);
+        assert!(include(&["hue"]));
+        assert!(!include(&["hue", "HIDDEN"]));
+        assert!(!include(&["other"]));
+        assert!(!include(&[]));
+    }
+
+    #[test]
+    fn label_rooms_and_zones() {
+        let mut cfg = HassUiConfig::default();
+        cfg.label_rooms.insert(
+            "Kitchen".to_string(),
+            HassUiConfig::DEFAULT_ROOM_ID.to_string(),
+        );
+        cfg.label_rooms
+            .insert("Attic".to_string(), "missing-room".to_string());
+        cfg.label_zones
+            .insert("Evening".to_string(), "Cosy lights".to_string());
+
+        assert_eq!(
+            cfg.room_for_labels(&labels(&["attic", "kitchen"]))
+                .as_deref(),
+            Some(HassUiConfig::DEFAULT_ROOM_ID)
+        );
+        assert_eq!(cfg.room_for_labels(&labels(&["attic"])), None);
+
+        assert!(cfg.is_label_zone("cosy lights"));
+        assert!(!cfg.is_label_zone("Downstairs"));
+        assert!(cfg.in_label_zone("Cosy Lights", &labels(&["evening"])));
+        assert!(!cfg.in_label_zone("Cosy Lights", &labels(&["kitchen"])));
+        assert!(!cfg.in_label_zone("Downstairs", &labels(&["evening"])));
+    }
+
+    fn light(on: bool, brightness: f64, updated: i64) -> HassLightBrightness {
+        HassLightBrightness {
+            on,
+            brightness,
+            last_updated: DateTime::<Utc>::from_timestamp(updated, 0),
+        }
+    }
+
+    #[test]
+    fn brightness_strategies() {
+        use HassBrightnessStrategy::{Average, AverageOn, LastChanged, Max};
+
+        let mixed = [
+            light(true, 20.0, 3),
+            light(true, 60.0, 1),
+            light(false, 100.0, 2),
+        ];
+        let off = [light(false, 30.0, 1), light(false, 50.0, 2)];
+
+        let cases: &[(HassBrightnessStrategy, &[HassLightBrightness], Option<f64>)] = &[
+            (Average, &mixed, Some(60.0)),
+            (AverageOn, &mixed, Some(40.0)),
+            (Max, &mixed, Some(60.0)),
+            (LastChanged, &mixed, Some(20.0)),
+            // with all lights off, every strategy considers all of them
+            (Average, &off, Some(40.0)),
+            (AverageOn, &off, Some(40.0)),
+            (Max, &off, Some(50.0)),
+            (LastChanged, &off, Some(50.0)),
+            (Average, &[], None),
+            (AverageOn, &[], None),
+            (Max, &[], None),
+            (LastChanged, &[], None),
+        ];
+
+        for (strategy, lights, expected) in cases {
+            assert_eq!(
+                strategy.aggregate(lights),
+                *expected,
+                "{strategy:?} of {lights:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn patina_usage_counts() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-patina-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut ui = HassUiState::load(dir.join("ui.yaml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // every feature is reported, used or not
+        let report = ui.patina_usage();
+        assert_eq!(report.features.len(), HassPatinaFeature::ALL.len());
+        assert!(report.features.values().all(|usage| usage.count == 0));
+
+        ui.record_patina_usage(HassPatinaFeature::SceneRecall);
+        ui.record_patina_usage(HassPatinaFeature::SceneRecall);
+        ui.record_patina_usage(HassPatinaFeature::RoomCreate);
+
+        let today = Local::now().date_naive();
+        let report = ui.patina_usage();
+        let recall = &report.features[&HassPatinaFeature::SceneRecall];
+        assert_eq!(recall.count, 2);
+        assert_eq!(recall.first_used, Some(today));
+        assert_eq!(recall.last_used, Some(today));
+        assert_eq!(report.features[&HassPatinaFeature::RoomCreate].count, 1);
+        assert_eq!(report.features[&HassPatinaFeature::SyncRun].count, 0);
+        assert_eq!(
+            report.features[&HassPatinaFeature::SyncRun].first_used,
+            None
+        );
+    }
+
+    #[test]
+    fn patina_usage_reset() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-patina-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut ui = HassUiState::load(dir.join("ui.yaml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        ui.record_patina_event("apply", Some("scene"));
+        ui.record_patina_usage(HassPatinaFeature::SyncRun);
+        let level = ui.patina_public();
+        ui.reset_patina_usage();
+
+        // usage is forgotten, the patina level is kept
+        let report = ui.patina_usage();
+        assert!(report.features.values().all(|usage| usage.count == 0));
+        assert_eq!(ui.patina_public(), level);
+    }
+
+    #[test]
+    fn verify_reports_per_request() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-verify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut ui = HassUiState::load(dir.join("ui.yaml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let report = |backend: &str| HassVerifyReport {
+            backend: backend.to_string(),
+            checked_at: Utc::now().to_rfc3339(),
+            checked: 0,
+            mismatches: vec![],
+            reconciled: 0,
+            error: None,
+        };
+        let first = uuid::Uuid::new_v4();
+        let second = uuid::Uuid::new_v4();
+        let mut first_rx = ui.subscribe_verify(first);
+        let mut second_rx = ui.subscribe_verify(second);
+
+        // concurrent requests only receive their own reports
+        ui.finish_verify(Some(first), report("home"));
+        ui.finish_verify(None, report("nightly"));
+        ui.finish_verify(Some(second), report("office"));
+        assert_eq!(first_rx.try_recv().unwrap().backend, "home");
+        assert!(first_rx.try_recv().is_err());
+        assert_eq!(second_rx.try_recv().unwrap().backend, "office");
+        assert!(second_rx.try_recv().is_err());
+
+        // every report is kept as the latest one of its backend
+        assert_eq!(ui.verify.len(), 3);
+
+        ui.unsubscribe_verify(&first);
+        ui.finish_verify(Some(first), report("home"));
+        assert!(first_rx.try_recv().is_err());
+    }
+}
@@ -1,4 +1,8 @@
 pub mod hass;
+pub mod revision;
+pub mod search;
 pub mod state;
 pub mod throttle;
+pub mod timeline;
 pub mod upnp;
+pub mod voice;
@@ -0,0 +1,108 @@
+//! Cheap, monotonic revision numbers for the resource database, used to
+//! answer conditional requests (`ETag` / `If-None-Match`).
+use std::collections::BTreeMap;
+
+use hue::api::RType;
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct Revisions {
+    /// Random per-process value, so revisions from before a restart never
+    /// match the current ones
+    boot: u64,
+    current: u64,
+    /// Revision of the last change that could affect all resource types
+    epoch: u64,
+    by_type: BTreeMap<RType, u64>,
+}
+
+impl Default for Revisions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Revisions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            boot: Uuid::new_v4().as_u64_pair().0,
+            current: 0,
+            epoch: 0,
+            by_type: BTreeMap::new(),
+        }
+    }
+
+    /// Record a change to a resource of type `rtype`
+    pub fn touch(&mut self, rtype: RType) {
+        self.current += 1;
+        self.by_type.insert(rtype, self.current);
+    }
+
+    /// Record a change that could affect resources of any type
+    pub const fn touch_all(&mut self) {
+        self.current += 1;
+        self.epoch = self.current;
+    }
+
+    /// Revision of the resource database as a whole
+    #[must_use]
+    pub const fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// Revision of the resources of type `rtype`
+    #[must_use]
+    pub fn of_type(&self, rtype: RType) -> u64 {
+        self.by_type
+            .get(&rtype)
+            .copied()
+            .unwrap_or_default()
+            .max(self.epoch)
+    }
+
+    /// Format `revision` as a (quoted) entity tag
+    #[must_use]
+    pub fn etag(&self, revision: u64) -> String {
+        format!("\"{:x}-{revision}\"", self.boot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hue::api::RType;
+
+    use crate::model::revision::Revisions;
+
+    #[test]
+    fn touch_only_affects_type() {
+        let mut revs = Revisions::new();
+        let light = revs.of_type(RType::Light);
+        let room = revs.of_type(RType::Room);
+
+        revs.touch(RType::Light);
+
+        assert_ne!(revs.of_type(RType::Light), light);
+        assert_eq!(revs.of_type(RType::Room), room);
+    }
+
+    #[test]
+    fn touch_all_affects_every_type() {
+        let mut revs = Revisions::new();
+        revs.touch(RType::Light);
+        let light = revs.of_type(RType::Light);
+        let room = revs.of_type(RType::Room);
+
+        revs.touch_all();
+
+        assert!(revs.of_type(RType::Light) > light);
+        assert!(revs.of_type(RType::Room) > room);
+    }
+
+    #[test]
+    fn etag_differs_between_instances() {
+        let a = Revisions::new();
+        let b = Revisions::new();
+        assert_ne!(a.etag(1), b.etag(1));
+    }
+}
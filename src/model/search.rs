@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+
+/// Search index over Home Assistant entities (entity id, name, area, alias and
+/// room), rebuilt whenever the entity list changes.
+///
+/// Each query word must match a word of the entity, either exactly, as a
+/// prefix, or (for longer words) with a single typo. Matching entities are
+/// scored by how well the words matched.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EntitySearchIndex {
+    /// Entity ids, by position
+    entities: Vec<String>,
+    /// Words, mapped to the positions of entities containing them
+    words: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl EntitySearchIndex {
+    const SCORE_EXACT: u32 = 3;
+    const SCORE_PREFIX: u32 = 2;
+    const SCORE_FUZZY: u32 = 1;
+
+    /// Shortest query word allowed to match with a typo
+    const FUZZY_MIN_LEN: usize = 4;
+
+    fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+    }
+
+    /// Build index from (entity id, searchable fields) pairs
+    #[must_use]
+    pub fn build<'a, I, F>(entities: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, F)>,
+        F: IntoIterator<Item = &'a str>,
+    {
+        let mut index = Self::default();
+
+        for (entity_id, fields) in entities {
+            let pos = index.entities.len();
+            index.entities.push(entity_id.to_string());
+
+            for word in Self::tokenize(entity_id) {
+                index.words.entry(word).or_default().insert(pos);
+            }
+            for field in fields {
+                for word in Self::tokenize(field) {
+                    index.words.entry(word).or_default().insert(pos);
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Is `a` within a single edit (insertion, deletion or substitution) of `b`?
+    fn within_one_edit(a: &str, b: &str) -> bool {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+
+        let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+        if long.len() - short.len() > 1 {
+            return false;
+        }
+
+        let prefix = short.iter().zip(&long).take_while(|(x, y)| x == y).count();
+        if prefix == short.len() {
+            return true;
+        }
+
+        if short.len() == long.len() {
+            short[prefix + 1..] == long[prefix + 1..]
+        } else {
+            short[prefix..] == long[prefix + 1..]
+        }
+    }
+
+    /// Best score for each entity matching a single query word
+    fn search_word(&self, query: &str) -> HashMap<usize, u32> {
+        let mut scores: HashMap<usize, u32> = HashMap::new();
+        let mut add = |positions: &BTreeSet<usize>, score: u32| {
+            for pos in positions {
+                let best = scores.entry(*pos).or_default();
+                *best = (*best).max(score);
+            }
+        };
+
+        let range = self
+            .words
+            .range::<str, _>((Bound::Included(query), Bound::Unbounded))
+            .take_while(|(word, _)| word.starts_with(query));
+
+        for (word, positions) in range {
+            let score = if word == query {
+                Self::SCORE_EXACT
+            } else {
+                Self::SCORE_PREFIX
+            };
+            add(positions, score);
+        }
+
+        if query.chars().count() >= Self::FUZZY_MIN_LEN {
+            for (word, positions) in &self.words {
+                if word != query && Self::within_one_edit(word, query) {
+                    add(positions, Self::SCORE_FUZZY);
+                }
+            }
+        }
+
+        scores
+    }
+
+    /// Entity ids matching every word of `query`, with their score (higher is
+    /// better).
+    ///
+    /// Returns `None` if the query contains no searchable words.
+    #[must_use]
+    pub fn search(&self, query: &str) -> Option<HashMap<&str, u32>> {
+        let mut total: Option<HashMap<usize, u32>> = None;
+
+        for word in Self::tokenize(query) {
+            let scores = self.search_word(&word);
+            total = Some(match total {
+                None => scores,
+                Some(prev) => prev
+                    .into_iter()
+                    .filter_map(|(pos, score)| scores.get(&pos).map(|s| (pos, score + s)))
+                    .collect(),
+            });
+        }
+
+        Some(
+            total?
+                .into_iter()
+                .map(|(pos, score)| (self.entities[pos].as_str(), score))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EntitySearchIndex;
+
+    fn index() -> EntitySearchIndex {
+        EntitySearchIndex::build([
+            (
+                "light.kitchen_ceiling",
+                vec!["Kitchen Ceiling", "Downstairs"],
+            ),
+            ("light.kitchen_counter", vec!["Counter strip", "Downstairs"]),
+            ("switch.bedroom_fan", vec!["Fan", "Upstairs"]),
+        ])
+    }
+
+    #[test]
+    fn exact_and_prefix() {
+        let index = index();
+
+        let res = index.search("kitchen").unwrap();
+        assert_eq!(res.len(), 2);
+
+        let res = index.search("kit cei").unwrap();
+        assert_eq!(res.len(), 1);
+        assert!(res.contains_key("light.kitchen_ceiling"));
+
+        let res = index.search("upst").unwrap();
+        assert_eq!(
+            res.keys().copied().collect::<Vec<_>>(),
+            ["switch.bedroom_fan"]
+        );
+    }
+
+    #[test]
+    fn exact_scores_higher() {
+        let index = index();
+        let res = index.search("counter").unwrap();
+        assert!(res["light.kitchen_counter"] > 0);
+
+        let res = index.search("coun").unwrap();
+        assert!(
+            res["light.kitchen_counter"]
+                < index.search("counter").unwrap()["light.kitchen_counter"]
+        );
+    }
+
+    #[test]
+    fn fuzzy() {
+        let index = index();
+        let res = index.search("kitchn").unwrap();
+        assert_eq!(res.len(), 2);
+
+        let res = index.search("bedrom").unwrap();
+        assert!(res.contains_key("switch.bedroom_fan"));
+
+        assert!(index.search("xyzzy").unwrap().is_empty());
+    }
+
+    #[test]
+    fn empty_query() {
+        assert!(index().search(" _ ").is_none());
+    }
+
+    #[test]
+    fn within_one_edit() {
+        assert!(EntitySearchIndex::within_one_edit("kitchen", "kitchen"));
+        assert!(EntitySearchIndex::within_one_edit("kitchen", "kitchn"));
+        assert!(EntitySearchIndex::within_one_edit("kitchen", "kitchens"));
+        assert!(EntitySearchIndex::within_one_edit("kitchen", "kotchen"));
+        assert!(!EntitySearchIndex::within_one_edit("kitchen", "kotchn"));
+        assert!(!EntitySearchIndex::within_one_edit("kitchen", "kit"));
+    }
+}
@@ -1,20 +1,27 @@
 use std::collections::BTreeMap;
 use std::io::Read;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_yml::Value;
 use uuid::Uuid;
 
+use bifrost_api::light::{LightSegmentMap, LightStartup};
+use bifrost_api::rule::{ContactRule, MotionRule};
+use bifrost_api::scene::SceneVariants;
 use hue::api::{DeviceArchetype, Resource};
 use hue::error::{HueError, HueResult};
 use hue::version::SwVersion;
 
 use crate::error::{ApiError, ApiResult};
+use crate::model::hass::HassEntityAux;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct AuxData {
     pub topic: Option<String>,
     pub index: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hass: Option<HassEntityAux>,
 }
 
 impl AuxData {
@@ -38,6 +45,14 @@ impl AuxData {
             ..self
         }
     }
+
+    #[must_use]
+    pub fn with_hass(self, hass: HassEntityAux) -> Self {
+        Self {
+            hass: Some(hass),
+            ..self
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -91,6 +106,33 @@ impl IdMap {
     }
 }
 
+/// An app paired through the v1 api
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApiUser {
+    pub devicetype: String,
+    /// Reported as `hue-application-id`, and used as PSK identity by v2
+    /// entertainment streams
+    pub application_id: Uuid,
+    /// Entertainment streaming key (hex), if the app asked for one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clientkey: Option<String>,
+    pub create_date: DateTime<Utc>,
+}
+
+impl ApiUser {
+    /// A new user, with a random application id and (optionally) a random
+    /// clientkey
+    #[must_use]
+    pub fn generate(devicetype: &str, clientkey: bool) -> Self {
+        Self {
+            devicetype: devicetype.to_string(),
+            application_id: Uuid::new_v4(),
+            clientkey: clientkey.then(|| hex::encode_upper(rand::random::<[u8; 16]>())),
+            create_date: Utc::now(),
+        }
+    }
+}
+
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub enum StateVersion {
     /// Version 0: (`res`, `aux`) tuple, no version field in state
@@ -107,6 +149,22 @@ pub struct State {
     aux: BTreeMap<Uuid, AuxData>,
     id_v1: IdMap,
     pub res: BTreeMap<Uuid, Resource>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    startup: BTreeMap<Uuid, LightStartup>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    segments: BTreeMap<Uuid, LightSegmentMap>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    rules: BTreeMap<Uuid, MotionRule>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    contact_rules: BTreeMap<Uuid, ContactRule>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    variants: BTreeMap<Uuid, SceneVariants>,
+    /// Paired apps, by username
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    users: BTreeMap<String, ApiUser>,
+    /// Is the presence simulation ("away mode") enabled?
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    presence: bool,
 }
 
 impl State {
@@ -189,6 +247,12 @@ impl State {
             aux,
             id_v1,
             res,
+            startup: BTreeMap::new(),
+            segments: BTreeMap::new(),
+            rules: BTreeMap::new(),
+            contact_rules: BTreeMap::new(),
+            variants: BTreeMap::new(),
+            users: BTreeMap::new(),
         })
     }
 
@@ -212,6 +276,93 @@ impl State {
         self.aux.insert(id, aux);
     }
 
+    #[must_use]
+    pub const fn startups(&self) -> &BTreeMap<Uuid, LightStartup> {
+        &self.startup
+    }
+
+    pub fn startup_set(&mut self, id: Uuid, startup: Option<LightStartup>) {
+        match startup {
+            Some(startup) => self.startup.insert(id, startup),
+            None => self.startup.remove(&id),
+        };
+    }
+
+    #[must_use]
+    pub const fn segment_maps(&self) -> &BTreeMap<Uuid, LightSegmentMap> {
+        &self.segments
+    }
+
+    pub fn segment_map_set(&mut self, id: Uuid, map: Option<LightSegmentMap>) {
+        match map {
+            Some(map) => self.segments.insert(id, map),
+            None => self.segments.remove(&id),
+        };
+    }
+
+    #[must_use]
+    pub const fn scene_variants(&self) -> &BTreeMap<Uuid, SceneVariants> {
+        &self.variants
+    }
+
+    pub fn scene_variants_set(&mut self, id: Uuid, variants: Option<SceneVariants>) {
+        match variants {
+            Some(variants) => self.variants.insert(id, variants),
+            None => self.variants.remove(&id),
+        };
+    }
+
+    #[must_use]
+    pub const fn rules(&self) -> &BTreeMap<Uuid, MotionRule> {
+        &self.rules
+    }
+
+    pub fn rule_set(&mut self, id: Uuid, rule: Option<MotionRule>) {
+        match rule {
+            Some(rule) => self.rules.insert(id, rule),
+            None => self.rules.remove(&id),
+        };
+    }
+
+    #[must_use]
+    pub const fn contact_rules(&self) -> &BTreeMap<Uuid, ContactRule> {
+        &self.contact_rules
+    }
+
+    pub fn contact_rule_set(&mut self, id: Uuid, rule: Option<ContactRule>) {
+        match rule {
+            Some(rule) => self.contact_rules.insert(id, rule),
+            None => self.contact_rules.remove(&id),
+        };
+    }
+
+    #[must_use]
+    pub const fn presence_enabled(&self) -> bool {
+        self.presence
+    }
+
+    pub const fn presence_set_enabled(&mut self, enabled: bool) {
+        self.presence = enabled;
+    }
+
+    #[must_use]
+    pub const fn users(&self) -> &BTreeMap<String, ApiUser> {
+        &self.users
+    }
+
+    pub fn user_add(&mut self, username: String, user: ApiUser) {
+        self.users.insert(username, user);
+    }
+
+    pub fn user_remove(&mut self, username: &str) -> Option<ApiUser> {
+        self.users.remove(username)
+    }
+
+    /// Remove all users, returning how many there were
+    pub fn users_clear(&mut self) -> usize {
+        std::mem::take(&mut self.users).len()
+    }
+
     #[must_use]
     pub fn try_get(&self, id: &Uuid) -> Option<&Resource> {
         self.res.get(id)
@@ -232,6 +383,9 @@ impl State {
 
     pub fn remove(&mut self, id: &Uuid) -> ApiResult<()> {
         self.aux.remove(id);
+        self.startup.remove(id);
+        self.segments.remove(id);
+        self.variants.remove(id);
         self.id_v1.remove(id);
         self.res.remove(id).ok_or(HueError::NotFound(*id))?;
         Ok(())
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineCategory {
+    /// Home Assistant syncs, imports and exports
+    Sync,
+    /// Commands from hue clients (scene recalls, group toggles, ..)
+    Command,
+    /// Home Assistant connection state
+    Connection,
+    /// Changes to the web UI configuration
+    Config,
+    /// Link button presses and app pairing
+    Pairing,
+    /// Service lifecycle (started, stopped, failed, ..)
+    Service,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineLevel {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TimelineEntry {
+    pub at: DateTime<Utc>,
+    #[serde(default)]
+    pub level: TimelineLevel,
+    pub category: TimelineCategory,
+    /// Origin of the event (e.g. backend or service name), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Home Assistant entity the event is about, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+    pub message: String,
+}
+
+impl TimelineEntry {
+    #[must_use]
+    pub fn new(category: TimelineCategory, message: impl Into<String>) -> Self {
+        Self {
+            at: Utc::now(),
+            level: TimelineLevel::default(),
+            category,
+            source: None,
+            entity_id: None,
+            message: message.into(),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_level(self, level: TimelineLevel) -> Self {
+        Self { level, ..self }
+    }
+
+    #[must_use]
+    pub fn with_source(self, source: impl Into<String>) -> Self {
+        Self {
+            source: Some(source.into()),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_entity(self, entity_id: impl Into<String>) -> Self {
+        Self {
+            entity_id: Some(entity_id.into()),
+            ..self
+        }
+    }
+
+    /// Render as a single log line, in local time (the format used before
+    /// entries were structured)
+    #[must_use]
+    pub fn render(&self) -> String {
+        let ts = self.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        let level = match self.level {
+            TimelineLevel::Info => "",
+            TimelineLevel::Warning => "WARNING: ",
+            TimelineLevel::Error => "ERROR: ",
+        };
+        match &self.source {
+            Some(source) => format!("[{ts}] [{source}] {level}{}", self.message),
+            None => format!("[{ts}] {level}{}", self.message),
+        }
+    }
+}
+
+/// Query parameters for `GET /bifrost/hass/timeline`
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
+pub struct TimelineQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<TimelineCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Minimum level
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<TimelineLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+    /// Only return entries newer than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl TimelineQuery {
+    fn matches(&self, entry: &TimelineEntry) -> bool {
+        self.category.is_none_or(|cat| cat == entry.category)
+            && self
+                .source
+                .as_ref()
+                .is_none_or(|source| entry.source.as_ref() == Some(source))
+            && self.level.is_none_or(|level| entry.level >= level)
+            && self
+                .entity_id
+                .as_ref()
+                .is_none_or(|entity_id| entry.entity_id.as_ref() == Some(entity_id))
+            && self.since.is_none_or(|since| entry.at > since)
+    }
+}
+
+/// Bounded, in-memory timeline of bridge activity
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Timeline {
+    entries: VecDeque<TimelineEntry>,
+}
+
+impl Timeline {
+    const CAPACITY: usize = 500;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(Self::CAPACITY),
+        }
+    }
+
+    pub fn push(&mut self, entry: TimelineEntry) {
+        if self.entries.len() >= Self::CAPACITY {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+
+    /// Remove all entries in `category`. Returns the number removed.
+    pub fn clear_category(&mut self, category: TimelineCategory) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.category != category);
+        before - self.entries.len()
+    }
+
+    /// Matching entries, newest first
+    #[must_use]
+    pub fn query(&self, query: &TimelineQuery) -> Vec<TimelineEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// All entries rendered as log lines, newest first
+    #[must_use]
+    pub fn render(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .rev()
+            .map(TimelineEntry::render)
+            .collect()
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The words of `text`, in lowercase
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Name of a device, qualified with the name of its room.
+///
+/// Voice assistants (Alexa, Google Home) import devices by name only, so
+/// "Ceiling" in several rooms is ambiguous. Names that already mention the
+/// room (as whole words) are left unchanged.
+#[must_use]
+pub fn room_qualified_name(room: &str, name: &str) -> String {
+    let room = room.trim();
+    let name = name.trim();
+
+    let room_words = words(room);
+    let mentions_room = room_words.is_empty()
+        || words(name)
+            .windows(room_words.len())
+            .any(|window| window == room_words.as_slice());
+
+    if mentions_room {
+        name.to_string()
+    } else {
+        format!("{room} {name}")
+    }
+}
+
+/// Names that are used more than once (ignoring case and surrounding
+/// whitespace), in lowercase.
+#[must_use]
+pub fn ambiguous_names<'a>(names: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+
+    for name in names {
+        *counts.entry(name.trim().to_lowercase()).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::voice::{ambiguous_names, room_qualified_name};
+
+    #[test]
+    fn prefixes_room() {
+        assert_eq!(room_qualified_name("Kitchen", "Ceiling"), "Kitchen Ceiling");
+    }
+
+    #[test]
+    fn keeps_names_mentioning_room() {
+        assert_eq!(
+            room_qualified_name("Kitchen", "Kitchen spots"),
+            "Kitchen spots"
+        );
+        assert_eq!(
+            room_qualified_name("Kitchen", "Spots kitchen"),
+            "Spots kitchen"
+        );
+    }
+
+    #[test]
+    fn matches_whole_words() {
+        assert_eq!(
+            room_qualified_name("Bed", "Bedroom Lamp"),
+            "Bed Bedroom Lamp"
+        );
+        assert_eq!(
+            room_qualified_name("Living room", "Lamp (living room)"),
+            "Lamp (living room)"
+        );
+        assert_eq!(
+            room_qualified_name("Living room", "Living lamp"),
+            "Living room Living lamp"
+        );
+    }
+
+    #[test]
+    fn keeps_names_without_room() {
+        assert_eq!(room_qualified_name("", "Ceiling"), "Ceiling");
+        assert_eq!(room_qualified_name("  ", " Ceiling "), "Ceiling");
+    }
+
+    #[test]
+    fn finds_ambiguous_names() {
+        let names = ambiguous_names(["Ceiling", "ceiling ", "Desk", "Kitchen Ceiling"]);
+
+        assert_eq!(names.into_iter().collect::<Vec<_>>(), ["ceiling"]);
+    }
+}
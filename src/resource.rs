@@ -1,29 +1,39 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::io::{Read, Write};
 use std::sync::Arc;
 
+use chrono::{Local, Timelike};
 use itertools::Itertools;
 use maplit::btreeset;
 use serde::Serialize;
-use serde_json::json;
+use serde_json::{Value, json};
 use tokio::sync::Notify;
-use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::sync::broadcast::Receiver;
 use uuid::Uuid;
 
-use bifrost_api::backend::BackendRequest;
+use bifrost_api::backend::{BackendCapabilities, BackendRequest, BifrostActivity, HassRequest};
+use bifrost_api::config::{BridgeHomeConfig, TimeOfDay, TimeOfDayConfig};
+use bifrost_api::light::{LightSegmentMap, LightStartup};
+use bifrost_api::room::{RoomDuplicate, RoomOwner};
+use bifrost_api::rule::{ContactRule, MotionRule};
+use bifrost_api::scene::SceneVariants;
 use hue::api::{
-    Bridge, BridgeHome, Device, DeviceArchetype, DeviceProductData, DimmingUpdate, Entertainment,
-    EntertainmentConfiguration, GroupedLight, Light, Metadata, On, RType, Resource, ResourceLink,
-    ResourceRecord, Room, Stub, TimeZone, ZigbeeConnectivity, ZigbeeConnectivityStatus,
-    ZigbeeDeviceDiscovery, ZigbeeDeviceDiscoveryAction, ZigbeeDeviceDiscoveryStatus, Zone,
+    Bridge, BridgeHome, Device, DeviceArchetype, DeviceProductData, DeviceSoftwareUpdate,
+    DimmingUpdate, Entertainment, EntertainmentConfiguration, GroupedLight, Light, Metadata, On,
+    RType, Resource, ResourceLink, ResourceRecord, Room, Scene, SceneStatusEnum, SceneUpdate, Stub,
+    Taurus, TimeZone, ZigbeeConnectivity, ZigbeeConnectivityStatus, ZigbeeDeviceDiscovery,
+    ZigbeeDeviceDiscoveryAction, ZigbeeDeviceDiscoveryStatus, Zone,
 };
 use hue::api::{InternetConnectivity, InternetConnectivityStatus};
 use hue::error::{HueError, HueResult};
 use hue::event::EventBlock;
 use hue::version::SwVersion;
 
-use crate::error::ApiResult;
-use crate::model::state::{AuxData, State};
+use crate::backend::ownership::OwnershipRegistry;
+use crate::backend::router::{BackendRouter, LatencySignal};
+use crate::error::{ApiError, ApiResult};
+use crate::model::revision::Revisions;
+use crate::model::state::{ApiUser, AuxData, State};
 use crate::server::hueevents::HueEventStream;
 
 #[derive(Clone, Debug)]
@@ -31,13 +41,19 @@ pub struct Resources {
     state: State,
     version: SwVersion,
     state_updates: Arc<Notify>,
-    backend_updates: Sender<Arc<BackendRequest>>,
+    revisions: Revisions,
+    backends: BackendRouter,
+    owners: OwnershipRegistry,
     hue_event_stream: HueEventStream,
+    time_of_day: TimeOfDayConfig,
+    home: BridgeHomeConfig,
 }
 
 impl Resources {
     const MAX_SCENE_ID: u32 = 100;
     const HUE_EVENTS_BUFFER_SIZE: usize = 128;
+    pub const DEFAULT_EVENT_CHANNEL_SIZE: usize = 32;
+    const MAX_OWNER_DEPTH: usize = 8;
 
     #[allow(clippy::new_without_default)]
     #[must_use]
@@ -46,14 +62,173 @@ impl Resources {
             state,
             version,
             state_updates: Arc::new(Notify::new()),
-            backend_updates: Sender::new(32),
-            hue_event_stream: HueEventStream::new(Self::HUE_EVENTS_BUFFER_SIZE),
+            revisions: Revisions::new(),
+            backends: BackendRouter::new(),
+            owners: OwnershipRegistry::new(),
+            hue_event_stream: HueEventStream::new(
+                Self::HUE_EVENTS_BUFFER_SIZE,
+                Self::DEFAULT_EVENT_CHANNEL_SIZE,
+            ),
+            time_of_day: TimeOfDayConfig::default(),
+            home: BridgeHomeConfig::default(),
         }
     }
 
+    /// Use custom sizes for the backend request and hue event channels.
+    ///
+    /// Must be called before anything subscribes to these channels.
+    #[must_use]
+    pub fn with_channel_sizes(
+        self,
+        backend_channel_size: usize,
+        event_channel_size: usize,
+    ) -> Self {
+        Self {
+            backends: BackendRouter::with_buffer_size(backend_channel_size),
+            hue_event_stream: HueEventStream::new(Self::HUE_EVENTS_BUFFER_SIZE, event_channel_size),
+            ..self
+        }
+    }
+
+    /// Use custom start hours for the parts of the day (for scene variants)
+    #[must_use]
+    pub fn with_time_of_day(self, time_of_day: TimeOfDayConfig) -> Self {
+        Self {
+            time_of_day,
+            ..self
+        }
+    }
+
+    /// Use a custom name and room handling for the bridge home
+    #[must_use]
+    pub fn with_home(self, home: BridgeHomeConfig) -> Self {
+        Self { home, ..self }
+    }
+
+    /// Name of the bridge home, if configured
+    #[must_use]
+    pub fn home_name(&self) -> Option<&str> {
+        self.home.name.as_deref()
+    }
+
+    /// Add room `link` to the bridge home (unless disabled in the config)
+    pub fn attach_room(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        if !self.home.attach_rooms {
+            return Ok(());
+        }
+
+        self.update_by_type(|bridge_home: &mut BridgeHome| {
+            bridge_home.children.insert(*link);
+        })
+    }
+
+    /// Remove room `link` from the bridge home
+    pub fn detach_room(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        self.update_by_type(|bridge_home: &mut BridgeHome| {
+            bridge_home.children.remove(link);
+        })
+    }
+
+    /// Remove all rooms from the bridge home, if rooms are not attached to it
+    fn detach_all_rooms(&mut self) -> ApiResult<()> {
+        if self.home.attach_rooms {
+            return Ok(());
+        }
+
+        let attached = self
+            .get_resource_ids_by_type(RType::BridgeHome)
+            .into_iter()
+            .filter_map(|id| self.get_id::<BridgeHome>(id).ok())
+            .flat_map(|bridge_home| &bridge_home.children)
+            .any(|child| child.rtype == RType::Room);
+
+        if !attached {
+            return Ok(());
+        }
+
+        self.update_by_type(|bridge_home: &mut BridgeHome| {
+            bridge_home
+                .children
+                .retain(|child| child.rtype != RType::Room);
+        })
+    }
+
+    /// The links in `links` that point to resources that no longer exist
+    fn dangling_links<'a>(
+        &self,
+        links: impl IntoIterator<Item = &'a ResourceLink>,
+    ) -> HashSet<ResourceLink> {
+        links
+            .into_iter()
+            .filter(|link| self.state.try_get(&link.rid).is_none())
+            .copied()
+            .collect()
+    }
+
+    /// Remove links to resources that no longer exist from the children and
+    /// services of the bridge home and all rooms, and from scene actions.
+    /// Returns the number of links removed.
+    pub fn prune_dangling_links(&mut self) -> ApiResult<usize> {
+        let mut pruned = 0;
+
+        for id in self.get_resource_ids_by_type(RType::BridgeHome) {
+            let home: &BridgeHome = self.get_id(id)?;
+            let stale = self.dangling_links(home.children.iter().chain(&home.services));
+            if stale.is_empty() {
+                continue;
+            }
+            log::warn!("Pruning dangling links from bridge home {id}: {stale:?}");
+            pruned += stale.len();
+            self.update(&id, |home: &mut BridgeHome| {
+                home.children.retain(|link| !stale.contains(link));
+                home.services.retain(|link| !stale.contains(link));
+            })?;
+        }
+
+        for id in self.get_resource_ids_by_type(RType::Room) {
+            let room: &Room = self.get_id(id)?;
+            let stale = self.dangling_links(room.children.iter().chain(&room.services));
+            if stale.is_empty() {
+                continue;
+            }
+            log::warn!("Pruning dangling links from room {id}: {stale:?}");
+            pruned += stale.len();
+            self.update(&id, |room: &mut Room| {
+                room.children.retain(|link| !stale.contains(link));
+                room.services.retain(|link| !stale.contains(link));
+            })?;
+        }
+
+        for id in self.get_resource_ids_by_type(RType::Scene) {
+            let scene: &Scene = self.get_id(id)?;
+            let stale = self.dangling_links(scene.actions.iter().map(|act| &act.target));
+            if stale.is_empty() {
+                continue;
+            }
+            log::warn!("Pruning actions for missing lights from scene {id}: {stale:?}");
+            pruned += stale.len();
+            self.update(&id, |scene: &mut Scene| {
+                scene.actions.retain(|act| !stale.contains(&act.target));
+            })?;
+        }
+
+        Ok(pruned)
+    }
+
+    #[must_use]
+    pub const fn revisions(&self) -> &Revisions {
+        &self.revisions
+    }
+
+    #[must_use]
+    pub const fn bridge_version(&self) -> &SwVersion {
+        &self.version
+    }
+
     pub fn update_bridge_version(&mut self, version: SwVersion) {
         self.version = version;
         self.state.patch_bridge_version(&self.version);
+        self.revisions.touch_all();
         self.state_updates.notify_one();
     }
 
@@ -79,6 +254,7 @@ impl Resources {
 
     pub fn read(&mut self, rdr: impl Read) -> ApiResult<()> {
         self.state = State::from_reader(rdr)?;
+        self.revisions.touch_all();
         Ok(())
     }
 
@@ -90,6 +266,19 @@ impl Resources {
         Ok(serde_yml::to_string(&self.state)?)
     }
 
+    /// The state database as json (the same content as [`Self::serialize`]),
+    /// without the paired users.
+    ///
+    /// Their usernames and client keys are the credentials of the paired
+    /// apps, so they are never exported.
+    pub fn export(&self) -> ApiResult<Value> {
+        let mut value = serde_json::to_value(&self.state)?;
+        if let Value::Object(obj) = &mut value {
+            obj.remove("users");
+        }
+        Ok(value)
+    }
+
     pub fn init(&mut self, bridge_id: &str) -> ApiResult<()> {
         self.add_bridge(bridge_id.to_owned())
     }
@@ -100,6 +289,7 @@ impl Resources {
     pub fn factory_reset(&mut self, bridge_id: &str) -> ApiResult<()> {
         self.state = State::new();
         self.add_bridge(bridge_id.to_owned())?;
+        self.revisions.touch_all();
         self.state_updates.notify_one();
         Ok(())
     }
@@ -111,6 +301,9 @@ impl Resources {
         let link_bridge = RType::Bridge.deterministic(bridge_id);
         let link_bridge_dev = RType::Device.deterministic(link_bridge.rid);
         let link_ic = RType::InternetConnectivity.deterministic(link_bridge.rid);
+        let link_swu = RType::DeviceSoftwareUpdate.deterministic(link_bridge.rid);
+        let link_taurus = RType::Taurus.deterministic(link_bridge.rid);
+        let link_auth = RType::AuthV1.deterministic(link_bridge.rid);
 
         // If the bridge device doesn't exist yet, there's nothing sensible to patch.
         if self.state.try_get(&link_bridge_dev.rid).is_none() {
@@ -126,13 +319,47 @@ impl Resources {
             self.add(&link_ic, Resource::InternetConnectivity(ic))?;
         }
 
-        // Ensure the bridge device advertises the service link too.
+        // Hue app uses this resource for the "update bridge" flow.
+        if self.state.try_get(&link_swu.rid).is_none() {
+            let swu = DeviceSoftwareUpdate::new(link_bridge_dev);
+            self.add(&link_swu, Resource::DeviceSoftwareUpdate(swu))?;
+        }
+
+        // Newer Hue app versions probe these, and get confused by empty lists.
+        if self.state.try_get(&link_taurus.rid).is_none() {
+            let taurus = Taurus::new(link_bridge_dev);
+            self.add(&link_taurus, Resource::Taurus(taurus))?;
+        }
+
+        // auth_v1 is not a resource on real bridges. Remove the one added by
+        // older versions.
+        if self.state.try_get(&link_auth.rid).is_some() {
+            self.delete(&link_auth)?;
+        }
+
+        // Ensure the bridge device advertises the service links too.
         self.try_update::<Device>(&link_bridge_dev.rid, |dev| {
             dev.services.insert(link_ic);
+            dev.services.insert(link_swu);
+            dev.services.insert(link_taurus);
             Ok(())
         })?;
 
-        Ok(())
+        let link_bridge_home = RType::BridgeHome.deterministic(format!("{bridge_id}HOME"));
+        let link_bridge_home_dev = RType::Device.deterministic(link_bridge_home.rid);
+        let name = self
+            .home
+            .name
+            .clone()
+            .filter(|_| self.state.try_get(&link_bridge_home_dev.rid).is_some());
+        if let Some(name) = name {
+            self.update::<Device>(&link_bridge_home_dev.rid, |dev| {
+                dev.metadata.name = name;
+            })?;
+        }
+
+        self.prune_dangling_links()?;
+        self.detach_all_rooms()
     }
 
     pub fn aux_get(&self, link: &ResourceLink) -> ApiResult<&AuxData> {
@@ -143,6 +370,112 @@ impl Resources {
         self.state.aux_set(link.rid, aux);
     }
 
+    /// Startup states of all lights that have one
+    #[must_use]
+    pub const fn light_startups(&self) -> &BTreeMap<Uuid, LightStartup> {
+        self.state.startups()
+    }
+
+    #[must_use]
+    pub fn light_startup(&self, link: &ResourceLink) -> Option<&LightStartup> {
+        self.state.startups().get(&link.rid)
+    }
+
+    /// Set (or with `None`, remove) the startup state of a light
+    pub fn set_light_startup(&mut self, link: &ResourceLink, startup: Option<LightStartup>) {
+        self.state.startup_set(link.rid, startup);
+        self.state_updates.notify_one();
+    }
+
+    #[must_use]
+    pub fn light_segment_map(&self, link: &ResourceLink) -> Option<&LightSegmentMap> {
+        self.state.segment_maps().get(&link.rid)
+    }
+
+    /// Set (or with `None`, remove) the segment order of a gradient light
+    pub fn set_light_segment_map(&mut self, link: &ResourceLink, map: Option<LightSegmentMap>) {
+        self.state.segment_map_set(link.rid, map);
+        self.state_updates.notify_one();
+    }
+
+    #[must_use]
+    pub const fn all_scene_variants(&self) -> &BTreeMap<Uuid, SceneVariants> {
+        self.state.scene_variants()
+    }
+
+    #[must_use]
+    pub fn scene_variants(&self, link: &ResourceLink) -> Option<&SceneVariants> {
+        self.state.scene_variants().get(&link.rid)
+    }
+
+    /// Set (or with `None`, remove) the time of day variants of a scene
+    pub fn set_scene_variants(&mut self, link: &ResourceLink, variants: Option<SceneVariants>) {
+        self.state.scene_variants_set(link.rid, variants);
+        self.state_updates.notify_one();
+    }
+
+    /// Is the presence simulation enabled? (kept across restarts)
+    #[must_use]
+    pub const fn presence_enabled(&self) -> bool {
+        self.state.presence_enabled()
+    }
+
+    pub fn set_presence_enabled(&mut self, enabled: bool) {
+        if self.state.presence_enabled() != enabled {
+            self.state.presence_set_enabled(enabled);
+            self.state_updates.notify_one();
+        }
+    }
+
+    #[must_use]
+    pub const fn motion_rules(&self) -> &BTreeMap<Uuid, MotionRule> {
+        self.state.rules()
+    }
+
+    /// Set (or with `None`, remove) motion rule `id`
+    pub fn set_motion_rule(&mut self, id: Uuid, rule: Option<MotionRule>) {
+        self.state.rule_set(id, rule);
+        self.state_updates.notify_one();
+    }
+
+    #[must_use]
+    pub const fn contact_rules(&self) -> &BTreeMap<Uuid, ContactRule> {
+        self.state.contact_rules()
+    }
+
+    /// Set (or with `None`, remove) contact rule `id`
+    pub fn set_contact_rule(&mut self, id: Uuid, rule: Option<ContactRule>) {
+        self.state.contact_rule_set(id, rule);
+        self.state_updates.notify_one();
+    }
+
+    /// Apps paired through the v1 api, by username
+    #[must_use]
+    pub const fn api_users(&self) -> &BTreeMap<String, ApiUser> {
+        self.state.users()
+    }
+
+    pub fn add_api_user(&mut self, username: String, user: ApiUser) {
+        self.state.user_add(username, user);
+        self.state_updates.notify_one();
+    }
+
+    /// Forget the paired app `username`, and with it, its streaming key
+    pub fn remove_api_user(&mut self, username: &str) -> Option<ApiUser> {
+        let user = self.state.user_remove(username)?;
+        self.state_updates.notify_one();
+        Some(user)
+    }
+
+    /// Forget all paired apps. Returns the number of removed users.
+    pub fn clear_api_users(&mut self) -> usize {
+        let removed = self.state.users_clear();
+        if removed > 0 {
+            self.state_updates.notify_one();
+        }
+        removed
+    }
+
     pub fn try_update<T: Serialize>(
         &mut self,
         id: &Uuid,
@@ -161,6 +494,13 @@ impl Resources {
         func(obj)?;
         let after = serde_json::to_value(&obj)?;
 
+        // any change (including removed keys, which are not part of the
+        // event delta) must give the resource type a new revision
+        if before != after {
+            self.revisions.touch(resource.rtype());
+            self.state_updates.notify_one();
+        }
+
         // if the function affected a meaningful difference, send an update event
         if let Some(delta) = hue::diff::event_update_diff(before, after)? {
             log::trace!("Hue event: {id_v1:?} {delta:#?}");
@@ -170,8 +510,6 @@ impl Resources {
                 resource.rtype(),
                 delta,
             )?);
-
-            self.state_updates.notify_one();
         }
 
         Ok(())
@@ -221,6 +559,27 @@ impl Resources {
             .collect()
     }
 
+    /// Light services in `zone`, either directly or through a device
+    pub fn get_zone_lights(&self, zone: &ResourceLink) -> HueResult<BTreeSet<ResourceLink>> {
+        let mut lights = BTreeSet::new();
+
+        for child in &self.get::<Zone>(zone)?.children {
+            match child.rtype {
+                RType::Light => {
+                    lights.insert(*child);
+                }
+                RType::Device => {
+                    if let Ok(dev) = self.get::<Device>(child) {
+                        lights.extend(dev.services.iter().filter(|svc| svc.rtype == RType::Light));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(lights)
+    }
+
     pub fn add(&mut self, link: &ResourceLink, obj: Resource) -> ApiResult<()> {
         assert!(
             link.rtype == obj.rtype(),
@@ -236,6 +595,7 @@ impl Resources {
 
         self.state.insert(link.rid, obj);
 
+        self.revisions.touch(link.rtype);
         self.state_updates.notify_one();
 
         let evt = EventBlock::add(vec![self.get_resource_by_id(&link.rid)?]);
@@ -247,6 +607,23 @@ impl Resources {
         Ok(())
     }
 
+    /// Register backend `owner` as the owner of a resource.
+    ///
+    /// Requests for this resource (and any resources it owns) are routed to
+    /// that backend. Fails if the resource is owned by another backend.
+    pub fn claim(&mut self, owner: &str, link: &ResourceLink) -> ApiResult<()> {
+        self.owners.claim(link, owner)
+    }
+
+    /// Add resource owned by backend `owner` (see [`Self::claim`]).
+    ///
+    /// The ownership is registered even if the resource is already known (e.g.
+    /// loaded from the state file).
+    pub fn add_owned(&mut self, owner: &str, link: &ResourceLink, obj: Resource) -> ApiResult<()> {
+        self.claim(owner, link)?;
+        self.add(link, obj)
+    }
+
     pub fn delete(&mut self, link: &ResourceLink) -> ApiResult<()> {
         log::info!("Deleting {link:?}..");
 
@@ -279,11 +656,16 @@ impl Resources {
             zone.services.remove(link);
         })?;
 
+        self.update_by_type(|scene: &mut Scene| {
+            scene.actions.retain(|act| act.target != *link);
+        })?;
+
         // Get id_v1 before deleting
         let id_v1 = self.id_v1_scope(&link.rid, self.state.get(&link.rid)?);
 
         // Remove resource from state database
         self.state.remove(&link.rid)?;
+        self.owners.release(&link.rid);
 
         // Find ids of all resources owned by the deleted node
         let owned_by = self
@@ -304,6 +686,7 @@ impl Resources {
             self.delete(&owned)?;
         }
 
+        self.revisions.touch(link.rtype);
         self.state_updates.notify_one();
 
         let evt = EventBlock::delete(*link, id_v1)?;
@@ -322,12 +705,22 @@ impl Resources {
         let link_zbdd = RType::ZigbeeDeviceDiscovery.deterministic(link_bridge.rid);
         let link_zbc = RType::ZigbeeConnectivity.deterministic(link_bridge.rid);
         let link_ic = RType::InternetConnectivity.deterministic(link_bridge.rid);
+        let link_swu = RType::DeviceSoftwareUpdate.deterministic(link_bridge.rid);
+        let link_taurus = RType::Taurus.deterministic(link_bridge.rid);
         let link_bhome_glight = RType::GroupedLight.deterministic(link_bridge_home.rid);
 
         let bridge_dev = Device {
             product_data: DeviceProductData::hue_bridge_v2(&self.version),
             metadata: Metadata::new(DeviceArchetype::BridgeV2, "Bifrost"),
-            services: btreeset![link_bridge, link_zbc, link_ic, link_bridge_ent, link_zbdd],
+            services: btreeset![
+                link_bridge,
+                link_zbc,
+                link_ic,
+                link_swu,
+                link_bridge_ent,
+                link_zbdd,
+                link_taurus
+            ],
             identify: Some(Stub),
             usertest: None,
         };
@@ -340,7 +733,10 @@ impl Resources {
 
         let bridge_home_dev = Device {
             product_data: DeviceProductData::hue_bridge_v2(&self.version),
-            metadata: Metadata::new(DeviceArchetype::BridgeV2, "Bifrost Bridge Home"),
+            metadata: Metadata::new(
+                DeviceArchetype::BridgeV2,
+                self.home_name().unwrap_or("Bifrost Bridge Home"),
+            ),
             services: btreeset![link_bridge],
             identify: None,
             usertest: None,
@@ -402,6 +798,10 @@ impl Resources {
             status: InternetConnectivityStatus::Connected,
         };
 
+        let swu = DeviceSoftwareUpdate::new(link_bridge_dev);
+
+        let taurus = Taurus::new(link_bridge_dev);
+
         let brent = Entertainment {
             equalizer: false,
             owner: link_bridge_dev,
@@ -419,6 +819,8 @@ impl Resources {
         self.add(&link_zbdd, Resource::ZigbeeDeviceDiscovery(zbdd))?;
         self.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
         self.add(&link_ic, Resource::InternetConnectivity(ic))?;
+        self.add(&link_swu, Resource::DeviceSoftwareUpdate(swu))?;
+        self.add(&link_taurus, Resource::Taurus(taurus))?;
         self.add(&link_bridge_ent, Resource::Entertainment(brent))?;
         self.add(&link_bhome_glight, Resource::GroupedLight(bhome_glight))?;
 
@@ -614,6 +1016,31 @@ impl Resources {
             .collect()
     }
 
+    /// Resources referenced by resource `id` (the services of a device, or
+    /// the children and services of a room, zone or bridge home), whether
+    /// they exist or not
+    #[must_use]
+    pub fn get_linked_resources(&self, id: &Uuid) -> Vec<ResourceLink> {
+        match self.state.get(id) {
+            Ok(Resource::Device(dev)) => dev.services.iter().copied().collect(),
+            Ok(Resource::Room(room)) => room.children.union(&room.services).copied().collect(),
+            Ok(Resource::Zone(zone)) => zone.children.union(&zone.services).copied().collect(),
+            Ok(Resource::BridgeHome(home)) => {
+                home.children.union(&home.services).copied().collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The room that `device` is assigned to (if any)
+    #[must_use]
+    pub fn device_room(&self, device: &ResourceLink) -> Option<&Room> {
+        self.state.res.values().find_map(|res| match res {
+            Resource::Room(room) if room.children.contains(device) => Some(room),
+            _ => None,
+        })
+    }
+
     pub fn get_id_v1_index(&self, uuid: Uuid) -> HueResult<u32> {
         self.state.id_v1(&uuid).ok_or(HueError::NotFound(uuid))
     }
@@ -636,18 +1063,1046 @@ impl Resources {
         &self.hue_event_stream
     }
 
+    /// Find the backend owning resource `id`, by following the owner links
+    /// (and the room of scenes) up to the resource claimed by a backend.
+    ///
+    /// Returns the id of the claimed resource, and the id of the backend.
     #[must_use]
-    pub fn backend_event_stream(&self) -> Receiver<Arc<BackendRequest>> {
-        self.backend_updates.subscribe()
+    pub fn backend_owner(&self, id: &Uuid) -> Option<(Uuid, &str)> {
+        let mut id = *id;
+
+        for _ in 0..Self::MAX_OWNER_DEPTH {
+            if let Some(owner) = self.owners.get(&id) {
+                return Some((id, owner));
+            }
+
+            id = match self.state.get(&id).ok()? {
+                Resource::Scene(scene) => scene.group.rid,
+                res => res.owner()?.rid,
+            };
+        }
+
+        None
+    }
+
+    /// Backends other than `owner`, that have devices in the room of grouped
+    /// light `link` (i.e. rooms merged across backends)
+    fn room_guests(&self, link: &ResourceLink, owner: &str) -> BTreeSet<String> {
+        let Some(room) = self
+            .get::<GroupedLight>(link)
+            .ok()
+            .and_then(|grouped| self.get::<Room>(&grouped.owner).ok())
+        else {
+            return BTreeSet::new();
+        };
+
+        room.children
+            .iter()
+            .filter_map(|device| self.backend_owner(&device.rid))
+            .map(|(_, id)| id)
+            .filter(|id| *id != owner)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// True if grouped light `link` belongs to a room merged across backends
+    /// (see [`Self::refresh_merged_room`])
+    #[must_use]
+    pub fn is_merged_room(&self, link: &ResourceLink) -> bool {
+        self.backend_owner(&link.rid)
+            .is_some_and(|(_, owner)| !self.room_guests(link, owner).is_empty())
+    }
+
+    /// Update the grouped light of the merged room holding light `id` (if
+    /// any).
+    ///
+    /// Rooms merged across backends hold lights of each of them, so no single
+    /// backend knows the state of the room. Instead of each backend writing
+    /// its part, their state is derived here from all of their lights.
+    pub fn refresh_merged_room(&mut self, id: &Uuid) -> ApiResult<()> {
+        let Some(room) = self
+            .get_id::<Light>(*id)
+            .ok()
+            .and_then(|light| self.device_room(&light.owner))
+        else {
+            return Ok(());
+        };
+
+        let Some(glight) = room.grouped_light_service().copied() else {
+            return Ok(());
+        };
+        if !self.is_merged_room(&glight) {
+            return Ok(());
+        }
+
+        let lights: Vec<&Light> = room
+            .children
+            .iter()
+            .filter_map(|dev| self.get::<Device>(dev).ok())
+            .filter_map(|dev| dev.service(RType::Light))
+            .filter_map(|link| self.get::<Light>(link).ok())
+            .collect();
+
+        let (on, dimming) = group_state(&lights);
+        self.update::<GroupedLight>(&glight.rid, |grouped| {
+            grouped.on = Some(on);
+            grouped.dimming = dimming;
+        })
+    }
+
+    /// Update the grouped light of `zone` from the state of its lights.
+    ///
+    /// Zones are not owned by a backend, so nothing reports their state.
+    pub fn refresh_zone(&mut self, zone: &ResourceLink) -> ApiResult<()> {
+        let Some(glight) = self.get::<Zone>(zone)?.grouped_light_service().copied() else {
+            return Ok(());
+        };
+
+        let links = self.get_zone_lights(zone)?;
+        let lights: Vec<&Light> = links
+            .iter()
+            .filter_map(|link| self.get::<Light>(link).ok())
+            .collect();
+
+        let (on, dimming) = group_state(&lights);
+        self.update::<GroupedLight>(&glight.rid, |grouped| {
+            grouped.on = Some(on);
+            grouped.dimming = dimming;
+        })
     }
 
+    /// Update the grouped lights of all zones holding light `id`
+    pub fn refresh_zones(&mut self, id: &Uuid) -> ApiResult<()> {
+        let light = ResourceLink::new(*id, RType::Light);
+        for zone in self.get_resource_ids_by_type(RType::Zone) {
+            let zone = ResourceLink::new(zone, RType::Zone);
+            if self.get_zone_lights(&zone)?.contains(&light) {
+                self.refresh_zone(&zone)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the grouped lights derived from light `id`, after its state
+    /// changed: those of a merged room, and of zones (see
+    /// [`Self::refresh_merged_room`] and [`Self::refresh_zone`])
+    pub fn refresh_light_groups(&mut self, id: &Uuid) -> ApiResult<()> {
+        self.refresh_merged_room(id)?;
+        self.refresh_zones(id)
+    }
+
+    /// Rooms sharing their name (ignoring case) with a room of another backend
+    #[must_use]
+    pub fn duplicate_rooms(&self) -> Vec<RoomDuplicate> {
+        let mut by_name: BTreeMap<String, RoomDuplicate> = BTreeMap::new();
+
+        for id in self.get_resource_ids_by_type(RType::Room) {
+            let Ok(room) = self.get_id::<Room>(id) else {
+                continue;
+            };
+            let name = room.metadata.name.trim();
+            by_name
+                .entry(name.to_lowercase())
+                .or_insert_with(|| RoomDuplicate {
+                    name: name.to_string(),
+                    rooms: vec![],
+                })
+                .rooms
+                .push(RoomOwner {
+                    room: RType::Room.link_to(id),
+                    backend: self.owners.get(&id).map(ToString::to_string),
+                });
+        }
+
+        by_name
+            .into_values()
+            .filter(|dup| dup.rooms.iter().map(|room| &room.backend).unique().count() > 1)
+            .map(|mut dup| {
+                dup.rooms.sort_by_key(|room| room.room.rid);
+                dup
+            })
+            .collect()
+    }
+
+    /// Subscribe to requests for backend `id`, which implements `capabilities`
+    pub fn backend_event_stream(
+        &mut self,
+        id: &str,
+        capabilities: BackendCapabilities,
+    ) -> Receiver<Arc<BackendRequest>> {
+        self.backends.subscribe(id, capabilities)
+    }
+
+    /// Subscribe to Home Assistant requests for backend `id`
+    pub fn hass_event_stream(&mut self, id: &str) -> Receiver<Arc<HassRequest>> {
+        self.backends.subscribe_hass(id)
+    }
+
+    /// Record that backend `id` fell behind, and skipped `count` requests
+    pub fn backend_lagged(&mut self, id: &str, count: u64) {
+        self.backends.record_lag(id, count);
+    }
+
+    /// Features of each backend
+    #[must_use]
+    pub const fn backend_capabilities(&self) -> &BTreeMap<String, BackendCapabilities> {
+        self.backends.capabilities()
+    }
+
+    /// Number of requests skipped by each backend, because it fell behind
+    #[must_use]
+    pub const fn backend_lag(&self) -> &BTreeMap<String, u64> {
+        self.backends.lagged()
+    }
+
+    /// Subscribe to a copy of all backend requests, regardless of target
+    #[must_use]
+    pub fn backend_monitor_stream(&self) -> Receiver<Arc<BackendRequest>> {
+        self.backends.monitor()
+    }
+
+    /// Subscribe to state changing requests, and state reports from backends
+    #[must_use]
+    pub fn backend_latency_stream(&self) -> Receiver<LatencySignal> {
+        self.backends.latency()
+    }
+
+    /// Record that a backend reported the actual state of resource `id`
+    /// (e.g., as received from the device, or from Home Assistant)
+    pub fn backend_report(&self, id: &Uuid) {
+        self.backends.signal(LatencySignal::Reported(*id));
+    }
+
+    /// True if `req` would change the state of its target. Requests that do
+    /// not are never confirmed by a state report, so they are not measured.
+    fn changes_state(&self, req: &BackendRequest) -> bool {
+        match req {
+            BackendRequest::LightUpdate(link, upd) => self.get::<Light>(link).is_ok_and(|light| {
+                let mut updated = light.clone();
+                updated += upd;
+                updated != *light
+            }),
+            BackendRequest::GroupedLightUpdate(link, upd) => {
+                self.get::<GroupedLight>(link).is_ok_and(|glight| {
+                    upd.on.is_some_and(|on| Some(on) != glight.on)
+                        || upd.dimming.is_some_and(|dim| Some(dim) != glight.dimming)
+                        || upd.color.is_some()
+                        || upd.color_temperature.is_some()
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// Send request to the backend owning the target resource, or to all
+    /// backends if the request has no target.
     pub fn backend_request(&self, req: BackendRequest) -> ApiResult<()> {
         if !matches!(req, BackendRequest::EntertainmentFrame(_)) {
             log::debug!("Backend request: {req:#?}");
         }
 
-        self.backend_updates.send(Arc::new(req))?;
+        let activity = self.activity(&req);
+
+        let Some(target) = req.target() else {
+            return self.backends.broadcast(req);
+        };
+
+        let Some((_, owner)) = self.backend_owner(&target.rid) else {
+            // e.g. resources restored from the state file, before their
+            // backend has reclaimed them
+            log::warn!("No backend owns {target:?}, dropping request");
+            return Err(ApiError::NoBackendOwner(target));
+        };
+
+        if let BackendRequest::GroupedLightUpdate(link, _) = &req {
+            for guest in self.room_guests(link, owner) {
+                if let Err(err) = self.backends.send(&guest, req.clone()) {
+                    log::warn!("Failed to forward grouped light update to {guest}: {err}");
+                }
+            }
+        }
+
+        let changes_state = self.changes_state(&req);
+
+        self.backends.send(owner, req)?;
+
+        if changes_state {
+            self.backends.signal(LatencySignal::Sent {
+                id: target.rid,
+                backend: owner.to_string(),
+            });
+        }
+
+        if let Some(activity) = activity {
+            self.forward_activity(activity);
+        }
 
         Ok(())
     }
+
+    /// The scene to recall for `link`: for active and static recalls, the
+    /// variant configured for `time` (if any), otherwise `link` itself
+    #[must_use]
+    pub fn scene_variant(
+        &self,
+        link: ResourceLink,
+        upd: &SceneUpdate,
+        time: TimeOfDay,
+    ) -> ResourceLink {
+        // dynamic palettes are not redirected, since the variant may not
+        // have a palette
+        let recall = upd.recall.as_ref().and_then(|recall| recall.action);
+        if !matches!(
+            recall,
+            Some(SceneStatusEnum::Active | SceneStatusEnum::Static)
+        ) {
+            return link;
+        }
+
+        let Some(variant) = self
+            .scene_variants(&link)
+            .and_then(|variants| variants.get(time))
+            .copied()
+        else {
+            return link;
+        };
+
+        // variants pointing to deleted scenes are ignored
+        if self.get::<Scene>(&variant).is_err() {
+            log::warn!("Scene variant {variant:?} of {link:?} does not exist");
+            return link;
+        }
+
+        log::debug!("Recalling {time:?} variant {variant:?} instead of scene {link:?}");
+        variant
+    }
+
+    /// The scene to recall for `link` right now (see [`Self::scene_variant`])
+    #[must_use]
+    pub fn current_scene_variant(&self, link: ResourceLink, upd: &SceneUpdate) -> ResourceLink {
+        self.scene_variant(link, upd, self.time_of_day.at(Local::now().hour()))
+    }
+
+    fn room_name(&self, link: &ResourceLink) -> Option<String> {
+        self.get::<Room>(link)
+            .ok()
+            .map(|room| room.metadata.name.clone())
+    }
+
+    /// The activity (if any) a backend request represents
+    fn activity(&self, req: &BackendRequest) -> Option<BifrostActivity> {
+        match req {
+            BackendRequest::SceneUpdate(link, upd) => {
+                upd.recall.as_ref().and_then(|recall| recall.action)?;
+                let scene = self.get::<Scene>(link).ok()?;
+                Some(BifrostActivity::SceneRecall {
+                    scene: *link,
+                    name: scene.metadata.name.clone(),
+                    room: self.room_name(&scene.group),
+                })
+            }
+            BackendRequest::GroupedLightUpdate(link, upd) => {
+                let on = upd.on?.on;
+                let glight = self.get::<GroupedLight>(link).ok()?;
+                Some(BifrostActivity::GroupedLightToggle {
+                    grouped_light: *link,
+                    room: self.room_name(&glight.owner),
+                    on,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Forward activity to Home Assistant backends (if any)
+    pub fn forward_activity(&self, activity: BifrostActivity) {
+        if let Err(err) = self
+            .backends
+            .broadcast_hass(HassRequest::ForwardActivity(activity))
+        {
+            log::trace!("Activity not forwarded: {err}");
+        }
+    }
+
+    /// Send request to all Home Assistant backends
+    pub fn hass_request(&self, req: HassRequest) -> ApiResult<()> {
+        log::debug!("Home Assistant request: {req:?}");
+
+        self.backends.broadcast_hass(req)
+    }
+}
+
+/// State of a group of `lights`: on if any of them is on, with the average
+/// brightness of the lights that are on (or of all lights, if none are)
+fn group_state(lights: &[&Light]) -> (On, Option<DimmingUpdate>) {
+    let any_on = lights.iter().any(|light| light.on.on);
+    let brightness: Vec<f64> = lights
+        .iter()
+        .filter(|light| light.on.on || !any_on)
+        .filter_map(|light| light.dimming.map(|dim| dim.brightness))
+        .collect();
+    let dimming = (!brightness.is_empty()).then(|| {
+        let count = u32::try_from(brightness.len()).map_or(1.0, f64::from);
+        DimmingUpdate::new(brightness.iter().sum::<f64>() / count)
+    });
+
+    (On::new(any_on), dimming)
+}
+
+#[cfg(test)]
+mod tests {
+    use bifrost_api::backend::{BackendCapabilities, BackendRequest};
+    use bifrost_api::config::{BridgeHomeConfig, TimeOfDay};
+    use bifrost_api::light::{LightSegmentMap, LightStartup};
+    use bifrost_api::scene::SceneVariants;
+    use std::collections::BTreeSet;
+
+    use hue::api::{
+        BridgeHome, Device, DeviceArchetype, Dimming, GroupedLight, GroupedLightUpdate, Light,
+        LightMetadata, LightUpdate, On, RType, Resource, ResourceLink, Room, RoomArchetype,
+        RoomMetadata, Scene, SceneActive, SceneStatus, SceneUpdate, Zone,
+    };
+    use hue::error::HueError;
+    use hue::version::SwVersion;
+    use serde_json::json;
+
+    use crate::error::ApiError;
+    use crate::model::state::{ApiUser, AuxData, State};
+    use crate::resource::Resources;
+
+    fn resources() -> Resources {
+        Resources::new(SwVersion::default(), State::new())
+    }
+
+    fn add_scene(res: &mut Resources, room: &ResourceLink) -> (ResourceLink, u32) {
+        let index = res.get_next_scene_id(room).unwrap();
+        let link = RType::Scene.deterministic((room.rid, index));
+
+        let scene: Scene = serde_json::from_value(json!({
+            "actions": [],
+            "group": room,
+            "metadata": {"name": format!("Scene {index}")},
+            "status": null,
+        }))
+        .unwrap();
+
+        res.aux_set(&link, AuxData::new().with_index(index));
+        res.add(&link, Resource::Scene(scene)).unwrap();
+
+        (link, index)
+    }
+
+    #[test]
+    fn next_scene_id_empty_room() {
+        let res = resources();
+        let room = RType::Room.deterministic("room");
+
+        assert_eq!(res.get_next_scene_id(&room).unwrap(), 0);
+    }
+
+    #[test]
+    fn next_scene_id_sequential() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+
+        let ids: Vec<u32> = (0..5).map(|_| add_scene(&mut res, &room).1).collect();
+
+        assert_eq!(ids, [0, 1, 2, 3, 4]);
+        assert_eq!(res.get_next_scene_id(&room).unwrap(), 5);
+    }
+
+    #[test]
+    fn next_scene_id_per_room() {
+        let mut res = resources();
+        let room1 = RType::Room.deterministic("room1");
+        let room2 = RType::Room.deterministic("room2");
+
+        add_scene(&mut res, &room1);
+        add_scene(&mut res, &room1);
+
+        assert_eq!(res.get_next_scene_id(&room2).unwrap(), 0);
+    }
+
+    #[test]
+    fn next_scene_id_reuses_deleted() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+
+        let scenes: Vec<_> = (0..4).map(|_| add_scene(&mut res, &room)).collect();
+
+        res.delete(&scenes[1].0).unwrap();
+        assert!(res.aux_get(&scenes[1].0).is_err());
+        assert_eq!(res.get_next_scene_id(&room).unwrap(), 1);
+
+        // lowest free index is reused first
+        res.delete(&scenes[0].0).unwrap();
+        assert_eq!(res.get_next_scene_id(&room).unwrap(), 0);
+    }
+
+    #[test]
+    fn next_scene_id_churn() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+
+        let mut live: Vec<_> = (0..3).map(|_| add_scene(&mut res, &room)).collect();
+
+        // repeated create/delete never leaks indices
+        for round in 0..50 {
+            let (link, _) = live.remove(round % live.len());
+            res.delete(&link).unwrap();
+
+            let (link, index) = add_scene(&mut res, &room);
+            assert!(index < 3, "index {index} leaked in round {round}");
+            live.push((link, index));
+        }
+
+        let mut indices: Vec<u32> = live.iter().map(|(_, index)| *index).collect();
+        indices.sort_unstable();
+        assert_eq!(indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn next_scene_id_full() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+
+        for _ in 0..Resources::MAX_SCENE_ID {
+            add_scene(&mut res, &room);
+        }
+
+        assert!(matches!(
+            res.get_next_scene_id(&room),
+            Err(HueError::Full(RType::Scene))
+        ));
+    }
+
+    fn recall(active: SceneActive) -> SceneUpdate {
+        SceneUpdate::new().with_recall_action(Some(SceneStatus {
+            active,
+            last_recall: None,
+        }))
+    }
+
+    #[test]
+    fn scene_variant_redirects_recall() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+
+        let (scene, _) = add_scene(&mut res, &room);
+        let (evening, _) = add_scene(&mut res, &room);
+
+        let variants = SceneVariants {
+            evening: Some(evening),
+            ..SceneVariants::default()
+        };
+        res.set_scene_variants(&scene, Some(variants));
+
+        let upd = recall(SceneActive::Static);
+        assert_eq!(res.scene_variant(scene, &upd, TimeOfDay::Evening), evening);
+
+        // no variant for this time of day
+        assert_eq!(res.scene_variant(scene, &upd, TimeOfDay::Morning), scene);
+
+        // only active and static recalls are redirected
+        let upd = recall(SceneActive::DynamicPalette);
+        assert_eq!(res.scene_variant(scene, &upd, TimeOfDay::Evening), scene);
+
+        let upd = SceneUpdate::new();
+        assert_eq!(res.scene_variant(scene, &upd, TimeOfDay::Evening), scene);
+    }
+
+    #[test]
+    fn scene_variant_ignores_deleted() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+
+        let (scene, _) = add_scene(&mut res, &room);
+        let (night, _) = add_scene(&mut res, &room);
+
+        let variants = SceneVariants {
+            night: Some(night),
+            ..SceneVariants::default()
+        };
+        res.set_scene_variants(&scene, Some(variants));
+        res.delete(&night).unwrap();
+
+        let upd = recall(SceneActive::Static);
+        assert_eq!(res.scene_variant(scene, &upd, TimeOfDay::Night), scene);
+
+        // variants of a deleted scene are removed with it
+        res.delete(&scene).unwrap();
+        assert!(res.all_scene_variants().is_empty());
+    }
+
+    #[test]
+    fn light_startup_removed_with_light() {
+        let mut res = resources();
+        let device = RType::Device.deterministic("device");
+        let link = RType::Light.deterministic("light");
+
+        let light = Light::new(
+            device,
+            LightMetadata::new(DeviceArchetype::SultanBulb, "Light"),
+        );
+        res.add(&link, Resource::Light(light)).unwrap();
+
+        let startup = LightStartup {
+            brightness: Some(40.0),
+            mirek: Some(300),
+        };
+        res.set_light_startup(&link, Some(startup.clone()));
+        assert_eq!(res.light_startup(&link), Some(&startup));
+
+        res.delete(&link).unwrap();
+
+        assert_eq!(res.light_startup(&link), None);
+        assert!(res.light_startups().is_empty());
+    }
+
+    #[test]
+    fn light_segment_map_removed_with_light() {
+        let mut res = resources();
+        let device = RType::Device.deterministic("device");
+        let link = RType::Light.deterministic("light");
+
+        let light = Light::new(
+            device,
+            LightMetadata::new(DeviceArchetype::HueLightstrip, "Gradient"),
+        );
+        res.add(&link, Resource::Light(light)).unwrap();
+
+        let map = LightSegmentMap {
+            segments: vec![6, 5, 4, 3, 2, 1, 0],
+        };
+        res.set_light_segment_map(&link, Some(map.clone()));
+        assert_eq!(res.light_segment_map(&link), Some(&map));
+        assert_eq!(map.segment(1), 5);
+        assert_eq!(map.segment(9), 9);
+
+        res.delete(&link).unwrap();
+
+        assert_eq!(res.light_segment_map(&link), None);
+    }
+
+    #[test]
+    fn update_removing_key_touches_revision() {
+        let mut res = resources();
+        let device = RType::Device.deterministic("device");
+        let link = RType::Light.deterministic("light");
+
+        let mut metadata = LightMetadata::new(DeviceArchetype::SultanBulb, "Light");
+        metadata.fixed_mired = Some(366);
+        res.add(&link, Resource::Light(Light::new(device, metadata)))
+            .unwrap();
+
+        let before = res.revisions().of_type(RType::Light);
+        res.update::<Light>(&link.rid, |light| light.metadata.fixed_mired = None)
+            .unwrap();
+
+        assert!(res.revisions().of_type(RType::Light) > before);
+    }
+
+    #[test]
+    fn noop_requests_do_not_change_state() {
+        let mut res = resources();
+        let device = RType::Device.deterministic("device");
+        let link = RType::Light.deterministic("light");
+
+        let light = Light::new(
+            device,
+            LightMetadata::new(DeviceArchetype::SultanBulb, "Light"),
+        );
+        let on = light.on.on;
+        res.add(&link, Resource::Light(light)).unwrap();
+
+        let same = BackendRequest::LightUpdate(link, LightUpdate::new().with_on(On::new(on)));
+        let toggle = BackendRequest::LightUpdate(link, LightUpdate::new().with_on(On::new(!on)));
+
+        assert!(!res.changes_state(&same));
+        assert!(res.changes_state(&toggle));
+    }
+
+    #[test]
+    fn duplicate_rooms_across_backends() {
+        let mut res = resources();
+        let add_room = |res: &mut Resources, key: &str, name: &str, owner: &str| {
+            let link = RType::Room.deterministic(key);
+            let room = Room {
+                children: BTreeSet::new(),
+                metadata: RoomMetadata::new(RoomArchetype::Home, name),
+                services: BTreeSet::new(),
+            };
+            res.add_owned(owner, &link, Resource::Room(room)).unwrap();
+            link
+        };
+
+        let z2m = add_room(&mut res, "z2m", "Kitchen", "z2m@default");
+        let hass = add_room(&mut res, "hass", "kitchen ", "hass@default");
+        add_room(&mut res, "z2m-a", "Office", "z2m@default");
+        add_room(&mut res, "z2m-b", "Office", "z2m@default");
+
+        let dups = res.duplicate_rooms();
+        assert_eq!(dups.len(), 1);
+
+        let mut expected = vec![z2m, hass];
+        expected.sort_by_key(|link| link.rid);
+        let rooms = dups[0]
+            .rooms
+            .iter()
+            .map(|room| room.room)
+            .collect::<Vec<_>>();
+        assert_eq!(rooms, expected);
+    }
+
+    #[test]
+    fn bridge_home_room_bookkeeping() {
+        let bridge_id = "0017880000000000";
+        let home = RType::BridgeHome.deterministic(format!("{bridge_id}HOME"));
+        let room = RType::Room.deterministic("room");
+
+        let mut res = resources();
+        res.init(bridge_id).unwrap();
+        res.attach_room(&room).unwrap();
+        assert!(
+            res.get::<BridgeHome>(&home)
+                .unwrap()
+                .children
+                .contains(&room)
+        );
+
+        // the room was never added, so it is pruned
+        res.ensure_core_bridge_resources(bridge_id).unwrap();
+        assert!(
+            !res.get::<BridgeHome>(&home)
+                .unwrap()
+                .children
+                .contains(&room)
+        );
+
+        let mut res = resources().with_home(BridgeHomeConfig {
+            name: Some("Home".to_string()),
+            attach_rooms: false,
+        });
+        res.init(bridge_id).unwrap();
+        res.attach_room(&room).unwrap();
+        assert!(
+            !res.get::<BridgeHome>(&home)
+                .unwrap()
+                .children
+                .contains(&room)
+        );
+        assert_eq!(res.home_name(), Some("Home"));
+    }
+
+    #[test]
+    fn prune_dangling_links() {
+        let bridge_id = "0017880000000000";
+        let home = RType::BridgeHome.deterministic(format!("{bridge_id}HOME"));
+        let device = RType::Device.deterministic("device");
+        let glight = RType::GroupedLight.deterministic("glight");
+        let room = RType::Room.deterministic("room");
+
+        let mut res = resources();
+        res.init(bridge_id).unwrap();
+        res.add(
+            &room,
+            Resource::Room(Room {
+                children: BTreeSet::from([device]),
+                metadata: RoomMetadata::new(RoomArchetype::Home, "Room"),
+                services: BTreeSet::from([glight]),
+            }),
+        )
+        .unwrap();
+        res.update::<BridgeHome>(&home.rid, |bridge_home| {
+            bridge_home.children.insert(device);
+        })
+        .unwrap();
+
+        assert_eq!(res.prune_dangling_links().unwrap(), 3);
+        let room = res.get::<Room>(&room).unwrap();
+        assert!(room.children.is_empty());
+        assert!(room.services.is_empty());
+        assert!(
+            !res.get::<BridgeHome>(&home)
+                .unwrap()
+                .children
+                .contains(&device)
+        );
+
+        // nothing left to prune
+        assert_eq!(res.prune_dangling_links().unwrap(), 0);
+    }
+
+    #[test]
+    fn linked_resources_of_device() {
+        let mut res = resources();
+        let device = RType::Device.deterministic("device");
+        let light = RType::Light.deterministic("light");
+        let missing = RType::Motion.deterministic("motion");
+
+        let dev: Device = serde_json::from_value(json!({
+            "product_data": {
+                "model_id": "test",
+                "manufacturer_name": "test",
+                "product_name": "test",
+                "product_archetype": "sultan_bulb",
+                "certified": false,
+                "software_version": "1.0.0",
+            },
+            "metadata": {"name": "Device", "archetype": "sultan_bulb"},
+            "services": [light, missing],
+        }))
+        .unwrap();
+        res.add(&device, Resource::Device(dev)).unwrap();
+
+        let mut linked = res.get_linked_resources(&device.rid);
+        linked.sort();
+        let mut expected = vec![light, missing];
+        expected.sort();
+
+        assert_eq!(linked, expected);
+        assert!(res.get_linked_resources(&light.rid).is_empty());
+    }
+
+    #[test]
+    fn delete_light_removes_scene_actions() {
+        let mut res = resources();
+        let room = RType::Room.deterministic("room");
+        let light = RType::Light.deterministic("light");
+        let other = RType::Light.deterministic("other");
+
+        for link in [light, other] {
+            let obj = Light::new(
+                RType::Device.deterministic(link.rid),
+                LightMetadata::new(DeviceArchetype::SultanBulb, "Light"),
+            );
+            res.add(&link, Resource::Light(obj)).unwrap();
+        }
+
+        let (scene, _) = add_scene(&mut res, &room);
+        res.update::<Scene>(&scene.rid, |scene| {
+            scene.actions = serde_json::from_value(json!([
+                {"target": light, "action": {"on": {"on": true}}},
+                {"target": other, "action": {"on": {"on": false}}},
+            ]))
+            .unwrap();
+        })
+        .unwrap();
+
+        res.delete(&light).unwrap();
+
+        let targets = res
+            .get::<Scene>(&scene)
+            .unwrap()
+            .actions
+            .iter()
+            .map(|act| act.target)
+            .collect::<Vec<_>>();
+        assert_eq!(targets, [other]);
+    }
+
+    #[test]
+    fn zone_lights_and_removal() {
+        let mut res = resources();
+        let zone = RType::Zone.deterministic("zone");
+        let glight = RType::GroupedLight.deterministic("zone");
+        let device = RType::Device.deterministic("device");
+        let light = RType::Light.deterministic("light");
+        let device_light = RType::Light.deterministic("device_light");
+
+        let dev: Device = serde_json::from_value(json!({
+            "product_data": {
+                "model_id": "test",
+                "manufacturer_name": "test",
+                "product_name": "test",
+                "product_archetype": "sultan_bulb",
+                "certified": false,
+                "software_version": "1.0.0",
+            },
+            "metadata": {"name": "Device", "archetype": "sultan_bulb"},
+            "services": [device_light],
+        }))
+        .unwrap();
+        res.add(&device, Resource::Device(dev)).unwrap();
+
+        let obj = Light::new(
+            RType::Device.deterministic("other"),
+            LightMetadata::new(DeviceArchetype::SultanBulb, "Light"),
+        );
+        res.add(&light, Resource::Light(obj)).unwrap();
+
+        let obj = Zone {
+            children: BTreeSet::from([device, light]),
+            metadata: RoomMetadata::new(RoomArchetype::Home, "Zone"),
+            services: BTreeSet::from([glight]),
+        };
+        res.add(&zone, Resource::Zone(obj)).unwrap();
+        res.add(&glight, Resource::GroupedLight(GroupedLight::new(zone)))
+            .unwrap();
+
+        assert_eq!(
+            res.get_zone_lights(&zone).unwrap(),
+            BTreeSet::from([light, device_light])
+        );
+
+        // deleted lights leave the zone
+        res.delete(&light).unwrap();
+        assert_eq!(
+            res.get_zone_lights(&zone).unwrap(),
+            BTreeSet::from([device_light])
+        );
+
+        // the grouped light is deleted with the zone
+        res.delete(&zone).unwrap();
+        assert!(res.get_resource(&glight).is_err());
+    }
+
+    #[test]
+    fn backend_request_without_owner_fails() {
+        let mut res = resources();
+        let mut z2m = res.backend_event_stream("z2m", BackendCapabilities::default());
+        let mut hass = res.backend_event_stream("hass", BackendCapabilities::default());
+
+        let owned = RType::Light.deterministic("owned");
+        let unowned = RType::Light.deterministic("unowned");
+        res.claim("z2m", &owned).unwrap();
+
+        res.backend_request(BackendRequest::LightUpdate(owned, LightUpdate::default()))
+            .unwrap();
+        assert!(matches!(
+            *z2m.try_recv().unwrap(),
+            BackendRequest::LightUpdate(link, _) if link == owned
+        ));
+        assert!(hass.try_recv().is_err());
+
+        let result =
+            res.backend_request(BackendRequest::LightUpdate(unowned, LightUpdate::default()));
+        assert!(matches!(result, Err(ApiError::NoBackendOwner(link)) if link == unowned));
+        assert!(z2m.try_recv().is_err());
+        assert!(hass.try_recv().is_err());
+    }
+
+    #[test]
+    fn grouped_light_update_reaches_merged_backends() {
+        let mut res = resources();
+        let mut z2m = res.backend_event_stream("z2m", BackendCapabilities::default());
+        let mut hass = res.backend_event_stream("hass", BackendCapabilities::default());
+        let mut other = res.backend_event_stream("other", BackendCapabilities::default());
+
+        // a z2m room, with a device of hass merged into it
+        let z2m_device = RType::Device.deterministic("z2m-device");
+        let hass_device = RType::Device.deterministic("hass-device");
+        res.claim("z2m", &z2m_device).unwrap();
+        res.claim("hass", &hass_device).unwrap();
+
+        let room = RType::Room.deterministic("room");
+        let glight = RType::GroupedLight.deterministic("glight");
+        res.add_owned(
+            "z2m",
+            &room,
+            Resource::Room(Room {
+                children: BTreeSet::from([z2m_device, hass_device]),
+                metadata: RoomMetadata::new(RoomArchetype::Home, "Room"),
+                services: BTreeSet::from([glight]),
+            }),
+        )
+        .unwrap();
+        res.add(&glight, Resource::GroupedLight(GroupedLight::new(room)))
+            .unwrap();
+
+        res.backend_request(BackendRequest::GroupedLightUpdate(
+            glight,
+            GroupedLightUpdate::new(),
+        ))
+        .unwrap();
+
+        for rx in [&mut z2m, &mut hass] {
+            assert!(matches!(
+                *rx.try_recv().unwrap(),
+                BackendRequest::GroupedLightUpdate(link, _) if link == glight
+            ));
+        }
+        assert!(other.try_recv().is_err());
+    }
+
+    #[test]
+    fn merged_room_state_from_all_lights() {
+        let mut res = resources();
+
+        // a z2m room, with a light of hass merged into it
+        let room = RType::Room.deterministic("room");
+        let glight = RType::GroupedLight.deterministic("glight");
+        let mut devices = BTreeSet::new();
+        let mut lights = vec![];
+        for (owner, on, brightness) in [("z2m", false, 10.0), ("hass", true, 60.0)] {
+            let device = RType::Device.deterministic(owner);
+            let light = RType::Light.deterministic(owner);
+            res.claim(owner, &device).unwrap();
+
+            let dev: Device = serde_json::from_value(json!({
+                "product_data": {
+                    "model_id": "test",
+                    "manufacturer_name": "test",
+                    "product_name": "test",
+                    "product_archetype": "sultan_bulb",
+                    "certified": false,
+                    "software_version": "1.0.0",
+                },
+                "metadata": {"name": owner, "archetype": "sultan_bulb"},
+                "services": [light],
+            }))
+            .unwrap();
+            res.add(&device, Resource::Device(dev)).unwrap();
+
+            let mut obj = Light::new(
+                device,
+                LightMetadata::new(DeviceArchetype::SultanBulb, owner),
+            );
+            obj.on = On::new(on);
+            obj.dimming = Some(Dimming {
+                brightness,
+                min_dim_level: None,
+            });
+            res.add(&light, Resource::Light(obj)).unwrap();
+
+            devices.insert(device);
+            lights.push(light);
+        }
+
+        res.add_owned(
+            "z2m",
+            &room,
+            Resource::Room(Room {
+                children: devices,
+                metadata: RoomMetadata::new(RoomArchetype::Home, "Room"),
+                services: BTreeSet::from([glight]),
+            }),
+        )
+        .unwrap();
+        res.add(&glight, Resource::GroupedLight(GroupedLight::new(room)))
+            .unwrap();
+        assert!(res.is_merged_room(&glight));
+
+        res.refresh_merged_room(&lights[0].rid).unwrap();
+
+        // on, with the brightness of the light that is on
+        let grouped = res.get::<GroupedLight>(&glight).unwrap();
+        assert_eq!(grouped.on, Some(On::new(true)));
+        assert_eq!(grouped.dimming.map(|dim| dim.brightness), Some(60.0));
+    }
+
+    #[test]
+    fn export_leaves_out_users() {
+        let mut res = resources();
+        res.add_api_user(
+            "secret-username".to_string(),
+            ApiUser::generate("app#device", true),
+        );
+
+        let export = res.export().unwrap();
+
+        assert!(export.get("users").is_none());
+        assert!(!export.to_string().contains("secret-username"));
+        assert!(res.serialize().unwrap().contains("secret-username"));
+    }
 }
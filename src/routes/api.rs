@@ -1,23 +1,26 @@
 use std::collections::{BTreeMap, HashMap};
 
 use axum::Router;
-use axum::extract::{Path, State};
-use axum::routing::{get, post, put};
+use axum::extract::{Extension, Path, State};
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post, put};
 use bytes::Bytes;
 use chrono::Utc;
 use log::{info, warn};
 use serde::Serialize;
 use serde_json::{Value, json};
 use tokio::sync::MutexGuard;
+use uuid::Uuid;
 
-use bifrost_api::backend::BackendRequest;
+use bifrost_api::backend::{BackendRequest, HassRequest};
 use hue::api::{
     Device, DeviceArchetype, Entertainment, EntertainmentConfiguration,
     EntertainmentConfigurationAction, EntertainmentConfigurationLocationsNew,
     EntertainmentConfigurationMetadata, EntertainmentConfigurationNew,
     EntertainmentConfigurationServiceLocationsNew, EntertainmentConfigurationType,
     EntertainmentConfigurationUpdate, GroupedLight, GroupedLightUpdate, Light, LightUpdate, RType,
-    ResourceLink, Room, Scene, SceneActive, SceneStatus, SceneUpdate, V1Reply,
+    ResourceLink, Room, Scene, SceneActive, SceneStatus, SceneUpdate, V1Reply, ZigbeeConnectivity,
+    ZigbeeConnectivityStatus,
 };
 use hue::error::{HueApiV1Error, HueError, HueResult};
 use hue::legacy_api::{
@@ -28,12 +31,19 @@ use hue::legacy_api::{
 };
 
 use crate::error::{ApiError, ApiResult};
+use crate::model::hass::HassPatinaFeature;
+use crate::model::state::ApiUser;
+use crate::model::timeline::TimelineCategory;
+use crate::model::voice::room_qualified_name;
 use crate::resource::Resources;
-use crate::routes::auth::{STANDARD_APPLICATION_ID, STANDARD_CLIENT_KEY};
+use crate::routes::auth::STANDARD_APPLICATION_ID;
 use crate::routes::clip::entertainment_configuration::{self, POSITIONS};
 use crate::routes::extractor::Json;
 use crate::routes::{ApiV1Error, ApiV1Result};
 use crate::server::appstate::AppState;
+use crate::server::compat::{self, ClientQuirks, family_for_devicetype};
+use crate::server::forwarded::ClientAddr;
+use crate::server::gaps::Unsupported;
 
 async fn get_api_config(State(state): State<AppState>) -> Json<impl Serialize> {
     match state.api_config("testuser".to_string()).await {
@@ -44,30 +54,115 @@ async fn get_api_config(State(state): State<AppState>) -> Json<impl Serialize> {
     }
 }
 
-async fn post_api(bytes: Bytes) -> ApiV1Result<Json<impl Serialize>> {
+async fn post_api(
+    State(state): State<AppState>,
+    client: Option<Extension<ClientAddr>>,
+    bytes: Bytes,
+) -> ApiV1Result<Json<impl Serialize>> {
     info!("post: {bytes:?}");
     let json: NewUser = serde_json::from_slice(&bytes)?;
 
+    let client_ip = client.map(|Extension(client)| client.ip);
+    let client = client_ip.map_or_else(|| "unknown address".to_string(), |ip| ip.to_string());
+
+    let allowed = match client_ip {
+        Some(ip) => state.pairing_limit().allow(ip).await,
+        None => true,
+    };
+    if !allowed {
+        warn!("Too many pairing attempts from {client}, refusing");
+        return Err(ApiV1Error::TooManyPairingAttempts);
+    }
+
+    let linkbutton = state.linkbutton_active().await;
+    if !linkbutton {
+        warn!(
+            "App {:?} paired from {client} without link button press",
+            json.devicetype
+        );
+    }
+
+    state.hass_ui().lock().await.push_event(
+        TimelineCategory::Pairing,
+        format!(
+            "App {:?} paired from {client}{}",
+            json.devicetype,
+            if linkbutton {
+                ""
+            } else {
+                " (link button not pressed)"
+            }
+        ),
+    );
+
+    let notify = HassRequest::NotifyPairing(json.devicetype.clone(), linkbutton);
+    let sent = state.res.lock().await.hass_request(notify);
+    if let Err(err) = sent {
+        warn!("Could not send pairing notification: {err}");
+    }
+
+    if let Some(family) = family_for_devicetype(&state.config().compat, &json.devicetype) {
+        info!(
+            "App {:?} uses compatibility settings {family:?}",
+            json.devicetype
+        );
+    }
+
+    let user = ApiUser::generate(&json.devicetype, json.generateclientkey);
+
+    let username = hex::encode(rand::random::<[u8; 20]>());
     let res = NewUserReply {
-        clientkey: if json.generateclientkey {
-            Some(hex::encode_upper(STANDARD_CLIENT_KEY))
-        } else {
-            None
-        },
-        username: STANDARD_APPLICATION_ID.to_string(),
+        clientkey: user.clientkey.clone(),
+        username: username.clone(),
     };
+    state.res.lock().await.add_api_user(username, user);
+
     Ok(Json(vec![HueApiResult::Success(res)]))
 }
 
-fn get_lights(res: &MutexGuard<Resources>) -> ApiResult<HashMap<String, ApiLight>> {
+fn get_light(
+    res: &MutexGuard<Resources>,
+    uuid: &Uuid,
+    light: &Light,
+    room_prefix: bool,
+    quirks: ClientQuirks,
+) -> ApiResult<ApiLight> {
+    let dev = res.get::<Device>(&light.owner)?;
+    // devices without zigbee connectivity have no way to report otherwise
+    let reachable = dev
+        .service(RType::ZigbeeConnectivity)
+        .and_then(|link| res.get::<ZigbeeConnectivity>(link).ok())
+        .is_none_or(|zbc| matches!(zbc.status, ZigbeeConnectivityStatus::Connected));
+    let mut api_light = ApiLight::from_dev_and_light(uuid, dev, light).with_reachable(reachable);
+    if quirks.hue_uniqueid {
+        api_light = api_light.with_hue_uniqueid(uuid);
+    }
+
+    if !room_prefix {
+        return Ok(api_light);
+    }
+
+    Ok(match res.device_room(&light.owner) {
+        Some(room) => api_light.with_name(room_qualified_name(
+            &room.metadata.name,
+            &light.metadata.name,
+        )),
+        None => api_light,
+    })
+}
+
+fn get_lights(
+    res: &MutexGuard<Resources>,
+    room_prefix: bool,
+    quirks: ClientQuirks,
+) -> ApiResult<HashMap<String, ApiLight>> {
     let mut lights = HashMap::new();
 
     for rr in res.get_resources_by_type(RType::Light) {
         let light: Light = rr.obj.try_into()?;
-        let dev = res.get::<Device>(&light.owner)?;
         lights.insert(
             res.get_id_v1(rr.id)?,
-            ApiLight::from_dev_and_light(&rr.id, dev, &light),
+            get_light(res, &rr.id, &light, room_prefix, quirks)?,
         );
     }
 
@@ -78,7 +173,11 @@ fn get_groups(res: &MutexGuard<Resources>, group_0: bool) -> ApiResult<HashMap<S
     let mut rooms = HashMap::new();
 
     if group_0 {
-        rooms.insert("0".into(), ApiGroup::make_group_0());
+        let mut group_0 = ApiGroup::make_group_0();
+        if let Some(name) = res.home_name() {
+            group_0.name = name.to_string();
+        }
+        rooms.insert("0".into(), group_0);
     }
 
     for rr in res.get_resources_by_type(RType::Room) {
@@ -163,7 +262,12 @@ fn get_groups(res: &MutexGuard<Resources>, group_0: bool) -> ApiResult<HashMap<S
     Ok(rooms)
 }
 
-pub fn get_scene(res: &Resources, owner: String, scene: &Scene) -> ApiV1Result<ApiScene> {
+pub fn get_scene(
+    res: &Resources,
+    owner: String,
+    scene: &Scene,
+    quirks: ClientQuirks,
+) -> ApiV1Result<ApiScene> {
     let lights = scene
         .actions
         .iter()
@@ -192,9 +296,16 @@ pub fn get_scene(res: &Resources, owner: String, scene: &Scene) -> ApiV1Result<A
         recycle: false,
         locked: false,
         /* Some clients (e.g. Hue Essentials) require .appdata */
-        appdata: ApiSceneAppData {
-            data: Some(format!("xxxxx_r{room_id}")),
-            version: Some(1),
+        appdata: if quirks.scene_appdata {
+            ApiSceneAppData {
+                data: Some(format!("xxxxx_r{room_id}")),
+                version: Some(1),
+            }
+        } else {
+            ApiSceneAppData {
+                data: None,
+                version: None,
+            }
         },
         picture: String::new(),
         lastupdated: Utc::now(),
@@ -204,7 +315,11 @@ pub fn get_scene(res: &Resources, owner: String, scene: &Scene) -> ApiV1Result<A
     })
 }
 
-fn get_scenes(owner: &str, res: &MutexGuard<Resources>) -> ApiV1Result<HashMap<String, ApiScene>> {
+fn get_scenes(
+    owner: &str,
+    res: &MutexGuard<Resources>,
+    quirks: ClientQuirks,
+) -> ApiV1Result<HashMap<String, ApiScene>> {
     let mut scenes = HashMap::new();
 
     for rr in res.get_resources_by_type(RType::Scene) {
@@ -212,27 +327,49 @@ fn get_scenes(owner: &str, res: &MutexGuard<Resources>) -> ApiV1Result<HashMap<S
 
         scenes.insert(
             res.get_id_v1(rr.id)?,
-            get_scene(res, owner.to_string(), scene)?,
+            get_scene(res, owner.to_string(), scene, quirks)?,
         );
     }
 
     Ok(scenes)
 }
 
+/// Workarounds for the app paired as `username`
+async fn client_quirks(
+    state: &AppState,
+    client: Option<Extension<ClientAddr>>,
+    username: &str,
+) -> ClientQuirks {
+    let devicetype = state
+        .res
+        .lock()
+        .await
+        .api_users()
+        .get(username)
+        .map(|user| user.devicetype.clone());
+    compat::client_quirks(
+        &state.config().compat,
+        client.map(|Extension(client)| client.ip),
+        devicetype.as_deref(),
+    )
+}
+
 #[allow(clippy::zero_sized_map_values)]
 async fn get_api_user(
     state: State<AppState>,
+    client: Option<Extension<ClientAddr>>,
     Path(username): Path<String>,
 ) -> ApiV1Result<Json<impl Serialize>> {
+    let quirks = client_quirks(&state, client, &username).await;
     let lock = state.res.lock().await;
 
     Ok(Json(ApiUserConfig {
         config: state.api_config(username.clone()).await?,
-        groups: get_groups(&lock, false)?,
-        lights: get_lights(&lock)?,
+        groups: get_groups(&lock, quirks.list_group_0)?,
+        lights: get_lights(&lock, state.config().bifrost.voice_room_prefix, quirks)?,
         resourcelinks: HashMap::new(),
         rules: HashMap::new(),
-        scenes: get_scenes(&username, &lock)?,
+        scenes: get_scenes(&username, &lock, quirks)?,
         schedules: HashMap::new(),
         sensors: HashMap::from([(1, ApiSensor::builtin_daylight_sensor())]),
     }))
@@ -240,14 +377,20 @@ async fn get_api_user(
 
 async fn get_api_user_resource(
     State(state): State<AppState>,
+    client: Option<Extension<ClientAddr>>,
     Path((username, artype)): Path<(String, ApiResourceType)>,
 ) -> ApiV1Result<Json<Value>> {
+    let quirks = client_quirks(&state, client, &username).await;
     let lock = &state.res.lock().await;
     match artype {
         ApiResourceType::Config => Ok(Json(json!(state.api_config(username).await?))),
-        ApiResourceType::Lights => Ok(Json(json!(get_lights(lock)?))),
-        ApiResourceType::Groups => Ok(Json(json!(get_groups(lock, false)?))),
-        ApiResourceType::Scenes => Ok(Json(json!(get_scenes(&username, lock)?))),
+        ApiResourceType::Lights => Ok(Json(json!(get_lights(
+            lock,
+            state.config().bifrost.voice_room_prefix,
+            quirks
+        )?))),
+        ApiResourceType::Groups => Ok(Json(json!(get_groups(lock, quirks.list_group_0)?))),
+        ApiResourceType::Scenes => Ok(Json(json!(get_scenes(&username, lock, quirks)?))),
         ApiResourceType::Resourcelinks
         | ApiResourceType::Rules
         | ApiResourceType::Schedules
@@ -343,27 +486,32 @@ async fn post_api_user_resource(
 async fn put_api_user_resource(
     Path((_username, _resource)): Path<(String, String)>,
     Json(req): Json<Value>,
-) -> ApiV1Result<Json<impl Serialize>> {
+) -> ApiV1Result<impl IntoResponse> {
     warn!("PUT v1 user resource {req:?}");
     //Json(format!("user {username} resource {resource}"))
-    Ok(Json(vec![HueApiResult::Success(req)]))
+    Ok((
+        Extension(Unsupported::new("Updating whole resource types")),
+        Json(vec![HueApiResult::Success(req)]),
+    ))
 }
 
 #[allow(clippy::significant_drop_tightening)]
 async fn get_api_user_resource_id(
     State(state): State<AppState>,
+    client: Option<Extension<ClientAddr>>,
     Path((username, resource, id)): Path<(String, ApiResourceType, u32)>,
 ) -> ApiV1Result<Json<impl Serialize>> {
     log::debug!("GET v1 username={username} resource={resource:?} id={id}");
+    let quirks = client_quirks(&state, client, &username).await;
     let result = match resource {
         ApiResourceType::Lights => {
             let lock = state.res.lock().await;
             let uuid = lock.from_id_v1(id)?;
             let link = ResourceLink::new(uuid, RType::Light);
             let light = lock.get::<Light>(&link)?;
-            let dev = lock.get::<Device>(&light.owner)?;
+            let room_prefix = state.config().bifrost.voice_room_prefix;
 
-            json!(ApiLight::from_dev_and_light(&uuid, dev, light))
+            json!(get_light(&lock, &uuid, light, room_prefix, quirks)?)
         }
         ApiResourceType::Scenes => {
             let lock = state.res.lock().await;
@@ -371,7 +519,7 @@ async fn get_api_user_resource_id(
             let link = ResourceLink::new(uuid, RType::Scene);
             let scene = lock.get::<Scene>(&link)?;
 
-            json!(get_scene(&lock, username, scene)?)
+            json!(get_scene(&lock, username, scene, quirks)?)
         }
         ApiResourceType::Groups => {
             let lock = state.res.lock().await;
@@ -512,9 +660,14 @@ async fn put_api_user_resource_id_path(
                         active: SceneActive::Static,
                         last_recall: None,
                     }));
-                    lock.backend_request(BackendRequest::SceneUpdate(rlink, updv2))?;
+                    let target = lock.current_scene_variant(rlink, &updv2);
+                    lock.backend_request(BackendRequest::SceneUpdate(target, updv2))?;
                     drop(lock);
 
+                    state
+                        .record_patina_usage(HassPatinaFeature::SceneRecall)
+                        .await;
+
                     V1Reply::for_group_path(id, &path).add("scene", upd.scene)?
                 }
             };
@@ -537,6 +690,10 @@ async fn put_api_user_resource_id_path(
                     let updv2 = GroupedLightUpdate::from(&upd);
 
                     for res in lock.get_resources_by_type(RType::GroupedLight) {
+                        // skip grouped lights not backed by any backend (e.g. bridge home)
+                        if lock.backend_owner(&res.id).is_none() {
+                            continue;
+                        }
                         let link = RType::GroupedLight.link_to(res.id);
                         let req = BackendRequest::GroupedLightUpdate(link, updv2.clone());
                         lock.backend_request(req)?;
@@ -574,6 +731,31 @@ pub async fn workaround_iconnect_hue() -> ApiV1Result<()> {
     Err(HueApiV1Error::UnauthorizedUser)?
 }
 
+/// Unpair the app `key`, as the official app does from its settings
+async fn delete_api_user_whitelist(
+    State(state): State<AppState>,
+    Path((username, key)): Path<(String, String)>,
+) -> ApiV1Result<Json<impl Serialize>> {
+    let mut lock = state.res.lock().await;
+    if !lock.api_users().contains_key(&username) {
+        return Err(HueApiV1Error::UnauthorizedUser)?;
+    }
+    let Some(user) = lock.remove_api_user(&key) else {
+        return Err(HueApiV1Error::ResourceNotfound)?;
+    };
+    drop(lock);
+
+    info!("App {:?} was unpaired", user.devicetype);
+    state.hass_ui().lock().await.push_event(
+        TimelineCategory::Pairing,
+        format!("App {:?} was unpaired", user.devicetype),
+    );
+
+    Ok(Json(vec![HueApiResult::Success(format!(
+        "/config/whitelist/{key} deleted"
+    ))]))
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", post(post_api))
@@ -586,6 +768,10 @@ pub fn router() -> Router<AppState> {
         .route("/{user}/{rtype}", put(put_api_user_resource))
         .route("/{user}/{rtype}/{id}", get(get_api_user_resource_id))
         .route("/{user}/{rtype}/{id}", put(put_api_user_resource_id))
+        .route(
+            "/{user}/config/whitelist/{key}",
+            delete(delete_api_user_whitelist),
+        )
         .route(
             "/{user}/{rtype}/{id}/{key}",
             put(put_api_user_resource_id_path),
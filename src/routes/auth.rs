@@ -1,4 +1,5 @@
 use axum::Router;
+use axum::extract::{Extension, State};
 use axum::http::HeaderValue;
 use axum::response::IntoResponse;
 use axum::routing::get;
@@ -9,14 +10,39 @@ use hue::api::HueStreamKey;
 
 use crate::routes::extractor::Json;
 use crate::server::appstate::AppState;
+use crate::server::forwarded::ClientAddr;
 
 pub const STANDARD_APPLICATION_ID: &str = "01010101-0202-0303-0404-050505050505";
 
-/// This 16-byte key is used for all DTLS entertainment streams
+/// This 16-byte key is used for DTLS entertainment streams from apps without
+/// a key of their own (apps paired with older versions of bifrost)
 pub const STANDARD_CLIENT_KEY: HueStreamKey = HueStreamKey::new(*b"BifrostHueTlsKey");
 
-pub async fn auth_v1() -> impl IntoResponse {
-    let value = HeaderValue::from_static(STANDARD_APPLICATION_ID);
+pub async fn auth_v1(
+    State(state): State<AppState>,
+    client: Option<Extension<ClientAddr>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let application_id = {
+        let lock = state.res.lock().await;
+        headers
+            .get("hue-application-key")
+            .and_then(|key| key.to_str().ok())
+            .and_then(|key| lock.api_users().get(key))
+            .map(|user| user.application_id)
+    };
+
+    if application_id.is_none() {
+        let client = client.map_or_else(
+            || "unknown address".to_string(),
+            |Extension(client)| client.ip.to_string(),
+        );
+        log::debug!("Unknown application key from {client}, using standard application id");
+    }
+
+    let value = application_id
+        .and_then(|id| HeaderValue::from_str(&id.to_string()).ok())
+        .unwrap_or_else(|| HeaderValue::from_static(STANDARD_APPLICATION_ID));
 
     let mut headers = HeaderMap::new();
     headers.append("hue-application-id", value);
@@ -0,0 +1,91 @@
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::post;
+use uuid::Uuid;
+
+use bifrost_api::action::ActionResponse;
+use bifrost_api::backend::BackendRequest;
+use hue::api::{GroupedLightUpdate, On, RType, Room, Scene, SceneActive, SceneStatus, SceneUpdate};
+
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+fn off() -> GroupedLightUpdate {
+    GroupedLightUpdate::new().with_on(Some(On::new(false)))
+}
+
+async fn post_all_off(State(state): State<AppState>) -> BifrostApiResult<Json<ActionResponse>> {
+    let lock = state.res.lock().await;
+
+    let mut targets = vec![];
+    for rid in lock.get_resource_ids_by_type(RType::Room) {
+        let Some(glight) = lock.get_id::<Room>(rid)?.grouped_light_service() else {
+            continue;
+        };
+        // skip rooms not backed by any backend
+        if lock.backend_owner(&glight.rid).is_none() {
+            continue;
+        }
+        lock.backend_request(BackendRequest::GroupedLightUpdate(*glight, off()))?;
+        targets.push(*glight);
+    }
+
+    drop(lock);
+
+    Ok(Json(ActionResponse {
+        targets,
+        failed: vec![],
+    }))
+}
+
+async fn post_room_off(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<ActionResponse>> {
+    let lock = state.res.lock().await;
+
+    let room = lock.get_id::<Room>(id)?;
+    let targets: Vec<_> = room.grouped_light_service().copied().into_iter().collect();
+
+    for glight in &targets {
+        lock.backend_request(BackendRequest::GroupedLightUpdate(*glight, off()))?;
+    }
+
+    drop(lock);
+
+    Ok(Json(ActionResponse {
+        targets,
+        failed: vec![],
+    }))
+}
+
+async fn post_scene_recall(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<ActionResponse>> {
+    let lock = state.res.lock().await;
+
+    lock.get_id::<Scene>(id)?;
+
+    let link = RType::Scene.link_to(id);
+    let upd = SceneUpdate::new().with_recall_action(Some(SceneStatus {
+        active: SceneActive::Static,
+        last_recall: None,
+    }));
+    lock.backend_request(BackendRequest::SceneUpdate(link, upd))?;
+
+    drop(lock);
+
+    Ok(Json(ActionResponse {
+        targets: vec![link],
+        failed: vec![],
+    }))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/all-off", post(post_all_off))
+        .route("/room/{id}/off", post(post_room_off))
+        .route("/scene/{id}/recall", post(post_scene_recall))
+}
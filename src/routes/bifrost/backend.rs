@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+
 use axum::Router;
 use axum::extract::{Path, State};
-use axum::routing::post;
+use axum::routing::{get, post};
 
+use bifrost_api::backend::BackendCapabilities;
 use bifrost_api::config::{HassServer, Z2mServer};
 
 use crate::backend::hass::HassBackend;
@@ -21,9 +24,10 @@ async fn post_backend_z2m(
     let mut mgr = state.manager();
 
     let svc = Z2mBackend::new(name.clone(), server, state.config(), state.res.clone())?;
+    let policy = state.config().service_policy(&format!("z2m@{name}"));
     let name = format!("z2m-{name}");
 
-    mgr.register_service(&name, svc).await?;
+    mgr.register_service_with_policy(&name, svc, policy).await?;
     mgr.start(&name).await?;
 
     Ok(Json(()))
@@ -42,20 +46,43 @@ async fn post_backend_hass(
     let svc = HassBackend::new(
         name.clone(),
         server,
+        state.config().bifrost.proxy.clone(),
         state.res.clone(),
         state.hass_ui(),
         state.hass_runtime(),
-    )?;
+    )?
+    .with_linkbutton(state.linkbutton(), state.config().linkbutton.clone())
+    .with_presence(
+        state.presence(),
+        state.config().presence.hass_entity.clone(),
+    );
+    let policy = state.config().service_policy(&format!("hass@{name}"));
     let name = format!("hass-{name}");
 
-    mgr.register_service(&name, svc).await?;
+    mgr.register_service_with_policy(&name, svc, policy).await?;
     mgr.start(&name).await?;
 
     Ok(Json(()))
 }
 
+async fn get_backends(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<String, BackendCapabilities>>> {
+    let caps = state.res.lock().await.backend_capabilities().clone();
+    Ok(Json(caps))
+}
+
+async fn get_backend_lag(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<String, u64>>> {
+    let lag = state.res.lock().await.backend_lag().clone();
+    Ok(Json(lag))
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
+        .route("/", get(get_backends))
+        .route("/lag", get(get_backend_lag))
         .route("/z2m/{name}", post(post_backend_z2m))
         .route("/hass/{name}", post(post_backend_hass))
 }
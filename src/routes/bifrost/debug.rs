@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use nix::sys::resource::{UsageWho, getrusage};
+use nix::sys::time::TimeValLike;
+use uuid::Uuid;
+
+use bifrost_api::debug::{
+    AllocStats, CompatGap, EntertainmentReport, LatencyReport, ProcessStats, ResourceInspection,
+    ResourceTree, RouteStats, RuntimeStats, ServiceUsage, WorkerStats,
+};
+use hue::api::ResourceLink;
+
+use crate::error::ApiResult;
+use crate::resource::Resources;
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+/// Resource trees deeper than this are cut off
+const MAX_TREE_DEPTH: usize = 8;
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn process_stats() -> ApiResult<ProcessStats> {
+    let usage = getrusage(UsageWho::RUSAGE_SELF)?;
+
+    Ok(ProcessStats {
+        user_ms: u64::try_from(usage.user_time().num_milliseconds()).unwrap_or_default(),
+        system_ms: u64::try_from(usage.system_time().num_milliseconds()).unwrap_or_default(),
+        max_rss: u64::try_from(usage.max_rss()).unwrap_or_default(),
+    })
+}
+
+#[cfg(feature = "alloc-stats")]
+fn alloc_stats() -> Option<AllocStats> {
+    Some(crate::server::alloc::stats())
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+const fn alloc_stats() -> Option<AllocStats> {
+    None
+}
+
+async fn get_runtime(State(state): State<AppState>) -> BifrostApiResult<Json<RuntimeStats>> {
+    let metrics = tokio::runtime::Handle::current().metrics();
+
+    let workers = (0..metrics.num_workers())
+        .map(|worker| WorkerStats {
+            busy_ms: millis(metrics.worker_total_busy_duration(worker)),
+            park_count: metrics.worker_park_count(worker),
+        })
+        .collect();
+
+    let mut svm = state.manager();
+    let mut services = BTreeMap::new();
+    for (id, name) in svm.list().await? {
+        let usage = svm.usage(id).await?;
+        let service = ServiceUsage {
+            name,
+            busy_ms: millis(usage.busy),
+            uptime_ms: millis(usage.uptime),
+            cpu_percent: usage.cpu_percent(),
+        };
+        services.insert(id, service);
+    }
+
+    Ok(Json(RuntimeStats {
+        workers,
+        alive_tasks: metrics.num_alive_tasks(),
+        global_queue_depth: metrics.global_queue_depth(),
+        process: process_stats()?,
+        services,
+        alloc: alloc_stats(),
+    }))
+}
+
+async fn get_routes(State(state): State<AppState>) -> BifrostApiResult<Json<Vec<RouteStats>>> {
+    Ok(Json(state.metrics().summary().await))
+}
+
+async fn get_latency(State(state): State<AppState>) -> BifrostApiResult<Json<LatencyReport>> {
+    Ok(Json(state.latency().report().await))
+}
+
+/// Resources owned by, or referenced from, `link`
+fn related_resources(res: &Resources, link: ResourceLink) -> BTreeSet<ResourceLink> {
+    let mut related: BTreeSet<_> = res.get_linked_resources(&link.rid).into_iter().collect();
+    related.extend(
+        res.get_resources_by_owner(link)
+            .into_iter()
+            .map(|rr| ResourceLink::new(rr.id, rr.obj.rtype())),
+    );
+    related
+}
+
+fn resource_tree(
+    res: &Resources,
+    link: ResourceLink,
+    depth: usize,
+    seen: &mut HashSet<ResourceLink>,
+) -> ResourceTree {
+    let Ok(record) = res.get_resource(&link) else {
+        return ResourceTree {
+            resource: link,
+            id_v1: None,
+            backend: None,
+            missing: true,
+            children: vec![],
+        };
+    };
+
+    let mut children = vec![];
+    if depth < MAX_TREE_DEPTH && seen.insert(link) {
+        for child in related_resources(res, link) {
+            children.push(resource_tree(res, child, depth + 1, seen));
+        }
+    }
+
+    ResourceTree {
+        resource: link,
+        id_v1: record.id_v1,
+        backend: res
+            .backend_owner(&link.rid)
+            .map(|(_, backend)| backend.to_string()),
+        missing: false,
+        children,
+    }
+}
+
+async fn get_resource(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<ResourceInspection>> {
+    let lock = state.res.lock().await;
+
+    let resource = lock.get_resource_by_id(&id)?;
+    let link = ResourceLink::new(id, resource.obj.rtype());
+    let owner = resource.obj.owner();
+    let backend = lock
+        .backend_owner(&id)
+        .map(|(_, backend)| backend.to_string());
+    let services = related_resources(&lock, link);
+    let missing = owner
+        .iter()
+        .chain(&services)
+        .filter(|rlink| lock.get_resource(rlink).is_err())
+        .copied()
+        .collect();
+
+    drop(lock);
+
+    Ok(Json(ResourceInspection {
+        resource,
+        owner,
+        backend,
+        services: services.into_iter().collect(),
+        missing,
+    }))
+}
+
+async fn get_entertainment(State(state): State<AppState>) -> Json<EntertainmentReport> {
+    Json(state.entertainment().report().await)
+}
+
+async fn get_gaps(State(state): State<AppState>) -> Json<Vec<CompatGap>> {
+    Json(state.gaps().list().await)
+}
+
+async fn delete_gaps(State(state): State<AppState>) -> Json<Vec<CompatGap>> {
+    Json(state.gaps().clear().await)
+}
+
+async fn get_resource_tree(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<ResourceTree>> {
+    let lock = state.res.lock().await;
+
+    let link = ResourceLink::new(id, lock.get_resource_by_id(&id)?.obj.rtype());
+    let tree = resource_tree(&lock, link, 0, &mut HashSet::new());
+
+    drop(lock);
+
+    Ok(Json(tree))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/resource/{id}", get(get_resource))
+        .route("/resource/{id}/tree", get(get_resource_tree))
+        .route("/runtime", get(get_runtime))
+        .route("/routes", get(get_routes))
+        .route("/latency", get(get_latency))
+        .route("/entertainment", get(get_entertainment))
+        .route("/gaps", get(get_gaps).delete(delete_gaps))
+}
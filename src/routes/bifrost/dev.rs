@@ -0,0 +1,196 @@
+use axum::Router;
+use axum::extract::State;
+use axum::response::Html;
+use axum::routing::get;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use bifrost_api::config::AppConfig;
+use bifrost_api::dev::{ApiCatalog, ApiExample};
+use hue::api::RType;
+
+use crate::resource::Resources;
+use crate::routes::auth::STANDARD_APPLICATION_ID;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+const API_EXPLORER: &str = include_str!("dev_api.html");
+
+fn example(api: &str, method: &str, path: String, description: &str) -> ApiExample {
+    ApiExample {
+        api: api.to_string(),
+        method: method.to_string(),
+        path,
+        description: description.to_string(),
+        body: None,
+        dry_run: false,
+    }
+}
+
+fn with_body(example: ApiExample, body: Value) -> ApiExample {
+    ApiExample {
+        body: Some(body),
+        ..example
+    }
+}
+
+fn dry_run(example: ApiExample) -> ApiExample {
+    ApiExample {
+        dry_run: true,
+        ..example
+    }
+}
+
+/// Example requests for the emulated hue apis, using resources that exist in
+/// `res`, so they can be sent as-is
+fn api_examples(res: &Resources) -> Vec<ApiExample> {
+    let first = |rtype: RType| res.get_resource_ids_by_type(rtype).into_iter().min();
+    let id_v1 = |id: Option<Uuid>| {
+        id.and_then(|id| res.get_id_v1(id).ok())
+            .unwrap_or_else(|| "1".to_string())
+    };
+
+    let light = first(RType::Light);
+    let room = first(RType::Room);
+    let scene = first(RType::Scene);
+    let light_v1 = id_v1(light);
+    let room_v1 = id_v1(room);
+    let scene_v1 = id_v1(scene);
+    let light_v2 = light.unwrap_or_default();
+
+    let user = STANDARD_APPLICATION_ID;
+
+    vec![
+        example(
+            "bridge",
+            "GET",
+            "/description.xml".to_string(),
+            "UPnP description, used for bridge discovery",
+        ),
+        example(
+            "v1",
+            "GET",
+            "/api/config".to_string(),
+            "Public bridge configuration (no username needed)",
+        ),
+        // pairing creates a user that stays around, so only show the request
+        dry_run(with_body(
+            example(
+                "v1",
+                "POST",
+                "/api".to_string(),
+                "Pair a new app (not sent, since it creates a user)",
+            ),
+            json!({"devicetype": "explorer#bifrost", "generateclientkey": true}),
+        )),
+        example(
+            "v1",
+            "GET",
+            format!("/api/{user}"),
+            "Full state (config, lights, groups, scenes, ..)",
+        ),
+        example("v1", "GET", format!("/api/{user}/lights"), "All lights"),
+        example(
+            "v1",
+            "GET",
+            format!("/api/{user}/lights/{light_v1}"),
+            "A single light",
+        ),
+        with_body(
+            example(
+                "v1",
+                "PUT",
+                format!("/api/{user}/lights/{light_v1}/state"),
+                "Change the state of a light",
+            ),
+            json!({"on": true, "bri": 127}),
+        ),
+        example("v1", "GET", format!("/api/{user}/groups"), "All rooms"),
+        example(
+            "v1",
+            "GET",
+            format!("/api/{user}/groups/0"),
+            "Group 0 (all lights)",
+        ),
+        with_body(
+            example(
+                "v1",
+                "PUT",
+                format!("/api/{user}/groups/{room_v1}/action"),
+                "Change the state of all lights in a room",
+            ),
+            json!({"on": true}),
+        ),
+        example("v1", "GET", format!("/api/{user}/scenes"), "All scenes"),
+        example(
+            "v1",
+            "GET",
+            format!("/api/{user}/scenes/{scene_v1}"),
+            "A single scene",
+        ),
+        example(
+            "v2",
+            "GET",
+            "/clip/v2/resource".to_string(),
+            "All resources",
+        ),
+        example(
+            "v2",
+            "GET",
+            "/clip/v2/resource/light".to_string(),
+            "All resources of one type",
+        ),
+        example(
+            "v2",
+            "GET",
+            format!("/clip/v2/resource/light/{light_v2}"),
+            "A single resource",
+        ),
+        with_body(
+            example(
+                "v2",
+                "PUT",
+                format!("/clip/v2/resource/light/{light_v2}"),
+                "Update a resource",
+            ),
+            json!({"on": {"on": true}, "dimming": {"brightness": 50.0}}),
+        ),
+        example(
+            "v2",
+            "GET",
+            "/clip/v2/resource/bridge".to_string(),
+            "The bridge itself",
+        ),
+    ]
+}
+
+/// Hue apis are only served by the bridge ports, when the management api
+/// has a server of its own
+fn hue_base_url(config: &AppConfig) -> Option<String> {
+    config.management.as_ref().map(|_| {
+        format!(
+            "http://{}:{}",
+            config.bridge.ipaddress, config.bridge.http_port
+        )
+    })
+}
+
+async fn get_explorer() -> Html<&'static str> {
+    Html(API_EXPLORER)
+}
+
+async fn get_endpoints(State(state): State<AppState>) -> Json<ApiCatalog> {
+    let endpoints = api_examples(&*state.res.lock().await);
+
+    Json(ApiCatalog {
+        base_url: hue_base_url(&state.config()),
+        username: STANDARD_APPLICATION_ID.to_string(),
+        endpoints,
+    })
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api", get(get_explorer))
+        .route("/api/endpoints", get(get_endpoints))
+}
@@ -1,30 +1,46 @@
 use std::collections::HashSet;
+use std::convert::Infallible;
 use std::path::Path;
 use std::time::Duration;
 
 use axum::Router;
-use axum::extract::{Request, State};
+use axum::extract::{FromRequestParts, Path as UrlPath, Query, Request, State};
 use axum::http::header;
+use axum::http::request::Parts;
 use axum::middleware::{self, Next};
 use axum::response::Response;
 use axum::routing::{get, post, put};
-use bifrost_api::backend::BackendRequest;
-use hue::api::{Device, RType};
+use bifrost_api::backend::{BackendRequest, HassRequest};
+use hue::api::{Device, Light, LightUpdate, On, RType, ResourceLink, Room};
+use tokio::time::Instant;
 use tower_http::services::{ServeDir, ServeFile};
+use uuid::Uuid;
 
+use crate::backend::hass::HassBackend;
+use crate::error::ApiError;
 use crate::model::hass::{
-    HassApplyResponse, HassBridgeInfo, HassConnectResponse, HassEntitiesResponse,
-    HassEntityPatchRequest, HassLinkButtonResponse, HassLogsResponse, HassPatinaEventRequest,
-    HassPatinaPublic, HassResetBridgeResponse, HassRoomCreateRequest, HassRoomDeleteRequest,
+    HassApplyResponse, HassBridgeInfo, HassConnectResponse, HassEntitiesQuery,
+    HassEntitiesResponse, HassEntityAux, HassEntityPatchRequest, HassEntityStateRequest,
+    HassEntityStateResponse, HassLinkButtonResponse, HassLogsResponse, HassPatinaEventRequest,
+    HassPatinaFeature, HassPatinaPublic, HassPatinaUsageReport, HassResetBridgeRequest,
+    HassResetBridgeResponse, HassResetScope, HassResetTokenRequest, HassResetTokenResponse,
+    HassRoomBrightnessRequest, HassRoomCreateRequest, HassRoomDeleteRequest, HassRoomMergeRequest,
     HassRoomRenameRequest, HassRoomsResponse, HassRuntimeConfigPublic, HassRuntimeConfigUpdate,
-    HassSensorKind, HassSwitchMode, HassSyncResponse, HassTokenRequest, HassUiConfig,
-    HassUiPayload,
+    HassSessionView, HassSwitchMode, HassSyncResponse, HassTimelineResponse, HassTokenRequest,
+    HassUiConfig, HassUiPayload, HassVerifyReport, HassVerifyResponse,
 };
+use crate::model::state::AuxData;
+use crate::model::timeline::{TimelineCategory, TimelineQuery};
+use crate::resource::Resources;
 use crate::routes::bifrost::BifrostApiResult;
 use crate::routes::extractor::Json;
 use crate::server::appstate::AppState;
+use crate::server::linkbutton::LinkButton;
+use crate::server::reset::ResetGuard;
 
-const LINKBUTTON_DURATION_SECS: u64 = 30;
+const LINKBUTTON_DURATION_SECS: u64 = LinkButton::PRESS_DURATION.as_secs();
+/// How long consistency checks wait for the reports of all backends
+const VERIFY_TIMEOUT_SECS: u64 = 20;
 
 fn resolve_ui_dir() -> String {
     if let Ok(path) = std::env::var("BIFROST_UI_DIR") {
@@ -79,48 +95,108 @@ fn ui_router() -> Router<AppState> {
         .layer(middleware::from_fn(ui_cache_control))
 }
 
-async fn get_ui_payload(State(state): State<AppState>) -> BifrostApiResult<Json<HassUiPayload>> {
+/// Web UI session id, from the `X-Bifrost-Session` header.
+///
+/// Sessions are created by `POST /hass/session`, and give each browser tab its
+/// own view of action results and sync progress.
+struct UiSession(Option<String>);
+
+impl UiSession {
+    const HEADER: &str = "x-bifrost-session";
+
+    fn id(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for UiSession {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let id = parts
+            .headers
+            .get(Self::HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        Ok(Self(id))
+    }
+}
+
+/// Names of the Home Assistant backends
+fn backend_names(state: &AppState) -> Vec<String> {
+    let config = state.config();
+    let servers = &config.hass.servers;
+    if servers.is_empty() {
+        vec![HassBackend::RUNTIME_NAME.to_string()]
+    } else {
+        servers.keys().cloned().collect()
+    }
+}
+
+async fn get_ui_payload(
+    State(state): State<AppState>,
+    session: UiSession,
+) -> BifrostApiResult<Json<HassUiPayload>> {
     let ui = state.hass_ui();
-    let payload = ui.lock().await.payload();
+    let mut payload = ui.lock().await.payload(session.id());
+    payload.backends = backend_names(&state);
     Ok(Json(payload))
 }
 
+async fn post_session(State(state): State<AppState>) -> BifrostApiResult<Json<HassSessionView>> {
+    let ui = state.hass_ui();
+    let view = ui.lock().await.create_session();
+    Ok(Json(view))
+}
+
+async fn get_session(
+    State(state): State<AppState>,
+    session: UiSession,
+) -> BifrostApiResult<Json<Option<HassSessionView>>> {
+    let ui = state.hass_ui();
+    let view = ui.lock().await.session(session.id());
+    Ok(Json(view))
+}
+
 async fn get_ui_config(State(state): State<AppState>) -> BifrostApiResult<Json<HassUiConfig>> {
     let ui = state.hass_ui();
     let config = ui.lock().await.config_normalized();
-    Ok(Json(config))
+    Ok(Json(HassUiConfig::clone(&config)))
 }
 
 async fn put_ui_config(
     State(state): State<AppState>,
+    session: UiSession,
     Json(config): Json<HassUiConfig>,
 ) -> BifrostApiResult<Json<HassUiConfig>> {
     let ui = state.hass_ui();
     let mut lock = ui.lock().await;
     lock.set_config(config);
-    lock.persist_and_log("Saved web UI configuration")?;
+    lock.persist_and_log(session.id(), "Saved web UI configuration")?;
     let normalized = lock.config_normalized();
     drop(lock);
 
     // Keep room metadata in Hue resources aligned with UI config updates.
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassUpdateRooms)?;
+        res.hass_request(HassRequest::UpdateRooms)?;
     }
 
-    Ok(Json(normalized))
+    Ok(Json(HassUiConfig::clone(&normalized)))
 }
 
 async fn get_entities(
     State(state): State<AppState>,
+    Query(query): Query<HassEntitiesQuery>,
 ) -> BifrostApiResult<Json<HassEntitiesResponse>> {
     let ui = state.hass_ui();
-    let entities = ui.lock().await.entities.clone();
-    Ok(Json(HassEntitiesResponse { entities }))
+    let response = ui.lock().await.query_entities(&query);
+    Ok(Json(response))
 }
 
 async fn patch_entity(
     State(state): State<AppState>,
+    session: UiSession,
     Json(req): Json<HassEntityPatchRequest>,
 ) -> BifrostApiResult<Json<HassUiConfig>> {
     let ui = state.hass_ui();
@@ -172,8 +248,12 @@ async fn patch_entity(
         lock.set_entity_light_archetype(&req.entity_id, Some(archetype));
         trigger_upsert = true;
     }
+    if let Some(policy) = req.conflict_policy {
+        lock.set_entity_conflict_policy(&req.entity_id, Some(policy));
+        trigger_upsert = true;
+    }
 
-    lock.persist_and_log(&format!("Updated entity {}", req.entity_id))?;
+    lock.persist_and_log(session.id(), &format!("Updated entity {}", req.entity_id))?;
     let cfg = lock.config_normalized();
 
     if let Some(summary) = lock
@@ -194,20 +274,21 @@ async fn patch_entity(
             summary.room_id = room_id;
         }
         summary.hidden = cfg.is_manually_hidden(&summary.entity_id);
-        let mut included = cfg.should_include(&summary.entity_id, &summary.name, summary.available);
-        if summary.domain == "binary_sensor" {
-            let detected = summary.sensor_kind.unwrap_or(HassSensorKind::Ignore);
-            let selected = cfg.sensor_kind(&summary.entity_id, detected);
-            summary.sensor_kind = Some(selected);
+        let included = summary.is_included(&cfg);
+        if matches!(summary.domain.as_str(), "binary_sensor" | "sensor" | "lock") {
+            summary.sensor_kind = summary
+                .sensor_kind
+                .map(|detected| cfg.sensor_kind(&summary.entity_id, detected));
             summary.enabled = cfg.sensor_enabled(&summary.entity_id);
-            if matches!(selected, HassSensorKind::Ignore) {
-                included = false;
-            }
             summary.light_archetype = None;
-        } else if summary.domain == "light" {
+        } else if matches!(summary.domain.as_str(), "light" | "cover") {
             summary.light_archetype = Some(cfg.light_archetype(&summary.entity_id));
-        } else if summary.domain == "switch" {
-            let mode = cfg.switch_mode(&summary.entity_id);
+        } else if matches!(summary.domain.as_str(), "switch" | "fan") {
+            let mode = if summary.domain == "fan" {
+                cfg.fan_mode(&summary.entity_id)
+            } else {
+                cfg.switch_mode(&summary.entity_id)
+            };
             summary.switch_mode = Some(mode);
             summary.mapped_type = if mode == HassSwitchMode::Light {
                 "light".to_string()
@@ -222,42 +303,73 @@ async fn patch_entity(
         }
         summary.included = included;
     }
+    lock.reindex();
     drop(lock);
 
     // Apply immediately so the Hue app updates without requiring manual save/sync.
     if trigger_remove {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassRemoveEntity(req.entity_id.clone()))?;
+        res.hass_request(HassRequest::RemoveEntity(req.entity_id.clone()))?;
     } else if trigger_upsert {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassUpsertEntity(req.entity_id.clone()))?;
+        res.hass_request(HassRequest::UpsertEntity(req.entity_id.clone()))?;
     }
 
-    Ok(Json(cfg))
+    Ok(Json(HassUiConfig::clone(&cfg)))
+}
+
+async fn put_entity_state(
+    State(state): State<AppState>,
+    UrlPath(entity_id): UrlPath<String>,
+    Json(req): Json<HassEntityStateRequest>,
+) -> BifrostApiResult<Json<HassEntityStateResponse>> {
+    let lock = state.res.lock().await;
+
+    // the entity may be imported by any of the configured servers
+    let light = entity_bindings(&lock)
+        .into_iter()
+        .filter(|(_, aux)| aux.entity_id == entity_id)
+        .map(|(_, aux)| aux.service_link)
+        .find(|link| lock.get::<Light>(link).is_ok())
+        .ok_or_else(|| ApiError::HassEntityNotFound(entity_id.clone()))?;
+
+    let upd = LightUpdate::new()
+        .with_on(req.on.map(On::new))
+        .with_brightness(req.brightness.map(|bri| bri.clamp(0.0, 100.0)))
+        .with_color_temperature(req.color_temperature)
+        .with_color_xy(req.color);
+
+    lock.backend_request(BackendRequest::LightUpdate(light, upd))?;
+    drop(lock);
+
+    Ok(Json(HassEntityStateResponse { entity_id, light }))
 }
 
 async fn get_rooms(State(state): State<AppState>) -> BifrostApiResult<Json<HassRoomsResponse>> {
     let ui = state.hass_ui();
-    let rooms = ui.lock().await.config_normalized().rooms;
+    let rooms = ui.lock().await.config_normalized().rooms.clone();
     Ok(Json(HassRoomsResponse { rooms }))
 }
 
 async fn post_room(
     State(state): State<AppState>,
+    session: UiSession,
     Json(req): Json<HassRoomCreateRequest>,
 ) -> BifrostApiResult<Json<HassRoomsResponse>> {
     let ui = state.hass_ui();
     let mut lock = ui.lock().await;
-    let _created = lock.add_room(&req.name);
-    lock.persist_and_log(&format!("Added room {}", req.name))?;
+    if lock.add_room(&req.name).is_some() {
+        lock.record_patina_usage(HassPatinaFeature::RoomCreate);
+    }
+    lock.persist_and_log(session.id(), &format!("Added room {}", req.name))?;
     let response = HassRoomsResponse {
-        rooms: lock.config_normalized().rooms,
+        rooms: lock.config_normalized().rooms.clone(),
     };
     drop(lock);
 
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassUpdateRooms)?;
+        res.hass_request(HassRequest::UpdateRooms)?;
     }
 
     Ok(Json(response))
@@ -265,24 +377,87 @@ async fn post_room(
 
 async fn put_room(
     State(state): State<AppState>,
+    session: UiSession,
     Json(req): Json<HassRoomRenameRequest>,
 ) -> BifrostApiResult<Json<HassRoomsResponse>> {
     let ui = state.hass_ui();
     let mut lock = ui.lock().await;
     lock.rename_room(&req.room_id, &req.name);
-    lock.persist_and_log(&format!(
-        "Renamed room {} to {}",
-        req.room_id,
-        req.name.trim()
-    ))?;
+    lock.persist_and_log(
+        session.id(),
+        &format!("Renamed room {} to {}", req.room_id, req.name.trim()),
+    )?;
+    let response = HassRoomsResponse {
+        rooms: lock.config_normalized().rooms.clone(),
+    };
+    drop(lock);
+
+    {
+        let res = state.res.lock().await;
+        res.hass_request(HassRequest::UpdateRooms)?;
+    }
+
+    Ok(Json(response))
+}
+
+/// Change how the brightness of a room is derived from its lights
+async fn put_room_brightness(
+    State(state): State<AppState>,
+    session: UiSession,
+    Json(req): Json<HassRoomBrightnessRequest>,
+) -> BifrostApiResult<Json<HassRoomsResponse>> {
+    let ui = state.hass_ui();
+    let mut lock = ui.lock().await;
+    lock.set_room_brightness_strategy(&req.room_id, req.brightness_strategy);
+    lock.persist_and_log(
+        session.id(),
+        &format!(
+            "Set brightness strategy of room {} to {:?}",
+            req.room_id, req.brightness_strategy
+        ),
+    )?;
     let response = HassRoomsResponse {
-        rooms: lock.config_normalized().rooms,
+        rooms: lock.config_normalized().rooms.clone(),
+    };
+    drop(lock);
+
+    // the backends derive the room state again when updating rooms
+    state
+        .res
+        .lock()
+        .await
+        .hass_request(HassRequest::UpdateRooms)?;
+
+    Ok(Json(response))
+}
+
+/// Place the devices of a room in a room of another backend (e.g. the room of
+/// a z2m group with the same name), instead of a room of its own
+async fn put_room_merge(
+    State(state): State<AppState>,
+    session: UiSession,
+    Json(req): Json<HassRoomMergeRequest>,
+) -> BifrostApiResult<Json<HassRoomsResponse>> {
+    if let Some(target) = req.target {
+        state.res.lock().await.get_id::<Room>(target)?;
+    }
+
+    let ui = state.hass_ui();
+    let mut lock = ui.lock().await;
+    lock.set_room_merge(&req.room_id, req.target);
+    let message = req.target.map_or_else(
+        || format!("Unmerged room {}", req.room_id),
+        |target| format!("Merged room {} into {target}", req.room_id),
+    );
+    lock.persist_and_log(session.id(), &message)?;
+    let response = HassRoomsResponse {
+        rooms: lock.config_normalized().rooms.clone(),
     };
     drop(lock);
 
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassUpdateRooms)?;
+        res.hass_request(HassRequest::UpdateRooms)?;
     }
 
     Ok(Json(response))
@@ -290,20 +465,21 @@ async fn put_room(
 
 async fn delete_room(
     State(state): State<AppState>,
+    session: UiSession,
     Json(req): Json<HassRoomDeleteRequest>,
 ) -> BifrostApiResult<Json<HassRoomsResponse>> {
     let ui = state.hass_ui();
     let mut lock = ui.lock().await;
     lock.remove_room(&req.room_id);
-    lock.persist_and_log(&format!("Removed room {}", req.room_id))?;
+    lock.persist_and_log(session.id(), &format!("Removed room {}", req.room_id))?;
     let response = HassRoomsResponse {
-        rooms: lock.config_normalized().rooms,
+        rooms: lock.config_normalized().rooms.clone(),
     };
     drop(lock);
 
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassUpdateRooms)?;
+        res.hass_request(HassRequest::UpdateRooms)?;
     }
 
     Ok(Json(response))
@@ -315,15 +491,23 @@ async fn get_logs(State(state): State<AppState>) -> BifrostApiResult<Json<HassLo
     Ok(Json(HassLogsResponse { logs }))
 }
 
+async fn get_timeline(
+    State(state): State<AppState>,
+    Query(query): Query<TimelineQuery>,
+) -> BifrostApiResult<Json<HassTimelineResponse>> {
+    let ui = state.hass_ui();
+    let entries = ui.lock().await.timeline.query(&query);
+    Ok(Json(HassTimelineResponse { entries }))
+}
+
 async fn get_bridge_info(State(state): State<AppState>) -> BifrostApiResult<Json<HassBridgeInfo>> {
     let conf = state.config();
     let bridge_id = hue::bridge_id(conf.bridge.mac);
     let software_version = state
-        .updater()
+        .res
         .lock()
         .await
-        .get()
-        .await
+        .bridge_version()
         .get_software_version();
     let linkbutton_active = state.linkbutton_active().await;
 
@@ -335,6 +519,7 @@ async fn get_bridge_info(State(state): State<AppState>) -> BifrostApiResult<Json
         defaults,
         sync_areas,
         sync_status,
+        retry_queue,
         ui_timezone,
         hass_lat,
         hass_long,
@@ -346,19 +531,7 @@ async fn get_bridge_info(State(state): State<AppState>) -> BifrostApiResult<Json
         let included = lock
             .entities
             .iter()
-            .filter(|ent| {
-                let mut include = cfg.should_include(&ent.entity_id, &ent.name, ent.available);
-                if ent.domain == "binary_sensor" {
-                    let detected = ent.sensor_kind.unwrap_or(HassSensorKind::Ignore);
-                    if matches!(
-                        cfg.sensor_kind(&ent.entity_id, detected),
-                        HassSensorKind::Ignore
-                    ) {
-                        include = false;
-                    }
-                }
-                include
-            })
+            .filter(|ent| ent.is_included(&cfg))
             .count();
         let hidden = total.saturating_sub(included);
         let room_count = cfg.rooms.len();
@@ -372,6 +545,7 @@ async fn get_bridge_info(State(state): State<AppState>) -> BifrostApiResult<Json
             defaults,
             sync_areas,
             lock.sync.clone(),
+            lock.retry_queue.clone(),
             cfg.hass_timezone,
             cfg.hass_lat,
             cfg.hass_long,
@@ -397,11 +571,13 @@ async fn get_bridge_info(State(state): State<AppState>) -> BifrostApiResult<Json
         default_add_new_devices_to_hue: defaults,
         sync_hass_areas_to_rooms: sync_areas,
         sync_status,
+        retry_queue,
     }))
 }
 
 async fn post_linkbutton(
     State(state): State<AppState>,
+    session: UiSession,
 ) -> BifrostApiResult<Json<HassLinkButtonResponse>> {
     state
         .press_linkbutton(Duration::from_secs(LINKBUTTON_DURATION_SECS))
@@ -410,10 +586,14 @@ async fn post_linkbutton(
     {
         let ui = state.hass_ui();
         let mut lock = ui.lock().await;
-        lock.push_log(format!(
-            "Virtual bridge button pressed ({}s active)",
-            LINKBUTTON_DURATION_SECS
-        ));
+        lock.push_session_event(
+            session.id(),
+            TimelineCategory::Pairing,
+            format!(
+                "Virtual bridge button pressed ({}s active)",
+                LINKBUTTON_DURATION_SECS
+            ),
+        );
     }
 
     Ok(Json(HassLinkButtonResponse {
@@ -422,54 +602,119 @@ async fn post_linkbutton(
     }))
 }
 
-async fn post_sync(State(state): State<AppState>) -> BifrostApiResult<Json<HassSyncResponse>> {
+async fn post_sync(
+    State(state): State<AppState>,
+    session: UiSession,
+) -> BifrostApiResult<Json<HassSyncResponse>> {
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassSync)?;
+        res.hass_request(HassRequest::Sync)?;
     }
-    let sync = state.hass_ui().lock().await.sync.clone();
+    let sync = {
+        let ui = state.hass_ui();
+        let mut lock = ui.lock().await;
+        lock.mark_sync_requested(session.id());
+        lock.sync.clone()
+    };
     Ok(Json(HassSyncResponse { queued: true, sync }))
 }
 
-async fn post_apply(State(state): State<AppState>) -> BifrostApiResult<Json<HassApplyResponse>> {
+fn verify_response(backends: &[String], reports: Vec<HassVerifyReport>) -> HassVerifyResponse {
+    let complete = backends
+        .iter()
+        .all(|name| reports.iter().any(|report| &report.backend == name));
+    HassVerifyResponse { complete, reports }
+}
+
+/// Request a consistency check (or a reconcile) from the Home Assistant
+/// backends, and wait for their reports
+async fn request_verify(state: &AppState, reconcile: bool) -> BifrostApiResult<HassVerifyResponse> {
+    let backends = backend_names(state);
+    let id = Uuid::new_v4();
+    let ui = state.hass_ui();
+    let mut reports_rx = ui.lock().await.subscribe_verify(id);
+
+    let req = if reconcile {
+        HassRequest::Reconcile(id)
+    } else {
+        HassRequest::Verify(id)
+    };
+    let sent = state.res.lock().await.hass_request(req);
+
+    // every backend reports once per request
+    let mut reports = vec![];
+    if sent.is_ok() {
+        let deadline = Instant::now() + Duration::from_secs(VERIFY_TIMEOUT_SECS);
+        while reports.len() < backends.len() {
+            match tokio::time::timeout_at(deadline, reports_rx.recv()).await {
+                Ok(Some(report)) => reports.push(report),
+                Ok(None) | Err(_) => break,
+            }
+        }
+    }
+    ui.lock().await.unsubscribe_verify(&id);
+    sent?;
+
+    Ok(verify_response(&backends, reports))
+}
+
+async fn get_verify(State(state): State<AppState>) -> BifrostApiResult<Json<HassVerifyResponse>> {
+    let reports = state
+        .hass_ui()
+        .lock()
+        .await
+        .verify
+        .values()
+        .cloned()
+        .collect();
+    Ok(Json(verify_response(&backend_names(&state), reports)))
+}
+
+async fn post_verify(State(state): State<AppState>) -> BifrostApiResult<Json<HassVerifyResponse>> {
+    Ok(Json(request_verify(&state, false).await?))
+}
+
+async fn post_reconcile(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<HassVerifyResponse>> {
+    Ok(Json(request_verify(&state, true).await?))
+}
+
+async fn post_apply(
+    State(state): State<AppState>,
+    session: UiSession,
+) -> BifrostApiResult<Json<HassApplyResponse>> {
     let (cfg, entities) = {
         let ui = state.hass_ui();
         let lock = ui.lock().await;
         (lock.config_normalized(), lock.entities.clone())
     };
 
-    let backend_name = state
-        .config()
-        .hass
-        .servers
-        .keys()
-        .next()
-        .cloned()
-        .unwrap_or_else(|| "homeassistant".to_string());
-
-    let mut keep_device_rids = HashSet::new();
-    for ent in &entities {
-        let mut include = cfg.should_include(&ent.entity_id, &ent.name, ent.available);
-        if ent.domain == "binary_sensor" {
-            let detected = ent.sensor_kind.unwrap_or(HassSensorKind::Ignore);
-            if matches!(
-                cfg.sensor_kind(&ent.entity_id, detected),
-                HassSensorKind::Ignore
-            ) {
-                include = false;
-            }
-        }
-
-        if include {
-            let link = RType::Device
-                .deterministic(format!("hass:{}:{}:device", backend_name, ent.entity_id));
-            keep_device_rids.insert(link.rid);
-        }
-    }
+    let included: HashSet<_> = entities
+        .iter()
+        .filter(|ent| ent.is_included(&cfg))
+        .map(|ent| ent.entity_id.as_str())
+        .collect();
 
     let removed_devices = {
         let mut removed = 0_usize;
         let mut res = state.res.lock().await;
+        // the devices are found through the bindings saved by the backends,
+        // so the links match the ones each backend created
+        let mut keep_device_rids: HashSet<_> = entity_bindings(&res)
+            .into_iter()
+            .filter(|(_, aux)| included.contains(aux.entity_id.as_str()))
+            .map(|(device, _)| device.rid)
+            .collect();
+        // devices created before the bindings were saved have no binding, but
+        // were linked by the entity id
+        for name in state.config().hass.servers.keys() {
+            keep_device_rids.extend(included.iter().map(|entity_id| {
+                RType::Device
+                    .deterministic(format!("hass:{name}:{entity_id}:device"))
+                    .rid
+            }));
+        }
         let device_ids = res.get_resource_ids_by_type(RType::Device);
         for rid in device_ids {
             if keep_device_rids.contains(&rid) {
@@ -478,10 +723,8 @@ async fn post_apply(State(state): State<AppState>) -> BifrostApiResult<Json<Hass
             let Ok(dev) = res.get_id::<Device>(rid) else {
                 continue;
             };
-            if dev.product_data.manufacturer_name != "Home Assistant" {
-                continue;
-            }
-            if !dev.product_data.model_id.starts_with("hass-") {
+            // the daylight sensor has no entity, it is kept while enabled
+            if !is_hass_device(dev) || dev.product_data.model_id == HassBackend::DAYLIGHT_MODEL_ID {
                 continue;
             }
             if res.delete(&RType::Device.link_to(rid)).is_ok() {
@@ -494,9 +737,11 @@ async fn post_apply(State(state): State<AppState>) -> BifrostApiResult<Json<Hass
     {
         let ui = state.hass_ui();
         let mut lock = ui.lock().await;
-        lock.push_log(format!(
-            "Applied selection to Hue bridge (removed {removed_devices} devices)"
-        ));
+        lock.push_session_event(
+            session.id(),
+            TimelineCategory::Config,
+            format!("Applied selection to Hue bridge (removed {removed_devices} devices)"),
+        );
     }
 
     Ok(Json(HassApplyResponse {
@@ -505,24 +750,140 @@ async fn post_apply(State(state): State<AppState>) -> BifrostApiResult<Json<Hass
     }))
 }
 
+/// Entity bindings saved by the Home Assistant backends, with the device of
+/// each entity. Hosted entities (e.g. light level sensors) are saved with
+/// their service, under the device of the host entity.
+fn entity_bindings(res: &Resources) -> Vec<(ResourceLink, HassEntityAux)> {
+    let mut bindings = vec![];
+    for rid in res.get_resource_ids_by_type(RType::Device) {
+        let device_link = RType::Device.link_to(rid);
+        let Ok(dev) = res.get::<Device>(&device_link) else {
+            continue;
+        };
+        for link in std::iter::once(&device_link).chain(&dev.services) {
+            if let Ok(AuxData {
+                hass: Some(aux), ..
+            }) = res.aux_get(link)
+            {
+                bindings.push((device_link, aux.clone()));
+            }
+        }
+    }
+    bindings
+}
+
+/// Is `dev` a device imported from Home Assistant?
+fn is_hass_device(dev: &Device) -> bool {
+    dev.product_data.manufacturer_name == "Home Assistant"
+        && dev.product_data.model_id.starts_with("hass-")
+}
+
+/// Delete the resources in `links`. Returns the number of deleted resources.
+fn delete_all(res: &mut Resources, links: impl IntoIterator<Item = ResourceLink>) -> usize {
+    links
+        .into_iter()
+        .filter(|link| res.delete(link).is_ok())
+        .count()
+}
+
+/// Delete the scenes in `links`. Scenes owned by a backend are deleted by
+/// that backend, so it can also remove its own copy (like the scene exported
+/// to Home Assistant). Returns the number of deleted scenes.
+fn delete_scenes(res: &mut Resources, links: impl IntoIterator<Item = ResourceLink>) -> usize {
+    links
+        .into_iter()
+        .filter(|link| {
+            if res.backend_owner(&link.rid).is_some() {
+                res.backend_request(BackendRequest::Delete(*link)).is_ok()
+            } else {
+                res.delete(link).is_ok()
+            }
+        })
+        .count()
+}
+
+async fn post_reset_bridge_token(
+    State(state): State<AppState>,
+    Json(req): Json<HassResetTokenRequest>,
+) -> Json<HassResetTokenResponse> {
+    let token = state.reset_guard().issue(req.scope).await;
+
+    Json(HassResetTokenResponse {
+        token,
+        scope: req.scope,
+        expires_in_seconds: ResetGuard::TOKEN_VALIDITY.as_secs(),
+    })
+}
+
 async fn post_reset_bridge(
     State(state): State<AppState>,
+    session: UiSession,
+    Json(req): Json<HassResetBridgeRequest>,
 ) -> BifrostApiResult<Json<HassResetBridgeResponse>> {
+    if !state.reset_guard().confirm(&req.token, req.scope).await {
+        return Err(ApiError::ResetNotConfirmed.into());
+    }
+
     let conf = state.config();
     let bridge_id = hue::bridge_id(conf.bridge.mac);
 
-    {
-        let mut res = state.res.lock().await;
-        res.factory_reset(&bridge_id)?;
-    }
+    let (removed, message) = match req.scope {
+        HassResetScope::All => {
+            let mut res = state.res.lock().await;
+            let removed = res.get_resources().len();
+            res.factory_reset(&bridge_id)?;
+            (
+                removed,
+                "Hue bridge factory reset (resources cleared)".to_string(),
+            )
+        }
+        HassResetScope::HassDevices => {
+            let mut res = state.res.lock().await;
+            let devices: Vec<_> = res
+                .get_resource_ids_by_type(RType::Device)
+                .into_iter()
+                .filter(|rid| res.get_id::<Device>(*rid).is_ok_and(is_hass_device))
+                .map(|rid| RType::Device.link_to(rid))
+                .collect();
+            let removed = delete_all(&mut res, devices);
+            (removed, format!("Removed {removed} Home Assistant devices"))
+        }
+        HassResetScope::Scenes => {
+            let mut res = state.res.lock().await;
+            let scenes: Vec<_> = res
+                .get_resource_ids_by_type(RType::Scene)
+                .into_iter()
+                .map(|rid| RType::Scene.link_to(rid))
+                .collect();
+            let removed = delete_scenes(&mut res, scenes);
+            (removed, format!("Removed {removed} scenes"))
+        }
+        HassResetScope::Pairings => {
+            let users = state.res.lock().await.clear_api_users();
+            let ui = state.hass_ui();
+            let entries = ui
+                .lock()
+                .await
+                .timeline
+                .clear_category(TimelineCategory::Pairing);
+            (
+                users + entries,
+                format!("Removed {users} paired apps and {entries} pairing entries"),
+            )
+        }
+    };
 
     {
         let ui = state.hass_ui();
         let mut lock = ui.lock().await;
-        lock.push_log("Hue bridge factory reset (resources cleared)");
+        lock.push_session_event(session.id(), TimelineCategory::Config, message);
     }
 
-    Ok(Json(HassResetBridgeResponse { reset: true }))
+    Ok(Json(HassResetBridgeResponse {
+        reset: true,
+        scope: req.scope,
+        removed,
+    }))
 }
 
 async fn get_runtime_config(
@@ -541,16 +902,16 @@ async fn put_runtime_config(
         let runtime = state.hass_runtime();
         let mut lock = runtime.lock().await;
         lock.set_config_update(update);
-        lock.save()?;
+        lock.save();
         lock.public_config()
     };
 
     {
         let res = state.res.lock().await;
         if config.enabled {
-            res.backend_request(BackendRequest::HassConnect)?;
+            res.hass_request(HassRequest::Connect)?;
         } else {
-            res.backend_request(BackendRequest::HassDisconnect)?;
+            res.hass_request(HassRequest::Disconnect)?;
         }
     }
 
@@ -565,7 +926,7 @@ async fn put_token(
         let runtime = state.hass_runtime();
         let mut lock = runtime.lock().await;
         lock.set_token(req.token)?;
-        lock.save()?;
+        lock.save();
         lock.public_config()
     };
     Ok(Json(config))
@@ -578,7 +939,7 @@ async fn delete_token(
         let runtime = state.hass_runtime();
         let mut lock = runtime.lock().await;
         lock.clear_token();
-        lock.save()?;
+        lock.save();
         lock.public_config()
     };
     Ok(Json(config))
@@ -591,12 +952,12 @@ async fn post_connect(
         let runtime = state.hass_runtime();
         let mut lock = runtime.lock().await;
         lock.config.enabled = true;
-        lock.save()?;
+        lock.save();
         lock.public_config()
     };
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassConnect)?;
+        res.hass_request(HassRequest::Connect)?;
     }
 
     Ok(Json(HassConnectResponse {
@@ -612,12 +973,12 @@ async fn post_disconnect(
         let runtime = state.hass_runtime();
         let mut lock = runtime.lock().await;
         lock.config.enabled = false;
-        lock.save()?;
+        lock.save();
         lock.public_config()
     };
     {
         let res = state.res.lock().await;
-        res.backend_request(BackendRequest::HassDisconnect)?;
+        res.hass_request(HassRequest::Disconnect)?;
     }
 
     Ok(Json(HassConnectResponse {
@@ -639,28 +1000,54 @@ async fn post_patina_event(
     let ui = state.hass_ui();
     let mut lock = ui.lock().await;
     lock.record_patina_event(&req.kind, req.key.as_deref());
-    lock.save_config()?;
+    lock.save_config();
     Ok(Json(lock.patina_public()))
 }
 
+async fn get_patina_usage(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<HassPatinaUsageReport>> {
+    let ui = state.hass_ui();
+    let usage = ui.lock().await.patina_usage();
+    Ok(Json(usage))
+}
+
+async fn delete_patina_usage(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<HassPatinaUsageReport>> {
+    let ui = state.hass_ui();
+    let mut lock = ui.lock().await;
+    lock.reset_patina_usage();
+    lock.save_config();
+    Ok(Json(lock.patina_usage()))
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .merge(ui_router())
         .route("/hass/ui-payload", get(get_ui_payload))
+        .route("/hass/session", get(get_session).post(post_session))
         .route("/hass/ui-config", get(get_ui_config).put(put_ui_config))
         .route("/hass/entities", get(get_entities))
         .route("/hass/entity", put(patch_entity))
+        .route("/hass/entity/{id}/state", put(put_entity_state))
         .route(
             "/hass/rooms",
             get(get_rooms).post(post_room).delete(delete_room),
         )
         .route("/hass/room", put(put_room))
+        .route("/hass/room/brightness", put(put_room_brightness))
+        .route("/hass/room/merge", put(put_room_merge))
         .route("/hass/logs", get(get_logs))
+        .route("/hass/timeline", get(get_timeline))
         .route("/hass/bridge-info", get(get_bridge_info))
         .route("/hass/linkbutton", post(post_linkbutton))
         .route("/hass/sync", post(post_sync))
+        .route("/hass/verify", get(get_verify).post(post_verify))
+        .route("/hass/verify/reconcile", post(post_reconcile))
         .route("/hass/apply", post(post_apply))
         .route("/hass/reset-bridge", post(post_reset_bridge))
+        .route("/hass/reset-bridge/token", post(post_reset_bridge_token))
         .route(
             "/hass/runtime-config",
             get(get_runtime_config).put(put_runtime_config),
@@ -670,4 +1057,8 @@ pub fn router() -> Router<AppState> {
         .route("/hass/disconnect", post(post_disconnect))
         .route("/hass/patina", get(get_patina))
         .route("/hass/patina/event", post(post_patina_event))
+        .route(
+            "/hass/patina/usage",
+            get(get_patina_usage).delete(delete_patina_usage),
+        )
 }
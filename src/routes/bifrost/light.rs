@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use uuid::Uuid;
+
+use bifrost_api::light::{LightSegmentMap, LightSegments, LightStartup};
+use hue::api::{Device, Entertainment, Light, RType};
+
+use crate::error::{ApiError, ApiResult};
+use crate::resource::Resources;
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn get_startups(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<Uuid, LightStartup>>> {
+    Ok(Json(state.res.lock().await.light_startups().clone()))
+}
+
+async fn get_startup(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<Option<LightStartup>>> {
+    let lock = state.res.lock().await;
+
+    lock.get_id::<Light>(id)?;
+
+    Ok(Json(lock.light_startup(&RType::Light.link_to(id)).cloned()))
+}
+
+async fn put_startup(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(startup): Json<LightStartup>,
+) -> BifrostApiResult<Json<()>> {
+    let mut lock = state.res.lock().await;
+
+    lock.get_id::<Light>(id)?;
+
+    log::info!("Setting startup state of light {id}: {startup:?}");
+
+    // an empty startup state does nothing, so there is no need to keep it
+    let startup = Some(startup).filter(|st| !st.is_empty());
+    lock.set_light_startup(&RType::Light.link_to(id), startup);
+
+    Ok(Json(()))
+}
+
+async fn delete_startup(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<()>> {
+    log::info!("Removing startup state of light {id}");
+
+    state
+        .res
+        .lock()
+        .await
+        .set_light_startup(&RType::Light.link_to(id), None);
+
+    Ok(Json(()))
+}
+
+/// Entertainment segments of light `id`, as announced by its entertainment
+/// service
+fn light_segments(res: &Resources, id: Uuid) -> ApiResult<LightSegments> {
+    let light = res.get_id::<Light>(id)?;
+    let count = res
+        .get::<Device>(&light.owner)?
+        .entertainment_service()
+        .and_then(|link| res.get::<Entertainment>(link).ok())
+        .and_then(|ent| ent.segments.as_ref())
+        .map_or(0, |segments| segments.segments.len());
+
+    Ok(LightSegments {
+        name: light.metadata.name.clone(),
+        count: u32::try_from(count)?,
+        map: res.light_segment_map(&RType::Light.link_to(id)).cloned(),
+    })
+}
+
+async fn get_all_segments(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<Uuid, LightSegments>>> {
+    let lock = state.res.lock().await;
+
+    let mut res = BTreeMap::new();
+    for id in lock.get_resource_ids_by_type(RType::Light) {
+        let segments = light_segments(&lock, id)?;
+        if segments.count > 1 {
+            res.insert(id, segments);
+        }
+    }
+
+    Ok(Json(res))
+}
+
+async fn get_segments(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<LightSegments>> {
+    Ok(Json(light_segments(&*state.res.lock().await, id)?))
+}
+
+async fn put_segments(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(map): Json<LightSegmentMap>,
+) -> BifrostApiResult<Json<()>> {
+    let mut lock = state.res.lock().await;
+
+    let segments = light_segments(&lock, id)?;
+    if let Some(&segment) = map
+        .segments
+        .iter()
+        .find(|&&seg| u32::from(seg) >= segments.count)
+    {
+        return Err(ApiError::SegmentNotFound(id, segment, segments.count).into());
+    }
+
+    log::info!("Setting segment order of light {id}: {:?}", map.segments);
+
+    // an empty map changes nothing, so there is no need to keep it
+    let map = Some(map).filter(|map| !map.segments.is_empty());
+    lock.set_light_segment_map(&RType::Light.link_to(id), map);
+
+    Ok(Json(()))
+}
+
+async fn delete_segments(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<()>> {
+    log::info!("Restoring default segment order of light {id}");
+
+    state
+        .res
+        .lock()
+        .await
+        .set_light_segment_map(&RType::Light.link_to(id), None);
+
+    Ok(Json(()))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/startup", get(get_startups))
+        .route(
+            "/{id}/startup",
+            get(get_startup).put(put_startup).delete(delete_startup),
+        )
+        .route("/segments", get(get_all_segments))
+        .route(
+            "/{id}/segments",
+            get(get_segments).put(put_segments).delete(delete_segments),
+        )
+}
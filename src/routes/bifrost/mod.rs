@@ -1,6 +1,17 @@
+pub mod action;
 pub mod backend;
+pub mod debug;
+pub mod dev;
 pub mod hass;
+pub mod light;
+pub mod presence;
+pub mod resource;
+pub mod room;
+pub mod rule;
+pub mod scene;
 pub mod service;
+pub mod updater;
+pub mod voice;
 pub mod websocket;
 
 use std::error::Error;
@@ -9,11 +20,15 @@ use axum::Router;
 use axum::extract::State;
 use axum::response::{IntoResponse, Response};
 use axum::routing::{any, get};
+use chrono::{DateTime, Utc};
 use hyper::StatusCode;
+use hyper::header::CONTENT_TYPE;
 use serde::Serialize;
-use serde_json::json;
+use serde_json::{Value, json};
 
+use bifrost_api::about::About;
 use bifrost_api::config::AppConfig;
+use bifrost_api::csrf::CsrfToken;
 
 use crate::routes::bifrost::websocket::websocket;
 use crate::routes::extractor::Json;
@@ -50,11 +65,73 @@ async fn get_config(State(state): State<AppState>) -> BifrostApiResult<Json<AppC
     Ok(Json((*state.config()).clone()))
 }
 
+/// Cargo features this binary was built with
+const FEATURES: &[(&str, bool)] = &[
+    ("server-banner", cfg!(feature = "server-banner")),
+    ("alloc-stats", cfg!(feature = "alloc-stats")),
+    ("mqtt", cfg!(feature = "mqtt")),
+];
+
+/// Version and build information, for bug reports
+async fn get_about(State(state): State<AppState>) -> Json<About> {
+    let build_date = option_env!("BIFROST_BUILD_TIME")
+        .and_then(|secs| secs.parse().ok())
+        .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339());
+
+    Json(About {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: option_env!("BIFROST_GIT_HASH").map(ToString::to_string),
+        build_date,
+        rust_version: option_env!("BIFROST_RUST_VERSION").map(ToString::to_string),
+        uptime_secs: state.uptime().as_secs(),
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| (*name).to_string())
+            .collect(),
+    })
+}
+
+/// Csrf token to send with requests that change anything
+async fn get_csrf(State(state): State<AppState>) -> Json<CsrfToken> {
+    Json(CsrfToken {
+        token: state.csrf().token().map(ToString::to_string),
+    })
+}
+
+/// The state database of this instance
+async fn get_state(State(state): State<AppState>) -> BifrostApiResult<Json<Value>> {
+    Ok(Json(state.res.lock().await.export()?))
+}
+
+/// Route and backend metrics, in the prometheus text exposition format
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut body = state.metrics().prometheus().await;
+    body += &state.latency().prometheus().await;
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .nest("/service", service::router())
         .nest("/backend", backend::router())
+        .nest("/debug", debug::router())
+        .nest("/dev", dev::router())
+        .nest("/updater", updater::router())
+        .nest("/resources", resource::router())
+        .nest("/rooms", room::router())
+        .nest("/lights", light::router())
+        .nest("/presence", presence::router())
+        .nest("/rules", rule::router())
+        .nest("/scenes", scene::router())
+        .nest("/actions", action::router())
+        .nest("/voice", voice::router())
         .merge(hass::router())
+        .route("/about", get(get_about))
         .route("/config", get(get_config))
+        .route("/state", get(get_state))
+        .route("/csrf", get(get_csrf))
+        .route("/metrics", get(get_metrics))
         .route("/ws", any(websocket))
 }
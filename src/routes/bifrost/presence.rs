@@ -0,0 +1,27 @@
+use axum::Router;
+use axum::extract::State;
+use axum::routing::get;
+
+use bifrost_api::presence::{PresenceStatus, PresenceUpdate};
+
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn get_presence(State(state): State<AppState>) -> BifrostApiResult<Json<PresenceStatus>> {
+    Ok(Json(state.presence().status().await))
+}
+
+async fn put_presence(
+    State(state): State<AppState>,
+    Json(upd): Json<PresenceUpdate>,
+) -> BifrostApiResult<Json<PresenceStatus>> {
+    let presence = state.presence();
+    presence.set_enabled(upd.enabled).await;
+
+    Ok(Json(presence.status().await))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(get_presence).put(put_presence))
+}
@@ -0,0 +1,43 @@
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use uuid::Uuid;
+
+use bifrost_api::resource::ResourceOwner;
+use hue::api::ResourceLink;
+
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn get_resource_owner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<ResourceOwner>> {
+    let lock = state.res.lock().await;
+
+    let resource = ResourceLink::new(id, lock.get_resource_by_id(&id)?.obj.rtype());
+
+    let (claimed, owner) = match lock.backend_owner(&id) {
+        Some((rid, owner)) => {
+            let claimed = lock
+                .get_resource_by_id(&rid)
+                .map(|rr| ResourceLink::new(rid, rr.obj.rtype()))
+                .ok();
+            (claimed, Some(owner.to_string()))
+        }
+        None => (None, None),
+    };
+
+    drop(lock);
+
+    Ok(Json(ResourceOwner {
+        resource,
+        owner,
+        claimed,
+    }))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/{id}/owner", get(get_resource_owner))
+}
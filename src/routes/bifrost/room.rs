@@ -0,0 +1,189 @@
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use uuid::Uuid;
+
+use bifrost_api::action::{ActionFailure, ActionResponse};
+use bifrost_api::backend::BackendRequest;
+use bifrost_api::room::{LightPowerupState, RoomDuplicate, RoomSummary};
+use hue::api::{
+    Device, Light, LightPowerupUpdate, LightUpdate, Motion, RType, ResourceLink, Room, Temperature,
+};
+
+use crate::resource::Resources;
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+#[allow(clippy::cast_precision_loss)]
+fn average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn room_summary(res: &Resources, room: &Room, link: ResourceLink) -> RoomSummary {
+    let mut lights = 0;
+    let mut brightness = vec![];
+    let mut motion_sensors = 0;
+    let mut motion = false;
+    let mut temperatures = vec![];
+
+    let services = room
+        .children
+        .iter()
+        .filter_map(|child| res.get::<Device>(child).ok())
+        .flat_map(|dev| &dev.services);
+
+    for svc in services {
+        match svc.rtype {
+            RType::Light => {
+                let Ok(light) = res.get::<Light>(svc) else {
+                    continue;
+                };
+                lights += 1;
+                if light.on.on {
+                    brightness.push(light.dimming.map_or(100.0, |dim| dim.brightness));
+                }
+            }
+            RType::Motion => {
+                let Ok(sensor) = res.get::<Motion>(svc) else {
+                    continue;
+                };
+                if !sensor.enabled {
+                    continue;
+                }
+                motion_sensors += 1;
+                motion |= sensor.motion["motion"].as_bool().unwrap_or(false);
+            }
+            RType::Temperature => {
+                let Ok(sensor) = res.get::<Temperature>(svc) else {
+                    continue;
+                };
+                if !sensor.enabled {
+                    continue;
+                }
+                if let Some(temp) = sensor.temperature["temperature"].as_f64() {
+                    temperatures.push(temp);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    RoomSummary {
+        room: link,
+        name: room.metadata.name.clone(),
+        lights,
+        lights_on: brightness.len(),
+        average_brightness: average(&brightness),
+        motion_sensors,
+        motion,
+        temperature: average(&temperatures),
+    }
+}
+
+async fn get_summary(State(state): State<AppState>) -> BifrostApiResult<Json<Vec<RoomSummary>>> {
+    let lock = state.res.lock().await;
+
+    let mut summaries: Vec<RoomSummary> = lock
+        .get_resource_ids_by_type(RType::Room)
+        .into_iter()
+        .filter_map(|rid| {
+            let room = lock.get_id::<Room>(rid).ok()?;
+            Some(room_summary(&lock, room, RType::Room.link_to(rid)))
+        })
+        .collect();
+
+    drop(lock);
+
+    summaries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Json(summaries))
+}
+
+async fn get_duplicates(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<Vec<RoomDuplicate>>> {
+    Ok(Json(state.res.lock().await.duplicate_rooms()))
+}
+
+/// Links to all lights in `room`
+fn room_lights(res: &Resources, room: &Room) -> Vec<ResourceLink> {
+    room.children
+        .iter()
+        .filter_map(|child| res.get::<Device>(child).ok())
+        .flat_map(|dev| &dev.services)
+        .filter(|svc| svc.rtype == RType::Light)
+        .copied()
+        .collect()
+}
+
+async fn get_powerup(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<Vec<LightPowerupState>>> {
+    let lock = state.res.lock().await;
+
+    let room = lock.get_id::<Room>(id)?;
+
+    let lights = room_lights(&lock, room)
+        .into_iter()
+        .filter_map(|link| {
+            let light = lock.get::<Light>(&link).ok()?;
+            Some(LightPowerupState {
+                light: link,
+                name: light.metadata.name.clone(),
+                powerup: light.powerup.clone(),
+            })
+        })
+        .collect();
+
+    drop(lock);
+
+    Ok(Json(lights))
+}
+
+async fn put_powerup(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(powerup): Json<LightPowerupUpdate>,
+) -> BifrostApiResult<Json<ActionResponse>> {
+    let lock = state.res.lock().await;
+
+    let room = lock.get_id::<Room>(id)?;
+
+    // keep going after a failure, so one unreachable light does not leave
+    // the rest of the room unconfigured
+    let mut targets = vec![];
+    let mut failed = vec![];
+    for light in room_lights(&lock, room) {
+        let upd = LightUpdate {
+            powerup: Some(powerup.clone()),
+            ..LightUpdate::default()
+        };
+        match lock.backend_request(BackendRequest::LightUpdate(light, upd)) {
+            Ok(()) => targets.push(light),
+            Err(err) => {
+                log::warn!("Failed to set power-on behavior of {light:?}: {err}");
+                failed.push(ActionFailure {
+                    target: light,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    drop(lock);
+
+    Ok(Json(ActionResponse { targets, failed }))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/summary", get(get_summary))
+        .route("/duplicates", get(get_duplicates))
+        .route("/{id}/powerup", get(get_powerup).put(put_powerup))
+}
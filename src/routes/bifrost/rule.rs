@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use uuid::Uuid;
+
+use bifrost_api::rule::{ContactRule, MotionRule, RuleAction};
+use hue::api::{Motion, RType, ResourceLink, Room, Scene};
+use hue::error::HueError;
+
+use crate::error::ApiError;
+use crate::resource::Resources;
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+/// Make sure the action refers to an existing scene (if any) in `room`
+fn validate_action(
+    res: &Resources,
+    room: &ResourceLink,
+    action: &RuleAction,
+) -> BifrostApiResult<()> {
+    if let RuleAction::Scene { scene } = action {
+        let group = res.get::<Scene>(scene)?.group;
+        if group.rid != room.rid {
+            return Err(ApiError::RuleSceneWrongRoom(scene.rid, room.rid).into());
+        }
+    }
+    Ok(())
+}
+
+/// Make sure the rule refers to an existing motion sensor, room and scenes
+fn validate(res: &Resources, rule: &MotionRule) -> BifrostApiResult<()> {
+    res.get::<Motion>(&rule.sensor)?;
+    res.get::<Room>(&rule.room)?;
+    validate_action(res, &rule.room, &rule.day)?;
+    if let Some(night) = &rule.night {
+        validate_action(res, &rule.room, night)?;
+    }
+    Ok(())
+}
+
+/// Make sure the rule refers to an existing contact sensor, room and scenes
+fn validate_contact(res: &Resources, rule: &ContactRule) -> BifrostApiResult<()> {
+    let sensor = res.get_resource(&rule.sensor)?.obj.rtype();
+    if sensor != RType::Contact {
+        return Err(HueError::WrongType(RType::Contact, sensor).into());
+    }
+    res.get::<Room>(&rule.room)?;
+    for action in [&rule.open, &rule.close].into_iter().flatten() {
+        validate_action(res, &rule.room, action)?;
+    }
+    Ok(())
+}
+
+async fn get_rules(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<Uuid, MotionRule>>> {
+    Ok(Json(state.res.lock().await.motion_rules().clone()))
+}
+
+async fn post_rule(
+    State(state): State<AppState>,
+    Json(rule): Json<MotionRule>,
+) -> BifrostApiResult<Json<Uuid>> {
+    let mut lock = state.res.lock().await;
+
+    validate(&lock, &rule)?;
+
+    let id = Uuid::new_v4();
+    log::info!("Adding motion rule {id}: {:?}", rule.name);
+    lock.set_motion_rule(id, Some(rule));
+
+    Ok(Json(id))
+}
+
+async fn get_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<MotionRule>> {
+    let lock = state.res.lock().await;
+
+    let rule = lock.motion_rules().get(&id).ok_or(HueError::NotFound(id))?;
+
+    Ok(Json(rule.clone()))
+}
+
+async fn put_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(rule): Json<MotionRule>,
+) -> BifrostApiResult<Json<()>> {
+    let mut lock = state.res.lock().await;
+
+    if !lock.motion_rules().contains_key(&id) {
+        return Err(HueError::NotFound(id).into());
+    }
+    validate(&lock, &rule)?;
+
+    log::info!("Updating motion rule {id}: {:?}", rule.name);
+    lock.set_motion_rule(id, Some(rule));
+
+    Ok(Json(()))
+}
+
+async fn delete_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<()>> {
+    log::info!("Removing motion rule {id}");
+
+    state.res.lock().await.set_motion_rule(id, None);
+
+    Ok(Json(()))
+}
+
+async fn get_contact_rules(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<Uuid, ContactRule>>> {
+    Ok(Json(state.res.lock().await.contact_rules().clone()))
+}
+
+async fn post_contact_rule(
+    State(state): State<AppState>,
+    Json(rule): Json<ContactRule>,
+) -> BifrostApiResult<Json<Uuid>> {
+    let mut lock = state.res.lock().await;
+
+    validate_contact(&lock, &rule)?;
+
+    let id = Uuid::new_v4();
+    log::info!("Adding contact rule {id}: {:?}", rule.name);
+    lock.set_contact_rule(id, Some(rule));
+
+    Ok(Json(id))
+}
+
+async fn get_contact_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<ContactRule>> {
+    let lock = state.res.lock().await;
+
+    let rule = lock
+        .contact_rules()
+        .get(&id)
+        .ok_or(HueError::NotFound(id))?;
+
+    Ok(Json(rule.clone()))
+}
+
+async fn put_contact_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(rule): Json<ContactRule>,
+) -> BifrostApiResult<Json<()>> {
+    let mut lock = state.res.lock().await;
+
+    if !lock.contact_rules().contains_key(&id) {
+        return Err(HueError::NotFound(id).into());
+    }
+    validate_contact(&lock, &rule)?;
+
+    log::info!("Updating contact rule {id}: {:?}", rule.name);
+    lock.set_contact_rule(id, Some(rule));
+
+    Ok(Json(()))
+}
+
+async fn delete_contact_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<()>> {
+    log::info!("Removing contact rule {id}");
+
+    state.res.lock().await.set_contact_rule(id, None);
+
+    Ok(Json(()))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_rules).post(post_rule))
+        .route("/{id}", get(get_rule).put(put_rule).delete(delete_rule))
+        .route("/contact", get(get_contact_rules).post(post_contact_rule))
+        .route(
+            "/contact/{id}",
+            get(get_contact_rule)
+                .put(put_contact_rule)
+                .delete(delete_contact_rule),
+        )
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use uuid::Uuid;
+
+use bifrost_api::scene::SceneVariants;
+use hue::api::{RType, Scene};
+
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn get_all_variants(
+    State(state): State<AppState>,
+) -> BifrostApiResult<Json<BTreeMap<Uuid, SceneVariants>>> {
+    Ok(Json(state.res.lock().await.all_scene_variants().clone()))
+}
+
+async fn get_variants(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<Option<SceneVariants>>> {
+    let lock = state.res.lock().await;
+
+    lock.get_id::<Scene>(id)?;
+
+    Ok(Json(
+        lock.scene_variants(&RType::Scene.link_to(id)).cloned(),
+    ))
+}
+
+async fn put_variants(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(variants): Json<SceneVariants>,
+) -> BifrostApiResult<Json<()>> {
+    let mut lock = state.res.lock().await;
+
+    lock.get_id::<Scene>(id)?;
+    for variant in variants.scenes() {
+        lock.get::<Scene>(variant)?;
+    }
+
+    log::info!("Setting time of day variants of scene {id}: {variants:?}");
+
+    let variants = Some(variants).filter(|vars| !vars.is_empty());
+    lock.set_scene_variants(&RType::Scene.link_to(id), variants);
+
+    Ok(Json(()))
+}
+
+async fn delete_variants(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BifrostApiResult<Json<()>> {
+    log::info!("Removing time of day variants of scene {id}");
+
+    state
+        .res
+        .lock()
+        .await
+        .set_scene_variants(&RType::Scene.link_to(id), None);
+
+    Ok(Json(()))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/variants", get(get_all_variants))
+        .route(
+            "/{id}/variants",
+            get(get_variants).put(put_variants).delete(delete_variants),
+        )
+}
@@ -3,11 +3,17 @@ use std::collections::BTreeMap;
 use axum::Router;
 use axum::extract::{Path, State};
 use axum::routing::{get, put};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use uuid::Uuid;
 
+use bifrost_api::config::AppConfig;
 use bifrost_api::service::{Service, ServiceList};
+use svc::serviceid::ServiceName;
 use svc::traits::ServiceState;
 
+use crate::error::{ApiError, ApiResult};
 use crate::routes::bifrost::BifrostApiResult;
 use crate::routes::extractor::Json;
 use crate::server::appstate::AppState;
@@ -18,8 +24,17 @@ async fn service_list(state: &AppState) -> BifrostApiResult<ServiceList> {
     let mut services = BTreeMap::new();
     for (id, name) in svm.list().await? {
         let state = svm.status(id).await?;
+        let restarts = svm.restarts(id).await?;
+        let deps = svm.dependencies(id).await?;
 
-        let service = Service { id, name, state };
+        let service = Service {
+            id,
+            name,
+            state,
+            restarts,
+            depends_on: deps.depends_on,
+            waiting_for: deps.waiting_for,
+        };
         services.insert(id, service);
     }
 
@@ -50,8 +65,73 @@ async fn put_service(
     Ok(Json(uuid))
 }
 
+/// Apply (partial) settings in `overrides` to the server config for instance
+/// `name`
+fn override_server<T: Serialize + DeserializeOwned>(
+    servers: &mut BTreeMap<String, T>,
+    name: &ServiceName,
+    overrides: Value,
+) -> ApiResult<()> {
+    let server = name
+        .instance()
+        .and_then(|instance| servers.get_mut(instance))
+        .ok_or_else(|| ApiError::ServiceNotConfigurable(name.clone()))?;
+
+    let value = match (serde_json::to_value(&*server)?, overrides) {
+        (Value::Object(mut current), Value::Object(overrides)) => {
+            current.extend(overrides);
+            Value::Object(current)
+        }
+        (_, overrides) => overrides,
+    };
+    *server = serde_json::from_value(value)?;
+
+    Ok(())
+}
+
+async fn put_service_config(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(overrides): Json<Value>,
+) -> BifrostApiResult<Json<Uuid>> {
+    let mut mgr = state.manager();
+    let name = mgr.lookup_name(id).await?;
+
+    let lock = state.reconfigure_lock();
+    let _guard = lock.lock().await;
+
+    // validate the new settings, before changing anything
+    let previous = state.config();
+    let mut config = AppConfig::clone(&previous);
+    match name.name() {
+        "z2m" => override_server(&mut config.z2m.servers, &name, overrides)?,
+        "hass" => override_server(&mut config.hass.servers, &name, overrides)?,
+        _ => return Err(ApiError::ServiceNotConfigurable(name).into()),
+    }
+    state.set_config(config);
+
+    log::info!("Regenerating service {name} with new settings");
+    match mgr.regenerate(id).await {
+        Ok(uuid) => Ok(Json(uuid)),
+        Err(err) => {
+            log::error!("Failed to regenerate service {name}, restoring its settings: {err}");
+            state.set_config(AppConfig::clone(&previous));
+            // the service may already have been removed
+            let restored = match mgr.resolve(&name).await {
+                Ok(uuid) => mgr.regenerate(uuid).await,
+                Err(_) => mgr.start(&name).await,
+            };
+            if let Err(err) = restored {
+                log::error!("Failed to restore service {name}: {err}");
+            }
+            Err(err.into())
+        }
+    }
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(get_services))
         .route("/{id}", put(put_service))
+        .route("/{id}/config", put(put_service_config))
 }
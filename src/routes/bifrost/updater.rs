@@ -0,0 +1,32 @@
+use axum::Router;
+use axum::extract::State;
+use axum::routing::{get, post};
+
+use bifrost_api::updater::{ReleaseStatus, UpdaterStatus};
+
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+use crate::server::release::ReleaseChecker;
+
+async fn get_updater(State(state): State<AppState>) -> BifrostApiResult<Json<UpdaterStatus>> {
+    let status = state.updater().lock().await.status().await;
+    Ok(Json(status))
+}
+
+/// Newest Bifrost release, from the last check
+async fn get_release(State(state): State<AppState>) -> Json<ReleaseStatus> {
+    Json(state.release_checker().lock().await.status())
+}
+
+/// Check for a new Bifrost release now
+async fn post_release_check(State(state): State<AppState>) -> Json<ReleaseStatus> {
+    Json(ReleaseChecker::check(&state.release_checker()).await)
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_updater))
+        .route("/release", get(get_release))
+        .route("/release/check", post(post_release_check))
+}
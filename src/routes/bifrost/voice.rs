@@ -0,0 +1,58 @@
+use axum::Router;
+use axum::extract::State;
+use axum::routing::get;
+
+use bifrost_api::voice::VoiceDevice;
+use hue::api::{Light, RType};
+
+use crate::model::voice::{ambiguous_names, room_qualified_name};
+use crate::routes::bifrost::BifrostApiResult;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn get_devices(State(state): State<AppState>) -> BifrostApiResult<Json<Vec<VoiceDevice>>> {
+    let prefixed = state.config().bifrost.voice_room_prefix;
+    let lock = state.res.lock().await;
+
+    let mut devices = vec![];
+    for id in lock.get_resource_ids_by_type(RType::Light) {
+        let light = lock.get_id::<Light>(id)?;
+        let room = lock
+            .device_room(&light.owner)
+            .map(|room| room.metadata.name.clone());
+        let voice_name = room.as_ref().map_or_else(
+            || light.metadata.name.clone(),
+            |room| room_qualified_name(room, &light.metadata.name),
+        );
+
+        devices.push(VoiceDevice {
+            id,
+            id_v1: lock.get_id_v1_index(id).ok(),
+            room,
+            name: light.metadata.name.clone(),
+            voice_name,
+            prefixed,
+            ambiguous: false,
+        });
+    }
+
+    let exposed = |dev: &VoiceDevice| {
+        if dev.prefixed {
+            dev.voice_name.clone()
+        } else {
+            dev.name.clone()
+        }
+    };
+
+    let names: Vec<String> = devices.iter().map(exposed).collect();
+    let ambiguous = ambiguous_names(names.iter().map(String::as_str));
+    for dev in &mut devices {
+        dev.ambiguous = ambiguous.contains(&exposed(dev).trim().to_lowercase());
+    }
+
+    Ok(Json(devices))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/devices", get(get_devices))
+}
@@ -67,11 +67,15 @@ impl WebSocketTask {
         log::trace!("service event: {service_event:?}");
 
         let name = self.mgr.lookup_name(service_event.id()).await?;
+        let deps = self.mgr.dependencies(service_event.id()).await?;
 
         let service = Service {
             id: service_event.id(),
             name,
             state: service_event.state(),
+            restarts: service_event.restarts(),
+            depends_on: deps.depends_on,
+            waiting_for: deps.waiting_for,
         };
 
         Ok(Some(Update::ServiceUpdate(service)))
@@ -79,7 +83,7 @@ impl WebSocketTask {
 
     async fn handle_socket(mut self) -> BifrostApiResult<()> {
         let lock = self.state.res.lock().await;
-        let mut backend_events = lock.backend_event_stream();
+        let mut backend_events = lock.backend_monitor_stream();
         let mut hue_events = lock.hue_event_stream().subscribe();
         let hue_state = lock.get_resources();
         drop(lock);
@@ -0,0 +1,68 @@
+use serde_json::Value;
+
+use bifrost_api::backend::BackendRequest;
+use hue::api::{Button, ResourceLink};
+
+use crate::error::ApiError;
+use crate::routes::V2Reply;
+use crate::routes::clip::ApiV2Result;
+use crate::server::appstate::AppState;
+
+/// Button events that count as a press
+const PRESS_EVENTS: &[&str] = &[
+    "initial_press",
+    "repeat",
+    "short_release",
+    "long_press",
+    "long_release",
+    "double_short_release",
+];
+
+/// Does `put` contain a press, either as `button.button_report.event` or as
+/// `button.last_event`?
+fn is_press(put: &Value) -> bool {
+    let button = &put["button"];
+    [&button["button_report"]["event"], &button["last_event"]]
+        .into_iter()
+        .filter_map(Value::as_str)
+        .any(|event| PRESS_EVENTS.contains(&event))
+}
+
+/// Updates with a press event press the button
+pub async fn put_button(state: &AppState, rlink: ResourceLink, put: Value) -> ApiV2Result {
+    let lock = state.res.lock().await;
+    let _ = lock.get::<Button>(&rlink)?;
+    if !is_press(&put) {
+        return Err(ApiError::ButtonNoPress);
+    }
+    lock.backend_request(BackendRequest::ButtonPress(rlink))?;
+    drop(lock);
+
+    V2Reply::ok(rlink)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::routes::clip::button::is_press;
+
+    #[test]
+    fn press_events() {
+        assert!(is_press(
+            &json!({"button": {"button_report": {"event": "short_release"}}})
+        ));
+        assert!(is_press(
+            &json!({"button": {"last_event": "initial_press"}})
+        ));
+    }
+
+    #[test]
+    fn not_a_press() {
+        assert!(!is_press(&json!({})));
+        assert!(!is_press(&json!({"button": {}})));
+        assert!(!is_press(&json!({"metadata": {"control_id": 1}})));
+        assert!(!is_press(&json!({"button": {"last_event": "unknown"}})));
+        assert!(!is_press(&json!({"button": {"last_event": 1}})));
+    }
+}
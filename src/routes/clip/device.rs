@@ -1,11 +1,12 @@
 use bifrost_api::backend::BackendRequest;
 use serde_json::Value;
 
-use hue::api::{Device, DeviceUpdate, LightUpdate, ResourceLink};
+use hue::api::{Device, DeviceUpdate, Light, LightUpdate, RType, ResourceLink};
 
 use crate::routes::V2Reply;
 use crate::routes::clip::ApiV2Result;
 use crate::server::appstate::AppState;
+use crate::server::identify;
 
 pub async fn put_device(state: &AppState, rlink: ResourceLink, put: Value) -> ApiV2Result {
     let upd: DeviceUpdate = serde_json::from_value(put)?;
@@ -17,10 +18,37 @@ pub async fn put_device(state: &AppState, rlink: ResourceLink, put: Value) -> Ap
         if let Some(light) = dev.light_service() {
             let upd = LightUpdate::new().with_identify(Some(*identify));
             lock.backend_request(BackendRequest::LightUpdate(*light, upd))?;
+        } else if dev.service(RType::Bridge).is_some() {
+            let res = state.res.clone();
+            let config = state.config().identify.clone();
+            tokio::spawn(async move {
+                identify::identify_bridge(&res, &config).await;
+            });
         }
     }
 
     lock.update::<Device>(&rlink.rid, |obj| *obj += &upd)?;
+
+    if let Some(md) = &upd.metadata {
+        // keep the light service in sync with the device metadata
+        let dev: &Device = lock.get(&rlink)?;
+        if let Some(light) = dev.light_service().copied() {
+            lock.update::<Light>(&light.rid, |obj| {
+                if let Some(name) = &md.name {
+                    obj.metadata.name.clone_from(name);
+                }
+                if let Some(archetype) = &md.archetype {
+                    obj.metadata.archetype.clone_from(archetype);
+                }
+            })?;
+        }
+
+        // let the owning backend persist the change, so it survives the next sync
+        if lock.backend_owner(&rlink.rid).is_some() {
+            lock.backend_request(BackendRequest::DeviceUpdate(rlink, upd.clone()))?;
+        }
+    }
+
     drop(lock);
 
     V2Reply::ok(rlink)
@@ -0,0 +1,52 @@
+use serde_json::Value;
+
+use hue::api::{
+    DeviceSoftwareUpdate, DeviceSoftwareUpdateAction, DeviceSoftwareUpdateState,
+    DeviceSoftwareUpdateUpdate, ResourceLink,
+};
+
+use crate::routes::clip::{ApiV2Result, V2Reply};
+use crate::server::appstate::AppState;
+use crate::server::swupdate;
+
+pub async fn put_device_software_update(
+    state: &AppState,
+    rlink: ResourceLink,
+    put: Value,
+) -> ApiV2Result {
+    let upd: DeviceSoftwareUpdateUpdate = serde_json::from_value(put)?;
+
+    let current = state
+        .res
+        .lock()
+        .await
+        .get::<DeviceSoftwareUpdate>(&rlink)?
+        .state;
+
+    let res = state.res.clone();
+    let updater = state.updater();
+
+    match (upd.action, current) {
+        (DeviceSoftwareUpdateAction::Check, DeviceSoftwareUpdateState::NoUpdate) => {
+            let _job = tokio::spawn(async move {
+                if let Err(err) = swupdate::check(res, updater, rlink).await {
+                    log::error!("Bridge software update check failed: {err}");
+                }
+            });
+        }
+        (DeviceSoftwareUpdateAction::Install, DeviceSoftwareUpdateState::ReadyToInstall) => {
+            let _job = tokio::spawn(async move {
+                if let Err(err) = swupdate::install(res, updater, rlink).await {
+                    log::error!("Bridge software update install failed: {err}");
+                }
+            });
+        }
+        (action, current) => {
+            // Repeated requests (e.g., the app polling "check") are harmless,
+            // so ignore them instead of failing the request.
+            log::debug!("Ignoring software update action {action:?} in state {current:?}");
+        }
+    }
+
+    V2Reply::ok(rlink)
+}
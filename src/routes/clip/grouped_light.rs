@@ -1,17 +1,43 @@
 use serde_json::Value;
 
 use bifrost_api::backend::BackendRequest;
-use hue::api::{GroupedLight, GroupedLightUpdate, ResourceLink};
+use hue::api::{GroupedLight, GroupedLightUpdate, LightUpdate, RType, ResourceLink};
 
+use crate::error::ApiResult;
+use crate::resource::Resources;
 use crate::routes::clip::{ApiV2Result, V2Reply};
 use crate::server::appstate::AppState;
 
+/// Zones are not owned by a backend, so update each light on its own.
+///
+/// The grouped light of the zone is left alone: it follows the state the
+/// lights report back (see [`Resources::refresh_zone`]).
+fn update_zone_lights(
+    res: &Resources,
+    zone: &ResourceLink,
+    upd: &GroupedLightUpdate,
+) -> ApiResult<()> {
+    let light_upd = LightUpdate::from(upd);
+    for light in res.get_zone_lights(zone)? {
+        if let Err(err) = res.backend_request(BackendRequest::LightUpdate(light, light_upd.clone()))
+        {
+            log::warn!("Failed to update {light:?} in zone {zone:?}: {err}");
+        }
+    }
+    Ok(())
+}
+
 pub async fn put_grouped_light(state: &AppState, rlink: ResourceLink, put: Value) -> ApiV2Result {
     let upd: GroupedLightUpdate = serde_json::from_value(put)?;
 
     let lock = state.res.lock().await;
-    lock.get::<GroupedLight>(&rlink)?;
-    lock.backend_request(BackendRequest::GroupedLightUpdate(rlink, upd))?;
+    let owner = lock.get::<GroupedLight>(&rlink)?.owner;
+
+    if owner.rtype == RType::Zone {
+        update_zone_lights(&lock, &owner, &upd)?;
+    } else {
+        lock.backend_request(BackendRequest::GroupedLightUpdate(rlink, upd))?;
+    }
 
     drop(lock);
 
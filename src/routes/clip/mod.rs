@@ -1,4 +1,6 @@
+pub mod button;
 pub mod device;
+pub mod device_software_update;
 pub mod entertainment_configuration;
 pub mod grouped_light;
 pub mod light;
@@ -6,14 +8,21 @@ pub mod room;
 pub mod scene;
 pub mod sensor;
 pub mod zigbee_device_discovery;
+pub mod zone;
 
 use bifrost_api::backend::BackendRequest;
 use entertainment_configuration as ent_conf;
 
 use axum::Router;
+use axum::body::Body;
 use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, post, put};
+use bytes::Bytes;
+use futures::{StreamExt, stream};
 use hue::api::{RType, ResourceLink};
+use hyper::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use hyper::{HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -53,11 +62,73 @@ impl<T: Serialize> V2Reply<T> {
     }
 }
 
-async fn get_all_resources(State(state): State<AppState>) -> ApiV2Result {
+/// Number of objects serialized into each chunk of a streamed reply
+const STREAM_CHUNK_SIZE: usize = 64;
+
+impl<T: Serialize + Send + 'static> V2Reply<T> {
+    /// Reply with a list of objects, serialized chunk by chunk while the
+    /// response body is sent, instead of building the whole reply in memory.
+    fn list_streamed(data: Vec<T>) -> Response {
+        let chunks = stream::iter(data)
+            .chunks(STREAM_CHUNK_SIZE)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut buf = vec![];
+                for (n, obj) in chunk.iter().enumerate() {
+                    if index > 0 || n > 0 {
+                        buf.push(b',');
+                    }
+                    serde_json::to_writer(&mut buf, obj)?;
+                }
+                Ok::<_, serde_json::Error>(Bytes::from(buf))
+            });
+
+        let body = stream::once(async { Ok(Bytes::from_static(br#"{"data":["#)) })
+            .chain(chunks)
+            .chain(stream::once(async {
+                Ok(Bytes::from_static(br#"],"errors":[]}"#))
+            }));
+
+        (
+            [(CONTENT_TYPE, "application/json")],
+            Body::from_stream(body),
+        )
+            .into_response()
+    }
+}
+
+/// Returns true if the `If-None-Match` header in a request matches `etag`
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+fn not_modified(etag: String) -> Response {
+    (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response()
+}
+
+fn with_etag(mut res: Response, etag: String) -> Response {
+    if let Ok(value) = etag.parse() {
+        res.headers_mut().insert(ETAG, value);
+    }
+    res
+}
+
+async fn get_all_resources(State(state): State<AppState>, headers: HeaderMap) -> Response {
     let lock = state.res.lock().await;
+    let revs = lock.revisions();
+    let etag = revs.etag(revs.current());
+    if etag_matches(&headers, &etag) {
+        return not_modified(etag);
+    }
     let res = lock.get_resources();
     drop(lock);
-    V2Reply::list(res)
+    with_etag(V2Reply::list_streamed(res), etag)
 }
 
 async fn get_wifi_connectivity() -> ApiV2Result {
@@ -66,11 +137,20 @@ async fn get_wifi_connectivity() -> ApiV2Result {
     V2Reply::list(Vec::<Value>::new())
 }
 
-pub async fn get_resource(State(state): State<AppState>, Path(rtype): Path<RType>) -> ApiV2Result {
+pub async fn get_resource(
+    State(state): State<AppState>,
+    Path(rtype): Path<RType>,
+    headers: HeaderMap,
+) -> Response {
     let lock = state.res.lock().await;
+    let revs = lock.revisions();
+    let etag = revs.etag(revs.of_type(rtype));
+    if etag_matches(&headers, &etag) {
+        return not_modified(etag);
+    }
     let res = lock.get_resources_by_type(rtype);
     drop(lock);
-    V2Reply::list(res)
+    with_etag(V2Reply::list_streamed(res), etag)
 }
 
 async fn post_resource(
@@ -84,14 +164,14 @@ async fn post_resource(
     match rtype {
         RType::EntertainmentConfiguration => ent_conf::post_resource(&state, req).await,
         RType::Scene => scene::post_scene(&state, req).await,
+        RType::Zone => zone::post_zone(&state, req).await,
 
         /* Not supported yet by Bifrost */
         RType::BehaviorInstance
         | RType::GeofenceClient
         | RType::Room
         | RType::ServiceGroup
-        | RType::SmartScene
-        | RType::Zone => {
+        | RType::SmartScene => {
             let err = ApiError::CreateNotYetSupported(rtype);
             log::warn!("{err}");
             Err(err)
@@ -139,8 +219,17 @@ async fn post_resource(
 pub async fn get_resource_id(
     State(state): State<AppState>,
     Path(rlink): Path<ResourceLink>,
-) -> ApiV2Result {
-    V2Reply::ok(state.res.lock().await.get_resource(&rlink)?)
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let lock = state.res.lock().await;
+    let revs = lock.revisions();
+    let etag = revs.etag(revs.of_type(rlink.rtype));
+    if etag_matches(&headers, &etag) {
+        return Ok(not_modified(etag));
+    }
+    let res = lock.get_resource(&rlink)?;
+    drop(lock);
+    Ok(with_etag(V2Reply::ok(res)?.into_response(), etag))
 }
 
 async fn put_resource_id(
@@ -153,24 +242,29 @@ async fn put_resource_id(
 
     match rlink.rtype {
         /* Allowed + supported */
+        RType::Button => button::put_button(&state, rlink, put).await,
         RType::Device => device::put_device(&state, rlink, put).await,
+        RType::DeviceSoftwareUpdate => {
+            device_software_update::put_device_software_update(&state, rlink, put).await
+        }
         RType::EntertainmentConfiguration => ent_conf::put_resource_id(&state, rlink, put).await,
         RType::GroupedLight => grouped_light::put_grouped_light(&state, rlink, put).await,
         RType::Light => light::put_light(&state, rlink, put).await,
-        RType::Motion | RType::Contact => sensor::put_sensor(&state, rlink, put).await,
+        RType::Motion | RType::Contact | RType::Temperature | RType::LightLevel => {
+            sensor::put_sensor(&state, rlink, put).await
+        }
         RType::Scene => scene::put_scene(&state, rlink, put).await,
         RType::Room => room::put_room(&state, rlink, put).await,
         RType::ZigbeeDeviceDiscovery => {
             zigbee_device_discovery::put_zigbee_device_discovery(&state, rlink, put).await
         }
+        RType::Zone => zone::put_zone(&state, rlink, put).await,
 
         /* Allowed, but support is missing in Bifrost */
         RType::BehaviorInstance
         | RType::Bridge
-        | RType::Button
         | RType::CameraMotion
         | RType::DevicePower
-        | RType::DeviceSoftwareUpdate
         | RType::Entertainment
         | RType::GeofenceClient
         | RType::Geolocation
@@ -178,15 +272,12 @@ async fn put_resource_id(
         | RType::GroupedMotion
         | RType::Homekit
         | RType::InternetConnectivity
-        | RType::LightLevel
         | RType::Matter
         | RType::RelativeRotary
         | RType::ServiceGroup
         | RType::SmartScene
-        | RType::Temperature
         | RType::ZgpConnectivity
-        | RType::ZigbeeConnectivity
-        | RType::Zone => {
+        | RType::ZigbeeConnectivity => {
             /* check that the resource exists, otherwise we should return 404 */
             state.res.lock().await.get_resource(&rlink)?;
 
@@ -227,8 +318,7 @@ async fn delete_resource_id(
         | RType::Room
         | RType::Scene
         | RType::ServiceGroup
-        | RType::SmartScene
-        | RType::Zone => {
+        | RType::SmartScene => {
             let lock = state.res.lock().await;
 
             /* check that the resource exists, otherwise we should return 404 */
@@ -242,6 +332,9 @@ async fn delete_resource_id(
             V2Reply::ok(rlink)
         }
 
+        /* Allowed (handled by bridge) */
+        RType::Zone => zone::delete_zone(&state, rlink).await,
+
         /* Not allowed by protocol */
         RType::AuthV1
         | RType::BehaviorScript
@@ -10,11 +10,12 @@ pub async fn put_room(state: &AppState, rlink: ResourceLink, put: Value) -> ApiV
     let mut lock = state.res.lock().await;
     lock.get::<Room>(&rlink)?;
 
-    let mut upd: RoomUpdate = serde_json::from_value(put)?;
+    let upd: RoomUpdate = serde_json::from_value(put)?;
 
-    if let Some(metadata) = upd.metadata.take() {
+    // metadata is also passed on, so backends can persist renames
+    if let Some(metadata) = &upd.metadata {
         lock.update(&rlink.rid, |room: &mut Room| {
-            room.metadata += &metadata;
+            room.metadata += metadata;
         })?;
     }
 
@@ -3,6 +3,7 @@ use serde_json::Value;
 use bifrost_api::backend::BackendRequest;
 use hue::api::{RType, ResourceLink, Scene, SceneUpdate};
 
+use crate::model::hass::HassPatinaFeature;
 use crate::routes::clip::{ApiV2Result, V2Reply};
 use crate::server::appstate::AppState;
 
@@ -22,6 +23,10 @@ pub async fn post_scene(state: &AppState, req: Value) -> ApiV2Result {
     V2Reply::ok(link_scene)
 }
 
+/// Update (or recall) a scene. Actions sent along with a recall override the
+/// stored actions of their lights, for that recall only. A recall with
+/// `dimming` scales the brightness of the recalled lights, and one with
+/// `duration` transitions them over that time.
 pub async fn put_scene(state: &AppState, rlink: ResourceLink, put: Value) -> ApiV2Result {
     let mut lock = state.res.lock().await;
 
@@ -33,8 +38,21 @@ pub async fn put_scene(state: &AppState, rlink: ResourceLink, put: Value) -> Api
 
     let _scene = lock.get::<Scene>(&rlink)?;
 
-    lock.backend_request(BackendRequest::SceneUpdate(rlink, upd))?;
+    // recalls may be redirected to the variant for the time of day
+    let target = lock.current_scene_variant(rlink, &upd);
+
+    let recall = upd
+        .recall
+        .as_ref()
+        .is_some_and(|recall| recall.action.is_some());
+    lock.backend_request(BackendRequest::SceneUpdate(target, upd))?;
     drop(lock);
 
+    if recall {
+        state
+            .record_patina_usage(HassPatinaFeature::SceneRecall)
+            .await;
+    }
+
     V2Reply::ok(rlink)
 }
@@ -1,7 +1,7 @@
 use serde_json::Value;
 
 use bifrost_api::backend::BackendRequest;
-use hue::api::{Motion, RType, ResourceLink};
+use hue::api::{LightLevel, Motion, RType, ResourceLink, Temperature};
 
 use crate::error::ApiError;
 use crate::routes::V2Reply;
@@ -35,6 +35,18 @@ pub async fn put_sensor(state: &AppState, rlink: ResourceLink, put: Value) -> Ap
                 motion.enabled = enabled;
             })?;
         }
+        RType::Temperature => {
+            let _ = lock.get::<Temperature>(&rlink)?;
+            lock.update::<Temperature>(&rlink.rid, |temp| {
+                temp.enabled = enabled;
+            })?;
+        }
+        RType::LightLevel => {
+            let _ = lock.get::<LightLevel>(&rlink)?;
+            lock.update::<LightLevel>(&rlink.rid, |level| {
+                level.enabled = enabled;
+            })?;
+        }
         RType::Contact => {
             let record = lock.get_resource(&rlink)?;
             let mut raw = match record.obj {
@@ -0,0 +1,200 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use hue::api::{GroupedLight, RType, Resource, ResourceLink, Zone, ZoneUpdate};
+
+use crate::error::{ApiError, ApiResult};
+use crate::resource::Resources;
+use crate::routes::clip::{ApiV2Result, V2Reply};
+use crate::server::appstate::AppState;
+
+/*
+ * Zones group lights across rooms (and backends), so they are kept by the
+ * bridge itself, instead of being owned by a backend.
+ */
+
+/// Zones hold existing lights, or devices (meaning their lights)
+fn check_children(res: &Resources, children: &BTreeSet<ResourceLink>) -> ApiResult<()> {
+    for child in children {
+        if !matches!(child.rtype, RType::Light | RType::Device) {
+            return Err(ApiError::ZoneChildInvalid(*child));
+        }
+        res.get_resource(child)?;
+    }
+    Ok(())
+}
+
+fn add_zone(res: &mut Resources, mut zone: Zone) -> ApiResult<ResourceLink> {
+    check_children(res, &zone.children)?;
+
+    let link_zone = ResourceLink::new(Uuid::new_v4(), RType::Zone);
+    let link_glight = RType::GroupedLight.deterministic(link_zone.rid);
+
+    zone.services.insert(link_glight);
+
+    res.add(&link_zone, Resource::Zone(zone))?;
+    res.add(
+        &link_glight,
+        Resource::GroupedLight(GroupedLight::new(link_zone)),
+    )?;
+    res.refresh_zone(&link_zone)?;
+
+    Ok(link_zone)
+}
+
+fn update_zone(res: &mut Resources, link: &ResourceLink, upd: &ZoneUpdate) -> ApiResult<()> {
+    if let Some(children) = &upd.children {
+        check_children(res, children)?;
+    }
+
+    res.update::<Zone>(&link.rid, |zone| *zone += upd)?;
+    res.refresh_zone(link)
+}
+
+pub async fn post_zone(state: &AppState, req: Value) -> ApiV2Result {
+    let zone: Zone = serde_json::from_value(req)?;
+
+    let link_zone = add_zone(&mut *state.res.lock().await, zone)?;
+
+    V2Reply::ok(link_zone)
+}
+
+pub async fn put_zone(state: &AppState, rlink: ResourceLink, put: Value) -> ApiV2Result {
+    let upd: ZoneUpdate = serde_json::from_value(put)?;
+
+    update_zone(&mut *state.res.lock().await, &rlink, &upd)?;
+
+    V2Reply::ok(rlink)
+}
+
+pub async fn delete_zone(state: &AppState, rlink: ResourceLink) -> ApiV2Result {
+    let mut lock = state.res.lock().await;
+    lock.get::<Zone>(&rlink)?;
+
+    /* the grouped light of the zone is deleted along with it */
+    lock.delete(&rlink)?;
+    drop(lock);
+
+    V2Reply::ok(rlink)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use hue::api::{
+        Device, DeviceArchetype, Dimming, GroupedLight, Light, LightMetadata, On, RType, Resource,
+        ResourceLink, RoomArchetype, RoomMetadata, Zone, ZoneUpdate,
+    };
+    use hue::version::SwVersion;
+    use serde_json::json;
+
+    use crate::error::ApiError;
+    use crate::model::state::State;
+    use crate::resource::Resources;
+    use crate::routes::clip::zone::{add_zone, update_zone};
+
+    fn add_light(res: &mut Resources, name: &str, on: bool, brightness: f64) -> ResourceLink {
+        let device = RType::Device.deterministic(name);
+        let light = RType::Light.deterministic(name);
+
+        let dev: Device = serde_json::from_value(json!({
+            "product_data": {
+                "model_id": "test",
+                "manufacturer_name": "test",
+                "product_name": "test",
+                "product_archetype": "sultan_bulb",
+                "certified": false,
+                "software_version": "1.0.0",
+            },
+            "metadata": {"name": name, "archetype": "sultan_bulb"},
+            "services": [light],
+        }))
+        .unwrap();
+        res.add(&device, Resource::Device(dev)).unwrap();
+
+        let mut obj = Light::new(
+            device,
+            LightMetadata::new(DeviceArchetype::SultanBulb, name),
+        );
+        obj.on = On::new(on);
+        obj.dimming = Some(Dimming {
+            brightness,
+            min_dim_level: None,
+        });
+        res.add(&light, Resource::Light(obj)).unwrap();
+
+        light
+    }
+
+    fn zone(children: impl IntoIterator<Item = ResourceLink>) -> Zone {
+        Zone {
+            children: children.into_iter().collect(),
+            metadata: RoomMetadata::new(RoomArchetype::Home, "Zone"),
+            services: BTreeSet::new(),
+        }
+    }
+
+    fn zone_state(res: &Resources, zone: &ResourceLink) -> (Option<On>, Option<f64>) {
+        let glight = *res
+            .get::<Zone>(zone)
+            .unwrap()
+            .grouped_light_service()
+            .unwrap();
+        let glight = res.get::<GroupedLight>(&glight).unwrap();
+        (glight.on, glight.dimming.map(|dim| dim.brightness))
+    }
+
+    #[test]
+    fn children_are_checked() {
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let light = add_light(&mut res, "desk", false, 50.0);
+        let device = res.get::<Light>(&light).unwrap().owner;
+
+        let room = RType::Room.deterministic("room");
+        assert!(matches!(
+            add_zone(&mut res, zone([light, room])),
+            Err(ApiError::ZoneChildInvalid(link)) if link == room
+        ));
+        let missing = RType::Light.deterministic("missing");
+        assert!(add_zone(&mut res, zone([missing])).is_err());
+        assert!(res.get_resource_ids_by_type(RType::Zone).is_empty());
+
+        let link = add_zone(&mut res, zone([light])).unwrap();
+        let upd = ZoneUpdate {
+            children: Some(BTreeSet::from([device, missing])),
+            ..ZoneUpdate::default()
+        };
+        assert!(update_zone(&mut res, &link, &upd).is_err());
+        assert_eq!(
+            res.get::<Zone>(&link).unwrap().children,
+            BTreeSet::from([light])
+        );
+    }
+
+    #[test]
+    fn state_from_lights() {
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let desk = add_light(&mut res, "desk", true, 40.0);
+        let couch = add_light(&mut res, "couch", false, 80.0);
+
+        let link = add_zone(&mut res, zone([desk, couch])).unwrap();
+        assert_eq!(zone_state(&res, &link), (Some(On::new(true)), Some(40.0)));
+
+        // follows the lights, as they report their state
+        res.update::<Light>(&desk.rid, |light| light.on = On::new(false))
+            .unwrap();
+        res.refresh_zones(&desk.rid).unwrap();
+        assert_eq!(zone_state(&res, &link), (Some(On::new(false)), Some(60.0)));
+
+        // and its members
+        let upd = ZoneUpdate {
+            children: Some(BTreeSet::from([couch])),
+            ..ZoneUpdate::default()
+        };
+        update_zone(&mut res, &link, &upd).unwrap();
+        assert_eq!(zone_state(&res, &link), (Some(On::new(false)), Some(80.0)));
+    }
+}
@@ -2,20 +2,23 @@ use std::time::Duration;
 
 use axum::Router;
 use axum::extract::State;
-use axum::http::{HeaderMap, HeaderValue};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::response::IntoResponse;
 use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::routing::get;
 use futures::StreamExt;
-use futures::stream::{self, Stream};
+use futures::stream;
 use tokio_stream::wrappers::BroadcastStream;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::SizeAbove;
 
+use crate::config::EventStreamConfig;
 use crate::error::ApiResult;
 use crate::server::appstate::AppState;
 
-pub async fn get_clip_v2(
-    headers: HeaderMap,
-    State(state): State<AppState>,
-) -> Sse<impl Stream<Item = ApiResult<Event>>> {
+pub async fn get_clip_v2(headers: HeaderMap, State(state): State<AppState>) -> impl IntoResponse {
+    let config = state.config();
+
     let hello = tokio_stream::iter([Ok(Event::default().comment("hi"))]);
     let last_event_id = headers.get("last-event-id").map(HeaderValue::to_str);
 
@@ -36,7 +39,7 @@ pub async fn get_clip_v2(
         _ => stream.boxed(),
     };
 
-    let stream = events.map(move |e| {
+    let stream = events.map(move |e| -> ApiResult<Event> {
         let evt = e?;
         let evt_id = evt.id();
         let json = [evt.block];
@@ -49,13 +52,35 @@ pub async fn get_clip_v2(
 
     // Hue clients (especially on mobile) rely on a long-lived SSE connection to get realtime
     // updates; without keep-alives, intermediaries/OSes can silently tear down the stream.
-    Sse::new(hello.chain(stream)).keep_alive(
+    let keep_alive_secs = u64::from(config.eventstream.keep_alive_secs.get());
+    let sse = Sse::new(hello.chain(stream)).keep_alive(
         KeepAlive::new()
-            .interval(Duration::from_secs(15))
+            .interval(Duration::from_secs(keep_alive_secs))
             .text(": ping"),
-    )
+    );
+
+    // Reverse proxies (nginx in particular) buffer responses by default, which
+    // delays events until the buffer fills up, and makes clients time out.
+    let mut reply_headers = HeaderMap::new();
+    if config.eventstream.behind_proxy {
+        reply_headers.insert("x-accel-buffering", HeaderValue::from_static("no"));
+        reply_headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-cache, no-transform"),
+        );
+    }
+
+    (reply_headers, sse)
 }
 
-pub fn router() -> Router<AppState> {
-    Router::new().route("/clip/v2", get(get_clip_v2))
+pub fn router(config: &EventStreamConfig) -> Router<AppState> {
+    let router = Router::new().route("/clip/v2", get(get_clip_v2));
+
+    if config.gzip {
+        // the default predicate never compresses event streams, so compress
+        // everything, as long as the client accepts it.
+        router.layer(CompressionLayer::new().compress_when(SizeAbove::new(0)))
+    } else {
+        router
+    }
 }
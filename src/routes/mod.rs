@@ -1,16 +1,23 @@
 use axum::Router;
 use axum::extract::DefaultBodyLimit;
+use axum::middleware;
 use axum::response::{IntoResponse, Response};
 use hue::error::{HueApiV1Error, HueError};
 use hue::legacy_api::ApiResourceType;
-use hyper::StatusCode;
+use hyper::header::HeaderValue;
+use hyper::{StatusCode, Uri};
 use serde_json::{Value, json};
 use thiserror::Error;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use bifrost_api::config::SecurityConfig;
 
 use crate::error::ApiError;
 use crate::routes::clip::{V2Error, V2Reply};
 use crate::routes::extractor::Json;
 use crate::server::appstate::AppState;
+use crate::server::csrf;
+use crate::server::gaps::{self, Unsupported};
 
 pub mod api;
 pub mod auth;
@@ -38,6 +45,9 @@ pub enum ApiV1Error {
 
     #[error("Cannot create resources of type: {0:?}")]
     V1CreateUnsupported(ApiResourceType),
+
+    #[error("Too many pairing attempts, try again later")]
+    TooManyPairingAttempts,
 }
 
 impl ApiV1Error {
@@ -59,10 +69,13 @@ impl ApiV1Error {
                 | HueApiV1Error::InvalidValueForParameter
                 | HueApiV1Error::ParameterNotModifiable
                 | HueApiV1Error::TooManyItemsInList
-                | HueApiV1Error::PortalConnectionIsRequired,
+                | HueApiV1Error::PortalConnectionIsRequired
+                | HueApiV1Error::LinkButtonNotPressed,
             ) => StatusCode::OK,
 
             Self::HueApiV1(HueApiV1Error::BridgeInternalError) => StatusCode::INTERNAL_SERVER_ERROR,
+
+            Self::TooManyPairingAttempts => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
@@ -72,9 +85,10 @@ impl ApiV1Error {
                 HueApiV1Error::ResourceNotfound.error_code()
             }
             Self::HueApiV1(err) => err.error_code(),
-            Self::ApiError(_) | Self::HueError(_) | Self::SerdeJsonError(_) => {
-                HueApiV1Error::BridgeInternalError.error_code()
-            }
+            Self::ApiError(_)
+            | Self::HueError(_)
+            | Self::SerdeJsonError(_)
+            | Self::TooManyPairingAttempts => HueApiV1Error::BridgeInternalError.error_code(),
             Self::V1CreateUnsupported(_) => {
                 HueApiV1Error::MethodNotAvailableForResource.error_code()
             }
@@ -101,7 +115,19 @@ impl IntoResponse for ApiV1Error {
 
         let status = self.http_status_code();
 
-        (status, res).into_response()
+        let mut response = (status, res).into_response();
+        if matches!(
+            self,
+            Self::V1CreateUnsupported(_)
+                | Self::ApiError(
+                    ApiError::CreateNotYetSupported(_)
+                        | ApiError::UpdateNotYetSupported(_)
+                        | ApiError::DeleteNotYetSupported(_)
+                )
+        ) {
+            response.extensions_mut().insert(Unsupported(error_msg));
+        }
+        response
     }
 }
 
@@ -110,6 +136,15 @@ impl IntoResponse for ApiError {
         let error_msg = format!("{self}");
         log::error!("Request failed: {error_msg}");
 
+        let unsupported = matches!(
+            self,
+            Self::CreateNotYetSupported(_)
+                | Self::UpdateNotYetSupported(_)
+                | Self::DeleteNotYetSupported(_)
+                | Self::BackendUnsupported(_, _)
+        )
+        .then(|| Unsupported(error_msg.clone()));
+
         let res = Json(V2Reply::<Value> {
             data: vec![],
             errors: vec![V2Error {
@@ -142,7 +177,17 @@ impl IntoResponse for ApiError {
                 | HueError::Unmergable => StatusCode::INTERNAL_SERVER_ERROR,
             },
 
-            Self::AuxNotFound(_) => StatusCode::NOT_FOUND,
+            Self::AuxNotFound(_) | Self::HassEntityNotFound(_) => StatusCode::NOT_FOUND,
+
+            Self::ZoneChildInvalid(_) | Self::RuleSceneWrongRoom(_, _) | Self::ButtonNoPress => {
+                StatusCode::BAD_REQUEST
+            }
+
+            Self::NoBackendOwner(_) | Self::BackendNotRunning(_) | Self::NoBackends => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+
+            Self::OwnershipConflict(_, _, _) => StatusCode::CONFLICT,
 
             Self::CreateNotAllowed(_) | Self::UpdateNotAllowed(_) | Self::DeleteNotAllowed(_) => {
                 StatusCode::METHOD_NOT_ALLOWED
@@ -150,25 +195,99 @@ impl IntoResponse for ApiError {
 
             Self::CreateNotYetSupported(_)
             | Self::UpdateNotYetSupported(_)
-            | Self::DeleteNotYetSupported(_) => StatusCode::FORBIDDEN,
+            | Self::DeleteNotYetSupported(_)
+            | Self::BackendUnsupported(_, _) => StatusCode::FORBIDDEN,
 
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
-        (status, res).into_response()
+        let mut response = (status, res).into_response();
+        if let Some(unsupported) = unsupported {
+            response.extensions_mut().insert(unsupported);
+        }
+        response
     }
 }
 
-pub fn router(appstate: AppState) -> Router<()> {
-    Router::new()
-        .nest("/api", api::router())
-        .nest("/auth", auth::router())
-        .nest("/updater", updater::router())
-        .nest("/licenses", licenses::router())
-        .nest("/description.xml", upnp::router())
-        .nest("/clip/v2/resource", clip::router())
-        .nest("/eventstream", eventstream::router())
-        .nest("/bifrost", bifrost::router())
+/// The routes served by an http(s) server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteSet {
+    /// Hue bridge emulation, and the bifrost management api
+    All,
+    /// Only hue bridge emulation
+    Hue,
+    /// Only the bifrost management api (including the web ui)
+    Management,
+}
+
+/// Hue clients (including web apps) may use the bridge from anywhere
+fn hue_cors() -> CorsLayer {
+    CorsLayer::new()
+        .allow_methods(Any)
+        .allow_origin(AllowOrigin::any())
+        .allow_headers(Any)
+}
+
+/// The management api may only be used from the configured origins
+fn management_cors(config: &SecurityConfig) -> CorsLayer {
+    let cors = CorsLayer::new().allow_methods(Any).allow_headers(Any);
+
+    if config.cors_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_origin(AllowOrigin::any());
+    }
+
+    let origins = config.cors_origins.iter().filter_map(|origin| {
+        HeaderValue::from_str(origin)
+            .inspect_err(|_| log::warn!("Ignoring invalid cors origin {origin:?}"))
+            .ok()
+    });
+
+    cors.allow_origin(AllowOrigin::list(origins))
+}
+
+/// Requests for paths that no route matches. Those on the management api are
+/// simply not found, while the rest are recorded as compatibility gaps.
+async fn fallback(uri: Uri) -> Response {
+    let mut response = StatusCode::NOT_FOUND.into_response();
+    if !uri.path().starts_with("/bifrost") {
+        response
+            .extensions_mut()
+            .insert(Unsupported::new("Unknown endpoint"));
+    }
+    response
+}
+
+pub fn router(appstate: AppState, routes: RouteSet) -> Router<()> {
+    let config = appstate.config();
+
+    let mut router = Router::new();
+
+    if routes != RouteSet::Management {
+        let hue = Router::new()
+            .nest("/api", api::router())
+            .nest("/auth", auth::router())
+            .nest("/updater", updater::router())
+            .nest("/licenses", licenses::router())
+            .nest("/description.xml", upnp::router())
+            .nest("/clip/v2/resource", clip::router())
+            .nest("/eventstream", eventstream::router(&config.eventstream))
+            .fallback(fallback)
+            .layer(middleware::from_fn_with_state(
+                appstate.clone(),
+                gaps::track,
+            ))
+            .layer(hue_cors());
+        router = router.merge(hue);
+    }
+
+    if routes != RouteSet::Hue {
+        let management = bifrost::router()
+            .layer(middleware::from_fn_with_state(appstate.csrf(), csrf::check))
+            .layer(management_cors(&config.security));
+        router = router.nest("/bifrost", management);
+    }
+
+    router
         .with_state(appstate)
         .layer(DefaultBodyLimit::max(100 * 1024 * 1024))
 }
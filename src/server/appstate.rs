@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 use std::fs::{self, File};
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use camino::Utf8Path;
 use chrono::Utc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, watch};
+
+use bifrost_api::backend::BifrostActivity;
 
 use hue::legacy_api::{
     ApiConfig, ApiShortConfig, ConnectionState, Portal, PortalAction, PortalState, PortalTrust,
@@ -13,26 +16,49 @@ use hue::legacy_api::{
 };
 use svc::manager::SvmClient;
 
+use crate::backend::router::BackendRouter;
 use crate::config::AppConfig;
 use crate::error::ApiResult;
 use crate::model::hass::{
-    HassPortalAction, HassPortalCommunication, HassPortalConnectionState, HassRuntimeState,
-    HassUiState,
+    HassPatinaFeature, HassPortalAction, HassPortalCommunication, HassPortalConnectionState,
+    HassRuntimeState, HassUiState,
 };
 use crate::model::state::{State, StateVersion};
 use crate::resource::Resources;
 use crate::server::certificate;
+use crate::server::csrf::Csrf;
+use crate::server::entertainment::EntertainmentStats;
+use crate::server::gaps::CompatGaps;
+use crate::server::latency::BackendLatency;
+use crate::server::linkbutton::LinkButton;
+use crate::server::metrics::RouteMetrics;
+use crate::server::presence::Presence;
+use crate::server::ratelimit::PairingLimit;
+use crate::server::release::ReleaseChecker;
+use crate::server::reset::ResetGuard;
 use crate::server::updater::VersionUpdater;
 
 #[derive(Clone)]
 pub struct AppState {
-    conf: Arc<AppConfig>,
+    conf: Arc<watch::Sender<Arc<AppConfig>>>,
+    /// Held while the configuration of a service is changed
+    reconfigure: Arc<Mutex<()>>,
     upd: Arc<Mutex<VersionUpdater>>,
+    release: Arc<Mutex<ReleaseChecker>>,
     svm: SvmClient,
     pub res: Arc<Mutex<Resources>>,
     hass_ui: Arc<Mutex<HassUiState>>,
     hass_runtime: Arc<Mutex<HassRuntimeState>>,
-    linkbutton_until: Arc<Mutex<Option<Instant>>>,
+    linkbutton: LinkButton,
+    presence: Presence,
+    metrics: RouteMetrics,
+    latency: BackendLatency,
+    entertainment: EntertainmentStats,
+    csrf: Csrf,
+    reset: ResetGuard,
+    pairing_limit: PairingLimit,
+    gaps: CompatGaps,
+    started: Instant,
 }
 
 impl AppState {
@@ -48,7 +74,10 @@ impl AppState {
         }
 
         let mut res;
-        let upd = Arc::new(Mutex::new(VersionUpdater::with_default_version()));
+        let upd = Arc::new(Mutex::new(VersionUpdater::from_config(
+            config.updater.clone(),
+            config.bifrost.proxy.clone(),
+        )));
         let swversion = upd.lock().await.get().await.clone();
 
         if let Ok(fd) = File::open(&config.bifrost.state_file) {
@@ -74,6 +103,20 @@ impl AppState {
             res.init(&hue::bridge_id(config.bridge.mac))?;
         }
 
+        res = res
+            .with_channel_sizes(
+                config
+                    .bifrost
+                    .backend_channel_size
+                    .map_or(BackendRouter::DEFAULT_BUFFER_SIZE, NonZeroUsize::get),
+                config
+                    .bifrost
+                    .event_channel_size
+                    .map_or(Resources::DEFAULT_EVENT_CHANNEL_SIZE, NonZeroUsize::get),
+            )
+            .with_time_of_day(config.time_of_day.clone())
+            .with_home(config.home.clone());
+
         res.reset_all_streaming()?;
         res.ensure_core_bridge_resources(&hue::bridge_id(config.bridge.mac))?;
 
@@ -90,23 +133,97 @@ impl AppState {
             config.bifrost.hass_runtime_file.clone(),
             fallback_hass_url,
         )?));
-        let conf = Arc::new(config);
+        let release = Arc::new(Mutex::new(ReleaseChecker::new(
+            config.self_update.clone(),
+            config.bifrost.proxy.clone(),
+        )));
+        let csrf = Csrf::new(config.security.csrf);
+        let conf = Arc::new(watch::Sender::new(Arc::new(config)));
+        let presence_enabled = res.presence_enabled();
         let res = Arc::new(Mutex::new(res));
+        let presence = Presence::new(res.clone(), presence_enabled);
 
         Ok(Self {
             conf,
+            reconfigure: Arc::new(Mutex::new(())),
             upd,
+            release,
             svm,
             res,
             hass_ui,
             hass_runtime,
-            linkbutton_until: Arc::new(Mutex::new(None)),
+            linkbutton: LinkButton::new(),
+            presence,
+            metrics: RouteMetrics::new(),
+            latency: BackendLatency::new(),
+            entertainment: EntertainmentStats::new(),
+            csrf,
+            reset: ResetGuard::new(),
+            pairing_limit: PairingLimit::new(),
+            gaps: CompatGaps::new(),
+            started: Instant::now(),
         })
     }
 
     #[must_use]
     pub fn config(&self) -> Arc<AppConfig> {
-        self.conf.clone()
+        self.conf.borrow().clone()
+    }
+
+    /// Replace the running configuration (without saving it)
+    ///
+    /// Services keep the configuration they were created with, until they
+    /// are regenerated.
+    pub fn set_config(&self, config: AppConfig) {
+        self.conf.send_replace(Arc::new(config));
+    }
+
+    /// Lock to hold while changing the configuration of a service (see
+    /// [`Self::set_config`]), so concurrent changes are not lost
+    #[must_use]
+    pub fn reconfigure_lock(&self) -> Arc<Mutex<()>> {
+        self.reconfigure.clone()
+    }
+
+    #[must_use]
+    pub fn metrics(&self) -> RouteMetrics {
+        self.metrics.clone()
+    }
+
+    #[must_use]
+    pub fn latency(&self) -> BackendLatency {
+        self.latency.clone()
+    }
+
+    #[must_use]
+    pub fn entertainment(&self) -> EntertainmentStats {
+        self.entertainment.clone()
+    }
+
+    #[must_use]
+    pub fn csrf(&self) -> Csrf {
+        self.csrf.clone()
+    }
+
+    #[must_use]
+    pub fn reset_guard(&self) -> ResetGuard {
+        self.reset.clone()
+    }
+
+    #[must_use]
+    pub fn pairing_limit(&self) -> PairingLimit {
+        self.pairing_limit.clone()
+    }
+
+    #[must_use]
+    pub fn gaps(&self) -> CompatGaps {
+        self.gaps.clone()
+    }
+
+    /// Time since this instance was started
+    #[must_use]
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
     }
 
     #[must_use]
@@ -114,6 +231,12 @@ impl AppState {
         self.upd.clone()
     }
 
+    /// Checks for new Bifrost releases
+    #[must_use]
+    pub fn release_checker(&self) -> Arc<Mutex<ReleaseChecker>> {
+        self.release.clone()
+    }
+
     #[must_use]
     pub fn manager(&self) -> SvmClient {
         self.svm.clone()
@@ -129,31 +252,45 @@ impl AppState {
         self.hass_runtime.clone()
     }
 
+    #[must_use]
+    pub fn linkbutton(&self) -> LinkButton {
+        self.linkbutton.clone()
+    }
+
+    #[must_use]
+    pub fn presence(&self) -> Presence {
+        self.presence.clone()
+    }
+
     pub async fn press_linkbutton(&self, active_for: Duration) {
-        let mut lock = self.linkbutton_until.lock().await;
-        *lock = Some(Instant::now() + active_for);
+        self.linkbutton.press(active_for).await;
+        self.res
+            .lock()
+            .await
+            .forward_activity(BifrostActivity::LinkButton {
+                source: "api".to_string(),
+            });
+    }
+
+    /// Count a use of `feature` (once, no matter how many backends serve it)
+    pub async fn record_patina_usage(&self, feature: HassPatinaFeature) {
+        let mut ui = self.hass_ui.lock().await;
+        ui.record_patina_usage(feature);
+        ui.save_config();
     }
 
     pub async fn linkbutton_active(&self) -> bool {
-        let now = Instant::now();
-        let mut lock = self.linkbutton_until.lock().await;
-        match *lock {
-            Some(until) if until > now => true,
-            Some(_) => {
-                *lock = None;
-                false
-            }
-            None => false,
-        }
+        self.linkbutton.is_active().await
     }
 
     #[must_use]
     pub async fn api_short_config(&self) -> ApiShortConfig {
-        let mac = self.conf.bridge.mac;
-        ApiShortConfig::from_mac_and_version(mac, self.upd.lock().await.get().await)
+        let mac = self.config().bridge.mac;
+        ApiShortConfig::from_mac_and_version(mac, self.res.lock().await.bridge_version())
     }
 
     pub async fn api_config(&self, username: String) -> ApiResult<ApiConfig> {
+        let conf = self.config();
         let (ui_cfg, cloud) = {
             let ui = self.hass_ui.lock().await;
             let cfg = ui.config_normalized();
@@ -163,27 +300,41 @@ impl AppState {
         let timezone = ui_cfg
             .hass_timezone
             .clone()
-            .unwrap_or_else(|| self.conf.bridge.timezone.clone());
+            .unwrap_or_else(|| conf.bridge.timezone.clone());
         let tz = tzfile::Tz::named(&timezone)?;
         let localtime = Utc::now().with_timezone(&&tz).naive_local();
         let linkbutton = self.linkbutton_active().await;
 
+        let mut whitelist: HashMap<String, Whitelist> = self
+            .res
+            .lock()
+            .await
+            .api_users()
+            .iter()
+            .map(|(name, user)| {
+                let entry = Whitelist {
+                    create_date: user.create_date,
+                    last_use_date: Utc::now(),
+                    name: user.devicetype.clone(),
+                };
+                (name.clone(), entry)
+            })
+            .collect();
+        whitelist.entry(username).or_insert_with(|| Whitelist {
+            create_date: Utc::now(),
+            last_use_date: Utc::now(),
+            name: "User#foo".to_string(),
+        });
+
         let res = ApiConfig {
             short_config: self.api_short_config().await,
-            ipaddress: self.conf.bridge.ipaddress,
-            netmask: self.conf.bridge.netmask,
-            gateway: self.conf.bridge.gateway,
+            ipaddress: conf.bridge.ipaddress,
+            netmask: conf.bridge.netmask,
+            gateway: conf.bridge.gateway,
             timezone,
             lat: ui_cfg.hass_lat.unwrap_or_else(|| "0.0000".to_string()),
             long: ui_cfg.hass_long.unwrap_or_else(|| "0.0000".to_string()),
-            whitelist: HashMap::from([(
-                username,
-                Whitelist {
-                    create_date: Utc::now(),
-                    last_use_date: Utc::now(),
-                    name: "User#foo".to_string(),
-                },
-            )]),
+            whitelist,
             localtime,
             linkbutton,
             internet: cloud.internet,
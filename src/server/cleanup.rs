@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+
+use crate::error::ApiResult;
+use crate::resource::Resources;
+
+/// Periodically remove links to deleted resources from the bridge home and
+/// rooms, since resources are added to them, but not always removed again.
+pub async fn link_cleanup(res: Arc<Mutex<Resources>>) -> ApiResult<()> {
+    const INTERVAL: Duration = Duration::from_secs(600);
+
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        let pruned = res.lock().await.prune_dangling_links()?;
+        if pruned > 0 {
+            log::info!("Link cleanup: removed {pruned} dangling links");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use bifrost_api::config::ClientCompatConfig;
+
+/// Client specific workarounds on the v1 api
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClientQuirks {
+    pub scene_appdata: bool,
+    pub hue_uniqueid: bool,
+    pub list_group_0: bool,
+}
+
+impl Default for ClientQuirks {
+    fn default() -> Self {
+        Self {
+            scene_appdata: true,
+            hue_uniqueid: false,
+            list_group_0: false,
+        }
+    }
+}
+
+impl ClientQuirks {
+    #[must_use]
+    pub fn with_config(self, config: &ClientCompatConfig) -> Self {
+        Self {
+            scene_appdata: config.scene_appdata.unwrap_or(self.scene_appdata),
+            hue_uniqueid: config.hue_uniqueid.unwrap_or(self.hue_uniqueid),
+            list_group_0: config.list_group_0.unwrap_or(self.list_group_0),
+        }
+    }
+}
+
+/// Name of the client family matching `devicetype` (as sent when pairing)
+#[must_use]
+pub fn family_for_devicetype<'a>(
+    compat: &'a BTreeMap<String, ClientCompatConfig>,
+    devicetype: &str,
+) -> Option<&'a str> {
+    let devicetype = devicetype.to_ascii_lowercase();
+    compat
+        .iter()
+        .filter(|(_, cfg)| !cfg.devicetype.is_empty())
+        .find(|(_, cfg)| devicetype.starts_with(&cfg.devicetype.to_ascii_lowercase()))
+        .map(|(name, _)| name.as_str())
+}
+
+/// Workarounds for the client at `ip`, that paired as `devicetype`.
+///
+/// Configured addresses take precedence over the family of the devicetype.
+#[must_use]
+pub fn client_quirks(
+    compat: &BTreeMap<String, ClientCompatConfig>,
+    ip: Option<IpAddr>,
+    devicetype: Option<&str>,
+) -> ClientQuirks {
+    compat
+        .values()
+        .find(|cfg| ip.is_some_and(|ip| cfg.addresses.contains(&ip)))
+        .or_else(|| {
+            devicetype
+                .and_then(|devicetype| family_for_devicetype(compat, devicetype))
+                .and_then(|name| compat.get(name))
+        })
+        .map_or_else(ClientQuirks::default, |cfg| {
+            ClientQuirks::default().with_config(cfg)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use bifrost_api::config::ClientCompatConfig;
+
+    use crate::server::compat::{ClientQuirks, client_quirks, family_for_devicetype};
+
+    fn compat() -> BTreeMap<String, ClientCompatConfig> {
+        BTreeMap::from([(
+            "essentials".to_string(),
+            ClientCompatConfig {
+                devicetype: "Hue Essentials".to_string(),
+                addresses: vec![],
+                scene_appdata: None,
+                hue_uniqueid: Some(true),
+                list_group_0: Some(true),
+            },
+        )])
+    }
+
+    #[test]
+    fn family_by_devicetype_prefix() {
+        let compat = compat();
+        assert_eq!(
+            family_for_devicetype(&compat, "hue essentials#Pixel 8"),
+            Some("essentials")
+        );
+        assert_eq!(family_for_devicetype(&compat, "iConnectHue#iPhone"), None);
+    }
+
+    #[test]
+    fn quirks_by_devicetype() {
+        let compat = compat();
+        let ip = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 50)));
+
+        assert_eq!(client_quirks(&compat, ip, None), ClientQuirks::default());
+
+        let quirks = client_quirks(&compat, ip, Some("Hue Essentials#Pixel 8"));
+        assert!(quirks.scene_appdata);
+        assert!(quirks.hue_uniqueid);
+        assert!(quirks.list_group_0);
+
+        assert_eq!(
+            client_quirks(&compat, ip, Some("other#app")),
+            ClientQuirks::default()
+        );
+    }
+
+    #[test]
+    fn configured_address() {
+        let mut compat = compat();
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 51));
+        compat.get_mut("essentials").unwrap().addresses.push(ip);
+
+        assert!(client_quirks(&compat, Some(ip), Some("other#app")).hue_uniqueid);
+        assert!(!client_quirks(&compat, None, Some("other#app")).hue_uniqueid);
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use bifrost_api::rule::RuleAction;
+use hue::api::{RType, Resource};
+use hue::event::{Event, EventBlock};
+
+use crate::resource::Resources;
+use crate::server::rules::{room_on, run_action};
+
+/// Runtime state of the contact rules, by rule id
+pub type ContactStates = HashMap<Uuid, ContactState>;
+
+/// Runtime state of a single contact rule
+#[derive(Debug, Default)]
+pub struct ContactState {
+    /// Last known contact state (true if open)
+    open: Option<bool>,
+    /// When to turn the lights off again
+    off_at: Option<Instant>,
+}
+
+impl ContactState {
+    /// Record a contact report. Returns true if the contact changed since the
+    /// last known state.
+    fn update(&mut self, open: bool) -> bool {
+        let changed = self.open.is_some_and(|prev| prev != open);
+        self.open = Some(open);
+        changed
+    }
+
+    /// Record that `action` was performed, scheduling the automatic turn-off
+    /// (if any)
+    fn triggered(&mut self, action: &RuleAction, auto_off: Option<Duration>, now: Instant) {
+        self.off_at = match action {
+            RuleAction::Off => None,
+            RuleAction::Scene { .. } | RuleAction::Light { .. } => auto_off.map(|dur| now + dur),
+        };
+    }
+
+    /// Returns true if the rule should turn the lights off now.
+    fn expire(&mut self, room_on: bool, now: Instant) -> bool {
+        let Some(off_at) = self.off_at else {
+            return false;
+        };
+
+        if !room_on {
+            // already turned off by someone else
+            self.off_at = None;
+            return false;
+        }
+
+        if now < off_at {
+            return false;
+        }
+
+        self.off_at = None;
+        true
+    }
+}
+
+/// Is the contact open?
+///
+/// Understands both the hue bridge format (`contact_report.state`), and the
+/// one produced by the hass backend (`contact.contact`, true when open).
+fn contact_open(value: &Value) -> Option<bool> {
+    if let Some(state) = value["contact_report"]["state"].as_str() {
+        return Some(state == "no_contact");
+    }
+    value["contact"]["contact"].as_bool()
+}
+
+fn sensor_open(res: &Resources, id: &Uuid) -> Option<bool> {
+    match res.get_resource_by_id(id).ok()?.obj {
+        Resource::Contact(value) => contact_open(&value),
+        _ => None,
+    }
+}
+
+fn contact_changed(res: &Resources, states: &mut ContactStates, sensor: Uuid, open: bool) {
+    let rules = res
+        .contact_rules()
+        .iter()
+        .filter(|(_, rule)| rule.sensor.rid == sensor);
+
+    for (id, rule) in rules {
+        let state = states.entry(*id).or_default();
+        if !state.update(open) || !rule.enabled {
+            continue;
+        }
+
+        let action = if open { &rule.open } else { &rule.close };
+        let Some(action) = action else {
+            continue;
+        };
+
+        log::info!(
+            "Contact rule {:?} triggered ({})",
+            rule.name,
+            if open { "opened" } else { "closed" }
+        );
+        if let Err(err) = run_action(res, &rule.room, action) {
+            log::warn!("Contact rule {:?} failed: {err}", rule.name);
+            continue;
+        }
+
+        let auto_off = rule
+            .auto_off
+            .map(|secs| Duration::from_secs(u64::from(secs)));
+        state.triggered(action, auto_off, Instant::now());
+    }
+}
+
+/// Trigger the contact rules for the sensors added or updated in `block`.
+///
+/// A failing rule is logged, and does not keep the other rules from running.
+pub fn handle_event(res: &Resources, states: &mut ContactStates, block: &EventBlock) {
+    // sensors are either updated in place, or replaced entirely
+    let sensors: Vec<Uuid> = match &block.event {
+        Event::Add(add) => add
+            .data
+            .iter()
+            .filter(|rec| rec.obj.rtype() == RType::Contact)
+            .map(|rec| rec.id)
+            .collect(),
+        Event::Update(upd) => upd
+            .data
+            .iter()
+            .filter(|obj| obj.rtype == RType::Contact)
+            .map(|obj| obj.id)
+            .collect(),
+        Event::Delete(_) | Event::Error(_) => return,
+    };
+
+    for sensor in sensors {
+        if let Some(open) = sensor_open(res, &sensor) {
+            contact_changed(res, states, sensor, open);
+        }
+    }
+}
+
+pub fn expire_rules(res: &Resources, states: &mut ContactStates) {
+    let rules = res.contact_rules();
+    states.retain(|id, _| rules.contains_key(id));
+
+    for (id, rule) in rules {
+        let state = states.entry(*id).or_insert_with(|| ContactState {
+            // start from the current state, so the first report is not missed
+            open: sensor_open(res, &rule.sensor.rid),
+            off_at: None,
+        });
+
+        if state.expire(room_on(res, &rule.room), Instant::now()) {
+            log::info!("Contact rule {:?} timed out, turning off lights", rule.name);
+            if let Err(err) = run_action(res, &rule.room, &RuleAction::Off) {
+                log::warn!(
+                    "Contact rule {:?} failed to turn off lights: {err}",
+                    rule.name
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bifrost_api::rule::RuleAction;
+    use serde_json::json;
+    use tokio::time::Instant;
+
+    use crate::server::contact::{ContactState, contact_open};
+
+    const AUTO_OFF: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn contact_open_hue_format() {
+        let open = json!({"contact_report": {"state": "no_contact"}});
+        let closed = json!({"contact_report": {"state": "contact"}});
+
+        assert_eq!(contact_open(&open), Some(true));
+        assert_eq!(contact_open(&closed), Some(false));
+    }
+
+    #[test]
+    fn contact_open_hass_format() {
+        assert_eq!(
+            contact_open(&json!({"contact": {"contact": true}})),
+            Some(true)
+        );
+        assert_eq!(
+            contact_open(&json!({"contact": {"contact": false}})),
+            Some(false)
+        );
+        assert_eq!(contact_open(&json!({})), None);
+    }
+
+    #[test]
+    fn only_changes_trigger() {
+        let mut state = ContactState::default();
+
+        // first report only establishes the state
+        assert!(!state.update(false));
+        assert!(!state.update(false));
+        assert!(state.update(true));
+        assert!(!state.update(true));
+        assert!(state.update(false));
+    }
+
+    #[test]
+    fn auto_off_after_timeout() {
+        let mut state = ContactState::default();
+        let now = Instant::now();
+        let action = RuleAction::Light { brightness: None };
+
+        state.triggered(&action, Some(AUTO_OFF), now);
+        assert!(!state.expire(true, now + AUTO_OFF / 2));
+        assert!(state.expire(true, now + AUTO_OFF));
+        assert!(!state.expire(true, now + AUTO_OFF * 2));
+    }
+
+    #[test]
+    fn auto_off_cancelled() {
+        let now = Instant::now();
+        let action = RuleAction::Light { brightness: None };
+
+        // turned off by someone else
+        let mut state = ContactState::default();
+        state.triggered(&action, Some(AUTO_OFF), now);
+        assert!(!state.expire(false, now + AUTO_OFF));
+        assert!(!state.expire(true, now + AUTO_OFF));
+
+        // turned off by the rule itself
+        let mut state = ContactState::default();
+        state.triggered(&action, Some(AUTO_OFF), now);
+        state.triggered(&RuleAction::Off, Some(AUTO_OFF), now);
+        assert!(!state.expire(true, now + AUTO_OFF));
+    }
+
+    #[test]
+    fn no_auto_off() {
+        let mut state = ContactState::default();
+        let now = Instant::now();
+
+        state.triggered(&RuleAction::Light { brightness: None }, None, now);
+        assert!(!state.expire(true, now + AUTO_OFF));
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+
+use bifrost_api::csrf::CSRF_HEADER;
+
+use crate::routes::extractor::Json;
+
+/// Csrf protection for the management api.
+///
+/// Requests that change anything must carry the token in the
+/// [`CSRF_HEADER`] header. Other web sites can make a browser send requests
+/// to bifrost, but cannot read the token, so they cannot forge those.
+///
+/// The token is generated once at startup, and stays valid until bifrost is
+/// restarted. It is never rotated while running, since clients (like the web
+/// ui) fetch it only once.
+#[derive(Clone, Debug)]
+pub struct Csrf {
+    token: Option<Arc<str>>,
+}
+
+impl Csrf {
+    /// Protection with a fresh random token, or none if not `enabled`
+    #[must_use]
+    pub fn new(enabled: bool) -> Self {
+        let token = enabled.then(|| hex::encode(rand::random::<[u8; 32]>()).into());
+        Self { token }
+    }
+
+    /// The token (`None` if csrf protection is disabled)
+    #[must_use]
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Is a request with this header value allowed?
+    fn allows(&self, value: Option<&HeaderValue>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let Some(value) = value else {
+            return false;
+        };
+
+        // compare in constant time, to not leak the token through timing
+        let value = value.as_bytes();
+        value.len() == token.len()
+            && value
+                .iter()
+                .zip(token.as_bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+/// Middleware that rejects state-changing requests without a valid csrf token
+pub async fn check(State(csrf): State<Csrf>, req: Request, next: Next) -> Response {
+    let safe = [Method::GET, Method::HEAD, Method::OPTIONS].contains(req.method());
+
+    if safe || csrf.allows(req.headers().get(CSRF_HEADER)) {
+        return next.run(req).await;
+    }
+
+    log::warn!(
+        "Rejected {} {}: missing or invalid csrf token",
+        req.method(),
+        req.uri()
+    );
+
+    let res = json!({"error": "Missing or invalid csrf token"});
+    (StatusCode::FORBIDDEN, Json(res)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use crate::server::csrf::Csrf;
+
+    #[test]
+    fn disabled_allows_all() {
+        let csrf = Csrf::new(false);
+
+        assert!(csrf.token().is_none());
+        assert!(csrf.allows(None));
+    }
+
+    #[test]
+    fn requires_token() {
+        let csrf = Csrf::new(true);
+        let token = HeaderValue::from_str(csrf.token().unwrap()).unwrap();
+
+        assert!(csrf.allows(Some(&token)));
+        assert!(!csrf.allows(None));
+        assert!(!csrf.allows(Some(&HeaderValue::from_static("wrong"))));
+    }
+
+    #[test]
+    fn tokens_differ() {
+        assert_ne!(Csrf::new(true).token(), Csrf::new(true).token());
+    }
+}
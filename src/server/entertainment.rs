@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::io::ErrorKind;
 use std::net::{Ipv4Addr, SocketAddr};
 use std::os::fd::AsFd;
@@ -7,10 +7,12 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use nix::sys::socket;
 use nix::sys::socket::sockopt::RcvBuf;
+use openssl::ex_data::Index;
 use openssl::ssl::{Ssl, SslContext, SslMethod};
+use sha2::{Digest, Sha256};
 use tokio::io::AsyncReadExt;
 use tokio::net::UdpSocket;
 use tokio::sync::Mutex;
@@ -20,11 +22,12 @@ use udp_stream::{UdpListenBuilder, UdpListener, UdpStream};
 use uuid::Uuid;
 
 use bifrost_api::backend::BackendRequest;
-use hue::api::{Device, EntertainmentConfiguration, Light, RType};
+use bifrost_api::debug::{EntertainmentCounters, EntertainmentReport, EntertainmentSession};
+use hue::api::{Device, EntertainmentConfiguration, HueStreamKey, Light, RType};
 use hue::error::HueError;
 use hue::stream::{
-    HueStreamLightsV1, HueStreamLightsV2, HueStreamPacket, HueStreamPacketV1, HueStreamPacketV2,
-    Rgb16V2, Xy16V2,
+    HueStreamHeader, HueStreamLightsV1, HueStreamLightsV2, HueStreamPacket, HueStreamPacketV1,
+    HueStreamPacketV2, Rgb16V2, Xy16V2,
 };
 use svc::traits::Service;
 
@@ -32,20 +35,195 @@ use crate::error::{ApiError, ApiResult};
 use crate::resource::Resources;
 use crate::routes::auth::STANDARD_CLIENT_KEY;
 
+/// Number of finished sessions kept for diagnostics
+const RECENT_SESSIONS: usize = 10;
+
+/// Streaming keys, by PSK identity
+type StreamKeys = HashMap<String, HueStreamKey>;
+
+/// Streaming keys of all paired apps. The PSK identity is the username for
+/// v1 streams, and the application id for v2 streams.
+fn stream_keys(res: &Resources) -> StreamKeys {
+    let mut keys = HashMap::new();
+    for (username, user) in res.api_users() {
+        let key = user
+            .clientkey
+            .as_deref()
+            .and_then(|key| HueStreamKey::try_from(key).ok());
+        if let Some(key) = key {
+            keys.insert(user.application_id.to_string(), key.clone());
+            keys.insert(username.clone(), key);
+        }
+    }
+    keys
+}
+
+/// Result of the PSK lookup during the DTLS handshake
+#[derive(Clone, Debug)]
+struct PskInfo {
+    identity: String,
+    psk_digest: String,
+}
+
+/// Frames received since they were last added to the [`SessionDiag`]. Kept
+/// by the frame loop itself, so it does not need to lock the diagnostics for
+/// every frame.
+#[derive(Debug, Default)]
+struct FrameCounter {
+    last_seqnr: Option<u8>,
+    frames: u64,
+    dropped: u64,
+    last_frame: Option<DateTime<Utc>>,
+}
+
+impl FrameCounter {
+    /// Count a frame. Gaps in the sequence numbers are counted as dropped
+    /// frames, while repeated numbers are not (some senders never increment
+    /// them).
+    fn frame(&mut self, seqnr: u8, now: DateTime<Utc>) {
+        let dropped = match self.last_seqnr {
+            Some(last) if last != seqnr => u64::from(seqnr.wrapping_sub(last).wrapping_sub(1)),
+            _ => 0,
+        };
+        self.last_seqnr = Some(seqnr);
+
+        self.frames += 1;
+        self.dropped += dropped;
+        self.last_frame = Some(now);
+    }
+}
+
+#[derive(Debug, Default)]
+struct SessionDiag {
+    active: Option<EntertainmentSession>,
+    handshake_done: bool,
+    recent: VecDeque<EntertainmentSession>,
+    counters: EntertainmentCounters,
+}
+
+impl SessionDiag {
+    fn start(&mut self, client: String, now: String) {
+        self.counters.sessions += 1;
+        self.handshake_done = false;
+        self.active = Some(EntertainmentSession {
+            client,
+            identity: None,
+            psk_digest: None,
+            area: None,
+            started_at: now,
+            last_frame_at: None,
+            ended_at: None,
+            frames: 0,
+            dropped_frames: 0,
+            fps: 0,
+            error: None,
+        });
+    }
+
+    fn handshake(&mut self, psk: Option<&PskInfo>) {
+        self.handshake_done = true;
+        if let Some(sess) = &mut self.active {
+            sess.identity = psk.map(|psk| psk.identity.clone());
+            sess.psk_digest = psk.map(|psk| psk.psk_digest.clone());
+        }
+    }
+
+    fn area(&mut self, area: Uuid) {
+        if let Some(sess) = &mut self.active {
+            sess.area = Some(area);
+        }
+    }
+
+    /// Add the frames counted by `counter` (resetting it)
+    fn frames(&mut self, counter: &mut FrameCounter) {
+        let frames = std::mem::take(&mut counter.frames);
+        let dropped = std::mem::take(&mut counter.dropped);
+
+        self.counters.frames += frames;
+        self.counters.dropped_frames += dropped;
+        if let Some(sess) = &mut self.active {
+            sess.frames += frames;
+            sess.dropped_frames += dropped;
+            if let Some(last) = counter.last_frame.take() {
+                sess.last_frame_at = Some(last.to_rfc3339());
+            }
+        }
+    }
+
+    fn fps(&mut self, fps: u32) {
+        if let Some(sess) = &mut self.active {
+            sess.fps = fps;
+        }
+    }
+
+    fn finish(&mut self, result: &ApiResult<()>, now: String) {
+        match result {
+            Ok(()) => {}
+            Err(_) if !self.handshake_done => self.counters.handshake_failures += 1,
+            Err(ApiError::EntStreamTimeout) => self.counters.timeouts += 1,
+            Err(ApiError::EntStreamDesync) => self.counters.desyncs += 1,
+            Err(_) => self.counters.errors += 1,
+        }
+
+        if let Some(mut sess) = self.active.take() {
+            sess.ended_at = Some(now);
+            sess.error = result.as_ref().err().map(ToString::to_string);
+            self.recent.push_front(sess);
+            self.recent.truncate(RECENT_SESSIONS);
+        }
+    }
+
+    fn report(&self) -> EntertainmentReport {
+        EntertainmentReport {
+            active: self.active.iter().cloned().collect(),
+            recent: self.recent.iter().cloned().collect(),
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+/// Shared handle to the entertainment session diagnostics
+#[derive(Clone, Debug, Default)]
+pub struct EntertainmentStats {
+    diag: Arc<Mutex<SessionDiag>>,
+}
+
+impl EntertainmentStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn report(&self) -> EntertainmentReport {
+        self.diag.lock().await.report()
+    }
+}
+
 pub struct EntertainmentService {
     addr: SocketAddr,
     udp: Option<Arc<UdpListener>>,
     ctx: Option<SslContext>,
     res: Arc<Mutex<Resources>>,
+    stats: EntertainmentStats,
+    psk_index: Index<Ssl, PskInfo>,
+    keys_index: Index<Ssl, StreamKeys>,
 }
 
 impl EntertainmentService {
-    pub fn new(addr: Ipv4Addr, port: u16, res: Arc<Mutex<Resources>>) -> ApiResult<Self> {
+    pub fn new(
+        addr: Ipv4Addr,
+        port: u16,
+        res: Arc<Mutex<Resources>>,
+        stats: EntertainmentStats,
+    ) -> ApiResult<Self> {
         let res = Self {
             addr: SocketAddr::new(addr.into(), port),
             udp: None,
             ctx: None,
             res,
+            stats,
+            psk_index: Ssl::new_ex_index()?,
+            keys_index: Ssl::new_ex_index()?,
         };
 
         Ok(res)
@@ -191,13 +369,20 @@ impl EntertainmentService {
         }
     }
 
-    pub async fn run_loop(&self, mut sess: SslStream<UdpStream>) -> ApiResult<()> {
+    async fn run_loop(
+        &self,
+        mut sess: SslStream<UdpStream>,
+        counter: &mut FrameCounter,
+    ) -> ApiResult<()> {
         let mut buf = [0u8; 1024];
 
         timeout(Duration::from_secs(5), Pin::new(&mut sess).accept())
             .await
             .map_err(|_| ApiError::EntStreamTimeout)??;
 
+        let psk = sess.ssl().ex_data(self.psk_index).cloned();
+        self.stats.diag.lock().await.handshake(psk.as_ref());
+
         // read the first frame, and use it to look up area, color mode, etc.
         // this means we discard the first frame, but since we expect at least
         // 10 frames *per second*, this is acceptable.
@@ -217,6 +402,8 @@ impl EntertainmentService {
 
         drop(lock);
 
+        self.stats.diag.lock().await.area(header.area);
+
         let mut fps = 0;
         let mut period = Utc::now().timestamp();
 
@@ -224,8 +411,10 @@ impl EntertainmentService {
             let view = &buf[..sz];
             log::trace!("Packet buffer: {}", view.escape_ascii());
 
+            let seqnr = HueStreamHeader::parse(view)?.seqnr();
             let raw = HueStreamPacket::parse(view)?;
-            let pkt = Self::translate_frame(&*self.res.lock().await, raw)?;
+            let lock = self.res.lock().await;
+            let pkt = Self::translate_frame(&lock, raw)?;
 
             if pkt.color_mode() != header.color_mode() {
                 log::error!("Entertainment Mode color_mode changed mid-stream.");
@@ -237,16 +426,26 @@ impl EntertainmentService {
                 return Err(ApiError::EntStreamDesync);
             }
 
-            let ts = Utc::now().timestamp();
+            let req = BackendRequest::EntertainmentFrame(pkt.lights);
+            lock.backend_request(req)?;
+            drop(lock);
+
+            // frames are added to the diagnostics once per second, together
+            // with the fps
+            let now = Utc::now();
+            let ts = now.timestamp();
             if period != ts {
                 log::info!("Incoming entertainment fps: {fps}");
+                let mut diag = self.stats.diag.lock().await;
+                diag.fps(fps);
+                diag.frames(counter);
+                drop(diag);
                 period = ts;
                 fps = 0;
             }
 
             fps += 1;
-            let req = BackendRequest::EntertainmentFrame(pkt.lights);
-            self.res.lock().await.backend_request(req)?;
+            counter.frame(seqnr, now);
 
             sz = Self::read_frame(&mut sess, &mut buf).await?;
             if sz == 0 {
@@ -265,12 +464,27 @@ impl Service for EntertainmentService {
     async fn configure(&mut self) -> Result<(), Self::Error> {
         let mut bldr = SslContext::builder(SslMethod::dtls_server())?;
 
-        bldr.set_psk_server_callback(|_sslref, cid, psk| {
+        let psk_index = self.psk_index;
+        let keys_index = self.keys_index;
+        bldr.set_psk_server_callback(move |sslref, cid, psk| {
             let client_id = String::from_utf8_lossy(cid.unwrap_or_default());
             log::debug!("Setting PSK for {client_id}",);
-            STANDARD_CLIENT_KEY.write_to_slice(psk).unwrap();
+            let key = sslref
+                .ex_data(keys_index)
+                .and_then(|keys| keys.get(client_id.as_ref()));
+            if key.is_none() {
+                log::debug!("No streaming key for {client_id}, using the standard key");
+            }
+            key.unwrap_or(&STANDARD_CLIENT_KEY)
+                .write_to_slice(psk)
+                .unwrap();
 
             log::trace!("psk: {}", hex::encode(&psk[..16]));
+            let info = PskInfo {
+                identity: client_id.into_owned(),
+                psk_digest: hex::encode(&Sha256::digest(&psk[..16])[..4]),
+            };
+            sslref.set_ex_data(psk_index, info);
             Ok(16)
         });
 
@@ -302,15 +516,30 @@ impl Service for EntertainmentService {
         };
 
         loop {
-            let (socket, _addr) = udp.accept().await?;
-            let ssl = Ssl::new(ctx)?;
+            let (socket, addr) = udp.accept().await?;
+            let keys = stream_keys(&*self.res.lock().await);
+            let mut ssl = Ssl::new(ctx)?;
+            ssl.set_ex_data(self.keys_index, keys);
             let stream = SslStream::new(ssl, socket)?;
 
-            match self.run_loop(stream).await {
+            self.stats
+                .diag
+                .lock()
+                .await
+                .start(addr.to_string(), Utc::now().to_rfc3339());
+
+            let mut counter = FrameCounter::default();
+            let result = self.run_loop(stream, &mut counter).await;
+            match &result {
                 Ok(()) => log::info!("Entertainment stream finished"),
                 Err(err) => log::error!("Entertainment stream error: {err}"),
             }
 
+            let mut diag = self.stats.diag.lock().await;
+            diag.frames(&mut counter);
+            diag.finish(&result, Utc::now().to_rfc3339());
+            drop(diag);
+
             let req = BackendRequest::EntertainmentStop();
             self.res.lock().await.backend_request(req)?;
         }
@@ -321,3 +550,95 @@ impl Service for EntertainmentService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use hue::version::SwVersion;
+
+    use crate::error::ApiError;
+    use crate::model::state::{ApiUser, State};
+    use crate::resource::Resources;
+    use crate::server::entertainment::{FrameCounter, PskInfo, SessionDiag, stream_keys};
+
+    fn now() -> String {
+        "2026-01-01T00:00:00Z".to_string()
+    }
+
+    #[test]
+    fn dropped_frames() {
+        let mut diag = SessionDiag::default();
+        let mut counter = FrameCounter::default();
+        diag.start("10.0.0.2:2100".to_string(), now());
+
+        for seqnr in [254, 255, 0] {
+            counter.frame(seqnr, Utc::now());
+        }
+        diag.frames(&mut counter);
+        for seqnr in [3, 3, 4] {
+            counter.frame(seqnr, Utc::now());
+        }
+        diag.frames(&mut counter);
+
+        let report = diag.report();
+        let sess = &report.active[0];
+        assert_eq!(sess.frames, 6);
+        assert_eq!(sess.dropped_frames, 2);
+        assert!(sess.last_frame_at.is_some());
+        assert_eq!(report.counters.dropped_frames, 2);
+        assert_eq!(counter.frames, 0);
+    }
+
+    #[test]
+    fn finished_sessions() {
+        let mut diag = SessionDiag::default();
+
+        diag.start("10.0.0.2:2100".to_string(), now());
+        diag.finish(&Err(ApiError::EntStreamTimeout), now());
+
+        diag.start("10.0.0.2:2101".to_string(), now());
+        diag.handshake(Some(&PskInfo {
+            identity: "client".to_string(),
+            psk_digest: "00000000".to_string(),
+        }));
+        diag.finish(&Err(ApiError::EntStreamTimeout), now());
+
+        let report = diag.report();
+        assert!(report.active.is_empty());
+        assert_eq!(report.recent.len(), 2);
+        assert_eq!(report.recent[0].identity.as_deref(), Some("client"));
+        assert_eq!(report.counters.sessions, 2);
+        assert_eq!(report.counters.handshake_failures, 1);
+        assert_eq!(report.counters.timeouts, 1);
+    }
+
+    #[test]
+    fn stream_keys_by_identity() {
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let user = ApiUser::generate("app#phone", true);
+        let app_id = user.application_id.to_string();
+        let clientkey = user.clientkey.clone().unwrap();
+        res.add_api_user("user1".to_string(), user);
+        res.add_api_user("user2".to_string(), ApiUser::generate("app#tablet", false));
+
+        let keys = stream_keys(&res);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys["user1"].to_hex(), clientkey.to_lowercase());
+        assert_eq!(keys[&app_id].to_hex(), clientkey.to_lowercase());
+    }
+
+    #[test]
+    fn stream_keys_of_removed_user() {
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let user = ApiUser::generate("app#phone", true);
+        let app_id = user.application_id.to_string();
+        res.add_api_user("user1".to_string(), user);
+
+        assert!(res.remove_api_user("user1").is_some());
+        assert!(res.remove_api_user("user1").is_none());
+
+        let keys = stream_keys(&res);
+        assert!(!keys.contains_key("user1"));
+        assert!(!keys.contains_key(&app_id));
+    }
+}
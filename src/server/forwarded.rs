@@ -0,0 +1,142 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::server::Protocol;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// The real address and protocol of a client, as seen through any trusted
+/// reverse proxies. Inserted as a request extension by [`client_addr`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientAddr {
+    pub ip: IpAddr,
+    pub protocol: Protocol,
+}
+
+#[derive(Clone, Debug)]
+pub struct ForwardedResolver {
+    protocol: Protocol,
+    trusted: Arc<[IpAddr]>,
+}
+
+impl ForwardedResolver {
+    #[must_use]
+    pub fn new(protocol: Protocol, trusted: &[IpAddr]) -> Self {
+        Self {
+            protocol,
+            trusted: trusted.into(),
+        }
+    }
+
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted.contains(ip)
+    }
+
+    /// Find the real client behind `peer`.
+    ///
+    /// Forwarding headers are only considered when `peer` is a trusted proxy.
+    /// In that case, `X-Forwarded-For` is searched from right to left, for the
+    /// first address that is not itself a trusted proxy.
+    #[must_use]
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> ClientAddr {
+        if !self.is_trusted(&peer) {
+            return ClientAddr {
+                ip: peer,
+                protocol: self.protocol,
+            };
+        }
+
+        let chain: Vec<IpAddr> = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|addr| addr.trim().parse().ok())
+            .collect();
+
+        let ip = chain
+            .iter()
+            .rev()
+            .find(|ip| !self.is_trusted(ip))
+            .or_else(|| chain.first())
+            .copied()
+            .unwrap_or(peer);
+
+        let protocol = headers
+            .get(X_FORWARDED_PROTO)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|proto| match proto.trim().to_ascii_lowercase().as_str() {
+                "https" => Some(Protocol::Https),
+                "http" => Some(Protocol::Http),
+                _ => None,
+            })
+            .unwrap_or(self.protocol);
+
+        ClientAddr { ip, protocol }
+    }
+}
+
+/// Middleware that resolves the [`ClientAddr`] of every request
+pub async fn client_addr(
+    State(resolver): State<ForwardedResolver>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Some(peer) = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip())
+    {
+        let client = resolver.resolve(peer, req.headers());
+        req.extensions_mut().insert(client);
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use crate::server::Protocol;
+    use crate::server::forwarded::ForwardedResolver;
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_headers() {
+        let resolver = ForwardedResolver::new(Protocol::Http, &[ip("10.0.0.2"), ip("10.0.0.3")]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("1.2.3.4, 192.168.1.10, 10.0.0.3"),
+        );
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+
+        // untrusted peers cannot spoof their address
+        let client = resolver.resolve(ip("192.168.1.20"), &headers);
+        assert_eq!(client.ip, ip("192.168.1.20"));
+        assert_eq!(client.protocol, Protocol::Http);
+
+        // the right-most untrusted address is the client
+        let client = resolver.resolve(ip("10.0.0.2"), &headers);
+        assert_eq!(client.ip, ip("192.168.1.10"));
+        assert_eq!(client.protocol, Protocol::Https);
+
+        // without headers, the peer itself is the client
+        let client = resolver.resolve(ip("10.0.0.2"), &HeaderMap::new());
+        assert_eq!(client.ip, ip("10.0.0.2"));
+        assert_eq!(client.protocol, Protocol::Http);
+    }
+}
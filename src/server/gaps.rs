@@ -0,0 +1,316 @@
+//! Requests for hue api features that bifrost does not implement (yet).
+//!
+//! Routes mark such responses with [`Unsupported`], and the [`track`]
+//! middleware collects them, so users and maintainers can see which features
+//! their apps are missing.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::header::CONTENT_LENGTH;
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{SecondsFormat, Utc};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use bifrost_api::debug::CompatGap;
+
+use crate::server::appstate::AppState;
+
+/// Maximum number of distinct gaps kept. The least recently seen gap is
+/// dropped to make room for new ones.
+const MAX_GAPS: usize = 200;
+
+/// Larger request bodies are not digested
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Response extension, marking the request as not supported by bifrost
+#[derive(Clone, Debug)]
+pub struct Unsupported(pub String);
+
+impl Unsupported {
+    #[must_use]
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self(reason.into())
+    }
+}
+
+fn is_id(segment: &str) -> bool {
+    let numeric = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+    numeric || Uuid::parse_str(segment).is_ok()
+}
+
+/// Replace ids (and v1 usernames) in `path` by placeholders, so requests for
+/// different resources end up as the same gap
+#[must_use]
+pub fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = path.split('/').collect();
+
+    for (index, segment) in segments.iter_mut().enumerate() {
+        let username = index == 2 && *segment != "config" && path.starts_with("/api/");
+        if username {
+            *segment = "{username}";
+        } else if is_id(segment) {
+            *segment = "{id}";
+        }
+    }
+
+    segments.join("/")
+}
+
+/// Username of a v1 api request path (`/api/{username}/..`)
+#[must_use]
+pub fn path_username(path: &str) -> Option<&str> {
+    let mut segments = path.split('/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(""), Some("api"), Some(username)) if !username.is_empty() && username != "config" => {
+            Some(username)
+        }
+        _ => None,
+    }
+}
+
+/// Short digest of a request body, to tell different payloads apart without
+/// storing them
+#[must_use]
+pub fn body_digest(body: &[u8]) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+    Some(hex::encode(&Sha256::digest(body)[..8]))
+}
+
+#[derive(Debug, Default)]
+struct GapStore {
+    gaps: BTreeMap<(String, String), CompatGap>,
+}
+
+impl GapStore {
+    fn record(
+        &mut self,
+        method: &str,
+        path: &str,
+        reason: &str,
+        digest: Option<String>,
+        devicetype: Option<String>,
+        now: &str,
+    ) {
+        let key = (method.to_string(), normalize_path(path));
+
+        if !self.gaps.contains_key(&key) && self.gaps.len() >= MAX_GAPS {
+            let oldest = self
+                .gaps
+                .iter()
+                .min_by(|a, b| a.1.last_seen.cmp(&b.1.last_seen))
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.gaps.remove(&oldest);
+            }
+        }
+
+        let gap = self
+            .gaps
+            .entry(key)
+            .or_insert_with_key(|(method, path)| CompatGap {
+                method: method.clone(),
+                path: path.clone(),
+                reason: String::new(),
+                count: 0,
+                first_seen: now.to_string(),
+                last_seen: String::new(),
+                body_digest: None,
+                devicetypes: [].into(),
+            });
+
+        gap.count += 1;
+        gap.reason = reason.to_string();
+        gap.last_seen = now.to_string();
+        if digest.is_some() {
+            gap.body_digest = digest;
+        }
+        if let Some(devicetype) = devicetype {
+            gap.devicetypes.insert(devicetype);
+        }
+    }
+}
+
+/// Unsupported requests, keyed by (method, normalized path)
+#[derive(Clone, Debug, Default)]
+pub struct CompatGaps {
+    store: Arc<Mutex<GapStore>>,
+}
+
+impl CompatGaps {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn record(
+        &self,
+        method: &str,
+        path: &str,
+        reason: &str,
+        digest: Option<String>,
+        devicetype: Option<String>,
+    ) {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        self.store
+            .lock()
+            .await
+            .record(method, path, reason, digest, devicetype, &now);
+    }
+
+    pub async fn list(&self) -> Vec<CompatGap> {
+        self.store.lock().await.gaps.values().cloned().collect()
+    }
+
+    /// Forget all gaps, returning the ones that were recorded
+    pub async fn clear(&self) -> Vec<CompatGap> {
+        let gaps = std::mem::take(&mut self.store.lock().await.gaps);
+        gaps.into_values().collect()
+    }
+}
+
+/// Buffer small request bodies, so they can be digested after the request
+/// has been handled
+async fn buffer_body(req: Request) -> (Request, Option<Bytes>) {
+    let size = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    match size {
+        Some(size) if size > 0 && size <= MAX_BODY_SIZE => {
+            let (parts, body) = req.into_parts();
+            match axum::body::to_bytes(body, MAX_BODY_SIZE).await {
+                Ok(bytes) => (
+                    Request::from_parts(parts, Body::from(bytes.clone())),
+                    Some(bytes),
+                ),
+                Err(_) => (Request::from_parts(parts, Body::empty()), None),
+            }
+        }
+        _ => (req, None),
+    }
+}
+
+/// Middleware that records requests, which were answered as unsupported
+pub async fn track(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+
+    let (req, body) = buffer_body(req).await;
+
+    let res = next.run(req).await;
+
+    let Some(Unsupported(reason)) = res.extensions().get::<Unsupported>().cloned() else {
+        return res;
+    };
+
+    // the devicetype of the app, as it paired
+    let devicetype = match path_username(&path) {
+        Some(username) => state
+            .res
+            .lock()
+            .await
+            .api_users()
+            .get(username)
+            .map(|user| user.devicetype.clone()),
+        None => None,
+    };
+    let digest = body.as_deref().and_then(body_digest);
+
+    log::info!("Unsupported request: {method} {path} ({reason})");
+
+    state
+        .gaps()
+        .record(&method, &path, &reason, digest, devicetype)
+        .await;
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::gaps::{GapStore, MAX_GAPS, body_digest, normalize_path, path_username};
+
+    #[test]
+    fn normalize_ids() {
+        assert_eq!(
+            normalize_path("/api/abcdef/lights/12/state"),
+            "/api/{username}/lights/{id}/state"
+        );
+        assert_eq!(normalize_path("/api/config"), "/api/config");
+        assert_eq!(
+            normalize_path("/clip/v2/resource/zone/bb7a6f07-b4f6-4c39-a8ab-7d3bd2c1f8d4"),
+            "/clip/v2/resource/zone/{id}"
+        );
+        assert_eq!(normalize_path("/unknown/path"), "/unknown/path");
+    }
+
+    #[test]
+    fn username_of_path() {
+        assert_eq!(path_username("/api/abcdef/lights/12"), Some("abcdef"));
+        assert_eq!(path_username("/api/abcdef"), Some("abcdef"));
+        assert_eq!(path_username("/api/config"), None);
+        assert_eq!(path_username("/api"), None);
+        assert_eq!(path_username("/clip/v2/resource/light"), None);
+    }
+
+    #[test]
+    fn digest_empty_body() {
+        assert_eq!(body_digest(b""), None);
+        assert_eq!(body_digest(b"{}").map(|d| d.len()), Some(16));
+    }
+
+    #[test]
+    fn record_merges_requests() {
+        let mut store = GapStore::default();
+        store.record(
+            "POST",
+            "/api/user/rules",
+            "nope",
+            body_digest(b"{}"),
+            Some("app#phone".to_string()),
+            "2026-01-01T00:00:00.000Z",
+        );
+        store.record(
+            "POST",
+            "/api/other/rules",
+            "nope",
+            None,
+            Some("other#tablet".to_string()),
+            "2026-01-02T00:00:00.000Z",
+        );
+
+        assert_eq!(store.gaps.len(), 1);
+        let gap = store.gaps.values().next().unwrap();
+        assert_eq!(gap.path, "/api/{username}/rules");
+        assert_eq!(gap.count, 2);
+        assert_eq!(gap.first_seen, "2026-01-01T00:00:00.000Z");
+        assert_eq!(gap.last_seen, "2026-01-02T00:00:00.000Z");
+        assert!(gap.body_digest.is_some());
+        assert_eq!(gap.devicetypes.len(), 2);
+    }
+
+    #[test]
+    fn record_evicts_least_recent() {
+        let mut store = GapStore::default();
+        for n in 0..=MAX_GAPS {
+            let now = format!("2026-01-01T00:00:{n:08}Z");
+            store.record("GET", &format!("/x{n}"), "nope", None, None, &now);
+        }
+
+        assert_eq!(store.gaps.len(), MAX_GAPS);
+        assert!(
+            !store
+                .gaps
+                .contains_key(&("GET".to_string(), "/x0".to_string()))
+        );
+    }
+}
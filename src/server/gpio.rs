@@ -0,0 +1,34 @@
+//! Minimal access to gpio pins, through the sysfs gpio interface
+
+use crate::error::ApiResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+impl Direction {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::In => "in",
+            Self::Out => "out",
+        }
+    }
+}
+
+/// Export gpio `pin` (if needed) and set its direction.
+///
+/// Returns the path of the file holding the pin value.
+pub async fn prepare(pin: u32, direction: Direction) -> ApiResult<String> {
+    let path = format!("/sys/class/gpio/gpio{pin}");
+
+    if tokio::fs::metadata(&path).await.is_err() {
+        log::debug!("Exporting gpio pin {pin}");
+        tokio::fs::write("/sys/class/gpio/export", pin.to_string()).await?;
+    }
+
+    tokio::fs::write(format!("{path}/direction"), direction.as_str()).await?;
+
+    Ok(format!("{path}/value"))
+}
@@ -29,11 +29,11 @@ pub struct HueEventStream {
 
 impl HueEventStream {
     #[must_use]
-    pub fn new(buffer_capacity: usize) -> Self {
+    pub fn new(buffer_capacity: usize, channel_size: usize) -> Self {
         Self {
             timestamp: Utc::now(),
             index: 0,
-            hue_updates: Sender::new(32),
+            hue_updates: Sender::new(channel_size),
             buffer: VecDeque::with_capacity(buffer_capacity),
         }
     }
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+use bifrost_api::backend::BackendRequest;
+use hue::api::{DeviceIdentify, DeviceIdentifyUpdate, LightUpdate, RType};
+
+use crate::config::{IdentifyConfig, IdentifyGpio};
+use crate::error::ApiResult;
+use crate::resource::Resources;
+use crate::server::gpio::{self, Direction};
+
+/// Number of times the indicator led is blinked
+const BLINK_COUNT: u32 = 5;
+
+/// Time the led spends in each state (on/off) while blinking
+const BLINK_PERIOD: Duration = Duration::from_millis(300);
+
+async fn gpio_set(value_path: &str, gpio: &IdentifyGpio, on: bool) -> ApiResult<()> {
+    let value = if on != gpio.active_low { "1" } else { "0" };
+    tokio::fs::write(value_path, value).await?;
+    Ok(())
+}
+
+/// Blink the indicator led on the configured gpio pin a few times.
+///
+/// Uses the sysfs gpio interface, and leaves the led turned off.
+pub async fn blink_gpio(gpio: &IdentifyGpio) -> ApiResult<()> {
+    let value_path = gpio::prepare(gpio.pin, Direction::Out).await?;
+
+    for _ in 0..BLINK_COUNT {
+        gpio_set(&value_path, gpio, true).await?;
+        sleep(BLINK_PERIOD).await;
+        gpio_set(&value_path, gpio, false).await?;
+        sleep(BLINK_PERIOD).await;
+    }
+
+    Ok(())
+}
+
+/// Briefly pulse all known lights, using their "identify" action.
+pub fn pulse_lights(res: &Resources) {
+    let identify = DeviceIdentifyUpdate {
+        action: DeviceIdentify::Identify,
+    };
+
+    // one failing light (e.g. of a stopped backend) should not stop the others
+    for id in res.get_resource_ids_by_type(RType::Light) {
+        let upd = LightUpdate::new().with_identify(Some(identify));
+        let req = BackendRequest::LightUpdate(RType::Light.link_to(id), upd);
+        if let Err(err) = res.backend_request(req) {
+            log::warn!("Failed to pulse light {id}: {err}");
+        }
+    }
+}
+
+/// Make the bridge itself visibly identify itself.
+///
+/// Blinks the configured gpio led if there is one, and falls back to pulsing
+/// all lights (if enabled) when there isn't, or blinking it fails.
+pub async fn identify_bridge(res: &Mutex<Resources>, config: &IdentifyConfig) {
+    if let Some(gpio) = &config.gpio {
+        log::info!("Identifying bridge by blinking gpio pin {}", gpio.pin);
+        match blink_gpio(gpio).await {
+            Ok(()) => return,
+            Err(err) => log::warn!(
+                "Failed to blink identify led (gpio pin {}): {err}",
+                gpio.pin
+            ),
+        }
+    }
+
+    if config.pulse_lights {
+        log::info!("Identifying bridge by pulsing all lights");
+        pulse_lights(&*res.lock().await);
+    } else {
+        log::info!("Bridge identify requested, but no identify method is configured");
+    }
+}
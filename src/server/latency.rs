@@ -0,0 +1,456 @@
+//! Backend latency: the time from sending a request to a backend, until the
+//! state change it causes is reported back.
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use bifrost_api::debug::{BackendLatencyStats, LatencyReport, SlowEntity};
+use hue::api::{GroupedLight, Light, Room};
+
+use crate::backend::router::LatencySignal;
+use crate::error::ApiResult;
+use crate::model::hass::HassUiState;
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
+use crate::resource::Resources;
+use crate::server::metrics::{LatencyHistogram, escape_label};
+
+/// Requests taking longer than this are considered slow
+const SLOW: Duration = Duration::from_secs(2);
+
+/// Requests not confirmed within this time are counted as timeouts
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of recent requests kept for each entity
+const RECENT: usize = 5;
+
+/// Minimum number of recent requests, before an entity can be flagged as slow
+const RECENT_MIN: usize = 3;
+
+#[derive(Debug)]
+struct Pending {
+    backend: String,
+    name: String,
+    sent: Instant,
+}
+
+#[derive(Debug, Default)]
+struct EntityLatency {
+    backend: String,
+    name: String,
+    recent: VecDeque<Duration>,
+    slow: bool,
+}
+
+impl EntityLatency {
+    /// Record a request. Returns the new slow state, if it changed.
+    fn record(&mut self, latency: Duration) -> Option<bool> {
+        if self.recent.len() == RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+
+        // slow if most of the recent requests were slow
+        let slow_count = self.recent.iter().filter(|lat| **lat > SLOW).count();
+        let slow = self.recent.len() >= RECENT_MIN && slow_count * 2 > self.recent.len();
+
+        if slow == self.slow {
+            return None;
+        }
+        self.slow = slow;
+        Some(slow)
+    }
+}
+
+#[derive(Debug, Default)]
+struct BackendStats {
+    latency: LatencyHistogram,
+    timeouts: u64,
+}
+
+/// An entity became slow, or recovered
+#[derive(Debug, PartialEq, Eq)]
+struct SlowChange {
+    backend: String,
+    name: String,
+    slow: bool,
+}
+
+#[derive(Debug, Default)]
+struct LatencyState {
+    pending: HashMap<Uuid, Pending>,
+    backends: BTreeMap<String, BackendStats>,
+    entities: BTreeMap<Uuid, EntityLatency>,
+}
+
+impl LatencyState {
+    fn sent(&mut self, id: Uuid, backend: String, name: String, now: Instant) {
+        // while a request is unconfirmed, newer ones are measured from the
+        // first one
+        self.pending.entry(id).or_insert(Pending {
+            backend,
+            name,
+            sent: now,
+        });
+    }
+
+    fn record(&mut self, id: Uuid, pending: Pending, latency: Duration) -> Option<SlowChange> {
+        let entity = self.entities.entry(id).or_default();
+        entity.backend.clone_from(&pending.backend);
+        entity.name.clone_from(&pending.name);
+
+        entity.record(latency).map(|slow| SlowChange {
+            backend: pending.backend,
+            name: pending.name,
+            slow,
+        })
+    }
+
+    fn confirmed(&mut self, id: &Uuid, now: Instant) -> Option<SlowChange> {
+        let pending = self.pending.remove(id)?;
+        let latency = now.duration_since(pending.sent);
+
+        self.backends
+            .entry(pending.backend.clone())
+            .or_default()
+            .latency
+            .record(latency);
+
+        self.record(*id, pending, latency)
+    }
+
+    /// Give up on requests that were not confirmed in time
+    fn expire(&mut self, now: Instant) -> Vec<SlowChange> {
+        let expired: Vec<Uuid> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.sent) >= TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut changes = vec![];
+        for id in expired {
+            let Some(pending) = self.pending.remove(&id) else {
+                continue;
+            };
+
+            self.backends
+                .entry(pending.backend.clone())
+                .or_default()
+                .timeouts += 1;
+
+            changes.extend(self.record(id, pending, TIMEOUT));
+        }
+
+        changes
+    }
+
+    fn report(&self) -> LatencyReport {
+        let backends = self
+            .backends
+            .iter()
+            .map(|(backend, stats)| BackendLatencyStats {
+                backend: backend.clone(),
+                count: stats.latency.count(),
+                timeouts: stats.timeouts,
+                max_ms: stats.latency.max().as_secs_f64() * 1000.0,
+                p50_ms: stats.latency.quantile(0.50) * 1000.0,
+                p90_ms: stats.latency.quantile(0.90) * 1000.0,
+                p99_ms: stats.latency.quantile(0.99) * 1000.0,
+            })
+            .collect();
+
+        let slow = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.slow)
+            .map(|(id, entity)| SlowEntity {
+                id: *id,
+                name: entity.name.clone(),
+                backend: entity.backend.clone(),
+                recent_ms: entity
+                    .recent
+                    .iter()
+                    .map(|lat| lat.as_secs_f64() * 1000.0)
+                    .collect(),
+            })
+            .collect();
+
+        LatencyReport { backends, slow }
+    }
+
+    fn prometheus(&self) -> String {
+        const LATENCY: &str = "bifrost_backend_latency_seconds";
+        const TIMEOUTS: &str = "bifrost_backend_timeouts_total";
+        const SLOW_ENTITIES: &str = "bifrost_backend_slow_entities";
+
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP {LATENCY} Time until a backend request is confirmed, by backend"
+        );
+        let _ = writeln!(out, "# TYPE {LATENCY} histogram");
+        for (backend, stats) in &self.backends {
+            let labels = format!("backend=\"{}\"", escape_label(backend));
+            stats.latency.write_prometheus(&mut out, LATENCY, &labels);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP {TIMEOUTS} Backend requests that were never confirmed, by backend"
+        );
+        let _ = writeln!(out, "# TYPE {TIMEOUTS} counter");
+        for (backend, stats) in &self.backends {
+            let _ = writeln!(
+                out,
+                "{TIMEOUTS}{{backend=\"{}\"}} {}",
+                escape_label(backend),
+                stats.timeouts
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP {SLOW_ENTITIES} Entities consistently slow to confirm requests, by backend"
+        );
+        let _ = writeln!(out, "# TYPE {SLOW_ENTITIES} gauge");
+        for backend in self.backends.keys() {
+            let count = self
+                .entities
+                .values()
+                .filter(|entity| entity.slow && entity.backend == *backend)
+                .count();
+            let _ = writeln!(
+                out,
+                "{SLOW_ENTITIES}{{backend=\"{}\"}} {count}",
+                escape_label(backend)
+            );
+        }
+
+        out
+    }
+}
+
+/// Shared handle to the backend latency statistics
+#[derive(Clone, Debug, Default)]
+pub struct BackendLatency {
+    state: Arc<Mutex<LatencyState>>,
+}
+
+impl BackendLatency {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn report(&self) -> LatencyReport {
+        self.state.lock().await.report()
+    }
+
+    /// Render the statistics in the prometheus text exposition format
+    pub async fn prometheus(&self) -> String {
+        self.state.lock().await.prometheus()
+    }
+}
+
+/// Name of the light (or room) a request is measured for
+fn entity_name(res: &Resources, id: Uuid) -> String {
+    if let Ok(light) = res.get_id::<Light>(id) {
+        return light.metadata.name.clone();
+    }
+
+    res.get_id::<GroupedLight>(id)
+        .and_then(|glight| res.get::<Room>(&glight.owner))
+        .map_or_else(|_| id.to_string(), |room| room.metadata.name.clone())
+}
+
+/// Resources confirmed by a state report for `id`: the resource itself, and
+/// for lights, the grouped light of their room
+fn reported(res: &Resources, id: Uuid) -> Vec<Uuid> {
+    let room = res
+        .get_id::<Light>(id)
+        .ok()
+        .and_then(|light| res.device_room(&light.owner))
+        .and_then(Room::grouped_light_service)
+        .map(|glight| glight.rid);
+
+    std::iter::once(id).chain(room).collect()
+}
+
+async fn report_change(ui: &Mutex<HassUiState>, change: SlowChange) {
+    let entry = if change.slow {
+        log::warn!(
+            "[{}] {:?} is consistently slow to respond (over {}s)",
+            change.backend,
+            change.name,
+            SLOW.as_secs()
+        );
+        TimelineEntry::new(
+            TimelineCategory::Command,
+            format!("{} is slow to respond", change.name),
+        )
+        .with_level(TimelineLevel::Warning)
+    } else {
+        log::info!(
+            "[{}] {:?} responds normally again",
+            change.backend,
+            change.name
+        );
+        TimelineEntry::new(
+            TimelineCategory::Command,
+            format!("{} responds normally again", change.name),
+        )
+    };
+
+    ui.lock()
+        .await
+        .push_entry(entry.with_source(change.backend));
+}
+
+/// Measure the latency of light and room requests, for each backend.
+///
+/// A request is confirmed by the first state report for its target, by the
+/// backend itself (not by the optimistic update of the hue state). Requests
+/// that would not change anything are not measured. Lights (and rooms) that
+/// are consistently slow are logged, and flagged in the activity timeline.
+pub async fn backend_latency(
+    latency: BackendLatency,
+    res: Arc<Mutex<Resources>>,
+    ui: Arc<Mutex<HassUiState>>,
+) -> ApiResult<()> {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    let mut signals = res.lock().await.backend_latency_stream();
+
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        let changes = tokio::select! {
+            sig = signals.recv() => match sig {
+                Ok(LatencySignal::Sent { id, backend }) => {
+                    let name = entity_name(&*res.lock().await, id);
+                    latency.state.lock().await.sent(id, backend, name, Instant::now());
+                    vec![]
+                }
+                Ok(LatencySignal::Reported(id)) => {
+                    let ids = reported(&*res.lock().await, id);
+                    let now = Instant::now();
+                    let mut state = latency.state.lock().await;
+                    ids.iter().filter_map(|id| state.confirmed(id, now)).collect()
+                }
+                Err(RecvError::Lagged(_)) => vec![],
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = interval.tick() => latency.state.lock().await.expire(Instant::now()),
+        };
+
+        for change in changes {
+            report_change(&ui, change).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use uuid::Uuid;
+
+    use crate::server::latency::{LatencyState, SlowChange, TIMEOUT};
+
+    const FAST: Duration = Duration::from_millis(200);
+    const SLOW: Duration = Duration::from_secs(3);
+
+    fn request(
+        state: &mut LatencyState,
+        id: Uuid,
+        now: Instant,
+        latency: Duration,
+    ) -> Option<SlowChange> {
+        state.sent(id, "z2m".to_string(), "Lamp".to_string(), now);
+        state.confirmed(&id, now + latency)
+    }
+
+    #[test]
+    fn measures_confirmed_requests() {
+        let mut state = LatencyState::default();
+        let id = Uuid::new_v4();
+        let now = Instant::now();
+
+        assert_eq!(request(&mut state, id, now, FAST), None);
+
+        // updates without a pending request are ignored
+        assert_eq!(state.confirmed(&id, now + SLOW), None);
+
+        let report = state.report();
+        assert_eq!(report.backends.len(), 1);
+        assert_eq!(report.backends[0].count, 1);
+        assert_eq!(report.backends[0].timeouts, 0);
+        assert!(report.slow.is_empty());
+    }
+
+    #[test]
+    fn flags_consistently_slow() {
+        let mut state = LatencyState::default();
+        let id = Uuid::new_v4();
+        let now = Instant::now();
+
+        // a single slow request is not enough
+        assert_eq!(request(&mut state, id, now, SLOW), None);
+        assert_eq!(request(&mut state, id, now, FAST), None);
+        assert_eq!(
+            request(&mut state, id, now, SLOW),
+            Some(SlowChange {
+                backend: "z2m".to_string(),
+                name: "Lamp".to_string(),
+                slow: true,
+            })
+        );
+        assert_eq!(state.report().slow.len(), 1);
+
+        // recovers once most recent requests are fast again
+        assert!(request(&mut state, id, now, FAST).is_some_and(|change| !change.slow));
+        assert!(state.report().slow.is_empty());
+    }
+
+    #[test]
+    fn timeouts_count_as_slow() {
+        let mut state = LatencyState::default();
+        let id = Uuid::new_v4();
+        let now = Instant::now();
+
+        state.sent(id, "hass".to_string(), "Lamp".to_string(), now);
+        assert!(state.expire(now + TIMEOUT / 2).is_empty());
+
+        for round in 0..3 {
+            let sent = now + TIMEOUT * round;
+            state.sent(id, "hass".to_string(), "Lamp".to_string(), sent);
+            state.expire(sent + TIMEOUT);
+        }
+
+        let report = state.report();
+        assert_eq!(report.backends[0].timeouts, 3);
+        assert_eq!(report.backends[0].count, 0);
+        assert_eq!(report.slow.len(), 1);
+    }
+
+    #[test]
+    fn measures_from_first_unconfirmed() {
+        let mut state = LatencyState::default();
+        let id = Uuid::new_v4();
+        let now = Instant::now();
+
+        state.sent(id, "z2m".to_string(), "Lamp".to_string(), now);
+        state.sent(id, "z2m".to_string(), "Lamp".to_string(), now + SLOW);
+        state.confirmed(&id, now + SLOW + FAST);
+
+        assert!(state.report().backends[0].max_ms > 3000.0);
+    }
+}
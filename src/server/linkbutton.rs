@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+
+use bifrost_api::backend::BifrostActivity;
+
+use crate::config::LinkButtonGpio;
+use crate::error::ApiResult;
+use crate::model::hass::HassUiState;
+use crate::model::timeline::TimelineCategory;
+use crate::resource::Resources;
+use crate::server::gpio::{self, Direction};
+
+/// The virtual link button of the emulated bridge
+#[derive(Clone, Debug, Default)]
+pub struct LinkButton {
+    until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl LinkButton {
+    /// How long the link button stays active after being pressed
+    pub const PRESS_DURATION: Duration = Duration::from_secs(30);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn press(&self, active_for: Duration) {
+        let mut lock = self.until.lock().await;
+        *lock = Some(Instant::now() + active_for);
+    }
+
+    pub async fn is_active(&self) -> bool {
+        let now = Instant::now();
+        let mut lock = self.until.lock().await;
+        match *lock {
+            Some(until) if until > now => true,
+            Some(_) => {
+                *lock = None;
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// Press the link button whenever the configured gpio pin becomes active.
+///
+/// Uses the sysfs gpio interface, polling the pin value.
+pub async fn linkbutton_gpio(
+    linkbutton: LinkButton,
+    res: Arc<Mutex<Resources>>,
+    ui: Arc<Mutex<HassUiState>>,
+    gpio: LinkButtonGpio,
+) -> ApiResult<()> {
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    let value_path = gpio::prepare(gpio.pin, Direction::In).await?;
+    log::info!("Watching gpio pin {} for link button presses", gpio.pin);
+
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut was_active = false;
+
+    loop {
+        interval.tick().await;
+
+        let value = tokio::fs::read_to_string(&value_path).await?;
+        let active = (value.trim() == "1") != gpio.active_low;
+
+        if active && !was_active {
+            log::info!("Link button pressed (gpio pin {})", gpio.pin);
+            linkbutton.press(LinkButton::PRESS_DURATION).await;
+            res.lock()
+                .await
+                .forward_activity(BifrostActivity::LinkButton {
+                    source: "gpio".to_string(),
+                });
+            ui.lock().await.push_event(
+                TimelineCategory::Pairing,
+                format!(
+                    "Virtual bridge button pressed via gpio pin {} ({}s active)",
+                    gpio.pin,
+                    LinkButton::PRESS_DURATION.as_secs()
+                ),
+            );
+        }
+
+        was_active = active;
+    }
+}
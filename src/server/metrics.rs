@@ -0,0 +1,278 @@
+//! Per-route latency and status histograms, for the http(s) servers.
+//!
+//! The latency histogram is also used for the backend latency statistics.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::sync::Mutex;
+
+use bifrost_api::debug::RouteStats;
+
+/// Upper bounds (in seconds) of the latency histogram buckets
+const BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    /// Number of samples in each bucket (not cumulative). The last entry
+    /// counts samples slower than the largest bucket.
+    buckets: [u64; BUCKETS.len() + 1],
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        let index = BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(BUCKETS.len());
+
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    #[must_use]
+    pub const fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    #[must_use]
+    pub const fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Estimate the latency (in seconds) at quantile `q` (0.0 - 1.0), as the
+    /// upper bound of the bucket it falls in.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn quantile(&self, q: f64) -> f64 {
+        let mut seen = 0;
+        for (count, bound) in self.buckets.iter().zip(BUCKETS) {
+            seen += count;
+            if seen as f64 >= q * self.count as f64 {
+                return bound.min(self.max.as_secs_f64());
+            }
+        }
+        self.max.as_secs_f64()
+    }
+
+    /// Write this histogram in the prometheus text exposition format
+    pub fn write_prometheus(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (count, bound) in self.buckets.iter().zip(BUCKETS) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum.as_secs_f64());
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RouteHistogram {
+    latency: LatencyHistogram,
+    status: BTreeMap<u16, u64>,
+}
+
+impl RouteHistogram {
+    pub fn record(&mut self, latency: Duration, status: u16) {
+        self.latency.record(latency);
+        *self.status.entry(status).or_default() += 1;
+    }
+
+    #[must_use]
+    pub fn quantile(&self, q: f64) -> f64 {
+        self.latency.quantile(q)
+    }
+
+    #[must_use]
+    pub fn stats(&self, method: &str, route: &str) -> RouteStats {
+        RouteStats {
+            method: method.to_string(),
+            route: route.to_string(),
+            count: self.latency.count,
+            sum_ms: self.latency.sum.as_secs_f64() * 1000.0,
+            max_ms: self.latency.max.as_secs_f64() * 1000.0,
+            p50_ms: self.quantile(0.50) * 1000.0,
+            p90_ms: self.quantile(0.90) * 1000.0,
+            p99_ms: self.quantile(0.99) * 1000.0,
+            status: self.status.clone(),
+        }
+    }
+}
+
+/// Histograms for all routes, keyed by (method, route)
+#[derive(Clone, Debug, Default)]
+pub struct RouteMetrics {
+    routes: Arc<Mutex<BTreeMap<(String, String), RouteHistogram>>>,
+}
+
+/// Escape a label value for the prometheus text format
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+impl RouteMetrics {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn record(&self, method: &str, route: &str, latency: Duration, status: u16) {
+        self.routes
+            .lock()
+            .await
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .record(latency, status);
+    }
+
+    pub async fn summary(&self) -> Vec<RouteStats> {
+        self.routes
+            .lock()
+            .await
+            .iter()
+            .map(|((method, route), hist)| hist.stats(method, route))
+            .collect()
+    }
+
+    /// Render all histograms in the prometheus text exposition format
+    pub async fn prometheus(&self) -> String {
+        const LATENCY: &str = "bifrost_http_request_duration_seconds";
+        const RESPONSES: &str = "bifrost_http_responses_total";
+
+        let routes = self.routes.lock().await;
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP {LATENCY} HTTP request latency, by route");
+        let _ = writeln!(out, "# TYPE {LATENCY} histogram");
+        for ((method, route), hist) in routes.iter() {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\"",
+                escape_label(method),
+                escape_label(route)
+            );
+            hist.latency.write_prometheus(&mut out, LATENCY, &labels);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP {RESPONSES} HTTP responses, by route and status"
+        );
+        let _ = writeln!(out, "# TYPE {RESPONSES} counter");
+        for ((method, route), hist) in routes.iter() {
+            for (status, count) in &hist.status {
+                let _ = writeln!(
+                    out,
+                    "{RESPONSES}{{method=\"{}\",route=\"{}\",status=\"{status}\"}} {count}",
+                    escape_label(method),
+                    escape_label(route),
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// The method label of `method`. Unknown (extension) methods share one label,
+/// so clients cannot create an unbounded number of series.
+fn method_label(method: &Method) -> &'static str {
+    match method.as_str() {
+        "GET" => "GET",
+        "POST" => "POST",
+        "PUT" => "PUT",
+        "DELETE" => "DELETE",
+        "HEAD" => "HEAD",
+        "OPTIONS" => "OPTIONS",
+        "PATCH" => "PATCH",
+        "CONNECT" => "CONNECT",
+        "TRACE" => "TRACE",
+        _ => "other",
+    }
+}
+
+/// Middleware that records latency and status of every request, by route
+pub async fn record(State(metrics): State<RouteMetrics>, req: Request, next: Next) -> Response {
+    let method = method_label(req.method());
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("<unmatched>", MatchedPath::as_str)
+        .to_string();
+
+    let start = Instant::now();
+    let res = next.run(req).await;
+
+    metrics
+        .record(method, &route, start.elapsed(), res.status().as_u16())
+        .await;
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::Method;
+
+    use crate::server::metrics::{RouteHistogram, method_label};
+
+    #[test]
+    fn histogram_quantiles() {
+        let mut hist = RouteHistogram::default();
+        for _ in 0..90 {
+            hist.record(Duration::from_millis(3), 200);
+        }
+        for _ in 0..10 {
+            hist.record(Duration::from_millis(300), 500);
+        }
+
+        assert!((hist.quantile(0.5) - 0.005).abs() < f64::EPSILON);
+        assert!((hist.quantile(0.9) - 0.005).abs() < f64::EPSILON);
+        assert!((hist.quantile(0.99) - 0.3).abs() < f64::EPSILON);
+        assert_eq!(hist.status[&200], 90);
+        assert_eq!(hist.status[&500], 10);
+    }
+
+    #[test]
+    fn histogram_empty() {
+        let hist = RouteHistogram::default();
+        assert!(hist.quantile(0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn unknown_methods_share_label() {
+        assert_eq!(method_label(&Method::GET), "GET");
+        assert_eq!(
+            method_label(&Method::from_bytes(b"PROPFIND").unwrap()),
+            "other"
+        );
+        assert_eq!(
+            method_label(&Method::from_bytes(b"X-RANDOM").unwrap()),
+            "other"
+        );
+    }
+}
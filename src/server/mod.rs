@@ -1,13 +1,37 @@
 #[cfg(feature = "server-banner")]
 pub mod banner;
 
+#[cfg(feature = "alloc-stats")]
+pub mod alloc;
 pub mod appstate;
 pub mod certificate;
+pub mod cleanup;
+pub mod compat;
+pub mod contact;
+pub mod csrf;
 pub mod entertainment;
+pub mod forwarded;
+pub mod gaps;
+pub mod gpio;
 pub mod http;
 pub mod hueevents;
+pub mod identify;
+pub mod latency;
+pub mod linkbutton;
 pub mod mdns;
+pub mod metrics;
+pub mod motion;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod presence;
+pub mod proxy;
+pub mod ratelimit;
+pub mod release;
+pub mod reset;
+pub mod rules;
 pub mod ssdp;
+pub mod swupdate;
+pub mod timeline;
 pub mod updater;
 
 use std::fs::File;
@@ -19,24 +43,26 @@ use std::time::Duration;
 use axum::body::Body;
 use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
 use axum::extract::{ConnectInfo, Request};
+use axum::middleware;
 use axum::response::Response;
 use axum::{Router, ServiceExt};
 
 use camino::Utf8PathBuf;
 use tokio::select;
 use tokio::sync::Mutex;
-use tokio::time::{MissedTickBehavior, sleep_until};
+use tokio::time::sleep_until;
 use tower::Layer;
-use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::normalize_path::{NormalizePath, NormalizePathLayer};
 use tower_http::trace::TraceLayer;
 use tracing::{Span, info_span};
 
 use crate::error::ApiResult;
+use crate::model::hass::StateFile;
 use crate::resource::Resources;
 use crate::routes;
+use crate::routes::RouteSet;
 use crate::server::appstate::AppState;
-use crate::server::updater::VersionUpdater;
+use crate::server::forwarded::{ClientAddr, ForwardedResolver};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Protocol {
@@ -45,10 +71,16 @@ pub enum Protocol {
 }
 
 fn trace_layer_make_span_with(request: &Request, protocol: Protocol) -> Span {
-    let addr = request
-        .extensions()
-        .get::<ConnectInfo<SocketAddr>>()
-        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ci| ci.0.ip());
+    let (addr, protocol) = request.extensions().get::<ClientAddr>().map_or_else(
+        || {
+            let addr = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ci| ci.0.ip());
+            (addr, protocol)
+        },
+        |client| (client.ip, client.protocol),
+    );
 
     match protocol {
         Protocol::Https => info_span!(
@@ -76,25 +108,36 @@ fn trace_layer_on_response(response: &Response<Body>, latency: Duration, span: &
     span.record("status", tracing::field::display(response.status()));
 }
 
-fn router(protocol: Protocol, appstate: AppState) -> Router<()> {
-    routes::router(appstate).layer(
-        TraceLayer::new_for_http()
-            .make_span_with(move |request: &Request| trace_layer_make_span_with(request, protocol))
-            .on_response(trace_layer_on_response),
-    )
+fn router(protocol: Protocol, appstate: AppState, routes: RouteSet) -> Router<()> {
+    let resolver = ForwardedResolver::new(protocol, &appstate.config().bifrost.trusted_proxies);
+    let route_metrics = appstate.metrics();
+
+    routes::router(appstate, routes)
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(move |request: &Request| {
+                    trace_layer_make_span_with(request, protocol)
+                })
+                .on_response(trace_layer_on_response),
+        )
+        .layer(middleware::from_fn_with_state(
+            route_metrics,
+            metrics::record,
+        ))
+        .layer(middleware::from_fn_with_state(
+            resolver,
+            forwarded::client_addr,
+        ))
 }
 
 #[must_use]
 pub fn build_service(
     protocol: Protocol,
     appstate: AppState,
+    routes: RouteSet,
 ) -> IntoMakeServiceWithConnectInfo<NormalizePath<Router>, SocketAddr> {
-    let cors_layer = CorsLayer::new()
-        .allow_methods(Any)
-        .allow_origin(AllowOrigin::any())
-        .allow_headers(Any);
-    let normalized = NormalizePathLayer::trim_trailing_slash()
-        .layer(router(protocol, appstate).layer(cors_layer));
+    let normalized =
+        NormalizePathLayer::trim_trailing_slash().layer(router(protocol, appstate, routes));
 
     ServiceExt::<Request>::into_make_service_with_connect_info(normalized)
 }
@@ -103,8 +146,6 @@ pub async fn config_writer(res: Arc<Mutex<Resources>>, filename: Utf8PathBuf) ->
     const STABILIZE_TIME: Duration = Duration::from_secs(1);
 
     let rx = res.lock().await.state_channel();
-    let tmp = filename.with_extension("tmp");
-
     let mut old_state = res.lock().await.serialize()?;
 
     loop {
@@ -132,34 +173,65 @@ pub async fn config_writer(res: Arc<Mutex<Resources>>, filename: Utf8PathBuf) ->
 
         log::debug!("Config changed, saving..");
 
+        write_file(filename.clone(), new_state.clone()).await?;
+
+        old_state = new_state;
+    }
+}
+
+/// Write file through a temporary file, on the blocking thread pool
+async fn write_file(filename: Utf8PathBuf, data: String) -> ApiResult<()> {
+    tokio::task::spawn_blocking(move || {
+        let tmp = filename.with_extension("tmp");
         let mut fd = File::create(&tmp)?;
-        fd.write_all(new_state.as_bytes())?;
+        fd.write_all(data.as_bytes())?;
         std::fs::rename(&tmp, &filename)?;
+        Ok(())
+    })
+    .await?
+}
 
-        old_state = new_state;
+/// Save a state file now. Failures are logged, and reported to the state.
+pub async fn save_state_file<T: StateFile + Send + 'static>(state: &Arc<Mutex<T>>) {
+    let mut lock = state.lock().await;
+    let filename = lock.filename().to_owned();
+    let data = match lock.serialize_file() {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("Failed to serialize {filename}: {err}");
+            lock.save_failed(&err);
+            return;
+        }
+    };
+    drop(lock);
+
+    log::debug!("Saving {filename}..");
+
+    if let Err(err) = write_file(filename.clone(), data).await {
+        log::error!("Failed to save {filename}: {err}");
+        state.lock().await.save_failed(&err);
     }
 }
 
-#[allow(clippy::significant_drop_tightening)]
-pub async fn version_updater(
-    res: Arc<Mutex<Resources>>,
-    upd: Arc<Mutex<VersionUpdater>>,
+pub async fn state_file_writer<T: StateFile + Send + 'static>(
+    state: Arc<Mutex<T>>,
 ) -> ApiResult<()> {
-    const INTERVAL: Duration = Duration::from_secs(60);
-    let mut interval = tokio::time::interval(INTERVAL);
-    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    /* Saves are requested on every change, so batch them up */
+    const STABILIZE_TIME: Duration = Duration::from_millis(500);
 
-    let mut version = upd.lock().await.get().await.clone();
+    let rx = state.lock().await.changes();
 
     loop {
-        interval.tick().await;
-
-        let mut lock = upd.lock().await;
-        let new_version = lock.get().await;
-        if new_version != &version {
-            log::info!("New version detected! Patching state database with new version numbers..");
-            version.clone_from(new_version);
-            res.lock().await.update_bridge_version(version.clone());
+        rx.notified().await;
+
+        let deadline = tokio::time::Instant::now() + STABILIZE_TIME;
+        loop {
+            select! {
+                () = rx.notified() => {},
+                () = sleep_until(deadline) => break,
+            }
         }
+
+        save_state_file(&state).await;
     }
 }
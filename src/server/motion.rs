@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{Local, Timelike};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use bifrost_api::rule::{MotionRule, RuleAction};
+use hue::api::{Device, LightLevel, Motion, RType};
+use hue::event::{Event, EventBlock};
+
+use crate::resource::Resources;
+use crate::server::presence::in_window;
+use crate::server::rules::{room_on, run_action};
+
+/// Runtime state of the motion rules, by rule id
+pub type MotionStates = HashMap<Uuid, MotionState>;
+
+/// Runtime state of a single motion rule
+#[derive(Debug, Default)]
+pub struct MotionState {
+    /// Did this rule turn on the lights?
+    triggered: bool,
+    /// When to turn the lights off again, once motion has stopped
+    off_at: Option<Instant>,
+}
+
+impl MotionState {
+    /// Handle a motion report. Returns true if the rule should turn on the
+    /// lights.
+    fn motion(
+        &mut self,
+        motion: bool,
+        room_on: bool,
+        dark: bool,
+        timeout: Duration,
+        now: Instant,
+    ) -> bool {
+        if !motion {
+            if self.triggered && self.off_at.is_none() {
+                self.off_at = Some(now + timeout);
+            }
+            return false;
+        }
+
+        self.off_at = None;
+
+        // lights someone else turned on are left alone
+        if self.triggered || room_on || !dark {
+            return false;
+        }
+
+        self.triggered = true;
+        true
+    }
+
+    /// Returns true if the rule should turn the lights off now.
+    fn expire(&mut self, room_on: bool, now: Instant) -> bool {
+        let Some(off_at) = self.off_at else {
+            return false;
+        };
+
+        if !room_on {
+            // already turned off by someone else
+            *self = Self::default();
+            return false;
+        }
+
+        if now < off_at {
+            return false;
+        }
+
+        *self = Self::default();
+        true
+    }
+}
+
+/// Is it dark enough for the rule to trigger?
+///
+/// Uses the light level sensor on the same device as the motion sensor. Without
+/// one, it is always considered dark enough.
+fn is_dark(res: &Resources, rule: &MotionRule) -> bool {
+    let Some(max) = rule.max_light_level else {
+        return true;
+    };
+
+    let level = res
+        .get::<Motion>(&rule.sensor)
+        .and_then(|motion| res.get::<Device>(&motion.owner))
+        .ok()
+        .and_then(|dev| {
+            dev.services
+                .iter()
+                .find(|svc| svc.rtype == RType::LightLevel)
+        })
+        .and_then(|svc| res.get::<LightLevel>(svc).ok())
+        .and_then(|ll| ll.light["light_level"].as_u64());
+
+    level.is_none_or(|level| level < u64::from(max))
+}
+
+/// The action to perform, depending on the time of day
+fn rule_action(rule: &MotionRule) -> &RuleAction {
+    let night = in_window(
+        Local::now().hour(),
+        rule.night_start_hour,
+        rule.night_end_hour,
+    );
+    if night {
+        rule.night.as_ref().unwrap_or(&rule.day)
+    } else {
+        &rule.day
+    }
+}
+
+/// Trigger the motion rules for the sensors updated in `block`.
+///
+/// A failing rule is logged, and does not keep the other rules from running.
+pub fn handle_event(res: &Resources, states: &mut MotionStates, block: &EventBlock) {
+    let Event::Update(upd) = &block.event else {
+        return;
+    };
+
+    for obj in upd.data.iter().filter(|obj| obj.rtype == RType::Motion) {
+        let Ok(motion) = res.get_id::<Motion>(obj.id) else {
+            continue;
+        };
+        let Some(detected) = motion.motion["motion"].as_bool() else {
+            continue;
+        };
+
+        let rules = res
+            .motion_rules()
+            .iter()
+            .filter(|(_, rule)| rule.enabled && rule.sensor.rid == obj.id);
+
+        for (id, rule) in rules {
+            let state = states.entry(*id).or_default();
+            let timeout = Duration::from_secs(u64::from(rule.timeout));
+            let room_on = room_on(res, &rule.room);
+
+            if state.motion(
+                detected,
+                room_on,
+                is_dark(res, rule),
+                timeout,
+                Instant::now(),
+            ) {
+                log::info!("Motion rule {:?} triggered", rule.name);
+                if let Err(err) = run_action(res, &rule.room, rule_action(rule)) {
+                    log::warn!("Motion rule {:?} failed: {err}", rule.name);
+                }
+            }
+        }
+    }
+}
+
+pub fn expire_rules(res: &Resources, states: &mut MotionStates) {
+    let rules = res.motion_rules();
+    states.retain(|id, _| rules.contains_key(id));
+
+    for (id, state) in states.iter_mut() {
+        let rule = &rules[id];
+        if state.expire(room_on(res, &rule.room), Instant::now()) {
+            log::info!("Motion rule {:?} timed out, turning off lights", rule.name);
+            if let Err(err) = run_action(res, &rule.room, &RuleAction::Off) {
+                log::warn!(
+                    "Motion rule {:?} failed to turn off lights: {err}",
+                    rule.name
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use crate::server::motion::MotionState;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn triggers_when_dark_and_off() {
+        let mut state = MotionState::default();
+        let now = Instant::now();
+
+        assert!(state.motion(true, false, true, TIMEOUT, now));
+        // continued motion does not trigger again
+        assert!(!state.motion(true, true, true, TIMEOUT, now));
+    }
+
+    #[test]
+    fn ignores_lit_room_and_daylight() {
+        let now = Instant::now();
+
+        assert!(!MotionState::default().motion(true, true, true, TIMEOUT, now));
+        assert!(!MotionState::default().motion(true, false, false, TIMEOUT, now));
+    }
+
+    #[test]
+    fn turns_off_after_timeout() {
+        let mut state = MotionState::default();
+        let now = Instant::now();
+
+        state.motion(true, false, true, TIMEOUT, now);
+        assert!(!state.expire(true, now));
+
+        state.motion(false, true, true, TIMEOUT, now);
+        assert!(!state.expire(true, now + TIMEOUT / 2));
+        assert!(state.expire(true, now + TIMEOUT));
+
+        // once expired, it triggers again on the next motion
+        assert!(state.motion(true, false, true, TIMEOUT, now + TIMEOUT));
+    }
+
+    #[test]
+    fn motion_cancels_timeout() {
+        let mut state = MotionState::default();
+        let now = Instant::now();
+
+        state.motion(true, false, true, TIMEOUT, now);
+        state.motion(false, true, true, TIMEOUT, now);
+        state.motion(true, true, true, TIMEOUT, now + TIMEOUT / 2);
+
+        assert!(!state.expire(true, now + TIMEOUT * 2));
+    }
+
+    #[test]
+    fn manual_off_resets() {
+        let mut state = MotionState::default();
+        let now = Instant::now();
+
+        state.motion(true, false, true, TIMEOUT, now);
+        state.motion(false, true, true, TIMEOUT, now);
+
+        assert!(!state.expire(false, now + TIMEOUT));
+        assert!(state.motion(true, false, true, TIMEOUT, now + TIMEOUT));
+    }
+
+    #[test]
+    fn untriggered_never_expires() {
+        let mut state = MotionState::default();
+        let now = Instant::now();
+
+        state.motion(false, true, true, TIMEOUT, now);
+        assert!(!state.expire(true, now + TIMEOUT * 2));
+    }
+}
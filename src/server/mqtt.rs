@@ -0,0 +1,484 @@
+//! Publish bifrost controls (link button, sync, room toggles) to an mqtt
+//! broker, using Home Assistant mqtt discovery.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event as MqttEvent, Incoming, LastWill, MqttOptions, QoS};
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use bifrost_api::backend::{BackendRequest, BifrostActivity, HassRequest};
+use bifrost_api::config::MqttConfig;
+use hue::api::{GroupedLightUpdate, On, RType, Room};
+use hue::event::Event;
+
+use crate::error::ApiResult;
+use crate::model::hass::HassUiState;
+use crate::model::timeline::TimelineCategory;
+use crate::resource::Resources;
+use crate::server::linkbutton::LinkButton;
+use crate::server::rules::room_on;
+
+/// Size of the outgoing message queue
+const QUEUE_SIZE: usize = 256;
+
+/// Time to wait before reconnecting, after losing the connection
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Minimum time between refreshes of the published room states, so bursts of
+/// hue events result in a single update
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A command received from Home Assistant
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    LinkButton,
+    Sync,
+    Room(Uuid, bool),
+}
+
+/// Parse a message on one of the command topics
+fn parse_command(base: &str, topic: &str, payload: &[u8]) -> Option<Command> {
+    let path = topic.strip_prefix(base)?.strip_prefix('/')?;
+
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        ["linkbutton", "press"] => Some(Command::LinkButton),
+        ["sync", "press"] => Some(Command::Sync),
+        ["room", id, "set"] => {
+            let id = id.parse().ok()?;
+            match payload {
+                b"ON" => Some(Command::Room(id, true)),
+                b"OFF" => Some(Command::Room(id, false)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parse a (retained) room discovery topic, as published by an earlier run
+fn parse_discovery(config: &MqttConfig, topic: &str) -> Option<Uuid> {
+    let object_id = topic
+        .strip_prefix(&config.discovery_prefix)?
+        .strip_prefix("/switch/")?
+        .strip_prefix(&config.client_id)?
+        .strip_prefix("/room_")?
+        .strip_suffix("/config")?;
+
+    object_id.parse().ok()
+}
+
+/// Empty (retained) messages, deleting the discovery and state topics of a
+/// removed room
+fn removal_messages(config: &MqttConfig, id: &Uuid) -> [(String, String); 2] {
+    [
+        (
+            format!(
+                "{}/switch/{}/room_{}/config",
+                config.discovery_prefix,
+                config.client_id,
+                id.simple()
+            ),
+            String::new(),
+        ),
+        (
+            format!("{}/room/{id}/state", config.base_topic),
+            String::new(),
+        ),
+    ]
+}
+
+/// The (retained) discovery messages announcing the bifrost controls, by
+/// topic. Removed rooms get empty messages, which delete the entity.
+fn discovery_messages(
+    config: &MqttConfig,
+    rooms: &BTreeMap<Uuid, String>,
+    removed: &[Uuid],
+) -> Vec<(String, String)> {
+    let base = &config.base_topic;
+    let prefix = &config.discovery_prefix;
+
+    let device = json!({
+        "identifiers": [config.client_id],
+        "name": "Bifrost",
+        "manufacturer": "Bifrost",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let availability = format!("{base}/status");
+
+    let entity = |component: &str, object_id: String, fields: Value| {
+        let mut msg = json!({
+            "unique_id": format!("{}_{object_id}", config.client_id),
+            "object_id": format!("bifrost_{object_id}"),
+            "availability_topic": availability,
+            "device": device,
+        });
+        if let (Some(msg), Value::Object(fields)) = (msg.as_object_mut(), fields) {
+            msg.extend(fields);
+        }
+        (
+            format!(
+                "{prefix}/{component}/{}/{object_id}/config",
+                config.client_id
+            ),
+            msg.to_string(),
+        )
+    };
+
+    let mut msgs = vec![
+        entity(
+            "button",
+            "linkbutton".to_string(),
+            json!({
+                "name": "Link button",
+                "icon": "mdi:gesture-tap-button",
+                "command_topic": format!("{base}/linkbutton/press"),
+            }),
+        ),
+        entity(
+            "button",
+            "sync".to_string(),
+            json!({
+                "name": "Sync",
+                "icon": "mdi:sync",
+                "command_topic": format!("{base}/sync/press"),
+            }),
+        ),
+    ];
+
+    for (id, name) in rooms {
+        msgs.push(entity(
+            "switch",
+            format!("room_{}", id.simple()),
+            json!({
+                "name": name,
+                "icon": "mdi:lightbulb-group",
+                "state_topic": format!("{base}/room/{id}/state"),
+                "command_topic": format!("{base}/room/{id}/set"),
+            }),
+        ));
+    }
+
+    for id in removed {
+        msgs.extend(removal_messages(config, id));
+    }
+
+    msgs
+}
+
+fn rooms(res: &Resources) -> BTreeMap<Uuid, String> {
+    res.get_resource_ids_by_type(RType::Room)
+        .into_iter()
+        .filter_map(|id| Some((id, res.get_id::<Room>(id).ok()?.metadata.name.clone())))
+        .collect()
+}
+
+struct Publisher {
+    config: MqttConfig,
+    client: AsyncClient,
+    res: Arc<Mutex<Resources>>,
+    linkbutton: LinkButton,
+    ui: Arc<Mutex<HassUiState>>,
+    rooms: BTreeMap<Uuid, String>,
+    states: BTreeMap<Uuid, bool>,
+}
+
+impl Publisher {
+    fn publish(&self, topic: String, payload: impl Into<Vec<u8>>) {
+        if let Err(err) = self
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, true, payload)
+        {
+            log::warn!("Failed to queue mqtt message: {err}");
+        }
+    }
+
+    fn subscribe(&self, topic: String) {
+        if let Err(err) = self.client.try_subscribe(topic, QoS::AtLeastOnce) {
+            log::warn!("Failed to subscribe to mqtt topic: {err}");
+        }
+    }
+
+    /// (Re-)announce everything, after (re)connecting
+    async fn connected(&mut self) {
+        let base = &self.config.base_topic;
+        log::info!("Connected to mqtt broker {}", self.config.host);
+
+        self.subscribe(format!("{base}/+/press"));
+        self.subscribe(format!("{base}/room/+/set"));
+        self.subscribe(format!(
+            "{}/switch/{}/+/config",
+            self.config.discovery_prefix, self.config.client_id
+        ));
+        self.publish(format!("{base}/status"), "online");
+
+        self.rooms.clear();
+        self.states.clear();
+        self.refresh().await;
+    }
+
+    /// Publish discovery for added (and removed) rooms, and changed room
+    /// states
+    async fn refresh(&mut self) {
+        let lock = self.res.lock().await;
+        let rooms = rooms(&lock);
+
+        let states: BTreeMap<Uuid, bool> = rooms
+            .keys()
+            .map(|id| (*id, room_on(&lock, &RType::Room.link_to(*id))))
+            .collect();
+        drop(lock);
+
+        if rooms != self.rooms {
+            let removed: Vec<Uuid> = self
+                .rooms
+                .keys()
+                .filter(|id| !rooms.contains_key(id))
+                .copied()
+                .collect();
+
+            for (topic, msg) in discovery_messages(&self.config, &rooms, &removed) {
+                self.publish(topic, msg);
+            }
+            self.rooms = rooms;
+        }
+
+        for (id, on) in &states {
+            if self.states.get(id) != Some(on) {
+                let topic = format!("{}/room/{id}/state", self.config.base_topic);
+                self.publish(topic, if *on { "ON" } else { "OFF" });
+            }
+        }
+        self.states = states;
+    }
+
+    /// Delete a room announced by an earlier run, which no longer exists
+    fn discovered(&self, id: Uuid) {
+        if !self.rooms.contains_key(&id) {
+            log::debug!("Removing stale mqtt discovery for room {id}");
+            for (topic, msg) in removal_messages(&self.config, &id) {
+                self.publish(topic, msg);
+            }
+        }
+    }
+
+    async fn command(&self, cmd: Command) -> ApiResult<()> {
+        log::debug!("Mqtt command: {cmd:?}");
+
+        match cmd {
+            Command::LinkButton => {
+                self.linkbutton.press(LinkButton::PRESS_DURATION).await;
+                self.res
+                    .lock()
+                    .await
+                    .forward_activity(BifrostActivity::LinkButton {
+                        source: "mqtt".to_string(),
+                    });
+                self.ui.lock().await.push_event(
+                    TimelineCategory::Pairing,
+                    format!(
+                        "Virtual bridge button pressed via mqtt ({}s active)",
+                        LinkButton::PRESS_DURATION.as_secs()
+                    ),
+                );
+            }
+            Command::Sync => {
+                self.res.lock().await.hass_request(HassRequest::Sync)?;
+            }
+            Command::Room(id, on) => {
+                let lock = self.res.lock().await;
+                let room = lock.get_id::<Room>(id)?;
+                if let Some(glight) = room.grouped_light_service() {
+                    let upd = GroupedLightUpdate::new().with_on(Some(On::new(on)));
+                    lock.backend_request(BackendRequest::GroupedLightUpdate(*glight, upd))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Connect to the configured mqtt broker, and keep the bifrost controls
+/// published there (reconnecting as needed).
+pub async fn mqtt_publisher(
+    config: MqttConfig,
+    res: Arc<Mutex<Resources>>,
+    linkbutton: LinkButton,
+    ui: Arc<Mutex<HassUiState>>,
+) -> ApiResult<()> {
+    let mut opts = MqttOptions::new(&config.client_id, &config.host, config.port);
+    opts.set_keep_alive(Duration::from_secs(30));
+    opts.set_last_will(LastWill::new(
+        format!("{}/status", config.base_topic),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+
+    if let Some(username) = &config.username {
+        let password = config
+            .password_env
+            .as_ref()
+            .and_then(|env| std::env::var(env).ok())
+            .unwrap_or_default();
+        opts.set_credentials(username, password);
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(opts, QUEUE_SIZE);
+    let mut events = res.lock().await.hue_event_stream().subscribe();
+
+    let mut publisher = Publisher {
+        config,
+        client,
+        res,
+        linkbutton,
+        ui,
+        rooms: BTreeMap::new(),
+        states: BTreeMap::new(),
+    };
+
+    let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+    refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut pending = false;
+
+    loop {
+        tokio::select! {
+            ev = eventloop.poll() => match ev {
+                Ok(MqttEvent::Incoming(Incoming::ConnAck(_))) => publisher.connected().await,
+                Ok(MqttEvent::Incoming(Incoming::Publish(msg))) => {
+                    let base = &publisher.config.base_topic;
+                    let discovered = parse_discovery(&publisher.config, &msg.topic)
+                        .filter(|_| !msg.payload.is_empty());
+                    if let Some(id) = discovered {
+                        publisher.discovered(id);
+                    } else if let Some(cmd) = parse_command(base, &msg.topic, &msg.payload) {
+                        if let Err(err) = publisher.command(cmd).await {
+                            log::warn!("Failed to handle mqtt command on {}: {err}", msg.topic);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log::warn!("Mqtt connection to {} failed: {err}", publisher.config.host);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            },
+            ev = events.recv() => match ev {
+                Ok(record) => pending |= !matches!(record.block.event, Event::Error(_)),
+                Err(RecvError::Lagged(_)) => pending = true,
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = refresh.tick(), if pending => {
+                pending = false;
+                publisher.refresh().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use bifrost_api::config::MqttConfig;
+
+    use crate::server::mqtt::{Command, discovery_messages, parse_command, parse_discovery};
+
+    fn config() -> MqttConfig {
+        serde_json::from_value(serde_json::json!({"host": "localhost"})).unwrap()
+    }
+
+    #[test]
+    fn parse_commands() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(
+            parse_command("bifrost", "bifrost/linkbutton/press", b""),
+            Some(Command::LinkButton)
+        );
+        assert_eq!(
+            parse_command("bifrost", "bifrost/sync/press", b"PRESS"),
+            Some(Command::Sync)
+        );
+        assert_eq!(
+            parse_command("bifrost", &format!("bifrost/room/{id}/set"), b"ON"),
+            Some(Command::Room(id, true))
+        );
+        assert_eq!(
+            parse_command("bifrost", &format!("bifrost/room/{id}/set"), b"OFF"),
+            Some(Command::Room(id, false))
+        );
+    }
+
+    #[test]
+    fn parse_invalid_commands() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(parse_command("bifrost", "other/sync/press", b""), None);
+        assert_eq!(parse_command("bifrost", "bifrostx/sync/press", b""), None);
+        assert_eq!(parse_command("bifrost", "bifrost/room/1/set", b"ON"), None);
+        assert_eq!(
+            parse_command("bifrost", &format!("bifrost/room/{id}/set"), b"TOGGLE"),
+            None
+        );
+    }
+
+    #[test]
+    fn discovery_for_rooms() {
+        let config = config();
+        let kitchen = Uuid::new_v4();
+        let removed = Uuid::new_v4();
+        let rooms = BTreeMap::from([(kitchen, "Kitchen".to_string())]);
+
+        let msgs = discovery_messages(&config, &rooms, &[removed]);
+        assert_eq!(msgs.len(), 5);
+
+        let (topic, msg) = &msgs[2];
+        assert_eq!(
+            topic,
+            &format!(
+                "homeassistant/switch/bifrost/room_{}/config",
+                kitchen.simple()
+            )
+        );
+        let msg: Value = serde_json::from_str(msg).unwrap();
+        assert_eq!(msg["name"], "Kitchen");
+        assert_eq!(msg["command_topic"], format!("bifrost/room/{kitchen}/set"));
+        assert_eq!(msg["availability_topic"], "bifrost/status");
+
+        // removed rooms are deleted with empty messages
+        assert_eq!(parse_discovery(&config, &msgs[3].0), Some(removed));
+        assert!(msgs[3].1.is_empty());
+        assert_eq!(msgs[4].0, format!("bifrost/room/{removed}/state"));
+        assert!(msgs[4].1.is_empty());
+    }
+
+    #[test]
+    fn parse_discovery_topics() {
+        let config = config();
+        let id = Uuid::new_v4();
+        let topic = |object_id: &str| format!("homeassistant/switch/bifrost/{object_id}/config");
+
+        assert_eq!(
+            parse_discovery(&config, &topic(&format!("room_{}", id.simple()))),
+            Some(id)
+        );
+        assert_eq!(parse_discovery(&config, &topic("linkbutton")), None);
+        assert_eq!(parse_discovery(&config, &topic("room_1")), None);
+        assert_eq!(
+            parse_discovery(
+                &config,
+                &format!("homeassistant/switch/other/room_{}/config", id.simple())
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Local, Timelike};
+use tokio::sync::Mutex;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use bifrost_api::backend::BackendRequest;
+use bifrost_api::presence::PresenceStatus;
+use hue::api::{GroupedLight, GroupedLightUpdate, On, RType, ResourceLink, Room};
+
+use crate::config::PresenceConfig;
+use crate::error::ApiResult;
+use crate::resource::Resources;
+
+#[derive(Debug, Default)]
+struct PresenceState {
+    enabled: bool,
+    active: bool,
+    lit: BTreeSet<ResourceLink>,
+}
+
+/// Shared handle to the presence simulation ("away mode")
+#[derive(Clone, Debug)]
+pub struct Presence {
+    state: Arc<Mutex<PresenceState>>,
+    res: Arc<Mutex<Resources>>,
+}
+
+impl Presence {
+    /// The enabled flag is kept in the state file, so `enabled` is the
+    /// value loaded from there
+    #[must_use]
+    pub fn new(res: Arc<Mutex<Resources>>, enabled: bool) -> Self {
+        let state = PresenceState {
+            enabled,
+            ..PresenceState::default()
+        };
+
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            res,
+        }
+    }
+
+    pub async fn set_enabled(&self, enabled: bool) {
+        let mut lock = self.state.lock().await;
+        if lock.enabled != enabled {
+            log::info!(
+                "Presence simulation {}",
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+        lock.enabled = enabled;
+        self.res.lock().await.set_presence_enabled(enabled);
+    }
+
+    pub async fn status(&self) -> PresenceStatus {
+        let lock = self.state.lock().await;
+        PresenceStatus {
+            enabled: lock.enabled,
+            active: lock.active,
+            lit: lock.lit.iter().copied().collect(),
+        }
+    }
+}
+
+/// Is `hour` within the simulation hours? (which may wrap past midnight)
+#[must_use]
+pub const fn in_window(hour: u32, start: u32, end: u32) -> bool {
+    if start <= end {
+        start <= hour && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+/// Random time until the next toggle
+fn next_interval(config: &PresenceConfig) -> Duration {
+    let min = config.min_interval.min(config.max_interval);
+    let max = config.max_interval.max(config.min_interval);
+    Duration::from_secs(u64::from(rand::random_range(min..=max)) * 60)
+}
+
+/// Grouped lights of the configured rooms (that currently exist)
+fn room_lights(res: &Resources, config: &PresenceConfig) -> Vec<ResourceLink> {
+    res.get_resource_ids_by_type(RType::Room)
+        .into_iter()
+        .filter_map(|rid| res.get_id::<Room>(rid).ok())
+        .filter(|room| config.rooms.contains(&room.metadata.name))
+        .filter_map(Room::grouped_light_service)
+        .copied()
+        .collect()
+}
+
+fn set_on(res: &Resources, glight: ResourceLink, on: bool) -> ApiResult<()> {
+    let upd = GroupedLightUpdate::new().with_on(Some(On::new(on)));
+    res.backend_request(BackendRequest::GroupedLightUpdate(glight, upd))?;
+    Ok(())
+}
+
+/// Rooms the simulation may toggle: the ones it turned on itself, and the
+/// ones that are off. Rooms turned on by someone else are left alone.
+fn candidates(
+    res: &Resources,
+    config: &PresenceConfig,
+    lit: &BTreeSet<ResourceLink>,
+) -> Vec<ResourceLink> {
+    room_lights(res, config)
+        .into_iter()
+        .filter(|glight| {
+            lit.contains(glight)
+                || res
+                    .get::<GroupedLight>(glight)
+                    .is_ok_and(|gl| !gl.on.is_some_and(|on| on.on))
+        })
+        .collect()
+}
+
+/// Toggle a random one of the configured rooms
+fn toggle_random_room(
+    res: &Resources,
+    config: &PresenceConfig,
+    lit: &mut BTreeSet<ResourceLink>,
+) -> ApiResult<()> {
+    let lights = candidates(res, config, lit);
+    if lights.is_empty() {
+        log::debug!(
+            "Presence simulation: none of the rooms {:?} can be toggled",
+            config.rooms
+        );
+        return Ok(());
+    }
+
+    let glight = lights[rand::random_range(0..lights.len())];
+    let on = !lit.contains(&glight);
+
+    log::debug!(
+        "Presence simulation: turning {} {glight:?}",
+        if on { "on" } else { "off" }
+    );
+    set_on(res, glight, on)?;
+
+    if on {
+        lit.insert(glight);
+    } else {
+        lit.remove(&glight);
+    }
+
+    Ok(())
+}
+
+/// Run the presence simulation.
+///
+/// While enabled, and within the configured hours, a random configured room
+/// is toggled every now and then. Outside of that, the lights turned on by
+/// the simulation are turned off again.
+pub async fn presence_simulation(
+    presence: Presence,
+    res: Arc<Mutex<Resources>>,
+    config: PresenceConfig,
+) -> ApiResult<()> {
+    const INTERVAL: Duration = Duration::from_secs(60);
+
+    if config.rooms.is_empty() {
+        log::info!("Presence simulation has no rooms configured");
+    } else {
+        log::info!(
+            "Presence simulation ready for rooms {:?} ({}:00 - {}:00)",
+            config.rooms,
+            config.start_hour,
+            config.end_hour
+        );
+    }
+
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut next_toggle = Instant::now();
+
+    loop {
+        interval.tick().await;
+
+        let mut state = presence.state.lock().await;
+        let hour = Local::now().hour();
+        state.active = state.enabled && in_window(hour, config.start_hour, config.end_hour);
+
+        let res = res.lock().await;
+
+        if !state.active {
+            for glight in std::mem::take(&mut state.lit) {
+                log::debug!("Presence simulation: turning off {glight:?}");
+                if let Err(err) = set_on(&res, glight, false) {
+                    log::warn!("Presence simulation: failed to turn off {glight:?}: {err}");
+                }
+            }
+            continue;
+        }
+
+        if Instant::now() < next_toggle {
+            continue;
+        }
+
+        if let Err(err) = toggle_random_room(&res, &config, &mut state.lit) {
+            log::warn!("Presence simulation: failed to toggle room: {err}");
+        }
+        next_toggle = Instant::now() + next_interval(&config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bifrost_api::config::PresenceConfig;
+    use hue::api::{
+        GroupedLight, On, RType, Resource, ResourceLink, Room, RoomArchetype, RoomMetadata,
+    };
+    use hue::version::SwVersion;
+    use maplit::btreeset;
+
+    use crate::model::state::State;
+    use crate::resource::Resources;
+    use crate::server::presence::{candidates, in_window, next_interval};
+
+    fn add_room(res: &mut Resources, name: &str, on: bool) -> ResourceLink {
+        let room = RType::Room.deterministic(name);
+        let glight = RType::GroupedLight.deterministic(name);
+
+        res.add(
+            &room,
+            Resource::Room(Room {
+                children: BTreeSet::new(),
+                metadata: RoomMetadata::new(RoomArchetype::LivingRoom, name),
+                services: btreeset![glight],
+            }),
+        )
+        .unwrap();
+
+        let mut grouped = GroupedLight::new(room);
+        grouped.on = Some(On::new(on));
+        res.add(&glight, Resource::GroupedLight(grouped)).unwrap();
+
+        glight
+    }
+
+    #[test]
+    fn window_same_day() {
+        assert!(!in_window(17, 18, 23));
+        assert!(in_window(18, 18, 23));
+        assert!(in_window(22, 18, 23));
+        assert!(!in_window(23, 18, 23));
+    }
+
+    #[test]
+    fn window_past_midnight() {
+        assert!(in_window(22, 20, 2));
+        assert!(in_window(0, 20, 2));
+        assert!(in_window(1, 20, 2));
+        assert!(!in_window(2, 20, 2));
+        assert!(!in_window(12, 20, 2));
+    }
+
+    #[test]
+    fn window_empty() {
+        assert!(!in_window(18, 18, 18));
+    }
+
+    #[test]
+    fn interval_in_range() {
+        let config = PresenceConfig {
+            min_interval: 5,
+            max_interval: 10,
+            ..PresenceConfig::default()
+        };
+
+        for _ in 0..100 {
+            let mins = next_interval(&config).as_secs() / 60;
+            assert!((5..=10).contains(&mins));
+        }
+    }
+
+    #[test]
+    fn interval_swapped_bounds() {
+        let config = PresenceConfig {
+            min_interval: 10,
+            max_interval: 5,
+            ..PresenceConfig::default()
+        };
+
+        let mins = next_interval(&config).as_secs() / 60;
+        assert!((5..=10).contains(&mins));
+    }
+
+    #[test]
+    fn leaves_rooms_turned_on_by_others() {
+        let mut res = Resources::new(SwVersion::default(), State::new());
+        let off = add_room(&mut res, "Kitchen", false);
+        let user = add_room(&mut res, "Study", true);
+        let lit = add_room(&mut res, "Hall", true);
+
+        let config = PresenceConfig {
+            rooms: vec!["Kitchen".into(), "Study".into(), "Hall".into()],
+            ..PresenceConfig::default()
+        };
+
+        let found = candidates(&res, &config, &btreeset![lit]);
+
+        assert!(found.contains(&off));
+        assert!(found.contains(&lit));
+        assert!(!found.contains(&user));
+    }
+}
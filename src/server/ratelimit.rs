@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// Recent attempts per client address
+#[derive(Debug, Default)]
+struct Attempts {
+    clients: HashMap<IpAddr, Vec<Instant>>,
+}
+
+impl Attempts {
+    fn allow(&mut self, ip: IpAddr, now: Instant) -> bool {
+        // forget clients without recent attempts, to keep the map small
+        self.clients.retain(|_, times| {
+            times.retain(|time| now.duration_since(*time) < PairingLimit::WINDOW);
+            !times.is_empty()
+        });
+
+        let times = self.clients.entry(ip).or_default();
+        if times.len() >= PairingLimit::MAX_ATTEMPTS {
+            return false;
+        }
+        times.push(now);
+        true
+    }
+}
+
+/// Rate limit for pairing attempts.
+///
+/// Clients are told apart by their real address (see
+/// [`crate::server::forwarded`]), so clients behind a reverse proxy are not
+/// limited as a single client.
+#[derive(Clone, Debug, Default)]
+pub struct PairingLimit {
+    attempts: Arc<Mutex<Attempts>>,
+}
+
+impl PairingLimit {
+    /// Attempts allowed per client within [`Self::WINDOW`]. Apps poll for
+    /// pairing about once a second while waiting for the link button, so
+    /// this is well above that.
+    pub const MAX_ATTEMPTS: usize = 300;
+
+    pub const WINDOW: Duration = Duration::from_secs(60);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a pairing attempt from `ip`. Returns false if the client has
+    /// made too many recent attempts.
+    pub async fn allow(&self, ip: IpAddr) -> bool {
+        self.attempts.lock().await.allow(ip, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    use crate::server::ratelimit::{Attempts, PairingLimit};
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn limits_attempts_per_client() {
+        let mut attempts = Attempts::default();
+        let now = Instant::now();
+
+        for _ in 0..PairingLimit::MAX_ATTEMPTS {
+            assert!(attempts.allow(ip("10.0.0.1"), now));
+        }
+        assert!(!attempts.allow(ip("10.0.0.1"), now));
+
+        // other clients are not affected
+        assert!(attempts.allow(ip("10.0.0.2"), now));
+    }
+
+    #[test]
+    fn allows_attempts_after_window() {
+        let mut attempts = Attempts::default();
+        let now = Instant::now();
+
+        for _ in 0..PairingLimit::MAX_ATTEMPTS {
+            attempts.allow(ip("10.0.0.1"), now);
+        }
+
+        let later = now + PairingLimit::WINDOW + Duration::from_secs(1);
+        assert!(attempts.allow(ip("10.0.0.1"), later));
+    }
+}
@@ -0,0 +1,480 @@
+use std::sync::Arc;
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use url::Url;
+
+use bifrost_api::updater::ReleaseStatus;
+
+use crate::config::SelfUpdateConfig;
+use crate::error::{ApiError, ApiResult};
+use crate::server::proxy;
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Files marking a container, which is updated by pulling a new image
+const CONTAINER_MARKERS: &[&str] = &["/.dockerenv", "/run/.containerenv"];
+
+/// A release, as returned by the GitHub releases api
+#[derive(Clone, Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: Url,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: Url,
+}
+
+/// Numeric parts of a version like "v1.2.3", without trailing zeros (so
+/// "1.2" and "1.2.0" are equal). Pre-release and build suffixes are ignored.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    while parts.last() == Some(&0) {
+        parts.pop();
+    }
+    Some(parts)
+}
+
+/// Is version `latest` newer than version `current`?
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// The release binary for the platform we are running on, named
+/// "bifrost-{arch}-{os}"
+fn platform_asset<'a>(
+    assets: &'a [ReleaseAsset],
+    os: &str,
+    arch: &str,
+) -> Option<&'a ReleaseAsset> {
+    let name = format!("bifrost-{arch}-{os}");
+    assets
+        .iter()
+        .find(|asset| asset.name.eq_ignore_ascii_case(&name))
+}
+
+/// The checksum file published with `asset`, named "{asset}.sha256"
+fn checksum_asset<'a>(
+    assets: &'a [ReleaseAsset],
+    asset: &ReleaseAsset,
+) -> Option<&'a ReleaseAsset> {
+    let name = format!("{}.sha256", asset.name);
+    assets.iter().find(|other| other.name == name)
+}
+
+/// Does `data` match `checksum`, in the format of sha256sum ("{hex digest}
+/// {file name}")?
+fn verify_checksum(data: &[u8], checksum: &str) -> ApiResult<bool> {
+    let expected = checksum.split_whitespace().next().unwrap_or_default();
+    Ok(hex::decode(expected)? == Sha256::digest(data).as_slice())
+}
+
+fn in_container() -> bool {
+    CONTAINER_MARKERS
+        .iter()
+        .any(|marker| Utf8Path::new(marker).exists())
+}
+
+#[derive(Debug)]
+pub struct ReleaseChecker {
+    config: Option<SelfUpdateConfig>,
+    proxy: Option<Url>,
+    latest: Option<Release>,
+    last_check: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    staged: Option<Utf8PathBuf>,
+    /// Held for the duration of a check, so concurrent checks do not
+    /// download into the same files
+    checking: Arc<Mutex<()>>,
+}
+
+impl ReleaseChecker {
+    #[must_use]
+    pub fn new(config: Option<SelfUpdateConfig>, proxy: Option<Url>) -> Self {
+        Self {
+            config,
+            proxy,
+            latest: None,
+            last_check: None,
+            last_error: None,
+            staged: None,
+            checking: Arc::new(Mutex::new(())),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn fetch(config: &SelfUpdateConfig, proxy: Option<&Url>) -> ApiResult<Release> {
+        let url = format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            config.repository
+        );
+        let client = proxy::client_builder(proxy)?
+            .user_agent(format!("bifrost/{CURRENT_VERSION}"))
+            .build()?;
+        let release = client
+            .get(url)
+            .header("accept", "application/vnd.github+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(release)
+    }
+
+    /// Where the release binary `asset` of release `tag` is staged. Each
+    /// release gets its own directory, since asset names are the same in
+    /// every release.
+    fn staged_path(staging_dir: &Utf8Path, tag: &str, asset: &str) -> ApiResult<Utf8PathBuf> {
+        let mut components = Utf8Path::new(tag).components();
+        match (components.next(), components.next()) {
+            (Some(Utf8Component::Normal(_)), None) => Ok(staging_dir.join(tag).join(asset)),
+            _ => Err(ApiError::ReleaseTagInvalid(tag.to_string())),
+        }
+    }
+
+    /// Check `data` against the checksum `expected`, and write it to `path`
+    async fn write_staged(path: &Utf8Path, data: &[u8], expected: &str) -> ApiResult<()> {
+        let name = path.file_name().unwrap_or_default();
+        if !verify_checksum(data, expected)? {
+            return Err(ApiError::ReleaseChecksumMismatch(name.to_string()));
+        }
+
+        // write to a temporary file first, so a partial download is never
+        // mistaken for a staged update
+        let partial = path.with_extension("part");
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&partial, data).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::Permissions::from_mode(0o755);
+            tokio::fs::set_permissions(&partial, mode).await?;
+        }
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    /// Download the release binary for this platform into the staging
+    /// directory (once per release), after checking it against the checksum
+    /// published with it
+    async fn stage(
+        config: &SelfUpdateConfig,
+        proxy: Option<&Url>,
+        tag: &str,
+        asset: &ReleaseAsset,
+        checksum: Option<&ReleaseAsset>,
+    ) -> ApiResult<Utf8PathBuf> {
+        let path = Self::staged_path(&config.staging_dir, tag, &asset.name)?;
+        if path.is_file() {
+            return Ok(path);
+        }
+
+        let Some(checksum) = checksum else {
+            return Err(ApiError::ReleaseChecksumMissing(asset.name.clone()));
+        };
+
+        log::info!("Downloading Bifrost update {} ({tag})..", asset.name);
+        let client = proxy::client_builder(proxy)?
+            .user_agent(format!("bifrost/{CURRENT_VERSION}"))
+            .build()?;
+        let expected = client
+            .get(checksum.browser_download_url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let data = client
+            .get(asset.browser_download_url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        Self::write_staged(&path, &data, &expected).await?;
+        log::info!("Bifrost update staged as {path}");
+
+        Ok(path)
+    }
+
+    /// Look for a new release, and stage it if configured. Returns the
+    /// release, and the path of the staged binary (if any)
+    async fn try_check(
+        config: &SelfUpdateConfig,
+        proxy: Option<&Url>,
+    ) -> ApiResult<(Release, Option<Utf8PathBuf>)> {
+        let release = Self::fetch(config, proxy).await?;
+        let mut staged = None;
+
+        if is_newer(&release.tag_name, CURRENT_VERSION) {
+            log::info!(
+                "New Bifrost release available: {} ({})",
+                release.tag_name,
+                release.html_url
+            );
+            let asset = platform_asset(
+                &release.assets,
+                std::env::consts::OS,
+                std::env::consts::ARCH,
+            );
+            if let Some(asset) = asset.filter(|_| config.download && !in_container()) {
+                let checksum = checksum_asset(&release.assets, asset);
+                staged =
+                    Some(Self::stage(config, proxy, &release.tag_name, asset, checksum).await?);
+            }
+        }
+
+        Ok((release, staged))
+    }
+
+    /// Look for a new release (and stage it, if configured)
+    ///
+    /// The checker is only locked to read its configuration and to store the
+    /// result, so the status stays available while a release is downloaded.
+    /// Concurrent checks run one after the other.
+    pub async fn check(checker: &Mutex<Self>) -> ReleaseStatus {
+        let (config, proxy, checking) = {
+            let lock = checker.lock().await;
+            (
+                lock.config.clone(),
+                lock.proxy.clone(),
+                lock.checking.clone(),
+            )
+        };
+        let _checking = checking.lock().await;
+
+        let Some(config) = config else {
+            return checker.lock().await.status();
+        };
+
+        let res = Self::try_check(&config, proxy.as_ref()).await;
+
+        let mut lock = checker.lock().await;
+        lock.last_error = match res {
+            Ok((release, staged)) => {
+                lock.latest = Some(release);
+                // a binary staged for an older release is no longer relevant
+                lock.staged = staged;
+                None
+            }
+            Err(err) => {
+                log::warn!("Failed to check for Bifrost releases: {err}");
+                Some(err.to_string())
+            }
+        };
+        lock.last_check = Some(Utc::now());
+
+        lock.status()
+    }
+
+    #[must_use]
+    pub fn status(&self) -> ReleaseStatus {
+        let latest_version = self.latest.as_ref().map(|rel| rel.tag_name.clone());
+
+        ReleaseStatus {
+            enabled: self.is_enabled(),
+            current_version: CURRENT_VERSION.to_string(),
+            update_available: latest_version
+                .as_deref()
+                .is_some_and(|latest| is_newer(latest, CURRENT_VERSION)),
+            latest_version,
+            release_url: self.latest.as_ref().map(|rel| rel.html_url.clone()),
+            published_at: self
+                .latest
+                .as_ref()
+                .and_then(|rel| rel.published_at.clone()),
+            last_check: self.last_check.map(|time| time.to_rfc3339()),
+            last_error: self.last_error.clone(),
+            staged_path: self.staged.as_ref().map(ToString::to_string),
+        }
+    }
+}
+
+pub async fn release_checker(upd: Arc<Mutex<ReleaseChecker>>) -> ApiResult<()> {
+    let hours = upd
+        .lock()
+        .await
+        .config
+        .as_ref()
+        .map_or(24, |config| config.interval_hours.get());
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(u64::from(hours) * 3600));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        ReleaseChecker::check(&upd).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use sha2::{Digest, Sha256};
+    use url::Url;
+
+    use crate::server::release::{
+        ReleaseAsset, ReleaseChecker, checksum_asset, is_newer, parse_version, platform_asset,
+        verify_checksum,
+    };
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: Url::parse(&format!("https://example.com/{name}")).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse_version("v1.2.3"), Some(vec![1, 2, 3]));
+        assert_eq!(parse_version("0.2.0"), Some(vec![0, 2]));
+        assert_eq!(parse_version("1.0.0-rc1"), Some(vec![1]));
+        assert_eq!(parse_version("nightly"), None);
+    }
+
+    #[test]
+    fn newer_versions() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.1"));
+        assert!(!is_newer("v0.1", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.2.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn asset_for_platform() {
+        let assets = [
+            asset("bifrost-x86_64-linux"),
+            asset("bifrost-aarch64-linux"),
+            asset("bifrost-aarch64-macos"),
+        ];
+
+        let found = platform_asset(&assets, "linux", "aarch64").unwrap();
+        assert_eq!(found.name, "bifrost-aarch64-linux");
+        assert!(platform_asset(&assets, "windows", "x86_64").is_none());
+    }
+
+    #[test]
+    fn asset_for_platform_is_exact() {
+        let assets = [
+            asset("bifrost-aarch64-linux.sha256"),
+            asset("bifrost-aarch64-linux-musl"),
+            asset("bifrost-aarch64-linux"),
+        ];
+
+        let found = platform_asset(&assets, "linux", "aarch64").unwrap();
+        assert_eq!(found.name, "bifrost-aarch64-linux");
+        assert!(platform_asset(&assets[..2], "linux", "aarch64").is_none());
+    }
+
+    #[test]
+    fn checksum_for_asset() {
+        let assets = [
+            asset("bifrost-aarch64-linux"),
+            asset("bifrost-aarch64-linux.sha256"),
+            asset("bifrost-x86_64-linux"),
+        ];
+
+        let found = checksum_asset(&assets, &assets[0]).unwrap();
+        assert_eq!(found.name, "bifrost-aarch64-linux.sha256");
+        assert!(checksum_asset(&assets, &assets[2]).is_none());
+    }
+
+    #[test]
+    fn verify_checksums() {
+        let digest = hex::encode(Sha256::digest(b"bifrost"));
+
+        assert!(verify_checksum(b"bifrost", &digest).unwrap());
+        assert!(
+            verify_checksum(b"bifrost", &format!("{digest}  bifrost-aarch64-linux\n")).unwrap()
+        );
+        assert!(!verify_checksum(b"bifrost2", &digest).unwrap());
+        assert!(verify_checksum(b"bifrost", "not hex").is_err());
+    }
+
+    #[tokio::test]
+    async fn stage_per_release() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("bifrost-release-{}", uuid::Uuid::new_v4()));
+        let name = "bifrost-aarch64-linux";
+
+        let old = ReleaseChecker::staged_path(&dir, "v0.2.0", name).unwrap();
+        let old_sum = hex::encode(Sha256::digest(b"bifrost 0.2.0"));
+        ReleaseChecker::write_staged(&old, b"bifrost 0.2.0", &old_sum)
+            .await
+            .unwrap();
+
+        // a later release must not find the binary staged for an earlier one
+        let new = ReleaseChecker::staged_path(&dir, "v0.3.0", name).unwrap();
+        assert_ne!(old, new);
+        assert!(!new.is_file());
+
+        let new_sum = hex::encode(Sha256::digest(b"bifrost 0.3.0"));
+        let mismatch = ReleaseChecker::write_staged(&new, b"bifrost 0.2.0", &new_sum).await;
+        assert!(mismatch.is_err());
+        assert!(!new.is_file());
+
+        ReleaseChecker::write_staged(&new, b"bifrost 0.3.0", &new_sum)
+            .await
+            .unwrap();
+
+        let old_data = std::fs::read(&old).unwrap();
+        let new_data = std::fs::read(&new).unwrap();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&new).unwrap().permissions().mode()
+        };
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        #[cfg(unix)]
+        assert_eq!(mode & 0o111, 0o111);
+
+        assert_eq!(old_data, b"bifrost 0.2.0");
+        assert_eq!(new_data, b"bifrost 0.3.0");
+    }
+
+    #[test]
+    fn staged_path_rejects_unsafe_tags() {
+        let dir = Utf8PathBuf::from("/staging");
+        let name = "bifrost-aarch64-linux";
+
+        assert_eq!(
+            ReleaseChecker::staged_path(&dir, "v0.2.0", name).unwrap(),
+            "/staging/v0.2.0/bifrost-aarch64-linux"
+        );
+        assert!(ReleaseChecker::staged_path(&dir, "..", name).is_err());
+        assert!(ReleaseChecker::staged_path(&dir, "v1/../..", name).is_err());
+        assert!(ReleaseChecker::staged_path(&dir, "/etc", name).is_err());
+        assert!(ReleaseChecker::staged_path(&dir, "", name).is_err());
+    }
+}
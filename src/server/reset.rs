@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::model::hass::HassResetScope;
+
+#[derive(Debug)]
+struct Pending {
+    token: String,
+    scope: HassResetScope,
+    expires: Instant,
+}
+
+/// The outstanding reset confirmation token (at most one at a time)
+#[derive(Debug, Default)]
+struct ResetTokens {
+    pending: Option<Pending>,
+}
+
+impl ResetTokens {
+    fn issue(&mut self, scope: HassResetScope, now: Instant) -> String {
+        let token = hex::encode(rand::random::<[u8; 16]>());
+        self.pending = Some(Pending {
+            token: token.clone(),
+            scope,
+            expires: now + ResetGuard::TOKEN_VALIDITY,
+        });
+        token
+    }
+
+    /// Consume the pending token. Any attempt uses it up, so a wrong guess
+    /// requires requesting a new token.
+    fn confirm(&mut self, token: &str, scope: HassResetScope, now: Instant) -> bool {
+        self.pending.take().is_some_and(|pending| {
+            pending.token == token && pending.scope == scope && pending.expires > now
+        })
+    }
+}
+
+/// Confirmation for bridge resets.
+///
+/// A reset must present a short-lived, single-use token, issued by a
+/// prior request for the same reset scope.
+#[derive(Clone, Debug, Default)]
+pub struct ResetGuard {
+    tokens: Arc<Mutex<ResetTokens>>,
+}
+
+impl ResetGuard {
+    /// How long an issued token can be used
+    pub const TOKEN_VALIDITY: Duration = Duration::from_secs(60);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Issue a new token for a reset of `scope` (replacing any earlier token)
+    pub async fn issue(&self, scope: HassResetScope) -> String {
+        self.tokens.lock().await.issue(scope, Instant::now())
+    }
+
+    /// Is `token` valid for a reset of `scope`? Consumes the token.
+    pub async fn confirm(&self, token: &str, scope: HassResetScope) -> bool {
+        self.tokens
+            .lock()
+            .await
+            .confirm(token, scope, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::model::hass::HassResetScope;
+    use crate::server::reset::{ResetGuard, ResetTokens};
+
+    #[test]
+    fn confirms_issued_token() {
+        let mut tokens = ResetTokens::default();
+        let now = Instant::now();
+        let token = tokens.issue(HassResetScope::Scenes, now);
+
+        assert!(tokens.confirm(&token, HassResetScope::Scenes, now));
+    }
+
+    #[test]
+    fn token_is_single_use() {
+        let mut tokens = ResetTokens::default();
+        let now = Instant::now();
+        let token = tokens.issue(HassResetScope::All, now);
+
+        assert!(tokens.confirm(&token, HassResetScope::All, now));
+        assert!(!tokens.confirm(&token, HassResetScope::All, now));
+    }
+
+    #[test]
+    fn rejects_wrong_token_or_scope() {
+        let mut tokens = ResetTokens::default();
+        let now = Instant::now();
+
+        tokens.issue(HassResetScope::All, now);
+        assert!(!tokens.confirm("guess", HassResetScope::All, now));
+
+        let token = tokens.issue(HassResetScope::Scenes, now);
+        assert!(!tokens.confirm(&token, HassResetScope::All, now));
+    }
+
+    #[test]
+    fn rejects_expired_token() {
+        let mut tokens = ResetTokens::default();
+        let now = Instant::now();
+        let token = tokens.issue(HassResetScope::All, now);
+        let later = now + ResetGuard::TOKEN_VALIDITY + Duration::from_secs(1);
+
+        assert!(!tokens.confirm(&token, HassResetScope::All, later));
+    }
+
+    #[test]
+    fn rejects_without_token() {
+        let mut tokens = ResetTokens::default();
+
+        assert!(!tokens.confirm("", HassResetScope::All, Instant::now()));
+    }
+}
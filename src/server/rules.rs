@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+
+use bifrost_api::backend::BackendRequest;
+use bifrost_api::rule::RuleAction;
+use hue::api::{
+    GroupedLight, GroupedLightUpdate, On, ResourceLink, Room, SceneActive, SceneStatus, SceneUpdate,
+};
+
+use crate::error::ApiResult;
+use crate::resource::Resources;
+use crate::server::{contact, motion};
+
+/// Are any of the lights in `room` on?
+pub fn room_on(res: &Resources, room: &ResourceLink) -> bool {
+    res.get::<Room>(room)
+        .ok()
+        .and_then(Room::grouped_light_service)
+        .and_then(|glight| res.get::<GroupedLight>(glight).ok())
+        .is_some_and(|glight| glight.on.is_some_and(|on| on.on))
+}
+
+/// Perform a rule `action` on `room`
+pub fn run_action(res: &Resources, room: &ResourceLink, action: &RuleAction) -> ApiResult<()> {
+    match action {
+        RuleAction::Scene { scene } => {
+            let upd = SceneUpdate::new().with_recall_action(Some(SceneStatus {
+                active: SceneActive::Static,
+                last_recall: None,
+            }));
+            res.backend_request(BackendRequest::SceneUpdate(*scene, upd))?;
+        }
+        RuleAction::Light { brightness } => {
+            let Some(glight) = res.get::<Room>(room)?.grouped_light_service() else {
+                return Ok(());
+            };
+            let upd = GroupedLightUpdate::new()
+                .with_on(Some(On::new(true)))
+                .with_brightness(*brightness);
+            res.backend_request(BackendRequest::GroupedLightUpdate(*glight, upd))?;
+        }
+        RuleAction::Off => {
+            let Some(glight) = res.get::<Room>(room)?.grouped_light_service() else {
+                return Ok(());
+            };
+            let upd = GroupedLightUpdate::new().with_on(Some(On::new(false)));
+            res.backend_request(BackendRequest::GroupedLightUpdate(*glight, upd))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the lighting rules configured in bifrost.
+///
+/// Watches the hue event stream for sensor updates, so rules work the same
+/// for sensors from any backend.
+pub async fn rule_engine(res: Arc<Mutex<Resources>>) -> ApiResult<()> {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    let mut events = res.lock().await.hue_event_stream().subscribe();
+
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut motion_states = motion::MotionStates::new();
+    let mut contact_states = contact::ContactStates::new();
+
+    loop {
+        tokio::select! {
+            ev = events.recv() => match ev {
+                Ok(record) => {
+                    let lock = res.lock().await;
+                    motion::handle_event(&lock, &mut motion_states, &record.block);
+                    contact::handle_event(&lock, &mut contact_states, &record.block);
+                }
+                Err(RecvError::Lagged(count)) => {
+                    log::warn!("Rule engine missed {count} events");
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = interval.tick() => {
+                let lock = res.lock().await;
+                motion::expire_rules(&lock, &mut motion_states);
+                contact::expire_rules(&lock, &mut contact_states);
+            }
+        }
+    }
+}
@@ -11,11 +11,11 @@ use svc::traits::{Service, StopResult};
 use uuid::Uuid;
 
 use crate::error::ApiError;
-use crate::server::updater::VersionUpdater;
+use crate::resource::Resources;
 
 pub struct SsdpService {
     service: Option<Server>,
-    updater: Arc<Mutex<VersionUpdater>>,
+    res: Arc<Mutex<Resources>>,
     usn: Uuid,
     mac: MacAddress,
     ip: Ipv4Addr,
@@ -41,10 +41,10 @@ pub fn hue_bridge_usn(mac: MacAddress) -> Uuid {
 
 impl SsdpService {
     #[must_use]
-    pub fn new(mac: MacAddress, ip: Ipv4Addr, updater: Arc<Mutex<VersionUpdater>>) -> Self {
+    pub fn new(mac: MacAddress, ip: Ipv4Addr, res: Arc<Mutex<Resources>>) -> Self {
         Self {
             service: None,
-            updater,
+            res,
             mac,
             ip,
             usn: hue_bridge_usn(mac),
@@ -65,12 +65,12 @@ impl Service for SsdpService {
     async fn start(&mut self) -> Result<(), Self::Error> {
         let location = format!("http://{}:80/description.xml", self.ip);
 
+        // announce the installed version, as reported by the api
         let legacy_api_version = self
-            .updater
+            .res
             .lock()
             .await
-            .get()
-            .await
+            .bridge_version()
             .get_legacy_apiversion();
 
         let usn = format!("uuid:{}", self.usn);
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+use hue::api::{DeviceSoftwareUpdate, DeviceSoftwareUpdateState, ResourceLink};
+
+use crate::error::ApiResult;
+use crate::resource::Resources;
+use crate::server::updater::VersionUpdater;
+
+/// Simulated time spent "downloading" a firmware update
+const DOWNLOAD_DURATION: Duration = Duration::from_secs(5);
+
+/// Simulated time spent "installing" a firmware update
+const INSTALL_DURATION: Duration = Duration::from_secs(10);
+
+async fn set_state(
+    res: &Mutex<Resources>,
+    rlink: &ResourceLink,
+    state: DeviceSoftwareUpdateState,
+) -> ApiResult<()> {
+    log::info!("Bridge software update: {state:?}");
+    res.lock()
+        .await
+        .update::<DeviceSoftwareUpdate>(&rlink.rid, |swu| swu.state = state)
+}
+
+/// Check for a newer firmware version, and "download" it if one is found.
+///
+/// Ends in [`DeviceSoftwareUpdateState::ReadyToInstall`] if a newer version
+/// is available, otherwise in [`DeviceSoftwareUpdateState::NoUpdate`].
+pub async fn check(
+    res: Arc<Mutex<Resources>>,
+    upd: Arc<Mutex<VersionUpdater>>,
+    rlink: ResourceLink,
+) -> ApiResult<()> {
+    let newest = {
+        let mut lock = upd.lock().await;
+        lock.reset_cache();
+        lock.get().await.as_u64()
+    };
+
+    let current = res.lock().await.bridge_version().as_u64();
+
+    if newest <= current {
+        log::info!("Bridge software update: version {current} is up to date");
+        return set_state(&res, &rlink, DeviceSoftwareUpdateState::NoUpdate).await;
+    }
+
+    log::info!("Bridge software update: version {newest} is available (current: {current})");
+    set_state(&res, &rlink, DeviceSoftwareUpdateState::UpdatePending).await?;
+
+    sleep(DOWNLOAD_DURATION).await;
+
+    set_state(&res, &rlink, DeviceSoftwareUpdateState::ReadyToInstall).await
+}
+
+/// "Install" the newest firmware version, by patching the bridge version
+pub async fn install(
+    res: Arc<Mutex<Resources>>,
+    upd: Arc<Mutex<VersionUpdater>>,
+    rlink: ResourceLink,
+) -> ApiResult<()> {
+    set_state(&res, &rlink, DeviceSoftwareUpdateState::Installing).await?;
+
+    sleep(INSTALL_DURATION).await;
+
+    let version = upd.lock().await.get().await.clone();
+    res.lock().await.update_bridge_version(version);
+
+    set_state(&res, &rlink, DeviceSoftwareUpdateState::NoUpdate).await
+}
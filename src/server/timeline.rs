@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use svc::manager::SvmClient;
+use svc::traits::ServiceState;
+
+use crate::error::ApiResult;
+use crate::model::hass::HassUiState;
+use crate::model::timeline::{TimelineCategory, TimelineEntry, TimelineLevel};
+
+/// Record service lifecycle changes (started, stopped, failed, restarted) in
+/// the activity timeline.
+pub async fn service_timeline(mut mgr: SvmClient, ui: Arc<Mutex<HassUiState>>) -> ApiResult<()> {
+    let (_id, mut events) = mgr.subscribe().await?;
+    let mut restarts = HashMap::new();
+
+    while let Some(event) = events.recv().await {
+        let last = restarts
+            .insert(event.id(), event.restarts())
+            .unwrap_or_default();
+
+        let (level, message) = match event.state() {
+            _ if event.restarts() > last => (TimelineLevel::Warning, "Service restarted"),
+            ServiceState::Running => (TimelineLevel::Info, "Service started"),
+            ServiceState::Stopped => (TimelineLevel::Info, "Service stopped"),
+            ServiceState::Failed => (TimelineLevel::Error, "Service failed"),
+            ServiceState::Registered
+            | ServiceState::Configured
+            | ServiceState::Starting
+            | ServiceState::Stopping => continue,
+        };
+
+        // the service may already be gone (e.g. a stopped template instance)
+        let name = match mgr.lookup_name(event.id()).await {
+            Ok(name) => name.to_string(),
+            Err(err) => {
+                log::debug!("Skipping timeline entry for unknown service: {err}");
+                continue;
+            }
+        };
+
+        let entry = TimelineEntry::new(TimelineCategory::Service, message)
+            .with_level(level)
+            .with_source(name);
+        ui.lock().await.push_entry(entry);
+    }
+
+    Ok(())
+}
@@ -1,14 +1,42 @@
+use std::sync::Arc;
+
 use chrono::{DateTime, Duration, Utc};
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
 
+use bifrost_api::updater::UpdaterStatus;
 use hue::HUE_BRIDGE_V2_MODEL_ID;
+use hue::api::{DeviceSoftwareUpdate, DeviceSoftwareUpdateState, RType};
 use hue::update::{UpdateEntries, UpdateEntry, update_url_for_bridge};
 use hue::version::SwVersion;
+use url::Url;
 
+use crate::config::UpdaterConfig;
 use crate::error::{ApiError, ApiResult};
+use crate::resource::Resources;
+use crate::server::proxy;
+
+fn update_url(mirror: Option<&Url>, since_version: u64) -> String {
+    mirror.map_or_else(
+        || update_url_for_bridge(HUE_BRIDGE_V2_MODEL_ID, since_version),
+        |mirror| {
+            let mut url = mirror.clone();
+            url.query_pairs_mut()
+                .append_pair("deviceTypeId", HUE_BRIDGE_V2_MODEL_ID)
+                .append_pair("version", &since_version.to_string());
+            url.to_string()
+        },
+    )
+}
 
-pub async fn fetch_updates(since_version: Option<u64>) -> ApiResult<Vec<UpdateEntry>> {
-    let url = update_url_for_bridge(HUE_BRIDGE_V2_MODEL_ID, since_version.unwrap_or_default());
-    let response: UpdateEntries = reqwest::get(url).await?.json().await?;
+pub async fn fetch_updates(
+    since_version: Option<u64>,
+    mirror: Option<&Url>,
+    proxy: Option<&Url>,
+) -> ApiResult<Vec<UpdateEntry>> {
+    let url = update_url(mirror, since_version.unwrap_or_default());
+    let client = proxy::client_builder(proxy)?.build()?;
+    let response: UpdateEntries = client.get(url).send().await?.json().await?;
     Ok(response.updates)
 }
 
@@ -16,6 +44,8 @@ pub async fn fetch_updates(since_version: Option<u64>) -> ApiResult<Vec<UpdateEn
 pub struct VersionUpdater {
     version: Option<SwVersion>,
     last_fetch: Option<DateTime<Utc>>,
+    config: UpdaterConfig,
+    proxy: Option<Url>,
 }
 
 impl Default for VersionUpdater {
@@ -28,10 +58,12 @@ impl VersionUpdater {
     const CACHE_TIME: Duration = Duration::hours(24);
 
     #[must_use]
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             version: None,
             last_fetch: None,
+            config: UpdaterConfig::default(),
+            proxy: None,
         }
     }
 
@@ -40,15 +72,37 @@ impl VersionUpdater {
         Self {
             version: Some(SwVersion::default()),
             last_fetch: Some(Utc::now()),
+            ..Self::new()
+        }
+    }
+
+    #[must_use]
+    pub fn from_config(config: UpdaterConfig, proxy: Option<Url>) -> Self {
+        let version = config
+            .pinned_version
+            .map_or_else(SwVersion::default, SwVersion::from_u64);
+
+        Self {
+            version: Some(version),
+            last_fetch: Some(Utc::now()),
+            config,
+            proxy,
         }
     }
 
+    /// True if the emulated version can change at runtime (i.e., the updater
+    /// is enabled, and no version is pinned)
+    #[must_use]
+    pub const fn is_dynamic(&self) -> bool {
+        self.config.enabled && self.config.pinned_version.is_none()
+    }
+
     pub const fn reset_cache(&mut self) {
         self.last_fetch = None;
     }
 
     pub async fn fetch_version(&mut self) -> ApiResult<SwVersion> {
-        fetch_updates(None)
+        fetch_updates(None, self.config.mirror_url.as_ref(), self.proxy.as_ref())
             .await?
             .into_iter()
             .max_by(|x, y| x.version.cmp(&y.version))
@@ -57,6 +111,10 @@ impl VersionUpdater {
     }
 
     pub async fn get(&mut self) -> &SwVersion {
+        if !self.is_dynamic() {
+            return self.version.get_or_insert_with(SwVersion::default);
+        }
+
         let expired = self
             .last_fetch
             .is_none_or(|time| (Utc::now() - time) > Self::CACHE_TIME);
@@ -85,4 +143,62 @@ impl VersionUpdater {
 
         self.version.as_ref().unwrap()
     }
+
+    pub async fn status(&mut self) -> UpdaterStatus {
+        let version = self.get().await.clone();
+
+        UpdaterStatus {
+            enabled: self.config.enabled,
+            pinned_version: self.config.pinned_version,
+            mirror_url: self.config.mirror_url.clone(),
+            last_fetch: self.last_fetch.map(|time| time.to_rfc3339()),
+            version: version.as_u64(),
+            version_name: version.name().to_string(),
+            software_version: version.get_software_version(),
+        }
+    }
+}
+
+pub async fn version_updater(
+    res: Arc<Mutex<Resources>>,
+    upd: Arc<Mutex<VersionUpdater>>,
+    bridge_id: String,
+) -> ApiResult<()> {
+    const INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    // only the bridge itself is updated from the firmware changelog
+    let link_bridge = RType::Bridge.deterministic(&bridge_id);
+    let link_swu = RType::DeviceSoftwareUpdate.deterministic(link_bridge.rid);
+
+    let mut version = upd.lock().await.get().await.clone();
+
+    loop {
+        interval.tick().await;
+
+        let new_version = upd.lock().await.get().await.clone();
+        if new_version == version {
+            continue;
+        }
+
+        // Only offer the update here. Patching the bridge version is left
+        // to an explicit install request (see [`crate::server::swupdate`])
+        log::info!("New version detected! Waiting for install request..");
+        let offered = res
+            .lock()
+            .await
+            .update::<DeviceSoftwareUpdate>(&link_swu.rid, |swu| {
+                if swu.state == DeviceSoftwareUpdateState::NoUpdate {
+                    swu.state = DeviceSoftwareUpdateState::ReadyToInstall;
+                }
+            });
+
+        // keep the old version on failure, so the update is offered again on
+        // the next tick
+        match offered {
+            Ok(()) => version = new_version,
+            Err(err) => log::error!("Failed to offer bridge software update: {err}"),
+        }
+    }
 }
@@ -33,6 +33,10 @@ type TabId =
   | 'logs'
   | 'about'
 
+const LIGHT_DOMAINS = ['light', 'cover']
+const SWITCH_DOMAINS = ['switch', 'fan', 'media_player', 'script', 'button', 'event']
+const SENSOR_DOMAINS = ['binary_sensor', 'sensor', 'lock']
+
 const TABS: Array<{ id: TabId; label: string }> = [
   { id: 'setup', label: 'Setup' },
   { id: 'lights', label: 'Lights' },
@@ -105,16 +109,19 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
 
   const payload = data.payload
   const config = payload?.config || emptyConfig()
-  const entities = useMemo(() => payload?.entities || [], [payload?.entities])
+  const entityCounts = payload?.entity_counts
   const rooms = config.rooms || []
 
   const counters = useMemo(() => {
-    const lights = entities.filter((e) => e.domain === 'light').length
-    const switches = entities.filter((e) => e.domain === 'switch').length
-    const sensors = entities.filter((e) => e.domain === 'binary_sensor').length
-    const hidden = entities.filter((e) => !e.included).length
-    return { lights, switches, sensors, hidden }
-  }, [entities])
+    const byDomain = entityCounts?.by_domain || {}
+    const count = (domains: string[]) => domains.reduce((sum, d) => sum + (byDomain[d] || 0), 0)
+    return {
+      lights: count(LIGHT_DOMAINS),
+      switches: count(SWITCH_DOMAINS),
+      sensors: count(SENSOR_DOMAINS),
+      hidden: entityCounts?.hidden || 0,
+    }
+  }, [entityCounts])
 
   async function callWithToast(okText: string, fn: () => Promise<void>) {
     try {
@@ -246,6 +253,7 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
             <SetupPage
               runtime={data.runtime}
               config={config}
+              backends={payload?.backends || []}
               onSaveConfig={saveConfig}
               onRefresh={data.refresh}
             />
@@ -254,10 +262,10 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
           {tab === 'lights' && (
             <EntitiesPage
               title="Lights"
-              subtitle="Home Assistant light entities exposed as Hue lights."
-              entities={entities}
+              subtitle="Home Assistant lights and covers exposed as Hue lights (covers are on while open, with the position as brightness)."
+              query={{ domain: LIGHT_DOMAINS.join(',') }}
+              refreshKey={payload}
               rooms={rooms}
-              predicate={(e) => e.domain === 'light'}
               onSetIncluded={setIncluded}
               onSetRoom={setRoom}
               onSetAlias={setAlias}
@@ -271,10 +279,10 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
           {tab === 'switches' && (
             <EntitiesPage
               title="Switches"
-              subtitle="Home Assistant switches with selectable Hue type (plug or light)."
-              entities={entities}
+              subtitle="Home Assistant switches and fans with selectable Hue type (plug or light). Fans shown as lights are dimmed by changing their speed. Media players are plugs, turned off with the room. Scripts, buttons and remotes (event entities) are Hue buttons."
+              query={{ domain: SWITCH_DOMAINS.join(',') }}
+              refreshKey={payload}
               rooms={rooms}
-              predicate={(e) => e.domain === 'switch'}
               onSetIncluded={setIncluded}
               onSetRoom={setRoom}
               onSetAlias={setAlias}
@@ -288,10 +296,10 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
           {tab === 'sensors' && (
             <EntitiesPage
               title="Sensors"
-              subtitle="Binary sensors mapped as Hue motion/contact sensors."
-              entities={entities}
+              subtitle="Binary sensors (and numeric sensors with a threshold, and locks) mapped as Hue motion/contact sensors. Temperature and illuminance sensors are Hue temperature and light level sensors."
+              query={{ domain: SENSOR_DOMAINS.join(',') }}
+              refreshKey={payload}
               rooms={rooms}
-              predicate={(e) => e.domain === 'binary_sensor'}
               onSetIncluded={setIncluded}
               onSetRoom={setRoom}
               onSetAlias={setAlias}
@@ -306,9 +314,9 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
             <EntitiesPage
               title="Hidden"
               subtitle="All entities currently not exposed in Hue."
-              entities={entities}
+              query={{ included: false }}
+              refreshKey={payload}
               rooms={rooms}
-              predicate={(e) => !e.included}
               onSetIncluded={setIncluded}
               onSetRoom={setRoom}
               onSetAlias={setAlias}
@@ -329,7 +337,7 @@ function AppContent(props: { data: ReturnType<typeof useBifrostData> }) {
             <BridgePage payload={payload} bridge={data.bridge} onRefresh={data.refresh} />
           )}
 
-          {tab === 'logs' && <LogsPage logs={payload?.logs || []} onRefresh={data.refresh} />}
+          {tab === 'logs' && <LogsPage timeline={payload?.timeline || []} onRefresh={data.refresh} />}
 
           {tab === 'about' && <AboutPage bridge={data.bridge} patina={payload?.patina} />}
         </main>
@@ -123,11 +123,11 @@ export function EntityRow(props: {
         </div>
       </div>
 
-      {e.domain === 'switch' && (
+      {(e.domain === 'switch' || e.domain === 'fan') && (
         <div className="mt-2 grid gap-2 md:grid-cols-[minmax(0,1fr)_190px]">
           <SelectField
             label="Hue device type"
-            value={(e.switch_mode || 'plug') as HassSwitchMode}
+            value={(e.switch_mode || (e.domain === 'fan' ? 'light' : 'plug')) as HassSwitchMode}
             onChange={(v) => props.onSetSwitchMode(e, v as HassSwitchMode)}
             options={[
               { value: 'plug', label: 'Power plug' },
@@ -142,7 +142,7 @@ export function EntityRow(props: {
         </div>
       )}
 
-      {e.domain === 'light' && (
+      {(e.domain === 'light' || e.domain === 'cover') && (
         <div className="mt-2 grid gap-2 md:grid-cols-[minmax(0,1fr)_280px]">
           <SelectField
             label="Light icon"
@@ -156,18 +156,24 @@ export function EntityRow(props: {
         </div>
       )}
 
-      {e.domain === 'binary_sensor' && (
+      {['binary_sensor', 'sensor', 'lock'].includes(e.domain) && (
         <div className="mt-2 grid gap-2 md:grid-cols-[minmax(0,1fr)_190px]">
-          <SelectField
-            label="Sensor type"
-            value={(e.sensor_kind || 'ignore') as string}
-            onChange={(v) => props.onSetSensorKind(e, v as HassSensorKind)}
-            options={[
-              { value: 'motion', label: 'Motion sensor' },
-              { value: 'contact', label: 'Door/contact sensor' },
-              { value: 'ignore', label: 'Ignore' },
-            ]}
-          />
+          {e.mapped_type === 'temperature' || e.mapped_type === 'light_level' ? (
+            <div className="self-center text-xs text-ink-1">
+              {e.mapped_type === 'temperature' ? 'Temperature' : 'Light level'} sensor ({e.state})
+            </div>
+          ) : (
+            <SelectField
+              label="Sensor type"
+              value={(e.sensor_kind || 'ignore') as string}
+              onChange={(v) => props.onSetSensorKind(e, v as HassSensorKind)}
+              options={[
+                { value: 'motion', label: 'Motion sensor' },
+                { value: 'contact', label: 'Door/contact sensor' },
+                { value: 'ignore', label: 'Ignore' },
+              ]}
+            />
+          )}
           <ToggleSwitch
             checked={!!e.enabled}
             onChange={(v) => props.onSetSensorEnabled(e, v)}
@@ -1,8 +1,18 @@
 import type {
+  HassBrightnessStrategy,
+  BifrostAbout,
+  BifrostReleaseStatus,
   HassBridgeInfo,
+  HassEntitiesQuery,
+  HassEntitiesResponse,
+  HassPatinaUsageReport,
+  HassResetScope,
   HassRuntimeConfigPublic,
   HassUiConfig,
+  HassSessionView,
   HassUiPayload,
+  HassVerifyResponse,
+  RoomDuplicate,
 } from './types'
 
 type JsonValue = unknown
@@ -25,8 +35,92 @@ async function readError(res: Response): Promise<string> {
   }
 }
 
+const SESSION_HEADER = 'x-bifrost-session'
+const SESSION_KEY = 'bifrost-session'
+const CSRF_HEADER = 'x-bifrost-csrf'
+
+// Requests that change anything must carry the csrf token of the server. The
+// token changes when bifrost restarts, so it is fetched again after a reject.
+let csrfToken: Promise<string | null> | null = null
+
+async function getCsrfToken(): Promise<string | null> {
+  if (!csrfToken) {
+    csrfToken = (async () => {
+      try {
+        const res = await fetch('/bifrost/csrf')
+        if (!res.ok) return null
+        const view = (await res.json()) as { token: string | null }
+        return view.token
+      } catch {
+        return null
+      }
+    })()
+  }
+  return csrfToken
+}
+
+function isSafeMethod(init?: RequestInit): boolean {
+  const method = (init?.method || 'GET').toUpperCase()
+  return method === 'GET' || method === 'HEAD' || method === 'OPTIONS'
+}
+
+async function send(path: string, init?: RequestInit): Promise<Response> {
+  if (isSafeMethod(init)) return fetch(path, init)
+
+  const attempt = async () => {
+    const headers = new Headers(init?.headers)
+    const token = await getCsrfToken()
+    if (token) headers.set(CSRF_HEADER, token)
+    return fetch(path, { ...init, headers })
+  }
+
+  const res = await attempt()
+  if (res.status !== 403) return res
+
+  // bifrost may have restarted with a new token
+  csrfToken = null
+  return attempt()
+}
+
+// Each browser tab gets its own server-side session, so action results and
+// sync progress from other tabs do not show up in this one.
+let sessionId: Promise<string | null> | null = null
+// When creating a session failed, do not try again before this time (ms)
+let sessionRetryAt = 0
+const SESSION_RETRY_MS = 60_000
+
+async function createSession(): Promise<string | null> {
+  const stored = sessionStorage.getItem(SESSION_KEY)
+  if (stored) return stored
+  try {
+    const res = await send('/bifrost/hass/session', { method: 'POST' })
+    if (res.ok) {
+      const view = (await res.json()) as HassSessionView
+      sessionStorage.setItem(SESSION_KEY, view.session_id)
+      return view.session_id
+    }
+  } catch {
+    // handled below
+  }
+  sessionRetryAt = Date.now() + SESSION_RETRY_MS
+  return null
+}
+
+async function getSessionId(): Promise<string | null> {
+  if (!sessionId) {
+    sessionId = createSession()
+  } else if (sessionRetryAt && Date.now() >= sessionRetryAt && !(await sessionId)) {
+    sessionRetryAt = 0
+    sessionId = createSession()
+  }
+  return sessionId
+}
+
 export async function api<T = JsonValue>(path: string, init?: RequestInit): Promise<T> {
-  const res = await fetch(path, init)
+  const session = await getSessionId()
+  const headers = new Headers(init?.headers)
+  if (session) headers.set(SESSION_HEADER, session)
+  const res = await send(path, { ...init, headers })
   if (!res.ok) {
     throw new Error(await readError(res))
   }
@@ -35,13 +129,40 @@ export async function api<T = JsonValue>(path: string, init?: RequestInit): Prom
 }
 
 export async function getUiPayload(): Promise<HassUiPayload> {
-  return api('/bifrost/hass/ui-payload')
+  const payload = await api<HassUiPayload>('/bifrost/hass/ui-payload')
+  if (!payload.session && !sessionRetryAt) {
+    // session expired (or bifrost restarted), start a new one
+    sessionStorage.removeItem(SESSION_KEY)
+    sessionId = null
+  }
+  return payload
+}
+
+export async function getEntities(query: HassEntitiesQuery = {}): Promise<HassEntitiesResponse> {
+  const params = new URLSearchParams()
+  for (const [key, value] of Object.entries(query)) {
+    if (value !== undefined && value !== '') params.set(key, String(value))
+  }
+  const qs = params.toString()
+  return api(`/bifrost/hass/entities${qs ? `?${qs}` : ''}`)
 }
 
 export async function getBridgeInfo(): Promise<HassBridgeInfo> {
   return api('/bifrost/hass/bridge-info')
 }
 
+export async function getAbout(): Promise<BifrostAbout> {
+  return api('/bifrost/about')
+}
+
+export async function getReleaseStatus(): Promise<BifrostReleaseStatus> {
+  return api('/bifrost/updater/release')
+}
+
+export async function postReleaseCheck(): Promise<BifrostReleaseStatus> {
+  return api<BifrostReleaseStatus>('/bifrost/updater/release/check', { method: 'POST' })
+}
+
 export async function getRuntimeConfig(): Promise<HassRuntimeConfigPublic> {
   return api('/bifrost/hass/runtime-config')
 }
@@ -86,12 +207,30 @@ export async function postApply(): Promise<void> {
   await api('/bifrost/hass/apply', { method: 'POST' })
 }
 
+export async function postVerify(): Promise<HassVerifyResponse> {
+  return api<HassVerifyResponse>('/bifrost/hass/verify', { method: 'POST' })
+}
+
+export async function postReconcile(): Promise<HassVerifyResponse> {
+  return api<HassVerifyResponse>('/bifrost/hass/verify/reconcile', { method: 'POST' })
+}
+
 export async function postLinkButton(): Promise<void> {
   await api('/bifrost/hass/linkbutton', { method: 'POST' })
 }
 
-export async function postResetBridge(): Promise<void> {
-  await api('/bifrost/hass/reset-bridge', { method: 'POST' })
+export async function postResetBridge(scope: HassResetScope = 'all'): Promise<void> {
+  // resets need a short-lived confirmation token, requested just before
+  const { token } = await api<{ token: string }>('/bifrost/hass/reset-bridge/token', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ scope }),
+  })
+  await api('/bifrost/hass/reset-bridge', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ scope, token }),
+  })
 }
 
 export async function putUiConfig(config: HassUiConfig): Promise<HassUiConfig> {
@@ -118,6 +257,29 @@ export async function putRoomRename(room_id: string, name: string): Promise<void
   })
 }
 
+export async function putRoomBrightnessStrategy(
+  room_id: string,
+  brightness_strategy: HassBrightnessStrategy,
+): Promise<void> {
+  await api('/bifrost/hass/room/brightness', {
+    method: 'PUT',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ room_id, brightness_strategy }),
+  })
+}
+
+export async function putRoomMerge(room_id: string, target: string | null): Promise<void> {
+  await api('/bifrost/hass/room/merge', {
+    method: 'PUT',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ room_id, target }),
+  })
+}
+
+export async function getRoomDuplicates(): Promise<RoomDuplicate[]> {
+  return api('/bifrost/rooms/duplicates')
+}
+
 export async function postRoom(name: string): Promise<void> {
   await api('/bifrost/hass/rooms', {
     method: 'POST',
@@ -141,3 +303,22 @@ export async function postPatinaEvent(kind: string, key?: string): Promise<void>
     body: JSON.stringify({ kind, key }),
   })
 }
+
+export async function getPatinaUsage(): Promise<HassPatinaUsageReport> {
+  return api('/bifrost/hass/patina/usage')
+}
+
+export async function resetPatinaUsage(): Promise<HassPatinaUsageReport> {
+  return api('/bifrost/hass/patina/usage', { method: 'DELETE' })
+}
+
+export async function putEntityState(
+  entityId: string,
+  body: { on?: boolean; brightness?: number; color_temperature?: number; color?: { x: number; y: number } },
+): Promise<{ entity_id: string; light: { rid: string; rtype: string } }> {
+  return api(`/bifrost/hass/entity/${encodeURIComponent(entityId)}/state`, {
+    method: 'PUT',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify(body),
+  })
+}
@@ -24,10 +24,13 @@ export type HassLightArchetype =
   | 'hue_iris'
   | 'hue_signe'
   | 'hue_tube'
+export type HassConflictPolicy = 'backend_wins' | 'last_write_wins' | 'hold'
+export type HassBrightnessStrategy = 'average' | 'average_on' | 'max' | 'last_changed'
 export type HassFakeCloudMode = 'off' | 'connected' | 'outage' | 'custom'
 export type HassPortalCommunication = 'connected' | 'disconnected' | 'error'
 export type HassPortalConnectionState = 'connected' | 'disconnected' | 'connecting'
 export type HassPortalAction = 'none' | 'link_button'
+export type HassResetScope = 'all' | 'hass_devices' | 'scenes' | 'pairings'
 
 export interface HassFakeCloudState {
   internet: boolean
@@ -46,6 +49,13 @@ export interface HassRoomConfig {
   name: string
   source_area?: string | null
   auto_created: boolean
+  brightness_strategy?: HassBrightnessStrategy | null
+  merge_into?: string | null
+}
+
+export interface RoomDuplicate {
+  name: string
+  rooms: { room: { rid: string; rtype: string }; backend?: string | null }[]
 }
 
 export interface HassEntityPreference {
@@ -56,6 +66,7 @@ export interface HassEntityPreference {
   sensor_enabled?: boolean | null
   switch_mode?: HassSwitchMode | null
   light_archetype?: HassLightArchetype | null
+  conflict_policy?: HassConflictPolicy | null
 }
 
 export interface HassUiConfig {
@@ -66,18 +77,46 @@ export interface HassUiConfig {
   rooms: HassRoomConfig[]
   entity_preferences: Record<string, HassEntityPreference>
   ignored_area_names: string[]
+  included_area_names?: string[]
   default_add_new_devices_to_hue: boolean
   sync_hass_areas_to_rooms: boolean
+  import_buttons?: boolean
+  import_remotes?: boolean
+  daylight_sensor?: boolean
+  daylight_weather_entity?: string | null
   fake_cloud_mode: HassFakeCloudMode
   fake_cloud_custom: HassFakeCloudState
   hass_timezone?: string | null
   hass_lat?: string | null
   hass_long?: string | null
+  disabled_domains?: Record<string, string[]>
+  include_labels?: string[]
+  exclude_labels?: string[]
+  label_rooms?: Record<string, string>
+  label_zones?: Record<string, string>
+  sensor_thresholds?: Record<string, HassSensorThreshold>
+}
+
+export interface HassSensorThreshold {
+  sensor_kind: HassSensorKind
+  below?: number | null
+  above?: number | null
 }
 
 export interface HassEntitySummary {
   entity_id: string
-  domain: 'light' | 'switch' | 'binary_sensor' | string
+  domain:
+    | 'light'
+    | 'switch'
+    | 'cover'
+    | 'fan'
+    | 'media_player'
+    | 'binary_sensor'
+    | 'lock'
+    | 'script'
+    | 'button'
+    | 'event'
+    | string
   name: string
   state: string
   available: boolean
@@ -94,6 +133,7 @@ export interface HassEntitySummary {
   sensor_kind?: HassSensorKind | null
   light_archetype?: HassLightArchetype | null
   enabled: boolean
+  labels?: string[]
 }
 
 export interface HassSyncStatus {
@@ -101,6 +141,40 @@ export interface HassSyncStatus {
   last_sync_result?: string | null
   sync_in_progress: boolean
   last_sync_duration_ms?: number | null
+  last_reconcile_at?: string | null
+  last_reconcile_result?: string | null
+  ws_latency_ms?: number | null
+  last_event_at?: string | null
+  ws_stalls?: number
+}
+
+export interface HassRetryQueueStatus {
+  queued: number
+  retried: number
+  failed: number
+  dropped_expired: number
+  dropped_overflow: number
+}
+
+export interface BifrostAbout {
+  version: string
+  git_hash: string | null
+  build_date: string | null
+  rust_version: string | null
+  uptime_secs: number
+  features: string[]
+}
+
+export interface BifrostReleaseStatus {
+  enabled: boolean
+  current_version: string
+  latest_version: string | null
+  update_available: boolean
+  release_url: string | null
+  published_at: string | null
+  last_check: string | null
+  last_error: string | null
+  staged_path: string | null
 }
 
 export interface HassBridgeInfo {
@@ -122,6 +196,7 @@ export interface HassBridgeInfo {
   default_add_new_devices_to_hue: boolean
   sync_hass_areas_to_rooms: boolean
   sync_status: HassSyncStatus
+  retry_queue: Record<string, HassRetryQueueStatus>
 }
 
 export interface HassRuntimeConfigPublic {
@@ -138,10 +213,89 @@ export interface HassPatinaPublic {
   stage: 'fresh' | 'used' | 'loved'
 }
 
+export type HassPatinaFeature = 'sync_run' | 'scene_recall' | 'room_create'
+
+export interface HassPatinaUsage {
+  count: number
+  first_used?: string
+  last_used?: string
+}
+
+export interface HassPatinaUsageReport {
+  install_date: string
+  days_since_install: number
+  features: Record<HassPatinaFeature, HassPatinaUsage>
+}
+
 export interface HassUiPayload {
   config: HassUiConfig
-  entities: HassEntitySummary[]
-  logs: string[]
+  backends: string[]
+  entity_counts: HassEntityCounts
+  timeline: TimelineEntry[]
   sync: HassSyncStatus
   patina: HassPatinaPublic
+  session?: HassSessionView
+}
+
+export type TimelineCategory = 'sync' | 'command' | 'connection' | 'config' | 'pairing' | 'service'
+
+export type TimelineLevel = 'info' | 'warning' | 'error'
+
+export interface TimelineEntry {
+  at: string
+  level: TimelineLevel
+  category: TimelineCategory
+  source?: string
+  entity_id?: string
+  message: string
+}
+
+export interface HassEntityCounts {
+  by_domain: Record<string, number>
+  hidden: number
+}
+
+export interface HassEntitiesResponse {
+  entities: HassEntitySummary[]
+  total: number
+}
+
+export interface HassEntitiesQuery {
+  domain?: string
+  room?: string
+  included?: boolean
+  search?: string
+  sort?: 'entity_id' | 'name' | 'domain' | 'room' | 'state'
+  order?: 'asc' | 'desc'
+  offset?: number
+  limit?: number
+}
+
+export interface HassSessionView {
+  session_id: string
+  results: string[]
+  sync_pending: boolean
+}
+
+export type HassMismatchKind = 'state' | 'missing' | 'orphan'
+
+export interface HassMismatch {
+  kind: HassMismatchKind
+  entity_id?: string | null
+  device?: { rid: string; rtype: string } | null
+  detail: string
+}
+
+export interface HassVerifyReport {
+  backend: string
+  checked_at: string
+  checked: number
+  mismatches: HassMismatch[]
+  reconciled: number
+  error?: string | null
+}
+
+export interface HassVerifyResponse {
+  complete: boolean
+  reports: HassVerifyReport[]
 }
@@ -1,6 +1,15 @@
 import { useEffect, useMemo, useState } from 'react'
-import type { HassBridgeInfo, HassPatinaPublic } from '../lib/types'
+import { getAbout, getPatinaUsage, getReleaseStatus, postReleaseCheck, resetPatinaUsage } from '../lib/api'
+import type {
+  BifrostAbout,
+  BifrostReleaseStatus,
+  HassBridgeInfo,
+  HassPatinaFeature,
+  HassPatinaPublic,
+  HassPatinaUsageReport,
+} from '../lib/types'
 import { Panel } from '../components/Panel'
+import { TactileButton } from '../components/TactileButton'
 import { ToggleSwitch } from '../components/ToggleSwitch'
 import { usePatina } from '../state/PatinaContext'
 
@@ -11,6 +20,36 @@ export function AboutPage(props: {
   const { patinaLevel, actualLevel, stage, setPreviewLevel } = usePatina()
   const [preview, setPreview] = useState(false)
   const [slider, setSlider] = useState(0)
+  const [about, setAbout] = useState<BifrostAbout | null>(null)
+  const [release, setRelease] = useState<BifrostReleaseStatus | null>(null)
+  const [checking, setChecking] = useState(false)
+  const [usage, setUsage] = useState<HassPatinaUsageReport | null>(null)
+
+  useEffect(() => {
+    getAbout()
+      .then(setAbout)
+      .catch(() => setAbout(null))
+    getReleaseStatus()
+      .then(setRelease)
+      .catch(() => setRelease(null))
+    getPatinaUsage()
+      .then(setUsage)
+      .catch(() => setUsage(null))
+  }, [])
+
+  const resetUsage = () => {
+    resetPatinaUsage()
+      .then(setUsage)
+      .catch(() => undefined)
+  }
+
+  const checkRelease = () => {
+    setChecking(true)
+    postReleaseCheck()
+      .then(setRelease)
+      .catch(() => undefined)
+      .finally(() => setChecking(false))
+  }
 
   useEffect(() => {
     setSlider(actualLevel)
@@ -42,6 +81,46 @@ export function AboutPage(props: {
         </div>
       </Panel>
 
+      <Panel title="Build" subtitle="Include this in bug reports.">
+        <div className="grid gap-2 sm:grid-cols-2">
+          <AboutKv k="Version" v={about?.version || '-'} mono />
+          <AboutKv k="Git commit" v={about?.git_hash || '-'} mono />
+          <AboutKv k="Build date" v={about?.build_date || '-'} mono />
+          <AboutKv k="Rust" v={about?.rust_version || '-'} mono />
+          <AboutKv k="Uptime" v={about ? formatUptime(about.uptime_secs) : '-'} />
+          <AboutKv k="Features" v={about?.features.join(', ') || '-'} mono />
+        </div>
+      </Panel>
+
+      {release?.enabled && (
+        <Panel title="Bifrost updates" subtitle="New releases, from the configured release feed.">
+          <div className="grid gap-2 sm:grid-cols-2">
+            <AboutKv
+              k="Latest release"
+              v={
+                release.latest_version
+                  ? `${release.latest_version}${release.update_available ? ' (update available)' : ' (up to date)'}`
+                  : '-'
+              }
+              mono
+            />
+            <AboutKv k="Last check" v={release.last_check || '-'} mono />
+            {release.staged_path && <AboutKv k="Downloaded to" v={release.staged_path} mono />}
+            {release.last_error && <AboutKv k="Last error" v={release.last_error} />}
+          </div>
+          <div className="mt-4 flex flex-wrap gap-2">
+            <TactileButton variant="neutral" disabled={checking} onClick={checkRelease} wearKey="about:release-check">
+              Check now
+            </TactileButton>
+            {release.update_available && release.release_url && (
+              <a className="self-center text-sm underline" href={release.release_url} target="_blank" rel="noreferrer">
+                Release notes
+              </a>
+            )}
+          </div>
+        </Panel>
+      )}
+
       <Panel title="Digital Patina" subtitle="Interface wear based on usage and install age.">
         <div className="grid gap-2 sm:grid-cols-2">
           <AboutKv k="Install date" v={props.patina?.install_date || '-'} mono />
@@ -75,6 +154,29 @@ export function AboutPage(props: {
         </div>
       </Panel>
 
+      {usage && (
+        <Panel title="Achievements" subtitle="Feature usage, stored only on this bridge and never shared.">
+          <div className="grid gap-2 sm:grid-cols-3">
+            {USAGE_FEATURES.map(([feature, label]) => {
+              const entry = usage.features[feature]
+              return (
+                <AboutKv
+                  key={feature}
+                  k={label}
+                  v={entry?.last_used ? `${entry.count} (last ${entry.last_used})` : String(entry?.count ?? 0)}
+                  mono
+                />
+              )
+            })}
+          </div>
+          <div className="mt-3">
+            <TactileButton variant="neutral" onClick={resetUsage} wearKey="about:usage-reset">
+              Reset usage
+            </TactileButton>
+          </div>
+        </Panel>
+      )}
+
       <Panel
         title="What This UI Does"
         subtitle="English-only v1, LAN-only configuration panel, no file editing needed."
@@ -90,6 +192,21 @@ export function AboutPage(props: {
   )
 }
 
+const USAGE_FEATURES: [HassPatinaFeature, string][] = [
+  ['sync_run', 'Syncs run'],
+  ['scene_recall', 'Scenes recalled'],
+  ['room_create', 'Rooms created'],
+]
+
+function formatUptime(secs: number): string {
+  const days = Math.floor(secs / 86400)
+  const hours = Math.floor((secs % 86400) / 3600)
+  const minutes = Math.floor((secs % 3600) / 60)
+  if (days > 0) return `${days}d ${hours}h`
+  if (hours > 0) return `${hours}h ${minutes}m`
+  return `${minutes}m`
+}
+
 function AboutKv(props: { k: string; v: string; mono?: boolean }) {
   return (
     <div className="sub-panel px-3 py-2">
@@ -3,14 +3,29 @@ import {
   postApply,
   postLinkButton,
   postPatinaEvent,
+  postReconcile,
   postResetBridge,
   postSync,
+  postVerify,
 } from '../lib/api'
-import type { HassBridgeInfo, HassUiPayload } from '../lib/types'
+import type {
+  HassBridgeInfo,
+  HassResetScope,
+  HassUiPayload,
+  HassVerifyResponse,
+} from '../lib/types'
 import { ConfirmDialog } from '../components/ConfirmDialog'
 import { Panel } from '../components/Panel'
+import { SelectField } from '../components/SelectField'
 import { TactileButton } from '../components/TactileButton'
 
+const RESET_SCOPES: { value: HassResetScope; label: string }[] = [
+  { value: 'all', label: 'Everything (factory reset)' },
+  { value: 'hass_devices', label: 'Only Home Assistant devices' },
+  { value: 'scenes', label: 'Only scenes' },
+  { value: 'pairings', label: 'Only paired apps' },
+]
+
 export function BridgePage(props: {
   payload: HassUiPayload
   bridge: HassBridgeInfo | null
@@ -18,6 +33,8 @@ export function BridgePage(props: {
 }) {
   const [busy, setBusy] = useState<string | null>(null)
   const [confirmReset, setConfirmReset] = useState(false)
+  const [resetScope, setResetScope] = useState<HassResetScope>('all')
+  const [verify, setVerify] = useState<HassVerifyResponse | null>(null)
 
   const kv = useMemo(() => {
     const b = props.bridge
@@ -111,6 +128,85 @@ export function BridgePage(props: {
         <div className="mt-1 text-sm text-ink-0">
           Result: <span className="font-mono">{props.payload.sync.last_sync_result || '-'}</span>
         </div>
+        <div className="mt-1 text-sm text-ink-0">
+          Last reconcile:{' '}
+          <span className="font-mono">{props.payload.sync.last_reconcile_at || 'never'}</span>
+        </div>
+        <div className="mt-1 text-sm text-ink-0">
+          Reconcile result:{' '}
+          <span className="font-mono">{props.payload.sync.last_reconcile_result || '-'}</span>
+        </div>
+        <div className="mt-1 text-sm text-ink-0">
+          Websocket:{' '}
+          <span className="font-mono">
+            {props.payload.sync.ws_latency_ms != null ? `${props.payload.sync.ws_latency_ms} ms` : '-'}
+          </span>
+          , last event <span className="font-mono">{props.payload.sync.last_event_at || '-'}</span>
+          , stalls <span className="font-mono">{props.payload.sync.ws_stalls ?? 0}</span>
+        </div>
+        {props.payload.session && (
+          <>
+            <div className="mt-1 text-sm text-ink-0">
+              Sync requested from this tab:{' '}
+              <span className="font-semibold">
+                {props.payload.session.sync_pending ? 'pending' : 'no'}
+              </span>
+            </div>
+            {props.payload.session.results.length > 0 && (
+              <div className="mt-2 text-sm text-ink-0">
+                <div className="font-semibold">Results of this tab</div>
+                <ul className="mt-1 space-y-0.5">
+                  {props.payload.session.results.slice(0, 10).map((result, idx) => (
+                    <li key={idx} className="font-mono text-xs text-ink-1">
+                      {result}
+                    </li>
+                  ))}
+                </ul>
+              </div>
+            )}
+          </>
+        )}
+      </Panel>
+
+      <Panel title="Consistency" subtitle="Compares Home Assistant states with the Hue resources.">
+        <div className="flex flex-wrap gap-2">
+          <TactileButton
+            variant="neutral"
+            disabled={!!busy}
+            onClick={() => run('verify', async () => setVerify(await postVerify()))}
+            wearKey="bridge:verify"
+          >
+            Check consistency
+          </TactileButton>
+          <TactileButton
+            variant="primary"
+            disabled={!!busy || !verify?.reports.some((r) => r.mismatches.length > 0)}
+            onClick={() => run('reconcile', async () => setVerify(await postReconcile()))}
+            wearKey="bridge:reconcile"
+          >
+            Reconcile
+          </TactileButton>
+        </div>
+        {verify && !verify.complete && (
+          <div className="mt-2 text-sm text-ink-1">Not all backends reported back yet.</div>
+        )}
+        {verify?.reports.map((report) => (
+          <div key={report.backend} className="mt-2 text-sm text-ink-0">
+            <div className="font-semibold">
+              {report.backend}: {report.error
+                ? `error: ${report.error}`
+                : `${report.mismatches.length} mismatches in ${report.checked} entities`}
+              {report.reconciled > 0 && ` (${report.reconciled} reconciled)`}
+            </div>
+            <ul className="mt-1 space-y-0.5">
+              {report.mismatches.map((m, i) => (
+                <li key={i} className="font-mono text-xs">
+                  [{m.kind}] {m.entity_id || m.device?.rid}: {m.detail}
+                </li>
+              ))}
+            </ul>
+          </div>
+        ))}
       </Panel>
 
       <ConfirmDialog
@@ -120,15 +216,27 @@ export function BridgePage(props: {
         confirmText="Reset"
         body={
           <div className="space-y-2">
-            <div>This clears the Hue resource database inside Bifrost.</div>
-            <div className="font-semibold">You will need to re-pair the bridge in the Hue app.</div>
+            <SelectField
+              label="What to reset"
+              value={resetScope}
+              onChange={(v) => setResetScope(v as HassResetScope)}
+              options={RESET_SCOPES}
+            />
+            {resetScope === 'all' ? (
+              <>
+                <div>This clears the Hue resource database inside Bifrost.</div>
+                <div className="font-semibold">You will need to re-pair the bridge in the Hue app.</div>
+              </>
+            ) : (
+              <div>Everything else is kept, and apps stay paired.</div>
+            )}
           </div>
         }
         onClose={() => setConfirmReset(false)}
         onConfirm={() =>
           run('reset', async () => {
             setConfirmReset(false)
-            await postResetBridge()
+            await postResetBridge(resetScope)
             await postPatinaEvent('reset', 'bridge-reset').catch(() => {})
           })
         }
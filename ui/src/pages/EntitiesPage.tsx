@@ -1,6 +1,8 @@
-import { useMemo, useState } from 'react'
+import { useEffect, useState } from 'react'
 import { useVirtualizer } from '@tanstack/react-virtual'
+import { getEntities } from '../lib/api'
 import type {
+  HassEntitiesQuery,
   HassEntitySummary,
   HassLightArchetype,
   HassRoomConfig,
@@ -8,17 +10,18 @@ import type {
   HassSwitchMode,
 } from '../lib/types'
 import { EntityRow } from '../components/EntityRow'
+import { TactileButton } from '../components/TactileButton'
 
-function norm(s: string) {
-  return (s || '').toLowerCase()
-}
+const PAGE_SIZE = 100
 
 export function EntitiesPage(props: {
   title: string
   subtitle: string
-  entities: HassEntitySummary[]
+  // Filters for this page, evaluated by bifrost
+  query: HassEntitiesQuery
+  // Entities are fetched again whenever this changes
+  refreshKey: unknown
   rooms: HassRoomConfig[]
-  predicate: (e: HassEntitySummary) => boolean
   onSetIncluded: (entity: HassEntitySummary, included: boolean) => void
   onSetRoom: (entity: HassEntitySummary, roomId: string) => void
   onSetAlias: (entity: HassEntitySummary, alias: string) => void
@@ -28,26 +31,45 @@ export function EntitiesPage(props: {
   onSetLightArchetype: (entity: HassEntitySummary, archetype: HassLightArchetype) => void
 }) {
   const [q, setQ] = useState('')
+  const [search, setSearch] = useState('')
+  const [offset, setOffset] = useState(0)
+  const [filtered, setFiltered] = useState<HassEntitySummary[]>([])
+  const [total, setTotal] = useState(0)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    const timeout = window.setTimeout(() => {
+      setSearch(q.trim())
+      setOffset(0)
+    }, 250)
+    return () => window.clearTimeout(timeout)
+  }, [q])
 
-  const filtered = useMemo(() => {
-    const query = norm(q).trim()
-    const list = props.entities.filter(props.predicate)
-    const sorted = list.slice().sort((a, b) => {
-      const ai = a.included ? 0 : 1
-      const bi = b.included ? 0 : 1
-      if (ai !== bi) return ai - bi
-      const ar = norm(a.room_name)
-      const br = norm(b.room_name)
-      if (ar !== br) return ar.localeCompare(br)
-      return norm(a.name).localeCompare(norm(b.name))
-    })
-    if (!query) return sorted
-    return sorted.filter((e) => {
-      const s =
-        `${e.name} ${e.entity_id} ${e.room_name} ${e.area_name || ''} ${e.mapped_type}`.toLowerCase()
-      return s.includes(query)
-    })
-  }, [props.entities, props.predicate, q])
+  const queryKey = JSON.stringify(props.query)
+  useEffect(() => {
+    let alive = true
+    const query: HassEntitiesQuery = {
+      ...(JSON.parse(queryKey) as HassEntitiesQuery),
+      search: search || undefined,
+      // without a sort key, search results are sorted by relevance
+      sort: search ? undefined : 'room',
+      offset,
+      limit: PAGE_SIZE,
+    }
+    getEntities(query)
+      .then((res) => {
+        if (!alive) return
+        setFiltered(res.entities)
+        setTotal(res.total)
+        setError(null)
+      })
+      .catch((err) => {
+        if (alive) setError(err instanceof Error ? err.message : String(err))
+      })
+    return () => {
+      alive = false
+    }
+  }, [queryKey, search, offset, props.refreshKey])
 
   const parentRef = useState(() => ({ current: null as HTMLDivElement | null }))[0]
   // eslint-disable-next-line react-hooks/incompatible-library
@@ -75,11 +97,35 @@ export function EntitiesPage(props: {
           placeholder="Search by name, entity_id, room, area…"
           className="w-full rounded-control border border-[rgba(122,146,201,0.35)] bg-[rgba(16,27,44,0.88)] px-2.5 py-1.5 text-[13px] text-ink-0 shadow-inset focus:outline-none focus:ring-2 focus:ring-accent-blue/60"
         />
-        <div className="text-xs text-ink-1">
-          Showing <span className="font-semibold text-ink-0">{filtered.length}</span>
+        <div className="flex items-center gap-1.5 text-xs text-ink-1">
+          <TactileButton
+            variant="neutral"
+            disabled={offset === 0}
+            onClick={() => setOffset(Math.max(0, offset - PAGE_SIZE))}
+            wearKey="entities:prev"
+          >
+            Prev
+          </TactileButton>
+          <span>
+            Showing{' '}
+            <span className="font-semibold text-ink-0">
+              {total === 0 ? 0 : offset + 1}-{offset + filtered.length}
+            </span>{' '}
+            of <span className="font-semibold text-ink-0">{total}</span>
+          </span>
+          <TactileButton
+            variant="neutral"
+            disabled={offset + PAGE_SIZE >= total}
+            onClick={() => setOffset(offset + PAGE_SIZE)}
+            wearKey="entities:next"
+          >
+            Next
+          </TactileButton>
         </div>
       </div>
 
+      {error ? <div className="text-sm text-ink-0">Failed to load entities: {error}</div> : null}
+
       <div
         ref={(el) => {
           parentRef.current = el
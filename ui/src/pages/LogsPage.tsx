@@ -1,18 +1,71 @@
+import { useState } from 'react'
+
 import { Panel } from '../components/Panel'
 import { TactileButton } from '../components/TactileButton'
+import type { TimelineCategory, TimelineEntry, TimelineLevel } from '../lib/types'
+
+const CATEGORIES: TimelineCategory[] = ['sync', 'command', 'connection', 'config', 'pairing', 'service']
+
+const LEVELS: TimelineLevel[] = ['info', 'warning', 'error']
+
+function formatEntry(entry: TimelineEntry): string {
+  const ts = new Date(entry.at).toLocaleString()
+  const source = entry.source ? ` [${entry.source}]` : ''
+  const level = entry.level === 'info' ? '' : `${entry.level.toUpperCase()}: `
+  return `[${ts}] ${entry.category.padEnd(10)}${source} ${level}${entry.message}`
+}
+
+export function LogsPage(props: { timeline: TimelineEntry[]; onRefresh: () => void }) {
+  const [category, setCategory] = useState<TimelineCategory | 'all'>('all')
+  const [level, setLevel] = useState<TimelineLevel>('info')
+  const [entity, setEntity] = useState('')
 
-export function LogsPage(props: { logs: string[]; onRefresh: () => void }) {
-  const lines = props.logs || []
+  const lines = (props.timeline || [])
+    .filter((entry) => category === 'all' || entry.category === category)
+    .filter((entry) => LEVELS.indexOf(entry.level) >= LEVELS.indexOf(level))
+    .filter((entry) => !entity || (entry.entity_id || '').includes(entity))
+    .map(formatEntry)
 
   return (
     <div className="space-y-4">
       <Panel
         title="Logs"
-        subtitle="Operational logs from the Home Assistant backend and bridge actions."
+        subtitle="Activity timeline of syncs, commands, connections and services."
         right={
-          <TactileButton variant="neutral" onClick={props.onRefresh} wearKey="logs:refresh">
-            Refresh
-          </TactileButton>
+          <div className="flex items-center gap-2">
+            <select
+              value={category}
+              onChange={(e) => setCategory(e.target.value as TimelineCategory | 'all')}
+              className="rounded-control border border-black/20 bg-white/80 px-2 py-1 text-[12px]"
+            >
+              <option value="all">all</option>
+              {CATEGORIES.map((cat) => (
+                <option key={cat} value={cat}>
+                  {cat}
+                </option>
+              ))}
+            </select>
+            <select
+              value={level}
+              onChange={(e) => setLevel(e.target.value as TimelineLevel)}
+              className="rounded-control border border-black/20 bg-white/80 px-2 py-1 text-[12px]"
+            >
+              {LEVELS.map((lvl) => (
+                <option key={lvl} value={lvl}>
+                  {lvl}+
+                </option>
+              ))}
+            </select>
+            <input
+              value={entity}
+              onChange={(e) => setEntity(e.target.value)}
+              placeholder="entity id"
+              className="rounded-control border border-black/20 bg-white/80 px-2 py-1 text-[12px]"
+            />
+            <TactileButton variant="neutral" onClick={props.onRefresh} wearKey="logs:refresh">
+              Refresh
+            </TactileButton>
+          </div>
         }
       >
         <textarea
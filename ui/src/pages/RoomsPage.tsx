@@ -1,7 +1,16 @@
-import { useMemo, useState } from 'react'
-import { deleteRoom, postPatinaEvent, postRoom, putRoomRename } from '../lib/api'
-import type { HassRoomConfig, HassUiConfig } from '../lib/types'
+import { useEffect, useMemo, useState } from 'react'
+import {
+  deleteRoom,
+  getRoomDuplicates,
+  postPatinaEvent,
+  postRoom,
+  putRoomBrightnessStrategy,
+  putRoomMerge,
+  putRoomRename,
+} from '../lib/api'
+import type { HassBrightnessStrategy, HassRoomConfig, HassUiConfig, RoomDuplicate } from '../lib/types'
 import { Panel } from '../components/Panel'
+import { SelectField } from '../components/SelectField'
 import { TactileButton } from '../components/TactileButton'
 import { TextField } from '../components/TextField'
 import { ToggleSwitch } from '../components/ToggleSwitch'
@@ -13,6 +22,14 @@ export function RoomsPage(props: {
 }) {
   const [newRoom, setNewRoom] = useState('')
   const [busy, setBusy] = useState<string | null>(null)
+  const [duplicates, setDuplicates] = useState<RoomDuplicate[]>([])
+
+  useEffect(() => {
+    if (busy) return
+    getRoomDuplicates()
+      .then(setDuplicates)
+      .catch(() => setDuplicates([]))
+  }, [busy, props.config.rooms])
 
   const editable = useMemo(() => {
     return (props.config.rooms || []).slice().sort((a, b) => a.name.localeCompare(b.name))
@@ -45,6 +62,42 @@ export function RoomsPage(props: {
         />
       </Panel>
 
+      {duplicates.length > 0 && (
+        <Panel
+          title="Duplicate Rooms"
+          subtitle="These rooms share a name across backends, and show up twice in the Hue app. Merge them into one Hue room."
+        >
+          <div className="grid gap-3">
+            {duplicates.map((dup) => {
+              const own = editable.find((r) => r.name.trim().toLowerCase() === dup.name.trim().toLowerCase())
+              const target = dup.rooms.find((r) => !r.backend?.startsWith('hass@'))
+              return (
+                <div key={dup.name} className="sub-panel flex flex-col gap-2 p-3 sm:flex-row sm:items-center sm:justify-between">
+                  <div>
+                    <div className="font-semibold">{dup.name}</div>
+                    <div className="font-mono text-[12px] text-ink-1/70">
+                      {dup.rooms.map((r) => r.backend || 'unknown').join(', ')}
+                    </div>
+                  </div>
+                  <TactileButton
+                    variant="primary"
+                    disabled={!!busy || !own || !target}
+                    onClick={() =>
+                      own && target && run('merge', async () => {
+                        await putRoomMerge(own.id, target.room.rid)
+                      })
+                    }
+                    wearKey={`rooms:merge:${dup.name}`}
+                  >
+                    Merge
+                  </TactileButton>
+                </div>
+              )
+            })}
+          </div>
+        </Panel>
+      )}
+
       <Panel title="Create Room" subtitle="Add a new Hue room for organizing entities.">
         <div className="flex flex-col gap-3 sm:flex-row sm:items-end">
           <div className="flex-1">
@@ -86,6 +139,16 @@ export function RoomsPage(props: {
                   await postPatinaEvent('toggle', `room-rename:${r.id}`).catch(() => {})
                 })
               }
+              onSetStrategy={(strategy) =>
+                run('strategy', async () => {
+                  await putRoomBrightnessStrategy(r.id, strategy)
+                })
+              }
+              onUnmerge={() =>
+                run('unmerge', async () => {
+                  await putRoomMerge(r.id, null)
+                })
+              }
               onDelete={() =>
                 run('delete', async () => {
                   await deleteRoom(r.id)
@@ -104,6 +167,8 @@ function RoomRow(props: {
   room: HassRoomConfig
   disabled: boolean
   onRename: (name: string) => void
+  onSetStrategy: (strategy: HassBrightnessStrategy) => void
+  onUnmerge: () => void
   onDelete: () => void
 }) {
   const [name, setName] = useState(props.room.name)
@@ -127,8 +192,35 @@ function RoomRow(props: {
             }
           />
           <div className="mt-1 font-mono text-[12px] text-ink-1/70">id: {props.room.id}</div>
+          {props.room.merge_into && (
+            <div className="mt-1 font-mono text-[12px] text-ink-1/70">
+              merged into: {props.room.merge_into}
+            </div>
+          )}
+          <SelectField
+            className="mt-2"
+            label="Room brightness"
+            value={props.room.brightness_strategy || 'average'}
+            onChange={(v) => props.onSetStrategy(v as HassBrightnessStrategy)}
+            options={[
+              { value: 'average', label: 'Average of all lights' },
+              { value: 'average_on', label: 'Average of lights that are on' },
+              { value: 'max', label: 'Brightest light' },
+              { value: 'last_changed', label: 'Last changed light' },
+            ]}
+          />
         </div>
         <div className="flex gap-2">
+          {props.room.merge_into && (
+            <TactileButton
+              variant="neutral"
+              disabled={props.disabled}
+              onClick={props.onUnmerge}
+              wearKey={`room:unmerge:${props.room.id}`}
+            >
+              Unmerge
+            </TactileButton>
+          )}
           <TactileButton
             variant="neutral"
             disabled={props.disabled || name.trim() === props.room.name.trim()}
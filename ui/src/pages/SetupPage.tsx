@@ -16,9 +16,46 @@ import { TactileButton } from '../components/TactileButton'
 import { TextField } from '../components/TextField'
 import { ToggleSwitch } from '../components/ToggleSwitch'
 
+const SYNC_DOMAINS: { domain: string; label: string }[] = [
+  { domain: 'light', label: 'Lights' },
+  { domain: 'switch', label: 'Switches' },
+  { domain: 'cover', label: 'Covers' },
+  { domain: 'fan', label: 'Fans' },
+  { domain: 'media_player', label: 'Media players' },
+  { domain: 'binary_sensor', label: 'Binary sensors' },
+  { domain: 'sensor', label: 'Threshold sensors' },
+  { domain: 'lock', label: 'Locks' },
+  { domain: 'script', label: 'Scripts' },
+  { domain: 'button', label: 'Buttons' },
+  { domain: 'event', label: 'Remotes' },
+]
+
+function splitList(value: string): string[] {
+  return value
+    .split(',')
+    .map((x) => x.trim())
+    .filter((x) => x.length > 0)
+}
+
+function parseLabelPairs(value: string): Record<string, string> {
+  const pairs: Record<string, string> = {}
+  for (const pair of splitList(value)) {
+    const [label, target] = pair.split('=').map((x) => x.trim())
+    if (label && target) pairs[label] = target
+  }
+  return pairs
+}
+
+function formatLabelPairs(pairs: Record<string, string> | undefined): string {
+  return Object.entries(pairs || {})
+    .map(([label, target]) => `${label}=${target}`)
+    .join(', ')
+}
+
 export function SetupPage(props: {
   runtime: HassRuntimeConfigPublic | null
   config: HassUiConfig
+  backends: string[]
   onSaveConfig: (next: HassUiConfig) => Promise<void>
   onRefresh: () => void
 }) {
@@ -26,12 +63,38 @@ export function SetupPage(props: {
   const [enabled, setEnabled] = useState(false)
   const [token, setToken] = useState('')
   const [busy, setBusy] = useState<string | null>(null)
+  const [includeLabels, setIncludeLabels] = useState('')
+  const [excludeLabels, setExcludeLabels] = useState('')
+  const [labelRooms, setLabelRooms] = useState('')
+  const [labelZones, setLabelZones] = useState('')
+  const [includedAreas, setIncludedAreas] = useState('')
+  const [weatherEntity, setWeatherEntity] = useState('')
 
   useEffect(() => {
     setUrl(props.runtime?.url || '')
     setEnabled(!!props.runtime?.enabled)
   }, [props.runtime?.url, props.runtime?.enabled])
 
+  useEffect(() => {
+    setIncludeLabels((props.config.include_labels || []).join(', '))
+    setExcludeLabels((props.config.exclude_labels || []).join(', '))
+    setLabelRooms(formatLabelPairs(props.config.label_rooms))
+    setLabelZones(formatLabelPairs(props.config.label_zones))
+  }, [
+    props.config.include_labels,
+    props.config.exclude_labels,
+    props.config.label_rooms,
+    props.config.label_zones,
+  ])
+
+  useEffect(() => {
+    setIncludedAreas((props.config.included_area_names || []).join(', '))
+  }, [props.config.included_area_names])
+
+  useEffect(() => {
+    setWeatherEntity(props.config.daylight_weather_entity || '')
+  }, [props.config.daylight_weather_entity])
+
   async function run(label: string, fn: () => Promise<void>) {
     setBusy(label)
     try {
@@ -195,6 +258,149 @@ export function SetupPage(props: {
             help="Creates rooms from Home Assistant areas on sync."
             wearKey="cfg:sync-areas"
           />
+          <ToggleSwitch
+            checked={!!props.config.import_buttons}
+            onChange={(v) => props.onSaveConfig({ ...props.config, import_buttons: v })}
+            label="Import scripts and buttons"
+            help="Exposes script.* and button.* entities as Hue buttons. Pressing one runs the script."
+            wearKey="cfg:import-buttons"
+          />
+          <ToggleSwitch
+            checked={!!props.config.import_remotes}
+            onChange={(v) => props.onSaveConfig({ ...props.config, import_remotes: v })}
+            label="Import remotes"
+            help="Exposes event.* entities (remote buttons, doorbells) as Hue buttons, so Hue apps can react to presses."
+            wearKey="cfg:import-remotes"
+          />
+        </div>
+      </Panel>
+
+      <Panel title="Daylight" subtitle="A virtual Hue light level sensor, computed from sun.sun and the weather.">
+        <div className="grid gap-2 sm:grid-cols-2">
+          <ToggleSwitch
+            checked={!!props.config.daylight_sensor}
+            onChange={(v) => props.onSaveConfig({ ...props.config, daylight_sensor: v })}
+            label="Daylight sensor"
+            help="Estimates the outdoor light level, for daylight-aware automations."
+            wearKey="cfg:daylight"
+          />
+          <TextField
+            label="Weather entity"
+            value={weatherEntity}
+            onChange={setWeatherEntity}
+            placeholder="weather.home"
+            help="Optional. Clouds and rain lower the light level."
+          />
+          <div className="flex items-end gap-1.5">
+            <TactileButton
+              variant="primary"
+              disabled={!!busy}
+              onClick={() =>
+                run('daylight', () =>
+                  props.onSaveConfig({
+                    ...props.config,
+                    daylight_weather_entity: weatherEntity.trim() || null,
+                  }),
+                )
+              }
+              wearKey="cfg:daylight-weather"
+            >
+              Save weather entity
+            </TactileButton>
+          </div>
+        </div>
+      </Panel>
+
+      <Panel title="Domains" subtitle="Only import what you need. Disabled domains are removed on the next sync.">
+        {props.backends.map((backend) => {
+          const disabled = props.config.disabled_domains?.[backend] || []
+          const setEnabled = (domain: string, enabled: boolean) => {
+            const next = disabled.filter((d) => d !== domain)
+            if (!enabled) next.push(domain)
+            props.onSaveConfig({
+              ...props.config,
+              disabled_domains: { ...props.config.disabled_domains, [backend]: next },
+            })
+          }
+          return (
+            <div key={backend} className="mt-2 first:mt-0">
+              {props.backends.length > 1 && (
+                <div className="text-xs font-semibold text-ink-1/70">{backend}</div>
+              )}
+              <div className="mt-1 grid gap-2 sm:grid-cols-3">
+                {SYNC_DOMAINS.map(({ domain, label }) => (
+                  <ToggleSwitch
+                    key={domain}
+                    checked={!disabled.includes(domain)}
+                    onChange={(v) => setEnabled(domain, v)}
+                    label={label}
+                    wearKey={`cfg:domain:${backend}:${domain}`}
+                  />
+                ))}
+              </div>
+            </div>
+          )
+        })}
+      </Panel>
+
+      <Panel title="Exposure Rules" subtitle="Home Assistant areas and labels (by name), comma separated. Applied on the next sync.">
+        <div className="grid gap-2 sm:grid-cols-2">
+          <TextField
+            label="Include areas"
+            value={includedAreas}
+            onChange={setIncludedAreas}
+            placeholder="Living Room, Kitchen"
+            help="Entities in these areas are added to Hue."
+          />
+          <TextField
+            label="Include labels"
+            value={includeLabels}
+            onChange={setIncludeLabels}
+            placeholder="Hue"
+            help="Entities with any of these labels are added to Hue."
+          />
+          <TextField
+            label="Exclude labels"
+            value={excludeLabels}
+            onChange={setExcludeLabels}
+            placeholder="Hidden"
+            help="Entities with any of these labels are never added, unless added manually."
+          />
+          <TextField
+            label="Label rooms"
+            value={labelRooms}
+            onChange={setLabelRooms}
+            placeholder="Kitchen lights=kitchen"
+            help="label=room_id pairs. Used when an entity has no room assigned manually."
+          />
+          <TextField
+            label="Label zones"
+            value={labelZones}
+            onChange={setLabelZones}
+            placeholder="Evening=Cosy lights"
+            help="label=zone name pairs. The Home Assistant lights of these Hue zones follow the labels."
+          />
+          <div className="flex items-end gap-1.5">
+            <TactileButton
+              variant="primary"
+              disabled={!!busy}
+              onClick={() =>
+                run('labels', () =>
+                  props.onSaveConfig({
+                    ...props.config,
+                    include_labels: splitList(includeLabels),
+                    exclude_labels: splitList(excludeLabels),
+                    label_rooms: parseLabelPairs(labelRooms),
+                    label_zones: parseLabelPairs(labelZones),
+                    included_area_names: splitList(includedAreas),
+                  }),
+                )
+              }
+              wearKey="cfg:rules"
+            >
+              Save rules
+            </TactileButton>
+          </div>
         </div>
       </Panel>
     </div>